@@ -4,6 +4,21 @@
 @group(#{MATERIAL_BIND_GROUP}) @binding(1) var<uniform> outer_color: vec4<f32>;
 @group(#{MATERIAL_BIND_GROUP}) @binding(2) var base_color_texture: texture_2d<f32>;
 @group(#{MATERIAL_BIND_GROUP}) @binding(3) var base_color_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(4) var<uniform> pattern: u32;
+
+const PATTERN_STRIPES: u32 = 1u;
+const PATTERN_DOTS: u32 = 2u;
+
+// Whether the pattern covers the point at `uv`, where the outer color is darkened toward the inner color.
+fn in_pattern(uv: vec2<f32>) -> bool {
+    if pattern == PATTERN_STRIPES {
+        return fract((uv.x + uv.y) * 4.0) < 0.5;
+    }
+    if pattern == PATTERN_DOTS {
+        return length(fract(uv * 5.0) - vec2<f32>(0.5)) < 0.25;
+    }
+    return false;
+}
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
@@ -12,11 +27,16 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
     let r = color.r;
     let g = color.g;
     let b = color.b;
+
+    var outer = outer_color.rgb;
+    if in_pattern(in.uv) {
+        outer = mix(outer_color.rgb, inner_color.rgb, 0.5);
+    }
     
     return vec4<f32>(
-        r * inner_color.r + g * outer_color.r,
-        r * inner_color.g + g * outer_color.g,
-        r * inner_color.b + g * outer_color.b,
+        r * inner_color.r + g * outer.r,
+        r * inner_color.g + g * outer.g,
+        r * inner_color.b + g * outer.b,
         color.a
      );
 }
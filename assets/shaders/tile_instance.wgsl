@@ -0,0 +1,55 @@
+#import bevy_sprite::mesh2d_functions::{get_world_from_local, mesh2d_position_local_to_clip}
+
+struct TileInstance {
+    position: vec2<f32>,
+    texture_index: u32,
+    inner_color: vec4<f32>,
+    outer_color: vec4<f32>,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<storage, read> instances: array<TileInstance>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var textures: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2) var textures_sampler: sampler;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) tile_instance: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) tile_instance: u32,
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let instance = instances[vertex.tile_instance];
+    let local_position = vec4<f32>(vertex.position.xy + instance.position, vertex.position.z, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = mesh2d_position_local_to_clip(
+        get_world_from_local(vertex.instance_index),
+        local_position,
+    );
+    out.uv = vertex.uv;
+    out.tile_instance = vertex.tile_instance;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let instance = instances[in.tile_instance];
+    let color = textureSample(textures, textures_sampler, in.uv, instance.texture_index);
+
+    if instance.outer_color.a == 0.0 {
+        return color;
+    }
+
+    return vec4<f32>(
+        color.r * instance.inner_color.rgb + color.g * instance.outer_color.rgb,
+        color.a
+    );
+}
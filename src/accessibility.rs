@@ -0,0 +1,189 @@
+//! The accessibility settings, kept between games in [`ACCESSIBILITY_SETTINGS_PATH`] and changed in the options
+//! of the pause menu, see [`crate::pause_menu`].
+//!
+//! The colors of the nations in the ruleset are hard to tell apart with a color vision deficiency, so the player can
+//! replace them with a palette made for it, and add a pattern to the flags of the units so nations sharing a color
+//! stay distinguishable.
+
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset};
+use enum_map::Enum;
+use serde::{Deserialize, Serialize};
+
+/// The accessibility settings changed by the player.
+pub const ACCESSIBILITY_SETTINGS_PATH: &str = "settings/accessibility.json";
+
+/// The palette the nations are drawn with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum PlayerPalette {
+    /// The colors of the nations in the ruleset.
+    #[default]
+    Ruleset,
+    /// The palette of Okabe and Ito, distinguishable with every common color vision deficiency.
+    OkabeIto,
+    /// The bright palette of Paul Tol, distinguishable with every common color vision deficiency.
+    TolBright,
+}
+
+impl PlayerPalette {
+    pub const ALL: [Self; 3] = [Self::Ruleset, Self::OkabeIto, Self::TolBright];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ruleset => "Ruleset",
+            Self::OkabeIto => "Okabe-Ito",
+            Self::TolBright => "Tol bright",
+        }
+    }
+
+    /// The colors of the palette, `None` for the colors of the ruleset.
+    fn colors(self) -> Option<&'static [[u8; 3]]> {
+        match self {
+            Self::Ruleset => None,
+            Self::OkabeIto => Some(&[
+                [230, 159, 0],
+                [86, 180, 233],
+                [0, 158, 115],
+                [240, 228, 66],
+                [0, 114, 178],
+                [213, 94, 0],
+                [204, 121, 167],
+                [0, 0, 0],
+            ]),
+            Self::TolBright => Some(&[
+                [68, 119, 170],
+                [102, 204, 238],
+                [34, 136, 51],
+                [204, 187, 68],
+                [238, 102, 119],
+                [170, 51, 119],
+                [187, 187, 187],
+            ]),
+        }
+    }
+}
+
+/// The pattern drawn over the outer color of a unit flag, see [`crate::custom_material::ColorReplaceMaterial`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FlagPattern {
+    #[default]
+    Solid,
+    Stripes,
+    Dots,
+}
+
+impl FlagPattern {
+    const ALL: [Self; 3] = [Self::Solid, Self::Stripes, Self::Dots];
+
+    /// The value of the pattern in `shaders/color_replace.wgsl`.
+    pub fn shader_value(self) -> u32 {
+        self as u32
+    }
+}
+
+/// The colors a nation is drawn with.
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerColors {
+    pub outer_color: [u8; 3],
+    pub inner_color: [u8; 3],
+    pub pattern: FlagPattern,
+}
+
+impl PlayerColors {
+    pub fn outer(&self) -> Color {
+        let [red, green, blue] = self.outer_color;
+        Color::srgb_u8(red, green, blue)
+    }
+
+    pub fn inner(&self) -> Color {
+        let [red, green, blue] = self.inner_color;
+        Color::srgb_u8(red, green, blue)
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub palette: PlayerPalette,
+    /// Whether the flags of the units get a pattern, so nations sharing a color stay distinguishable.
+    pub patterns: bool,
+}
+
+impl AccessibilitySettings {
+    /// The settings saved by the last game, or the default settings if there are none.
+    pub fn load() -> Self {
+        fs::read_to_string(ACCESSIBILITY_SETTINGS_PATH)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// The colors of `nation` in the chosen palette.
+    ///
+    /// A palette has fewer colors than there are nations, so the nations take its colors in turn. The number of
+    /// patterns shares no divisor with the number of colors, so the nations sharing a color get different patterns.
+    pub fn player_colors(&self, nation: Nation, ruleset: &Ruleset) -> PlayerColors {
+        let index = nation.into_usize();
+        let pattern = if self.patterns {
+            FlagPattern::ALL[index % FlagPattern::ALL.len()]
+        } else {
+            FlagPattern::Solid
+        };
+        let nation_info = &ruleset.nations[nation.as_str()];
+        match self.palette.colors() {
+            None => PlayerColors {
+                outer_color: nation_info.outer_color,
+                inner_color: nation_info.inner_color,
+                pattern,
+            },
+            Some(colors) => {
+                let outer_color = colors[index % colors.len()];
+                PlayerColors {
+                    outer_color,
+                    inner_color: contrasting_color(outer_color),
+                    pattern,
+                }
+            }
+        }
+    }
+}
+
+/// Black on light colors and white on dark colors.
+fn contrasting_color([red, green, blue]: [u8; 3]) -> [u8; 3] {
+    let luma = 0.299 * red as f32 + 0.587 * green as f32 + 0.114 * blue as f32;
+    if luma > 140. {
+        [0, 0, 0]
+    } else {
+        [255, 255, 255]
+    }
+}
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AccessibilitySettings::load())
+            .add_systems(
+                Update,
+                save_accessibility_settings.run_if(resource_changed::<AccessibilitySettings>),
+            );
+    }
+}
+
+/// Save the accessibility settings whenever the player changes them.
+fn save_accessibility_settings(settings: Res<AccessibilitySettings>) {
+    if !settings.is_added()
+        && let Err(error) = settings.write(Path::new(ACCESSIBILITY_SETTINGS_PATH))
+    {
+        error!("Can't save the accessibility settings: {error}");
+    }
+}
@@ -0,0 +1,536 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset, tile::Tile};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    city::{
+        ChangeProduction, City, CityProduction, FoundCity, can_build_unit, can_found_city,
+        is_city_site,
+    },
+    civilization::{Civilizations, PlayerCivilization},
+    combat::{Attack, CITY_STRIKE_RANGE, CityStrike, closest_target_in_reach},
+    connection::CityConnections,
+    deal::{AnswerDeal, DealItem, Deals},
+    diplomacy::DiplomacyState,
+    improvement::TileImprovementLayer,
+    network::NetworkSession,
+    pathfinding::{MovementRules, ZoneOfControl, ZoneOfControlRule, find_path},
+    pillage::builds_improvements,
+    random_event::{ChooseEventOption, EventEffect, PendingEvents, RandomEvents},
+    road::{BuildRoad, can_build_road},
+    technology::{ChooseResearch, can_research},
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, MovePath, UnitDomain, is_civilian_unit_type, tile_movement_cost},
+    unit_component::{Movement, Owner, RangedStrength, Unit, UnitOrder},
+    visibility::VisibilityLayer,
+};
+
+/// An AI civilization stops training settlers once it has this many cities.
+const MAX_AI_CITIES: usize = 4;
+/// A city trains a settler only once it has at least this many citizens.
+const MIN_POPULATION_FOR_SETTLER: u32 = 2;
+/// Settlers look for a city site within this distance.
+const SETTLE_DISTANCE: u32 = 5;
+/// Military units explore the unexplored tiles within this distance.
+const EXPLORE_DISTANCE: u32 = 10;
+
+/// Return `true` if a person plays the civilization: the local player, or another player of the network game.
+fn is_human(
+    nation: Nation,
+    player_civilization: &PlayerCivilization,
+    network_session: Option<&NetworkSession>,
+) -> bool {
+    nation == player_civilization.0
+        || network_session.is_some_and(|session| session.players.contains(&nation))
+}
+
+/// Return `true` if the AI controls the owner, i.e. it is a civilization nobody plays or a city-state.
+fn is_ai(
+    owner: &Owner,
+    player_civilization: &PlayerCivilization,
+    network_session: Option<&NetworkSession>,
+) -> bool {
+    match *owner {
+        Owner::Civilization(nation) => !is_human(nation, player_civilization, network_session),
+        Owner::CityState(_) => true,
+        Owner::Barbarian => false,
+    }
+}
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            TurnProcessing,
+            (
+                choose_ai_research,
+                choose_ai_production,
+                answer_ai_deals,
+                choose_ai_event_options,
+                strike_with_ai_cities,
+                move_ai_units,
+            )
+                .in_set(TurnSet::Ai),
+        );
+    }
+}
+
+/// Let every AI civilization without research choose the cheapest technology it can research.
+fn choose_ai_research(
+    mut choose_research: MessageWriter<ChooseResearch>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    player_civilization: Res<PlayerCivilization>,
+    network_session: Option<Res<NetworkSession>>,
+    civilizations: Res<Civilizations>,
+) {
+    let ruleset = &ruleset.0;
+
+    for &nation in map.0.starting_tile_and_civilization.values() {
+        let civilization = civilizations.get(nation);
+        if is_human(nation, &player_civilization, network_session.as_deref())
+            || civilization.current_research.is_some()
+        {
+            continue;
+        }
+
+        let technology = ruleset
+            .technologies
+            .values()
+            .filter(|technology| can_research(&technology.name, civilization, ruleset))
+            // Break ties by name so the choice doesn't depend on the order of the ruleset.
+            .min_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.name.cmp(&b.name)));
+        if let Some(technology) = technology {
+            choose_research.write(ChooseResearch {
+                nation,
+                technology: technology.name.clone(),
+            });
+        }
+    }
+}
+
+/// The worth of a deal item for the AI: gold is worth its amount and a technology its cost.
+///
+/// Research agreements are worth nothing, as both sides give and get the same.
+fn deal_item_value(item: &DealItem, ruleset: &Ruleset) -> i64 {
+    match item {
+        DealItem::ResearchAgreement => 0,
+        DealItem::Technology(technology) => ruleset.technologies[technology].cost as i64,
+        &DealItem::Gold(gold) => gold as i64,
+    }
+}
+
+/// Let every AI civilization accept the deals proposed to it which give it at least as much as they ask.
+fn answer_ai_deals(
+    mut answer_deal: MessageWriter<AnswerDeal>,
+    ruleset: Res<RulesetResource>,
+    player_civilization: Res<PlayerCivilization>,
+    network_session: Option<Res<NetworkSession>>,
+    deals: Res<Deals>,
+) {
+    let ruleset = &ruleset.0;
+
+    for deal in &deals.proposed {
+        if is_human(deal.to, &player_civilization, network_session.as_deref()) {
+            continue;
+        }
+        let value = |items: &[DealItem]| -> i64 {
+            items
+                .iter()
+                .map(|item| deal_item_value(item, ruleset))
+                .sum()
+        };
+        answer_deal.write(AnswerDeal {
+            nation: deal.to,
+            from: deal.from,
+            accept: value(&deal.offered) >= value(&deal.asked),
+        });
+    }
+}
+
+/// Let every AI civilization choose the first choice of its pending events it has the gold for,
+/// or else the last choice.
+fn choose_ai_event_options(
+    mut choose_event_option: MessageWriter<ChooseEventOption>,
+    player_civilization: Res<PlayerCivilization>,
+    network_session: Option<Res<NetworkSession>>,
+    civilizations: Res<Civilizations>,
+    random_events: Res<RandomEvents>,
+    pending_events: Res<PendingEvents>,
+) {
+    for pending in &pending_events.0 {
+        if is_human(
+            pending.nation,
+            &player_civilization,
+            network_session.as_deref(),
+        ) {
+            continue;
+        }
+        let Some(event) = random_events.get(&pending.event) else {
+            continue;
+        };
+        let gold = civilizations.get(pending.nation).gold;
+        let affordable = event.choices.iter().position(|choice| {
+            let gold_cost: f32 = choice
+                .effects
+                .iter()
+                .map(|effect| match *effect {
+                    EventEffect::Gold(gold) => -gold,
+                    _ => 0.,
+                })
+                .sum();
+            gold >= gold_cost
+        });
+        choose_event_option.write(ChooseEventOption {
+            nation: pending.nation,
+            event: pending.event.clone(),
+            choice: affordable.unwrap_or(event.choices.len().saturating_sub(1)),
+        });
+    }
+}
+
+/// Let every idle AI city produce a settler while its civilization needs more cities, or its strongest military unit.
+/// City-states never produce settlers.
+fn choose_ai_production(
+    mut change_production: MessageWriter<ChangeProduction>,
+    ruleset: Res<RulesetResource>,
+    player_civilization: Res<PlayerCivilization>,
+    network_session: Option<Res<NetworkSession>>,
+    civilizations: Res<Civilizations>,
+    query_city: Query<(Entity, &City, &Owner)>,
+    query_unit: Query<(&Unit, &Owner)>,
+) {
+    let ruleset = &ruleset.0;
+
+    for (entity, city, owner) in query_city.iter() {
+        // Puppets choose their production themselves.
+        if !is_ai(owner, &player_civilization, network_session.as_deref())
+            || city.production.is_some()
+            || city.is_puppet
+        {
+            continue;
+        }
+        let nation = owner.nation();
+        let civilization = civilizations.get(nation);
+
+        let settler_count = query_unit
+            .iter()
+            .filter(|(unit, unit_owner)| {
+                unit_owner.nation() == nation && can_found_city(unit.name(), ruleset)
+            })
+            .count();
+        let city_count = query_city
+            .iter()
+            .filter(|(_, _, city_owner)| city_owner.nation() == nation)
+            .count();
+        let wants_settler = matches!(owner, Owner::Civilization(_))
+            && city.population >= MIN_POPULATION_FOR_SETTLER
+            && city_count + settler_count < MAX_AI_CITIES;
+
+        let buildable_units = ruleset
+            .units
+            .values()
+            .filter(|unit| can_build_unit(&unit.name, nation, civilization, ruleset));
+        let unit = if wants_settler {
+            buildable_units
+                .filter(|unit| can_found_city(&unit.name, ruleset))
+                .min_by(|a, b| a.name.cmp(&b.name))
+        } else {
+            buildable_units
+                .filter(|unit| {
+                    !is_civilian_unit_type(&unit.unit_type)
+                        && UnitDomain::of_unit(&unit.name, ruleset) == UnitDomain::Land
+                })
+                .max_by(|a, b| {
+                    a.strength
+                        .max(a.ranged_strength)
+                        .cmp(&b.strength.max(b.ranged_strength))
+                        .then_with(|| b.name.cmp(&a.name))
+                })
+        };
+
+        if let Some(unit) = unit {
+            change_production.write(ChangeProduction {
+                city: entity,
+                production: Some(CityProduction::Unit(unit.name.clone())),
+            });
+        }
+    }
+}
+
+/// Let every AI city strike the closest enemy unit in its reach.
+fn strike_with_ai_cities(
+    mut city_strike: MessageWriter<CityStrike>,
+    map: Res<TileMapResource>,
+    player_civilization: Res<PlayerCivilization>,
+    network_session: Option<Res<NetworkSession>>,
+    diplomacy: Res<DiplomacyState>,
+    query_city: Query<(Entity, &City, &Owner)>,
+    query_unit: Query<(Entity, &MapUnit, &Owner), With<Unit>>,
+) {
+    let grid = map.0.world_grid.grid;
+    let reach = RangedStrength {
+        strength: 1,
+        range: CITY_STRIKE_RANGE,
+    };
+
+    for (entity, city, owner) in query_city.iter() {
+        if city.has_struck || !is_ai(owner, &player_civilization, network_session.as_deref()) {
+            continue;
+        }
+        let enemy_units: Vec<_> = query_unit
+            .iter()
+            .filter(|(.., enemy_owner)| diplomacy.is_at_war(owner.nation(), enemy_owner.nation()))
+            .map(|(enemy, enemy_unit, _)| (enemy, enemy_unit.tile))
+            .collect();
+        if let Some(target) = closest_target_in_reach(city.tile, &reach, &enemy_units, grid) {
+            city_strike.write(CityStrike {
+                city: entity,
+                target,
+            });
+        }
+    }
+}
+
+/// The tiles missing a road on the way from the cities of `nation` which aren't connected to its capital,
+/// along the paths a unit with `rules` takes to the capital.
+fn missing_roads(
+    nation: Nation,
+    rules: &MovementRules,
+    improvement_layer: &TileImprovementLayer,
+    city_connections: &CityConnections,
+    query_city: &Query<(&City, &Owner)>,
+) -> HashSet<Tile> {
+    let cities: Vec<&City> = query_city
+        .iter()
+        .filter(|(_, owner)| owner.nation() == nation)
+        .map(|(city, _)| city)
+        .collect();
+    let Some(capital) = cities.iter().find(|city| city.is_capital) else {
+        return HashSet::new();
+    };
+
+    cities
+        .iter()
+        .filter(|city| !city_connections.is_connected(city.tile))
+        .filter_map(|city| find_path(city.tile, capital.tile, rules))
+        .flat_map(|path| path.tiles)
+        .filter(|&tile| {
+            !improvement_layer.has_road(tile) && cities.iter().all(|city| city.tile != tile)
+        })
+        .collect()
+}
+
+/// Give orders to the units of the AI.
+///
+/// Settlers found a city where they stand if they can, or move to the closest city site.
+/// Workers build the roads connecting the cities to their capital.
+/// Military units attack an enemy within reach, or defend an undefended city, or explore the closest unexplored tile.
+fn move_ai_units(
+    (mut attack, mut found_city, mut build_road): (
+        MessageWriter<Attack>,
+        MessageWriter<FoundCity>,
+        MessageWriter<BuildRoad>,
+    ),
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    player_civilization: Res<PlayerCivilization>,
+    network_session: Option<Res<NetworkSession>>,
+    diplomacy: Res<DiplomacyState>,
+    zone_of_control_rule: Res<ZoneOfControlRule>,
+    visibility_layer: Res<VisibilityLayer>,
+    improvement_layer: Res<TileImprovementLayer>,
+    city_connections: Res<CityConnections>,
+    query_city: Query<(&City, &Owner)>,
+    query_unit: Query<(Entity, &Unit, &MapUnit, &Owner)>,
+    mut query_ai_unit: Query<(
+        Entity,
+        &Unit,
+        &Owner,
+        &MapUnit,
+        &Movement,
+        &RangedStrength,
+        &UnitOrder,
+        &mut MovePath,
+    )>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+
+    let city_tiles: Vec<_> = query_city.iter().map(|(city, _)| city.tile).collect();
+    // The cities which already have a defender, or one on its way.
+    let mut defended_cities: HashSet<Tile> = query_unit
+        .iter()
+        .filter(|(_, unit, map_unit, _)| {
+            matches!(unit, Unit::Military(_)) && city_tiles.contains(&map_unit.tile)
+        })
+        .map(|(_, _, map_unit, _)| map_unit.tile)
+        .collect();
+    // The cities which were founded this turn, so settlers don't found two cities too close to each other.
+    let mut planned_cities: Vec<Tile> = Vec::new();
+    // The roads left to build by every nation, each one left to a single worker.
+    let mut roads_to_build: HashMap<Nation, HashSet<Tile>> = HashMap::new();
+    let military_units: Vec<_> = query_unit
+        .iter()
+        .filter(|(_, unit, ..)| matches!(unit, Unit::Military(_)))
+        .map(|(_, _, map_unit, owner)| (map_unit.tile, owner.nation()))
+        .collect();
+
+    // Sort the units by tile so the orders don't depend on the order of the entities,
+    // which differ between the players of a network game.
+    let mut ai_units: Vec<_> = query_ai_unit
+        .iter_mut()
+        .filter(|(_, _, owner, ..)| is_ai(owner, &player_civilization, network_session.as_deref()))
+        .collect();
+    ai_units.sort_by(|(_, a, _, a_unit, ..), (_, b, _, b_unit, ..)| {
+        a_unit
+            .tile
+            .index()
+            .cmp(&b_unit.tile.index())
+            .then_with(|| a.name().cmp(b.name()))
+    });
+
+    for (entity, unit, owner, map_unit, movement, ranged, order, mut move_path) in ai_units {
+        let nation = owner.nation();
+        let tile = map_unit.tile;
+        let zone_of_control = ZoneOfControl::of_enemies(
+            nation,
+            unit.name(),
+            &military_units,
+            *zone_of_control_rule,
+            &diplomacy,
+            tile_map,
+            ruleset,
+        );
+        let ends_movement = |from, to| zone_of_control.ends_movement(from, to);
+        let rules = MovementRules {
+            // The AI keeps its units on land, where they can defend themselves.
+            embarkation: None,
+            ends_movement: Some(&ends_movement),
+            ..MovementRules::new(
+                unit.name(),
+                civilizations.get(nation),
+                movement.max,
+                tile_map,
+                ruleset,
+            )
+        };
+
+        if can_found_city(unit.name(), ruleset) {
+            let is_site = |site: Tile| {
+                is_city_site(
+                    site,
+                    tile_map,
+                    city_tiles.iter().chain(&planned_cities).copied(),
+                )
+            };
+            if is_site(tile) {
+                move_path.0.clear();
+                planned_cities.push(tile);
+                found_city.write(FoundCity { settler: entity });
+                continue;
+            }
+            if move_path.0.is_empty() {
+                let site = tile
+                    .tiles_in_distance(SETTLE_DISTANCE, grid)
+                    .filter(|&site| is_site(site))
+                    .min_by_key(|site| (site.distance_to(tile, grid), site.index()));
+                if let Some(path) = site.and_then(|site| find_path(tile, site, &rules)) {
+                    move_path.0 = path.tiles.into();
+                }
+            }
+            continue;
+        }
+
+        if builds_improvements(unit.name(), ruleset) {
+            if matches!(order, UnitOrder::BuildRoad { .. }) || !move_path.0.is_empty() {
+                continue;
+            }
+            let roads = roads_to_build.entry(nation).or_insert_with(|| {
+                missing_roads(
+                    nation,
+                    &rules,
+                    &improvement_layer,
+                    &city_connections,
+                    &query_city,
+                )
+            });
+            if roads.contains(&tile)
+                && can_build_road(
+                    unit.name(),
+                    tile,
+                    civilizations.get(nation),
+                    &improvement_layer,
+                    tile_map,
+                    ruleset,
+                )
+            {
+                roads.remove(&tile);
+                build_road.write(BuildRoad { worker: entity });
+                continue;
+            }
+            let road = roads
+                .iter()
+                .copied()
+                .min_by_key(|road| (road.distance_to(tile, grid), road.index()));
+            if let Some(road) = road
+                && let Some(path) = find_path(tile, road, &rules)
+            {
+                roads.remove(&road);
+                move_path.0 = path.tiles.into();
+            }
+            continue;
+        }
+
+        if !matches!(unit, Unit::Military(_)) {
+            continue;
+        }
+
+        let enemy_units: Vec<_> = query_unit
+            .iter()
+            .filter(|(_, _, _, enemy_owner)| diplomacy.is_at_war(nation, enemy_owner.nation()))
+            .map(|(enemy, _, enemy_unit, _)| (enemy, enemy_unit.tile))
+            .collect();
+        if let Some(defender) = closest_target_in_reach(tile, ranged, &enemy_units, grid) {
+            move_path.0.clear();
+            attack.write(Attack {
+                attacker: entity,
+                defender,
+            });
+            continue;
+        }
+
+        if defended_cities.contains(&tile) || !move_path.0.is_empty() {
+            continue;
+        }
+
+        let undefended_city = query_city
+            .iter()
+            .filter(|(city, city_owner)| {
+                city_owner.nation() == nation && !defended_cities.contains(&city.tile)
+            })
+            .map(|(city, _)| city.tile)
+            .min_by_key(|city_tile| (city_tile.distance_to(tile, grid), city_tile.index()));
+        if let Some(city_tile) = undefended_city
+            && let Some(path) = find_path(tile, city_tile, &rules)
+        {
+            defended_cities.insert(city_tile);
+            move_path.0 = path.tiles.into();
+            continue;
+        }
+
+        let unexplored_tile = tile
+            .tiles_in_distance(EXPLORE_DISTANCE, grid)
+            .filter(|&target| !visibility_layer.is_explored(nation, target))
+            .filter(|&target| tile_movement_cost(target, rules.domain, tile_map, ruleset).is_some())
+            .min_by_key(|target| (target.distance_to(tile, grid), target.index()));
+        if let Some(path) = unexplored_tile.and_then(|target| find_path(tile, target, &rules)) {
+            move_path.0 = path.tiles.into();
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! The ambient sounds of the terrain seen by the main camera: the waves near the coasts, the birds in the forests
+//! and the jungles, and the wind on the tundra and the snow.
+//!
+//! Every sound is a loop playing all the time, from the center of its tiles in the view and as loud as its tiles
+//! are many in the view, so the sounds fade into each other and move from ear to ear as the camera moves.
+//! The loops are the files `ambient_ocean`, `ambient_forest` and `ambient_wind` of [`AudioResource`].
+
+use bevy::{
+    audio::{SpatialScale, Volume},
+    prelude::*,
+};
+use civ_map_generator::{
+    tile::Tile,
+    tile_component::{BaseTerrain, Feature},
+    tile_map::TileMap,
+};
+
+use crate::{
+    MainCamera, TileMapResource,
+    assets::{AppState, AudioResource},
+    audio::AudioSettings,
+    world_map::{WorldTile, show_main_camera_area},
+};
+
+/// How fast a loop gets louder or quieter, in volume per second.
+const FADE_SPEED: f32 = 0.5;
+/// A loop is at its full volume when its tiles cover this share of the view.
+const FULL_VOLUME_SHARE: f32 = 0.5;
+/// The distance between the ears of the listener, in pixels of the map.
+const EAR_GAP: f32 = 400.;
+/// The number of pixels of the map in a unit of distance of the spatial audio, so a loop on the edge of the view
+/// isn't much quieter than a loop at its center.
+const PIXELS_PER_AUDIO_UNIT: f32 = 400.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Ambience {
+    Ocean,
+    Forest,
+    Wind,
+}
+
+impl Ambience {
+    const ALL: [Self; 3] = [Self::Ocean, Self::Forest, Self::Wind];
+
+    fn file_stem(self) -> &'static str {
+        match self {
+            Self::Ocean => "ambient_ocean",
+            Self::Forest => "ambient_forest",
+            Self::Wind => "ambient_wind",
+        }
+    }
+
+    /// The ambience of a tile, if it has one.
+    fn of(tile: Tile, tile_map: &TileMap) -> Option<Self> {
+        if matches!(
+            tile.feature(tile_map),
+            Some(Feature::Forest | Feature::Jungle)
+        ) {
+            return Some(Self::Forest);
+        }
+        match tile.base_terrain(tile_map) {
+            BaseTerrain::Coast => Some(Self::Ocean),
+            BaseTerrain::Tundra | BaseTerrain::Snow => Some(Self::Wind),
+            _ => None,
+        }
+    }
+}
+
+/// A loop of an ambient sound, with its volume while it fades in or out.
+#[derive(Component)]
+struct AmbientLoop {
+    ambience: Ambience,
+    volume: f32,
+}
+
+pub struct AmbientSoundPlugin;
+
+impl Plugin for AmbientSoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameStart), start_ambient_loops)
+            .add_systems(
+                Update,
+                update_ambient_loops
+                    .after(show_main_camera_area)
+                    .run_if(in_state(AppState::GameStart).and(resource_exists::<TileMapResource>)),
+            )
+            .add_systems(OnExit(AppState::GameStart), stop_ambient_loops);
+    }
+}
+
+fn start_ambient_loops(
+    mut commands: Commands,
+    audio: Option<Res<AudioResource>>,
+    main_camera: Single<Entity, With<MainCamera>>,
+) {
+    let Some(audio) = audio else {
+        return;
+    };
+    commands
+        .entity(*main_camera)
+        .insert(SpatialListener::new(EAR_GAP));
+    for ambience in Ambience::ALL {
+        let Some(source) = audio.get(ambience.file_stem()) else {
+            continue;
+        };
+        commands.spawn((
+            AudioPlayer::new(source),
+            PlaybackSettings::LOOP
+                .with_volume(Volume::SILENT)
+                .with_spatial(true)
+                .with_spatial_scale(SpatialScale::new_2d(1. / PIXELS_PER_AUDIO_UNIT)),
+            Transform::default(),
+            AmbientLoop {
+                ambience,
+                volume: 0.,
+            },
+        ));
+    }
+}
+
+fn stop_ambient_loops(mut commands: Commands, query_loop: Query<Entity, With<AmbientLoop>>) {
+    for entity in query_loop.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Move every loop to the center of its tiles in the view, and fade it toward the share of its tiles in the view.
+fn update_ambient_loops(
+    time: Res<Time>,
+    map: Res<TileMapResource>,
+    audio_settings: Res<AudioSettings>,
+    main_camera: Single<(&Transform, &Camera, &Projection), With<MainCamera>>,
+    query_tile: Query<(&WorldTile, &Transform, &Visibility), Without<AmbientLoop>>,
+    mut query_loop: Query<
+        (
+            &mut AmbientLoop,
+            &mut Transform,
+            Option<&mut SpatialAudioSink>,
+        ),
+        Without<MainCamera>,
+    >,
+) {
+    if query_loop.is_empty() {
+        return;
+    }
+
+    let (camera_transform, camera, projection) = main_camera.into_inner();
+    let zoom = match projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.,
+    };
+    let view = Rect::from_center_size(
+        camera_transform.translation.truncate(),
+        camera.logical_viewport_size().unwrap_or_default() * zoom,
+    );
+
+    let tile_map = &map.0;
+    let mut tile_count = 0;
+    let mut ambience_tiles = Ambience::ALL.map(|_| (0, Vec2::ZERO));
+    for (&WorldTile(tile), transform, visibility) in query_tile.iter() {
+        let position = transform.translation.truncate();
+        if *visibility == Visibility::Hidden || !view.contains(position) {
+            continue;
+        }
+        tile_count += 1;
+        if let Some(ambience) = Ambience::of(tile, tile_map) {
+            let (count, sum) = &mut ambience_tiles[ambience as usize];
+            *count += 1;
+            *sum += position;
+        }
+    }
+
+    let max_volume = audio_settings.master * audio_settings.effects;
+    let fade = FADE_SPEED * time.delta_secs();
+    for (mut ambient_loop, mut transform, sink) in query_loop.iter_mut() {
+        let (count, sum) = ambience_tiles[ambient_loop.ambience as usize];
+        let share = count as f32 / tile_count.max(1) as f32;
+        let target = (share / FULL_VOLUME_SHARE).min(1.) * max_volume;
+        ambient_loop.volume += (target - ambient_loop.volume).clamp(-fade, fade);
+        if count > 0 {
+            let center = sum / count as f32;
+            transform.translation = center.extend(0.);
+        }
+        // The sink is added once the sound starts playing.
+        if let Some(mut sink) = sink {
+            sink.set_volume(Volume::Linear(ambient_loop.volume));
+        }
+    }
+}
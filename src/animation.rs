@@ -0,0 +1,93 @@
+//! The animation settings, kept between games in [`ANIMATION_SETTINGS_PATH`] and changed in the options of the
+//! pause menu, see [`crate::pause_menu`].
+//!
+//! The units walk along their path tile by tile instead of jumping to the end of it, see
+//! [`crate::unit_sprite::MoveAnimation`]. The turn can't be ended and the units can't be given orders while a unit
+//! walks, so the player always sees where the units stand before acting.
+//!
+//! The fights flash and shake the units hit and show the damage dealt, see [`crate::combat_effect`].
+
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The animation settings changed by the player.
+pub const ANIMATION_SETTINGS_PATH: &str = "settings/animation.json";
+
+/// How fast the units walk.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum AnimationSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl AnimationSpeed {
+    pub const ALL: [Self; 3] = [Self::Slow, Self::Normal, Self::Fast];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Slow => "Slow",
+            Self::Normal => "Normal",
+            Self::Fast => "Fast",
+        }
+    }
+
+    /// The number of tiles a unit walks in a second.
+    pub fn tiles_per_second(self) -> f32 {
+        match self {
+            Self::Slow => 2.,
+            Self::Normal => 4.,
+            Self::Fast => 8.,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationSettings {
+    pub speed: AnimationSpeed,
+    /// Whether the animations are skipped for a faster game, the units jumping to the end of their path.
+    pub skip_animations: bool,
+    /// Whether the flashes and the damage numbers of the fights are skipped for a faster game.
+    pub skip_combat_effects: bool,
+}
+
+impl AnimationSettings {
+    /// The settings saved by the last game, or the default settings if there are none.
+    pub fn load() -> Self {
+        fs::read_to_string(ANIMATION_SETTINGS_PATH)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+pub struct GameAnimationPlugin;
+
+impl Plugin for GameAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AnimationSettings::load()).add_systems(
+            Update,
+            save_animation_settings.run_if(resource_changed::<AnimationSettings>),
+        );
+    }
+}
+
+/// Save the animation settings whenever the player changes them.
+fn save_animation_settings(settings: Res<AnimationSettings>) {
+    if !settings.is_added()
+        && let Err(error) = settings.write(Path::new(ANIMATION_SETTINGS_PATH))
+    {
+        error!("Can't save the animation settings: {error}");
+    }
+}
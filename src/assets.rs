@@ -1,6 +1,12 @@
+use std::{fs, path::Path};
+
 use bevy::{platform::collections::HashMap, prelude::*};
 use bevy_asset_loader::{asset_collection::AssetCollection, mapped::AssetFileStem};
 
+/// The folder of the music and the sound effects, in the assets folder.
+const AUDIO_PATH: &str = "Audio";
+const AUDIO_EXTENSIONS: [&str; 2] = ["ogg", "wav"];
+
 #[derive(AssetCollection, Resource)]
 pub struct MaterialResource {
     #[asset(path = "Images", collection(typed, mapped))]
@@ -14,12 +20,58 @@ impl MaterialResource {
             .unwrap_or_else(|| panic!("Can't find Image: {}", name))
             .clone()
     }
+
+    /// Like [`MaterialResource::texture_handle`], but return `None` if there is no image named `name`.
+    pub fn get_texture_handle(&self, name: &str) -> Option<Handle<Image>> {
+        self.textures.get(name).cloned()
+    }
+}
+
+/// The music and the sound effects, by the stem of their file name, e.g. `click` for `Audio/click.ogg`.
+///
+/// The audio files are optional, unlike the images: they are listed when the loading state ends,
+/// and a missing file only leaves its sound silent, see [`crate::audio`].
+#[derive(Resource)]
+pub struct AudioResource {
+    sounds: HashMap<String, Handle<AudioSource>>,
+}
+
+impl FromWorld for AudioResource {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let sounds = fs::read_dir(Path::new("assets").join(AUDIO_PATH))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let extension = path.extension()?.to_str()?;
+                if !AUDIO_EXTENSIONS.contains(&extension) {
+                    return None;
+                }
+                let stem = path.file_stem()?.to_str()?.to_owned();
+                let file_name = path.file_name()?.to_str()?;
+                Some((stem, asset_server.load(format!("{AUDIO_PATH}/{file_name}"))))
+            })
+            .collect();
+        Self { sounds }
+    }
+}
+
+impl AudioResource {
+    /// The sound named `name`, `None` if there is no audio file of this name.
+    pub fn get(&self, name: &str) -> Option<Handle<AudioSource>> {
+        self.sounds.get(name).cloned()
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum AppState {
     #[default]
     AssetLoading,
+    /// The setup of a new game, see [`crate::main_menu`].
+    MainMenu,
     MapGenerating,
     GameStart,
+    /// A saved game is replacing the current one, see [`crate::save::LoadGame`].
+    LoadingSave,
 }
@@ -0,0 +1,371 @@
+//! The music and the sound effects, played from the files of [`AudioResource`]:
+//!
+//! - the music of the era of the player, `music_<era>` e.g. `music_medieval_era`, or else `music`, looped,
+//! - `click` when a button is clicked,
+//! - `combat` when a fight of the player is over, and `build` when the player founds a city or builds a wonder.
+//!
+//! The volumes are kept between games in the settings of the player, see [`crate::settings`], and changed with the
+//! sliders in the options of the pause menu, see [`crate::pause_menu`].
+
+use bevy::{
+    audio::Volume,
+    picking::{
+        events::{Click, Drag},
+        pointer::{Location, PointerButton},
+    },
+    prelude::*,
+    ui::UiGlobalTransform,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource,
+    assets::{AppState, AudioResource},
+    civilization::{Civilizations, PlayerCivilization},
+    era::civilization_era,
+    game_event::{CityFounded, CombatResolved},
+    wonder::WonderBuilt,
+};
+
+/// A volume of [`AudioSettings`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AudioChannel {
+    Master,
+    Music,
+    Effects,
+}
+
+impl AudioChannel {
+    pub const ALL: [Self; 3] = [Self::Master, Self::Music, Self::Effects];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Master => "Master volume",
+            Self::Music => "Music volume",
+            Self::Effects => "Effects volume",
+        }
+    }
+}
+
+/// The volumes, from 0 to 1. The volume of the music and of the effects is scaled by the master volume.
+#[derive(Resource, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 0.8,
+            music: 0.6,
+            effects: 0.8,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn get(&self, channel: AudioChannel) -> f32 {
+        match channel {
+            AudioChannel::Master => self.master,
+            AudioChannel::Music => self.music,
+            AudioChannel::Effects => self.effects,
+        }
+    }
+
+    fn set(&mut self, channel: AudioChannel, volume: f32) {
+        let volume = volume.clamp(0., 1.);
+        match channel {
+            AudioChannel::Master => self.master = volume,
+            AudioChannel::Music => self.music = volume,
+            AudioChannel::Effects => self.effects = volume,
+        }
+    }
+
+    fn music_volume(&self) -> Volume {
+        Volume::Linear(self.master * self.music)
+    }
+
+    fn effects_volume(&self) -> Volume {
+        Volume::Linear(self.master * self.effects)
+    }
+}
+
+/// A sound effect to play, see the [module documentation](self).
+#[derive(Message, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlaySound {
+    Click,
+    Combat,
+    Build,
+}
+
+impl PlaySound {
+    fn file_stem(self) -> &'static str {
+        match self {
+            Self::Click => "click",
+            Self::Combat => "combat",
+            Self::Build => "build",
+        }
+    }
+}
+
+/// The music playing, with the name of its file.
+#[derive(Component)]
+struct MusicTrack(String);
+
+/// The track of a volume slider, see [`spawn_volume_sliders`].
+#[derive(Component)]
+struct VolumeSlider(AudioChannel);
+
+/// The filled part of a volume slider.
+#[derive(Component)]
+struct VolumeSliderFill(AudioChannel);
+
+/// The name and the value of a volume slider.
+#[derive(Component)]
+struct VolumeSliderLabel(AudioChannel);
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AudioSettings>()
+            .add_message::<PlaySound>()
+            .add_systems(
+                Update,
+                (
+                    (play_era_music, play_game_sounds).run_if(in_state(AppState::GameStart)),
+                    play_click_sounds,
+                    play_sounds.run_if(resource_exists::<AudioResource>),
+                    (apply_music_volume, update_volume_sliders)
+                        .run_if(resource_changed::<AudioSettings>),
+                )
+                    .chain(),
+            )
+            .add_systems(OnExit(AppState::GameStart), stop_music);
+    }
+}
+
+/// Play the music of the era of the player, once the player enters a new era.
+fn play_era_music(
+    mut commands: Commands,
+    audio: Option<Res<AudioResource>>,
+    audio_settings: Res<AudioSettings>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Option<Res<Civilizations>>,
+    player_civilization: Option<Res<PlayerCivilization>>,
+    query_music: Query<(Entity, &MusicTrack)>,
+) {
+    let (Some(audio), Some(civilizations), Some(player_civilization)) =
+        (audio, civilizations, player_civilization)
+    else {
+        return;
+    };
+    if !civilizations.is_changed() && !query_music.is_empty() {
+        return;
+    }
+
+    let era = civilization_era(civilizations.get(player_civilization.0), &ruleset.0);
+    let era_track = format!("music_{}", era.to_lowercase().replace(' ', "_"));
+    let Some((name, music)) = [era_track, "music".to_owned()]
+        .into_iter()
+        .find_map(|name| audio.get(&name).map(|music| (name, music)))
+    else {
+        return;
+    };
+    if query_music.iter().any(|(_, track)| track.0 == name) {
+        return;
+    }
+
+    for (entity, _) in query_music.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn((
+        AudioPlayer::new(music),
+        PlaybackSettings::LOOP.with_volume(audio_settings.music_volume()),
+        MusicTrack(name),
+    ));
+}
+
+fn stop_music(mut commands: Commands, query_music: Query<Entity, With<MusicTrack>>) {
+    for entity in query_music.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Play the sounds of the fights of the player, and of the cities and the wonders built by the player.
+fn play_game_sounds(
+    player_civilization: Option<Res<PlayerCivilization>>,
+    mut combat_resolved: MessageReader<CombatResolved>,
+    mut city_founded: MessageReader<CityFounded>,
+    mut wonder_built: MessageReader<WonderBuilt>,
+    mut play_sound: MessageWriter<PlaySound>,
+) {
+    let Some(player_civilization) = player_civilization else {
+        return;
+    };
+    let player = player_civilization.0;
+    // Every message is read, so none is left for the next frame.
+    if combat_resolved
+        .read()
+        .filter(|combat| combat.attacker_nation == player || combat.defender_nation == player)
+        .count()
+        > 0
+    {
+        play_sound.write(PlaySound::Combat);
+    }
+    let city_founded = city_founded
+        .read()
+        .filter(|founded| founded.nation == player)
+        .count()
+        > 0;
+    let wonder_built = wonder_built
+        .read()
+        .filter(|built| built.nation == player)
+        .count()
+        > 0;
+    if city_founded || wonder_built {
+        play_sound.write(PlaySound::Build);
+    }
+}
+
+/// Play a click when a button of the UI is clicked.
+fn play_click_sounds(
+    mut clicks: MessageReader<Pointer<Click>>,
+    query_node: Query<(), With<Node>>,
+    mut play_sound: MessageWriter<PlaySound>,
+) {
+    if clicks
+        .read()
+        .filter(|click| {
+            matches!(click.button, PointerButton::Primary) && query_node.contains(click.entity)
+        })
+        .count()
+        > 0
+    {
+        play_sound.write(PlaySound::Click);
+    }
+}
+
+fn play_sounds(
+    mut commands: Commands,
+    audio: Res<AudioResource>,
+    audio_settings: Res<AudioSettings>,
+    mut play_sound: MessageReader<PlaySound>,
+) {
+    for &sound in play_sound.read() {
+        if let Some(source) = audio.get(sound.file_stem()) {
+            commands.spawn((
+                AudioPlayer::new(source),
+                PlaybackSettings::DESPAWN.with_volume(audio_settings.effects_volume()),
+            ));
+        }
+    }
+}
+
+fn apply_music_volume(
+    audio_settings: Res<AudioSettings>,
+    mut query_music: Query<&mut AudioSink, With<MusicTrack>>,
+) {
+    for mut sink in query_music.iter_mut() {
+        sink.set_volume(audio_settings.music_volume());
+    }
+}
+
+fn volume_label(channel: AudioChannel, audio_settings: &AudioSettings) -> String {
+    format!(
+        "{}: {}%",
+        channel.name(),
+        (audio_settings.get(channel) * 100.).round()
+    )
+}
+
+/// Spawn a slider for every volume, set by clicking or dragging along the slider.
+pub fn spawn_volume_sliders(window: &mut ChildSpawnerCommands, audio_settings: &AudioSettings) {
+    for channel in AudioChannel::ALL {
+        window.spawn((
+            Text(volume_label(channel, audio_settings)),
+            TextFont {
+                font_size: 14.,
+                ..default()
+            },
+            VolumeSliderLabel(channel),
+            Pickable::IGNORE,
+        ));
+        window
+            .spawn((
+                Node {
+                    height: Val::Px(14.),
+                    border: UiRect::all(Val::Px(1.)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                BorderColor::all(Color::WHITE),
+                VolumeSlider(channel),
+            ))
+            .with_child((
+                Node {
+                    width: Val::Percent(audio_settings.get(channel) * 100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.35, 0.65, 0.95)),
+                VolumeSliderFill(channel),
+                Pickable::IGNORE,
+            ))
+            .observe(set_volume_on_click)
+            .observe(set_volume_on_drag);
+    }
+}
+
+/// The volume at the pointer on the slider: 0 at its left edge, 1 at its right edge.
+fn volume_at(location: &Location, node: &ComputedNode, transform: &UiGlobalTransform) -> f32 {
+    // The pointer is in logical pixels, the node in physical pixels.
+    let x = location.position.x / node.inverse_scale_factor();
+    let left = transform.translation.x - node.size().x / 2.;
+    (x - left) / node.size().x.max(1.)
+}
+
+fn set_volume_on_click(
+    click: On<Pointer<Click>>,
+    mut audio_settings: ResMut<AudioSettings>,
+    query_slider: Query<(&VolumeSlider, &ComputedNode, &UiGlobalTransform)>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    if let Ok((slider, node, transform)) = query_slider.get(click.entity) {
+        audio_settings.set(
+            slider.0,
+            volume_at(&click.pointer_location, node, transform),
+        );
+    }
+}
+
+fn set_volume_on_drag(
+    drag: On<Pointer<Drag>>,
+    mut audio_settings: ResMut<AudioSettings>,
+    query_slider: Query<(&VolumeSlider, &ComputedNode, &UiGlobalTransform)>,
+) {
+    if !matches!(drag.button, PointerButton::Primary) {
+        return;
+    }
+    if let Ok((slider, node, transform)) = query_slider.get(drag.entity) {
+        audio_settings.set(slider.0, volume_at(&drag.pointer_location, node, transform));
+    }
+}
+
+fn update_volume_sliders(
+    audio_settings: Res<AudioSettings>,
+    mut query_fill: Query<(&VolumeSliderFill, &mut Node)>,
+    mut query_label: Query<(&VolumeSliderLabel, &mut Text)>,
+) {
+    for (fill, mut node) in query_fill.iter_mut() {
+        node.width = Val::Percent(audio_settings.get(fill.0) * 100.);
+    }
+    for (label, mut text) in query_label.iter_mut() {
+        text.0 = volume_label(label.0, &audio_settings);
+    }
+}
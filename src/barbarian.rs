@@ -0,0 +1,433 @@
+use bevy::prelude::*;
+use civ_map_generator::{
+    grid::hex_grid::HexGrid, nation::Nation, ruleset::Ruleset, tile::Tile, tile_map::TileMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::{AppState, MaterialResource},
+    city::City,
+    civilization::{Civilizations, Difficulty},
+    combat::{Attack, closest_target_in_reach},
+    diplomacy::DiplomacyState,
+    game_event::TileChanged,
+    improvement::TileImprovementLayer,
+    pathfinding::{MovementRules, ZoneOfControl, ZoneOfControlRule, find_path},
+    pillage::{Pillage, can_pillage},
+    rng::GameRng,
+    turn::{TurnManager, TurnProcessing, TurnSet},
+    unit::{
+        MapUnit, MovePath, SpawnUnit, UnitDomain, find_spawn_tile, is_civilian_unit_type,
+        taken_tiles, tile_movement_cost, unit_kind,
+    },
+    unit_component::{Movement, Owner, RangedStrength, Unit},
+    visibility::VisibilityLayer,
+    world_map::WorldTileEntities,
+};
+
+/// The improvement which marks a barbarian encampment on the map.
+pub const BARBARIAN_ENCAMPMENT: &str = "Barbarian encampment";
+/// At most this many encampments exist for every civilization in the game.
+const ENCAMPMENTS_PER_CIVILIZATION: usize = 2;
+/// Encampments are never placed closer than this to a city or to another encampment.
+const MIN_ENCAMPMENT_DISTANCE: u32 = 4;
+/// An encampment produces a unit every 6 to 10 turns.
+const ENCAMPMENT_UNIT_INTERVAL: (i32, i32) = (6, 11);
+/// An encampment stops producing units while this many barbarian units are around it.
+const MAX_UNITS_NEAR_ENCAMPMENT: usize = 2;
+/// Barbarian units go for the enemy units and improvements within this distance.
+const RAID_DISTANCE: u32 = 6;
+
+/// A barbarian encampment, a child of the [`crate::world_map::WorldTile`] entity it stands on.
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct BarbarianEncampment {
+    pub tile: Tile,
+    /// The turns left before the encampment produces its next unit.
+    pub turns_until_next_unit: u32,
+}
+
+/// The encampment with its sprite, to spawn as a child of the tile entity.
+pub fn encampment_bundle(
+    encampment: BarbarianEncampment,
+    grid: HexGrid,
+    materials: &MaterialResource,
+) -> impl Bundle {
+    (
+        encampment,
+        Sprite {
+            custom_size: Some(Vec2::from(grid.layout.size)),
+            image: materials.texture_handle(BARBARIAN_ENCAMPMENT),
+            ..default()
+        },
+        Transform::from_xyz(0., 0., 3.5),
+    )
+}
+
+/// Written when a unit clears a barbarian encampment, giving `gold` to its civilization.
+#[derive(Message)]
+pub struct EncampmentCleared {
+    pub nation: Nation,
+    pub tile: Tile,
+    pub gold: f32,
+}
+
+pub struct BarbarianPlugin;
+
+impl Plugin for BarbarianPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<EncampmentCleared>()
+            .add_systems(
+                Update,
+                clear_encampments.run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                (
+                    spawn_encampments,
+                    produce_barbarian_units,
+                    move_barbarian_units,
+                )
+                    .chain()
+                    .in_set(TurnSet::Barbarians),
+            );
+    }
+}
+
+/// Return the unit the barbarians produce: the strongest land military unit every civilization can already build.
+///
+/// Barbarians get stronger as the slowest civilization advances, and never field units nobody can answer.
+pub fn barbarian_unit<'a>(
+    civilizations: &Civilizations,
+    tile_map: &TileMap,
+    ruleset: &'a Ruleset,
+) -> &'a str {
+    ruleset
+        .units
+        .values()
+        .filter(|unit| {
+            unit.unique_to.is_empty()
+                && !is_civilian_unit_type(&unit.unit_type)
+                && unit.strength > 0
+                && UnitDomain::of_unit(&unit.name, ruleset) == UnitDomain::Land
+                && tile_map
+                    .starting_tile_and_civilization
+                    .values()
+                    .all(|&nation| {
+                        civilizations
+                            .get(nation)
+                            .has_technology(&unit.required_tech)
+                    })
+        })
+        // Break ties by name so the choice doesn't depend on the order of the ruleset.
+        .max_by(|a, b| {
+            a.strength
+                .cmp(&b.strength)
+                .then_with(|| b.name.cmp(&a.name))
+        })
+        .map_or("Warrior", |unit| unit.name.as_str())
+}
+
+/// Place a new encampment on a land tile no civilization sees, outside the borders of every city.
+///
+/// Encampments start to appear after the `barbarianSpawnDelay` turns of the difficulty, at most one per turn.
+/// Every new encampment comes with a unit to defend it.
+fn spawn_encampments(
+    mut commands: Commands,
+    mut spawn_unit: MessageWriter<SpawnUnit>,
+    mut tile_changed: MessageWriter<TileChanged>,
+    mut rng: ResMut<GameRng>,
+    turn_manager: Res<TurnManager>,
+    difficulty: Res<Difficulty>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    materials: Res<MaterialResource>,
+    tile_entities: Res<WorldTileEntities>,
+    visibility_layer: Res<VisibilityLayer>,
+    civilizations: Res<Civilizations>,
+    mut improvement_layer: ResMut<TileImprovementLayer>,
+    query_encampment: Query<&BarbarianEncampment>,
+    query_city: Query<&City>,
+    query_unit: Query<(&MapUnit, &Unit)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+
+    if (turn_manager.turn as i32) < difficulty.info(ruleset).barbarian_spawn_delay {
+        return;
+    }
+
+    let max_encampments =
+        tile_map.starting_tile_and_civilization.len() * ENCAMPMENTS_PER_CIVILIZATION;
+    if query_encampment.iter().count() >= max_encampments {
+        return;
+    }
+
+    let taken_tiles = taken_tiles(&query_unit);
+    let candidates: Vec<_> = tile_map
+        .all_tiles()
+        .filter(|&tile| {
+            tile_movement_cost(tile, UnitDomain::Land, tile_map, ruleset).is_some()
+                && improvement_layer.improvement(tile).is_none()
+                && !taken_tiles.contains(&(tile, true))
+                && !taken_tiles.contains(&(tile, false))
+                && civilizations.iter().all(|(nation, _)| {
+                    nation == Nation::Barbarians || !visibility_layer.is_visible(nation, tile)
+                })
+                && query_city.iter().all(|city| {
+                    !city.owned_tiles.contains(&tile)
+                        && city.tile.distance_to(tile, grid) >= MIN_ENCAMPMENT_DISTANCE
+                })
+                && query_encampment.iter().all(|encampment| {
+                    encampment.tile.distance_to(tile, grid) >= MIN_ENCAMPMENT_DISTANCE
+                })
+        })
+        .collect();
+    if candidates.is_empty() {
+        return;
+    }
+
+    let tile = candidates[rng.gen_range(0, candidates.len() as i32) as usize];
+    improvement_layer.set_improvement(
+        tile,
+        Some(BARBARIAN_ENCAMPMENT.to_owned()),
+        &mut tile_changed,
+    );
+    let encampment = BarbarianEncampment {
+        tile,
+        turns_until_next_unit: rng.gen_range(ENCAMPMENT_UNIT_INTERVAL.0, ENCAMPMENT_UNIT_INTERVAL.1)
+            as u32,
+    };
+    commands
+        .entity(tile_entities.0[&tile])
+        .with_child(encampment_bundle(encampment, grid, &materials));
+    spawn_unit.write(SpawnUnit {
+        unit_name: barbarian_unit(&civilizations, tile_map, ruleset).to_owned(),
+        owner: Owner::Barbarian,
+        tile,
+    });
+}
+
+/// Let every encampment produce a unit when its countdown ends, unless enough barbarian units are already around it.
+fn produce_barbarian_units(
+    mut spawn_unit: MessageWriter<SpawnUnit>,
+    mut rng: ResMut<GameRng>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    mut query_encampment: Query<&mut BarbarianEncampment>,
+    query_unit: Query<(&MapUnit, &Unit)>,
+    query_owner: Query<(&MapUnit, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+    let taken_tiles = taken_tiles(&query_unit);
+
+    // Sort the encampments so the random rolls don't depend on the order of the entities.
+    let mut encampments: Vec<_> = query_encampment.iter_mut().collect();
+    encampments.sort_by_key(|encampment| encampment.tile.index());
+
+    for mut encampment in encampments {
+        encampment.turns_until_next_unit = encampment.turns_until_next_unit.saturating_sub(1);
+        if encampment.turns_until_next_unit > 0 {
+            continue;
+        }
+        encampment.turns_until_next_unit =
+            rng.gen_range(ENCAMPMENT_UNIT_INTERVAL.0, ENCAMPMENT_UNIT_INTERVAL.1) as u32;
+
+        let nearby_units = query_owner
+            .iter()
+            .filter(|(map_unit, owner)| {
+                matches!(owner, Owner::Barbarian)
+                    && map_unit.tile.distance_to(encampment.tile, grid) <= MIN_ENCAMPMENT_DISTANCE
+            })
+            .count();
+        if nearby_units >= MAX_UNITS_NEAR_ENCAMPMENT {
+            continue;
+        }
+
+        let unit_name = barbarian_unit(&civilizations, tile_map, ruleset);
+        let Some(tile) = find_spawn_tile(
+            encampment.tile,
+            &unit_kind(unit_name, ruleset),
+            |tile, is_military| taken_tiles.contains(&(tile, is_military)),
+            tile_map,
+            ruleset,
+        ) else {
+            continue;
+        };
+        spawn_unit.write(SpawnUnit {
+            unit_name: unit_name.to_owned(),
+            owner: Owner::Barbarian,
+            tile,
+        });
+    }
+}
+
+/// Return `true` if barbarians can pillage `tile`: an improvement or a road inside the borders of a city,
+/// see [`can_pillage`].
+fn can_raid(
+    tile: Tile,
+    improvement_layer: &TileImprovementLayer,
+    query_city: &Query<&City>,
+) -> bool {
+    can_pillage(tile, improvement_layer)
+        && query_city
+            .iter()
+            .any(|city| city.tile != tile && city.owned_tiles.contains(&tile))
+}
+
+/// Give orders to every barbarian military unit, in order of priority:
+/// attack an enemy unit within reach, pillage the improvement it stands on,
+/// or move toward the closest enemy unit or improvement within [`RAID_DISTANCE`].
+fn move_barbarian_units(
+    mut attack: MessageWriter<Attack>,
+    mut pillage: MessageWriter<Pillage>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    improvement_layer: Res<TileImprovementLayer>,
+    diplomacy: Res<DiplomacyState>,
+    zone_of_control_rule: Res<ZoneOfControlRule>,
+    query_city: Query<&City>,
+    query_unit: Query<(Entity, &MapUnit, &Owner)>,
+    mut query_barbarian: Query<(
+        Entity,
+        &Unit,
+        &Owner,
+        &MapUnit,
+        &Movement,
+        &RangedStrength,
+        &mut MovePath,
+    )>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+
+    let enemy_units: Vec<_> = query_unit
+        .iter()
+        .filter(|(_, _, owner)| !matches!(owner, Owner::Barbarian))
+        .map(|(entity, map_unit, _)| (entity, map_unit.tile))
+        .collect();
+    let pillage_targets: Vec<_> = query_city
+        .iter()
+        .flat_map(|city| city.owned_tiles.iter().copied())
+        .filter(|&tile| can_raid(tile, &improvement_layer, &query_city))
+        .collect();
+    let military_units: Vec<_> = query_barbarian
+        .iter()
+        .filter(|(_, unit, ..)| matches!(unit, Unit::Military(_)))
+        .map(|(_, _, owner, map_unit, ..)| (map_unit.tile, owner.nation()))
+        .collect();
+
+    for (entity, unit, owner, map_unit, movement, ranged, mut move_path) in
+        query_barbarian.iter_mut()
+    {
+        if !matches!(owner, Owner::Barbarian) || !matches!(unit, Unit::Military(_)) {
+            continue;
+        }
+        let tile = map_unit.tile;
+
+        if let Some(defender) = closest_target_in_reach(tile, ranged, &enemy_units, grid) {
+            move_path.0.clear();
+            attack.write(Attack {
+                attacker: entity,
+                defender,
+            });
+            continue;
+        }
+
+        if can_raid(tile, &improvement_layer, &query_city) {
+            move_path.0.clear();
+            pillage.write(Pillage { unit: entity });
+            continue;
+        }
+
+        // Stop next to enemy units so they can be attacked next turn, and go onto improvements to pillage them.
+        let target = enemy_units
+            .iter()
+            .map(|&(_, enemy_tile)| (enemy_tile, true))
+            .chain(pillage_targets.iter().map(|&target| (target, false)))
+            .filter(|(target, _)| target.distance_to(tile, grid) <= RAID_DISTANCE)
+            .min_by_key(|(target, _)| (target.distance_to(tile, grid), target.index()));
+        let Some((target, is_unit)) = target else {
+            continue;
+        };
+
+        let zone_of_control = ZoneOfControl::of_enemies(
+            owner.nation(),
+            unit.name(),
+            &military_units,
+            *zone_of_control_rule,
+            &diplomacy,
+            tile_map,
+            ruleset,
+        );
+        let ends_movement = |from, to| zone_of_control.ends_movement(from, to);
+        let rules = MovementRules {
+            embarkation: None,
+            ends_movement: Some(&ends_movement),
+            ..MovementRules::new(
+                unit.name(),
+                civilizations.get(Nation::Barbarians),
+                movement.max,
+                tile_map,
+                ruleset,
+            )
+        };
+        if let Some(path) = find_path(tile, target, &rules) {
+            let mut tiles = path.tiles;
+            if is_unit {
+                tiles.pop();
+            }
+            move_path.0 = tiles.into();
+        }
+    }
+}
+
+/// Remove the encampments entered by a military unit of a civilization or a city-state once no barbarian defends them,
+/// and give the reward of the difficulty to its civilization.
+fn clear_encampments(
+    mut commands: Commands,
+    mut encampment_cleared: MessageWriter<EncampmentCleared>,
+    mut tile_changed: MessageWriter<TileChanged>,
+    ruleset: Res<RulesetResource>,
+    difficulty: Res<Difficulty>,
+    mut civilizations: ResMut<Civilizations>,
+    mut improvement_layer: ResMut<TileImprovementLayer>,
+    query_encampment: Query<(Entity, &BarbarianEncampment)>,
+    query_moved_unit: Query<(&MapUnit, &Unit, &Owner), Changed<MapUnit>>,
+    query_unit: Query<(&MapUnit, &Unit, &Owner)>,
+) {
+    let gold = difficulty.info(&ruleset.0).clear_barbarian_camp_reward as f32;
+
+    for (map_unit, unit, owner) in query_moved_unit.iter() {
+        if matches!(owner, Owner::Barbarian) || !matches!(unit, Unit::Military(_)) {
+            continue;
+        }
+        let tile = map_unit.tile;
+        let Some((encampment, _)) = query_encampment
+            .iter()
+            .find(|(_, encampment)| encampment.tile == tile)
+        else {
+            continue;
+        };
+        let is_defended = query_unit.iter().any(|(other, other_unit, other_owner)| {
+            other.tile == tile
+                && matches!(other_unit, Unit::Military(_))
+                && matches!(other_owner, Owner::Barbarian)
+        });
+        if is_defended {
+            continue;
+        }
+
+        commands.entity(encampment).despawn();
+        improvement_layer.set_improvement(tile, None, &mut tile_changed);
+        let nation = owner.nation();
+        civilizations.get_mut(nation).gold += gold;
+        encampment_cleared.write(EncampmentCleared { nation, tile, gold });
+    }
+}
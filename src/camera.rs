@@ -0,0 +1,209 @@
+//! Smooth moves of the main camera to a place of the map, e.g. to the capital of the player with the `C` key
+//! or to the place of the newest notification with the `N` key.
+//!
+//! Systems request a move with [`CameraCommands::pan_to`], and the camera eases to the target over the
+//! requested duration, taking the shortest way across the edge of a map which wraps around.
+
+use bevy::prelude::*;
+use civ_map_generator::{
+    grid::{Grid, hex_grid::HexGrid},
+    tile::Tile,
+};
+
+use crate::{
+    MainCamera, TileMapResource,
+    assets::AppState,
+    city::City,
+    civilization::PlayerCivilization,
+    grid::{map_pixel_width, wrap_x_position},
+    notification::Notifications,
+    unit_component::Owner,
+};
+
+/// How long the camera takes to reach a place chosen with a hotkey, in seconds.
+pub const PAN_DURATION: f32 = 0.5;
+
+/// The moves of the main camera requested by the game and the UI.
+#[derive(Resource, Default)]
+pub struct CameraCommands {
+    /// The move requested this frame, replacing the move in progress.
+    request: Option<(Vec2, f32)>,
+    pan: Option<CameraPan>,
+}
+
+impl CameraCommands {
+    /// Ease the main camera to `world_pos` in `duration` seconds, or move it at once if `duration` is 0.
+    pub fn pan_to(&mut self, world_pos: Vec2, duration: f32) {
+        self.request = Some((world_pos, duration));
+    }
+
+    /// Ease the main camera to the center of `tile`, see [`CameraCommands::pan_to`].
+    pub fn pan_to_tile(&mut self, tile: Tile, grid: HexGrid, duration: f32) {
+        let position = grid.offset_to_pixel(tile.to_offset(grid));
+        self.pan_to(Vec2::from(position), duration);
+    }
+
+    /// Whether the camera is moving to a requested position.
+    pub fn is_panning(&self) -> bool {
+        self.request.is_some() || self.pan.is_some()
+    }
+}
+
+/// Scrolling the main camera when the cursor rests near an edge of the window.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct EdgePanSettings {
+    pub enabled: bool,
+    /// The speed of the camera, in pixels of the map per second.
+    pub speed: f32,
+    /// How close to an edge of the window the cursor scrolls the camera, in logical pixels.
+    pub margin: f32,
+}
+
+impl Default for EdgePanSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            speed: 300.,
+            margin: 12.,
+        }
+    }
+}
+
+impl EdgePanSettings {
+    /// The direction to scroll the camera to with the cursor at `cursor_position` in a window of `window_size`,
+    /// zero if the cursor isn't near an edge.
+    pub fn direction(&self, cursor_position: Vec2, window_size: Vec2) -> Vec2 {
+        let mut direction = Vec2::ZERO;
+        if cursor_position.x <= self.margin {
+            direction.x -= 1.;
+        } else if cursor_position.x >= window_size.x - self.margin {
+            direction.x += 1.;
+        }
+        // The y-axis of the window goes down, the y-axis of the map goes up.
+        if cursor_position.y <= self.margin {
+            direction.y += 1.;
+        } else if cursor_position.y >= window_size.y - self.margin {
+            direction.y -= 1.;
+        }
+        direction
+    }
+}
+
+struct CameraPan {
+    from: Vec2,
+    to: Vec2,
+    duration: f32,
+    elapsed: f32,
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraCommands>()
+            .init_resource::<EdgePanSettings>()
+            .add_systems(
+                Update,
+                (
+                    pan_to_capital_on_key,
+                    pan_to_notification_on_key,
+                    pan_main_camera,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            );
+    }
+}
+
+/// Move the main camera along the requested pan, easing in and out.
+pub fn pan_main_camera(
+    time: Res<Time>,
+    map: Option<Res<TileMapResource>>,
+    mut camera_commands: ResMut<CameraCommands>,
+    mut camera_transform: Single<&mut Transform, With<MainCamera>>,
+) {
+    let Some(map) = map else {
+        return;
+    };
+    if !camera_commands.is_panning() {
+        return;
+    }
+    let grid = map.0.world_grid.grid;
+    let camera_position = camera_transform.translation.truncate();
+
+    if let Some((mut target, duration)) = camera_commands.request.take() {
+        // The camera takes the shortest way to the closest copy of the target on a wrapping map.
+        if grid.wrap_x() {
+            let map_width = grid.offset_to_pixel(OffsetCoordinate::new(grid.width() as i32, 0))[0]
+                - grid.offset_to_pixel(OffsetCoordinate::new(0, 0))[0];
+            target.x += ((camera_position.x - target.x) / map_width).round() * map_width;
+        }
+        camera_commands.pan = Some(CameraPan {
+            from: camera_position,
+            to: target,
+            duration,
+            elapsed: 0.,
+        });
+    }
+
+    let Some(pan) = &mut camera_commands.pan else {
+        return;
+    };
+    pan.elapsed += time.delta_secs();
+    let progress = if pan.duration > 0. {
+        (pan.elapsed / pan.duration).min(1.)
+    } else {
+        1.
+    };
+    // Smoothstep easing, so the camera starts and stops gently.
+    let eased = progress * progress * (3. - 2. * progress);
+    let position = pan.from.lerp(pan.to, eased);
+    camera_transform.translation.x = if grid.wrap_x() {
+        wrap_x_position(position.x, grid)
+    } else {
+        position.x
+    };
+    camera_transform.translation.y = position.y;
+
+    if progress >= 1. {
+        camera_commands.pan = None;
+    }
+}
+
+fn pan_to_capital_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    map: Res<TileMapResource>,
+    player_civilization: Res<PlayerCivilization>,
+    query_city: Query<(&City, &Owner)>,
+    mut camera_commands: ResMut<CameraCommands>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyC) {
+        return;
+    }
+    if let Some((capital, _)) = query_city
+        .iter()
+        .find(|(city, owner)| city.is_capital && owner.nation() == player_civilization.0)
+    {
+        camera_commands.pan_to_tile(capital.tile, map.0.world_grid.grid, PAN_DURATION);
+    }
+}
+
+/// Move the camera to the place of the newest notification of the player about a place.
+fn pan_to_notification_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    map: Res<TileMapResource>,
+    player_civilization: Res<PlayerCivilization>,
+    notifications: Res<Notifications>,
+    mut camera_commands: ResMut<CameraCommands>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyN) {
+        return;
+    }
+    if let Some(location) = notifications
+        .for_nation(player_civilization.0)
+        .filter_map(|notification| notification.location)
+        .last()
+    {
+        camera_commands.pan_to_tile(location, map.0.world_grid.grid, PAN_DURATION);
+    }
+}
@@ -0,0 +1,1244 @@
+use std::collections::HashSet;
+
+use bevy::{picking::events::Out, picking::events::Over, prelude::*};
+use civ_map_generator::{
+    grid::hex_grid::HexGrid, nation::Nation, ruleset::Ruleset, tile::Tile, tile_map::TileMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    accessibility::AccessibilitySettings,
+    assets::AppState,
+    civilization::{Civilization, Civilizations},
+    command::{PlayerCommand, UnitId},
+    connection::CityConnections,
+    effect::{CityEffects, placement_allows},
+    game_event::{CityFounded, TileChanged},
+    key_bindings::{InputAction, KeyBindings},
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, SpawnUnit, UnitDomain, find_spawn_tile, taken_tiles, unit_kind},
+    unit_component::{Owner, Unit},
+    visibility::SightRange,
+    wonder::{WonderBuilt, Wonders},
+    world_map::{SelectedUnit, WorldTileEntities},
+    yields::{TileYields, Yields, update_changed_tile_yields},
+};
+
+/// Cities can't be founded within this distance of another city.
+pub const MIN_CITY_DISTANCE: u32 = 3;
+
+/// Citizens can only work tiles within this distance of the city.
+pub const CITY_WORK_RANGE: u32 = 3;
+
+/// A new city owns its tile and the 6 tiles around it.
+const INITIAL_OWNED_TILES: usize = 7;
+
+/// Every citizen eats 2 food per turn.
+const FOOD_PER_CITIZEN: f32 = 2.;
+
+/// A city producing gold gets 1 gold for every 4 production, like Wealth in Civ V.
+const PRODUCTION_PER_GOLD: f32 = 4.;
+
+/// Cities have 200 health points before the bonuses of their buildings, as in Civ V.
+const BASE_CITY_HEALTH: u32 = 200;
+/// The combat strength of a city before its population and its buildings.
+const BASE_CITY_STRENGTH: f32 = 8.;
+/// Every citizen adds to the combat strength of its city.
+const CITY_STRENGTH_PER_CITIZEN: f32 = 0.4;
+/// Cities heal this many health points every turn.
+const CITY_HEALING_PER_TURN: u32 = 20;
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct City {
+    pub name: String,
+    pub tile: Tile,
+    pub population: u32,
+    pub food_stored: f32,
+    /// The tiles inside the borders of the city, including the city tile.
+    pub owned_tiles: Vec<Tile>,
+    /// The tiles worked by the citizens of the city. The city tile is always worked for free and is not in this list.
+    pub worked_tiles: Vec<Tile>,
+    /// The tiles the player chose to work. They are worked before the tiles chosen automatically.
+    pub locked_tiles: Vec<Tile>,
+    /// The names of the specialists of the city, one for every citizen working in a building instead of a tile,
+    /// see [`crate::specialist`].
+    pub specialists: Vec<String>,
+    pub production: Option<CityProduction>,
+    /// The production accumulated toward [`City::production`].
+    pub production_stored: f32,
+    /// The buildings built in the city.
+    pub buildings: Vec<String>,
+    /// The first city of a civilization is its capital.
+    pub is_capital: bool,
+    /// The culture accumulated toward the next tile of the borders, see [`City::border_growth_cost`].
+    pub border_culture: f32,
+    /// The health points lost to attacks, see [`City::max_health`].
+    pub damage: u32,
+    /// Cities strike once per turn, see [`crate::combat::CityStrike`].
+    pub has_struck: bool,
+    /// The water tiles of the city next to an enemy ship, which its citizens can't work, see [`crate::naval`].
+    pub blockaded_tiles: Vec<Tile>,
+    /// Captured cities are puppets until they are annexed: they choose their production themselves
+    /// and don't make policies more expensive.
+    pub is_puppet: bool,
+}
+
+/// What a city is producing.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CityProduction {
+    Unit(String),
+    Building(String),
+    /// The production is turned into gold every turn, see [`PRODUCTION_PER_GOLD`].
+    Gold,
+}
+
+impl CityProduction {
+    /// The production needed to finish it, `None` for gold which is never finished.
+    pub fn cost(&self, ruleset: &Ruleset) -> Option<f32> {
+        match self {
+            CityProduction::Unit(unit_name) => Some(ruleset.units[unit_name].cost as f32),
+            CityProduction::Building(building) => Some(ruleset.buildings[building].cost as f32),
+            CityProduction::Gold => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            CityProduction::Unit(name) | CityProduction::Building(name) => name,
+            CityProduction::Gold => "Gold",
+        }
+    }
+}
+
+impl City {
+    /// The food needed to grow from the current population, using the Civ V formula.
+    pub fn food_needed_to_grow(&self) -> f32 {
+        let population = self.population as f32;
+        (15. + 6. * (population - 1.) + (population - 1.).powf(1.8)).floor()
+    }
+
+    /// The culture needed to add a tile to the borders, using the Civ V formula `20 + (10 * n)^1.1`
+    /// where `n` is the number of tiles the city already added.
+    pub fn border_growth_cost(&self) -> f32 {
+        let tiles_added = self.owned_tiles.len().saturating_sub(INITIAL_OWNED_TILES) as f32;
+        20. + (10. * tiles_added).powf(1.1)
+    }
+
+    pub fn food_consumption(&self) -> f32 {
+        self.population as f32 * FOOD_PER_CITIZEN
+    }
+
+    /// The city tile followed by the tiles worked by citizens.
+    pub fn all_worked_tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        std::iter::once(self.tile).chain(self.worked_tiles.iter().copied())
+    }
+
+    /// The total yields of the worked tiles.
+    pub fn yields(&self, tile_yields: &TileYields) -> Yields {
+        self.all_worked_tiles()
+            .map(|tile| tile_yields.get(tile))
+            .sum()
+    }
+
+    /// The yields of the worked tiles and of the specialists, plus the city yields given by the effects
+    /// of the civilization and of the buildings of the city, increased by the percentages of these effects.
+    /// The capital also gets the yields of the city-states the civilization is friends with.
+    pub fn total_yields(
+        &self,
+        tile_yields: &TileYields,
+        civilization: &Civilization,
+        ruleset: &Ruleset,
+    ) -> Yields {
+        let city_effects = CityEffects::of_buildings(&self.buildings, ruleset);
+        let mut yields = self.yields(tile_yields)
+            + self.specialist_yields(ruleset)
+            + civilization.effects.city_yields
+            + city_effects.yields;
+        if self.is_capital {
+            yields += civilization.city_state_yields;
+        }
+        yields.increased_by_percent(
+            civilization.effects.city_yield_percent + city_effects.yield_percent,
+        )
+    }
+
+    /// The science of the city plus 1 science per citizen, as in Civ V.
+    pub fn science(
+        &self,
+        tile_yields: &TileYields,
+        civilization: &Civilization,
+        ruleset: &Ruleset,
+    ) -> f32 {
+        self.total_yields(tile_yields, civilization, ruleset)
+            .science
+            + self.population as f32
+    }
+
+    /// The culture of the city plus 1 culture from the city center.
+    pub fn culture(
+        &self,
+        tile_yields: &TileYields,
+        civilization: &Civilization,
+        ruleset: &Ruleset,
+    ) -> f32 {
+        self.total_yields(tile_yields, civilization, ruleset)
+            .culture
+            + 1.
+    }
+
+    /// The food produced by the city minus the food eaten by the citizens.
+    pub fn food_surplus(
+        &self,
+        tile_yields: &TileYields,
+        civilization: &Civilization,
+        ruleset: &Ruleset,
+    ) -> f32 {
+        self.total_yields(tile_yields, civilization, ruleset).food - self.food_consumption()
+    }
+
+    /// Return `true` if a citizen of the city can work `tile`. Blockaded tiles can't be worked.
+    pub fn can_work_tile(&self, tile: Tile, grid: HexGrid) -> bool {
+        tile != self.tile
+            && self.owned_tiles.contains(&tile)
+            && !self.blockaded_tiles.contains(&tile)
+            && self.tile.distance_to(tile, grid) <= CITY_WORK_RANGE
+    }
+
+    /// Return `true` if the city is next to a water tile, so it can build ships.
+    pub fn is_coastal(&self, tile_map: &TileMap) -> bool {
+        self.tile
+            .neighbor_tiles(tile_map.world_grid.grid)
+            .any(|neighbor| neighbor.is_water(tile_map))
+    }
+
+    /// The health points of the city when it is not damaged, with the bonuses of its buildings such as walls.
+    pub fn max_health(&self, ruleset: &Ruleset) -> u32 {
+        let building_health: i32 = self
+            .buildings
+            .iter()
+            .map(|building| ruleset.buildings[building].city_health)
+            .sum();
+        BASE_CITY_HEALTH.saturating_add_signed(building_health)
+    }
+
+    /// The combat strength of the city: 8, plus 0.4 per citizen, plus the strength of its buildings such as walls.
+    ///
+    /// Like units, damaged cities fight weaker.
+    pub fn combat_strength(&self, ruleset: &Ruleset) -> f32 {
+        let building_strength: i32 = self
+            .buildings
+            .iter()
+            .map(|building| ruleset.buildings[building].city_strength)
+            .sum();
+        let max_health = self.max_health(ruleset);
+        let wounded_modifier = 1. - self.damage.min(max_health) as f32 / max_health as f32 / 2.;
+        (BASE_CITY_STRENGTH
+            + self.population as f32 * CITY_STRENGTH_PER_CITIZEN
+            + building_strength as f32)
+            * wounded_modifier
+    }
+
+    /// Let a citizen work `tile` until the player unlocks it.
+    ///
+    /// If every citizen working tiles already works a locked tile, the last specialist works `tile` instead,
+    /// or else the tile locked first is unlocked.
+    /// Return `false` if the tile can't be worked by the city.
+    pub fn lock_tile(&mut self, tile: Tile, grid: HexGrid, tile_yields: &TileYields) -> bool {
+        if !self.can_work_tile(tile, grid) {
+            return false;
+        }
+        if !self.locked_tiles.contains(&tile) {
+            let tile_workers = (self.population as usize).saturating_sub(self.specialists.len());
+            if self.locked_tiles.len() >= tile_workers
+                && self.specialists.pop().is_none()
+                && !self.locked_tiles.is_empty()
+            {
+                self.locked_tiles.remove(0);
+            }
+            self.locked_tiles.push(tile);
+        }
+        self.assign_citizens(grid, tile_yields);
+        true
+    }
+
+    /// Let the city choose again which tile the citizen working `tile` works.
+    pub fn unlock_tile(&mut self, tile: Tile, grid: HexGrid, tile_yields: &TileYields) {
+        self.locked_tiles.retain(|&locked_tile| locked_tile != tile);
+        self.assign_citizens(grid, tile_yields);
+    }
+
+    /// Let every citizen which is not a specialist work one of the tiles the city can work.
+    ///
+    /// Locked tiles are worked first. The other citizens work the tiles with the most food,
+    /// and then with the most production. When the city shrinks, the last specialists work tiles again.
+    pub fn assign_citizens(&mut self, grid: HexGrid, tile_yields: &TileYields) {
+        self.specialists.truncate(self.population as usize);
+        let population = self.population as usize - self.specialists.len();
+
+        let mut locked_tiles = std::mem::take(&mut self.locked_tiles);
+        locked_tiles.retain(|&tile| self.can_work_tile(tile, grid));
+        locked_tiles.truncate(population);
+        self.locked_tiles = locked_tiles;
+
+        let mut candidate_tiles: Vec<_> = self
+            .owned_tiles
+            .iter()
+            .copied()
+            .filter(|&tile| self.can_work_tile(tile, grid) && !self.locked_tiles.contains(&tile))
+            .collect();
+        candidate_tiles.sort_by(|&a, &b| {
+            let (a, b) = (tile_yields.get(a), tile_yields.get(b));
+            b.food
+                .total_cmp(&a.food)
+                .then(b.production.total_cmp(&a.production))
+        });
+        candidate_tiles.truncate(population - self.locked_tiles.len());
+
+        self.worked_tiles = self.locked_tiles.clone();
+        self.worked_tiles.extend(candidate_tiles);
+    }
+}
+
+/// Return `true` if `nation` may build or buy the unit: it has the required technology, the unit is not obsolete,
+/// and the unit is either unique to `nation` or a generic unit not replaced by one of its unique units.
+pub fn can_build_unit(
+    unit_name: &str,
+    nation: Nation,
+    civilization: &Civilization,
+    ruleset: &Ruleset,
+) -> bool {
+    let unit = &ruleset.units[unit_name];
+    if !civilization.has_technology(&unit.required_tech)
+        || civilization.obsolete_units.contains(unit_name)
+        || (!unit.obsolete_tech.is_empty() && civilization.has_technology(&unit.obsolete_tech))
+    {
+        return false;
+    }
+    if !unit.unique_to.is_empty() {
+        return unit.unique_to == nation.as_str();
+    }
+    !ruleset
+        .units
+        .values()
+        .any(|other| other.unique_to == nation.as_str() && other.replaces == unit_name)
+}
+
+/// Return `true` if the city may build the building: its nation has the required technology, the city has
+/// the required building and not the building itself, and the building is either unique to the nation or a generic
+/// building not replaced by one of its unique buildings. Wonders must still be available, see [`Wonders::allows`],
+/// and the city must be on the terrain the building needs, see [`placement_allows`].
+pub fn can_build_building(
+    building_name: &str,
+    city: &City,
+    nation: Nation,
+    civilization: &Civilization,
+    wonders: &Wonders,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+) -> bool {
+    let building = &ruleset.buildings[building_name];
+    if !wonders.allows(building_name, nation, ruleset)
+        || !placement_allows(building_name, city.tile, tile_map, ruleset)
+        || !civilization.has_technology(&building.required_tech)
+        || city.buildings.iter().any(|built| built == building_name)
+        || (!building.required_building.is_empty()
+            && !city.buildings.contains(&building.required_building))
+    {
+        return false;
+    }
+    if !building.unique_to.is_empty() {
+        return building.unique_to == nation.as_str();
+    }
+    !ruleset
+        .buildings
+        .values()
+        .any(|other| other.unique_to == nation.as_str() && other.replaces == building_name)
+}
+
+/// The gold needed to buy a unit, using the Civ V formula `(30 * cost)^0.75`
+/// modified by the `hurryCostModifier` of the unit and rounded down to a multiple of 10.
+pub fn unit_purchase_cost(unit_name: &str, ruleset: &Ruleset) -> f32 {
+    let unit = &ruleset.units[unit_name];
+    let cost = (30. * unit.cost as f32).powf(0.75) * (1. + unit.hurry_cost_modifier as f32 / 100.);
+    (cost / 10.).floor() * 10.
+}
+
+/// Request to change what a city produces. The production already accumulated is kept.
+#[derive(Message)]
+pub struct ChangeProduction {
+    pub city: Entity,
+    pub production: Option<CityProduction>,
+}
+
+/// Request to let a citizen of the city work `tile` until the player unlocks it, or to unlock it,
+/// see [`City::lock_tile`].
+#[derive(Message)]
+pub struct LockTile {
+    pub city: Entity,
+    pub tile: Tile,
+    pub locked: bool,
+}
+
+/// Request to buy a unit in a city with gold.
+#[derive(Message)]
+pub struct PurchaseUnit {
+    pub city: Entity,
+    pub unit_name: String,
+}
+
+/// Request to annex a puppet city, so its owner chooses its production.
+#[derive(Message)]
+pub struct AnnexCity {
+    pub city: Entity,
+}
+
+/// Written when a melee unit of `nation` takes a city from `previous_nation`.
+/// The city becomes a puppet of `nation`, see [`City::is_puppet`].
+#[derive(Message)]
+pub struct CityCaptured {
+    pub city: Entity,
+    pub nation: Nation,
+    pub previous_nation: Nation,
+    pub name: String,
+    pub tile: Tile,
+}
+
+/// Request to found a city with a settler on the tile it stands on.
+#[derive(Message)]
+pub struct FoundCity {
+    pub settler: Entity,
+}
+
+/// Written when the borders of a city grow to a new tile.
+#[derive(Message)]
+pub struct BordersExpanded {
+    pub city: Entity,
+    pub nation: Nation,
+    pub tile: Tile,
+}
+
+pub struct CityPlugin;
+
+impl Plugin for CityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<FoundCity>()
+            .add_message::<ChangeProduction>()
+            .add_message::<LockTile>()
+            .add_message::<PurchaseUnit>()
+            .add_message::<AnnexCity>()
+            .add_message::<CityCaptured>()
+            .add_message::<BordersExpanded>()
+            .add_systems(
+                Update,
+                (
+                    found_city_on_key,
+                    found_city,
+                    change_production,
+                    lock_tiles,
+                    purchase_units,
+                    annex_cities,
+                    capture_cities,
+                    reassign_citizens_on_tile_change
+                        .after(update_changed_tile_yields)
+                        .run_if(on_message::<TileChanged>),
+                    recolor_cities.run_if(resource_changed::<AccessibilitySettings>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart).and(resource_exists::<TileYields>)),
+            )
+            .add_systems(
+                TurnProcessing,
+                (
+                    heal_cities,
+                    grow_cities,
+                    choose_puppet_production,
+                    produce_in_cities,
+                    expand_borders,
+                )
+                    .chain()
+                    .in_set(TurnSet::Cities),
+            )
+            .add_observer(show_city_tooltip)
+            .add_observer(hide_city_tooltip);
+    }
+}
+
+fn found_city_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    selected_unit: Res<SelectedUnit>,
+    query_unit: Query<(&Unit, &MapUnit)>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if key_bindings.just_pressed(InputAction::FoundCity, &keyboard_input)
+        && let Some(settler) = selected_unit.0
+        && let Ok((unit, map_unit)) = query_unit.get(settler)
+    {
+        player_command.write(PlayerCommand::FoundCity {
+            settler: UnitId::new(unit, map_unit),
+        });
+    }
+}
+
+/// Return `true` if a city can be founded on `tile`: a land tile at least [`MIN_CITY_DISTANCE`] away from every city in `city_tiles`.
+pub fn is_city_site(
+    tile: Tile,
+    tile_map: &TileMap,
+    mut city_tiles: impl Iterator<Item = Tile>,
+) -> bool {
+    let grid = tile_map.world_grid.grid;
+    !tile.is_water(tile_map)
+        && city_tiles.all(|city_tile| city_tile.distance_to(tile, grid) >= MIN_CITY_DISTANCE)
+}
+
+/// Return `true` if the unit has the unique `Founds a new city`.
+pub fn can_found_city(unit_name: &str, ruleset: &Ruleset) -> bool {
+    ruleset.units[unit_name]
+        .uniques
+        .iter()
+        .any(|unique| unique == "Founds a new city")
+}
+
+fn found_city(
+    mut commands: Commands,
+    mut found_city: MessageReader<FoundCity>,
+    mut city_founded: MessageWriter<CityFounded>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    tile_yields: Res<TileYields>,
+    tile_entities: Res<WorldTileEntities>,
+    query_settler: Query<(&Unit, &MapUnit, &Owner)>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+
+    for &FoundCity { settler } in found_city.read() {
+        let Ok((unit, map_unit, &owner)) = query_settler.get(settler) else {
+            continue;
+        };
+
+        let tile = map_unit.tile;
+
+        if !can_found_city(unit.name(), ruleset)
+            || !is_city_site(tile, tile_map, query_city.iter().map(|(city, _)| city.tile))
+        {
+            continue;
+        }
+
+        let nation = owner.nation();
+        let city_names = &ruleset.nations[nation.as_str()].cities;
+        let city_count = query_city
+            .iter()
+            .filter(|(_, city_owner)| city_owner.nation() == nation)
+            .count();
+        let name = city_names
+            .get(city_count)
+            .cloned()
+            .unwrap_or_else(|| format!("{} {}", nation.as_str(), city_count + 1));
+
+        let owned_tiles: Vec<_> = tile
+            .tiles_in_distance(1, grid)
+            .filter(|&owned_tile| {
+                !query_city
+                    .iter()
+                    .any(|(city, _)| city.owned_tiles.contains(&owned_tile))
+            })
+            .collect();
+
+        let mut city = City {
+            name: name.clone(),
+            tile,
+            population: 1,
+            food_stored: 0.,
+            owned_tiles,
+            worked_tiles: Vec::new(),
+            locked_tiles: Vec::new(),
+            specialists: Vec::new(),
+            production: None,
+            production_stored: 0.,
+            buildings: Vec::new(),
+            is_capital: city_count == 0,
+            border_culture: 0.,
+            damage: 0,
+            has_struck: false,
+            blockaded_tiles: Vec::new(),
+            is_puppet: false,
+        };
+        city.assign_citizens(grid, &tile_yields);
+
+        let city_entity = commands
+            .spawn((
+                city_bundle(city, owner, grid, ruleset, &accessibility_settings),
+                ChildOf(tile_entities.0[&tile]),
+            ))
+            .id();
+        city_founded.write(CityFounded {
+            city: city_entity,
+            nation,
+            name,
+            tile,
+        });
+
+        commands.entity(settler).despawn();
+    }
+}
+
+/// The city with its sprite, to spawn as a child of the tile entity.
+pub fn city_bundle(
+    city: City,
+    owner: Owner,
+    grid: HexGrid,
+    ruleset: &Ruleset,
+    accessibility_settings: &AccessibilitySettings,
+) -> impl Bundle {
+    let color = accessibility_settings
+        .player_colors(owner.nation(), ruleset)
+        .outer();
+    let city_size = grid.layout.size[0];
+    (
+        city,
+        owner,
+        SightRange::default(),
+        Sprite::from_color(color, Vec2::splat(city_size)),
+        Transform::from_xyz(0., 0., 4.),
+        Pickable::default(),
+    )
+}
+
+/// Redraw the cities in the colors of the palette chosen in the accessibility settings.
+fn recolor_cities(
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    mut query_city: Query<(&Owner, &mut Sprite), With<City>>,
+) {
+    if accessibility_settings.is_added() {
+        return;
+    }
+    for (owner, mut sprite) in query_city.iter_mut() {
+        sprite.color = accessibility_settings
+            .player_colors(owner.nation(), &ruleset.0)
+            .outer();
+    }
+}
+
+/// Heal the damaged cities by [`CITY_HEALING_PER_TURN`].
+fn heal_cities(mut query_city: Query<&mut City>) {
+    for mut city in query_city.iter_mut() {
+        if city.damage > 0 {
+            city.damage = city.damage.saturating_sub(CITY_HEALING_PER_TURN);
+        }
+    }
+}
+
+/// Grow or starve cities with their food surplus.
+///
+/// Unhappy civilizations keep only a part of the food surplus of their cities, see [`crate::happiness::HappinessLevel::growth_modifier`].
+pub fn grow_cities(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    civilizations: Res<Civilizations>,
+    mut query_city: Query<(&mut City, &Owner)>,
+) {
+    let grid = map.0.world_grid.grid;
+    let ruleset = &ruleset.0;
+
+    for (mut city, owner) in query_city.iter_mut() {
+        let civilization = civilizations.get(owner.nation());
+        let mut food_surplus = city.food_surplus(&tile_yields, civilization, ruleset);
+        if food_surplus > 0. {
+            food_surplus *= civilization.happiness_level().growth_modifier();
+        }
+        city.food_stored += food_surplus;
+
+        let food_needed = city.food_needed_to_grow();
+        if city.food_stored >= food_needed {
+            city.food_stored -= food_needed;
+            city.population += 1;
+            city.assign_citizens(grid, &tile_yields);
+        } else if city.food_stored < 0. {
+            // Starvation: the city loses a citizen, but a city never drops below 1 population.
+            city.food_stored = 0.;
+            if city.population > 1 {
+                city.population -= 1;
+                city.assign_citizens(grid, &tile_yields);
+            }
+        }
+    }
+}
+
+/// Return `true` if the city can produce the unit: ships are only built in coastal cities.
+pub fn can_produce_unit_in(
+    city: &City,
+    unit_name: &str,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+) -> bool {
+    UnitDomain::of_unit(unit_name, ruleset) != UnitDomain::Water || city.is_coastal(tile_map)
+}
+
+fn change_production(
+    mut change_production: MessageReader<ChangeProduction>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    mut query_city: Query<(&mut City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let wonders = Wonders::of_cities(
+        query_city
+            .iter()
+            .map(|(city, owner)| (city, owner.nation())),
+        ruleset,
+    );
+
+    for ChangeProduction { city, production } in change_production.read() {
+        let Ok((mut city, owner)) = query_city.get_mut(*city) else {
+            continue;
+        };
+        // Puppets choose their production themselves, see `choose_puppet_production`.
+        if city.is_puppet {
+            continue;
+        }
+
+        let nation = owner.nation();
+        let civilization = civilizations.get(nation);
+        let can_produce = match production {
+            Some(CityProduction::Unit(unit_name)) => {
+                can_build_unit(unit_name, nation, civilization, ruleset)
+                    && can_produce_unit_in(&city, unit_name, tile_map, ruleset)
+            }
+            Some(CityProduction::Building(building)) => can_build_building(
+                building,
+                &city,
+                nation,
+                civilization,
+                &wonders,
+                tile_map,
+                ruleset,
+            ),
+            Some(CityProduction::Gold) | None => true,
+        };
+        if can_produce {
+            city.production = production.clone();
+        }
+    }
+}
+
+fn lock_tiles(
+    mut lock_tile: MessageReader<LockTile>,
+    map: Res<TileMapResource>,
+    tile_yields: Res<TileYields>,
+    mut query_city: Query<&mut City>,
+) {
+    let grid = map.0.world_grid.grid;
+
+    for &LockTile { city, tile, locked } in lock_tile.read() {
+        let Ok(mut city) = query_city.get_mut(city) else {
+            continue;
+        };
+        if locked {
+            city.lock_tile(tile, grid, &tile_yields);
+        } else {
+            city.unlock_tile(tile, grid, &tile_yields);
+        }
+    }
+}
+
+fn purchase_units(
+    mut purchase_unit: MessageReader<PurchaseUnit>,
+    mut spawn_unit: MessageWriter<SpawnUnit>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+    query_unit: Query<(&MapUnit, &Unit)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let mut taken_tiles = taken_tiles(&query_unit);
+
+    for PurchaseUnit { city, unit_name } in purchase_unit.read() {
+        let Ok((city, &owner)) = query_city.get(*city) else {
+            continue;
+        };
+
+        let civilization = civilizations.get_mut(owner.nation());
+        let gold_cost = unit_purchase_cost(unit_name, ruleset);
+        if city.is_puppet
+            || !can_build_unit(unit_name, owner.nation(), civilization, ruleset)
+            || !can_produce_unit_in(city, unit_name, tile_map, ruleset)
+            || civilization.gold < gold_cost
+        {
+            continue;
+        }
+
+        let unit = unit_kind(unit_name, ruleset);
+        let Some(tile) = find_spawn_tile(
+            city.tile,
+            &unit,
+            |tile, is_military| taken_tiles.contains(&(tile, is_military)),
+            tile_map,
+            ruleset,
+        ) else {
+            continue;
+        };
+
+        civilization.gold -= gold_cost;
+        taken_tiles.insert((tile, matches!(unit, Unit::Military(_))));
+        spawn_unit.write(SpawnUnit {
+            unit_name: unit_name.clone(),
+            owner,
+            tile,
+        });
+    }
+}
+
+/// Add the production of every city, and spawn the units and add the buildings which are finished.
+/// Cities producing gold give it to their civilization at once.
+///
+/// Very unhappy civilizations lose a part of the production, see [`crate::happiness::HappinessLevel::production_modifier`],
+/// and civilizations in a golden age get more, see [`crate::golden_age::GoldenAgeProgress::production_modifier`].
+/// Civilizations with more units than their supply lose a part too, see [`crate::economy::UnitSupply`].
+/// A finished unit waits in the city while there is no tile to place it, see [`find_spawn_tile`].
+pub fn produce_in_cities(
+    mut spawn_unit: MessageWriter<SpawnUnit>,
+    mut wonder_built: MessageWriter<WonderBuilt>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    mut civilizations: ResMut<Civilizations>,
+    mut query_city: Query<(&mut City, &Owner)>,
+    query_unit: Query<(&MapUnit, &Unit)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let mut taken_tiles = taken_tiles(&query_unit);
+    let mut wonders = Wonders::of_cities(
+        query_city
+            .iter()
+            .map(|(city, owner)| (city, owner.nation())),
+        ruleset,
+    );
+
+    // Cities completing the same world wonder on the same turn are served in a fixed order,
+    // the others lose the race, see `crate::wonder`.
+    let mut cities: Vec<_> = query_city.iter_mut().collect();
+    cities.sort_by_key(|(city, _)| city.tile.index());
+    for (mut city, &owner) in cities {
+        let civilization = civilizations.get_mut(owner.nation());
+        let yields = city.total_yields(&tile_yields, civilization, ruleset);
+        let production = yields.production
+            * civilization.happiness_level().production_modifier()
+            * civilization.golden_age.production_modifier()
+            * civilization.unit_supply.production_modifier();
+
+        match city.production.clone() {
+            Some(CityProduction::Gold) => {
+                civilization.gold += production / PRODUCTION_PER_GOLD;
+            }
+            Some(CityProduction::Building(building)) => {
+                city.production_stored += production;
+                let info = &ruleset.buildings[&building];
+                let cost = info.cost as f32;
+                if city.production_stored < cost
+                    || !wonders.allows(&building, owner.nation(), ruleset)
+                {
+                    continue;
+                }
+
+                city.production_stored -= cost;
+                city.production = None;
+                if info.is_wonder || info.is_national_wonder {
+                    wonders.insert(&building, owner.nation(), ruleset);
+                    wonder_built.write(WonderBuilt {
+                        nation: owner.nation(),
+                        wonder: building.clone(),
+                        city_name: city.name.clone(),
+                        tile: city.tile,
+                        is_world_wonder: info.is_wonder,
+                    });
+                }
+                city.buildings.push(building);
+            }
+            Some(CityProduction::Unit(unit_name)) => {
+                city.production_stored += production;
+                let cost = ruleset.units[&unit_name].cost as f32;
+                if city.production_stored < cost {
+                    continue;
+                }
+
+                let unit = unit_kind(&unit_name, ruleset);
+                let Some(tile) = find_spawn_tile(
+                    city.tile,
+                    &unit,
+                    |tile, is_military| taken_tiles.contains(&(tile, is_military)),
+                    tile_map,
+                    ruleset,
+                ) else {
+                    continue;
+                };
+
+                city.production_stored -= cost;
+                city.production = None;
+                taken_tiles.insert((tile, matches!(unit, Unit::Military(_))));
+                spawn_unit.write(SpawnUnit {
+                    unit_name,
+                    owner,
+                    tile,
+                });
+            }
+            None => city.production_stored += production,
+        }
+    }
+}
+
+/// Let every puppet city without production build the cheapest building it can, or produce gold if there is none.
+/// Puppets never build wonders.
+fn choose_puppet_production(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    mut query_city: Query<(&mut City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let wonders = Wonders::of_cities(
+        query_city
+            .iter()
+            .map(|(city, owner)| (city, owner.nation())),
+        ruleset,
+    );
+
+    for (mut city, owner) in query_city.iter_mut() {
+        if !city.is_puppet || matches!(city.production, Some(CityProduction::Building(_))) {
+            continue;
+        }
+
+        let nation = owner.nation();
+        let building = ruleset
+            .buildings
+            .values()
+            .filter(|building| {
+                !building.is_wonder
+                    && !building.is_national_wonder
+                    && can_build_building(
+                        &building.name,
+                        &city,
+                        nation,
+                        civilizations.get(nation),
+                        &wonders,
+                        tile_map,
+                        ruleset,
+                    )
+            })
+            .min_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.name.cmp(&b.name)));
+        city.production = Some(match building {
+            Some(building) => CityProduction::Building(building.name.clone()),
+            None => CityProduction::Gold,
+        });
+    }
+}
+
+/// Annex the puppet cities requested by [`AnnexCity`] messages.
+fn annex_cities(mut annex_city: MessageReader<AnnexCity>, mut query_city: Query<&mut City>) {
+    for AnnexCity { city } in annex_city.read() {
+        if let Ok(mut city) = query_city.get_mut(*city) {
+            city.is_puppet = false;
+        }
+    }
+}
+
+/// Give the cities of the [`CityCaptured`] messages to the nation which took them, as puppets.
+///
+/// The city loses its production, is no longer a capital, and is brought back to half of its health.
+/// When it was the capital of its previous owner, the largest city left to that owner becomes its capital.
+fn capture_cities(
+    mut city_captured: MessageReader<CityCaptured>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    tile_yields: Res<TileYields>,
+    mut query_city: Query<(&mut City, &mut Owner, &mut Sprite)>,
+) {
+    let grid = map.0.world_grid.grid;
+    let ruleset = &ruleset.0;
+
+    for &CityCaptured {
+        city: entity,
+        nation,
+        previous_nation,
+        ..
+    } in city_captured.read()
+    {
+        let Ok((mut city, mut owner, mut sprite)) = query_city.get_mut(entity) else {
+            continue;
+        };
+        let was_capital = city.is_capital;
+
+        *owner = Owner::Civilization(nation);
+        sprite.color = accessibility_settings
+            .player_colors(nation, ruleset)
+            .outer();
+        city.production = None;
+        city.production_stored = 0.;
+        city.is_capital = false;
+        city.is_puppet = true;
+        city.locked_tiles.clear();
+        city.damage = city.max_health(ruleset) / 2;
+        city.assign_citizens(grid, &tile_yields);
+
+        if was_capital {
+            let new_capital = query_city
+                .iter_mut()
+                .filter(|(_, owner, _)| owner.nation() == previous_nation)
+                .max_by(|(a, ..), (b, ..)| {
+                    a.population
+                        .cmp(&b.population)
+                        .then_with(|| b.tile.index().cmp(&a.tile.index()))
+                });
+            if let Some((mut new_capital, ..)) = new_capital {
+                new_capital.is_capital = true;
+            }
+        }
+    }
+}
+
+/// Add the culture of every city toward its borders, and add the best free tile next to the borders when there is enough.
+///
+/// The borders only grow within [`CITY_WORK_RANGE`], so every owned tile can be worked.
+fn expand_borders(
+    mut borders_expanded: MessageWriter<BordersExpanded>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    civilizations: Res<Civilizations>,
+    mut query_city: Query<(Entity, &mut City, &Owner)>,
+) {
+    let grid = map.0.world_grid.grid;
+    let ruleset = &ruleset.0;
+    let mut all_owned_tiles: HashSet<Tile> = query_city
+        .iter()
+        .flat_map(|(_, city, _)| city.owned_tiles.iter().copied())
+        .collect();
+
+    for (entity, mut city, owner) in query_city.iter_mut() {
+        let civilization = civilizations.get(owner.nation());
+        city.border_culture += city.culture(&tile_yields, civilization, ruleset);
+        let cost = city.border_growth_cost();
+        if city.border_culture < cost {
+            continue;
+        }
+
+        let new_tile = city
+            .tile
+            .tiles_in_distance(CITY_WORK_RANGE, grid)
+            .filter(|tile| {
+                !all_owned_tiles.contains(tile)
+                    && tile
+                        .neighbor_tiles(grid)
+                        .any(|neighbor| city.owned_tiles.contains(&neighbor))
+            })
+            .max_by(|&a, &b| {
+                let score = |tile: Tile| {
+                    let yields = tile_yields.get(tile);
+                    yields.food + yields.production + yields.gold
+                };
+                score(a)
+                    .total_cmp(&score(b))
+                    .then_with(|| {
+                        city.tile
+                            .distance_to(b, grid)
+                            .cmp(&city.tile.distance_to(a, grid))
+                    })
+                    .then_with(|| b.index().cmp(&a.index()))
+            });
+        let Some(new_tile) = new_tile else {
+            continue;
+        };
+
+        city.border_culture -= cost;
+        city.owned_tiles.push(new_tile);
+        all_owned_tiles.insert(new_tile);
+        city.assign_citizens(grid, &tile_yields);
+        borders_expanded.write(BordersExpanded {
+            city: entity,
+            nation: owner.nation(),
+            tile: new_tile,
+        });
+    }
+}
+
+/// Assign citizens again in the cities owning a tile whose yields changed.
+fn reassign_citizens_on_tile_change(
+    mut tile_changed: MessageReader<TileChanged>,
+    map: Res<TileMapResource>,
+    tile_yields: Res<TileYields>,
+    mut query_city: Query<&mut City>,
+) {
+    let grid = map.0.world_grid.grid;
+    let changed_tiles: Vec<_> = tile_changed.read().map(|&TileChanged(tile)| tile).collect();
+
+    for mut city in query_city.iter_mut() {
+        if changed_tiles
+            .iter()
+            .any(|tile| city.owned_tiles.contains(tile))
+        {
+            city.assign_citizens(grid, &tile_yields);
+        }
+    }
+}
+
+/// The tooltip shown while the cursor hovers a city.
+#[derive(Component)]
+struct CityTooltip;
+
+fn show_city_tooltip(
+    over: On<Pointer<Over>>,
+    mut commands: Commands,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    civilizations: Res<Civilizations>,
+    city_connections: Res<CityConnections>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let Ok((city, owner)) = query_city.get(over.entity) else {
+        return;
+    };
+
+    let food_surplus =
+        city.food_surplus(&tile_yields, civilizations.get(owner.nation()), &ruleset.0);
+    let growth = if food_surplus > 0. {
+        let food_left = city.food_needed_to_grow() - city.food_stored;
+        format!("Grows in {} turns", (food_left / food_surplus).ceil())
+    } else if food_surplus < 0. {
+        "Starving!".to_owned()
+    } else {
+        "Stagnant".to_owned()
+    };
+
+    let production = match &city.production {
+        Some(production) => match production.cost(&ruleset.0) {
+            Some(cost) => format!(
+                "Producing: {} ({}/{})",
+                production.name(),
+                city.production_stored,
+                cost
+            ),
+            None => format!("Producing: {}", production.name()),
+        },
+        None => "Producing: nothing".to_owned(),
+    };
+    let puppet = if city.is_puppet { " (puppet)" } else { "" };
+    let connection = if city.is_capital {
+        "Capital"
+    } else if city_connections.is_connected(city.tile) {
+        "Connected to the capital"
+    } else {
+        "Not connected to the capital"
+    };
+
+    let pointer_position = over.pointer_location.position;
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(pointer_position.x + 16.),
+            top: Val::Px(pointer_position.y + 16.),
+            padding: UiRect::all(Val::Px(6.0)),
+            border: UiRect::all(Val::Px(1.0)),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.8)),
+        BorderColor::all(Color::WHITE),
+        Pickable::IGNORE,
+        Text(format!(
+            "{}{}\n{}\nPopulation: {}\nFood: {}/{} ({:+})\n{}\nProduction: {}\n{}",
+            city.name,
+            puppet,
+            connection,
+            city.population,
+            city.food_stored,
+            city.food_needed_to_grow(),
+            food_surplus,
+            growth,
+            city.yields(&tile_yields).production,
+            production
+        )),
+        TextFont {
+            font_size: 14.,
+            ..default()
+        },
+        CityTooltip,
+    ));
+}
+
+fn hide_city_tooltip(
+    out: On<Pointer<Out>>,
+    mut commands: Commands,
+    query_city: Query<(), With<City>>,
+    query_tooltip: Query<Entity, With<CityTooltip>>,
+) {
+    if !query_city.contains(out.entity) {
+        return;
+    }
+
+    for tooltip in query_tooltip.iter() {
+        commands.entity(tooltip).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathfinding::tests::{test_map, tile};
+
+    #[test]
+    fn locks_no_more_tiles_than_citizens_working_tiles() {
+        let tile_map = test_map(&["...", "...", "..."]);
+        let grid = tile_map.world_grid.grid;
+        let tile_yields: TileYields = tile_map.all_tiles().map(|_| Yields::default()).collect();
+        let city_tile = tile(&tile_map, 1, 1);
+        let mut city = City {
+            name: "Test".to_owned(),
+            tile: city_tile,
+            population: 2,
+            food_stored: 0.,
+            owned_tiles: tile_map.all_tiles().collect(),
+            worked_tiles: Vec::new(),
+            locked_tiles: Vec::new(),
+            specialists: vec!["Scientist".to_owned()],
+            production: None,
+            production_stored: 0.,
+            buildings: Vec::new(),
+            is_capital: false,
+            border_culture: 0.,
+            damage: 0,
+            has_struck: false,
+            blockaded_tiles: Vec::new(),
+            is_puppet: false,
+        };
+        city.assign_citizens(grid, &tile_yields);
+        let [first, second, third] = [(0, 1), (2, 1), (1, 0)].map(|(x, y)| tile(&tile_map, x, y));
+
+        assert!(city.lock_tile(first, grid, &tile_yields));
+        assert_eq!(city.locked_tiles, [first]);
+        assert_eq!(city.specialists.len(), 1);
+
+        // The specialist goes back to work the second locked tile.
+        assert!(city.lock_tile(second, grid, &tile_yields));
+        assert_eq!(city.locked_tiles, [first, second]);
+        assert!(city.specialists.is_empty());
+        assert_eq!(city.worked_tiles, [first, second]);
+
+        // Without specialists left, the tile locked first is unlocked.
+        assert!(city.lock_tile(third, grid, &tile_yields));
+        assert_eq!(city.locked_tiles, [second, third]);
+        assert_eq!(city.worked_tiles, [second, third]);
+    }
+}
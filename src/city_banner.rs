@@ -0,0 +1,329 @@
+//! The banners above the cities: the name and the population of the city, what it produces and how close it is
+//! to growing and to finishing its production. Clicking the banner of a city of the player opens its city screen.
+//!
+//! Banners are UI nodes following the world position of their city, scaled with the zoom of the main camera.
+
+use bevy::{
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+
+use crate::{
+    MainCamera, RulesetResource, TileMapResource,
+    accessibility::AccessibilitySettings,
+    assets::{AppState, MaterialResource},
+    city::{City, CityCaptured},
+    city_screen::OpenCityScreen,
+    civilization::PlayerCivilization,
+    unit_component::Owner,
+    visibility::VisibilityLayer,
+    world_map::{WorldTile, show_main_camera_area},
+};
+
+const BANNER_WIDTH: f32 = 140.;
+const BANNER_HEIGHT: f32 = 34.;
+const PRODUCTION_ICON_SIZE: f32 = 20.;
+/// The banner can't shrink or grow beyond these scales, so it stays readable at every zoom.
+const MIN_BANNER_SCALE: f32 = 0.6;
+const MAX_BANNER_SCALE: f32 = 1.5;
+
+/// The banner of a city, with the entities of its parts updated when the city changes.
+#[derive(Component)]
+pub struct CityBanner {
+    pub city: Entity,
+    name_text: Entity,
+    production_icon: Entity,
+    growth_bar: Entity,
+    production_bar: Entity,
+}
+
+pub struct CityBannerPlugin;
+
+impl Plugin for CityBannerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_city_banners,
+                despawn_city_banners,
+                update_city_banners,
+                recolor_city_banners.run_if(
+                    resource_changed::<AccessibilitySettings>.or(on_message::<CityCaptured>),
+                ),
+                position_city_banners
+                    .after(show_main_camera_area)
+                    .run_if(resource_exists::<VisibilityLayer>),
+            )
+                .chain()
+                .run_if(in_state(AppState::GameStart)),
+        );
+    }
+}
+
+/// Spawn the banner of every new city.
+fn spawn_city_banners(
+    mut commands: Commands,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    query_city: Query<(Entity, &Owner), Added<City>>,
+) {
+    for (city, owner) in query_city.iter() {
+        let player_colors = accessibility_settings.player_colors(owner.nation(), &ruleset.0);
+        let background_color = player_colors.outer();
+        let text_color = player_colors.inner();
+
+        let name_text = commands
+            .spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 12.,
+                    ..default()
+                },
+                TextColor(text_color),
+                Pickable::IGNORE,
+            ))
+            .id();
+        let production_icon = commands
+            .spawn((
+                Node {
+                    width: Val::Px(PRODUCTION_ICON_SIZE),
+                    height: Val::Px(PRODUCTION_ICON_SIZE),
+                    ..default()
+                },
+                ImageNode::default(),
+                Pickable::IGNORE,
+            ))
+            .id();
+        let (growth_track, growth_bar) = progress_bar(&mut commands, Color::srgb(0.35, 0.8, 0.25));
+        let (production_track, production_bar) =
+            progress_bar(&mut commands, Color::srgb(0.9, 0.5, 0.15));
+
+        let bars = commands
+            .spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    flex_grow: 1.,
+                    row_gap: Val::Px(2.),
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ))
+            .add_children(&[name_text, growth_track, production_track])
+            .id();
+
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(BANNER_WIDTH),
+                    height: Val::Px(BANNER_HEIGHT),
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(4.),
+                    padding: UiRect::horizontal(Val::Px(4.)),
+                    border: UiRect::all(Val::Px(1.)),
+                    ..default()
+                },
+                BackgroundColor(background_color.with_alpha(0.85)),
+                BorderColor::all(text_color),
+                UiTransform::IDENTITY,
+                // Below the other UI, e.g. the city screen opened from the banner.
+                GlobalZIndex(-1),
+                Visibility::Hidden,
+                CityBanner {
+                    city,
+                    name_text,
+                    production_icon,
+                    growth_bar,
+                    production_bar,
+                },
+            ))
+            .add_children(&[production_icon, bars])
+            .observe(open_city_screen_on_click);
+    }
+}
+
+/// A progress bar, returning its track and the bar itself, whose width is the progress.
+fn progress_bar(commands: &mut Commands, color: Color) -> (Entity, Entity) {
+    let bar = commands
+        .spawn((
+            Node {
+                width: Val::Percent(0.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            BackgroundColor(color),
+            Pickable::IGNORE,
+        ))
+        .id();
+    let track = commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Px(3.),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.6)),
+            Pickable::IGNORE,
+        ))
+        .add_child(bar)
+        .id();
+    (track, bar)
+}
+
+/// Despawn the banners of the cities which don't exist anymore, e.g. razed cities.
+fn despawn_city_banners(
+    mut commands: Commands,
+    query_banner: Query<(Entity, &CityBanner)>,
+    query_city: Query<(), With<City>>,
+) {
+    for (entity, banner) in query_banner.iter() {
+        if !query_city.contains(banner.city) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Show the name, the population and the production of the cities which changed.
+fn update_city_banners(
+    ruleset: Res<RulesetResource>,
+    materials: Res<MaterialResource>,
+    query_banner: Query<&CityBanner>,
+    query_city: Query<&City, Changed<City>>,
+    mut query_text: Query<&mut Text>,
+    mut query_icon: Query<(&mut ImageNode, &mut Visibility)>,
+    mut query_bar: Query<&mut Node>,
+) {
+    for banner in query_banner.iter() {
+        let Ok(city) = query_city.get(banner.city) else {
+            continue;
+        };
+
+        if let Ok(mut text) = query_text.get_mut(banner.name_text) {
+            text.0 = format!("{} {}", city.population, city.name);
+        }
+
+        let production_texture = city
+            .production
+            .as_ref()
+            .and_then(|production| materials.get_texture_handle(production.name()));
+        if let Ok((mut image_node, mut visibility)) = query_icon.get_mut(banner.production_icon) {
+            *visibility = match production_texture {
+                Some(texture) => {
+                    image_node.image = texture;
+                    Visibility::Inherited
+                }
+                None => Visibility::Hidden,
+            };
+        }
+
+        let growth = city.food_stored / city.food_needed_to_grow();
+        let production = city
+            .production
+            .as_ref()
+            .and_then(|production| production.cost(&ruleset.0))
+            .map_or(0., |cost| city.production_stored / cost);
+        for (bar, progress) in [
+            (banner.growth_bar, growth),
+            (banner.production_bar, production),
+        ] {
+            if let Ok(mut node) = query_bar.get_mut(bar) {
+                node.width = Val::Percent(progress.clamp(0., 1.) * 100.);
+            }
+        }
+    }
+}
+
+/// Redraw the banners in the colors of the palette chosen in the accessibility settings,
+/// or of the new owner of a captured city.
+fn recolor_city_banners(
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    query_owner: Query<&Owner, With<City>>,
+    mut query_banner: Query<(&CityBanner, &mut BackgroundColor, &mut BorderColor)>,
+    mut query_text_color: Query<&mut TextColor>,
+) {
+    if accessibility_settings.is_added() {
+        return;
+    }
+    for (banner, mut background_color, mut border_color) in query_banner.iter_mut() {
+        let Ok(owner) = query_owner.get(banner.city) else {
+            continue;
+        };
+        let player_colors = accessibility_settings.player_colors(owner.nation(), &ruleset.0);
+        background_color.0 = player_colors.outer().with_alpha(0.85);
+        *border_color = BorderColor::all(player_colors.inner());
+        if let Ok(mut text_color) = query_text_color.get_mut(banner.name_text) {
+            text_color.0 = player_colors.inner();
+        }
+    }
+}
+
+/// Anchor every banner above its city on the screen, scaled with the zoom. Banners are hidden
+/// while the tile of their city is unexplored by the player or outside of the screen.
+fn position_city_banners(
+    map: Res<TileMapResource>,
+    player_civilization: Res<PlayerCivilization>,
+    visibility_layer: Res<VisibilityLayer>,
+    camera: Single<(&Camera, &Transform, &Projection), With<MainCamera>>,
+    query_city: Query<(&City, &ChildOf)>,
+    query_tile: Query<&Transform, With<WorldTile>>,
+    mut query_banner: Query<(&CityBanner, &mut Node, &mut UiTransform, &mut Visibility)>,
+) {
+    let (camera, camera_transform, projection) = camera.into_inner();
+    // The camera may have moved this frame, after its global transform was computed.
+    let camera_transform = GlobalTransform::from(*camera_transform);
+    let zoom = match projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.,
+    };
+    let scale = (1. / zoom).clamp(MIN_BANNER_SCALE, MAX_BANNER_SCALE);
+    // Just above the city, which covers half of its tile.
+    let anchor_offset = Vec3::new(0., map.0.world_grid.grid.layout.size[1], 0.);
+
+    for (banner, mut node, mut ui_transform, mut visibility) in query_banner.iter_mut() {
+        let Ok((city, child_of)) = query_city.get(banner.city) else {
+            continue;
+        };
+        let position = query_tile
+            .get(child_of.parent())
+            .ok()
+            .and_then(|tile_transform| {
+                // The tiles are wrapped around the camera, so the tile holds the position of the city on the screen.
+                camera
+                    .world_to_viewport(
+                        &camera_transform,
+                        tile_transform.translation + anchor_offset,
+                    )
+                    .ok()
+            });
+
+        match position {
+            Some(position) if visibility_layer.is_explored(player_civilization.0, city.tile) => {
+                // The banner is scaled around its center.
+                node.left = Val::Px(position.x - BANNER_WIDTH / 2.);
+                node.top = Val::Px(position.y - BANNER_HEIGHT / 2.);
+                ui_transform.scale = Vec2::splat(scale);
+                *visibility = Visibility::Inherited;
+            }
+            _ => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+fn open_city_screen_on_click(
+    click: On<Pointer<Click>>,
+    player_civilization: Res<PlayerCivilization>,
+    query_banner: Query<&CityBanner>,
+    query_owner: Query<&Owner>,
+    mut open_city_screen: MessageWriter<OpenCityScreen>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    if let Ok(banner) = query_banner.get(click.entity)
+        && let Ok(owner) = query_owner.get(banner.city)
+        && owner.nation() == player_civilization.0
+    {
+        open_city_screen.write(OpenCityScreen { city: banner.city });
+    }
+}
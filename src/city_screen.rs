@@ -0,0 +1,337 @@
+//! The city screen: the details of a city of the player and the choice of its production.
+
+use bevy::{
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    city::{City, CityProduction, can_build_building, can_build_unit, can_produce_unit_in},
+    civilization::{Civilizations, PlayerCivilization},
+    command::PlayerCommand,
+    unit_component::Owner,
+    wonder::Wonders,
+    yields::TileYields,
+};
+
+/// Request to open the city screen of `city`, replacing the city screen already open.
+#[derive(Message)]
+pub struct OpenCityScreen {
+    pub city: Entity,
+}
+
+/// The window of the city screen, showing this city.
+#[derive(Component)]
+pub struct CityScreen(pub Entity);
+
+/// A button of the [`CityScreen`] choosing what the city produces.
+#[derive(Component)]
+struct ProductionButton(CityProduction);
+
+pub struct CityScreenPlugin;
+
+impl Plugin for CityScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<OpenCityScreen>().add_systems(
+            Update,
+            (
+                open_city_screen,
+                refresh_city_screen,
+                close_city_screen_on_escape,
+            )
+                .chain()
+                .run_if(in_state(AppState::GameStart).and(resource_exists::<TileYields>)),
+        );
+    }
+}
+
+fn open_city_screen(
+    mut commands: Commands,
+    mut open_city_screen: MessageReader<OpenCityScreen>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    civilizations: Res<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+    query_screen: Query<Entity, With<CityScreen>>,
+) {
+    let Some(&OpenCityScreen { city }) = open_city_screen.read().last() else {
+        return;
+    };
+    for screen in query_screen.iter() {
+        commands.entity(screen).despawn();
+    }
+    spawn_city_screen(
+        &mut commands,
+        city,
+        &map,
+        &ruleset,
+        &tile_yields,
+        &civilizations,
+        &query_city,
+    );
+}
+
+/// Show the changes of the city on its open screen, e.g. its new production, and close the screen
+/// once the city doesn't exist anymore or belongs to another civilization.
+fn refresh_city_screen(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    player_civilization: Res<PlayerCivilization>,
+    civilizations: Res<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+    query_changed_city: Query<(), Changed<City>>,
+    query_screen: Query<(Entity, &CityScreen)>,
+) {
+    for (screen, &CityScreen(city)) in query_screen.iter() {
+        let is_own_city = query_city
+            .get(city)
+            .is_ok_and(|(_, owner)| owner.nation() == player_civilization.0);
+        if !is_own_city {
+            commands.entity(screen).despawn();
+        } else if query_changed_city.contains(city) {
+            commands.entity(screen).despawn();
+            spawn_city_screen(
+                &mut commands,
+                city,
+                &map,
+                &ruleset,
+                &tile_yields,
+                &civilizations,
+                &query_city,
+            );
+        }
+    }
+}
+
+fn close_city_screen_on_escape(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    query_screen: Query<Entity, With<CityScreen>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        for screen in query_screen.iter() {
+            commands.entity(screen).despawn();
+        }
+    }
+}
+
+fn spawn_city_screen(
+    commands: &mut Commands,
+    entity: Entity,
+    map: &TileMapResource,
+    ruleset: &RulesetResource,
+    tile_yields: &TileYields,
+    civilizations: &Civilizations,
+    query_city: &Query<(&City, &Owner)>,
+) {
+    let Ok((city, owner)) = query_city.get(entity) else {
+        return;
+    };
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let nation = owner.nation();
+    let civilization = civilizations.get(nation);
+
+    let yields = city.total_yields(tile_yields, civilization, ruleset);
+    let food_surplus = city.food_surplus(tile_yields, civilization, ruleset);
+    let production = match &city.production {
+        Some(production) => match production.cost(ruleset) {
+            Some(cost) => format!(
+                "{} ({}/{})",
+                production.name(),
+                city.production_stored,
+                cost
+            ),
+            None => production.name().to_owned(),
+        },
+        None => "Nothing".to_owned(),
+    };
+    let buildings = if city.buildings.is_empty() {
+        "None".to_owned()
+    } else {
+        city.buildings.join(", ")
+    };
+    let details = format!(
+        "{} ({})\nFood: {}/{} ({:+})\nProduction: {} per turn\nGold: {} per turn\nScience: {} per turn\nProducing: {}\nBuildings: {}",
+        city.name,
+        city.population,
+        city.food_stored,
+        city.food_needed_to_grow(),
+        food_surplus,
+        yields.production,
+        yields.gold,
+        city.science(tile_yields, civilization, ruleset),
+        production,
+        buildings,
+    );
+
+    // Puppets choose their production themselves, see `crate::city::choose_puppet_production`.
+    let mut choices = Vec::new();
+    if !city.is_puppet {
+        let wonders = Wonders::of_cities(
+            query_city
+                .iter()
+                .map(|(city, owner)| (city, owner.nation())),
+            ruleset,
+        );
+        let mut units: Vec<_> = ruleset
+            .units
+            .keys()
+            .filter(|unit_name| {
+                can_build_unit(unit_name, nation, civilization, ruleset)
+                    && can_produce_unit_in(city, unit_name, tile_map, ruleset)
+            })
+            .collect();
+        units.sort();
+        let mut buildings: Vec<_> = ruleset
+            .buildings
+            .keys()
+            .filter(|building| {
+                can_build_building(
+                    building,
+                    city,
+                    nation,
+                    civilization,
+                    &wonders,
+                    tile_map,
+                    ruleset,
+                )
+            })
+            .collect();
+        buildings.sort();
+
+        choices.extend(
+            units
+                .into_iter()
+                .map(|unit_name| CityProduction::Unit(unit_name.clone())),
+        );
+        choices.extend(
+            buildings
+                .into_iter()
+                .map(|building| CityProduction::Building(building.clone())),
+        );
+        choices.push(CityProduction::Gold);
+    }
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                top: Val::Px(60.),
+                width: Val::Px(320.),
+                max_height: Val::Percent(80.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.),
+                padding: UiRect::all(Val::Px(12.)),
+                border: UiRect::all(Val::Px(2.)),
+                overflow: Overflow::clip_y(),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.9)),
+            BorderColor::all(Color::WHITE),
+            CityScreen(entity),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        align_self: AlignSelf::FlexEnd,
+                        padding: UiRect::horizontal(Val::Px(4.)),
+                        border: UiRect::all(Val::Px(1.)),
+                        ..default()
+                    },
+                    BorderColor::all(Color::WHITE),
+                    Text("Close".to_owned()),
+                    TextFont {
+                        font_size: 14.,
+                        ..default()
+                    },
+                ))
+                .observe(close_city_screen_on_click);
+            parent.spawn((
+                Text(details),
+                TextFont {
+                    font_size: 14.,
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+            if !choices.is_empty() {
+                parent.spawn((
+                    Text("Choose production:".to_owned()),
+                    TextFont {
+                        font_size: 14.,
+                        ..default()
+                    },
+                    Pickable::IGNORE,
+                ));
+            }
+            for choice in choices {
+                let is_current = city.production.as_ref() == Some(&choice);
+                let label = match choice.cost(ruleset) {
+                    Some(cost) => format!("{} ({cost})", choice.name()),
+                    None => choice.name().to_owned(),
+                };
+                parent
+                    .spawn((
+                        Node {
+                            padding: UiRect::horizontal(Val::Px(4.)),
+                            border: UiRect::all(Val::Px(1.)),
+                            ..default()
+                        },
+                        BorderColor::all(if is_current {
+                            Color::srgb(1., 0.85, 0.1)
+                        } else {
+                            Color::WHITE
+                        }),
+                        Text(label),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        ProductionButton(choice),
+                    ))
+                    .observe(choose_production_on_click);
+            }
+        });
+}
+
+fn choose_production_on_click(
+    click: On<Pointer<Click>>,
+    query_button: Query<(&ProductionButton, &ChildOf)>,
+    query_screen: Query<&CityScreen>,
+    query_city: Query<&City>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    if let Ok((ProductionButton(production), child_of)) = query_button.get(click.entity)
+        && let Ok(&CityScreen(city)) = query_screen.get(child_of.parent())
+        && let Ok(city) = query_city.get(city)
+    {
+        player_command.write(PlayerCommand::ChangeProduction {
+            city: city.tile,
+            production: Some(production.clone()),
+        });
+    }
+}
+
+fn close_city_screen_on_click(
+    click: On<Pointer<Click>>,
+    mut commands: Commands,
+    query_screen: Query<Entity, With<CityScreen>>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    for screen in query_screen.iter() {
+        commands.entity(screen).despawn();
+    }
+}
@@ -0,0 +1,611 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    barbarian::{BarbarianEncampment, EncampmentCleared},
+    city::City,
+    civilization::Civilizations,
+    happiness::luxury_resources,
+    improvement::TileImprovementLayer,
+    rng::GameRng,
+    turn::{TurnManager, TurnProcessing, TurnSet},
+    unit_component::Owner,
+    wonder::{WonderBuilt, Wonders},
+    yields::Yields,
+};
+
+/// A civilization with this much influence is a friend of the city-state.
+pub const FRIEND_THRESHOLD: f32 = 30.;
+/// The civilization with the most influence is the ally of the city-state if it has at least this much influence.
+pub const ALLY_THRESHOLD: f32 = 60.;
+/// Influence moves back toward 0 by this much every turn.
+const INFLUENCE_DECAY: f32 = 1.;
+/// The gold a civilization gives for 1 influence.
+const GOLD_PER_INFLUENCE: f32 = 8.;
+/// City-states ask for the encampments within this distance of their city to be cleared.
+const BARBARIAN_THREAT_DISTANCE: u32 = 8;
+/// The names of the quests in the ruleset, which give their influence.
+const CLEAR_BARBARIAN_CAMP_QUEST: &str = "Clear Barbarian Camp";
+const CONNECT_RESOURCE_QUEST: &str = "Connect Resource";
+const CONSTRUCT_WONDER_QUEST: &str = "Construct Wonder";
+/// City-states ask for the luxury resources within this distance of their city.
+const QUEST_RESOURCE_DISTANCE: u32 = 8;
+/// A city-state has at most this many quests at once.
+const MAX_ACTIVE_QUESTS: usize = 2;
+/// The chance every turn that a city-state with room for a quest issues one.
+const QUEST_CHANCE: f32 = 0.1;
+/// Quests expire this many turns after they were issued.
+const QUEST_DURATION: u32 = 40;
+
+/// The kind of a city-state, read from the `cityStateType` of its nation, which decides what its friends get.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CityStateType {
+    Cultured,
+    Maritime,
+    Mercantile,
+    Militaristic,
+    Religious,
+}
+
+impl CityStateType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Cultured" => Some(Self::Cultured),
+            "Maritime" => Some(Self::Maritime),
+            "Mercantile" => Some(Self::Mercantile),
+            "Militaristic" => Some(Self::Militaristic),
+            "Religious" => Some(Self::Religious),
+            _ => None,
+        }
+    }
+
+    /// The yields a friend of the city-state gets in its capital. Allies get twice as much.
+    ///
+    /// The happiness of mercantile city-states and the units of militaristic city-states are not given yet.
+    pub fn friend_yields(&self) -> Yields {
+        match self {
+            CityStateType::Cultured => Yields {
+                culture: 3.,
+                ..default()
+            },
+            CityStateType::Maritime => Yields {
+                food: 2.,
+                ..default()
+            },
+            CityStateType::Religious => Yields {
+                faith: 2.,
+                ..default()
+            },
+            CityStateType::Mercantile | CityStateType::Militaristic => Yields::default(),
+        }
+    }
+}
+
+/// What a civilization must do to complete a quest of a city-state.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum QuestGoal {
+    /// Clear the barbarian encampment on this tile.
+    ClearBarbarianCamp(Tile),
+    /// Have this luxury resource improved inside its borders.
+    ConnectResource(String),
+    /// Build this world wonder.
+    ConstructWonder(String),
+}
+
+impl QuestGoal {
+    /// The name of the quest in the ruleset.
+    pub fn quest_name(&self) -> &'static str {
+        match self {
+            QuestGoal::ClearBarbarianCamp(_) => CLEAR_BARBARIAN_CAMP_QUEST,
+            QuestGoal::ConnectResource(_) => CONNECT_RESOURCE_QUEST,
+            QuestGoal::ConstructWonder(_) => CONSTRUCT_WONDER_QUEST,
+        }
+    }
+}
+
+/// A quest issued by a city-state to every civilization.
+///
+/// Every civilization can complete it once, until it expires. Quests which can only be done once in the world,
+/// such as clearing an encampment or building a world wonder, end when they are done.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Quest {
+    pub goal: QuestGoal,
+    /// The quest is removed at the start of this turn.
+    pub expires_on_turn: u32,
+    /// The civilizations which already completed the quest.
+    pub completed_by: Vec<Nation>,
+}
+
+/// How a city-state sees a civilization.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CityStateRelationship {
+    Neutral,
+    Friend,
+    Ally,
+}
+
+/// The state of a city-state toward the civilizations.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CityState {
+    pub city_state_type: CityStateType,
+    /// The influence of every civilization with the city-state. It can be negative.
+    pub influence: HashMap<Nation, f32>,
+    /// The civilization allied with the city-state, see [`ALLY_THRESHOLD`].
+    pub ally: Option<Nation>,
+    /// The quests in progress, see [`MAX_ACTIVE_QUESTS`].
+    pub quests: Vec<Quest>,
+}
+
+impl CityState {
+    pub fn influence(&self, nation: Nation) -> f32 {
+        self.influence.get(&nation).copied().unwrap_or_default()
+    }
+
+    pub fn relationship(&self, nation: Nation) -> CityStateRelationship {
+        if self.ally == Some(nation) {
+            CityStateRelationship::Ally
+        } else if self.influence(nation) >= FRIEND_THRESHOLD {
+            CityStateRelationship::Friend
+        } else {
+            CityStateRelationship::Neutral
+        }
+    }
+
+    /// The civilization which should be the ally: the one with the most influence, if it reaches [`ALLY_THRESHOLD`].
+    /// The current ally keeps the city-state on a tie.
+    fn strongest_ally(&self) -> Option<Nation> {
+        let current_ally_influence = self.ally.map(|ally| self.influence(ally));
+        let mut candidates: Vec<_> = self
+            .influence
+            .iter()
+            .filter(|&(_, &influence)| influence >= ALLY_THRESHOLD)
+            .collect();
+        // Sort by nation so the choice doesn't depend on the order of the map.
+        candidates.sort_by_key(|&(nation, _)| nation.as_str());
+        let (&nation, &influence) = candidates
+            .into_iter()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        match current_ally_influence {
+            Some(current) if current >= influence && current >= ALLY_THRESHOLD => self.ally,
+            _ => Some(nation),
+        }
+    }
+}
+
+/// The [`CityState`] of every city-state in the game.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct CityStates(pub HashMap<Nation, CityState>);
+
+impl CityStates {
+    /// Return the relationship of every city-state with `nation`.
+    pub fn relationships(
+        &self,
+        nation: Nation,
+    ) -> impl Iterator<Item = (Nation, CityStateRelationship)> + '_ {
+        self.0
+            .iter()
+            .map(move |(&city_state, state)| (city_state, state.relationship(nation)))
+    }
+}
+
+/// Request from `nation` to give `gold` to `city_state` in exchange for influence.
+#[derive(Message)]
+pub struct GiftGold {
+    pub nation: Nation,
+    pub city_state: Nation,
+    pub gold: f32,
+}
+
+/// Written when `nation` completes the quest named `quest` of `city_state`, giving the influence of the quest.
+///
+/// Only civilizations get influence, so quests completed by other city-states are ignored.
+#[derive(Message)]
+pub struct CompleteQuest {
+    pub nation: Nation,
+    pub city_state: Nation,
+    pub quest: String,
+}
+
+/// Written when `city_state` issues a quest.
+#[derive(Message)]
+pub struct QuestIssued {
+    pub city_state: Nation,
+    pub goal: QuestGoal,
+    pub expires_on_turn: u32,
+}
+
+/// Written when a city-state gets a new ally or loses its ally.
+#[derive(Message)]
+pub struct CityStateAllyChanged {
+    pub city_state: Nation,
+    pub ally: Option<Nation>,
+}
+
+pub struct CityStatePlugin;
+
+impl Plugin for CityStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<GiftGold>()
+            .add_message::<CompleteQuest>()
+            .add_message::<QuestIssued>()
+            .add_message::<CityStateAllyChanged>()
+            .add_systems(OnEnter(AppState::GameStart), setup_city_states)
+            .add_systems(
+                Update,
+                (
+                    complete_barbarian_quests.run_if(on_message::<EncampmentCleared>),
+                    complete_wonder_quests.run_if(on_message::<WonderBuilt>),
+                    gift_gold.run_if(on_message::<GiftGold>),
+                    complete_quests.run_if(on_message::<CompleteQuest>),
+                    update_city_state_bonuses.run_if(resource_exists_and_changed::<CityStates>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                (
+                    decay_influence,
+                    expire_quests,
+                    complete_resource_quests,
+                    issue_quests,
+                )
+                    .chain()
+                    .in_set(TurnSet::Diplomacy),
+            );
+    }
+}
+
+fn setup_city_states(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+
+    let city_states = tile_map
+        .starting_tile_and_city_state
+        .values()
+        .filter_map(|&city_state| {
+            let city_state_type =
+                CityStateType::from_name(&ruleset.nations[city_state.as_str()].city_state_type)?;
+            let influence = tile_map
+                .starting_tile_and_civilization
+                .values()
+                .map(|&nation| (nation, 0.))
+                .collect();
+            Some((
+                city_state,
+                CityState {
+                    city_state_type,
+                    influence,
+                    ally: None,
+                    quests: Vec::new(),
+                },
+            ))
+        })
+        .collect();
+    commands.insert_resource(CityStates(city_states));
+}
+
+/// Give the gold to the city-state if the civilization has enough of it.
+fn gift_gold(
+    mut gift_gold: MessageReader<GiftGold>,
+    mut civilizations: ResMut<Civilizations>,
+    mut city_states: ResMut<CityStates>,
+) {
+    for &GiftGold {
+        nation,
+        city_state,
+        gold,
+    } in gift_gold.read()
+    {
+        let civilization = civilizations.get_mut(nation);
+        let Some(influence) = city_states
+            .0
+            .get_mut(&city_state)
+            .and_then(|state| state.influence.get_mut(&nation))
+        else {
+            continue;
+        };
+        if gold <= 0. || civilization.gold < gold {
+            continue;
+        }
+
+        civilization.gold -= gold;
+        *influence += gold / GOLD_PER_INFLUENCE;
+    }
+}
+
+fn complete_quests(
+    mut complete_quest: MessageReader<CompleteQuest>,
+    ruleset: Res<RulesetResource>,
+    mut city_states: ResMut<CityStates>,
+) {
+    for CompleteQuest {
+        nation,
+        city_state,
+        quest,
+    } in complete_quest.read()
+    {
+        let influence = city_states
+            .0
+            .get_mut(city_state)
+            .and_then(|state| state.influence.get_mut(nation));
+        let (Some(influence), Some(quest)) = (influence, ruleset.0.quests.get(quest)) else {
+            continue;
+        };
+        *influence += quest.influence;
+    }
+}
+
+/// End the quests of `city_states` whose goal is `goal`, and let `nation` complete them.
+fn complete_unique_quests(
+    goal: &QuestGoal,
+    nation: Nation,
+    city_states: &mut CityStates,
+    complete_quest: &mut MessageWriter<CompleteQuest>,
+) {
+    for (&city_state, state) in city_states.0.iter_mut() {
+        let quest_count = state.quests.len();
+        state.quests.retain(|quest| quest.goal != *goal);
+        if state.quests.len() < quest_count {
+            complete_quest.write(CompleteQuest {
+                nation,
+                city_state,
+                quest: goal.quest_name().to_owned(),
+            });
+        }
+    }
+}
+
+/// Clearing an encampment completes the "Clear Barbarian Camp" quests asking for it.
+fn complete_barbarian_quests(
+    mut encampment_cleared: MessageReader<EncampmentCleared>,
+    mut complete_quest: MessageWriter<CompleteQuest>,
+    mut city_states: ResMut<CityStates>,
+) {
+    for &EncampmentCleared { nation, tile, .. } in encampment_cleared.read() {
+        complete_unique_quests(
+            &QuestGoal::ClearBarbarianCamp(tile),
+            nation,
+            &mut city_states,
+            &mut complete_quest,
+        );
+    }
+}
+
+/// Building a world wonder completes the "Construct Wonder" quests asking for it.
+fn complete_wonder_quests(
+    mut wonder_built: MessageReader<WonderBuilt>,
+    mut complete_quest: MessageWriter<CompleteQuest>,
+    mut city_states: ResMut<CityStates>,
+) {
+    for WonderBuilt {
+        nation,
+        wonder,
+        is_world_wonder,
+        ..
+    } in wonder_built.read()
+    {
+        if *is_world_wonder {
+            complete_unique_quests(
+                &QuestGoal::ConstructWonder(wonder.clone()),
+                *nation,
+                &mut city_states,
+                &mut complete_quest,
+            );
+        }
+    }
+}
+
+/// Having the asked luxury resource improved inside its borders completes a "Connect Resource" quest,
+/// once for every civilization.
+fn complete_resource_quests(
+    mut complete_quest: MessageWriter<CompleteQuest>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    improvement_layer: Res<TileImprovementLayer>,
+    civilizations: Res<Civilizations>,
+    mut city_states: ResMut<CityStates>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+
+    for (&city_state, state) in city_states.0.iter_mut() {
+        let nations: Vec<Nation> = state.influence.keys().copied().collect();
+        for quest in state.quests.iter_mut() {
+            let QuestGoal::ConnectResource(resource) = &quest.goal else {
+                continue;
+            };
+            for &nation in &nations {
+                if quest.completed_by.contains(&nation) {
+                    continue;
+                }
+                let cities = query_city
+                    .iter()
+                    .filter(|(_, owner)| owner.nation() == nation)
+                    .map(|(city, _)| city);
+                let resources = luxury_resources(
+                    cities,
+                    civilizations.get(nation),
+                    tile_map,
+                    ruleset,
+                    &improvement_layer,
+                );
+                if resources.contains(resource) {
+                    quest.completed_by.push(nation);
+                    complete_quest.write(CompleteQuest {
+                        nation,
+                        city_state,
+                        quest: CONNECT_RESOURCE_QUEST.to_owned(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Remove the quests which reached their expiry turn.
+fn expire_quests(turn_manager: Res<TurnManager>, mut city_states: ResMut<CityStates>) {
+    // The turn starting after this processing.
+    let turn = turn_manager.turn + 1;
+    for state in city_states.0.values_mut() {
+        state.quests.retain(|quest| quest.expires_on_turn > turn);
+    }
+}
+
+/// Let every city-state with room for a quest issue one with [`QUEST_CHANCE`]. The goal is drawn among
+/// the encampments within [`BARBARIAN_THREAT_DISTANCE`], the luxury resources within [`QUEST_RESOURCE_DISTANCE`]
+/// which are not inside its own borders, and the world wonders nobody has built.
+fn issue_quests(
+    mut quest_issued: MessageWriter<QuestIssued>,
+    mut rng: ResMut<GameRng>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    turn_manager: Res<TurnManager>,
+    mut city_states: ResMut<CityStates>,
+    query_city: Query<(&City, &Owner)>,
+    query_encampment: Query<&BarbarianEncampment>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+    let wonders = Wonders::of_cities(
+        query_city
+            .iter()
+            .map(|(city, owner)| (city, owner.nation())),
+        ruleset,
+    );
+    let mut available_wonders: Vec<&str> = ruleset
+        .buildings
+        .values()
+        .filter(|building| building.is_wonder)
+        .map(|building| building.name.as_str())
+        .collect();
+    available_wonders.sort();
+
+    // Draw in a fixed order, so every player of a network game issues the same quests.
+    let mut city_state_cities: Vec<_> = query_city
+        .iter()
+        .filter_map(|(city, owner)| match *owner {
+            Owner::CityState(city_state) => Some((city_state, city)),
+            _ => None,
+        })
+        .collect();
+    city_state_cities.sort_by_key(|(_, city)| city.tile.index());
+
+    for (city_state, city) in city_state_cities {
+        let Some(state) = city_states.0.get_mut(&city_state) else {
+            continue;
+        };
+        if state.quests.len() >= MAX_ACTIVE_QUESTS || !rng.gen_bool(QUEST_CHANCE) {
+            continue;
+        }
+
+        let mut encampments: Vec<Tile> = query_encampment
+            .iter()
+            .map(|encampment| encampment.tile)
+            .filter(|&tile| city.tile.distance_to(tile, grid) <= BARBARIAN_THREAT_DISTANCE)
+            .collect();
+        encampments.sort_by_key(|tile| tile.index());
+        let mut resources: Vec<String> = city
+            .tile
+            .tiles_in_distance(QUEST_RESOURCE_DISTANCE, grid)
+            .filter(|tile| !city.owned_tiles.contains(tile))
+            .filter_map(|tile| tile.resource(tile_map))
+            .map(|(resource, _)| resource.as_str().to_owned())
+            .filter(|resource| ruleset.tile_resources[resource].resource_type == "Luxury")
+            .collect();
+        resources.sort();
+        resources.dedup();
+
+        let candidates: Vec<QuestGoal> = encampments
+            .into_iter()
+            .map(QuestGoal::ClearBarbarianCamp)
+            .chain(resources.into_iter().map(QuestGoal::ConnectResource))
+            .chain(
+                available_wonders
+                    .iter()
+                    .filter(|wonder| wonders.allows(wonder, city_state, ruleset))
+                    .map(|wonder| QuestGoal::ConstructWonder((*wonder).to_owned())),
+            )
+            .filter(|goal| state.quests.iter().all(|quest| quest.goal != *goal))
+            .collect();
+        if candidates.is_empty() {
+            continue;
+        }
+
+        let goal = candidates[rng.gen_range(0, candidates.len() as i32) as usize].clone();
+        let expires_on_turn = turn_manager.turn + 1 + QUEST_DURATION;
+        state.quests.push(Quest {
+            goal: goal.clone(),
+            expires_on_turn,
+            completed_by: Vec::new(),
+        });
+        quest_issued.write(QuestIssued {
+            city_state,
+            goal,
+            expires_on_turn,
+        });
+    }
+}
+
+/// Move the influence of every civilization with every city-state one step back toward 0.
+fn decay_influence(mut city_states: ResMut<CityStates>) {
+    for influence in city_states
+        .0
+        .values_mut()
+        .flat_map(|state| state.influence.values_mut())
+    {
+        *influence = if *influence > 0. {
+            (*influence - INFLUENCE_DECAY).max(0.)
+        } else {
+            (*influence + INFLUENCE_DECAY).min(0.)
+        };
+    }
+}
+
+/// Choose the ally of every city-state, and give their friends and allies the yields of the city-states.
+fn update_city_state_bonuses(
+    mut city_state_ally_changed: MessageWriter<CityStateAllyChanged>,
+    mut civilizations: ResMut<Civilizations>,
+    mut city_states: ResMut<CityStates>,
+) {
+    // Only change the allies when needed, so this system doesn't trigger itself again.
+    let ally_changes: Vec<_> = city_states
+        .0
+        .iter()
+        .map(|(&city_state, state)| (city_state, state.strongest_ally()))
+        .filter(|(city_state, ally)| city_states.0[city_state].ally != *ally)
+        .collect();
+    for (city_state, ally) in ally_changes {
+        if let Some(state) = city_states.0.get_mut(&city_state) {
+            state.ally = ally;
+        }
+        city_state_ally_changed.write(CityStateAllyChanged { city_state, ally });
+    }
+
+    let mut nation_and_yields: HashMap<Nation, Yields> = HashMap::new();
+    for state in city_states.0.values() {
+        for &nation in state.influence.keys() {
+            let multiplier = match state.relationship(nation) {
+                CityStateRelationship::Neutral => 0.,
+                CityStateRelationship::Friend => 1.,
+                CityStateRelationship::Ally => 2.,
+            };
+            *nation_and_yields.entry(nation).or_default() +=
+                state.city_state_type.friend_yields() * multiplier;
+        }
+    }
+    for (nation, yields) in nation_and_yields {
+        civilizations.get_mut(nation).city_state_yields = yields;
+    }
+}
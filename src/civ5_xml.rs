@@ -0,0 +1,534 @@
+//! Reading the gameplay XML of Civilization V (`CIV5Terrains.xml`, `CIV5Features.xml`, `CIV5Resources.xml`,
+//! `CIV5Units.xml`, ...), so the content of Civ V and of its mods can be brought into a mod quickly: the XML files put
+//! in the ruleset folder of a mod are read with its JSON files, see [`read_civ5_xml`].
+//!
+//! The Civ V tables are read into the format of Unciv, which is then converted like an Unciv mod, see
+//! [`crate::unciv_ruleset`]. The names are the English texts of the `Language_en_US` table if a file has them, or
+//! made from the types, e.g. `UNIT_GREAT_GENERAL` is `Great General`.
+//!
+//! Only the terrains, the features, the resources and the units are read. The natural wonders, which Civ V places
+//! with Lua scripts, are left out, and the `<Delete>` rows are only read for the resources and the units.
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde_json::{Map, Value, json};
+
+/// The extension of the Civ V gameplay files.
+const CIV5_XML_EXTENSION: &str = "xml";
+
+/// The types whose English name isn't the name of our ruleset.
+const RENAMED_TYPES: [(&str, &str); 5] = [
+    ("TERRAIN_GRASS", "Grassland"),
+    ("TERRAIN_PLAINS", "Plain"),
+    ("TERRAIN_HILL", "Hill"),
+    ("TERRAIN_MOUNTAIN", "Mountain"),
+    ("FEATURE_FLOOD_PLAINS", "Floodplain"),
+];
+
+/// The unit type of the units of a Civ V combat class.
+const UNIT_TYPES: [(&str, &str); 14] = [
+    ("UNITCOMBAT_RECON", "Scout"),
+    ("UNITCOMBAT_MELEE", "Sword"),
+    ("UNITCOMBAT_ARCHER", "Archery"),
+    ("UNITCOMBAT_MOUNTED", "Mounted"),
+    ("UNITCOMBAT_SIEGE", "Siege"),
+    ("UNITCOMBAT_GUN", "Gunpowder"),
+    ("UNITCOMBAT_ARMOR", "Armored"),
+    ("UNITCOMBAT_HELICOPTER", "Helicopter"),
+    ("UNITCOMBAT_NAVALMELEE", "Melee Water"),
+    ("UNITCOMBAT_NAVALRANGED", "Ranged Water"),
+    ("UNITCOMBAT_SUBMARINE", "Submarine"),
+    ("UNITCOMBAT_CARRIER", "Aircraft Carrier"),
+    ("UNITCOMBAT_FIGHTER", "Fighter"),
+    ("UNITCOMBAT_BOMBER", "Bomber"),
+];
+
+/// A row of a Civ V table: its columns by name, from the attributes and the child elements of the `<Row>`.
+type Row = HashMap<String, String>;
+
+/// The tables of the Civ V files of a mod.
+#[derive(Default)]
+struct Civ5Tables {
+    /// The rows of the tables by table name, in the order of the files.
+    rows: HashMap<String, Vec<Row>>,
+    /// The types deleted by the `<Delete Type="...">` rows, by table name.
+    deleted: HashMap<String, Vec<String>>,
+    /// Our names of the types.
+    names: HashMap<String, String>,
+}
+
+impl Civ5Tables {
+    /// Add the tables of the `<GameData>` of a Civ V file.
+    fn read(&mut self, content: &str, path: &Path) -> io::Result<()> {
+        let document = roxmltree::Document::parse(content)
+            .map_err(|error| io::Error::other(format!("{}: {error}", path.display())))?;
+        for table in document
+            .root_element()
+            .children()
+            .filter(|node| node.is_element())
+        {
+            let table_name = table.tag_name().name();
+            for row in table.children().filter(|node| node.is_element()) {
+                match row.tag_name().name() {
+                    "Row" => {
+                        let mut columns: Row = row
+                            .attributes()
+                            .map(|attribute| {
+                                (attribute.name().to_owned(), attribute.value().to_owned())
+                            })
+                            .collect();
+                        for column in row.children().filter(|node| node.is_element()) {
+                            columns.insert(
+                                column.tag_name().name().to_owned(),
+                                column.text().unwrap_or_default().trim().to_owned(),
+                            );
+                        }
+                        self.rows
+                            .entry(table_name.to_owned())
+                            .or_default()
+                            .push(columns);
+                    }
+                    "Delete" => {
+                        if let Some(deleted) = row.attribute("Type") {
+                            self.deleted
+                                .entry(table_name.to_owned())
+                                .or_default()
+                                .push(deleted.to_owned());
+                        }
+                    }
+                    // The `<Update>` rows change the base game of Civ V, which we don't have.
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Name the types with the English texts of their description.
+    fn name_types(&mut self) {
+        let texts: HashMap<&str, &str> = self
+            .rows("Language_en_US")
+            .iter()
+            .filter_map(|row| Some((row.get("Tag")?.as_str(), row.get("Text")?.as_str())))
+            .collect();
+        let mut names = HashMap::new();
+        for row in self.rows.values().flatten() {
+            if let (Some(row_type), Some(text)) = (
+                row.get("Type"),
+                row.get("Description")
+                    .and_then(|description| texts.get(description.as_str())),
+            ) {
+                names.insert(row_type.clone(), (*text).to_owned());
+            }
+        }
+        for (row_type, name) in RENAMED_TYPES {
+            names.insert(row_type.to_owned(), name.to_owned());
+        }
+        self.names = names;
+    }
+
+    fn rows(&self, table: &str) -> &[Row] {
+        self.rows.get(table).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Our name of a Civ V type.
+    fn name(&self, row_type: &str) -> String {
+        self.names
+            .get(row_type)
+            .cloned()
+            .unwrap_or_else(|| name_from_type(row_type))
+    }
+
+    /// The names of the types in `column` of the rows of `table` whose `key_column` is `key`.
+    fn linked_names(&self, table: &str, key_column: &str, key: &str, column: &str) -> Vec<String> {
+        self.rows(table)
+            .iter()
+            .filter(|row| row.get(key_column).is_some_and(|value| value == key))
+            .filter_map(|row| Some(self.name(row.get(column)?)))
+            .collect()
+    }
+
+    /// The yields of the rows of `table` whose `key_column` is `key`, by our yield names, e.g. `food`.
+    fn yields(&self, table: &str, key_column: &str, key: &str) -> Map<String, Value> {
+        self.yields_where(table, |row| {
+            row.get(key_column).is_some_and(|value| value == key)
+        })
+    }
+
+    /// The yields of the rows of `table` matching `filter`, by our yield names.
+    fn yields_where(&self, table: &str, filter: impl Fn(&Row) -> bool) -> Map<String, Value> {
+        self.rows(table)
+            .iter()
+            .filter(|row| filter(row))
+            .filter_map(|row| {
+                let yield_type = row.get("YieldType")?.strip_prefix("YIELD_")?.to_lowercase();
+                let amount: i64 = row.get("Yield")?.parse().ok()?;
+                Some((yield_type, json!(amount)))
+            })
+            .collect()
+    }
+
+    /// The Unciv terrains of the `Terrains` and the `Features` tables.
+    fn terrains(&self) -> Vec<Value> {
+        let mut terrains = Vec::new();
+        for row in self.rows("Terrains") {
+            let Some(row_type) = row.get("Type") else {
+                continue;
+            };
+            let mut terrain = self.yields("Terrain_Yields", "TerrainType", row_type);
+            terrain.insert("name".to_owned(), json!(self.name(row_type)));
+            let water = is_true(row, "Water");
+            terrain.insert(
+                "type".to_owned(),
+                json!(if water { "Water" } else { "Land" }),
+            );
+            insert_terrain_columns(&mut terrain, row);
+            terrains.push(Value::Object(terrain));
+        }
+        for row in self.rows("Features") {
+            let Some(row_type) = row.get("Type") else {
+                continue;
+            };
+            if is_true(row, "NaturalWonder") {
+                continue;
+            }
+            let mut feature = self.yields("Feature_YieldChanges", "FeatureType", row_type);
+            feature.insert("name".to_owned(), json!(self.name(row_type)));
+            feature.insert("type".to_owned(), json!("TerrainFeature"));
+            feature.insert(
+                "occursOn".to_owned(),
+                json!(self.linked_names(
+                    "Feature_TerrainBooleans",
+                    "FeatureType",
+                    row_type,
+                    "TerrainType"
+                )),
+            );
+            insert_terrain_columns(&mut feature, row);
+            terrains.push(Value::Object(feature));
+        }
+        terrains
+    }
+
+    /// The resources of the `Resources` table, with the Unciv `terrainsCanBeFoundOn`.
+    fn resources(&self) -> Vec<Value> {
+        let mut resources = self.deleted_entries("Resources");
+        for row in self.rows("Resources") {
+            let Some(row_type) = row.get("Type") else {
+                continue;
+            };
+            let mut resource = self.yields("Resource_YieldChanges", "ResourceType", row_type);
+            resource.insert("name".to_owned(), json!(self.name(row_type)));
+            let resource_type = match row.get("ResourceClassType").map(String::as_str) {
+                Some("RESOURCECLASS_LUXURY") => "Luxury",
+                Some("RESOURCECLASS_RUSH" | "RESOURCECLASS_MODERN") => "Strategic",
+                _ => "Bonus",
+            };
+            resource.insert("resourceType".to_owned(), json!(resource_type));
+            let mut found_on = self.linked_names(
+                "Resource_TerrainBooleans",
+                "ResourceType",
+                row_type,
+                "TerrainType",
+            );
+            found_on.extend(self.linked_names(
+                "Resource_FeatureBooleans",
+                "ResourceType",
+                row_type,
+                "FeatureType",
+            ));
+            resource.insert("terrainsCanBeFoundOn".to_owned(), json!(found_on));
+            if let Some(tech) = row
+                .get("TechReveal")
+                .filter(|tech| !tech.is_empty() && *tech != "NULL")
+            {
+                resource.insert("revealedBy".to_owned(), json!(self.name(tech)));
+            }
+            if let Some(improvement) = self
+                .rows("Improvement_ResourceTypes")
+                .iter()
+                .find(|improvement| improvement.get("ResourceType") == Some(row_type))
+                .and_then(|improvement| improvement.get("ImprovementType"))
+            {
+                resource.insert("improvement".to_owned(), json!(self.name(improvement)));
+                let improvement_stats =
+                    self.yields_where("Improvement_ResourceType_Yields", |row| {
+                        row.get("ImprovementType") == Some(improvement)
+                            && row.get("ResourceType") == Some(row_type)
+                    });
+                if !improvement_stats.is_empty() {
+                    resource.insert(
+                        "improvementStats".to_owned(),
+                        Value::Object(improvement_stats),
+                    );
+                }
+            }
+            resources.push(Value::Object(resource));
+        }
+        resources
+    }
+
+    /// The units of the `Units` table.
+    fn units(&self) -> Vec<Value> {
+        let mut units = self.deleted_entries("Units");
+        for row in self.rows("Units") {
+            let Some(row_type) = row.get("Type") else {
+                continue;
+            };
+            let mut unit = Map::new();
+            unit.insert("name".to_owned(), json!(self.name(row_type)));
+            let unit_type = row
+                .get("CombatClass")
+                .and_then(|class| {
+                    UNIT_TYPES
+                        .iter()
+                        .find(|(civ5_class, _)| civ5_class == class)
+                })
+                .map(|(_, unit_type)| *unit_type)
+                .unwrap_or(
+                    match (row.get("Domain").map(String::as_str), number(row, "Combat")) {
+                        (Some("DOMAIN_SEA"), 0) => "Civilian Water",
+                        (Some("DOMAIN_SEA"), _) => "Melee Water",
+                        (_, 0) => "Civilian",
+                        _ => "Sword",
+                    },
+                );
+            unit.insert("unitType".to_owned(), json!(unit_type));
+            unit.insert("movement".to_owned(), json!(number(row, "Moves")));
+            for (column, field) in [
+                ("Cost", "cost"),
+                ("Combat", "strength"),
+                ("RangedCombat", "rangedStrength"),
+                ("Range", "range"),
+            ] {
+                if number(row, column) > 0 {
+                    unit.insert(field.to_owned(), json!(number(row, column)));
+                }
+            }
+            for (column, field) in [
+                ("PrereqTech", "requiredTech"),
+                ("ObsoleteTech", "obsoleteTech"),
+            ] {
+                if let Some(tech) = row
+                    .get(column)
+                    .filter(|tech| !tech.is_empty() && *tech != "NULL")
+                {
+                    unit.insert(field.to_owned(), json!(self.name(tech)));
+                }
+            }
+            if let Some(class) = self
+                .rows("Unit_ClassUpgrades")
+                .iter()
+                .find(|upgrade| upgrade.get("UnitType") == Some(row_type))
+                .and_then(|upgrade| upgrade.get("UnitClassType"))
+            {
+                // The units of a class upgrade to its default unit.
+                let upgrade = self
+                    .rows("UnitClasses")
+                    .iter()
+                    .find(|unit_class| unit_class.get("Type") == Some(class))
+                    .and_then(|unit_class| unit_class.get("DefaultUnit"))
+                    .map_or_else(
+                        || name_from_type(class),
+                        |default_unit| self.name(default_unit),
+                    );
+                unit.insert("upgradesTo".to_owned(), json!(upgrade));
+            }
+            if let Some(resource) = self
+                .linked_names(
+                    "Unit_ResourceQuantityRequirements",
+                    "UnitType",
+                    row_type,
+                    "ResourceType",
+                )
+                .into_iter()
+                .next()
+            {
+                unit.insert("requiredResource".to_owned(), json!(resource));
+            }
+            units.push(Value::Object(unit));
+        }
+        units
+    }
+
+    /// The entries deleting the types deleted from `table`.
+    fn deleted_entries(&self, table: &str) -> Vec<Value> {
+        self.deleted
+            .get(table)
+            .into_iter()
+            .flatten()
+            .map(|row_type| json!({ "name": self.name(row_type), "delete": true }))
+            .collect()
+    }
+}
+
+/// Read the Civ V files in `folder` into `files`, the ruleset files of a mod by file name. The terrains are returned
+/// in the format of Unciv, to be converted with the terrains of an Unciv mod. `None` if the folder has no Civ V file.
+pub fn read_civ5_xml(
+    folder: &Path,
+    files: &mut HashMap<&'static str, Vec<Value>>,
+) -> io::Result<Option<Vec<Value>>> {
+    let mut paths: Vec<_> = match fs::read_dir(folder) {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case(CIV5_XML_EXTENSION))
+            })
+            .collect(),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    paths.sort();
+
+    let mut tables = Civ5Tables::default();
+    for path in &paths {
+        tables.read(&fs::read_to_string(path)?, path)?;
+    }
+    tables.name_types();
+
+    files
+        .entry("TileResources.json")
+        .or_default()
+        .extend(tables.resources());
+    files
+        .entry("Units.json")
+        .or_default()
+        .extend(tables.units());
+    Ok(Some(tables.terrains()))
+}
+
+/// Add the columns the terrains and the features share.
+fn insert_terrain_columns(terrain: &mut Map<String, Value>, row: &Row) {
+    terrain.insert("movementCost".to_owned(), json!(number(row, "Movement")));
+    if is_true(row, "Impassable") {
+        terrain.insert("impassable".to_owned(), json!(true));
+    }
+    let defense = number(row, "Defense");
+    if defense != 0 {
+        terrain.insert("defenceBonus".to_owned(), json!(defense as f64 / 100.));
+    }
+}
+
+/// Whether a boolean column is true. Civ V writes the booleans as `true` or `1`.
+fn is_true(row: &Row, column: &str) -> bool {
+    row.get(column)
+        .is_some_and(|value| value.eq_ignore_ascii_case("true") || value == "1")
+}
+
+/// The value of a number column, 0 if the row doesn't have it.
+fn number(row: &Row, column: &str) -> i64 {
+    row.get(column)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default()
+}
+
+/// A name made from a Civ V type, without its prefix, e.g. `Bronze Working` for `TECH_BRONZE_WORKING`.
+fn name_from_type(row_type: &str) -> String {
+    let name = row_type.split_once('_').map_or(row_type, |(_, name)| name);
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let word = word.to_lowercase();
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use civ_map_generator::ruleset::Ruleset;
+
+    use super::*;
+    use crate::{
+        mod_manager::{merge_mod_files, read_mod_files},
+        ruleset_validation::RulesetFiles,
+        unit::UnitDomain,
+    };
+
+    const NAVAL_UNITS: &str = r#"<GameData>
+        <Units>
+            <Row>
+                <Type>UNIT_TRIREME</Type>
+                <Description>TXT_KEY_UNIT_TRIREME</Description>
+                <CombatClass>UNITCOMBAT_NAVALMELEE</CombatClass>
+                <Domain>DOMAIN_SEA</Domain>
+                <Combat>10</Combat>
+                <Moves>4</Moves>
+                <PrereqTech>TECH_SAILING</PrereqTech>
+            </Row>
+            <Row>
+                <Type>UNIT_WORKBOAT</Type>
+                <Description>TXT_KEY_UNIT_WORKBOAT</Description>
+                <Domain>DOMAIN_SEA</Domain>
+                <Moves>4</Moves>
+                <PrereqTech>TECH_SAILING</PrereqTech>
+            </Row>
+        </Units>
+        <Language_en_US>
+            <Row Tag="TXT_KEY_UNIT_TRIREME"><Text>Trireme</Text></Row>
+            <Row Tag="TXT_KEY_UNIT_WORKBOAT"><Text>Work Boat</Text></Row>
+        </Language_en_US>
+    </GameData>"#;
+
+    #[test]
+    fn imports_naval_units_with_the_unit_types_of_the_ruleset() {
+        let folder = std::env::temp_dir().join("civ5_xml_naval_units");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("CIV5Units.xml"), NAVAL_UNITS).unwrap();
+
+        let mut ruleset = Ruleset::default();
+        let files = read_mod_files(&folder, &ruleset).unwrap();
+        fs::remove_dir_all(&folder).unwrap();
+
+        let base_files = RulesetFiles::read_base().unwrap();
+        let base_diagnostics = base_files.validate();
+        let mut mod_files = base_files.clone();
+        mod_files.add(&folder, files.clone());
+        let new_diagnostics: Vec<_> = mod_files
+            .validate()
+            .into_iter()
+            .filter(|diagnostic| !base_diagnostics.contains(diagnostic))
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        assert!(new_diagnostics.is_empty(), "{new_diagnostics:?}");
+
+        merge_mod_files(&mut ruleset, files).unwrap();
+        assert_eq!(ruleset.units["Trireme"].unit_type, "Melee Water");
+        assert_eq!(ruleset.units["Work Boat"].unit_type, "Civilian Water");
+        for unit in ["Trireme", "Work Boat"] {
+            assert_eq!(UnitDomain::of_unit(unit, &ruleset), UnitDomain::Water);
+        }
+    }
+
+    #[test]
+    fn names_hills_and_mountains_as_the_ruleset() {
+        let mut tables = Civ5Tables::default();
+        tables
+            .read(
+                r#"<GameData>
+                    <Terrains>
+                        <Row><Type>TERRAIN_HILL</Type><Description>TXT_KEY_TERRAIN_HILL</Description></Row>
+                        <Row><Type>TERRAIN_MOUNTAIN</Type><Description>TXT_KEY_TERRAIN_MOUNTAIN</Description></Row>
+                    </Terrains>
+                    <Language_en_US>
+                        <Row Tag="TXT_KEY_TERRAIN_HILL"><Text>Hills</Text></Row>
+                        <Row Tag="TXT_KEY_TERRAIN_MOUNTAIN"><Text>Mountains</Text></Row>
+                    </Language_en_US>
+                </GameData>"#,
+                Path::new("CIV5Terrains.xml"),
+            )
+            .unwrap();
+        tables.name_types();
+        assert_eq!(tables.name("TERRAIN_HILL"), "Hill");
+        assert_eq!(tables.name("TERRAIN_MOUNTAIN"), "Mountain");
+    }
+}
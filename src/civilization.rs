@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+use civ_map_generator::{
+    nation::Nation,
+    ruleset::{DifficultyInfo, Ruleset},
+    tile::Tile,
+    tile_component::Resource,
+    tile_map::TileMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    economy::{GoldBreakdown, UnitSupply},
+    effect::CivilizationEffects,
+    era::{StartingEra, technologies_before_era},
+    espionage::Spy,
+    golden_age::GoldenAgeProgress,
+    great_person::GreatPeopleProgress,
+    happiness::HappinessLevel,
+    scenario::PendingScenario,
+    technology::learn_technologies_with_prerequisites,
+    yields::Yields,
+};
+
+/// The civilization controlled by the local player.
+#[derive(Resource, Clone, Copy)]
+pub struct PlayerCivilization(pub Nation);
+
+/// The state of a civilization or a city-state which doesn't belong to one of its units or cities.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Civilization {
+    pub gold: f32,
+    /// The gold income and expenses of the last turn.
+    pub gold_breakdown: GoldBreakdown,
+    /// The unit supply of the last turn, which lowers the production of the cities when it is exceeded.
+    pub unit_supply: UnitSupply,
+    pub researched_technologies: HashSet<String>,
+    /// The technology the science of the civilization goes to.
+    pub current_research: Option<String>,
+    /// The technologies to research after [`Civilization::current_research`], in order.
+    pub research_queue: VecDeque<String>,
+    /// The science accumulated toward each technology which was researched but not finished.
+    pub research_progress: HashMap<String, f32>,
+    /// The science left over when a technology is finished, added to the next research.
+    pub science_overflow: f32,
+    /// The current era, see [`crate::era::civilization_era`].
+    pub era: String,
+    /// The units made obsolete by the era effects, see [`crate::era::EraEffect`].
+    pub obsolete_units: HashSet<String>,
+    /// The buildings unlocked by the era effects, see [`crate::era::EraEffect`].
+    pub unlocked_buildings: HashSet<String>,
+    /// The culture accumulated toward the next policy.
+    pub culture: f32,
+    /// The adopted policies and the opened policy branches.
+    pub adopted_policies: HashSet<String>,
+    /// The sum of the effects of the uniques of the nation and of the adopted policies.
+    pub effects: CivilizationEffects,
+    /// The happiness minus the unhappiness of the civilization, updated every turn.
+    pub happiness: f32,
+    /// The resources the civilization can see on the map, see [`crate::technology::revealed_resources`].
+    pub revealed_resources: HashSet<String>,
+    pub great_people: GreatPeopleProgress,
+    pub golden_age: GoldenAgeProgress,
+    /// The yields added to the capital by the city-states the civilization is friends or allied with,
+    /// see [`crate::city_state::CityStates`].
+    pub city_state_yields: Yields,
+    pub spies: Vec<Spy>,
+}
+
+impl Civilization {
+    pub fn is_resource_revealed(&self, resource: &str) -> bool {
+        self.revealed_resources.contains(resource)
+    }
+
+    /// Return the resource on `tile` if the civilization can see it.
+    pub fn visible_resource(&self, tile: Tile, tile_map: &TileMap) -> Option<(Resource, u32)> {
+        tile.resource(tile_map)
+            .filter(|(resource, _)| self.is_resource_revealed(resource.as_str()))
+    }
+
+    pub fn happiness_level(&self) -> HappinessLevel {
+        HappinessLevel::from_happiness(self.happiness)
+    }
+}
+
+impl Civilization {
+    /// Return `true` if the technology has been researched. An empty technology name means no technology is required.
+    pub fn has_technology(&self, technology: &str) -> bool {
+        technology.is_empty() || self.researched_technologies.contains(technology)
+    }
+
+    /// Add a technology obtained without researching it, e.g. from a great person or a spy.
+    /// It leaves the research queue, and the science already put into it is lost.
+    pub fn learn_technology(&mut self, technology: &str) {
+        self.research_progress.remove(technology);
+        self.research_queue.retain(|queued| queued != technology);
+        if self.current_research.as_deref() == Some(technology) {
+            self.current_research = self.research_queue.pop_front();
+        }
+        self.researched_technologies.insert(technology.to_owned());
+    }
+}
+
+/// The difficulty of the game, the name of one of the difficulties of the ruleset.
+#[derive(Resource, Clone)]
+pub struct Difficulty(pub String);
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self("Prince".to_owned())
+    }
+}
+
+impl Difficulty {
+    pub fn info<'a>(&self, ruleset: &'a Ruleset) -> &'a DifficultyInfo {
+        &ruleset.difficulties[&self.0]
+    }
+}
+
+/// The [`Civilization`] of every civilization and city-state in the game.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct Civilizations(HashMap<Nation, Civilization>);
+
+impl Civilizations {
+    pub fn get(&self, nation: Nation) -> &Civilization {
+        &self.0[&nation]
+    }
+
+    pub fn get_mut(&mut self, nation: Nation) -> &mut Civilization {
+        self.0
+            .get_mut(&nation)
+            .expect("Every nation in the game should have a civilization")
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Nation, &Civilization)> {
+        self.0
+            .iter()
+            .map(|(&nation, civilization)| (nation, civilization))
+    }
+}
+
+impl FromIterator<(Nation, Civilization)> for Civilizations {
+    fn from_iter<T: IntoIterator<Item = (Nation, Civilization)>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+pub struct CivilizationPlugin;
+
+impl Plugin for CivilizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>().add_systems(
+            OnEnter(AppState::GameStart),
+            (setup_player_civilization, setup_civilizations),
+        );
+    }
+}
+
+/// Let the player control the civilization of the scenario being started, or else the civilization
+/// with the lowest starting tile index, so the choice is the same for the same map.
+fn setup_player_civilization(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    pending_scenario: Option<Res<PendingScenario>>,
+) {
+    let tile_map = &map.0;
+    let player_civilization = pending_scenario
+        .map(|pending_scenario| pending_scenario.0.player_civilization)
+        .or_else(|| {
+            tile_map
+                .starting_tile_and_civilization
+                .iter()
+                .min_by_key(|(tile, _)| tile.index())
+                .map(|(_, &nation)| nation)
+        })
+        .expect("The map should have at least one civilization");
+    commands.insert_resource(PlayerCivilization(player_civilization));
+}
+
+/// Every civilization starts with the technologies without prerequisites, e.g. Agriculture,
+/// and with the effects of the uniques of its nation.
+/// In a later [`StartingEra`], it also starts with the technologies of the earlier eras and the starting gold of the era.
+///
+/// The barbarians have a civilization too, so their units can be handled like the units of the other nations.
+fn setup_civilizations(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    starting_era: Res<StartingEra>,
+) {
+    let tile_map = &map.0;
+    let era_technologies = technologies_before_era(&starting_era.0, &ruleset.0);
+    let starting_gold = ruleset.0.eras[&starting_era.0].starting_gold as f32;
+    let starting_technologies: HashSet<_> = ruleset
+        .0
+        .technologies
+        .values()
+        .filter(|technology| technology.prerequisites.is_empty())
+        .map(|technology| technology.name.clone())
+        .collect();
+
+    let civilizations = tile_map
+        .starting_tile_and_civilization
+        .values()
+        .chain(tile_map.starting_tile_and_city_state.values())
+        .chain(std::iter::once(&Nation::Barbarians))
+        .map(|&nation| {
+            let mut civilization = Civilization {
+                gold: starting_gold,
+                researched_technologies: starting_technologies.clone(),
+                ..default()
+            };
+            learn_technologies_with_prerequisites(&era_technologies, &mut civilization, &ruleset.0);
+            if let Some(nation_info) = ruleset.0.nations.get(nation.as_str()) {
+                civilization.effects.apply_uniques(&nation_info.uniques);
+            }
+            (nation, civilization)
+        })
+        .collect();
+    commands.insert_resource(Civilizations(civilizations));
+}
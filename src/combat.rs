@@ -0,0 +1,891 @@
+use bevy::prelude::*;
+use civ_map_generator::{
+    grid::hex_grid::HexGrid, nation::Nation, ruleset::Ruleset, tile::Tile, tile_map::TileMap,
+};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    city::{City, CityCaptured, can_found_city},
+    civilization::Civilizations,
+    diplomacy::DiplomacyState,
+    effect::{Opponent, strength_bonus},
+    embarkation::Embarked,
+    game_event::{CombatResolved, UnitMoved},
+    grid::has_line_of_sight,
+    pathfinding::crosses_river,
+    rng::GameRng,
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, MovePath, UnitDomain},
+    unit_component::{Health, Movement, Owner, RangedStrength, Strength, Unit, UnitOrder},
+};
+
+/// The damage dealt by a fight between two units of the same strength, as in Civ V.
+const BASE_DAMAGE: f32 = 30.;
+/// Every friendly military unit next to the defender, besides the attacker, adds 10% to the attack strength.
+const FLANKING_BONUS: f32 = 0.1;
+/// Melee attacks across a river lose 20% of their strength.
+const RIVER_CROSSING_PENALTY: f32 = 0.2;
+/// Cities can strike the units within this distance, once per turn.
+pub const CITY_STRIKE_RANGE: u32 = 2;
+/// The unique of the civilians which are destroyed instead of captured, e.g. great people and work boats.
+const UNCAPTURABLE_UNIQUE: &str = "Uncapturable";
+
+/// Request for a unit to attack another unit, with a melee attack or a ranged attack if the attacker has one.
+#[derive(Message)]
+pub struct Attack {
+    pub attacker: Entity,
+    pub defender: Entity,
+}
+
+/// Request for a unit to attack a city, with a melee attack or a ranged attack if the attacker has one.
+#[derive(Message)]
+pub struct AttackCity {
+    pub attacker: Entity,
+    pub city: Entity,
+}
+
+/// Written when an attack on a city is over.
+#[derive(Message, Clone)]
+pub struct CityAttacked {
+    pub attacker: Entity,
+    pub city: Entity,
+    pub attacker_nation: Nation,
+    pub city_nation: Nation,
+    pub city_tile: Tile,
+    pub is_ranged: bool,
+    pub damage_to_attacker: u32,
+    pub damage_to_city: u32,
+    pub attacker_killed: bool,
+}
+
+/// Written when a melee unit of `nation` takes the tile of a civilian of `previous_nation`.
+/// The civilian joins `nation`, or is destroyed if it can't be captured, see [`can_be_captured`].
+#[derive(Message, Clone)]
+pub struct UnitCaptured {
+    pub unit: Entity,
+    pub unit_name: String,
+    pub nation: Nation,
+    pub previous_nation: Nation,
+    pub tile: Tile,
+    pub destroyed: bool,
+}
+
+/// Request for a city to strike a unit with its ranged attack.
+#[derive(Message)]
+pub struct CityStrike {
+    pub city: Entity,
+    pub target: Entity,
+}
+
+/// Written when a city struck a unit.
+#[derive(Message, Clone)]
+pub struct CityStruck {
+    pub city: Entity,
+    pub target: Entity,
+    pub city_nation: Nation,
+    pub target_nation: Nation,
+    pub target_tile: Tile,
+    pub damage_to_target: u32,
+    pub target_killed: bool,
+}
+
+/// The damage expected from a fight, before the random part of the damage.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CombatPrediction {
+    pub damage_to_attacker: f32,
+    pub damage_to_defender: f32,
+}
+
+/// Predict a fight between two strengths, with all the modifiers already applied.
+///
+/// The stronger side deals `30 * (((ratio + 3) / 4)^4 + 1) / 2` damage and the weaker side deals 30 divided by that,
+/// where `ratio` is the strength of the stronger side divided by the strength of the weaker side.
+/// Ranged attacks don't take any damage back.
+pub fn predict_combat(
+    attack_strength: f32,
+    defense_strength: f32,
+    is_ranged: bool,
+) -> CombatPrediction {
+    let attack_strength = attack_strength.max(f32::EPSILON);
+    let defense_strength = defense_strength.max(f32::EPSILON);
+    let ratio = attack_strength.max(defense_strength) / attack_strength.min(defense_strength);
+    let modifier = (((ratio + 3.) / 4.).powi(4) + 1.) / 2.;
+
+    let (damage_to_attacker, damage_to_defender) = if attack_strength >= defense_strength {
+        (BASE_DAMAGE / modifier, BASE_DAMAGE * modifier)
+    } else {
+        (BASE_DAMAGE * modifier, BASE_DAMAGE / modifier)
+    };
+
+    CombatPrediction {
+        damage_to_attacker: if is_ranged { 0. } else { damage_to_attacker },
+        damage_to_defender,
+    }
+}
+
+/// Predict the strike of `city` on a unit defending with `defense_strength`, see [`City::combat_strength`].
+pub fn predict_city_strike(
+    city: &City,
+    defense_strength: f32,
+    ruleset: &Ruleset,
+) -> CombatPrediction {
+    predict_combat(city.combat_strength(ruleset), defense_strength, true)
+}
+
+/// Wounded units fight weaker: a unit loses half of its missing health in percent of its strength.
+pub fn wounded_modifier(health: &Health) -> f32 {
+    1. - (health.max - health.current) as f32 / health.max as f32 / 2.
+}
+
+/// The defense bonus of the terrain type and the feature of a tile, e.g. `0.25` for hills.
+pub fn terrain_defense_bonus(tile: Tile, tile_map: &TileMap, ruleset: &Ruleset) -> f32 {
+    let terrain_type = &ruleset.terrain_types[tile.terrain_type(tile_map).as_str()];
+    let feature_bonus = tile.feature(tile_map).map_or(0., |feature| {
+        ruleset.features[feature.as_str()].defence_bonus
+    });
+    terrain_type.defence_bonus + feature_bonus
+}
+
+/// The attack strength of a unit, with the modifiers of the attack.
+///
+/// `flanking_units` is the number of friendly military units next to the defender, besides the attacker.
+/// Only melee attacks get the flanking bonus and the river crossing penalty.
+/// `strength_bonus` comes from the effects of the uniques, see [`strength_bonus`].
+pub fn attack_strength(
+    base_strength: u32,
+    health: &Health,
+    flanking_units: usize,
+    crosses_river: bool,
+    is_ranged: bool,
+    strength_bonus: f32,
+) -> f32 {
+    let mut modifier = 1. + strength_bonus;
+    if !is_ranged {
+        modifier += flanking_units as f32 * FLANKING_BONUS;
+        if crosses_river {
+            modifier -= RIVER_CROSSING_PENALTY;
+        }
+    }
+    base_strength as f32 * modifier * wounded_modifier(health)
+}
+
+/// The defense strength of a unit on `tile`, with the defense bonus of the terrain and of its order, see [`UnitOrder::defense_bonus`],
+/// and the bonus of the effects of the uniques, see [`strength_bonus`].
+pub fn defense_strength(
+    base_strength: u32,
+    health: &Health,
+    order: &UnitOrder,
+    tile: Tile,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+    strength_bonus: f32,
+) -> f32 {
+    base_strength as f32
+        * (1.
+            + terrain_defense_bonus(tile, tile_map, ruleset)
+            + order.defense_bonus()
+            + strength_bonus)
+        * wounded_modifier(health)
+}
+
+/// Return the closest of `enemy_units` the unit on `tile` can attack: its neighbors for melee units,
+/// and the tiles within its range for ranged units. Ties are broken by entity, so the choice is stable.
+pub fn closest_target_in_reach(
+    tile: Tile,
+    ranged: &RangedStrength,
+    enemy_units: &[(Entity, Tile)],
+    grid: HexGrid,
+) -> Option<Entity> {
+    let reach = if ranged.strength > 0 { ranged.range } else { 1 };
+    enemy_units
+        .iter()
+        .filter(|(_, enemy_tile)| enemy_tile.distance_to(tile, grid) <= reach)
+        .min_by_key(|&&(enemy, enemy_tile)| (enemy_tile.distance_to(tile, grid), enemy))
+        .map(|&(enemy, _)| enemy)
+}
+
+/// Return `true` if the civilian `unit_name` joins the enemy taking its tile. Settlers and the units with the
+/// [`UNCAPTURABLE_UNIQUE`] are destroyed instead, as in Civ V.
+pub fn can_be_captured(unit_name: &str, ruleset: &Ruleset) -> bool {
+    !can_found_city(unit_name, ruleset)
+        && !ruleset.units[unit_name]
+            .uniques
+            .iter()
+            .any(|unique| unique == UNCAPTURABLE_UNIQUE)
+}
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<Attack>()
+            .add_message::<AttackCity>()
+            .add_message::<CityAttacked>()
+            .add_message::<CityStrike>()
+            .add_message::<CityStruck>()
+            .add_message::<UnitCaptured>()
+            .add_systems(
+                Update,
+                (
+                    resolve_attacks.run_if(on_message::<Attack>),
+                    resolve_city_attacks.run_if(on_message::<AttackCity>),
+                    resolve_city_strikes.run_if(on_message::<CityStrike>),
+                )
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(TurnProcessing, reset_city_strikes.in_set(TurnSet::Cities));
+    }
+}
+
+/// Resolve the attacks requested by [`Attack`] messages.
+///
+/// Only units of nations at war with each other can fight, see [`DiplomacyState::is_at_war`].
+/// A melee attacker must be next to the defender and moves to its tile if the defender dies.
+/// A ranged attacker must have the defender within its range and in sight, and takes no damage.
+/// Attacking uses all the movement points of the attacker. Embarked units can't attack, and defend like civilians.
+///
+/// A melee attacker takes the tile of a civilian without a fight. The enemy civilians of the tile a melee attacker
+/// moves to are captured before it moves in, see [`UnitCaptured`], so units of two nations never share a tile.
+///
+/// Melee ships only attack units on water, and melee land units only attack the units on water which are embarked.
+fn resolve_attacks(
+    mut commands: Commands,
+    mut attack: MessageReader<Attack>,
+    mut combat_resolved: MessageWriter<CombatResolved>,
+    mut unit_moved: MessageWriter<UnitMoved>,
+    mut unit_captured: MessageWriter<UnitCaptured>,
+    mut rng: ResMut<GameRng>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    diplomacy: Res<DiplomacyState>,
+    civilizations: Res<Civilizations>,
+    mut query_unit: Query<(
+        Entity,
+        &Unit,
+        &Owner,
+        &mut MapUnit,
+        &Strength,
+        &RangedStrength,
+        &mut Health,
+        &mut Movement,
+        &UnitOrder,
+    )>,
+    query_embarked: Query<(), With<Embarked>>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+
+    for &Attack { attacker, defender } in attack.read() {
+        let Ok(
+            [
+                (
+                    _,
+                    attacker_kind,
+                    &attacker_owner,
+                    attacker_unit,
+                    &Strength(melee_strength),
+                    ranged,
+                    attacker_health,
+                    attacker_movement,
+                    _,
+                ),
+                (
+                    _,
+                    defender_kind,
+                    &defender_owner,
+                    defender_unit,
+                    &Strength(defender_strength),
+                    _,
+                    defender_health,
+                    _,
+                    defender_order,
+                ),
+            ],
+        ) = query_unit.get_many([attacker, defender])
+        else {
+            continue;
+        };
+
+        let from = attacker_unit.tile;
+        let to = defender_unit.tile;
+        let distance = from.distance_to(to, grid);
+        let is_ranged = ranged.strength > 0;
+        let attacker_domain = UnitDomain::of_unit(attacker_kind.name(), ruleset);
+        // Land units don't follow a killed embarked unit onto the water.
+        let stays_on_land = to.is_water(tile_map) && attacker_domain == UnitDomain::Land;
+        let can_reach_defender = is_ranged
+            || match attacker_domain {
+                UnitDomain::Land => !to.is_water(tile_map) || query_embarked.contains(defender),
+                UnitDomain::Water => to.is_water(tile_map),
+                UnitDomain::Air => false,
+            };
+
+        let can_attack = diplomacy.is_at_war(attacker_owner.nation(), defender_owner.nation())
+            && attacker_movement.current > 0.
+            && !query_embarked.contains(attacker)
+            && melee_strength.max(ranged.strength) > 0
+            && can_reach_defender
+            && if is_ranged {
+                distance <= ranged.range && has_line_of_sight(from, to, tile_map, ruleset)
+            } else {
+                distance == 1
+            };
+        if !can_attack {
+            continue;
+        }
+
+        let enemy_civilians: Vec<_> = query_unit
+            .iter()
+            .filter(|(_, unit, owner, map_unit, ..)| {
+                matches!(unit, Unit::Civilian(_))
+                    && map_unit.tile == to
+                    && diplomacy.is_at_war(attacker_owner.nation(), owner.nation())
+            })
+            .map(|(entity, unit, owner, .., movement, _)| {
+                (entity, unit.name().to_owned(), owner.nation(), movement.max)
+            })
+            .collect();
+        let mut capture_civilians = |commands: &mut Commands| {
+            for (civilian, unit_name, previous_nation, max_movement) in &enemy_civilians {
+                let destroyed = !can_be_captured(unit_name, ruleset);
+                if destroyed {
+                    commands.entity(*civilian).despawn();
+                } else {
+                    // The captured unit waits for the next turn to move for its new owner.
+                    commands.entity(*civilian).insert((
+                        attacker_owner,
+                        Movement {
+                            current: 0.,
+                            max: *max_movement,
+                        },
+                        MovePath::default(),
+                        UnitOrder::None,
+                    ));
+                }
+                unit_captured.write(UnitCaptured {
+                    unit: *civilian,
+                    unit_name: unit_name.clone(),
+                    nation: attacker_owner.nation(),
+                    previous_nation: *previous_nation,
+                    tile: to,
+                    destroyed,
+                });
+            }
+        };
+
+        if !is_ranged && !stays_on_land && matches!(defender_kind, Unit::Civilian(_)) {
+            let Ok((.., mut attacker_unit, _, _, _, mut attacker_movement, _)) =
+                query_unit.get_mut(attacker)
+            else {
+                continue;
+            };
+            attacker_movement.current = 0.;
+            capture_civilians(&mut commands);
+            attacker_unit.tile = to;
+            unit_moved.write(UnitMoved {
+                unit: attacker,
+                path: vec![from, to],
+            });
+            continue;
+        }
+
+        let flanking_units = query_unit
+            .iter()
+            .filter(|(_, unit, owner, map_unit, ..)| {
+                matches!(unit, Unit::Military(_))
+                    && owner.nation() == attacker_owner.nation()
+                    && map_unit.tile != from
+                    && map_unit.tile.distance_to(to, grid) == 1
+            })
+            .count();
+
+        let base_strength = if is_ranged {
+            ranged.strength
+        } else {
+            melee_strength
+        };
+        let attack_strength = attack_strength(
+            base_strength,
+            attacker_health,
+            flanking_units,
+            crosses_river(from, to, tile_map),
+            is_ranged,
+            strength_bonus(
+                attacker_kind.name(),
+                true,
+                Opponent::Unit(defender_kind.name()),
+                civilizations.get(attacker_owner.nation()),
+                ruleset,
+            ),
+        );
+        // Civilians and embarked units can't defend themselves.
+        let defense_strength =
+            if matches!(defender_kind, Unit::Civilian(_)) || query_embarked.contains(defender) {
+                0.
+            } else {
+                defense_strength(
+                    defender_strength,
+                    defender_health,
+                    defender_order,
+                    to,
+                    tile_map,
+                    ruleset,
+                    strength_bonus(
+                        defender_kind.name(),
+                        false,
+                        Opponent::Unit(attacker_kind.name()),
+                        civilizations.get(defender_owner.nation()),
+                        ruleset,
+                    ),
+                )
+            };
+
+        let prediction = predict_combat(attack_strength, defense_strength, is_ranged);
+        // As in Civ V, the damage varies randomly by up to 20%.
+        let mut random_damage =
+            |damage: f32| (damage * (0.8 + 0.4 * rng.next_f32())).round() as u32;
+        let damage_to_attacker = random_damage(prediction.damage_to_attacker);
+        let damage_to_defender = random_damage(prediction.damage_to_defender);
+
+        let Ok(
+            [
+                (.., mut attacker_unit, _, _, mut attacker_health, mut attacker_movement, _),
+                (.., mut defender_health, _, _),
+            ],
+        ) = query_unit.get_many_mut([attacker, defender])
+        else {
+            continue;
+        };
+
+        attacker_health.current = attacker_health.current.saturating_sub(damage_to_attacker);
+        defender_health.current = defender_health.current.saturating_sub(damage_to_defender);
+        attacker_movement.current = 0.;
+
+        let attacker_killed = attacker_health.current == 0;
+        let defender_killed = defender_health.current == 0;
+
+        if attacker_killed {
+            commands.entity(attacker).despawn();
+        }
+        if defender_killed {
+            commands.entity(defender).despawn();
+            if !is_ranged && !attacker_killed && !stays_on_land {
+                capture_civilians(&mut commands);
+                attacker_unit.tile = to;
+                unit_moved.write(UnitMoved {
+                    unit: attacker,
+                    path: vec![from, to],
+                });
+            }
+        }
+
+        combat_resolved.write(CombatResolved {
+            attacker,
+            defender,
+            attacker_nation: attacker_owner.nation(),
+            defender_nation: defender_owner.nation(),
+            defender_tile: to,
+            is_ranged,
+            damage_to_attacker,
+            damage_to_defender,
+            attacker_killed,
+            defender_killed,
+        });
+    }
+}
+
+/// Resolve the attacks on cities requested by [`AttackCity`] messages.
+///
+/// The city must belong to a nation at war with the attacker and have no military unit on its tile,
+/// which has to be defeated first. Ranged units must have the city within their range and in sight,
+/// take no damage and can't bring the city below 1 health point. Melee land units must be next to the city
+/// and take damage back. When a melee unit of a civilization brings the city to 0 health points,
+/// it moves in and takes the city, see [`CityCaptured`].
+fn resolve_city_attacks(
+    mut commands: Commands,
+    mut attack_city: MessageReader<AttackCity>,
+    mut city_attacked: MessageWriter<CityAttacked>,
+    mut city_captured: MessageWriter<CityCaptured>,
+    mut unit_moved: MessageWriter<UnitMoved>,
+    mut rng: ResMut<GameRng>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    diplomacy: Res<DiplomacyState>,
+    civilizations: Res<Civilizations>,
+    mut query_unit: Query<
+        (
+            Entity,
+            &Unit,
+            &Owner,
+            &mut MapUnit,
+            &Strength,
+            &RangedStrength,
+            &mut Health,
+            &mut Movement,
+        ),
+        Without<Embarked>,
+    >,
+    mut query_city: Query<(&mut City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+
+    for &AttackCity { attacker, city } in attack_city.read() {
+        let (
+            Ok((
+                _,
+                attacker_kind,
+                &attacker_owner,
+                attacker_unit,
+                &Strength(melee_strength),
+                ranged,
+                attacker_health,
+                attacker_movement,
+            )),
+            Ok((target_city, &city_owner)),
+        ) = (query_unit.get(attacker), query_city.get(city))
+        else {
+            continue;
+        };
+
+        let from = attacker_unit.tile;
+        let to = target_city.tile;
+        let distance = from.distance_to(to, grid);
+        let is_ranged = ranged.strength > 0;
+        let is_defended = query_unit.iter().any(|(_, unit, owner, map_unit, ..)| {
+            map_unit.tile == to
+                && matches!(unit, Unit::Military(_))
+                && owner.nation() != attacker_owner.nation()
+        });
+
+        let can_attack = diplomacy.is_at_war(attacker_owner.nation(), city_owner.nation())
+            && attacker_movement.current > 0.
+            && !is_defended
+            && if is_ranged {
+                distance <= ranged.range && has_line_of_sight(from, to, tile_map, ruleset)
+            } else {
+                distance == 1
+                    && melee_strength > 0
+                    && UnitDomain::of_unit(attacker_kind.name(), ruleset) == UnitDomain::Land
+            };
+        if !can_attack {
+            continue;
+        }
+
+        let base_strength = if is_ranged {
+            ranged.strength
+        } else {
+            melee_strength
+        };
+        let attack_strength = attack_strength(
+            base_strength,
+            attacker_health,
+            0,
+            crosses_river(from, to, tile_map),
+            is_ranged,
+            strength_bonus(
+                attacker_kind.name(),
+                true,
+                Opponent::City,
+                civilizations.get(attacker_owner.nation()),
+                ruleset,
+            ),
+        );
+        let prediction = predict_combat(
+            attack_strength,
+            target_city.combat_strength(ruleset),
+            is_ranged,
+        );
+        // As in Civ V, the damage varies randomly by up to 20%.
+        let mut random_damage =
+            |damage: f32| (damage * (0.8 + 0.4 * rng.next_f32())).round() as u32;
+        let damage_to_attacker = random_damage(prediction.damage_to_attacker);
+        let damage = random_damage(prediction.damage_to_defender);
+
+        let attacker_killed = attacker_health.current <= damage_to_attacker;
+        let can_capture =
+            !is_ranged && !attacker_killed && matches!(attacker_owner, Owner::Civilization(_));
+        let health_left = target_city
+            .max_health(ruleset)
+            .saturating_sub(target_city.damage);
+        let min_health = if can_capture { 0 } else { 1 };
+        let damage_to_city = damage.min(health_left.saturating_sub(min_health));
+        let captured = can_capture && damage_to_city == health_left;
+        // The civilians of the city are taken with it.
+        let units_in_city: Vec<Entity> = query_unit
+            .iter()
+            .filter(|(_, _, owner, map_unit, ..)| {
+                map_unit.tile == to && owner.nation() != attacker_owner.nation()
+            })
+            .map(|(entity, ..)| entity)
+            .collect();
+
+        let Ok((mut target_city, _)) = query_city.get_mut(city) else {
+            continue;
+        };
+        target_city.damage += damage_to_city;
+        let Ok((.., mut attacker_unit, _, _, mut attacker_health, mut attacker_movement)) =
+            query_unit.get_mut(attacker)
+        else {
+            continue;
+        };
+        attacker_health.current = attacker_health.current.saturating_sub(damage_to_attacker);
+        attacker_movement.current = 0.;
+
+        if attacker_killed {
+            commands.entity(attacker).despawn();
+        }
+        if captured {
+            attacker_unit.tile = to;
+            unit_moved.write(UnitMoved {
+                unit: attacker,
+                path: vec![from, to],
+            });
+            for unit in units_in_city {
+                commands.entity(unit).despawn();
+            }
+        }
+
+        city_attacked.write(CityAttacked {
+            attacker,
+            city,
+            attacker_nation: attacker_owner.nation(),
+            city_nation: city_owner.nation(),
+            city_tile: to,
+            is_ranged,
+            damage_to_attacker,
+            damage_to_city,
+            attacker_killed,
+        });
+        if captured {
+            city_captured.write(CityCaptured {
+                city,
+                nation: attacker_owner.nation(),
+                previous_nation: city_owner.nation(),
+                name: target_city.name.clone(),
+                tile: to,
+            });
+        }
+    }
+}
+
+/// Resolve the strikes of cities requested by [`CityStrike`] messages.
+///
+/// A city strikes once per turn a unit of a nation at war with it, within [`CITY_STRIKE_RANGE`] and in sight.
+/// The city takes no damage back, like the ranged attacks of units.
+fn resolve_city_strikes(
+    mut commands: Commands,
+    mut city_strike: MessageReader<CityStrike>,
+    mut city_struck: MessageWriter<CityStruck>,
+    mut rng: ResMut<GameRng>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    diplomacy: Res<DiplomacyState>,
+    civilizations: Res<Civilizations>,
+    mut query_unit: Query<(
+        &Unit,
+        &Owner,
+        &MapUnit,
+        &Strength,
+        &mut Health,
+        &UnitOrder,
+        Has<Embarked>,
+    )>,
+    mut query_city: Query<(&mut City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+
+    for &CityStrike { city, target } in city_strike.read() {
+        let (
+            Ok((mut striking_city, &city_owner)),
+            Ok((
+                target_kind,
+                &target_owner,
+                target_unit,
+                &Strength(strength),
+                mut health,
+                order,
+                is_embarked,
+            )),
+        ) = (query_city.get_mut(city), query_unit.get_mut(target))
+        else {
+            continue;
+        };
+
+        let from = striking_city.tile;
+        let to = target_unit.tile;
+        let can_strike = !striking_city.has_struck
+            && diplomacy.is_at_war(city_owner.nation(), target_owner.nation())
+            && from.distance_to(to, grid) <= CITY_STRIKE_RANGE
+            && has_line_of_sight(from, to, tile_map, ruleset);
+        if !can_strike {
+            continue;
+        }
+
+        // Civilians and embarked units can't defend themselves.
+        let defense_strength = if matches!(target_kind, Unit::Civilian(_)) || is_embarked {
+            0.
+        } else {
+            let strength_bonus = strength_bonus(
+                target_kind.name(),
+                false,
+                Opponent::City,
+                civilizations.get(target_owner.nation()),
+                ruleset,
+            );
+            defense_strength(
+                strength,
+                &health,
+                order,
+                to,
+                tile_map,
+                ruleset,
+                strength_bonus,
+            )
+        };
+        let prediction = predict_city_strike(&striking_city, defense_strength, ruleset);
+        // As in Civ V, the damage varies randomly by up to 20%.
+        let damage_to_target =
+            (prediction.damage_to_defender * (0.8 + 0.4 * rng.next_f32())).round() as u32;
+
+        striking_city.has_struck = true;
+        health.current = health.current.saturating_sub(damage_to_target);
+        let target_killed = health.current == 0;
+        if target_killed {
+            commands.entity(target).despawn();
+        }
+
+        city_struck.write(CityStruck {
+            city,
+            target,
+            city_nation: city_owner.nation(),
+            target_nation: target_owner.nation(),
+            target_tile: to,
+            damage_to_target,
+            target_killed,
+        });
+    }
+}
+
+/// Let every city strike again.
+fn reset_city_strikes(mut query_city: Query<&mut City>) {
+    for mut city in query_city.iter_mut() {
+        if city.has_struck {
+            city.has_struck = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bevy::ecs::{message::Messages, system::RunSystemOnce};
+
+    use super::*;
+    use crate::{
+        civilization::Civilization,
+        pathfinding::tests::{test_map, tile},
+        unit::{MAX_HEALTH, unit_components, unit_kind},
+    };
+
+    /// A world in which a barbarian warrior stands next to the `defenders` of a civilization, given with their health.
+    /// Return the world, the warrior, the defenders and their tile.
+    fn next_to_defenders(defenders: &[(&str, u32)]) -> (World, Entity, Vec<Entity>, Tile) {
+        let ruleset = Arc::new(Ruleset::default());
+        let tile_map = test_map(&["..."]);
+        let from = tile(&tile_map, 0, 0);
+        let to = tile(&tile_map, 1, 0);
+        let nation = (0..Nation::LENGTH)
+            .map(Nation::from_usize)
+            .find(|&nation| nation != Nation::Barbarians)
+            .unwrap();
+
+        let mut world = World::new();
+        world.init_resource::<Messages<Attack>>();
+        world.init_resource::<Messages<CombatResolved>>();
+        world.init_resource::<Messages<UnitMoved>>();
+        world.init_resource::<Messages<UnitCaptured>>();
+        world.init_resource::<DiplomacyState>();
+        world.insert_resource(
+            [nation, Nation::Barbarians]
+                .into_iter()
+                .map(|nation| (nation, Civilization::default()))
+                .collect::<Civilizations>(),
+        );
+        world.insert_resource(GameRng::new(0));
+        world.insert_resource(TileMapResource(tile_map));
+
+        let attacker = world
+            .spawn((
+                unit_components("Warrior", from, &ruleset),
+                Unit::Military("Warrior".to_owned()),
+                Owner::Barbarian,
+            ))
+            .id();
+        let defenders = defenders
+            .iter()
+            .map(|&(unit_name, health)| {
+                world
+                    .spawn((
+                        unit_components(unit_name, to, &ruleset),
+                        unit_kind(unit_name, &ruleset),
+                        Owner::Civilization(nation),
+                    ))
+                    .insert(Health {
+                        current: health,
+                        max: MAX_HEALTH,
+                    })
+                    .id()
+            })
+            .collect();
+        world.insert_resource(RulesetResource(ruleset));
+        (world, attacker, defenders, to)
+    }
+
+    fn attack(world: &mut World, attacker: Entity, defender: Entity) {
+        world
+            .resource_mut::<Messages<Attack>>()
+            .write(Attack { attacker, defender });
+        world.run_system_once(resolve_attacks).unwrap();
+    }
+
+    #[test]
+    fn captures_civilians_without_a_fight() {
+        let (mut world, attacker, defenders, to) = next_to_defenders(&[("Worker", MAX_HEALTH)]);
+        attack(&mut world, attacker, defenders[0]);
+
+        let worker = world.entity(defenders[0]);
+        assert_eq!(worker.get::<Owner>().unwrap().nation(), Nation::Barbarians);
+        assert_eq!(worker.get::<Health>().unwrap().current, MAX_HEALTH);
+        assert_eq!(worker.get::<Movement>().unwrap().current, 0.);
+        assert_eq!(world.get::<MapUnit>(attacker).unwrap().tile, to);
+    }
+
+    #[test]
+    fn destroys_settlers() {
+        let (mut world, attacker, defenders, to) = next_to_defenders(&[("Settler", MAX_HEALTH)]);
+        attack(&mut world, attacker, defenders[0]);
+
+        assert!(world.get_entity(defenders[0]).is_err());
+        assert_eq!(world.get::<MapUnit>(attacker).unwrap().tile, to);
+    }
+
+    #[test]
+    fn captures_civilians_of_the_defeated_unit_before_moving_in() {
+        let (mut world, attacker, defenders, to) =
+            next_to_defenders(&[("Warrior", 1), ("Worker", MAX_HEALTH)]);
+        attack(&mut world, attacker, defenders[0]);
+
+        assert!(world.get_entity(defenders[0]).is_err());
+        assert_eq!(world.get::<MapUnit>(attacker).unwrap().tile, to);
+        let worker = world.entity(defenders[1]);
+        assert_eq!(worker.get::<Owner>().unwrap().nation(), Nation::Barbarians);
+        assert_eq!(worker.get::<MapUnit>().unwrap().tile, to);
+    }
+}
@@ -0,0 +1,227 @@
+//! The effects of the fights: the units hit flash red and shake, and the damage dealt floats up from every unit and
+//! city hit.
+//!
+//! The effects are drawn from [`CombatResolved`], [`CityAttacked`] and [`CityStruck`], and skipped when the player
+//! turns them off in the [`AnimationSettings`].
+
+use bevy::prelude::*;
+use civ_map_generator::tile::Tile;
+
+use crate::{
+    animation::AnimationSettings,
+    assets::AppState,
+    combat::{CityAttacked, CityStruck},
+    game_event::CombatResolved,
+    unit::MapUnit,
+    unit_sprite::UnitMeshes,
+    world_map::WorldTileEntities,
+};
+
+/// How long a unit hit flashes and shakes, in seconds.
+const HIT_DURATION: f32 = 0.4;
+/// The largest angle of the shake, in radians.
+const SHAKE_ANGLE: f32 = 0.25;
+/// How many times a unit hit shakes from side to side.
+const SHAKE_COUNT: f32 = 3.;
+const FLASH_COLOR: Color = Color::srgba(1., 0.15, 0.1, 0.8);
+
+/// How long a damage number floats, in seconds.
+const DAMAGE_NUMBER_DURATION: f32 = 1.2;
+/// How far a damage number floats up, in pixels of the map.
+const DAMAGE_NUMBER_RISE: f32 = 30.;
+const DAMAGE_NUMBER_COLOR: Color = Color::srgb(1., 0.35, 0.3);
+
+/// A unit shaking after being hit.
+#[derive(Component)]
+struct HitShake {
+    elapsed: f32,
+}
+
+/// The red flash over a unit hit, a child of the unit.
+#[derive(Component)]
+struct HitFlash {
+    elapsed: f32,
+}
+
+/// The damage dealt to a unit or a city, floating up from it. A child of the tile of the unit or the city, so it
+/// stays there when the map wraps around or the unit dies.
+#[derive(Component)]
+struct DamageNumber {
+    elapsed: f32,
+    start: Vec3,
+}
+
+/// A unit or a city hit in a fight.
+struct Hit {
+    unit: Option<Entity>,
+    tile: Tile,
+    damage: u32,
+}
+
+pub struct CombatEffectPlugin;
+
+impl Plugin for CombatEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                start_combat_effects,
+                (shake_units_hit, fade_hit_flashes, float_damage_numbers),
+            )
+                .chain()
+                .run_if(
+                    in_state(AppState::GameStart)
+                        .and(resource_exists::<UnitMeshes>)
+                        .and(resource_exists::<WorldTileEntities>),
+                ),
+        );
+    }
+}
+
+/// Flash and shake the units hit, and show the damage dealt to the units and the cities hit.
+fn start_combat_effects(
+    mut commands: Commands,
+    animation_settings: Res<AnimationSettings>,
+    unit_meshes: Res<UnitMeshes>,
+    tile_entities: Res<WorldTileEntities>,
+    mut combat_resolved: MessageReader<CombatResolved>,
+    mut city_attacked: MessageReader<CityAttacked>,
+    mut city_struck: MessageReader<CityStruck>,
+    query_unit: Query<(&MapUnit, &Transform)>,
+) {
+    if animation_settings.skip_combat_effects {
+        combat_resolved.clear();
+        city_attacked.clear();
+        city_struck.clear();
+        return;
+    }
+
+    let mut hits = Vec::new();
+    // The tile of an attacker killed is lost with it, so its damage isn't shown.
+    for combat in combat_resolved.read() {
+        if let Ok((map_unit, _)) = query_unit.get(combat.attacker) {
+            hits.push(Hit {
+                unit: Some(combat.attacker),
+                tile: map_unit.tile,
+                damage: combat.damage_to_attacker,
+            });
+        }
+        hits.push(Hit {
+            unit: Some(combat.defender),
+            tile: combat.defender_tile,
+            damage: combat.damage_to_defender,
+        });
+    }
+    for attack in city_attacked.read() {
+        if let Ok((map_unit, _)) = query_unit.get(attack.attacker) {
+            hits.push(Hit {
+                unit: Some(attack.attacker),
+                tile: map_unit.tile,
+                damage: attack.damage_to_attacker,
+            });
+        }
+        hits.push(Hit {
+            unit: None,
+            tile: attack.city_tile,
+            damage: attack.damage_to_city,
+        });
+    }
+    for strike in city_struck.read() {
+        hits.push(Hit {
+            unit: Some(strike.target),
+            tile: strike.target_tile,
+            damage: strike.damage_to_target,
+        });
+    }
+
+    let icon_size = unit_meshes.icon_size();
+    for hit in hits {
+        // A ranged attacker takes no damage, so it doesn't flash either.
+        if hit.damage == 0 {
+            continue;
+        }
+        // The units killed are already despawned, so their damage floats from the center of their tile.
+        let unit = hit.unit.and_then(|unit| {
+            query_unit
+                .get(unit)
+                .ok()
+                .map(|(_, transform)| (unit, transform))
+        });
+        let start = match unit {
+            Some((unit, transform)) => {
+                commands.entity(unit).try_insert(HitShake { elapsed: 0. });
+                commands.entity(unit).with_child((
+                    HitFlash { elapsed: 0. },
+                    Sprite::from_color(FLASH_COLOR, Vec2::splat(icon_size)),
+                    Transform::from_xyz(0., 0., 2.),
+                ));
+                transform.translation.truncate()
+            }
+            None => Vec2::ZERO,
+        };
+        let Some(&tile_entity) = tile_entities.0.get(&hit.tile) else {
+            continue;
+        };
+        let start = (start + Vec2::Y * icon_size / 2.).extend(40.);
+        commands.entity(tile_entity).with_child((
+            DamageNumber { elapsed: 0., start },
+            Text2d::new(format!("-{}", hit.damage)),
+            TextFont {
+                font_size: 16.,
+                ..default()
+            },
+            TextColor(DAMAGE_NUMBER_COLOR),
+            Transform::from_translation(start),
+        ));
+    }
+}
+
+/// Rock the units hit from side to side, less and less until they stand still again.
+fn shake_units_hit(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query_unit: Query<(Entity, &mut Transform, &mut HitShake)>,
+) {
+    for (entity, mut transform, mut shake) in query_unit.iter_mut() {
+        shake.elapsed += time.delta_secs();
+        let t = (shake.elapsed / HIT_DURATION).min(1.);
+        let angle = SHAKE_ANGLE * (1. - t) * (t * SHAKE_COUNT * std::f32::consts::TAU).sin();
+        transform.rotation = Quat::from_rotation_z(angle);
+        if t >= 1. {
+            commands.entity(entity).try_remove::<HitShake>();
+        }
+    }
+}
+
+fn fade_hit_flashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query_flash: Query<(Entity, &mut Sprite, &mut HitFlash)>,
+) {
+    for (entity, mut sprite, mut flash) in query_flash.iter_mut() {
+        flash.elapsed += time.delta_secs();
+        let t = (flash.elapsed / HIT_DURATION).min(1.);
+        sprite.color = FLASH_COLOR.with_alpha(FLASH_COLOR.alpha() * (1. - t));
+        if t >= 1. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Float the damage numbers up while they fade out.
+fn float_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query_number: Query<(Entity, &mut Transform, &mut TextColor, &mut DamageNumber)>,
+) {
+    for (entity, mut transform, mut color, mut number) in query_number.iter_mut() {
+        number.elapsed += time.delta_secs();
+        let t = (number.elapsed / DAMAGE_NUMBER_DURATION).min(1.);
+        transform.translation = number.start + Vec3::Y * DAMAGE_NUMBER_RISE * t;
+        // Fully visible for the first half, then fading out.
+        color.0 = DAMAGE_NUMBER_COLOR.with_alpha((2. - 2. * t).min(1.));
+        if t >= 1. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::AppState,
+    city::{AnnexCity, ChangeProduction, City, CityProduction, FoundCity, LockTile},
+    civilization::PlayerCivilization,
+    combat::{Attack, AttackCity, CityStrike},
+    deal::{AnswerDeal, Deal, DealItem, ProposeDeal},
+    diplomacy::{DeclareWar, Denounce, MakePeace},
+    espionage::MoveSpy,
+    great_person::UseGreatPerson,
+    network::NetworkSession,
+    pillage::{Pillage, Repair},
+    random_event::ChooseEventOption,
+    road::BuildRoad,
+    specialist::SetSpecialists,
+    technology::ChooseResearch,
+    turn::EndTurn,
+    unit::{MapUnit, MovePath},
+    unit_component::{Owner, Unit, UnitOrder},
+    unit_order::SetUnitOrder,
+    unit_upgrade::UpgradeUnit,
+};
+
+/// A unit identified by its tile and its name instead of its entity, which is different for every player of a network game.
+///
+/// The stacking rules allow only one military and one civilian unit on a tile, so the tile and the name are enough.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct UnitId {
+    pub tile: Tile,
+    pub name: String,
+}
+
+impl UnitId {
+    pub fn new(unit: &Unit, map_unit: &MapUnit) -> Self {
+        Self {
+            tile: map_unit.tile,
+            name: unit.name().to_owned(),
+        }
+    }
+}
+
+/// A request of the local player.
+///
+/// The UI writes these instead of the requests of the game systems, so in a network game every player carries out
+/// the commands of all the players in the same order, see [`crate::network`].
+#[derive(Message, Clone, Debug, Serialize, Deserialize)]
+pub enum PlayerCommand {
+    EndTurn,
+    ChooseResearch {
+        technology: String,
+    },
+    /// Move the unit along `path`, the tiles to go through in order.
+    MoveUnit {
+        unit: UnitId,
+        path: Vec<Tile>,
+    },
+    Attack {
+        attacker: UnitId,
+        defender: UnitId,
+    },
+    /// Attack the city on `city`.
+    AttackCity {
+        attacker: UnitId,
+        city: Tile,
+    },
+    /// Strike `target` with the city on `city`.
+    CityStrike {
+        city: Tile,
+        target: UnitId,
+    },
+    FoundCity {
+        settler: UnitId,
+    },
+    /// Annex the puppet city on `city`.
+    AnnexCity {
+        city: Tile,
+    },
+    /// Let the city on `city` produce `production`, or nothing with `None`.
+    ChangeProduction {
+        city: Tile,
+        production: Option<CityProduction>,
+    },
+    /// Let `count` citizens of the city on `city` work as `specialist`.
+    SetSpecialists {
+        city: Tile,
+        specialist: String,
+        count: u32,
+    },
+    /// Let a citizen of the city on `city` work `tile` until it is unlocked, or unlock it.
+    LockTile {
+        city: Tile,
+        tile: Tile,
+        locked: bool,
+    },
+    UseGreatPerson {
+        unit: UnitId,
+    },
+    UpgradeUnit {
+        unit: UnitId,
+    },
+    SetOrder {
+        unit: UnitId,
+        order: UnitOrder,
+    },
+    Pillage {
+        unit: UnitId,
+    },
+    Repair {
+        worker: UnitId,
+    },
+    BuildRoad {
+        worker: UnitId,
+    },
+    /// Station the spy at index `spy` in the city on `city`, or bring it back with `None`.
+    MoveSpy {
+        spy: usize,
+        city: Option<Tile>,
+    },
+    DeclareWar {
+        target: Nation,
+    },
+    MakePeace {
+        target: Nation,
+    },
+    Denounce {
+        target: Nation,
+    },
+    /// Propose to `target` to give `offered` for `asked`.
+    ProposeDeal {
+        target: Nation,
+        offered: Vec<DealItem>,
+        asked: Vec<DealItem>,
+    },
+    /// Accept or refuse the deal proposed by `from`.
+    AnswerDeal {
+        from: Nation,
+        accept: bool,
+    },
+    /// Choose the choice at index `choice` of the pending random event `event`.
+    ChooseEventOption {
+        event: String,
+        choice: usize,
+    },
+}
+
+/// Written when the command of `nation` should be carried out.
+///
+/// Ending the turn is not carried out here: the turn ends once every player ended it.
+#[derive(Message)]
+pub struct ExecuteCommand {
+    pub nation: Nation,
+    pub command: PlayerCommand,
+}
+
+pub struct CommandPlugin;
+
+impl Plugin for CommandPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<PlayerCommand>()
+            .add_message::<ExecuteCommand>()
+            .add_systems(
+                Update,
+                (
+                    execute_local_commands.run_if(not(resource_exists::<NetworkSession>)),
+                    execute_commands.run_if(on_message::<ExecuteCommand>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            );
+    }
+}
+
+/// Without a network game the commands of the player are carried out at once.
+fn execute_local_commands(
+    mut player_command: MessageReader<PlayerCommand>,
+    mut execute_command: MessageWriter<ExecuteCommand>,
+    mut end_turn: MessageWriter<EndTurn>,
+    player_civilization: Res<PlayerCivilization>,
+) {
+    for command in player_command.read() {
+        if let PlayerCommand::EndTurn = command {
+            end_turn.write(EndTurn);
+        } else {
+            execute_command.write(ExecuteCommand {
+                nation: player_civilization.0,
+                command: command.clone(),
+            });
+        }
+    }
+}
+
+/// Turn the commands into the requests of the game systems.
+///
+/// Commands about a unit are ignored if the unit doesn't exist or doesn't belong to the nation giving the command.
+fn execute_commands(
+    mut execute_command: MessageReader<ExecuteCommand>,
+    mut choose_research: MessageWriter<ChooseResearch>,
+    (mut attack, mut attack_city, mut city_strike): (
+        MessageWriter<Attack>,
+        MessageWriter<AttackCity>,
+        MessageWriter<CityStrike>,
+    ),
+    mut found_city: MessageWriter<FoundCity>,
+    (mut annex_city, mut change_production, mut set_specialists, mut lock_tile): (
+        MessageWriter<AnnexCity>,
+        MessageWriter<ChangeProduction>,
+        MessageWriter<SetSpecialists>,
+        MessageWriter<LockTile>,
+    ),
+    (mut use_great_person, mut upgrade_unit, mut choose_event_option): (
+        MessageWriter<UseGreatPerson>,
+        MessageWriter<UpgradeUnit>,
+        MessageWriter<ChooseEventOption>,
+    ),
+    mut set_unit_order: MessageWriter<SetUnitOrder>,
+    (mut pillage, mut repair, mut build_road): (
+        MessageWriter<Pillage>,
+        MessageWriter<Repair>,
+        MessageWriter<BuildRoad>,
+    ),
+    mut move_spy: MessageWriter<MoveSpy>,
+    (mut declare_war, mut make_peace, mut denounce, mut propose_deal, mut answer_deal): (
+        MessageWriter<DeclareWar>,
+        MessageWriter<MakePeace>,
+        MessageWriter<Denounce>,
+        MessageWriter<ProposeDeal>,
+        MessageWriter<AnswerDeal>,
+    ),
+    query_unit: Query<(Entity, &Unit, &MapUnit, &Owner)>,
+    query_city: Query<(Entity, &City, &Owner)>,
+    mut query_move_path: Query<(&mut MovePath, &mut UnitOrder)>,
+) {
+    for ExecuteCommand { nation, command } in execute_command.read() {
+        let nation = *nation;
+        let own_unit = |unit_id: &UnitId| {
+            find_unit(unit_id, &query_unit)
+                .filter(|&(_, owner)| owner == nation)
+                .map(|(entity, _)| entity)
+        };
+
+        match command {
+            PlayerCommand::EndTurn => {}
+            PlayerCommand::ChooseResearch { technology } => {
+                choose_research.write(ChooseResearch {
+                    nation,
+                    technology: technology.clone(),
+                });
+            }
+            PlayerCommand::MoveUnit { unit, path } => {
+                if let Some(entity) = own_unit(unit)
+                    && let Ok((mut move_path, mut order)) = query_move_path.get_mut(entity)
+                {
+                    move_path.0 = path.iter().copied().collect();
+                    // Moving a unit by hand stops it exploring on its own.
+                    *order = UnitOrder::None;
+                }
+            }
+            PlayerCommand::Attack { attacker, defender } => {
+                if let (Some(attacker), Some((defender, _))) =
+                    (own_unit(attacker), find_unit(defender, &query_unit))
+                {
+                    attack.write(Attack { attacker, defender });
+                }
+            }
+            PlayerCommand::AttackCity { attacker, city } => {
+                if let Some(attacker) = own_unit(attacker)
+                    && let Some((city, ..)) = query_city
+                        .iter()
+                        .find(|(_, target_city, _)| target_city.tile == *city)
+                {
+                    attack_city.write(AttackCity { attacker, city });
+                }
+            }
+            PlayerCommand::CityStrike { city, target } => {
+                if let Some((city, ..)) = query_city.iter().find(|(_, striking_city, owner)| {
+                    striking_city.tile == *city && owner.nation() == nation
+                }) && let Some((target, _)) = find_unit(target, &query_unit)
+                {
+                    city_strike.write(CityStrike { city, target });
+                }
+            }
+            PlayerCommand::FoundCity { settler } => {
+                if let Some(settler) = own_unit(settler) {
+                    found_city.write(FoundCity { settler });
+                }
+            }
+            PlayerCommand::AnnexCity { city } => {
+                if let Some((city, ..)) = query_city.iter().find(|(_, target_city, owner)| {
+                    target_city.tile == *city && owner.nation() == nation
+                }) {
+                    annex_city.write(AnnexCity { city });
+                }
+            }
+            PlayerCommand::ChangeProduction { city, production } => {
+                if let Some((city, ..)) = query_city.iter().find(|(_, target_city, owner)| {
+                    target_city.tile == *city && owner.nation() == nation
+                }) {
+                    change_production.write(ChangeProduction {
+                        city,
+                        production: production.clone(),
+                    });
+                }
+            }
+            PlayerCommand::SetSpecialists {
+                city,
+                specialist,
+                count,
+            } => {
+                if let Some((city, ..)) = query_city.iter().find(|(_, target_city, owner)| {
+                    target_city.tile == *city && owner.nation() == nation
+                }) {
+                    set_specialists.write(SetSpecialists {
+                        city,
+                        specialist: specialist.clone(),
+                        count: *count,
+                    });
+                }
+            }
+            &PlayerCommand::LockTile { city, tile, locked } => {
+                if let Some((city, ..)) = query_city.iter().find(|(_, target_city, owner)| {
+                    target_city.tile == city && owner.nation() == nation
+                }) {
+                    lock_tile.write(LockTile { city, tile, locked });
+                }
+            }
+            PlayerCommand::UseGreatPerson { unit } => {
+                if let Some(unit) = own_unit(unit) {
+                    use_great_person.write(UseGreatPerson { unit });
+                }
+            }
+            PlayerCommand::UpgradeUnit { unit } => {
+                if let Some(unit) = own_unit(unit) {
+                    upgrade_unit.write(UpgradeUnit { unit });
+                }
+            }
+            &PlayerCommand::SetOrder { ref unit, order } => {
+                if let Some(unit) = own_unit(unit) {
+                    set_unit_order.write(SetUnitOrder { unit, order });
+                }
+            }
+            PlayerCommand::Pillage { unit } => {
+                if let Some(unit) = own_unit(unit) {
+                    pillage.write(Pillage { unit });
+                }
+            }
+            PlayerCommand::Repair { worker } => {
+                if let Some(worker) = own_unit(worker) {
+                    repair.write(Repair { worker });
+                }
+            }
+            PlayerCommand::BuildRoad { worker } => {
+                if let Some(worker) = own_unit(worker) {
+                    build_road.write(BuildRoad { worker });
+                }
+            }
+            &PlayerCommand::MoveSpy { spy, city } => {
+                move_spy.write(MoveSpy { nation, spy, city });
+            }
+            &PlayerCommand::DeclareWar { target } => {
+                declare_war.write(DeclareWar { nation, target });
+            }
+            &PlayerCommand::MakePeace { target } => {
+                make_peace.write(MakePeace { nation, target });
+            }
+            &PlayerCommand::Denounce { target } => {
+                denounce.write(Denounce { nation, target });
+            }
+            PlayerCommand::ProposeDeal {
+                target,
+                offered,
+                asked,
+            } => {
+                propose_deal.write(ProposeDeal {
+                    deal: Deal {
+                        from: nation,
+                        to: *target,
+                        offered: offered.clone(),
+                        asked: asked.clone(),
+                    },
+                });
+            }
+            &PlayerCommand::AnswerDeal { from, accept } => {
+                answer_deal.write(AnswerDeal {
+                    nation,
+                    from,
+                    accept,
+                });
+            }
+            &PlayerCommand::ChooseEventOption { ref event, choice } => {
+                choose_event_option.write(ChooseEventOption {
+                    nation,
+                    event: event.clone(),
+                    choice,
+                });
+            }
+        }
+    }
+}
+
+/// The entity and the nation of the unit.
+fn find_unit(
+    unit_id: &UnitId,
+    query_unit: &Query<(Entity, &Unit, &MapUnit, &Owner)>,
+) -> Option<(Entity, Nation)> {
+    query_unit
+        .iter()
+        .find(|(_, unit, map_unit, _)| map_unit.tile == unit_id.tile && unit.name() == unit_id.name)
+        .map(|(entity, _, _, owner)| (entity, owner.nation()))
+}
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset, tile::Tile, tile_map::TileMap};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    city::{City, CityCaptured},
+    diplomacy::DiplomacyState,
+    economy::collect_gold,
+    game_event::{CityFounded, TileChanged},
+    improvement::TileImprovementLayer,
+    turn::{TurnProcessing, TurnSet},
+    unit_component::Owner,
+};
+
+/// The unique of the buildings which connect their city to the other cities with one over water, e.g. the Harbor.
+const CONNECTS_OVER_WATER_UNIQUE: &str = "Connects trade routes over water";
+/// The gold of a connection is `CAPITAL_POPULATION_GOLD * capital population + CITY_POPULATION_GOLD * city population - 1`,
+/// as in Unciv.
+const CAPITAL_POPULATION_GOLD: f32 = 0.15;
+const CITY_POPULATION_GOLD: f32 = 1.1;
+
+/// The tiles of the cities connected to the capital of their nation, the capitals included,
+/// and the trade routes connecting them.
+///
+/// Updated every turn and whenever a road, a city or its owner changes.
+#[derive(Resource, Default)]
+pub struct CityConnections {
+    connected: HashSet<Tile>,
+    routes: Vec<TradeRoute>,
+}
+
+impl CityConnections {
+    /// Return `true` if the city on `city_tile` is connected to its capital, or is the capital.
+    pub fn is_connected(&self, city_tile: Tile) -> bool {
+        self.connected.contains(&city_tile)
+    }
+
+    /// The trade routes from every connected city to its capital, the capitals excluded.
+    pub fn routes(&self) -> &[TradeRoute] {
+        &self.routes
+    }
+}
+
+/// The shortest way a city is connected to the capital of `nation`, through roads, cities and water.
+pub struct TradeRoute {
+    pub nation: Nation,
+    /// The tiles from the city to the capital, both included.
+    pub tiles: Vec<Tile>,
+}
+
+/// The gold the connection of `city` to `capital` gives every turn.
+pub fn connection_gold(city: &City, capital: &City) -> f32 {
+    (CAPITAL_POPULATION_GOLD * capital.population as f32
+        + CITY_POPULATION_GOLD * city.population as f32
+        - 1.)
+        .max(0.)
+}
+
+pub struct ConnectionPlugin;
+
+impl Plugin for ConnectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CityConnections>()
+            .add_systems(
+                Update,
+                update_city_connections
+                    .run_if(
+                        on_message::<TileChanged>
+                            .or(on_message::<CityFounded>)
+                            .or(on_message::<CityCaptured>),
+                    )
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                update_city_connections
+                    .in_set(TurnSet::Economy)
+                    .before(collect_gold),
+            );
+    }
+}
+
+/// Return `true` if the city has a building connecting it to other cities over water.
+fn connects_over_water(city: &City, ruleset: &Ruleset) -> bool {
+    city.buildings.iter().any(|building| {
+        ruleset.buildings[building]
+            .uniques
+            .iter()
+            .any(|unique| unique == CONNECTS_OVER_WATER_UNIQUE)
+    })
+}
+
+/// Find the cities connected to the capital of every nation.
+///
+/// Starting from the capital, the connection goes through the cities of the nation and the working roads
+/// outside the borders of its enemies. Cities with a Harbor also connect through the water tiles
+/// to the other cities of the nation with a Harbor.
+fn update_city_connections(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    diplomacy: Res<DiplomacyState>,
+    improvement_layer: Res<TileImprovementLayer>,
+    mut city_connections: ResMut<CityConnections>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+
+    // The nation owning every tile, to keep the connections out of enemy lands.
+    let tile_owners: HashMap<Tile, Nation> = query_city
+        .iter()
+        .flat_map(|(city, owner)| {
+            city.owned_tiles
+                .iter()
+                .map(move |&tile| (tile, owner.nation()))
+        })
+        .collect();
+
+    let mut connected = HashSet::new();
+    let mut routes = Vec::new();
+    for (capital, owner) in query_city.iter().filter(|(city, _)| city.is_capital) {
+        let nation = owner.nation();
+        // The cities of the nation, with whether they connect over water.
+        let cities: HashMap<Tile, bool> = query_city
+            .iter()
+            .filter(|(_, city_owner)| city_owner.nation() == nation)
+            .map(|(city, _)| (city.tile, connects_over_water(city, ruleset)))
+            .collect();
+
+        let reachable = reachable_tiles(capital.tile, tile_map, |from, to| {
+            let is_enemy_land = tile_owners
+                .get(&to)
+                .is_some_and(|&tile_owner| diplomacy.is_at_war(nation, tile_owner));
+            match (cities.get(&from), cities.get(&to)) {
+                // Ships sail from a harbor city, and dock at another one.
+                _ if from.is_water(tile_map) && to.is_water(tile_map) => true,
+                (Some(&harbor), _) if to.is_water(tile_map) => harbor,
+                (_, Some(&harbor)) if from.is_water(tile_map) => harbor,
+                _ if from.is_water(tile_map) || to.is_water(tile_map) => false,
+                (_, Some(_)) => true,
+                _ => !is_enemy_land && improvement_layer.has_working_road(to),
+            }
+        });
+        for &city in cities.keys().filter(|tile| reachable.contains_key(tile)) {
+            connected.insert(city);
+            if city == capital.tile {
+                continue;
+            }
+            let mut tiles = vec![city];
+            let mut tile = city;
+            while tile != capital.tile {
+                tile = reachable[&tile];
+                tiles.push(tile);
+            }
+            routes.push(TradeRoute { nation, tiles });
+        }
+    }
+    city_connections.connected = connected;
+    city_connections.routes = routes;
+}
+
+/// The tiles reachable from `start`, going from a tile to a neighbor when `can_step` allows it,
+/// with the tile they are reached from. `start` is reached from itself.
+fn reachable_tiles(
+    start: Tile,
+    tile_map: &TileMap,
+    can_step: impl Fn(Tile, Tile) -> bool,
+) -> HashMap<Tile, Tile> {
+    let grid = tile_map.world_grid.grid;
+    let mut reachable = HashMap::from([(start, start)]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(tile) = queue.pop_front() {
+        for neighbor in tile.neighbor_tiles(grid) {
+            if !reachable.contains_key(&neighbor) && can_step(tile, neighbor) {
+                reachable.insert(neighbor, tile);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    reachable
+}
@@ -2,10 +2,17 @@ use bevy::{
     asset::{Asset, Handle},
     color::LinearRgba,
     image::Image,
+    math::Vec2,
+    mesh::{Mesh, MeshVertexAttribute, MeshVertexBufferLayoutRef, VertexFormat},
     reflect::TypePath,
-    render::render_resource::AsBindGroup,
+    render::{
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderType, SpecializedMeshPipelineError,
+        },
+        storage::ShaderStorageBuffer,
+    },
     shader::ShaderRef,
-    sprite_render::{AlphaMode2d, Material2d},
+    sprite_render::{AlphaMode2d, Material2d, Material2dKey},
 };
 
 #[derive(AsBindGroup, Asset, TypePath, Debug, Clone)]
@@ -17,6 +24,9 @@ pub struct ColorReplaceMaterial {
     #[texture(2)]
     #[sampler(3)]
     pub texture: Handle<Image>,
+    /// The pattern drawn over the outer color, see [`crate::accessibility::FlagPattern::shader_value`].
+    #[uniform(4)]
+    pub pattern: u32,
 }
 
 impl Material2d for ColorReplaceMaterial {
@@ -28,3 +38,57 @@ impl Material2d for ColorReplaceMaterial {
         AlphaMode2d::Blend
     }
 }
+
+/// The index of the [`TileInstance`] a vertex of a [`TileInstanceMaterial`] mesh belongs to.
+pub const ATTRIBUTE_TILE_INSTANCE: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_TileInstance", 982_451_653, VertexFormat::Uint32);
+
+/// A tile drawn by a [`TileInstanceMaterial`].
+#[derive(ShaderType, Clone, Copy, Debug, Default)]
+pub struct TileInstance {
+    /// The center of the tile, relative to the mesh.
+    pub position: Vec2,
+    /// The layer of the tile texture in [`TileInstanceMaterial::textures`].
+    pub texture_index: u32,
+    /// The colors replacing the red and the green of the texture like [`ColorReplaceMaterial`].
+    /// The texture keeps its own colors if `outer_color` is transparent.
+    pub inner_color: LinearRgba,
+    pub outer_color: LinearRgba,
+}
+
+/// Draw many tiles sharing a mesh in a single draw call.
+///
+/// Every vertex of the mesh is a corner of a tile relative to its center, with [`ATTRIBUTE_TILE_INSTANCE`]
+/// pointing to the [`TileInstance`] holding the position and the texture of the tile.
+#[derive(AsBindGroup, Asset, TypePath, Debug, Clone)]
+pub struct TileInstanceMaterial {
+    #[storage(0, read_only)]
+    pub instances: Handle<ShaderStorageBuffer>,
+    #[texture(1, dimension = "2d_array")]
+    #[sampler(2)]
+    pub textures: Handle<Image>,
+}
+
+impl Material2d for TileInstanceMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/tile_instance.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/tile_instance.wgsl".into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(1),
+            ATTRIBUTE_TILE_INSTANCE.at_shader_location(2),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
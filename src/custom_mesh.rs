@@ -1,39 +1,40 @@
 use bevy::{
     asset::RenderAssetUsages,
-    math::Vec3,
+    math::Vec2,
     mesh::{Indices, Mesh, PrimitiveTopology},
 };
 use civ_map_generator::grid::hex_grid::{Hex, HexGrid};
 
-pub fn line_mesh(start: Vec3, end: Vec3, width: f32) -> Mesh {
-    // Calculate direction vector from start to end points
-    let direction = end - start;
-    let _length = direction.length();
-    let normalized_direction = direction.normalize();
-
-    // Compute perpendicular vector to create the line width
-    let perpendicular =
-        Vec3::new(-normalized_direction.y, normalized_direction.x, 0.0).normalize() * width / 2.0;
-
-    // Create four vertices for the rectangle representing the line
-    let vertices = vec![
-        start + perpendicular,
-        start - perpendicular,
-        end + perpendicular,
-        end - perpendicular,
-    ];
-
-    let uvs = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
-
-    let indices = Indices::U32(vec![0, 1, 2, 2, 1, 3]);
+/// A mesh of lines going through their points, every point with its own width.
+///
+/// The width is measured across the line, perpendicular to the direction of the line at that point,
+/// so the consecutive segments of a line join without gaps.
+pub fn polyline_mesh(polylines: &[Vec<(Vec2, f32)>]) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for polyline in polylines.iter().filter(|polyline| polyline.len() >= 2) {
+        let first_index = vertices.len() as u32;
+        for (index, &(point, width)) in polyline.iter().enumerate() {
+            let previous = polyline[index.saturating_sub(1)].0;
+            let next = polyline[(index + 1).min(polyline.len() - 1)].0;
+            let direction = (next - previous).normalize_or_zero();
+            let perpendicular = direction.perp() * width / 2.0;
+            vertices.push((point + perpendicular).extend(0.0));
+            vertices.push((point - perpendicular).extend(0.0));
+        }
+        for segment in 0..polyline.len() as u32 - 1 {
+            let start = first_index + segment * 2;
+            indices.extend([start, start + 1, start + 2, start + 2, start + 1, start + 3]);
+        }
+    }
 
     let mut mesh = Mesh::new(
         PrimitiveTopology::TriangleList,
         RenderAssetUsages::default(),
     );
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-    mesh.with_inserted_indices(indices)
+    mesh.with_inserted_indices(Indices::U32(indices))
 }
 
 pub fn hex_mesh(grid: &HexGrid) -> Mesh {
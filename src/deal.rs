@@ -0,0 +1,361 @@
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource,
+    assets::AppState,
+    civilization::{Civilization, Civilizations},
+    diplomacy::{DiplomacyState, WarDeclared},
+    era::civilization_era,
+    game_event::TechResearched,
+    technology::can_research,
+    turn::{TurnProcessing, TurnSet},
+};
+
+/// The unique of the technology which lets a civilization sign research agreements, Philosophy in Civ V.
+const RESEARCH_AGREEMENT_UNIQUE: &str = "Enables Research agreements";
+/// A research agreement gives its science after 30 turns, as in Civ V.
+const RESEARCH_AGREEMENT_TURNS: u32 = 30;
+/// Every gold paid for a research agreement gives 2 science to each side when it ends.
+const RESEARCH_AGREEMENT_SCIENCE_PER_GOLD: f32 = 2.;
+
+/// Whether technologies can be traded in deals. The Civ V rulesets don't allow it, so it is off by default,
+/// but mods can turn it on.
+#[derive(Resource, Clone, Copy, Default)]
+pub struct TechTradingRule(pub bool);
+
+/// Something given in a [`Deal`].
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DealItem {
+    /// Both sides pay [`research_agreement_cost`] now and get science after [`RESEARCH_AGREEMENT_TURNS`].
+    /// It is the same on both sides of a deal.
+    ResearchAgreement,
+    /// Only allowed with the [`TechTradingRule`].
+    Technology(String),
+    Gold(u32),
+}
+
+/// A deal proposed by `from` to `to`: `from` gives `offered` and gets `asked` in return.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Deal {
+    pub from: Nation,
+    pub to: Nation,
+    pub offered: Vec<DealItem>,
+    pub asked: Vec<DealItem>,
+}
+
+impl Deal {
+    fn items(&self) -> impl Iterator<Item = (&DealItem, Nation, Nation)> {
+        let offered = self.offered.iter().map(|item| (item, self.from, self.to));
+        let asked = self.asked.iter().map(|item| (item, self.to, self.from));
+        offered.chain(asked)
+    }
+}
+
+/// A research agreement in progress between two nations.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResearchAgreement {
+    pub nations: [Nation; 2],
+    pub turns_left: u32,
+    /// The science each side gets when the agreement ends.
+    pub science: f32,
+}
+
+/// The deals waiting for an answer and the research agreements in progress.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct Deals {
+    pub proposed: Vec<Deal>,
+    pub research_agreements: Vec<ResearchAgreement>,
+}
+
+impl Deals {
+    pub fn has_research_agreement(&self, a: Nation, b: Nation) -> bool {
+        self.research_agreements
+            .iter()
+            .any(|agreement| agreement.nations.contains(&a) && agreement.nations.contains(&b))
+    }
+}
+
+/// The gold both sides pay for a research agreement: the cost of the latest of their eras.
+pub fn research_agreement_cost(a: &Civilization, b: &Civilization, ruleset: &Ruleset) -> f32 {
+    [a, b]
+        .iter()
+        .map(|civilization| {
+            ruleset.eras[&civilization_era(civilization, ruleset)].research_agreement_cost
+        })
+        .max()
+        .unwrap_or_default() as f32
+}
+
+/// Return `true` if the civilization knows a technology enabling research agreements.
+fn can_sign_research_agreements(civilization: &Civilization, ruleset: &Ruleset) -> bool {
+    civilization
+        .researched_technologies
+        .iter()
+        .any(|technology| {
+            ruleset.technologies[technology]
+                .uniques
+                .iter()
+                .any(|unique| unique == RESEARCH_AGREEMENT_UNIQUE)
+        })
+}
+
+/// Return `true` if every item of the deal can be given by its side.
+///
+/// The nations must be at peace. Research agreements need both sides to know a technology enabling them,
+/// to have the gold for them, and not to have one already. Technologies can only be traded with the
+/// [`TechTradingRule`], to a nation which can research them.
+pub fn is_deal_valid(
+    deal: &Deal,
+    tech_trading: TechTradingRule,
+    deals: &Deals,
+    diplomacy: &DiplomacyState,
+    civilizations: &Civilizations,
+    ruleset: &Ruleset,
+) -> bool {
+    if deal.from == deal.to || diplomacy.is_at_war(deal.from, deal.to) {
+        return false;
+    }
+    let (from, to) = (civilizations.get(deal.from), civilizations.get(deal.to));
+    let agreement_cost = research_agreement_cost(from, to, ruleset);
+    let has_agreement = deal
+        .items()
+        .any(|(item, ..)| *item == DealItem::ResearchAgreement);
+    let gold_needed = |nation: Nation| {
+        let gold: u32 = deal
+            .items()
+            .filter_map(|(item, giver, _)| match item {
+                DealItem::Gold(gold) if giver == nation => Some(*gold),
+                _ => None,
+            })
+            .sum();
+        gold as f32 + if has_agreement { agreement_cost } else { 0. }
+    };
+
+    let items_valid = deal.items().all(|(item, giver, receiver)| match item {
+        DealItem::ResearchAgreement => {
+            !deals.has_research_agreement(giver, receiver)
+                && can_sign_research_agreements(from, ruleset)
+                && can_sign_research_agreements(to, ruleset)
+        }
+        DealItem::Technology(technology) => {
+            tech_trading.0
+                && civilizations
+                    .get(giver)
+                    .researched_technologies
+                    .contains(technology)
+                && can_research(technology, civilizations.get(receiver), ruleset)
+        }
+        DealItem::Gold(_) => true,
+    });
+    items_valid && from.gold >= gold_needed(deal.from) && to.gold >= gold_needed(deal.to)
+}
+
+/// Request from `from` to propose a deal to `to`. It replaces the deal `from` proposed to `to` before, if any.
+#[derive(Message)]
+pub struct ProposeDeal {
+    pub deal: Deal,
+}
+
+/// Answer of `nation` to the deal proposed by `from`.
+#[derive(Message)]
+pub struct AnswerDeal {
+    pub nation: Nation,
+    pub from: Nation,
+    pub accept: bool,
+}
+
+/// Written when a deal is proposed to `deal.to`.
+#[derive(Message)]
+pub struct DealProposed {
+    pub deal: Deal,
+}
+
+/// Written when a deal has been accepted and carried out.
+#[derive(Message)]
+pub struct DealAccepted {
+    pub deal: Deal,
+}
+
+/// Written when a research agreement of `nation` with `partner` ends, giving `science` to `nation`.
+#[derive(Message)]
+pub struct ResearchAgreementEnded {
+    pub nation: Nation,
+    pub partner: Nation,
+    pub science: f32,
+}
+
+pub struct DealPlugin;
+
+impl Plugin for DealPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Deals>()
+            .init_resource::<TechTradingRule>()
+            .add_message::<ProposeDeal>()
+            .add_message::<AnswerDeal>()
+            .add_message::<DealProposed>()
+            .add_message::<DealAccepted>()
+            .add_message::<ResearchAgreementEnded>()
+            .add_systems(
+                Update,
+                (
+                    propose_deals.run_if(on_message::<ProposeDeal>),
+                    answer_deals.run_if(on_message::<AnswerDeal>),
+                    cancel_research_agreements_at_war.run_if(on_message::<WarDeclared>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                end_research_agreements.in_set(TurnSet::Diplomacy),
+            );
+    }
+}
+
+fn propose_deals(
+    mut propose_deal: MessageReader<ProposeDeal>,
+    mut deal_proposed: MessageWriter<DealProposed>,
+    ruleset: Res<RulesetResource>,
+    tech_trading: Res<TechTradingRule>,
+    diplomacy: Res<DiplomacyState>,
+    civilizations: Res<Civilizations>,
+    mut deals: ResMut<Deals>,
+) {
+    for ProposeDeal { deal } in propose_deal.read() {
+        if deal.offered.is_empty() && deal.asked.is_empty()
+            || !is_deal_valid(
+                deal,
+                *tech_trading,
+                &deals,
+                &diplomacy,
+                &civilizations,
+                &ruleset.0,
+            )
+        {
+            continue;
+        }
+        deals
+            .proposed
+            .retain(|proposed| (proposed.from, proposed.to) != (deal.from, deal.to));
+        deals.proposed.push(deal.clone());
+        deal_proposed.write(DealProposed { deal: deal.clone() });
+    }
+}
+
+/// Carry out the accepted deals if they are still valid, and drop the others.
+fn answer_deals(
+    mut answer_deal: MessageReader<AnswerDeal>,
+    mut deal_accepted: MessageWriter<DealAccepted>,
+    mut tech_researched: MessageWriter<TechResearched>,
+    ruleset: Res<RulesetResource>,
+    tech_trading: Res<TechTradingRule>,
+    diplomacy: Res<DiplomacyState>,
+    mut civilizations: ResMut<Civilizations>,
+    mut deals: ResMut<Deals>,
+) {
+    let ruleset = &ruleset.0;
+
+    for &AnswerDeal {
+        nation,
+        from,
+        accept,
+    } in answer_deal.read()
+    {
+        let Some(index) = deals
+            .proposed
+            .iter()
+            .position(|deal| deal.from == from && deal.to == nation)
+        else {
+            continue;
+        };
+        let deal = deals.proposed.remove(index);
+        if !accept
+            || !is_deal_valid(
+                &deal,
+                *tech_trading,
+                &deals,
+                &diplomacy,
+                &civilizations,
+                ruleset,
+            )
+        {
+            continue;
+        }
+
+        let agreement_cost =
+            research_agreement_cost(civilizations.get(from), civilizations.get(nation), ruleset);
+        for (item, giver, receiver) in deal.items() {
+            match item {
+                DealItem::ResearchAgreement => {
+                    if deals.has_research_agreement(giver, receiver) {
+                        continue;
+                    }
+                    civilizations.get_mut(giver).gold -= agreement_cost;
+                    civilizations.get_mut(receiver).gold -= agreement_cost;
+                    deals.research_agreements.push(ResearchAgreement {
+                        nations: [giver, receiver],
+                        turns_left: RESEARCH_AGREEMENT_TURNS,
+                        science: agreement_cost * RESEARCH_AGREEMENT_SCIENCE_PER_GOLD,
+                    });
+                }
+                DealItem::Technology(technology) => {
+                    civilizations.get_mut(receiver).learn_technology(technology);
+                    tech_researched.write(TechResearched {
+                        nation: receiver,
+                        technology: technology.clone(),
+                    });
+                }
+                &DealItem::Gold(gold) => {
+                    civilizations.get_mut(giver).gold -= gold as f32;
+                    civilizations.get_mut(receiver).gold += gold as f32;
+                }
+            }
+        }
+        deal_accepted.write(DealAccepted { deal });
+    }
+}
+
+/// A war ends the research agreement between the two nations, and the gold paid for it is lost.
+fn cancel_research_agreements_at_war(
+    mut war_declared: MessageReader<WarDeclared>,
+    mut deals: ResMut<Deals>,
+) {
+    for &WarDeclared { nation, target } in war_declared.read() {
+        deals.research_agreements.retain(|agreement| {
+            !(agreement.nations.contains(&nation) && agreement.nations.contains(&target))
+        });
+        deals.proposed.retain(|deal| {
+            !((deal.from, deal.to) == (nation, target) || (deal.from, deal.to) == (target, nation))
+        });
+    }
+}
+
+/// Count down the research agreements, and give their science to both sides when they end.
+///
+/// The science is added to the next research, like the overflow of a finished technology.
+fn end_research_agreements(
+    mut research_agreement_ended: MessageWriter<ResearchAgreementEnded>,
+    mut civilizations: ResMut<Civilizations>,
+    mut deals: ResMut<Deals>,
+) {
+    for agreement in deals.research_agreements.iter_mut() {
+        agreement.turns_left = agreement.turns_left.saturating_sub(1);
+        if agreement.turns_left > 0 {
+            continue;
+        }
+        let [a, b] = agreement.nations;
+        for (nation, partner) in [(a, b), (b, a)] {
+            civilizations.get_mut(nation).science_overflow += agreement.science;
+            research_agreement_ended.write(ResearchAgreementEnded {
+                nation,
+                partner,
+                science: agreement.science,
+            });
+        }
+    }
+    deals
+        .research_agreements
+        .retain(|agreement| agreement.turns_left > 0);
+}
@@ -0,0 +1,227 @@
+//! The debug overlays for developers, cycled with the `F3` key:
+//!
+//! - the offset coordinate, the hex coordinate and the index of every tile, written on the tile.
+//! - the area of every tile, colored by its area id and written on the tile, to check the areas after the terrain
+//!   changes, e.g. when a lake or a natural wonder is placed.
+//! - the river edges of every tile, with an arrow in the direction of the flow and the id of their river, to check
+//!   the rivers across the wrapping edge of the map and on maps of flat hexagons.
+//!
+//! The overlays are children of the [`WorldTile`] entities, so they are only drawn on the tiles shown around the
+//! camera and follow the tiles when the map wraps around.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use civ_map_generator::{
+    grid::hex_grid::{Hex, HexGrid},
+    tile::Tile,
+    tile_map::RiverEdge,
+};
+
+use crate::{
+    TileMapResource,
+    assets::AppState,
+    custom_mesh::{hex_mesh, polyline_mesh},
+    grid::tile_to_hex,
+    world_map::{WorldTile, WorldTileEntities},
+};
+
+/// The debug overlay shown.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DebugOverlay {
+    #[default]
+    Hidden,
+    Coordinates,
+    Areas,
+    Rivers,
+}
+
+impl DebugOverlay {
+    fn next(self) -> Self {
+        match self {
+            Self::Hidden => Self::Coordinates,
+            Self::Coordinates => Self::Areas,
+            Self::Areas => Self::Rivers,
+            Self::Rivers => Self::Hidden,
+        }
+    }
+}
+
+/// A part of the debug overlay on a tile.
+#[derive(Component)]
+struct TileDebugOverlay;
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>().add_systems(
+            Update,
+            (toggle_debug_overlay_on_key, update_tile_debug_overlays)
+                .chain()
+                .run_if(in_state(AppState::GameStart).and(resource_exists::<WorldTileEntities>)),
+        );
+    }
+}
+
+fn toggle_debug_overlay_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut debug_overlay: ResMut<DebugOverlay>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        *debug_overlay = debug_overlay.next();
+    }
+}
+
+/// The color of an area, the same for an area id in every game so an area keeps its color while the overlay is
+/// redrawn. The hues of consecutive ids are spread by the golden angle, so neighboring areas rarely look alike.
+fn area_color(area_id: usize) -> Color {
+    let hue = (area_id as f32 * 137.508).rem_euclid(360.);
+    Color::hsla(hue, 0.75, 0.5, 0.5)
+}
+
+const RIVER_ARROW_COLOR: Color = Color::srgb(1., 0.3, 0.8);
+const RIVER_ARROW_WIDTH: f32 = 2.;
+
+/// The arrows of the river edges of a tile, relative to the center of the tile, and the ids of their rivers
+/// with where to write them.
+///
+/// An arrow goes along its edge in the direction of the flow, pulled a little toward the center of the tile
+/// so it isn't hidden by the river itself.
+fn river_arrows(river_edges: &[(usize, &RiverEdge)], grid: HexGrid) -> (Mesh, Vec<(usize, Vec2)>) {
+    let mut polylines = Vec::new();
+    let mut labels = Vec::new();
+    for &(river_id, river_edge) in river_edges {
+        let [start_corner_direction, end_corner_direction] =
+            river_edge.start_and_end_corner_directions(grid);
+        let start = Vec2::from(grid.layout.corner(Hex::new(0, 0), start_corner_direction)) * 0.75;
+        let end = Vec2::from(grid.layout.corner(Hex::new(0, 0), end_corner_direction)) * 0.75;
+        let from = start.lerp(end, 0.15);
+        let to = start.lerp(end, 0.85);
+        let back = (from - to).normalize_or_zero() * (to - from).length() * 0.3;
+
+        polylines.push(vec![(from, RIVER_ARROW_WIDTH), (to, RIVER_ARROW_WIDTH)]);
+        for angle in [-0.5f32, 0.5] {
+            let head = to + Vec2::from_angle(angle).rotate(back);
+            polylines.push(vec![(to, RIVER_ARROW_WIDTH), (head, RIVER_ARROW_WIDTH)]);
+        }
+        labels.push((river_id, start.lerp(end, 0.5) * 0.7));
+    }
+    (polyline_mesh(&polylines), labels)
+}
+
+/// Draw the overlay on every tile when the overlay or the map changes, e.g. when a lake or a natural wonder
+/// changes the areas, and on the new tiles of a new map or a loaded game.
+fn update_tile_debug_overlays(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    debug_overlay: Res<DebugOverlay>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut area_materials: Local<HashMap<usize, Handle<ColorMaterial>>>,
+    mut river_material: Local<Option<Handle<ColorMaterial>>>,
+    mut hex: Local<Option<Handle<Mesh>>>,
+    query_tile: Query<(Entity, &WorldTile)>,
+    query_new_tile: Query<(Entity, &WorldTile), Added<WorldTile>>,
+    query_overlay: Query<Entity, With<TileDebugOverlay>>,
+) {
+    let redraw = debug_overlay.is_changed() || map.is_changed();
+    if redraw {
+        for overlay in query_overlay.iter() {
+            commands.entity(overlay).despawn();
+        }
+    }
+    if *debug_overlay == DebugOverlay::Hidden {
+        return;
+    }
+
+    let tile_map = &map.0;
+    let grid = tile_map.world_grid.grid;
+    if map.is_changed() {
+        *hex = None;
+    }
+    let hex = hex
+        .get_or_insert_with(|| meshes.add(hex_mesh(&grid)))
+        .clone();
+    let mut tile_river_edges: HashMap<Tile, Vec<(usize, &RiverEdge)>> = HashMap::new();
+    if *debug_overlay == DebugOverlay::Rivers {
+        for (river_id, river) in tile_map.river_list.iter().enumerate() {
+            for river_edge in river {
+                tile_river_edges
+                    .entry(river_edge.tile)
+                    .or_default()
+                    .push((river_id, river_edge));
+            }
+        }
+    }
+    let river_material = river_material
+        .get_or_insert_with(|| color_materials.add(RIVER_ARROW_COLOR))
+        .clone();
+    let tiles: Vec<(Entity, &WorldTile)> = if redraw {
+        query_tile.iter().collect()
+    } else {
+        query_new_tile.iter().collect()
+    };
+    for (entity, &WorldTile(tile)) in tiles {
+        let label = match *debug_overlay {
+            DebugOverlay::Hidden => unreachable!(),
+            DebugOverlay::Coordinates => {
+                let [x, y] = tile.to_offset(grid).to_array();
+                let [q, r] = tile_to_hex(tile, grid);
+                format!("({x}, {y})\n[{q}, {r}]\n#{}", tile.index())
+            }
+            DebugOverlay::Areas => {
+                let area_id = tile.area_id(tile_map);
+                let material = area_materials
+                    .entry(area_id)
+                    .or_insert_with(|| color_materials.add(area_color(area_id)))
+                    .clone();
+                commands.entity(entity).with_child((
+                    TileDebugOverlay,
+                    Mesh2d(hex.clone()),
+                    MeshMaterial2d(material),
+                    // Above the cities and the units.
+                    Transform::from_xyz(0., 0., 29.),
+                ));
+                format!("area {area_id}")
+            }
+            DebugOverlay::Rivers => {
+                let Some(river_edges) = tile_river_edges.get(&tile) else {
+                    continue;
+                };
+                let (arrows, labels) = river_arrows(river_edges, grid);
+                commands.entity(entity).with_child((
+                    TileDebugOverlay,
+                    Mesh2d(meshes.add(arrows)),
+                    MeshMaterial2d(river_material.clone()),
+                    Transform::from_xyz(0., 0., 29.),
+                ));
+                for (river_id, position) in labels {
+                    commands.entity(entity).with_child((
+                        TileDebugOverlay,
+                        Text2d::new(river_id.to_string()),
+                        TextFont {
+                            font_size: 9.,
+                            ..default()
+                        },
+                        TextColor(RIVER_ARROW_COLOR),
+                        Transform::from_translation(position.extend(30.)),
+                    ));
+                }
+                continue;
+            }
+        };
+        commands.entity(entity).with_child((
+            TileDebugOverlay,
+            Text2d::new(label),
+            TextFont {
+                font_size: 10.,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            TextLayout::new_with_justify(Justify::Center),
+            // Above the cities, the units and the other tile labels.
+            Transform::from_xyz(0., 0., 30.),
+        ));
+    }
+}
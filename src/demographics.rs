@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use civ_map_generator::nation::Nation;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    city::City,
+    civilization::Civilizations,
+    technology::science_per_turn,
+    turn::{TurnManager, TurnProcessing, TurnSet},
+    unit_component::{Owner, Strength, Unit},
+    yields::TileYields,
+};
+
+/// The score of every city.
+const SCORE_PER_CITY: u32 = 10;
+/// The score of every citizen.
+const SCORE_PER_CITIZEN: u32 = 3;
+/// The score of every tile owned by a city.
+const SCORE_PER_TILE: u32 = 1;
+/// The score of every researched technology.
+const SCORE_PER_TECHNOLOGY: u32 = 4;
+/// The score of every adopted policy and opened policy branch.
+const SCORE_PER_POLICY: u32 = 4;
+
+/// The statistics of a civilization at the end of a turn.
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct TurnStatistics {
+    pub turn: u32,
+    pub city_count: u32,
+    pub population: u32,
+    /// The number of tiles owned by the cities of the civilization.
+    pub land_area: u32,
+    /// The sum of the strength of the military units.
+    pub military_strength: u32,
+    /// The science per turn of the civilization.
+    pub science: f32,
+    pub gold: f32,
+    pub technology_count: u32,
+    pub policy_count: u32,
+}
+
+impl TurnStatistics {
+    /// The score of the civilization, close to the score of Civ V without wonders.
+    pub fn score(&self) -> u32 {
+        self.city_count * SCORE_PER_CITY
+            + self.population * SCORE_PER_CITIZEN
+            + self.land_area * SCORE_PER_TILE
+            + self.technology_count * SCORE_PER_TECHNOLOGY
+            + self.policy_count * SCORE_PER_POLICY
+    }
+}
+
+/// The statistics of every civilization for every turn since the start of the game, for the demographics,
+/// the graphs and the replays.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct History(HashMap<Nation, Vec<TurnStatistics>>);
+
+impl History {
+    /// The statistics of the civilization, one per turn, from the oldest to the newest.
+    pub fn of_nation(&self, nation: Nation) -> &[TurnStatistics] {
+        self.0.get(&nation).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn latest(&self, nation: Nation) -> Option<&TurnStatistics> {
+        self.of_nation(nation).last()
+    }
+
+    /// The civilizations with their latest score, from the highest to the lowest.
+    pub fn ranking(&self) -> Vec<(Nation, u32)> {
+        let mut ranking: Vec<_> = self
+            .0
+            .keys()
+            .filter_map(|&nation| Some((nation, self.latest(nation)?.score())))
+            .collect();
+        ranking.sort_by(|(a, a_score), (b, b_score)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| a.as_str().cmp(b.as_str()))
+        });
+        ranking
+    }
+}
+
+pub struct DemographicsPlugin;
+
+impl Plugin for DemographicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>().add_systems(
+            TurnProcessing,
+            record_statistics.in_set(TurnSet::Statistics),
+        );
+    }
+}
+
+/// Add the statistics of every civilization to the [`History`]. City-states and barbarians are not recorded.
+fn record_statistics(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    turn_manager: Res<TurnManager>,
+    tile_yields: Res<TileYields>,
+    civilizations: Res<Civilizations>,
+    mut history: ResMut<History>,
+    query_city: Query<(&City, &Owner)>,
+    query_unit: Query<(&Unit, &Strength, &Owner)>,
+) {
+    for &nation in map.0.starting_tile_and_civilization.values() {
+        let civilization = civilizations.get(nation);
+        let cities: Vec<_> = query_city
+            .iter()
+            .filter(|(_, owner)| owner.nation() == nation)
+            .map(|(city, _)| city)
+            .collect();
+        let military_strength = query_unit
+            .iter()
+            .filter(|(unit, _, owner)| {
+                owner.nation() == nation && matches!(unit, Unit::Military(_))
+            })
+            .map(|(_, &Strength(strength), _)| strength)
+            .sum();
+
+        let statistics = TurnStatistics {
+            turn: turn_manager.turn,
+            city_count: cities.len() as u32,
+            population: cities.iter().map(|city| city.population).sum(),
+            land_area: cities
+                .iter()
+                .map(|city| city.owned_tiles.len() as u32)
+                .sum(),
+            military_strength,
+            science: science_per_turn(nation, civilization, &query_city, &tile_yields, &ruleset.0),
+            gold: civilization.gold,
+            technology_count: civilization.researched_technologies.len() as u32,
+            policy_count: civilization.adopted_policies.len() as u32,
+        };
+        history.0.entry(nation).or_default().push(statistics);
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use civ_map_generator::nation::Nation;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::AppState,
+    city::City,
+    civilization::Civilizations,
+    save::map_as_pairs,
+    turn::{TurnProcessing, TurnSet},
+    unit::MapUnit,
+    unit_component::Owner,
+    visibility::VisibilityLayer,
+};
+
+/// A war lasts at least this many turns before peace can be made.
+const MIN_WAR_TURNS: u32 = 10;
+/// A peace treaty forbids declaring war again for this many turns.
+const PEACE_TREATY_TURNS: u32 = 10;
+/// A denouncement lasts this many turns, as in Civ V.
+const DENOUNCEMENT_TURNS: u32 = 50;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum DiplomaticStatus {
+    /// Every nation starts at peace with the others.
+    #[default]
+    Peace,
+    War,
+}
+
+/// The relation between two nations, the same from both sides.
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct Relation {
+    pub status: DiplomaticStatus,
+    /// The turns left before the status can change again, see [`MIN_WAR_TURNS`] and [`PEACE_TREATY_TURNS`].
+    pub turns_left_before_change: u32,
+}
+
+/// The war and peace between every pair of nations, and the denouncements.
+///
+/// Barbarians are always at war with everyone, so they have no relations here.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct DiplomacyState {
+    #[serde(with = "map_as_pairs")]
+    relations: HashMap<(Nation, Nation), Relation>,
+    /// The turns left in every denouncement, indexed by the denouncing nation and the denounced one.
+    #[serde(with = "map_as_pairs")]
+    denouncements: HashMap<(Nation, Nation), u32>,
+    /// The pairs of nations which have met, once a unit or a city of one was seen by the other.
+    #[serde(default)]
+    met: HashSet<(Nation, Nation)>,
+}
+
+/// The key of the relation between two nations, which doesn't depend on their order.
+fn pair(a: Nation, b: Nation) -> (Nation, Nation) {
+    if a.as_str() <= b.as_str() {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+impl DiplomacyState {
+    pub fn relation(&self, a: Nation, b: Nation) -> Relation {
+        self.relations.get(&pair(a, b)).copied().unwrap_or_default()
+    }
+
+    pub fn is_at_war(&self, a: Nation, b: Nation) -> bool {
+        if a == b {
+            return false;
+        }
+        a == Nation::Barbarians
+            || b == Nation::Barbarians
+            || self.relation(a, b).status == DiplomaticStatus::War
+    }
+
+    pub fn can_declare_war(&self, nation: Nation, target: Nation) -> bool {
+        let relation = self.relation(nation, target);
+        !self.is_at_war(nation, target)
+            && nation != target
+            && relation.turns_left_before_change == 0
+    }
+
+    pub fn can_make_peace(&self, nation: Nation, target: Nation) -> bool {
+        let relation = self.relation(nation, target);
+        relation.status == DiplomaticStatus::War && relation.turns_left_before_change == 0
+    }
+
+    /// Return `true` if `nation` currently denounces `target`.
+    pub fn has_denounced(&self, nation: Nation, target: Nation) -> bool {
+        self.denouncements.contains_key(&(nation, target))
+    }
+
+    /// Return `true` if `a` and `b` have met, see [`meet_nations`].
+    pub fn has_met(&self, a: Nation, b: Nation) -> bool {
+        a == b || self.met.contains(&pair(a, b))
+    }
+
+    fn set_status(&mut self, a: Nation, b: Nation, status: DiplomaticStatus, cooldown: u32) {
+        self.relations.insert(
+            pair(a, b),
+            Relation {
+                status,
+                turns_left_before_change: cooldown,
+            },
+        );
+    }
+}
+
+/// Request from `nation` to declare war on `target`.
+#[derive(Message)]
+pub struct DeclareWar {
+    pub nation: Nation,
+    pub target: Nation,
+}
+
+/// Request from `nation` to make peace with `target`.
+#[derive(Message)]
+pub struct MakePeace {
+    pub nation: Nation,
+    pub target: Nation,
+}
+
+/// Request from `nation` to denounce `target`.
+#[derive(Message)]
+pub struct Denounce {
+    pub nation: Nation,
+    pub target: Nation,
+}
+
+/// Written when `nation` declares war on `target`.
+#[derive(Message)]
+pub struct WarDeclared {
+    pub nation: Nation,
+    pub target: Nation,
+}
+
+/// Written when `nation` makes peace with `target`.
+#[derive(Message)]
+pub struct PeaceMade {
+    pub nation: Nation,
+    pub target: Nation,
+}
+
+/// Written when `nation` denounces `target`.
+#[derive(Message)]
+pub struct Denounced {
+    pub nation: Nation,
+    pub target: Nation,
+}
+
+pub struct DiplomacyPlugin;
+
+impl Plugin for DiplomacyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DiplomacyState>()
+            .add_message::<DeclareWar>()
+            .add_message::<MakePeace>()
+            .add_message::<Denounce>()
+            .add_message::<WarDeclared>()
+            .add_message::<PeaceMade>()
+            .add_message::<Denounced>()
+            .add_systems(
+                Update,
+                (
+                    declare_war.run_if(on_message::<DeclareWar>),
+                    make_peace.run_if(on_message::<MakePeace>),
+                    denounce.run_if(on_message::<Denounce>),
+                    meet_nations.run_if(resource_exists_and_changed::<VisibilityLayer>),
+                )
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                count_down_diplomacy.in_set(TurnSet::Diplomacy),
+            );
+    }
+}
+
+fn declare_war(
+    mut declare_war: MessageReader<DeclareWar>,
+    mut war_declared: MessageWriter<WarDeclared>,
+    mut diplomacy: ResMut<DiplomacyState>,
+) {
+    for &DeclareWar { nation, target } in declare_war.read() {
+        if !diplomacy.can_declare_war(nation, target) {
+            continue;
+        }
+        diplomacy.set_status(nation, target, DiplomaticStatus::War, MIN_WAR_TURNS);
+        war_declared.write(WarDeclared { nation, target });
+    }
+}
+
+fn make_peace(
+    mut make_peace: MessageReader<MakePeace>,
+    mut peace_made: MessageWriter<PeaceMade>,
+    mut diplomacy: ResMut<DiplomacyState>,
+) {
+    for &MakePeace { nation, target } in make_peace.read() {
+        if !diplomacy.can_make_peace(nation, target) {
+            continue;
+        }
+        diplomacy.set_status(nation, target, DiplomaticStatus::Peace, PEACE_TREATY_TURNS);
+        peace_made.write(PeaceMade { nation, target });
+    }
+}
+
+/// Denounce the target, unless it is already denounced by the same nation.
+fn denounce(
+    mut denounce: MessageReader<Denounce>,
+    mut denounced: MessageWriter<Denounced>,
+    mut diplomacy: ResMut<DiplomacyState>,
+) {
+    for &Denounce { nation, target } in denounce.read() {
+        if nation == target || diplomacy.has_denounced(nation, target) {
+            continue;
+        }
+        diplomacy
+            .denouncements
+            .insert((nation, target), DENOUNCEMENT_TURNS);
+        denounced.write(Denounced { nation, target });
+    }
+}
+
+/// Let the nations meet the nations whose units or cities they see.
+fn meet_nations(
+    visibility_layer: Res<VisibilityLayer>,
+    civilizations: Res<Civilizations>,
+    mut diplomacy: ResMut<DiplomacyState>,
+    query_unit: Query<(&Owner, &MapUnit)>,
+    query_city: Query<(&Owner, &City)>,
+) {
+    let nations: Vec<Nation> = civilizations
+        .iter()
+        .map(|(nation, _)| nation)
+        .filter(|&nation| nation != Nation::Barbarians)
+        .collect();
+    let sightings = query_unit
+        .iter()
+        .map(|(owner, map_unit)| (owner.nation(), map_unit.tile))
+        .chain(
+            query_city
+                .iter()
+                .map(|(owner, city)| (owner.nation(), city.tile)),
+        );
+
+    let mut new_meetings = HashSet::new();
+    for (owner, tile) in sightings {
+        if owner == Nation::Barbarians {
+            continue;
+        }
+        for &nation in &nations {
+            if !diplomacy.has_met(nation, owner) && visibility_layer.is_visible(nation, tile) {
+                new_meetings.insert(pair(nation, owner));
+            }
+        }
+    }
+    // Only touch the state when someone met, so its change detection stays meaningful.
+    if !new_meetings.is_empty() {
+        diplomacy.met.extend(new_meetings);
+    }
+}
+
+/// Count down the turns before the relations can change again, and end the expired denouncements.
+fn count_down_diplomacy(mut diplomacy: ResMut<DiplomacyState>) {
+    for relation in diplomacy.relations.values_mut() {
+        relation.turns_left_before_change = relation.turns_left_before_change.saturating_sub(1);
+    }
+    diplomacy.denouncements.retain(|_, turns_left| {
+        *turns_left -= 1;
+        *turns_left > 0
+    });
+}
@@ -0,0 +1,496 @@
+//! The diplomacy screen: the civilizations and the city-states the player has met, their relation with the player,
+//! and the actions of the player toward them. It is toggled with the `F4` key or the diplomacy button.
+
+use bevy::{
+    ecs::system::SystemParam,
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+use civ_map_generator::nation::Nation;
+
+use crate::{
+    RulesetResource,
+    assets::AppState,
+    city_state::{CityStateRelationship, CityStates},
+    civilization::{Civilizations, PlayerCivilization},
+    command::PlayerCommand,
+    deal::{Deal, DealItem, Deals, TechTradingRule, is_deal_valid, research_agreement_cost},
+    diplomacy::{DiplomacyState, DiplomaticStatus},
+};
+
+/// Request to open the diplomacy screen, or to close it if it is open.
+#[derive(Message)]
+pub struct ToggleDiplomacyScreen;
+
+/// The window of the diplomacy screen.
+#[derive(Component)]
+pub struct DiplomacyScreen;
+
+/// The button opening the [`DiplomacyScreen`].
+#[derive(Component)]
+pub struct DiplomacyScreenButton;
+
+/// A button of the [`DiplomacyScreen`] sending this command.
+#[derive(Component)]
+struct DiplomacyButton(PlayerCommand);
+
+pub struct DiplomacyScreenPlugin;
+
+impl Plugin for DiplomacyScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ToggleDiplomacyScreen>()
+            .add_systems(OnEnter(AppState::GameStart), setup_diplomacy_button)
+            .add_systems(
+                Update,
+                (
+                    toggle_diplomacy_screen_on_key,
+                    toggle_diplomacy_screen.run_if(on_message::<ToggleDiplomacyScreen>),
+                    refresh_diplomacy_screen.run_if(
+                        resource_changed::<DiplomacyState>
+                            .or(resource_changed::<Deals>)
+                            .or(resource_changed::<CityStates>)
+                            .or(resource_changed::<Civilizations>),
+                    ),
+                    close_diplomacy_screen_on_escape,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            );
+    }
+}
+
+fn setup_diplomacy_button(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                top: Val::Px(50.0),
+                width: Val::Auto,
+                height: Val::Auto,
+                border: UiRect::all(Val::Px(2.0)),
+                ..Default::default()
+            },
+            BackgroundColor(Color::BLACK),
+            BorderColor::all(Color::WHITE),
+            Text("Diplomacy".to_string()),
+            DiplomacyScreenButton,
+        ))
+        .observe(
+            |click: On<Pointer<Click>>,
+             mut toggle_diplomacy_screen: MessageWriter<ToggleDiplomacyScreen>| {
+                if matches!(click.button, PointerButton::Primary) {
+                    toggle_diplomacy_screen.write(ToggleDiplomacyScreen);
+                }
+            },
+        );
+}
+
+fn toggle_diplomacy_screen_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut toggle_diplomacy_screen: MessageWriter<ToggleDiplomacyScreen>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        toggle_diplomacy_screen.write(ToggleDiplomacyScreen);
+    }
+}
+
+fn toggle_diplomacy_screen(
+    mut commands: Commands,
+    mut toggle_diplomacy_screen: MessageReader<ToggleDiplomacyScreen>,
+    diplomacy_view: DiplomacyView,
+    query_screen: Query<Entity, With<DiplomacyScreen>>,
+) {
+    // Toggling twice in the same frame does nothing.
+    if toggle_diplomacy_screen.read().count().is_multiple_of(2) {
+        return;
+    }
+    if query_screen.is_empty() {
+        spawn_diplomacy_screen(&mut commands, &diplomacy_view);
+    } else {
+        for screen in query_screen.iter() {
+            commands.entity(screen).despawn();
+        }
+    }
+}
+
+/// Show the changes of the relations on the open screen, e.g. a war declared by another civilization.
+fn refresh_diplomacy_screen(
+    mut commands: Commands,
+    diplomacy_view: DiplomacyView,
+    query_screen: Query<Entity, With<DiplomacyScreen>>,
+) {
+    for screen in query_screen.iter() {
+        commands.entity(screen).despawn();
+        spawn_diplomacy_screen(&mut commands, &diplomacy_view);
+    }
+}
+
+fn close_diplomacy_screen_on_escape(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    query_screen: Query<Entity, With<DiplomacyScreen>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        for screen in query_screen.iter() {
+            commands.entity(screen).despawn();
+        }
+    }
+}
+
+/// What the diplomacy screen shows.
+#[derive(SystemParam)]
+struct DiplomacyView<'w> {
+    ruleset: Res<'w, RulesetResource>,
+    player_civilization: Res<'w, PlayerCivilization>,
+    civilizations: Res<'w, Civilizations>,
+    city_states: Res<'w, CityStates>,
+    diplomacy: Res<'w, DiplomacyState>,
+    deals: Res<'w, Deals>,
+    tech_trading: Res<'w, TechTradingRule>,
+}
+
+/// A line of the diplomacy screen: the name and the relation of a nation, and the buttons of the actions toward it.
+struct NationEntry {
+    nation: Nation,
+    status: String,
+    actions: Vec<(String, PlayerCommand)>,
+}
+
+impl DiplomacyView<'_> {
+    /// The civilizations and the city-states met by the player, sorted by name.
+    fn met_nations(&self) -> (Vec<Nation>, Vec<Nation>) {
+        let player = self.player_civilization.0;
+        let mut met: Vec<Nation> = self
+            .civilizations
+            .iter()
+            .map(|(nation, _)| nation)
+            .filter(|&nation| {
+                nation != player
+                    && nation != Nation::Barbarians
+                    && self.diplomacy.has_met(player, nation)
+            })
+            .collect();
+        met.sort_by_key(|nation| nation.as_str());
+        met.into_iter()
+            .partition(|nation| !self.city_states.0.contains_key(nation))
+    }
+
+    /// The war or the peace with `nation`, and the turns before it can change.
+    fn status(&self, nation: Nation) -> String {
+        let player = self.player_civilization.0;
+        let relation = self.diplomacy.relation(player, nation);
+        let mut status = match relation.status {
+            DiplomaticStatus::War => "At war".to_owned(),
+            DiplomaticStatus::Peace => "At peace".to_owned(),
+        };
+        if relation.turns_left_before_change > 0 {
+            status.push_str(&format!(
+                " ({} turns before it can change)",
+                relation.turns_left_before_change
+            ));
+        }
+        status
+    }
+
+    /// Declaring war or making peace with `nation`, when the relation can change.
+    fn war_and_peace_actions(&self, nation: Nation) -> Vec<(String, PlayerCommand)> {
+        let player = self.player_civilization.0;
+        let mut actions = Vec::new();
+        if self.diplomacy.can_declare_war(player, nation) {
+            actions.push((
+                "Declare war".to_owned(),
+                PlayerCommand::DeclareWar { target: nation },
+            ));
+        }
+        if self.diplomacy.can_make_peace(player, nation) {
+            actions.push((
+                "Propose peace".to_owned(),
+                PlayerCommand::MakePeace { target: nation },
+            ));
+        }
+        actions
+    }
+
+    fn civilization_entry(&self, nation: Nation) -> NationEntry {
+        let player = self.player_civilization.0;
+        let ruleset = &self.ruleset.0;
+        let mut status = self.status(nation);
+        if self.diplomacy.has_denounced(player, nation) {
+            status.push_str("\nDenounced by you");
+        }
+        if self.diplomacy.has_denounced(nation, player) {
+            status.push_str("\nDenounces you");
+        }
+        if self.deals.has_research_agreement(player, nation) {
+            status.push_str("\nResearch agreement in progress");
+        }
+
+        let mut actions = self.war_and_peace_actions(nation);
+        if !self.diplomacy.is_at_war(player, nation)
+            && !self.diplomacy.has_denounced(player, nation)
+        {
+            actions.push((
+                "Denounce".to_owned(),
+                PlayerCommand::Denounce { target: nation },
+            ));
+        }
+        let research_agreement = Deal {
+            from: player,
+            to: nation,
+            offered: vec![DealItem::ResearchAgreement],
+            asked: vec![DealItem::ResearchAgreement],
+        };
+        if is_deal_valid(
+            &research_agreement,
+            *self.tech_trading,
+            &self.deals,
+            &self.diplomacy,
+            &self.civilizations,
+            ruleset,
+        ) {
+            let cost = research_agreement_cost(
+                self.civilizations.get(player),
+                self.civilizations.get(nation),
+                ruleset,
+            );
+            actions.push((
+                format!("Trade: research agreement ({cost} gold)"),
+                PlayerCommand::ProposeDeal {
+                    target: nation,
+                    offered: research_agreement.offered,
+                    asked: research_agreement.asked,
+                },
+            ));
+        }
+        // The deal this civilization proposed to the player, waiting for an answer.
+        if let Some(deal) = self
+            .deals
+            .proposed
+            .iter()
+            .find(|deal| deal.from == nation && deal.to == player)
+        {
+            status.push_str(&format!(
+                "\nProposes: {} for {}",
+                deal_items_label(&deal.offered),
+                deal_items_label(&deal.asked)
+            ));
+            actions.push((
+                "Accept".to_owned(),
+                PlayerCommand::AnswerDeal {
+                    from: nation,
+                    accept: true,
+                },
+            ));
+            actions.push((
+                "Refuse".to_owned(),
+                PlayerCommand::AnswerDeal {
+                    from: nation,
+                    accept: false,
+                },
+            ));
+        }
+
+        NationEntry {
+            nation,
+            status,
+            actions,
+        }
+    }
+
+    fn city_state_entry(&self, nation: Nation) -> NationEntry {
+        let player = self.player_civilization.0;
+        let mut status = self.status(nation);
+        if let Some(city_state) = self.city_states.0.get(&nation) {
+            let relationship = match city_state.relationship(player) {
+                CityStateRelationship::Neutral => "Neutral",
+                CityStateRelationship::Friend => "Friend",
+                CityStateRelationship::Ally => "Ally",
+            };
+            status.push_str(&format!(
+                "\n{relationship}, influence {:.0}",
+                city_state.influence(player)
+            ));
+        }
+
+        NationEntry {
+            nation,
+            status,
+            actions: self.war_and_peace_actions(nation),
+        }
+    }
+}
+
+fn deal_items_label(items: &[DealItem]) -> String {
+    if items.is_empty() {
+        return "nothing".to_owned();
+    }
+    items
+        .iter()
+        .map(|item| match item {
+            DealItem::ResearchAgreement => "a research agreement".to_owned(),
+            DealItem::Technology(technology) => technology.clone(),
+            DealItem::Gold(gold) => format!("{gold} gold"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn spawn_diplomacy_screen(commands: &mut Commands, diplomacy_view: &DiplomacyView) {
+    let (civilizations, city_states) = diplomacy_view.met_nations();
+    let sections = [
+        (
+            "Civilizations",
+            civilizations
+                .into_iter()
+                .map(|nation| diplomacy_view.civilization_entry(nation))
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "City-states",
+            city_states
+                .into_iter()
+                .map(|nation| diplomacy_view.city_state_entry(nation))
+                .collect(),
+        ),
+    ];
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.),
+                top: Val::Px(90.),
+                width: Val::Px(360.),
+                max_height: Val::Percent(80.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.),
+                padding: UiRect::all(Val::Px(12.)),
+                border: UiRect::all(Val::Px(2.)),
+                overflow: Overflow::clip_y(),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.9)),
+            BorderColor::all(Color::WHITE),
+            DiplomacyScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        align_self: AlignSelf::FlexEnd,
+                        padding: UiRect::horizontal(Val::Px(4.)),
+                        border: UiRect::all(Val::Px(1.)),
+                        ..default()
+                    },
+                    BorderColor::all(Color::WHITE),
+                    Text("Close".to_owned()),
+                    TextFont {
+                        font_size: 14.,
+                        ..default()
+                    },
+                ))
+                .observe(close_diplomacy_screen_on_click);
+
+            for (title, entries) in sections {
+                parent.spawn((
+                    Text(title.to_owned()),
+                    TextFont {
+                        font_size: 16.,
+                        ..default()
+                    },
+                    Pickable::IGNORE,
+                ));
+                if entries.is_empty() {
+                    parent.spawn((
+                        Text("None met yet".to_owned()),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                        Pickable::IGNORE,
+                    ));
+                }
+                for entry in entries {
+                    let nation_info = &diplomacy_view.ruleset.0.nations[entry.nation.as_str()];
+                    let [red, green, blue] = nation_info.inner_color;
+                    parent.spawn((
+                        Text(entry.nation.as_str().to_owned()),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        TextColor(Color::srgb_u8(red, green, blue)),
+                        Pickable::IGNORE,
+                    ));
+                    parent.spawn((
+                        Text(entry.status),
+                        TextFont {
+                            font_size: 12.,
+                            ..default()
+                        },
+                        Pickable::IGNORE,
+                    ));
+                    if entry.actions.is_empty() {
+                        continue;
+                    }
+                    parent
+                        .spawn((
+                            Node {
+                                flex_wrap: FlexWrap::Wrap,
+                                column_gap: Val::Px(4.),
+                                row_gap: Val::Px(4.),
+                                ..default()
+                            },
+                            Pickable::IGNORE,
+                        ))
+                        .with_children(|row| {
+                            for (label, command) in entry.actions {
+                                row.spawn((
+                                    Node {
+                                        padding: UiRect::horizontal(Val::Px(4.)),
+                                        border: UiRect::all(Val::Px(1.)),
+                                        ..default()
+                                    },
+                                    BorderColor::all(Color::WHITE),
+                                    Text(label),
+                                    TextFont {
+                                        font_size: 12.,
+                                        ..default()
+                                    },
+                                    DiplomacyButton(command),
+                                ))
+                                .observe(send_diplomacy_command_on_click);
+                            }
+                        });
+                }
+            }
+        });
+}
+
+fn send_diplomacy_command_on_click(
+    click: On<Pointer<Click>>,
+    query_button: Query<&DiplomacyButton>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    if let Ok(DiplomacyButton(command)) = query_button.get(click.entity) {
+        player_command.write(command.clone());
+    }
+}
+
+fn close_diplomacy_screen_on_click(
+    click: On<Pointer<Click>>,
+    mut commands: Commands,
+    query_screen: Query<Entity, With<DiplomacyScreen>>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    for screen in query_screen.iter() {
+        commands.entity(screen).despawn();
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource,
+    city::City,
+    civilization::{Civilizations, Difficulty},
+    connection::{CityConnections, connection_gold},
+    era::{civilization_era, eras_in_order},
+    improvement::TileImprovementLayer,
+    rng::GameRng,
+    turn::{TurnProcessing, TurnSet},
+    unit::MapUnit,
+    unit_component::{Owner, Unit},
+    yields::TileYields,
+};
+
+/// Every civilization can support this many units without paying for them.
+const FREE_UNITS: usize = 3;
+/// The gold paid every turn for every unit beyond [`FREE_UNITS`] in the first era.
+const UNIT_MAINTENANCE: f32 = 1.;
+/// Every era after the first adds this much to the maintenance of every unit.
+const UNIT_MAINTENANCE_PER_ERA: f32 = 0.5;
+/// Every this many citizens of a civilization add 1 to its unit supply.
+const CITIZENS_PER_UNIT_SUPPLY: u32 = 2;
+/// Every unit beyond the supply removes 10% of the production of the cities, up to 70%, as in Unciv.
+const PRODUCTION_PENALTY_PER_UNIT_OVER_SUPPLY: f32 = 0.1;
+const MAX_SUPPLY_PRODUCTION_PENALTY: f32 = 0.7;
+/// The gold paid every turn for every road.
+const ROAD_MAINTENANCE: f32 = 1.;
+
+/// Where the gold of a civilization comes from and goes to in one turn.
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct GoldBreakdown {
+    /// The gold from the worked tiles of the cities and the effects of the civilization, with the golden age bonus.
+    pub city_gold: f32,
+    /// The gold from the buildings.
+    pub building_gold: f32,
+    /// The gold from the cities connected to the capital, see [`crate::connection`].
+    pub connection_gold: f32,
+    pub unit_maintenance: f32,
+    pub building_maintenance: f32,
+    pub road_maintenance: f32,
+}
+
+impl GoldBreakdown {
+    pub fn income(&self) -> f32 {
+        self.city_gold + self.building_gold + self.connection_gold
+    }
+
+    pub fn expenses(&self) -> f32 {
+        self.unit_maintenance + self.building_maintenance + self.road_maintenance
+    }
+
+    /// The gold added to the treasury every turn, negative if the expenses are higher than the income.
+    pub fn net(&self) -> f32 {
+        self.income() - self.expenses()
+    }
+}
+
+/// How many units a civilization can support without a production penalty, and how many it has.
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct UnitSupply {
+    /// The base supply of the difficulty, plus the supply of every city, plus 1 for every [`CITIZENS_PER_UNIT_SUPPLY`] citizens.
+    pub supply: u32,
+    pub unit_count: u32,
+}
+
+impl UnitSupply {
+    pub fn units_over_supply(&self) -> u32 {
+        self.unit_count.saturating_sub(self.supply)
+    }
+
+    /// The part of the production of the cities which is kept.
+    pub fn production_modifier(&self) -> f32 {
+        1. - (self.units_over_supply() as f32 * PRODUCTION_PENALTY_PER_UNIT_OVER_SUPPLY)
+            .min(MAX_SUPPLY_PRODUCTION_PENALTY)
+    }
+}
+
+/// Written when a unit is disbanded because its civilization ran out of gold.
+#[derive(Message)]
+pub struct UnitDisbanded {
+    pub nation: Nation,
+    pub unit_name: String,
+}
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<UnitDisbanded>().add_systems(
+            TurnProcessing,
+            (collect_gold, disband_units_when_bankrupt)
+                .chain()
+                .in_set(TurnSet::Economy),
+        );
+    }
+}
+
+/// Compute the [`GoldBreakdown`] and the [`UnitSupply`] of every civilization and add its net gold to the treasury.
+///
+/// Roads are paid by the civilization owning the tile they are built on, and units cost more in later eras.
+pub fn collect_gold(
+    ruleset: Res<RulesetResource>,
+    difficulty: Res<Difficulty>,
+    tile_yields: Res<TileYields>,
+    improvement_layer: Res<TileImprovementLayer>,
+    city_connections: Res<CityConnections>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+    query_unit: Query<&Owner, With<Unit>>,
+) {
+    let ruleset = &ruleset.0;
+
+    let mut nation_and_breakdown: HashMap<Nation, GoldBreakdown> = civilizations
+        .iter()
+        .map(|(nation, _)| (nation, GoldBreakdown::default()))
+        .collect();
+
+    let capitals: HashMap<Nation, &City> = query_city
+        .iter()
+        .filter(|(city, _)| city.is_capital)
+        .map(|(city, owner)| (owner.nation(), city))
+        .collect();
+
+    for (city, owner) in query_city.iter() {
+        let civilization = civilizations.get(owner.nation());
+        let breakdown = nation_and_breakdown.entry(owner.nation()).or_default();
+        breakdown.city_gold += city.total_yields(&tile_yields, civilization, ruleset).gold
+            * civilization.golden_age.gold_modifier();
+        if let Some(capital) = capitals.get(&owner.nation())
+            && !city.is_capital
+            && city_connections.is_connected(city.tile)
+        {
+            breakdown.connection_gold += connection_gold(city, capital);
+        }
+        for building in &city.buildings {
+            let building = &ruleset.buildings[building];
+            breakdown.building_gold += building.gold;
+            breakdown.building_maintenance += building.maintenance as f32;
+        }
+        breakdown.road_maintenance +=
+            road_maintenance(city.tile, &city.owned_tiles, &improvement_layer);
+    }
+
+    let mut nation_and_unit_count: HashMap<Nation, usize> = HashMap::new();
+    // Barbarians don't pay for their units.
+    for owner in query_unit
+        .iter()
+        .filter(|owner| !matches!(owner, Owner::Barbarian))
+    {
+        *nation_and_unit_count.entry(owner.nation()).or_default() += 1;
+    }
+    let eras = eras_in_order(ruleset);
+    for (&nation, &unit_count) in &nation_and_unit_count {
+        let era = civilization_era(civilizations.get(nation), ruleset);
+        let era_index = eras.iter().position(|e| *e == era).unwrap_or_else(|| {
+            warn!(
+                "{} is in the unknown era {era}, its units cost the maintenance of the first era",
+                nation.as_str()
+            );
+            0
+        });
+        nation_and_breakdown
+            .entry(nation)
+            .or_default()
+            .unit_maintenance = unit_maintenance(unit_count, era_index);
+    }
+
+    let difficulty = difficulty.info(ruleset);
+    for (nation, breakdown) in nation_and_breakdown {
+        let cities = query_city
+            .iter()
+            .filter(|(_, owner)| owner.nation() == nation)
+            .map(|(city, _)| city);
+        let (city_count, population) = cities.fold((0, 0), |(count, population), city| {
+            (count + 1, population + city.population)
+        });
+        let unit_supply = UnitSupply {
+            supply: (difficulty.unit_supply_base + difficulty.unit_supply_per_city * city_count)
+                .max(0) as u32
+                + population / CITIZENS_PER_UNIT_SUPPLY,
+            unit_count: nation_and_unit_count
+                .get(&nation)
+                .copied()
+                .unwrap_or_default() as u32,
+        };
+
+        let civilization = civilizations.get_mut(nation);
+        civilization.gold += breakdown.net();
+        civilization.gold_breakdown = breakdown;
+        civilization.unit_supply = unit_supply;
+    }
+}
+
+/// The gold paid every turn for `unit_count` units in the era at `era_index` in [`eras_in_order`].
+pub fn unit_maintenance(unit_count: usize, era_index: usize) -> f32 {
+    unit_count.saturating_sub(FREE_UNITS) as f32
+        * (UNIT_MAINTENANCE + era_index as f32 * UNIT_MAINTENANCE_PER_ERA)
+}
+
+/// The gold paid every turn for the roads on `owned_tiles`, the tiles of the city on `city_tile`.
+/// The city tile itself is free.
+pub fn road_maintenance(
+    city_tile: Tile,
+    owned_tiles: &[Tile],
+    improvement_layer: &TileImprovementLayer,
+) -> f32 {
+    owned_tiles
+        .iter()
+        .filter(|&&tile| tile != city_tile && improvement_layer.has_road(tile))
+        .count() as f32
+        * ROAD_MAINTENANCE
+}
+
+/// Disband a random military unit of every civilization with a negative treasury, and reset its treasury to 0.
+///
+/// One unit is disbanded per turn until the civilization can pay for its expenses again.
+fn disband_units_when_bankrupt(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    mut unit_disbanded: MessageWriter<UnitDisbanded>,
+    mut civilizations: ResMut<Civilizations>,
+    query_unit: Query<(Entity, &Unit, &Owner, &MapUnit)>,
+) {
+    let mut bankrupt_nations: Vec<_> = civilizations
+        .iter()
+        .filter(|(_, civilization)| civilization.gold < 0.)
+        .map(|(nation, _)| nation)
+        .collect();
+    // Sort the nations so the random rolls don't depend on the order of the map.
+    bankrupt_nations.sort_by_key(|nation| nation.as_str());
+
+    for nation in bankrupt_nations {
+        civilizations.get_mut(nation).gold = 0.;
+
+        let mut military_units: Vec<_> = query_unit
+            .iter()
+            .filter(|(_, unit, owner, _)| {
+                owner.nation() == nation && matches!(unit, Unit::Military(_))
+            })
+            .collect();
+        // Sort by tile and name so the choice only depends on the random number generator,
+        // and not on the entities, which differ between the players of a network game.
+        military_units.sort_by(|(_, a, _, a_unit), (_, b, _, b_unit)| {
+            a_unit
+                .tile
+                .index()
+                .cmp(&b_unit.tile.index())
+                .then_with(|| a.name().cmp(b.name()))
+        });
+        if military_units.is_empty() {
+            continue;
+        }
+
+        let index = rng.gen_range(0, military_units.len() as i32) as usize;
+        let (entity, unit, ..) = military_units[index];
+        commands.entity(entity).despawn();
+        unit_disbanded.write(UnitDisbanded {
+            nation,
+            unit_name: unit.name().to_owned(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units_over_supply(units_over_supply: u32) -> UnitSupply {
+        UnitSupply {
+            supply: 5,
+            unit_count: 5 + units_over_supply,
+        }
+    }
+
+    #[test]
+    fn removes_production_for_units_over_supply() {
+        let under_supply = UnitSupply {
+            supply: 5,
+            unit_count: 2,
+        };
+        assert_eq!(under_supply.units_over_supply(), 0);
+        assert_eq!(under_supply.production_modifier(), 1.);
+        assert_eq!(units_over_supply(0).production_modifier(), 1.);
+        assert!((units_over_supply(3).production_modifier() - 0.7).abs() < 1e-6);
+        // The penalty stops at 70%.
+        assert!((units_over_supply(7).production_modifier() - 0.3).abs() < 1e-6);
+        assert!((units_over_supply(12).production_modifier() - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn units_cost_more_in_later_eras() {
+        assert_eq!(unit_maintenance(FREE_UNITS, 3), 0.);
+        assert_eq!(unit_maintenance(FREE_UNITS + 4, 0), 4.);
+        assert_eq!(unit_maintenance(FREE_UNITS + 4, 1), 6.);
+        assert_eq!(unit_maintenance(FREE_UNITS + 4, 3), 10.);
+    }
+}
@@ -0,0 +1,363 @@
+use civ_map_generator::{ruleset::Ruleset, tile::Tile, tile_map::TileMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    civilization::Civilization,
+    pathfinding::crosses_river,
+    unit::{UnitDomain, unit_kind},
+    unit_component::Unit,
+    yields::Yields,
+};
+
+/// An effect of a unique of the ruleset, e.g. a unique of a policy, a nation, a building or a unit.
+///
+/// Uniques are compiled once into effects, which are then applied through a few hooks:
+/// the yields of the cities, see [`CivilizationEffects`] and [`CityEffects`], the combat strength of units,
+/// see [`strength_bonus`], and where buildings can be built, see [`placement_allows`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum Effect {
+    /// `[+N Yield] [in all cities]`, e.g. `[+1 Culture] [in all cities]`.
+    CityYields(Yields),
+    /// `[+N Yield] [in this city]`, for the uniques of buildings.
+    LocalYields(Yields),
+    /// `[+N]% [Yield] [in all cities]`, e.g. `[+10]% [Production] [in all cities]`.
+    CityYieldPercent(Yields),
+    /// `[+N]% [Yield] [in this city]`, for the uniques of buildings.
+    LocalYieldPercent(Yields),
+    /// `[+N Happiness]`.
+    Happiness(f32),
+    /// `[+N]% Strength`, optionally followed by [`CombatCondition`]s, e.g. `[+25]% Strength <when defending>`.
+    Strength(StrengthModifier),
+    /// `Must be next to [terrainFilter]`, for the uniques of buildings, see [`matches_terrain_filter`].
+    MustBeNextTo(String),
+    /// `Must be on [terrainFilter]`, for the uniques of buildings, see [`matches_terrain_filter`].
+    MustBeOn(String),
+}
+
+impl Effect {
+    /// Parse a unique. Return `None` if the unique is not an effect.
+    ///
+    /// The parameters between `[]` are replaced by placeholders to find the kind of the unique,
+    /// and the conditionals between `<>` at its end are parsed separately.
+    pub fn parse(unique: &str) -> Option<Self> {
+        let (text, conditionals) = split_conditionals(unique);
+        let parameters = parameters(text);
+        let placeholder_text = placeholder_text(text);
+
+        match (placeholder_text.as_str(), parameters.as_slice()) {
+            ("[] []", [amount_and_yield, target]) => {
+                let yields = parse_yields(amount_and_yield)?;
+                match *target {
+                    "in all cities" => Some(Effect::CityYields(yields)),
+                    "in this city" => Some(Effect::LocalYields(yields)),
+                    _ => None,
+                }
+            }
+            ("[]% [] []", [percent, yield_name, target]) => {
+                let yields = Yields::from_name(yield_name, percent.parse().ok()?)?;
+                match *target {
+                    "in all cities" => Some(Effect::CityYieldPercent(yields)),
+                    "in this city" => Some(Effect::LocalYieldPercent(yields)),
+                    _ => None,
+                }
+            }
+            ("[]", [amount_and_happiness]) => {
+                let amount = amount_and_happiness.strip_suffix(" Happiness")?;
+                amount.parse().ok().map(Effect::Happiness)
+            }
+            ("[]% Strength", [percent]) => {
+                // A unique with a conditional which is not supported yet is ignored as a whole.
+                let conditions = conditionals
+                    .iter()
+                    .map(|conditional| CombatCondition::parse(conditional))
+                    .collect::<Option<_>>()?;
+                Some(Effect::Strength(StrengthModifier {
+                    percent: percent.parse().ok()?,
+                    conditions,
+                }))
+            }
+            ("Must be next to []", [filter]) => Some(Effect::MustBeNextTo((*filter).to_owned())),
+            ("Must be on []", [filter]) => Some(Effect::MustBeOn((*filter).to_owned())),
+            _ => None,
+        }
+    }
+}
+
+/// Parse `+N Yield`, e.g. `+1 Culture`.
+fn parse_yields(amount_and_yield: &str) -> Option<Yields> {
+    let (amount, yield_name) = amount_and_yield.split_once(' ')?;
+    Yields::from_name(yield_name, amount.parse().ok()?)
+}
+
+/// Split the conditionals `<...>` from the end of a unique.
+fn split_conditionals(unique: &str) -> (&str, Vec<&str>) {
+    let Some((text, conditionals)) = unique.split_once('<') else {
+        return (unique, Vec::new());
+    };
+    let conditionals = conditionals
+        .split('<')
+        .filter_map(|part| part.split_once('>').map(|(conditional, _)| conditional))
+        .collect();
+    (text.trim_end(), conditionals)
+}
+
+/// The parameters between `[]` of a unique, in order.
+fn parameters(text: &str) -> Vec<&str> {
+    text.split('[')
+        .skip(1)
+        .filter_map(|part| part.split_once(']').map(|(parameter, _)| parameter))
+        .collect()
+}
+
+/// The unique with its parameters replaced by `[]`, e.g. `Must be next to []`.
+fn placeholder_text(text: &str) -> String {
+    let mut placeholder_text = String::with_capacity(text.len());
+    let mut in_parameter = false;
+    for character in text.chars() {
+        match character {
+            '[' => {
+                in_parameter = true;
+                placeholder_text.push('[');
+            }
+            ']' => {
+                in_parameter = false;
+                placeholder_text.push(']');
+            }
+            _ if !in_parameter => placeholder_text.push(character),
+            _ => {}
+        }
+    }
+    placeholder_text
+}
+
+/// When a [`StrengthModifier`] applies, from a conditional of its unique.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CombatCondition {
+    /// `<for [UnitFilter] units>`, e.g. `<for [All] units>`, see [`matches_unit_filter`].
+    ForUnits(String),
+    /// `<when attacking>`.
+    Attacking,
+    /// `<when defending>`.
+    Defending,
+    /// `<vs cities>`, when attacking a city or defending against its strike.
+    VsCities,
+    /// `<vs [UnitFilter] units>`, e.g. `<vs [Mounted] units>`, see [`matches_unit_filter`].
+    VsUnits(String),
+}
+
+impl CombatCondition {
+    fn parse(conditional: &str) -> Option<Self> {
+        match conditional {
+            "when attacking" => Some(CombatCondition::Attacking),
+            "when defending" => Some(CombatCondition::Defending),
+            "vs cities" => Some(CombatCondition::VsCities),
+            _ => {
+                let unit_filter = |prefix: &str| {
+                    conditional
+                        .strip_prefix(prefix)?
+                        .strip_suffix("] units")
+                        .map(str::to_owned)
+                };
+                unit_filter("for [")
+                    .map(CombatCondition::ForUnits)
+                    .or_else(|| unit_filter("vs [").map(CombatCondition::VsUnits))
+            }
+        }
+    }
+
+    fn applies(
+        &self,
+        unit_name: &str,
+        is_attacking: bool,
+        opponent: Opponent,
+        ruleset: &Ruleset,
+    ) -> bool {
+        match self {
+            CombatCondition::ForUnits(filter) => matches_unit_filter(unit_name, filter, ruleset),
+            CombatCondition::Attacking => is_attacking,
+            CombatCondition::Defending => !is_attacking,
+            CombatCondition::VsCities => opponent == Opponent::City,
+            CombatCondition::VsUnits(filter) => match opponent {
+                Opponent::Unit(opponent_name) => {
+                    matches_unit_filter(opponent_name, filter, ruleset)
+                }
+                Opponent::City => false,
+            },
+        }
+    }
+}
+
+/// Return `true` if the unit `unit_name` matches a unit filter of a unique: `All`, `Military`, `Civilian`,
+/// its domain (`Land`, `Water` or `Air`), its unit type (e.g. `Mounted`) or its name.
+pub fn matches_unit_filter(unit_name: &str, filter: &str, ruleset: &Ruleset) -> bool {
+    match filter {
+        "All" => true,
+        "Military" => matches!(unit_kind(unit_name, ruleset), Unit::Military(_)),
+        "Civilian" => matches!(unit_kind(unit_name, ruleset), Unit::Civilian(_)),
+        "Land" => UnitDomain::of_unit(unit_name, ruleset) == UnitDomain::Land,
+        "Water" => UnitDomain::of_unit(unit_name, ruleset) == UnitDomain::Water,
+        "Air" => UnitDomain::of_unit(unit_name, ruleset) == UnitDomain::Air,
+        _ => unit_name == filter || ruleset.units[unit_name].unit_type == filter,
+    }
+}
+
+/// A percentage added to the combat strength of units when all its conditions apply.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct StrengthModifier {
+    pub percent: f32,
+    pub conditions: Vec<CombatCondition>,
+}
+
+/// What a unit fights against.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Opponent<'a> {
+    /// A unit, by its name.
+    Unit(&'a str),
+    City,
+}
+
+/// The sum of all the effects applying to a civilization.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct CivilizationEffects {
+    /// Added to the yields of every city.
+    pub city_yields: Yields,
+    /// The percentages added to the yields of every city, see [`Yields::increased_by_percent`].
+    pub city_yield_percent: Yields,
+    pub happiness: f32,
+    /// The strength modifiers of every unit of the civilization.
+    pub strength: Vec<StrengthModifier>,
+}
+
+impl CivilizationEffects {
+    /// Apply an effect to the civilization. Effects of buildings, such as placement filters, are ignored.
+    pub fn apply(&mut self, effect: Effect) {
+        match effect {
+            Effect::CityYields(yields) => self.city_yields += yields,
+            Effect::CityYieldPercent(percent) => self.city_yield_percent += percent,
+            Effect::Happiness(happiness) => self.happiness += happiness,
+            Effect::Strength(modifier) => self.strength.push(modifier),
+            Effect::LocalYields(_)
+            | Effect::LocalYieldPercent(_)
+            | Effect::MustBeNextTo(_)
+            | Effect::MustBeOn(_) => {}
+        }
+    }
+
+    /// Apply every unique in `uniques` which is an effect.
+    pub fn apply_uniques<'a>(&mut self, uniques: impl IntoIterator<Item = &'a String>) {
+        uniques
+            .into_iter()
+            .filter_map(|unique| Effect::parse(unique))
+            .for_each(|effect| self.apply(effect));
+    }
+}
+
+/// The sum of the effects of the buildings of a city which only apply to that city.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CityEffects {
+    pub yields: Yields,
+    /// The percentages added to the yields of the city, see [`Yields::increased_by_percent`].
+    pub yield_percent: Yields,
+}
+
+impl CityEffects {
+    /// Gather the `[in this city]` effects of `buildings`.
+    pub fn of_buildings(buildings: &[String], ruleset: &Ruleset) -> Self {
+        let mut effects = Self::default();
+        let uniques = buildings
+            .iter()
+            .flat_map(|building| &ruleset.buildings[building].uniques);
+        for effect in uniques.filter_map(|unique| Effect::parse(unique)) {
+            match effect {
+                Effect::LocalYields(yields) => effects.yields += yields,
+                Effect::LocalYieldPercent(percent) => effects.yield_percent += percent,
+                _ => {}
+            }
+        }
+        effects
+    }
+}
+
+/// The strength bonus of the unit `unit_name` in a fight, e.g. `0.25` for +25%, from its own uniques
+/// and from the strength modifiers of its civilization.
+pub fn strength_bonus(
+    unit_name: &str,
+    is_attacking: bool,
+    opponent: Opponent,
+    civilization: &Civilization,
+    ruleset: &Ruleset,
+) -> f32 {
+    let unit_modifiers = ruleset.units[unit_name]
+        .uniques
+        .iter()
+        .filter_map(|unique| match Effect::parse(unique) {
+            Some(Effect::Strength(modifier)) => Some(modifier),
+            _ => None,
+        });
+    let percent: f32 = unit_modifiers
+        .chain(civilization.effects.strength.iter().cloned())
+        .filter(|modifier| {
+            modifier
+                .conditions
+                .iter()
+                .all(|condition| condition.applies(unit_name, is_attacking, opponent, ruleset))
+        })
+        .map(|modifier| modifier.percent)
+        .sum();
+    percent / 100.
+}
+
+/// Return `true` if the building `building_name` can be built in the city on `city_tile`,
+/// according to its `Must be next to [terrainFilter]` and `Must be on [terrainFilter]` uniques.
+/// As in Unciv, the city tile itself counts as next to the city, e.g. for a river on it.
+pub fn placement_allows(
+    building_name: &str,
+    city_tile: Tile,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+) -> bool {
+    let grid = tile_map.world_grid.grid;
+    ruleset.buildings[building_name]
+        .uniques
+        .iter()
+        .filter_map(|unique| Effect::parse(unique))
+        .all(|effect| match effect {
+            Effect::MustBeNextTo(filter) => std::iter::once(city_tile)
+                .chain(city_tile.neighbor_tiles(grid))
+                .any(|tile| matches_terrain_filter(tile, &filter, tile_map)),
+            Effect::MustBeOn(filter) => matches_terrain_filter(city_tile, &filter, tile_map),
+            _ => true,
+        })
+}
+
+/// Return `true` if `tile` matches a terrain filter of a unique: `Land`, `Water`, `River`, `Fresh water`,
+/// or the name of its terrain type, base terrain, feature or natural wonder, e.g. `Hill` or `Coast`.
+pub fn matches_terrain_filter(tile: Tile, filter: &str, tile_map: &TileMap) -> bool {
+    match filter {
+        "Land" => !tile.is_water(tile_map),
+        "Water" => tile.is_water(tile_map),
+        "River" => has_river(tile, tile_map),
+        "Fresh water" => {
+            has_river(tile, tile_map)
+                || matches_terrain_filter(tile, "Lakes", tile_map)
+                || matches_terrain_filter(tile, "Oasis", tile_map)
+        }
+        _ => {
+            tile.terrain_type(tile_map).as_str() == filter
+                || tile.base_terrain(tile_map).as_str() == filter
+                || tile
+                    .feature(tile_map)
+                    .is_some_and(|feature| feature.as_str() == filter)
+                || tile
+                    .natural_wonder(tile_map)
+                    .is_some_and(|natural_wonder| natural_wonder.as_str() == filter)
+        }
+    }
+}
+
+/// Return `true` if a river flows on one of the edges of `tile`.
+fn has_river(tile: Tile, tile_map: &TileMap) -> bool {
+    let grid = tile_map.world_grid.grid;
+    tile.neighbor_tiles(grid)
+        .any(|neighbor| crosses_river(tile, neighbor, tile_map))
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use civ_map_generator::{
+    ruleset::Ruleset, tile::Tile, tile_component::BaseTerrain, tile_map::TileMap,
+};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    civilization::{Civilization, Civilizations},
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, UnitDomain, restore_movement_points},
+    unit_component::{Movement, Owner, Unit},
+};
+
+/// The unique of the technology letting land units embark.
+const EMBARKATION_UNIQUE: &str = "Enables embarkation for land units";
+/// The unique of the technology letting embarked units enter ocean tiles.
+const OCEAN_UNIQUE: &str = "Enables [Embarked] units to enter ocean tiles";
+/// The end of the uniques `[+N] Movement <for [Embarked] units>`, which give movement points to embarked units.
+const MOVEMENT_UNIQUE_SUFFIX: &str = "] Movement <for [Embarked] units>";
+/// The movement points of embarked units without the bonuses of the technologies, as in Civ V.
+const BASE_EMBARKED_MOVEMENT: f32 = 2.;
+
+/// How the land units of a civilization can move over water, given by its technologies.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Embarkation {
+    /// `true` if embarked units can enter ocean tiles, and not only coast and lake tiles.
+    pub ocean: bool,
+    /// The movement points of embarked units, which replace the movement points of the unit.
+    pub movement: f32,
+}
+
+impl Embarkation {
+    /// Return how the land units of the civilization can embark, or `None` if they can't.
+    pub fn of_civilization(civilization: &Civilization, ruleset: &Ruleset) -> Option<Self> {
+        let uniques: Vec<&str> = civilization
+            .researched_technologies
+            .iter()
+            .flat_map(|technology| &ruleset.technologies[technology].uniques)
+            .map(String::as_str)
+            .collect();
+        if !uniques.contains(&EMBARKATION_UNIQUE) {
+            return None;
+        }
+
+        let movement_bonus: f32 = uniques
+            .iter()
+            .filter_map(|unique| {
+                unique
+                    .strip_prefix('[')?
+                    .strip_suffix(MOVEMENT_UNIQUE_SUFFIX)?
+                    .parse::<f32>()
+                    .ok()
+            })
+            .sum();
+        Some(Self {
+            ocean: uniques.contains(&OCEAN_UNIQUE),
+            movement: BASE_EMBARKED_MOVEMENT + movement_bonus,
+        })
+    }
+
+    /// Return `true` if an embarked unit can enter the water tile.
+    pub fn can_enter(&self, tile: Tile, tile_map: &TileMap) -> bool {
+        self.ocean || tile.base_terrain(tile_map) != BaseTerrain::Ocean
+    }
+}
+
+/// A land unit on a water tile. Embarked units can't attack and can't defend themselves.
+#[derive(Component)]
+pub struct Embarked;
+
+pub struct EmbarkationPlugin;
+
+impl Plugin for EmbarkationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_embarked_units.run_if(in_state(AppState::GameStart)),
+        )
+        .add_systems(
+            TurnProcessing,
+            update_embarked_movement
+                .in_set(TurnSet::Units)
+                .before(restore_movement_points),
+        );
+    }
+}
+
+/// The movement points of the embarked units of the civilization.
+fn embarked_movement(civilization: &Civilization, ruleset: &Ruleset) -> f32 {
+    Embarkation::of_civilization(civilization, ruleset)
+        .map_or(BASE_EMBARKED_MOVEMENT, |embarkation| embarkation.movement)
+}
+
+/// Embark the land units which moved onto water and disembark the ones which moved onto land.
+///
+/// Embarked units use the embarked movement points of their civilization instead of their own.
+/// The movement points left are kept, embarking and disembarking already use them up, see [`crate::pathfinding`].
+fn update_embarked_units(
+    mut commands: Commands,
+    map: Option<Res<TileMapResource>>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    mut query_unit: Query<
+        (
+            Entity,
+            &Unit,
+            &Owner,
+            &MapUnit,
+            &mut Movement,
+            Has<Embarked>,
+        ),
+        Changed<MapUnit>,
+    >,
+) {
+    let Some(map) = map else {
+        return;
+    };
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+
+    for (entity, unit, owner, map_unit, mut movement, is_embarked) in query_unit.iter_mut() {
+        if UnitDomain::of_unit(unit.name(), ruleset) != UnitDomain::Land {
+            continue;
+        }
+        let on_water = map_unit.tile.is_water(tile_map);
+        if on_water == is_embarked {
+            continue;
+        }
+
+        movement.max = if on_water {
+            commands.entity(entity).insert(Embarked);
+            embarked_movement(civilizations.get(owner.nation()), ruleset)
+        } else {
+            commands.entity(entity).remove::<Embarked>();
+            ruleset.units[unit.name()].movement as f32
+        };
+        movement.current = movement.current.min(movement.max);
+    }
+}
+
+/// Give the embarked units the movement bonuses of the technologies researched since they embarked.
+fn update_embarked_movement(
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    mut query_unit: Query<(&Owner, &mut Movement), With<Embarked>>,
+) {
+    let ruleset = &ruleset.0;
+
+    for (owner, mut movement) in query_unit.iter_mut() {
+        movement.max = embarked_movement(civilizations.get(owner.nation()), ruleset);
+    }
+}
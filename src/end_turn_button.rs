@@ -0,0 +1,179 @@
+//! The button ending the turn, in the bottom right corner of the screen. It shows where the turn is, see
+//! [`TurnPhase`]: the first decision the player still has to make, that the turn can be ended, or, with a spinner,
+//! that the turn is processed or waits for the other players of a network game.
+//!
+//! Clicking it does what `Enter` does, see [`crate::turn_blocker`].
+
+use bevy::{
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+
+use crate::{
+    RulesetResource,
+    assets::AppState,
+    city::City,
+    civilization::{Civilizations, PlayerCivilization},
+    network::NetworkSession,
+    turn::{TurnManager, TurnPhase},
+    turn_blocker::{RequestEndTurn, idle_units, pending_decisions},
+    unit::{MapUnit, MovePath},
+    unit_component::{Movement, Owner, UnitOrder},
+};
+
+const SPINNER_SIZE: f32 = 14.;
+/// The turns of the spinner in a second.
+const SPINNER_SPEED: f32 = 1.5;
+const READY_COLOR: Color = Color::srgb(0.15, 0.45, 0.2);
+const WAITING_COLOR: Color = Color::srgb(0.5, 0.35, 0.1);
+const BUSY_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+
+/// The button ending the turn.
+#[derive(Component)]
+pub struct EndTurnButton;
+
+#[derive(Component)]
+struct EndTurnButtonText;
+
+/// Spins while the turn is processed or waits for the other players.
+#[derive(Component)]
+struct EndTurnSpinner;
+
+pub struct EndTurnButtonPlugin;
+
+impl Plugin for EndTurnButtonPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameStart), setup_end_turn_button)
+            .add_systems(
+                Update,
+                (update_end_turn_button, spin_end_turn_spinner)
+                    .chain()
+                    .run_if(in_state(AppState::GameStart).and(resource_exists::<Civilizations>)),
+            );
+    }
+}
+
+fn setup_end_turn_button(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                bottom: Val::Px(10.),
+                min_width: Val::Px(180.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(8.),
+                padding: UiRect::axes(Val::Px(12.), Val::Px(8.)),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            BackgroundColor(READY_COLOR),
+            BorderColor::all(Color::WHITE),
+            EndTurnButton,
+        ))
+        .with_children(|button| {
+            button.spawn((
+                Node {
+                    width: Val::Px(SPINNER_SIZE),
+                    height: Val::Px(SPINNER_SIZE),
+                    border: UiRect::all(Val::Px(2.)),
+                    ..default()
+                },
+                // Only the top of the ring is drawn, so the ring is seen turning.
+                BorderColor {
+                    top: Color::WHITE,
+                    ..BorderColor::all(Color::NONE)
+                },
+                BorderRadius::MAX,
+                Visibility::Hidden,
+                EndTurnSpinner,
+                Pickable::IGNORE,
+            ));
+            button.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+                EndTurnButtonText,
+                Pickable::IGNORE,
+            ));
+        })
+        .observe(
+            |click: On<Pointer<Click>>, mut request_end_turn: MessageWriter<RequestEndTurn>| {
+                if matches!(click.button, PointerButton::Primary) {
+                    request_end_turn.write(RequestEndTurn {
+                        skip_pending: false,
+                    });
+                }
+            },
+        );
+}
+
+/// Show where the turn is on the button.
+fn update_end_turn_button(
+    turn_manager: Res<TurnManager>,
+    network_session: Option<Res<NetworkSession>>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    player_civilization: Res<PlayerCivilization>,
+    query_city: Query<(Entity, &City, &Owner)>,
+    query_unit: Query<(Entity, &Owner, &MapUnit, &Movement, &MovePath, &UnitOrder)>,
+    mut query_button: Query<&mut BackgroundColor, With<EndTurnButton>>,
+    mut query_text: Query<&mut Text, With<EndTurnButtonText>>,
+    mut query_spinner: Query<&mut Visibility, With<EndTurnSpinner>>,
+) {
+    let nation = player_civilization.0;
+    let (label, color, busy) = match turn_manager.phase {
+        TurnPhase::Playing => {
+            let decisions = pending_decisions(
+                nation,
+                civilizations.get(nation),
+                &ruleset.0,
+                &query_city,
+                idle_units(nation, &query_unit).len(),
+            );
+            match decisions.first() {
+                Some(decision) => (decision.label(), WAITING_COLOR, false),
+                None => ("Press to end turn".to_owned(), READY_COLOR, false),
+            }
+        }
+        // The local player is the only one in a game without network, so the AI plays at once.
+        TurnPhase::WaitingForPlayers if network_session.is_some() => {
+            ("Other players' turn".to_owned(), BUSY_COLOR, true)
+        }
+        TurnPhase::WaitingForPlayers | TurnPhase::Processing => {
+            ("AI thinking".to_owned(), BUSY_COLOR, true)
+        }
+    };
+
+    for mut background_color in query_button.iter_mut() {
+        background_color.set_if_neq(BackgroundColor(color));
+    }
+    for mut text in query_text.iter_mut() {
+        if text.0 != label {
+            text.0.clone_from(&label);
+        }
+    }
+    let spinner_visibility = if busy {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut visibility in query_spinner.iter_mut() {
+        visibility.set_if_neq(spinner_visibility);
+    }
+}
+
+fn spin_end_turn_spinner(
+    time: Res<Time>,
+    mut query_spinner: Query<(&mut UiTransform, &Visibility), With<EndTurnSpinner>>,
+) {
+    for (mut transform, visibility) in query_spinner.iter_mut() {
+        if *visibility != Visibility::Hidden {
+            transform.rotation = transform.rotation
+                * Rot2::radians(SPINNER_SPEED * std::f32::consts::TAU * time.delta_secs());
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset};
+
+use crate::{
+    RulesetResource,
+    assets::AppState,
+    civilization::{Civilization, Civilizations},
+    game_event::TechResearched,
+};
+
+/// The era the game starts in, from the command line: `--era <name>`, e.g. `--era "Medieval era"`.
+///
+/// Starting in a later era gives every civilization the technologies of the earlier eras,
+/// and the starting units and gold of the era, see [`starting_units`].
+#[derive(Resource, Clone)]
+pub struct StartingEra(pub String);
+
+impl Default for StartingEra {
+    fn default() -> Self {
+        Self("Ancient era".to_owned())
+    }
+}
+
+impl StartingEra {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Option<Self> {
+        while let Some(arg) = args.next() {
+            if arg == "--era" {
+                return args.next().map(StartingEra);
+            }
+        }
+        None
+    }
+}
+
+/// Written when a civilization enters a new era.
+#[derive(Message)]
+pub struct EraChanged {
+    pub nation: Nation,
+    pub era: String,
+}
+
+/// An effect applied to a civilization when it enters an era, read from the `uniques` of the era in the ruleset.
+#[derive(Clone, PartialEq, Debug)]
+pub enum EraEffect {
+    /// `Obsoletes [unit]`: the unit can no longer be built.
+    ObsoleteUnit(String),
+    /// `Unlocks [building]`: the building can be built, even without its required technology.
+    UnlockBuilding(String),
+}
+
+impl EraEffect {
+    /// Parse a unique of an era. Return `None` if the unique is not an era effect.
+    pub fn parse(unique: &str) -> Option<Self> {
+        let (name, parameter) = unique.split_once(" [")?;
+        let parameter = parameter.strip_suffix(']')?.to_owned();
+        match name {
+            "Obsoletes" => Some(EraEffect::ObsoleteUnit(parameter)),
+            "Unlocks" => Some(EraEffect::UnlockBuilding(parameter)),
+            _ => None,
+        }
+    }
+}
+
+/// Return the eras of the ruleset from the earliest to the latest.
+///
+/// `ruleset.eras` is sorted by name, so eras are sorted by the first column of their technologies in the tech tree instead.
+pub fn eras_in_order(ruleset: &Ruleset) -> Vec<String> {
+    let mut eras: Vec<_> = ruleset.eras.keys().cloned().collect();
+    eras.sort_by_key(|era| {
+        ruleset
+            .technologies
+            .values()
+            .filter(|technology| technology.era == *era)
+            .map(|technology| technology.column)
+            .min()
+            .unwrap_or(u32::MAX)
+    });
+    eras
+}
+
+/// Return the technologies of the eras before `era`, which civilizations starting in `era` know from the start.
+pub fn technologies_before_era(era: &str, ruleset: &Ruleset) -> Vec<String> {
+    let eras = eras_in_order(ruleset);
+    let earlier_eras = eras
+        .iter()
+        .position(|ordered_era| ordered_era == era)
+        .map_or(&eras[..0], |index| &eras[..index]);
+    let mut technologies: Vec<_> = ruleset
+        .technologies
+        .values()
+        .filter(|technology| earlier_eras.contains(&technology.era))
+        .map(|technology| technology.name.clone())
+        .collect();
+    technologies.sort();
+    technologies
+}
+
+/// Return the units a civilization starting in `era` starts with: its military units, settlers and workers,
+/// replaced by the unique units of its nation.
+pub fn starting_units(nation: Nation, era: &str, ruleset: &Ruleset) -> Vec<String> {
+    let era = &ruleset.eras[era];
+    let unit_of_nation = |unit_name: &str| {
+        ruleset
+            .units
+            .values()
+            .find(|unit| unit.unique_to == nation.as_str() && unit.replaces == unit_name)
+            .map_or_else(|| unit_name.to_owned(), |unit| unit.name.clone())
+    };
+    std::iter::repeat_n(
+        unit_of_nation(&era.starting_military_unit),
+        era.starting_military_unit_count as usize,
+    )
+    .chain(std::iter::repeat_n(
+        unit_of_nation("Settler"),
+        era.starting_settler_count as usize,
+    ))
+    .chain(std::iter::repeat_n(
+        unit_of_nation("Worker"),
+        era.starting_worker_count as usize,
+    ))
+    .collect()
+}
+
+/// Return the era of a civilization: the latest era among its researched technologies.
+pub fn civilization_era(civilization: &Civilization, ruleset: &Ruleset) -> String {
+    let eras = eras_in_order(ruleset);
+    civilization
+        .researched_technologies
+        .iter()
+        .filter_map(|technology| {
+            let era = &ruleset.technologies[technology].era;
+            eras.iter().position(|ordered_era| ordered_era == era)
+        })
+        .max()
+        .or((!eras.is_empty()).then_some(0))
+        .map(|index| eras[index].clone())
+        .unwrap_or_default()
+}
+
+/// Apply the effects of `era` and all the eras before it to the civilization.
+pub fn apply_era_effects(era: &str, civilization: &mut Civilization, ruleset: &Ruleset) {
+    for passed_era in eras_in_order(ruleset) {
+        let effects = ruleset.eras[&passed_era]
+            .uniques
+            .iter()
+            .filter_map(|unique| EraEffect::parse(unique));
+        for effect in effects {
+            match effect {
+                EraEffect::ObsoleteUnit(unit) => {
+                    civilization.obsolete_units.insert(unit);
+                }
+                EraEffect::UnlockBuilding(building) => {
+                    civilization.unlocked_buildings.insert(building);
+                }
+            }
+        }
+        if passed_era == era {
+            break;
+        }
+    }
+}
+
+pub struct EraPlugin;
+
+impl Plugin for EraPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(StartingEra::from_args(std::env::args().skip(1)).unwrap_or_default())
+            .add_message::<EraChanged>()
+            .add_systems(
+                Update,
+                update_civilization_eras
+                    .run_if(in_state(AppState::GameStart).and(on_message::<TechResearched>)),
+            );
+    }
+}
+
+fn update_civilization_eras(
+    mut tech_researched: MessageReader<TechResearched>,
+    mut era_changed: MessageWriter<EraChanged>,
+    ruleset: Res<RulesetResource>,
+    mut civilizations: ResMut<Civilizations>,
+) {
+    let ruleset = &ruleset.0;
+
+    for &TechResearched { nation, .. } in tech_researched.read() {
+        let civilization = civilizations.get_mut(nation);
+        let era = civilization_era(civilization, ruleset);
+        if era != civilization.era {
+            apply_era_effects(&era, civilization, ruleset);
+            civilization.era = era.clone();
+            era_changed.write(EraChanged { nation, era });
+        }
+    }
+}
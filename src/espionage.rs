@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource,
+    assets::AppState,
+    city::City,
+    city_state::CityStates,
+    civilization::Civilizations,
+    era::EraChanged,
+    game_event::TechResearched,
+    rng::GameRng,
+    technology::can_research,
+    turn::{TurnProcessing, TurnSet},
+    unit_component::Owner,
+    yields::TileYields,
+};
+
+/// A spy rigging the elections of a city-state succeeds after this many turns.
+const ELECTION_TURNS: f32 = 10.;
+/// The influence gained by rigging an election, multiplied by the level of the spy.
+const RIGGED_ELECTION_INFLUENCE: f32 = 10.;
+/// A detected thief is killed with this chance when a spy of the victim is stationed in the city.
+const COUNTER_SPY_KILL_CHANCE: f32 = 0.5;
+
+/// The experience of a spy, raised by every successful mission.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SpyRank {
+    #[default]
+    Recruit,
+    Agent,
+    SpecialAgent,
+}
+
+impl SpyRank {
+    /// 1 for a recruit, 2 for an agent and 3 for a special agent.
+    pub fn level(&self) -> f32 {
+        match self {
+            SpyRank::Recruit => 1.,
+            SpyRank::Agent => 2.,
+            SpyRank::SpecialAgent => 3.,
+        }
+    }
+
+    /// Higher ranks steal technologies faster.
+    pub fn steal_speed(&self) -> f32 {
+        1. + 0.25 * (self.level() - 1.)
+    }
+
+    /// The chance for the victim to find out who stole a technology.
+    pub fn detection_chance(&self) -> f32 {
+        match self {
+            SpyRank::Recruit => 0.5,
+            SpyRank::Agent => 0.35,
+            SpyRank::SpecialAgent => 0.2,
+        }
+    }
+
+    fn promoted(self) -> Self {
+        match self {
+            SpyRank::Recruit => SpyRank::Agent,
+            SpyRank::Agent | SpyRank::SpecialAgent => SpyRank::SpecialAgent,
+        }
+    }
+}
+
+/// A spy of a civilization. Every civilization recruits one spy when it enters a new era.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Spy {
+    pub rank: SpyRank,
+    /// The tile of the city the spy is stationed in, `None` if the spy is not stationed.
+    ///
+    /// In a city of another civilization the spy steals technologies, in a city-state it rigs the elections,
+    /// and in a city of its own civilization it guards against the spies of the others.
+    pub city: Option<Tile>,
+    /// The progress of the current mission: the science put toward stealing a technology,
+    /// or the turns spent rigging the elections.
+    pub progress: f32,
+}
+
+/// Request to station the spy at index `spy` of `nation` in the city on `city`, or to bring it back with `None`.
+#[derive(Message)]
+pub struct MoveSpy {
+    pub nation: Nation,
+    pub spy: usize,
+    pub city: Option<Tile>,
+}
+
+/// Written when a civilization recruits a new spy.
+#[derive(Message)]
+pub struct SpyRecruited {
+    pub nation: Nation,
+}
+
+/// Written when a spy of `nation` steals `technology` from `victim`.
+#[derive(Message)]
+pub struct TechStolen {
+    pub nation: Nation,
+    pub victim: Nation,
+    pub technology: String,
+}
+
+/// Written when `victim` finds out that a spy of `nation` stole from it. The spy may have been killed.
+#[derive(Message)]
+pub struct SpyDetected {
+    pub nation: Nation,
+    pub victim: Nation,
+    pub killed: bool,
+}
+
+/// Written when a spy of `nation` rigs the elections of `city_state`.
+#[derive(Message)]
+pub struct ElectionRigged {
+    pub nation: Nation,
+    pub city_state: Nation,
+    pub influence: f32,
+}
+
+pub struct EspionagePlugin;
+
+impl Plugin for EspionagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<MoveSpy>()
+            .add_message::<SpyRecruited>()
+            .add_message::<TechStolen>()
+            .add_message::<SpyDetected>()
+            .add_message::<ElectionRigged>()
+            .add_systems(
+                Update,
+                (
+                    recruit_spies.run_if(on_message::<EraChanged>),
+                    move_spies.run_if(on_message::<MoveSpy>),
+                )
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(TurnProcessing, run_spy_missions.in_set(TurnSet::Espionage));
+    }
+}
+
+fn recruit_spies(
+    mut era_changed: MessageReader<EraChanged>,
+    mut spy_recruited: MessageWriter<SpyRecruited>,
+    mut civilizations: ResMut<Civilizations>,
+) {
+    for &EraChanged { nation, .. } in era_changed.read() {
+        civilizations.get_mut(nation).spies.push(Spy::default());
+        spy_recruited.write(SpyRecruited { nation });
+    }
+}
+
+/// Station the spies in the requested cities. A spy starts its mission again when it moves.
+fn move_spies(
+    mut move_spy: MessageReader<MoveSpy>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<&City>,
+) {
+    for &MoveSpy { nation, spy, city } in move_spy.read() {
+        let city_exists = city.is_none_or(|tile| query_city.iter().any(|city| city.tile == tile));
+        let Some(spy) = civilizations.get_mut(nation).spies.get_mut(spy) else {
+            continue;
+        };
+        if !city_exists {
+            continue;
+        }
+        spy.city = city;
+        spy.progress = 0.;
+    }
+}
+
+/// Advance the mission of every stationed spy.
+///
+/// A spy in a city of another civilization adds the science of the city toward the cheapest technology
+/// the victim knows and its civilization can research, and steals it once the cost of the technology is reached.
+/// A spy in a city-state gives influence to its civilization every [`ELECTION_TURNS`] turns.
+/// Every successful mission promotes the spy.
+fn run_spy_missions(
+    mut tech_researched: MessageWriter<TechResearched>,
+    mut tech_stolen: MessageWriter<TechStolen>,
+    mut spy_detected: MessageWriter<SpyDetected>,
+    mut election_rigged: MessageWriter<ElectionRigged>,
+    mut rng: ResMut<GameRng>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    mut civilizations: ResMut<Civilizations>,
+    mut city_states: ResMut<CityStates>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let ruleset = &ruleset.0;
+
+    let city_tile_and_owner: HashMap<Tile, (&City, Owner)> = query_city
+        .iter()
+        .map(|(city, &owner)| (city.tile, (city, owner)))
+        .collect();
+    let guarded_cities: Vec<(Nation, Tile)> = civilizations
+        .iter()
+        .flat_map(|(nation, civilization)| {
+            civilization
+                .spies
+                .iter()
+                .filter_map(move |spy| spy.city.map(|tile| (nation, tile)))
+        })
+        .collect();
+
+    // Sort the nations so the random rolls don't depend on the order of the map.
+    let mut nations: Vec<_> = civilizations.iter().map(|(nation, _)| nation).collect();
+    nations.sort_by_key(|nation| nation.as_str());
+
+    for nation in nations {
+        let spy_count = civilizations.get(nation).spies.len();
+        // The spies are removed after the loop, so the indices stay valid.
+        let mut killed_spies = Vec::new();
+        for index in 0..spy_count {
+            let Some(tile) = civilizations.get(nation).spies[index].city else {
+                continue;
+            };
+            let Some(&(city, owner)) = city_tile_and_owner.get(&tile) else {
+                // The city doesn't exist anymore, so the spy comes back.
+                civilizations.get_mut(nation).spies[index].city = None;
+                continue;
+            };
+
+            match owner {
+                Owner::Civilization(victim) if victim != nation => {
+                    let civilization = civilizations.get(nation);
+                    let victim_civilization = civilizations.get(victim);
+                    let technology = victim_civilization
+                        .researched_technologies
+                        .iter()
+                        .filter(|technology| can_research(technology, civilization, ruleset))
+                        .map(|technology| &ruleset.technologies[technology])
+                        .min_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.name.cmp(&b.name)));
+                    let Some(technology) = technology else {
+                        continue;
+                    };
+                    let technology_name = technology.name.clone();
+                    let cost = technology.cost as f32;
+                    let science = city.science(&tile_yields, victim_civilization, ruleset);
+
+                    let spy = &mut civilizations.get_mut(nation).spies[index];
+                    spy.progress += science * spy.rank.steal_speed();
+                    if spy.progress < cost {
+                        continue;
+                    }
+                    spy.progress = 0.;
+                    let rank = spy.rank;
+
+                    civilizations
+                        .get_mut(nation)
+                        .learn_technology(&technology_name);
+                    tech_researched.write(TechResearched {
+                        nation,
+                        technology: technology_name.clone(),
+                    });
+                    tech_stolen.write(TechStolen {
+                        nation,
+                        victim,
+                        technology: technology_name,
+                    });
+
+                    let mut killed = false;
+                    if rng.gen_bool(rank.detection_chance()) {
+                        killed = guarded_cities.contains(&(victim, tile))
+                            && rng.gen_bool(COUNTER_SPY_KILL_CHANCE);
+                        spy_detected.write(SpyDetected {
+                            nation,
+                            victim,
+                            killed,
+                        });
+                    }
+                    if killed {
+                        killed_spies.push(index);
+                    } else {
+                        let spy = &mut civilizations.get_mut(nation).spies[index];
+                        spy.rank = spy.rank.promoted();
+                    }
+                }
+                Owner::CityState(city_state) => {
+                    let spy = &mut civilizations.get_mut(nation).spies[index];
+                    spy.progress += 1.;
+                    if spy.progress < ELECTION_TURNS {
+                        continue;
+                    }
+                    spy.progress = 0.;
+                    let influence = RIGGED_ELECTION_INFLUENCE * spy.rank.level();
+                    spy.rank = spy.rank.promoted();
+
+                    if let Some(current) = city_states
+                        .0
+                        .get_mut(&city_state)
+                        .and_then(|state| state.influence.get_mut(&nation))
+                    {
+                        *current += influence;
+                        election_rigged.write(ElectionRigged {
+                            nation,
+                            city_state,
+                            influence,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        for index in killed_spies.into_iter().rev() {
+            civilizations.get_mut(nation).spies.remove(index);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Fallback fonts for the texts the default font can't draw, e.g. the Chinese, Japanese and Korean names of
+//! the cities, the units and the tiles in a ruleset or in its translations.
+//!
+//! The fonts are configured in [`FONT_CONFIG_PATH`]: a font for every script, and the fonts replacing them for
+//! a language, e.g. the Japanese forms of the Han characters for Japanese:
+//!
+//! ```json
+//! {
+//!     "language": "Japanese",
+//!     "scripts": {
+//!         "Han": "fonts/NotoSansSC-Regular.otf",
+//!         "Kana": "fonts/NotoSansJP-Regular.otf",
+//!         "Hangul": "fonts/NotoSansKR-Regular.otf"
+//!     },
+//!     "languages": {
+//!         "Japanese": { "Han": "fonts/NotoSansJP-Regular.otf" }
+//!     }
+//! }
+//! ```
+//!
+//! Every text whose characters need a script is drawn with the font of that script instead of the default font.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// The configuration of the fallback fonts, in the assets folder.
+const FONT_CONFIG_PATH: &str = "assets/fonts/fonts.json";
+const ASSETS_PATH: &str = "assets";
+
+/// The scripts the default font can't draw.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+pub enum Script {
+    /// The Chinese characters, also used in Japanese and Korean.
+    Han,
+    /// The Japanese Hiragana and Katakana.
+    Kana,
+    Hangul,
+}
+
+impl Script {
+    fn of(character: char) -> Option<Self> {
+        match character as u32 {
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Some(Self::Hangul),
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Some(Self::Kana),
+            0x3000..=0x303F | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => {
+                Some(Self::Han)
+            }
+            _ => None,
+        }
+    }
+
+    /// The script `text` needs a font for. Kana and Hangul come first, so a Japanese or a Korean text mixing
+    /// Han characters gets the font of its language.
+    fn needed_by(text: &str) -> Option<Self> {
+        let mut needed = None;
+        for script in text.chars().filter_map(Self::of) {
+            match script {
+                Self::Kana | Self::Hangul => return Some(script),
+                Self::Han => needed = Some(Self::Han),
+            }
+        }
+        needed
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FontConfig {
+    language: Option<String>,
+    scripts: HashMap<Script, String>,
+    languages: HashMap<String, HashMap<Script, String>>,
+}
+
+/// The font drawing every script, for the configured language.
+#[derive(Resource, Default)]
+pub struct FallbackFonts(HashMap<Script, Handle<Font>>);
+
+impl FallbackFonts {
+    /// The font to draw `text` with, `None` for the default font.
+    pub fn font_for(&self, text: &str) -> Option<Handle<Font>> {
+        Script::needed_by(text).and_then(|script| self.0.get(&script).cloned())
+    }
+
+    fn contains(&self, font: &Handle<Font>) -> bool {
+        self.0.values().any(|fallback| fallback == font)
+    }
+}
+
+pub struct FontsPlugin;
+
+impl Plugin for FontsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FallbackFonts>()
+            .add_systems(Startup, load_fallback_fonts)
+            .add_systems(Update, (apply_fallback_fonts, apply_fallback_fonts_2d));
+    }
+}
+
+fn load_fallback_fonts(asset_server: Res<AssetServer>, mut fallback_fonts: ResMut<FallbackFonts>) {
+    let config: FontConfig = match fs::read_to_string(FONT_CONFIG_PATH) {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(config) => config,
+            Err(error) => {
+                error!("Can't read the font configuration {FONT_CONFIG_PATH}: {error}");
+                return;
+            }
+        },
+        // Without a configuration, every text uses the default font.
+        Err(_) => return,
+    };
+
+    let mut paths = config.scripts;
+    if let Some(language_fonts) = config
+        .language
+        .and_then(|language| config.languages.get(&language).cloned())
+    {
+        paths.extend(language_fonts);
+    }
+    for (script, path) in paths {
+        // Fonts are large and left out of the repository, so a missing font only leaves its script to the default font.
+        if !Path::new(ASSETS_PATH).join(&path).exists() {
+            warn!("The font {path} for the {script:?} script is missing.");
+            continue;
+        }
+        fallback_fonts.0.insert(script, asset_server.load(path));
+    }
+}
+
+/// The font of a text once its content changed: the fallback font it needs, or else the default font
+/// if it was drawn with a fallback font. Fonts chosen on purpose are kept.
+fn fallback_font(
+    text: &str,
+    font: &Handle<Font>,
+    fallback_fonts: &FallbackFonts,
+) -> Option<Handle<Font>> {
+    match fallback_fonts.font_for(text) {
+        Some(fallback) if fallback != *font => Some(fallback),
+        None if fallback_fonts.contains(font) => Some(Handle::default()),
+        _ => None,
+    }
+}
+
+fn apply_fallback_fonts(
+    fallback_fonts: Res<FallbackFonts>,
+    mut query_text: Query<(&Text, &mut TextFont), Changed<Text>>,
+) {
+    if fallback_fonts.0.is_empty() {
+        return;
+    }
+    for (text, mut text_font) in query_text.iter_mut() {
+        if let Some(font) = fallback_font(&text.0, &text_font.font, &fallback_fonts) {
+            text_font.font = font;
+        }
+    }
+}
+
+fn apply_fallback_fonts_2d(
+    fallback_fonts: Res<FallbackFonts>,
+    mut query_text: Query<(&Text2d, &mut TextFont), Changed<Text2d>>,
+) {
+    if fallback_fonts.0.is_empty() {
+        return;
+    }
+    for (text, mut text_font) in query_text.iter_mut() {
+        if let Some(font) = fallback_font(&text.0, &text_font.font, &fallback_fonts) {
+            text_font.font = font;
+        }
+    }
+}
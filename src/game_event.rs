@@ -0,0 +1,65 @@
+//! The events of the game: messages written by the gameplay systems when the game changes, and read by the
+//! rendering, the audio, the notifications and the AI to react to the change.
+//!
+//! A new feature reacting to the game reads these events, instead of being called by the systems making the change,
+//! e.g. the sound of a battle is played by reading [`CombatResolved`], not by the combat.
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile};
+
+/// Written whenever something which affects the yields of a tile changes,
+/// e.g. an improvement is built or a feature is removed.
+#[derive(Message, Clone, Copy)]
+pub struct TileChanged(pub Tile);
+
+/// Written when a unit walks along its [`crate::unit::MovePath`] or advances into the tile of the enemy it defeated,
+/// with the tiles it went through from the tile it started on, so its sprite can walk the same way,
+/// see [`crate::unit_sprite::MoveAnimation`].
+#[derive(Message)]
+pub struct UnitMoved {
+    pub unit: Entity,
+    pub path: Vec<Tile>,
+}
+
+/// Written when a city is founded.
+#[derive(Message)]
+pub struct CityFounded {
+    pub city: Entity,
+    pub nation: Nation,
+    pub name: String,
+    pub tile: Tile,
+}
+
+/// Written when a civilization finishes researching a technology.
+#[derive(Message)]
+pub struct TechResearched {
+    pub nation: Nation,
+    pub technology: String,
+}
+
+/// Written when a fight is over.
+#[derive(Message, Clone)]
+pub struct CombatResolved {
+    pub attacker: Entity,
+    pub defender: Entity,
+    pub attacker_nation: Nation,
+    pub defender_nation: Nation,
+    pub defender_tile: Tile,
+    pub is_ranged: bool,
+    pub damage_to_attacker: u32,
+    pub damage_to_defender: u32,
+    pub attacker_killed: bool,
+    pub defender_killed: bool,
+}
+
+pub struct GameEventPlugin;
+
+impl Plugin for GameEventPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<TileChanged>()
+            .add_message::<UnitMoved>()
+            .add_message::<CityFounded>()
+            .add_message::<TechResearched>()
+            .add_message::<CombatResolved>();
+    }
+}
@@ -7,14 +7,59 @@ use bevy::{
     },
     state::state::NextState,
     tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+    time::Time,
 };
 use civ_map_generator::{generate_map, tile_map::TileMap};
 
-use crate::{MapSetting, RulesetResource, TileMapResource, assets::AppState};
+use crate::{
+    MapSetting, RulesetResource, TileMapResource, assets::AppState, main_menu::NewGameSettings,
+};
+
+/// The seconds a map usually takes to generate. The generator doesn't report its progress, so the progress shown
+/// during the generation is estimated from the time spent.
+const EXPECTED_GENERATION_SECONDS: f32 = 4.;
 
 #[derive(Resource)]
 pub struct MapGenerator(Task<TileMap>);
 
+/// The stages of the generation of a new game, in order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MapGenerationStage {
+    #[default]
+    GeneratingMap,
+    PlacingCivilizations,
+}
+
+impl MapGenerationStage {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::GeneratingMap => "Generating the terrain",
+            Self::PlacingCivilizations => "Placing the civilizations",
+        }
+    }
+}
+
+/// How far the generation of the map is, shown on the loading screen, see [`crate::loading_screen`].
+#[derive(Resource, Default)]
+pub struct MapGenerationProgress {
+    pub stage: MapGenerationStage,
+    /// The seconds spent generating the map.
+    pub elapsed: f32,
+}
+
+impl MapGenerationProgress {
+    /// The part of the generation done, from 0 to 1. The generation of the terrain takes most of the bar, and
+    /// slows down as it goes over [`EXPECTED_GENERATION_SECONDS`] instead of filling the bar.
+    pub fn fraction(&self) -> f32 {
+        match self.stage {
+            MapGenerationStage::GeneratingMap => {
+                0.9 * (1. - (-self.elapsed / EXPECTED_GENERATION_SECONDS).exp())
+            }
+            MapGenerationStage::PlacingCivilizations => 1.,
+        }
+    }
+}
+
 pub fn generate_tile_map(
     mut commands: Commands,
     map_setting: Res<MapSetting>,
@@ -25,20 +70,27 @@ pub fn generate_tile_map(
     let thread_pool = AsyncComputeTaskPool::get();
     let task = thread_pool.spawn(async move { generate_map(&map_parameters, &ruleset) });
     commands.insert_resource(MapGenerator(task));
+    commands.insert_resource(MapGenerationProgress::default());
 }
 
 /// This system checks if the map generation task is done and if so,
 /// it inserts the generated map into the resource and transitions to the next state.
 pub fn check_map_generate_status(
     mut commands: Commands,
+    time: Res<Time>,
     task: Option<ResMut<MapGenerator>>,
+    progress: Option<ResMut<MapGenerationProgress>>,
+    new_game_settings: Res<NewGameSettings>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
-    let Some(mut task) = task else {
+    let (Some(mut task), Some(mut progress)) = (task, progress) else {
         return;
     };
+    progress.elapsed += time.delta_secs();
 
-    if let Some(tile_map) = block_on(future::poll_once(&mut task.0)) {
+    if let Some(mut tile_map) = block_on(future::poll_once(&mut task.0)) {
+        progress.stage = MapGenerationStage::PlacingCivilizations;
+        new_game_settings.assign_player_nation(&mut tile_map);
         commands.insert_resource(TileMapResource(tile_map));
         commands.remove_resource::<MapGenerator>();
         next_state.set(AppState::GameStart);
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use civ_map_generator::nation::Nation;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    civilization::Civilizations,
+    happiness::update_happiness,
+    turn::{TurnProcessing, TurnSet},
+};
+
+/// The golden age points needed for the first golden age, as in Civ V.
+const GOLDEN_AGE_BASE_THRESHOLD: f32 = 500.;
+/// Every golden age raises the points needed for the next one.
+const GOLDEN_AGE_THRESHOLD_INCREASE: f32 = 250.;
+const GOLDEN_AGE_DURATION: u32 = 10;
+/// The production of every city is increased by 20% during a golden age.
+pub const GOLDEN_AGE_PRODUCTION_BONUS: f32 = 0.2;
+/// The gold of every city is increased by 20% during a golden age.
+pub const GOLDEN_AGE_GOLD_BONUS: f32 = 0.2;
+
+/// The golden age meter of a civilization.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GoldenAgeProgress {
+    /// The happiness surplus accumulated toward the next golden age.
+    pub points: f32,
+    /// The number of golden ages the civilization had.
+    pub count: u32,
+    /// The turns left in the current golden age, 0 if there is no golden age now.
+    pub turns_left: u32,
+}
+
+impl GoldenAgeProgress {
+    pub fn is_active(&self) -> bool {
+        self.turns_left > 0
+    }
+
+    /// The points needed for the next golden age.
+    pub fn threshold(&self) -> f32 {
+        GOLDEN_AGE_BASE_THRESHOLD + GOLDEN_AGE_THRESHOLD_INCREASE * self.count as f32
+    }
+
+    /// The production modifier of the cities of the civilization.
+    pub fn production_modifier(&self) -> f32 {
+        if self.is_active() {
+            1. + GOLDEN_AGE_PRODUCTION_BONUS
+        } else {
+            1.
+        }
+    }
+
+    /// The gold modifier of the cities of the civilization.
+    pub fn gold_modifier(&self) -> f32 {
+        if self.is_active() {
+            1. + GOLDEN_AGE_GOLD_BONUS
+        } else {
+            1.
+        }
+    }
+}
+
+/// Written when a golden age starts for a civilization.
+#[derive(Message)]
+pub struct GoldenAgeStarted {
+    pub nation: Nation,
+}
+
+/// Written when the golden age of a civilization ends.
+#[derive(Message)]
+pub struct GoldenAgeEnded {
+    pub nation: Nation,
+}
+
+pub struct GoldenAgePlugin;
+
+impl Plugin for GoldenAgePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<GoldenAgeStarted>()
+            .add_message::<GoldenAgeEnded>()
+            .add_systems(
+                TurnProcessing,
+                update_golden_ages
+                    .after(update_happiness)
+                    .in_set(TurnSet::Happiness),
+            );
+    }
+}
+
+/// Count down the current golden ages, and accumulate the happiness surplus of the other civilizations toward their next one.
+fn update_golden_ages(
+    mut golden_age_started: MessageWriter<GoldenAgeStarted>,
+    mut golden_age_ended: MessageWriter<GoldenAgeEnded>,
+    mut civilizations: ResMut<Civilizations>,
+) {
+    let nations: Vec<_> = civilizations.iter().map(|(nation, _)| nation).collect();
+
+    for nation in nations {
+        let civilization = civilizations.get_mut(nation);
+        let happiness = civilization.happiness;
+        let golden_age = &mut civilization.golden_age;
+
+        if golden_age.is_active() {
+            golden_age.turns_left -= 1;
+            if !golden_age.is_active() {
+                golden_age_ended.write(GoldenAgeEnded { nation });
+            }
+            continue;
+        }
+
+        golden_age.points += happiness.max(0.);
+        if golden_age.points >= golden_age.threshold() {
+            golden_age.points -= golden_age.threshold();
+            golden_age.count += 1;
+            golden_age.turns_left = GOLDEN_AGE_DURATION;
+            golden_age_started.write(GoldenAgeStarted { nation });
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    city::City,
+    civilization::Civilizations,
+    command::{PlayerCommand, UnitId},
+    game_event::{CombatResolved, TechResearched, TileChanged},
+    improvement::TileImprovementLayer,
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, SpawnUnit, find_spawn_tile, taken_tiles, unit_kind},
+    unit_component::{Owner, Unit},
+    world_map::SelectedUnit,
+};
+
+/// The points needed for the first great person. Every great person born raises it by the same amount.
+const GREAT_PERSON_BASE_THRESHOLD: f32 = 100.;
+/// The great person born from fights instead of cities, with its own threshold.
+const GREAT_GENERAL: &str = "Great General";
+/// Every 10 damage dealt in a fight gives 1 point toward the next Great General.
+const DAMAGE_PER_GREAT_GENERAL_POINT: f32 = 10.;
+
+/// The great person points of a civilization and how many great people it got.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GreatPeopleProgress {
+    /// The points toward every kind of great person, by unit name.
+    pub points: HashMap<String, f32>,
+    /// The number of great people born from cities, which raises the threshold of all of them.
+    pub born_count: u32,
+    /// The number of Great Generals born, which raises only their threshold.
+    pub great_general_count: u32,
+}
+
+impl GreatPeopleProgress {
+    /// The points needed for the next great person of the kind `great_person`.
+    pub fn threshold(&self, great_person: &str) -> f32 {
+        let count = if great_person == GREAT_GENERAL {
+            self.great_general_count
+        } else {
+            self.born_count
+        };
+        GREAT_PERSON_BASE_THRESHOLD * (count + 1) as f32
+    }
+}
+
+/// The ability a great person uses once and is consumed by, read from the uniques of the unit.
+#[derive(Clone, PartialEq, Debug)]
+pub enum GreatPersonAbility {
+    /// `Can hurry technology research`: finish the current research.
+    FreeTechnology,
+    /// `Can speed up construction of a building`: finish the production of the city the unit stands in.
+    HurryProduction,
+    /// `Can construct [improvement]`, e.g. a Citadel: build the improvement on the tile the unit stands on.
+    BuildImprovement(String),
+}
+
+impl GreatPersonAbility {
+    pub fn of_unit(unit_name: &str, ruleset: &Ruleset) -> Option<Self> {
+        ruleset.units[unit_name]
+            .uniques
+            .iter()
+            .find_map(|unique| match unique.as_str() {
+                "Can hurry technology research" => Some(GreatPersonAbility::FreeTechnology),
+                "Can speed up construction of a building" => {
+                    Some(GreatPersonAbility::HurryProduction)
+                }
+                _ => unique
+                    .strip_prefix("Can construct [")
+                    .and_then(|improvement| improvement.strip_suffix(']'))
+                    .map(|improvement| {
+                        GreatPersonAbility::BuildImprovement(improvement.to_owned())
+                    }),
+            })
+    }
+}
+
+/// Request for a great person to use its ability.
+#[derive(Message)]
+pub struct UseGreatPerson {
+    pub unit: Entity,
+}
+
+/// Written when a great person is born.
+#[derive(Message)]
+pub struct GreatPersonBorn {
+    pub nation: Nation,
+    pub unit_name: String,
+}
+
+pub struct GreatPersonPlugin;
+
+impl Plugin for GreatPersonPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<UseGreatPerson>()
+            .add_message::<GreatPersonBorn>()
+            .add_systems(
+                Update,
+                (
+                    use_great_person_on_key,
+                    use_great_people.run_if(on_message::<UseGreatPerson>),
+                    add_great_general_points.run_if(on_message::<CombatResolved>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                (add_great_person_points, spawn_great_people)
+                    .chain()
+                    .in_set(TurnSet::Cities),
+            );
+    }
+}
+
+fn use_great_person_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    selected_unit: Res<SelectedUnit>,
+    query_unit: Query<(&Unit, &MapUnit)>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyG)
+        && let Some(selected) = selected_unit.0
+        && let Ok((unit, map_unit)) = query_unit.get(selected)
+    {
+        player_command.write(PlayerCommand::UseGreatPerson {
+            unit: UnitId::new(unit, map_unit),
+        });
+    }
+}
+
+/// Add the great person points of the buildings and of the specialists of every city to its civilization.
+fn add_great_person_points(
+    ruleset: Res<RulesetResource>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let ruleset = &ruleset.0;
+
+    for (city, owner) in query_city.iter() {
+        let great_people = &mut civilizations.get_mut(owner.nation()).great_people;
+        let building_points = city
+            .buildings
+            .iter()
+            .flat_map(|building| &ruleset.buildings[building].great_person_points);
+        let specialist_points = city
+            .specialists
+            .iter()
+            .flat_map(|specialist| &ruleset.specialists[specialist].great_person_points);
+        for (great_person, &points) in building_points.chain(specialist_points) {
+            *great_people.points.entry(great_person.clone()).or_default() += points as f32;
+        }
+    }
+}
+
+/// Give points toward a Great General to both sides of a fight, from the damage they dealt.
+fn add_great_general_points(
+    mut combat_resolved: MessageReader<CombatResolved>,
+    mut civilizations: ResMut<Civilizations>,
+) {
+    for combat in combat_resolved.read() {
+        for (nation, damage) in [
+            (combat.attacker_nation, combat.damage_to_defender),
+            (combat.defender_nation, combat.damage_to_attacker),
+        ] {
+            *civilizations
+                .get_mut(nation)
+                .great_people
+                .points
+                .entry(GREAT_GENERAL.to_owned())
+                .or_default() += damage as f32 / DAMAGE_PER_GREAT_GENERAL_POINT;
+        }
+    }
+}
+
+/// Spawn a great person in the capital of every civilization which has enough points for one.
+fn spawn_great_people(
+    mut spawn_unit: MessageWriter<SpawnUnit>,
+    mut great_person_born: MessageWriter<GreatPersonBorn>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+    query_unit: Query<(&MapUnit, &Unit)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let mut taken_tiles = taken_tiles(&query_unit);
+
+    for (capital, &owner) in query_city.iter().filter(|(city, _)| city.is_capital) {
+        let nation = owner.nation();
+        let great_people = &mut civilizations.get_mut(nation).great_people;
+
+        let ready: Vec<_> = great_people
+            .points
+            .iter()
+            .filter(|(great_person, points)| **points >= great_people.threshold(great_person))
+            .map(|(great_person, _)| great_person.clone())
+            .filter(|great_person| ruleset.units.contains_key(great_person))
+            .collect();
+
+        for great_person in ready {
+            let unit = unit_kind(&great_person, ruleset);
+            let Some(tile) = find_spawn_tile(
+                capital.tile,
+                &unit,
+                |tile, is_military| taken_tiles.contains(&(tile, is_military)),
+                tile_map,
+                ruleset,
+            ) else {
+                continue;
+            };
+
+            let threshold = great_people.threshold(&great_person);
+            *great_people.points.get_mut(&great_person).unwrap() -= threshold;
+            if great_person == GREAT_GENERAL {
+                great_people.great_general_count += 1;
+            } else {
+                great_people.born_count += 1;
+            }
+
+            taken_tiles.insert((tile, matches!(unit, Unit::Military(_))));
+            spawn_unit.write(SpawnUnit {
+                unit_name: great_person.clone(),
+                owner,
+                tile,
+            });
+            great_person_born.write(GreatPersonBorn {
+                nation,
+                unit_name: great_person,
+            });
+        }
+    }
+}
+
+/// Use the ability of great people and consume them. A great person which can't use its ability here stays.
+fn use_great_people(
+    mut commands: Commands,
+    mut use_great_person: MessageReader<UseGreatPerson>,
+    mut tech_researched: MessageWriter<TechResearched>,
+    mut tile_changed: MessageWriter<TileChanged>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    mut improvement_layer: ResMut<TileImprovementLayer>,
+    mut civilizations: ResMut<Civilizations>,
+    query_unit: Query<(&Unit, &MapUnit, &Owner)>,
+    mut query_city: Query<(&mut City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+
+    for &UseGreatPerson { unit: entity } in use_great_person.read() {
+        let Ok((unit, map_unit, owner)) = query_unit.get(entity) else {
+            continue;
+        };
+        let Some(ability) = GreatPersonAbility::of_unit(unit.name(), ruleset) else {
+            continue;
+        };
+        let nation = owner.nation();
+
+        let used = match ability {
+            GreatPersonAbility::FreeTechnology => {
+                let civilization = civilizations.get_mut(nation);
+                match civilization.current_research.clone() {
+                    Some(technology) => {
+                        civilization.learn_technology(&technology);
+                        tech_researched.write(TechResearched { nation, technology });
+                        true
+                    }
+                    None => false,
+                }
+            }
+            GreatPersonAbility::HurryProduction => query_city
+                .iter_mut()
+                .find(|(city, city_owner)| {
+                    city.tile == map_unit.tile && city_owner.nation() == nation
+                })
+                .and_then(|(mut city, _)| {
+                    let cost = city.production.as_ref()?.cost(ruleset)?;
+                    city.production_stored = city.production_stored.max(cost);
+                    Some(())
+                })
+                .is_some(),
+            GreatPersonAbility::BuildImprovement(improvement) => {
+                let tile = map_unit.tile;
+                let can_build = ruleset.tile_improvements.contains_key(&improvement)
+                    && !tile.is_water(tile_map)
+                    && !query_city.iter().any(|(city, _)| city.tile == tile);
+                if can_build {
+                    improvement_layer.set_improvement(tile, Some(improvement), &mut tile_changed);
+                }
+                can_build
+            }
+        };
+
+        if used {
+            commands.entity(entity).despawn();
+        }
+    }
+}
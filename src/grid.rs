@@ -0,0 +1,154 @@
+use std::sync::LazyLock;
+
+use bevy::{camera::Camera, math::Vec2, transform::components::GlobalTransform, window::Window};
+use civ_map_generator::{
+    grid::{
+        Grid,
+        hex_grid::{HexGrid, HexOrientation, Offset},
+        offset_coordinate::OffsetCoordinate,
+    },
+    ruleset::Ruleset,
+    tile::Tile,
+    tile_map::TileMap,
+};
+use regex::Regex;
+
+/// Convert a world position to the tile at that position.
+///
+/// Returns `None` if the position is outside the map on an axis that doesn't wrap.
+pub fn world_position_to_tile(position: Vec2, grid: HexGrid) -> Option<Tile> {
+    let offset_coordinate = grid.pixel_to_offset(position.to_array());
+    let [x, y] = offset_coordinate.to_array();
+
+    if !grid.wrap_x() && !(0..grid.width() as i32).contains(&x) {
+        return None;
+    }
+
+    if !grid.wrap_y() && !(0..grid.height() as i32).contains(&y) {
+        return None;
+    }
+
+    Some(Tile::from_offset(offset_coordinate, grid))
+}
+
+/// Return the tile under the cursor, or `None` if the cursor is outside the window or the map.
+pub fn cursor_to_tile(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    grid: HexGrid,
+) -> Option<Tile> {
+    let cursor_position = window.cursor_position()?;
+    let world_position = camera
+        .viewport_to_world_2d(camera_transform, cursor_position)
+        .ok()?;
+    world_position_to_tile(world_position, grid)
+}
+
+/// The axial coordinate `[q, r]` of a tile, from its offset coordinate in the layout of the grid.
+pub fn tile_to_hex(tile: Tile, grid: HexGrid) -> [i32; 2] {
+    let [x, y] = tile.to_offset(grid).to_array();
+    match (grid.layout.orientation, grid.offset) {
+        // Every other row is shifted.
+        (HexOrientation::Pointy, Offset::Odd) => [x - (y - (y & 1)) / 2, y],
+        (HexOrientation::Pointy, Offset::Even) => [x - (y + (y & 1)) / 2, y],
+        // Every other column is shifted.
+        (HexOrientation::Flat, Offset::Odd) => [x, y - (x - (x & 1)) / 2],
+        (HexOrientation::Flat, Offset::Even) => [x, y - (x + (x & 1)) / 2],
+    }
+}
+
+/// The distance in pixels between two copies of a tile on a map which wraps around horizontally.
+pub fn map_pixel_width(grid: HexGrid) -> f32 {
+    grid.offset_to_pixel(OffsetCoordinate::new(grid.width() as i32, 0))[0]
+        - grid.offset_to_pixel(OffsetCoordinate::new(0, 0))[0]
+}
+
+/// Move the x-coordinate `x` onto the copy of the map starting at its left edge.
+///
+/// The tiles are drawn around the camera wherever it is, see [`crate::world_map::show_main_camera_area`],
+/// so the camera can be moved back by the width of the map at any time without a visible jump.
+pub fn wrap_x_position(x: f32, grid: HexGrid) -> f32 {
+    let left = grid.left_bottom()[0];
+    left + (x - left).rem_euclid(map_pixel_width(grid))
+}
+
+/// Return the tiles on the straight line from `from` to `to`, both included.
+///
+/// The line is sampled between the tile centers, so it follows the shortest way across a wrapping edge.
+pub fn tile_line(from: Tile, to: Tile, grid: HexGrid) -> Vec<Tile> {
+    let [from_x, from_y] = from.to_offset(grid).to_array();
+    let [mut to_x, mut to_y] = to.to_offset(grid).to_array();
+
+    // Unwrap the destination so that it is the closest copy of `to` from `from`.
+    let (width, height) = (grid.width() as i32, grid.height() as i32);
+    if grid.wrap_x() && (to_x - from_x).abs() > width / 2 {
+        to_x -= width * (to_x - from_x).signum();
+    }
+    if grid.wrap_y() && (to_y - from_y).abs() > height / 2 {
+        to_y -= height * (to_y - from_y).signum();
+    }
+
+    let start = Vec2::from(grid.offset_to_pixel(OffsetCoordinate::new(from_x, from_y)));
+    let end = Vec2::from(grid.offset_to_pixel(OffsetCoordinate::new(to_x, to_y)));
+
+    // Nudge the line a little so samples never land exactly on a tile edge or corner.
+    let nudge = Vec2::splat(grid.layout.size[0] * 1e-3);
+
+    let steps = from.distance_to(to, grid).max(1);
+    let mut tiles: Vec<Tile> = Vec::with_capacity(steps as usize + 1);
+    for step in 0..=steps {
+        let position = start.lerp(end, step as f32 / steps as f32) + nudge;
+        if let Some(tile) = world_position_to_tile(position, grid)
+            && tiles.last() != Some(&tile)
+        {
+            tiles.push(tile);
+        }
+    }
+    tiles
+}
+
+/// The elevation of a tile's terrain type, read from the unique
+/// `Has an elevation of [n] for visibility calculations` in the ruleset. Terrain types without it are at 0.
+pub fn tile_elevation(tile: Tile, tile_map: &TileMap, ruleset: &Ruleset) -> u32 {
+    static ELEVATION_UNIQUE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^Has an elevation of \[(\d+)\] for visibility calculations$").unwrap()
+    });
+
+    ruleset.terrain_types[tile.terrain_type(tile_map).as_str()]
+        .uniques
+        .iter()
+        .find_map(|unique| ELEVATION_UNIQUE.captures(unique))
+        .map_or(0, |captures| captures[1].parse().unwrap())
+}
+
+/// The height of a tile as an obstacle to sight.
+///
+/// It is the elevation of the tile, plus 1 if its feature has the unique
+/// `Blocks line-of-sight from tiles at same elevation` (e.g. forest and jungle).
+pub fn sight_obstacle_height(tile: Tile, tile_map: &TileMap, ruleset: &Ruleset) -> u32 {
+    let feature_blocks_sight = tile.feature(tile_map).is_some_and(|feature| {
+        ruleset.features[feature.as_str()]
+            .uniques
+            .iter()
+            .any(|unique| unique == "Blocks line-of-sight from tiles at same elevation")
+    });
+    tile_elevation(tile, tile_map, ruleset) + feature_blocks_sight as u32
+}
+
+/// Return `true` if `to` can be seen from `from`.
+///
+/// Only the tiles between `from` and `to` can block the sight: a tile blocks it
+/// if its [`sight_obstacle_height`] is higher than the elevation of `from`.
+/// So units on flatland can't see behind hills and forests, units on hills can see over forests on flatland,
+/// and mountains block everyone but units standing on mountains.
+pub fn has_line_of_sight(from: Tile, to: Tile, tile_map: &TileMap, ruleset: &Ruleset) -> bool {
+    let viewer_elevation = tile_elevation(from, tile_map, ruleset);
+    let line = tile_line(from, to, tile_map.world_grid.grid);
+    match line.as_slice() {
+        [_, between @ .., _] => between
+            .iter()
+            .all(|&tile| sight_obstacle_height(tile, tile_map, ruleset) <= viewer_elevation),
+        _ => true,
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use civ_map_generator::{ruleset::Ruleset, tile_map::TileMap};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    city::City,
+    civilization::{Civilization, Civilizations, Difficulty},
+    improvement::TileImprovementLayer,
+    turn::{TurnProcessing, TurnSet},
+    unit_component::Owner,
+};
+
+/// Every city makes its civilization 3 unhappier.
+const UNHAPPINESS_PER_CITY: f32 = 3.;
+/// Every citizen makes its civilization 1 unhappier.
+const UNHAPPINESS_PER_CITIZEN: f32 = 1.;
+/// Every different luxury resource makes its civilization 4 happier, before the difficulty bonus.
+const HAPPINESS_PER_LUXURY: f32 = 4.;
+
+/// How happy a civilization is, which decides the penalties applied to its cities.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HappinessLevel {
+    Happy,
+    /// Happiness below 0.
+    Unhappy,
+    /// Happiness at -10 or below.
+    VeryUnhappy,
+}
+
+impl HappinessLevel {
+    pub fn from_happiness(happiness: f32) -> Self {
+        if happiness <= -10. {
+            HappinessLevel::VeryUnhappy
+        } else if happiness < 0. {
+            HappinessLevel::Unhappy
+        } else {
+            HappinessLevel::Happy
+        }
+    }
+
+    /// The part of the food surplus of a city which is kept: unhappy cities grow slower and very unhappy cities don't grow.
+    pub fn growth_modifier(self) -> f32 {
+        match self {
+            HappinessLevel::Happy => 1.,
+            HappinessLevel::Unhappy => 0.25,
+            HappinessLevel::VeryUnhappy => 0.,
+        }
+    }
+
+    /// The part of the production of a city which is kept.
+    pub fn production_modifier(self) -> f32 {
+        match self {
+            HappinessLevel::Happy | HappinessLevel::Unhappy => 1.,
+            HappinessLevel::VeryUnhappy => 0.67,
+        }
+    }
+}
+
+/// Return the luxury resources of the cities: the resources of type `Luxury` revealed to the civilization
+/// on tiles owned by one of the cities, improved with the improvement the resource needs.
+pub fn luxury_resources<'a>(
+    cities: impl IntoIterator<Item = &'a City>,
+    civilization: &Civilization,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+    improvement_layer: &TileImprovementLayer,
+) -> HashSet<String> {
+    cities
+        .into_iter()
+        .flat_map(|city| city.owned_tiles.iter().copied())
+        .filter_map(|tile| {
+            let (resource, _) = civilization.visible_resource(tile, tile_map)?;
+            let resource = &ruleset.tile_resources[resource.as_str()];
+            (resource.resource_type == "Luxury"
+                && improvement_layer.working_improvement(tile)
+                    == Some(resource.improvement.as_str()))
+            .then(|| resource.name.clone())
+        })
+        .collect()
+}
+
+pub struct HappinessPlugin;
+
+impl Plugin for HappinessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(TurnProcessing, update_happiness.in_set(TurnSet::Happiness));
+    }
+}
+
+/// Compute the happiness of every civilization.
+///
+/// Happiness comes from the difficulty, luxuries, buildings and policies.
+/// Unhappiness comes from the number of cities and citizens, scaled by the difficulty.
+pub fn update_happiness(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    difficulty: Res<Difficulty>,
+    improvement_layer: Res<TileImprovementLayer>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let difficulty = difficulty.info(ruleset);
+
+    let mut nation_and_cities: HashMap<_, Vec<_>> = HashMap::new();
+    for (city, owner) in query_city.iter() {
+        nation_and_cities
+            .entry(owner.nation())
+            .or_default()
+            .push(city);
+    }
+
+    for (nation, cities) in nation_and_cities {
+        let civilization = civilizations.get_mut(nation);
+        let luxury_count = luxury_resources(
+            cities.iter().copied(),
+            civilization,
+            tile_map,
+            ruleset,
+            &improvement_layer,
+        )
+        .len();
+        let building_happiness: f32 = cities
+            .iter()
+            .flat_map(|city| &city.buildings)
+            .map(|building| ruleset.buildings[building].happiness)
+            .sum();
+        let population: u32 = cities.iter().map(|city| city.population).sum();
+
+        let happiness = difficulty.base_happiness as f32
+            + luxury_count as f32
+                * (HAPPINESS_PER_LUXURY + difficulty.extra_happiness_per_luxury as f32)
+            + building_happiness;
+        let unhappiness = (cities.len() as f32 * UNHAPPINESS_PER_CITY
+            + population as f32 * UNHAPPINESS_PER_CITIZEN)
+            * difficulty.unhappiness_modifier;
+
+        civilization.happiness = happiness + civilization.effects.happiness - unhappiness;
+    }
+}
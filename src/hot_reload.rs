@@ -0,0 +1,251 @@
+//! Reloading the ruleset and the images when their files change, to try changes to the ruleset and to the mods
+//! without restarting the game. Only active with the `hot_reload` feature, e.g. `cargo run --features hot_reload`,
+//! which also makes the asset server watch the `assets` folder.
+//!
+//! - The ruleset files of the base ruleset and of the enabled mods, and of their subfolders, are checked every
+//!   [`RULESET_CHECK_INTERVAL`] seconds. When one changed, the ruleset is read again, and in a game the yields of all
+//!   the tiles are computed again. The screens read the ruleset when they are opened, so they show the new ruleset
+//!   the next time.
+//! - The images are reloaded by the asset server, and the materials drawing them are prepared again. The texture
+//!   array of `--instanced-tiles` is a copy of the base terrain images made for each map, which keeps the old images.
+//!
+//! The new ruleset replaces the old one only if it is valid: the base ruleset files can be read, they break no
+//! more references than at the start, see [`crate::ruleset_validation`], and every unit, building, technology and
+//! improvement used by the game is still in it. Otherwise the old ruleset is kept until the files are fixed.
+
+use std::{fs, path::Path, sync::Arc, time::SystemTime};
+
+use bevy::prelude::*;
+use civ_map_generator::{ruleset::Ruleset, tile_map::TileMap};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    city::{City, CityProduction},
+    civilization::Civilizations,
+    custom_material::ColorReplaceMaterial,
+    game_event::TileChanged,
+    improvement::TileImprovementLayer,
+    mod_manager::ModList,
+    road::ROAD,
+    ruleset_validation::{BASE_RULESET_PATH, RulesetDiagnostic, RulesetFiles},
+    unit_component::Unit,
+    yields::update_changed_tile_yields,
+};
+
+/// The seconds between two checks of the ruleset files.
+const RULESET_CHECK_INTERVAL: f32 = 1.;
+
+/// The last time a ruleset file changed, checked every [`RULESET_CHECK_INTERVAL`] seconds.
+#[derive(Resource)]
+struct RulesetWatcher {
+    timer: Timer,
+    last_modified: Option<SystemTime>,
+    /// The broken references of the base ruleset at the first check, which a new ruleset may keep.
+    diagnostics: Vec<RulesetDiagnostic>,
+}
+
+pub struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        if !cfg!(feature = "hot_reload") {
+            return;
+        }
+        app.insert_resource(RulesetWatcher {
+            timer: Timer::from_seconds(RULESET_CHECK_INTERVAL, TimerMode::Repeating),
+            last_modified: None,
+            diagnostics: Vec::new(),
+        })
+        .add_systems(
+            Update,
+            (
+                reload_changed_ruleset.before(update_changed_tile_yields),
+                refresh_materials,
+            ),
+        );
+    }
+}
+
+/// Read the ruleset again when one of its files changed since the last check, and use it if it is valid.
+fn reload_changed_ruleset(
+    time: Res<Time>,
+    mut watcher: ResMut<RulesetWatcher>,
+    mod_list: Res<ModList>,
+    mut ruleset: ResMut<RulesetResource>,
+    map: Option<Res<TileMapResource>>,
+    civilizations: Option<Res<Civilizations>>,
+    improvement_layer: Option<Res<TileImprovementLayer>>,
+    mut tile_changed: MessageWriter<TileChanged>,
+    query_unit: Query<&Unit>,
+    query_city: Query<&City>,
+) {
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let folders = std::iter::once(Path::new(BASE_RULESET_PATH).to_owned())
+        .chain(mod_list.enabled_folders().map(|(_, folder)| folder));
+    let last_modified = folders.filter_map(|folder| last_modified(&folder)).max();
+    let previous = std::mem::replace(&mut watcher.last_modified, last_modified);
+    // The first check only records the time and the broken references, the ruleset was just read.
+    if previous.is_none() {
+        watcher.diagnostics = RulesetFiles::read_base()
+            .map(|ruleset_files| ruleset_files.validate())
+            .unwrap_or_default();
+        return;
+    }
+    if previous == last_modified {
+        return;
+    }
+
+    info!("The ruleset files changed, reading the ruleset again");
+    let ruleset_files = match RulesetFiles::read_base() {
+        Ok(ruleset_files) => ruleset_files,
+        Err(error) => {
+            error!("Keeping the old ruleset, the base ruleset files can't be read: {error}");
+            return;
+        }
+    };
+    let new_diagnostics: Vec<_> = ruleset_files
+        .validate()
+        .into_iter()
+        .filter(|diagnostic| !watcher.diagnostics.contains(diagnostic))
+        .collect();
+    if !new_diagnostics.is_empty() {
+        error!("Keeping the old ruleset, the base ruleset breaks references:");
+        for diagnostic in new_diagnostics {
+            error!("  {diagnostic}");
+        }
+        return;
+    }
+
+    let new_ruleset = mod_list.ruleset();
+    let missing_entries = missing_entries(
+        &new_ruleset,
+        map.as_deref().map(|map| &map.0),
+        civilizations.as_deref(),
+        improvement_layer.as_deref(),
+        &query_unit,
+        &query_city,
+    );
+    if !missing_entries.is_empty() {
+        error!("Keeping the old ruleset, the game uses entries missing from the new ruleset:");
+        for entry in missing_entries {
+            error!("  {entry}");
+        }
+        return;
+    }
+
+    ruleset.0 = Arc::new(new_ruleset);
+    if let Some(map) = map {
+        tile_changed.write_batch(map.0.all_tiles().map(TileChanged));
+    }
+}
+
+/// The entries used by the game which are missing from `ruleset`: the units on the map, the buildings and the
+/// productions of the cities, the researched technologies, the improvements of the map and the road.
+/// The systems look them up by name, and would panic on a missing entry.
+fn missing_entries(
+    ruleset: &Ruleset,
+    tile_map: Option<&TileMap>,
+    civilizations: Option<&Civilizations>,
+    improvement_layer: Option<&TileImprovementLayer>,
+    query_unit: &Query<&Unit>,
+    query_city: &Query<&City>,
+) -> Vec<String> {
+    let mut missing_entries = Vec::new();
+    let mut check = |is_present: bool, kind: &str, name: &str| {
+        let entry = format!("{kind} {name}");
+        if !is_present && !missing_entries.contains(&entry) {
+            missing_entries.push(entry);
+        }
+    };
+
+    for unit in query_unit.iter() {
+        check(ruleset.units.contains_key(unit.name()), "unit", unit.name());
+    }
+    for city in query_city.iter() {
+        for building in &city.buildings {
+            check(
+                ruleset.buildings.contains_key(building),
+                "building",
+                building,
+            );
+        }
+        match &city.production {
+            Some(CityProduction::Unit(unit_name)) => {
+                check(ruleset.units.contains_key(unit_name), "unit", unit_name)
+            }
+            Some(CityProduction::Building(building)) => check(
+                ruleset.buildings.contains_key(building),
+                "building",
+                building,
+            ),
+            Some(CityProduction::Gold) | None => {}
+        }
+    }
+    for (_, civilization) in civilizations.into_iter().flat_map(Civilizations::iter) {
+        for technology in &civilization.researched_technologies {
+            check(
+                ruleset.technologies.contains_key(technology),
+                "technology",
+                technology,
+            );
+        }
+    }
+    if let (Some(tile_map), Some(improvement_layer)) = (tile_map, improvement_layer) {
+        for tile in tile_map.all_tiles() {
+            if let Some(improvement) = improvement_layer.improvement(tile) {
+                check(
+                    ruleset.tile_improvements.contains_key(improvement),
+                    "improvement",
+                    improvement,
+                );
+            }
+        }
+    }
+    check(
+        ruleset.tile_improvements.contains_key(ROAD),
+        "improvement",
+        ROAD,
+    );
+    missing_entries
+}
+
+/// The last time the folder, one of its files or one of its subfolders changed. A folder changes when a file is
+/// added or removed.
+fn last_modified(folder: &Path) -> Option<SystemTime> {
+    let folder_modified = fs::metadata(folder).and_then(|metadata| metadata.modified());
+    fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            if entry.file_type().ok()?.is_dir() {
+                last_modified(&entry.path())
+            } else {
+                entry.metadata().ok()?.modified().ok()
+            }
+        })
+        .chain(folder_modified.ok())
+        .max()
+}
+
+/// Prepare the materials again when an image is reloaded, so they don't keep drawing the old image.
+fn refresh_materials(
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    mut color_replace_materials: ResMut<Assets<ColorReplaceMaterial>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let reloaded = image_events
+        .read()
+        .filter(|event| matches!(event, AssetEvent::Modified { .. }))
+        .count()
+        > 0;
+    if !reloaded {
+        return;
+    }
+    // Iterating mutably marks every material as modified.
+    for _ in color_replace_materials.iter_mut() {}
+    for _ in color_materials.iter_mut() {}
+}
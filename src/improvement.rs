@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use civ_map_generator::{grid::Grid, tile::Tile};
+use serde::{Deserialize, Serialize};
+
+use crate::{TileMapResource, assets::AppState, game_event::TileChanged};
+
+/// The improvements and roads built on the tiles of the map.
+///
+/// The generated [`civ_map_generator::tile_map::TileMap`] has no improvements, so they are stored here, indexed by tile.
+/// Always change them through the methods of this resource, so a [`TileChanged`] message is written for every change.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct TileImprovementLayer {
+    improvement_list: Vec<Option<String>>,
+    road_list: Vec<bool>,
+    /// Pillaged improvements and roads stay on their tile, but do nothing until a worker repairs them.
+    pillaged_improvement_list: Vec<bool>,
+    pillaged_road_list: Vec<bool>,
+}
+
+impl TileImprovementLayer {
+    pub fn new(tile_count: usize) -> Self {
+        Self {
+            improvement_list: vec![None; tile_count],
+            road_list: vec![false; tile_count],
+            pillaged_improvement_list: vec![false; tile_count],
+            pillaged_road_list: vec![false; tile_count],
+        }
+    }
+
+    pub fn improvement(&self, tile: Tile) -> Option<&str> {
+        self.improvement_list[tile.index()].as_deref()
+    }
+
+    pub fn has_road(&self, tile: Tile) -> bool {
+        self.road_list[tile.index()]
+    }
+
+    pub fn is_improvement_pillaged(&self, tile: Tile) -> bool {
+        self.pillaged_improvement_list[tile.index()]
+    }
+
+    pub fn is_road_pillaged(&self, tile: Tile) -> bool {
+        self.pillaged_road_list[tile.index()]
+    }
+
+    /// The improvement of the tile, unless it is pillaged.
+    pub fn working_improvement(&self, tile: Tile) -> Option<&str> {
+        self.improvement(tile)
+            .filter(|_| !self.is_improvement_pillaged(tile))
+    }
+
+    /// Return `true` if the tile has a road which is not pillaged.
+    pub fn has_working_road(&self, tile: Tile) -> bool {
+        self.has_road(tile) && !self.is_road_pillaged(tile)
+    }
+
+    pub fn set_improvement(
+        &mut self,
+        tile: Tile,
+        improvement: Option<String>,
+        tile_changed: &mut MessageWriter<TileChanged>,
+    ) {
+        self.improvement_list[tile.index()] = improvement;
+        self.pillaged_improvement_list[tile.index()] = false;
+        tile_changed.write(TileChanged(tile));
+    }
+
+    pub fn set_road(
+        &mut self,
+        tile: Tile,
+        has_road: bool,
+        tile_changed: &mut MessageWriter<TileChanged>,
+    ) {
+        self.road_list[tile.index()] = has_road;
+        self.pillaged_road_list[tile.index()] = false;
+        tile_changed.write(TileChanged(tile));
+    }
+
+    pub fn set_improvement_pillaged(
+        &mut self,
+        tile: Tile,
+        is_pillaged: bool,
+        tile_changed: &mut MessageWriter<TileChanged>,
+    ) {
+        self.pillaged_improvement_list[tile.index()] = is_pillaged;
+        tile_changed.write(TileChanged(tile));
+    }
+
+    pub fn set_road_pillaged(
+        &mut self,
+        tile: Tile,
+        is_pillaged: bool,
+        tile_changed: &mut MessageWriter<TileChanged>,
+    ) {
+        self.pillaged_road_list[tile.index()] = is_pillaged;
+        tile_changed.write(TileChanged(tile));
+    }
+}
+
+pub struct ImprovementPlugin;
+
+impl Plugin for ImprovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameStart), setup_improvement_layer);
+    }
+}
+
+fn setup_improvement_layer(mut commands: Commands, map: Res<TileMapResource>) {
+    let grid = map.0.world_grid.grid;
+    let tile_count = (grid.width() * grid.height()) as usize;
+    commands.insert_resource(TileImprovementLayer::new(tile_count));
+}
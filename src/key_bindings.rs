@@ -0,0 +1,272 @@
+//! The keys bound to the actions of the player, e.g. moving the camera or giving an order to the selected unit, kept
+//! between games in the settings of the player, see [`crate::settings`], and changed in the options of the pause
+//! menu, see [`crate::pause_menu`].
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// An action of the player which can be bound to keys.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum InputAction {
+    CameraUp,
+    CameraDown,
+    CameraLeft,
+    CameraRight,
+    ZoomIn,
+    ZoomOut,
+    /// Pick the tile the selected unit moves to with the next left click, see [`crate::world_map::MoveMode`].
+    MoveUnit,
+    Fortify,
+    Alert,
+    Heal,
+    Sleep,
+    Explore,
+    /// Let the selected unit wait until the next turn, and select the next unit waiting for orders.
+    SkipUnit,
+    FoundCity,
+    BuildRoad,
+    Pillage,
+    Repair,
+    UpgradeUnit,
+    /// Select the next unit with movement points left waiting for orders, and move the camera to it.
+    NextUnit,
+    PreviousUnit,
+}
+
+impl InputAction {
+    pub const ALL: [Self; 20] = [
+        Self::CameraUp,
+        Self::CameraDown,
+        Self::CameraLeft,
+        Self::CameraRight,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::MoveUnit,
+        Self::Fortify,
+        Self::Alert,
+        Self::Heal,
+        Self::Sleep,
+        Self::Explore,
+        Self::SkipUnit,
+        Self::FoundCity,
+        Self::BuildRoad,
+        Self::Pillage,
+        Self::Repair,
+        Self::UpgradeUnit,
+        Self::NextUnit,
+        Self::PreviousUnit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::CameraUp => "Move camera up",
+            Self::CameraDown => "Move camera down",
+            Self::CameraLeft => "Move camera left",
+            Self::CameraRight => "Move camera right",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::MoveUnit => "Move unit",
+            Self::Fortify => "Fortify",
+            Self::Alert => "Alert",
+            Self::Heal => "Heal",
+            Self::Sleep => "Sleep",
+            Self::Explore => "Explore",
+            Self::SkipUnit => "Skip unit",
+            Self::FoundCity => "Found city",
+            Self::BuildRoad => "Build road",
+            Self::Pillage => "Pillage",
+            Self::Repair => "Repair",
+            Self::UpgradeUnit => "Upgrade unit",
+            Self::NextUnit => "Next unit",
+            Self::PreviousUnit => "Previous unit",
+        }
+    }
+
+    /// The camera moves with the arrows, so the letters are left to the orders of the units, as in Civ V.
+    fn default_keys(self) -> Vec<KeyCode> {
+        match self {
+            Self::CameraUp => vec![KeyCode::ArrowUp],
+            Self::CameraDown => vec![KeyCode::ArrowDown],
+            Self::CameraLeft => vec![KeyCode::ArrowLeft],
+            Self::CameraRight => vec![KeyCode::ArrowRight],
+            Self::ZoomIn => vec![KeyCode::KeyE],
+            Self::ZoomOut => vec![KeyCode::KeyQ],
+            Self::MoveUnit => vec![KeyCode::KeyM],
+            Self::Fortify => vec![KeyCode::KeyF],
+            Self::Alert => vec![KeyCode::KeyA, KeyCode::KeyX],
+            Self::Heal => vec![KeyCode::KeyH],
+            Self::Sleep => vec![KeyCode::KeyZ],
+            Self::Explore => vec![KeyCode::KeyO],
+            Self::SkipUnit => vec![KeyCode::Space],
+            Self::FoundCity => vec![KeyCode::KeyB],
+            Self::BuildRoad => vec![KeyCode::KeyW],
+            Self::Pillage => vec![KeyCode::KeyP],
+            Self::Repair => vec![KeyCode::KeyR],
+            Self::UpgradeUnit => vec![KeyCode::KeyU],
+            Self::NextUnit => vec![KeyCode::Tab, KeyCode::Period],
+            Self::PreviousUnit => vec![KeyCode::Comma],
+        }
+    }
+}
+
+/// The keys which can be bound to an action, with their names in the settings file and in the options.
+const BINDABLE_KEYS: [(KeyCode, &str); 52] = [
+    (KeyCode::KeyA, "A"),
+    (KeyCode::KeyB, "B"),
+    (KeyCode::KeyC, "C"),
+    (KeyCode::KeyD, "D"),
+    (KeyCode::KeyE, "E"),
+    (KeyCode::KeyF, "F"),
+    (KeyCode::KeyG, "G"),
+    (KeyCode::KeyH, "H"),
+    (KeyCode::KeyI, "I"),
+    (KeyCode::KeyJ, "J"),
+    (KeyCode::KeyK, "K"),
+    (KeyCode::KeyL, "L"),
+    (KeyCode::KeyM, "M"),
+    (KeyCode::KeyN, "N"),
+    (KeyCode::KeyO, "O"),
+    (KeyCode::KeyP, "P"),
+    (KeyCode::KeyQ, "Q"),
+    (KeyCode::KeyR, "R"),
+    (KeyCode::KeyS, "S"),
+    (KeyCode::KeyT, "T"),
+    (KeyCode::KeyU, "U"),
+    (KeyCode::KeyV, "V"),
+    (KeyCode::KeyW, "W"),
+    (KeyCode::KeyX, "X"),
+    (KeyCode::KeyY, "Y"),
+    (KeyCode::KeyZ, "Z"),
+    (KeyCode::Digit0, "0"),
+    (KeyCode::Digit1, "1"),
+    (KeyCode::Digit2, "2"),
+    (KeyCode::Digit3, "3"),
+    (KeyCode::Digit4, "4"),
+    (KeyCode::Digit5, "5"),
+    (KeyCode::Digit6, "6"),
+    (KeyCode::Digit7, "7"),
+    (KeyCode::Digit8, "8"),
+    (KeyCode::Digit9, "9"),
+    (KeyCode::ArrowUp, "Up"),
+    (KeyCode::ArrowDown, "Down"),
+    (KeyCode::ArrowLeft, "Left"),
+    (KeyCode::ArrowRight, "Right"),
+    (KeyCode::PageUp, "Page Up"),
+    (KeyCode::PageDown, "Page Down"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Minus, "-"),
+    (KeyCode::Equal, "="),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Comma, ","),
+    (KeyCode::Period, "."),
+];
+
+/// The name of a key which can be bound, see [`BINDABLE_KEYS`].
+pub fn key_name(key: KeyCode) -> Option<&'static str> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|&&(bindable_key, _)| bindable_key == key)
+        .map(|&(_, name)| name)
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|&&(_, key_name)| key_name == name)
+        .map(|&(key, _)| key)
+}
+
+/// The keys bound to every action, saved by the names of the keys.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(
+    from = "HashMap<InputAction, Vec<String>>",
+    into = "HashMap<InputAction, Vec<String>>"
+)]
+pub struct KeyBindings(HashMap<InputAction, Vec<KeyCode>>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self(
+            InputAction::ALL
+                .into_iter()
+                .map(|action| (action, action.default_keys()))
+                .collect(),
+        )
+    }
+}
+
+/// The actions missing from the saved bindings, or bound to unknown keys only, keep their default keys.
+impl From<HashMap<InputAction, Vec<String>>> for KeyBindings {
+    fn from(saved: HashMap<InputAction, Vec<String>>) -> Self {
+        let mut key_bindings = Self::default();
+        for (action, names) in saved {
+            let keys: Vec<KeyCode> = names
+                .iter()
+                .filter_map(|name| key_from_name(name))
+                .collect();
+            if !keys.is_empty() {
+                key_bindings.0.insert(action, keys);
+            }
+        }
+        key_bindings
+    }
+}
+
+impl From<KeyBindings> for HashMap<InputAction, Vec<String>> {
+    fn from(key_bindings: KeyBindings) -> Self {
+        key_bindings
+            .0
+            .into_iter()
+            .map(|(action, keys)| {
+                (
+                    action,
+                    keys.into_iter()
+                        .filter_map(key_name)
+                        .map(str::to_owned)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: InputAction) -> &[KeyCode] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bind `action` to `key` only.
+    pub fn bind(&mut self, action: InputAction, key: KeyCode) {
+        self.0.insert(action, vec![key]);
+    }
+
+    /// Whether a key of `action` is held down.
+    pub fn pressed(&self, action: InputAction, keyboard_input: &ButtonInput<KeyCode>) -> bool {
+        keyboard_input.any_pressed(self.keys(action).iter().copied())
+    }
+
+    /// Whether a key of `action` was pressed this frame.
+    pub fn just_pressed(&self, action: InputAction, keyboard_input: &ButtonInput<KeyCode>) -> bool {
+        keyboard_input.any_just_pressed(self.keys(action).iter().copied())
+    }
+
+    /// The keys of `action`, e.g. "W, Up".
+    pub fn keys_label(&self, action: InputAction) -> String {
+        let names: Vec<&str> = self
+            .keys(action)
+            .iter()
+            .filter_map(|&key| key_name(key))
+            .collect();
+        if names.is_empty() {
+            "None".to_owned()
+        } else {
+            names.join(", ")
+        }
+    }
+}
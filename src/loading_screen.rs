@@ -0,0 +1,189 @@
+//! The loading screen shown while a new map is generated: a progress bar with the current stage of the generation,
+//! see [`MapGenerationProgress`], and gameplay tips changing every few seconds.
+
+use bevy::prelude::*;
+
+use crate::{
+    MapSetting,
+    assets::AppState,
+    generating_map::{MapGenerationProgress, check_map_generate_status},
+};
+
+/// The gameplay tips, defined with the ruleset data in `assets/Tips/Tips.json`.
+const TIPS_JSON: &str = include_str!("../assets/Tips/Tips.json");
+/// The seconds a tip stays on the loading screen.
+const TIP_SECONDS: f32 = 6.;
+
+/// The gameplay tips of the ruleset.
+#[derive(Resource)]
+pub struct LoadingTips(pub Vec<String>);
+
+impl Default for LoadingTips {
+    fn default() -> Self {
+        Self(serde_json::from_str(TIPS_JSON).expect("Tips.json should be valid"))
+    }
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingBar;
+
+#[derive(Component)]
+struct LoadingStageText;
+
+/// The tip shown, and the time before the next one.
+#[derive(Component)]
+struct LoadingTipText {
+    index: usize,
+    timer: Timer,
+}
+
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadingTips>()
+            .add_systems(OnEnter(AppState::MapGenerating), setup_loading_screen)
+            .add_systems(OnExit(AppState::MapGenerating), despawn_loading_screen)
+            .add_systems(
+                Update,
+                (
+                    update_loading_progress
+                        .after(check_map_generate_status)
+                        .run_if(resource_exists::<MapGenerationProgress>),
+                    rotate_loading_tips,
+                )
+                    .run_if(in_state(AppState::MapGenerating)),
+            );
+    }
+}
+
+fn setup_loading_screen(
+    mut commands: Commands,
+    map_setting: Res<MapSetting>,
+    loading_tips: Res<LoadingTips>,
+) {
+    // The first tip depends on the seed, so a new game doesn't always start with the same tip.
+    let index = (map_setting.0.seed as usize)
+        .checked_rem(loading_tips.0.len())
+        .unwrap_or_default();
+    let tip = loading_tips.0.get(index).cloned().unwrap_or_default();
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK),
+            GlobalZIndex(30),
+            LoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("Civilization-Remastered".to_owned()),
+                TextFont {
+                    font_size: 32.,
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(400.),
+                        height: Val::Px(12.),
+                        border: UiRect::all(Val::Px(1.)),
+                        ..default()
+                    },
+                    BorderColor::all(Color::WHITE),
+                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                    Pickable::IGNORE,
+                ))
+                .with_child((
+                    Node {
+                        width: Val::Percent(0.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.9, 0.75, 0.3)),
+                    LoadingBar,
+                    Pickable::IGNORE,
+                ));
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+                LoadingStageText,
+                Pickable::IGNORE,
+            ));
+            parent.spawn((
+                Node {
+                    max_width: Val::Px(480.),
+                    margin: UiRect::top(Val::Px(24.)),
+                    ..default()
+                },
+                Text(tip),
+                TextFont {
+                    font_size: 14.,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.8, 0.8, 0.8)),
+                TextLayout::new_with_justify(Justify::Center),
+                LoadingTipText {
+                    index,
+                    timer: Timer::from_seconds(TIP_SECONDS, TimerMode::Repeating),
+                },
+                Pickable::IGNORE,
+            ));
+        });
+}
+
+fn despawn_loading_screen(
+    mut commands: Commands,
+    query_screen: Query<Entity, With<LoadingScreen>>,
+) {
+    for screen in query_screen.iter() {
+        commands.entity(screen).despawn();
+    }
+}
+
+/// Fill the bar with the progress of the generation, and show its stage.
+fn update_loading_progress(
+    progress: Res<MapGenerationProgress>,
+    mut query_bar: Query<&mut Node, With<LoadingBar>>,
+    mut query_stage: Query<&mut Text, With<LoadingStageText>>,
+) {
+    let fraction = progress.fraction().clamp(0., 1.);
+    for mut node in query_bar.iter_mut() {
+        node.width = Val::Percent(fraction * 100.);
+    }
+    for mut text in query_stage.iter_mut() {
+        text.0 = format!("{}... {:.0}%", progress.stage.name(), fraction * 100.);
+    }
+}
+
+fn rotate_loading_tips(
+    time: Res<Time>,
+    loading_tips: Res<LoadingTips>,
+    mut query_tip: Query<(&mut Text, &mut LoadingTipText)>,
+) {
+    if loading_tips.0.is_empty() {
+        return;
+    }
+    for (mut text, mut tip) in query_tip.iter_mut() {
+        if tip.timer.tick(time.delta()).just_finished() {
+            tip.index = (tip.index + 1) % loading_tips.0.len();
+            text.0 = loading_tips.0[tip.index].clone();
+        }
+    }
+}
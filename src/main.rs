@@ -5,38 +5,193 @@ use bevy_asset_loader::loading_state::{
 };
 
 use civ_map_generator::{
-    grid::{
-        Grid, GridSize, WorldSizeType, WrapFlags,
-        hex_grid::{HexGrid, HexLayout, HexOrientation, Offset},
-    },
-    map_parameters::{MapParameters, MapParametersBuilder, WorldGrid},
+    grid::{Grid, WrapFlags},
+    map_parameters::MapParameters,
     ruleset::Ruleset,
     tile_map::TileMap,
 };
 
-use assets::{AppState, MaterialResource};
+use assets::{AppState, AudioResource, MaterialResource};
 
 use bevy::{
     camera::visibility::RenderLayers, input::mouse::MouseWheel, input_focus::InputFocus,
-    prelude::*, sprite_render::Material2dPlugin, window::WindowResolution,
+    prelude::*, sprite_render::Material2dPlugin,
 };
 
 use crate::{
-    custom_material::ColorReplaceMaterial,
+    accessibility::AccessibilityPlugin,
+    ai::AiPlugin,
+    ambient_sound::AmbientSoundPlugin,
+    animation::GameAnimationPlugin,
+    audio::GameAudioPlugin,
+    barbarian::BarbarianPlugin,
+    camera::{CameraCommands, CameraPlugin, EdgePanSettings},
+    city::CityPlugin,
+    city_banner::CityBannerPlugin,
+    city_screen::CityScreenPlugin,
+    city_state::CityStatePlugin,
+    civilization::CivilizationPlugin,
+    combat::CombatPlugin,
+    combat_effect::CombatEffectPlugin,
+    command::CommandPlugin,
+    connection::ConnectionPlugin,
+    custom_material::{ColorReplaceMaterial, TileInstanceMaterial},
+    deal::DealPlugin,
+    debug_overlay::DebugOverlayPlugin,
+    demographics::DemographicsPlugin,
+    diplomacy::DiplomacyPlugin,
+    diplomacy_screen::DiplomacyScreenPlugin,
+    economy::EconomyPlugin,
+    embarkation::EmbarkationPlugin,
+    end_turn_button::EndTurnButtonPlugin,
+    era::EraPlugin,
+    espionage::EspionagePlugin,
+    fonts::FontsPlugin,
+    game_event::GameEventPlugin,
     generating_map::{check_map_generate_status, generate_tile_map},
-    minimap::{DefaultFovIndicatorSize, minimap_fov_update, setup_minimap},
-    technology::setup_tech_button,
-    world_map::{setup_tile_map, show_main_camera_area},
+    golden_age::GoldenAgePlugin,
+    great_person::GreatPersonPlugin,
+    grid::{map_pixel_width, wrap_x_position},
+    happiness::HappinessPlugin,
+    hot_reload::HotReloadPlugin,
+    improvement::ImprovementPlugin,
+    key_bindings::{InputAction, KeyBindings},
+    loading_screen::LoadingScreenPlugin,
+    main_menu::{MainMenuPlugin, NewGameSettings},
+    map_lens::MapLensPlugin,
+    minimap::{
+        DefaultFovIndicatorSize, MinimapFog, MinimapLayout, MinimapMode, apply_minimap_layout,
+        cycle_minimap_mode_on_key, minimap_fov_update, setup_minimap, update_minimap_fog,
+        update_minimap_tiles,
+    },
+    mod_manager::{ModList, ModManagerPlugin},
+    natural_wonder::NaturalWonderPlugin,
+    naval::NavalPlugin,
+    network::NetworkPlugin,
+    notification::NotificationPlugin,
+    overlay_chunk::OverlayChunkPlugin,
+    pathfinding::ZoneOfControlRule,
+    pause_menu::PauseMenuPlugin,
+    pillage::PillagePlugin,
+    policy::PolicyPlugin,
+    random_event::RandomEventPlugin,
+    rng::GameRng,
+    road::RoadPlugin,
+    route_overlay::RouteOverlayPlugin,
+    save::SavePlugin,
+    scenario::{PendingScenario, ScenarioPlugin},
+    screenshot::ScreenshotPlugin,
+    settings::{SettingsPlugin, UserSettings},
+    specialist::SpecialistPlugin,
+    technology::{TechTreeScreen, TechnologyPlugin, setup_tech_button},
+    territory_border::TerritoryBorderPlugin,
+    tile_instancing::{TileInstancingPlugin, setup_instanced_tiles},
+    turn::TurnPlugin,
+    turn_blocker::TurnBlockerPlugin,
+    unit::UnitPlugin,
+    unit_group::{UnitGroupPlugin, band_select_held},
+    unit_order::UnitOrderPlugin,
+    unit_sprite::{MoveAnimation, UnitSpritePlugin},
+    unit_status::UnitStatusPlugin,
+    unit_upgrade::UnitUpgradePlugin,
+    visibility::{VisibilityLayer, VisibilityPlugin},
+    wonder::WonderPlugin,
+    worked_tiles::WorkedTilesPlugin,
+    world_map::{
+        MoveMode, MovePathPreview, SelectedUnit, TileChunks, attack_on_right_click,
+        deselect_on_escape, draw_move_path_preview, move_order_on_right_click,
+        position_tile_chunks, select_unit_on_click, setup_tile_map, show_main_camera_area,
+        update_path_turn_badges, update_resource_icons, update_tile_chunks, update_tile_tooltip,
+    },
+    yield_overlay::YieldOverlayPlugin,
+    yields::YieldsPlugin,
 };
 
+mod accessibility;
+mod ai;
+mod ambient_sound;
+mod animation;
 mod assets;
+mod audio;
+mod barbarian;
+mod camera;
+mod city;
+mod city_banner;
+mod city_screen;
+mod city_state;
+mod civ5_xml;
+mod civilization;
+mod combat;
+mod combat_effect;
+mod command;
+mod connection;
 mod custom_material;
 mod custom_mesh;
+mod deal;
+mod debug_overlay;
+mod demographics;
+mod diplomacy;
+mod diplomacy_screen;
+mod economy;
+mod effect;
+mod embarkation;
+mod end_turn_button;
+mod era;
+mod espionage;
+mod fonts;
+mod game_event;
 mod generating_map;
+mod golden_age;
+mod great_person;
+mod grid;
+mod happiness;
+mod hot_reload;
+mod improvement;
+mod key_bindings;
+mod loading_screen;
+mod main_menu;
+mod map_lens;
 mod minimap;
+mod mod_manager;
+mod natural_wonder;
+mod naval;
+mod network;
+mod notification;
+mod overlay_chunk;
+mod pathfinding;
+mod pause_menu;
+mod pillage;
+mod policy;
+mod random_event;
+mod rng;
+mod road;
+mod route_overlay;
+mod ruleset_schema;
+mod ruleset_validation;
+mod save;
+mod scenario;
+mod screenshot;
+mod settings;
+mod specialist;
 mod technology;
+mod territory_border;
+mod tile_instancing;
+mod turn;
+mod turn_blocker;
+mod unciv_ruleset;
+mod unit;
 mod unit_component;
+mod unit_group;
+mod unit_order;
+mod unit_sprite;
+mod unit_status;
+mod unit_upgrade;
+mod visibility;
+mod wonder;
+mod worked_tiles;
 mod world_map;
+mod yield_overlay;
+mod yields;
 
 #[derive(Resource)]
 pub struct RulesetResource(Arc<Ruleset>);
@@ -52,84 +207,192 @@ fn main() {
     let ruleset = Ruleset::default();
     let ruleset_resource = RulesetResource(Arc::new(ruleset));
 
-    // Create map parameters resource
-    let world_size_type = WorldSizeType::Standard;
-    let grid = HexGrid {
-        size: HexGrid::default_size(world_size_type),
-        layout: HexLayout {
-            orientation: HexOrientation::Pointy,
-            size: [50., 50.],
-            origin: [0., 0.],
-        },
-        wrap_flags: WrapFlags::WrapX,
-        offset: Offset::Odd,
-    };
-    let world_grid = WorldGrid::from_grid(grid);
+    // Create map parameters resource, replaced by the choices of the player in the main menu
+    let new_game_settings = NewGameSettings::default();
+    let map_parameters = new_game_settings.map_parameters();
 
-    let map_parameters = MapParametersBuilder::new(world_grid).build();
+    // Create the gameplay random number generator, seeded from the seed of the game
+    let game_rng = GameRng::new(new_game_settings.seed);
 
     let map_setting = MapSetting(Arc::new(map_parameters));
 
     // Create default fov indicator size resource
     let default_fov_indicator_size = DefaultFovIndicatorSize::default();
 
+    // The settings of the player, read before the window is created
+    let user_settings = UserSettings::load();
+
     // App setup
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Civilization-Remastered".to_owned(),
-                resolution: WindowResolution::new(1280, 720),
-                window_level: bevy::window::WindowLevel::AlwaysOnTop,
-                ..default()
-            }),
+            primary_window: Some(user_settings.window()),
             ..default()
         }))
         .add_plugins(Material2dPlugin::<ColorReplaceMaterial>::default())
+        .add_plugins(Material2dPlugin::<TileInstanceMaterial>::default())
+        .add_plugins((
+            TurnPlugin,
+            CivilizationPlugin,
+            UnitPlugin,
+            CityPlugin,
+            VisibilityPlugin,
+            ImprovementPlugin,
+            YieldsPlugin,
+            TechnologyPlugin,
+            EraPlugin,
+            PolicyPlugin,
+            HappinessPlugin,
+            EconomyPlugin,
+            CombatPlugin,
+            GreatPersonPlugin,
+            GoldenAgePlugin,
+        ))
+        .add_plugins((
+            BarbarianPlugin,
+            CityStatePlugin,
+            DiplomacyPlugin,
+            AiPlugin,
+            EspionagePlugin,
+            DemographicsPlugin,
+            NotificationPlugin,
+            SavePlugin,
+            CommandPlugin,
+            NetworkPlugin,
+            EmbarkationPlugin,
+            NavalPlugin,
+            UnitOrderPlugin,
+            PillagePlugin,
+        ))
+        .add_plugins((
+            WonderPlugin,
+            SpecialistPlugin,
+            ConnectionPlugin,
+            DealPlugin,
+            RandomEventPlugin,
+            ScenarioPlugin,
+            YieldOverlayPlugin,
+            UnitSpritePlugin,
+            CityBannerPlugin,
+            CityScreenPlugin,
+            CameraPlugin,
+            TileInstancingPlugin,
+        ))
+        .add_plugins((
+            NaturalWonderPlugin,
+            DiplomacyScreenPlugin,
+            MainMenuPlugin,
+            PauseMenuPlugin,
+            FontsPlugin,
+            AccessibilityPlugin,
+            DebugOverlayPlugin,
+            ScreenshotPlugin,
+            GameAudioPlugin,
+            AmbientSoundPlugin,
+            GameAnimationPlugin,
+            CombatEffectPlugin,
+            UnitStatusPlugin,
+            TurnBlockerPlugin,
+            EndTurnButtonPlugin,
+        ))
+        .add_plugins((
+            WorkedTilesPlugin,
+            TerritoryBorderPlugin,
+            RouteOverlayPlugin,
+            UnitUpgradePlugin,
+            UnitGroupPlugin,
+            SettingsPlugin,
+            LoadingScreenPlugin,
+            OverlayChunkPlugin,
+            MapLensPlugin,
+            ModManagerPlugin,
+            HotReloadPlugin,
+            GameEventPlugin,
+            RoadPlugin,
+        ))
         .init_resource::<InputFocus>()
         .insert_resource(ruleset_resource)
         .insert_resource(map_setting)
+        .insert_resource(new_game_settings)
+        .insert_resource(game_rng)
         .insert_resource(default_fov_indicator_size)
+        .insert_resource(MinimapLayout::load())
+        .insert_resource(user_settings.key_bindings.clone())
+        .insert_resource(ModList::scan(&user_settings.mods))
+        .insert_resource(user_settings.audio)
+        .insert_resource(UiScale(user_settings.ui_scale))
+        .insert_resource(user_settings)
+        .init_resource::<SelectedUnit>()
+        .init_resource::<MovePathPreview>()
+        .init_resource::<MoveMode>()
+        .init_resource::<MinimapMode>()
+        .init_resource::<TileChunks>()
+        .init_resource::<ZoneOfControlRule>()
         .init_state::<AppState>()
         .add_loading_state(
             LoadingState::new(AppState::AssetLoading)
-                .continue_to_state(AppState::MapGenerating)
-                .load_collection::<MaterialResource>(),
+                .continue_to_state(AppState::MainMenu)
+                .load_collection::<MaterialResource>()
+                .init_resource::<AudioResource>(),
         )
         .add_systems(OnEnter(AppState::AssetLoading), main_camera_setup)
         .add_systems(
             Update,
             (
-                main_camera_movement,
-                cursor_drag_system,
-                zoom_main_camera_system,
+                (
+                    main_camera_movement,
+                    edge_pan_main_camera,
+                    cursor_drag_system,
+                    zoom_main_camera_system,
+                )
+                    .run_if(not(any_with_component::<TechTreeScreen>)),
                 minimap_fov_update.run_if(in_state(AppState::GameStart)),
                 setup_minimap.run_if(in_state(AppState::GameStart)),
+                (
+                    cycle_minimap_mode_on_key,
+                    update_minimap_tiles,
+                    update_minimap_fog.run_if(
+                        resource_exists_and_changed::<VisibilityLayer>
+                            .or(resource_added::<MinimapFog>),
+                    ),
+                    apply_minimap_layout,
+                )
+                    .chain()
+                    .after(setup_minimap)
+                    .run_if(in_state(AppState::GameStart)),
                 show_main_camera_area.run_if(in_state(AppState::GameStart)),
+                (
+                    (setup_instanced_tiles, update_tile_chunks)
+                        .chain()
+                        .run_if(resource_changed::<TileMapResource>),
+                    position_tile_chunks.after(show_main_camera_area),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart).and(resource_exists::<TileMapResource>)),
+                (
+                    select_unit_on_click,
+                    (attack_on_right_click, move_order_on_right_click)
+                        .chain()
+                        .run_if(not(any_with_component::<MoveAnimation>)),
+                    deselect_on_escape,
+                    draw_move_path_preview,
+                    update_path_turn_badges,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+                update_tile_tooltip.run_if(in_state(AppState::GameStart)),
+                update_resource_icons.run_if(in_state(AppState::GameStart)),
                 check_map_generate_status.run_if(in_state(AppState::MapGenerating)),
             ),
         )
-        .add_systems(OnEnter(AppState::MapGenerating), generate_tile_map)
+        .add_systems(
+            OnEnter(AppState::MapGenerating),
+            generate_tile_map.run_if(not(resource_exists::<PendingScenario>)),
+        )
         .add_systems(OnEnter(AppState::GameStart), setup_tech_button)
         .add_systems(OnEnter(AppState::GameStart), setup_tile_map)
         .run();
 }
 
-pub fn close_on_esc(
-    mut commands: Commands,
-    focused_windows: Query<(Entity, &Window)>,
-    input: Res<ButtonInput<KeyCode>>,
-) {
-    for (window, focus) in focused_windows.iter() {
-        if !focus.focused {
-            continue;
-        }
-
-        if input.just_pressed(KeyCode::Escape) {
-            commands.entity(window).despawn();
-        }
-    }
-}
-
 #[derive(Component)]
 struct MainCamera;
 
@@ -149,6 +412,7 @@ fn main_camera_setup(mut commands: Commands, map_setting: Res<MapSetting>) {
 fn main_camera_movement(
     time: Res<Time>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
     query: Single<&mut Transform, With<MainCamera>>,
     map_setting: Res<MapSetting>,
 ) {
@@ -156,16 +420,16 @@ fn main_camera_movement(
 
     let mut movement = Vec3::ZERO;
 
-    if keyboard_input.pressed(KeyCode::KeyW) {
+    if key_bindings.pressed(InputAction::CameraUp, &keyboard_input) {
         movement.y += 1.0;
     }
-    if keyboard_input.pressed(KeyCode::KeyS) {
+    if key_bindings.pressed(InputAction::CameraDown, &keyboard_input) {
         movement.y -= 1.0;
     }
-    if keyboard_input.pressed(KeyCode::KeyA) {
+    if key_bindings.pressed(InputAction::CameraLeft, &keyboard_input) {
         movement.x -= 1.0;
     }
-    if keyboard_input.pressed(KeyCode::KeyD) {
+    if key_bindings.pressed(InputAction::CameraRight, &keyboard_input) {
         movement.x += 1.0;
     }
 
@@ -175,20 +439,56 @@ fn main_camera_movement(
     limit_main_camera_within_map_bounds(&mut transform, &map_setting);
 }
 
+/// Scroll the main camera while the cursor rests near an edge of the window, see [`EdgePanSettings`].
+fn edge_pan_main_camera(
+    time: Res<Time>,
+    window: Single<&Window>,
+    edge_pan_settings: Res<EdgePanSettings>,
+    camera_commands: Res<CameraCommands>,
+    query: Single<&mut Transform, With<MainCamera>>,
+    map_setting: Res<MapSetting>,
+) {
+    // A requested pan has the camera until it ends.
+    if !edge_pan_settings.enabled || camera_commands.is_panning() || !window.focused {
+        return;
+    }
+    let Some(cursor_position) = window.cursor_position() else {
+        return;
+    };
+
+    let direction = edge_pan_settings.direction(cursor_position, window.size());
+    if direction == Vec2::ZERO {
+        return;
+    }
+
+    let mut transform = query.into_inner();
+    transform.translation += direction.extend(0.) * time.delta_secs() * edge_pan_settings.speed;
+
+    limit_main_camera_within_map_bounds(&mut transform, &map_setting);
+}
+
 fn cursor_drag_system(
     window: Single<&Window>,
     cameras: Single<(&mut Transform, &Camera, &GlobalTransform), With<MainCamera>>,
     mut last_cursor_pos: Local<Option<Vec2>>,
     input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
     map_setting: Res<MapSetting>,
 ) {
     let (mut transform, camera, camera_transform) = cameras.into_inner();
-    if input.pressed(MouseButton::Left) {
+    // Dragging with `Shift` draws a band selecting units instead, see `unit_group`.
+    if input.pressed(MouseButton::Left) && !band_select_held(&keyboard_input) {
         if let Some(cursor_position) = window.cursor_position()
             && let Ok(world_pos) = camera.viewport_to_world_2d(camera_transform, cursor_position)
         {
             if let Some(last_pos) = *last_cursor_pos {
-                let delta = world_pos - last_pos;
+                let mut delta = world_pos - last_pos;
+                // The camera may have been moved back by the width of the map while dragging a map which wraps around.
+                let grid = map_setting.0.world_grid.grid;
+                if grid.wrap_x() {
+                    let map_width = map_pixel_width(grid);
+                    delta.x -= (delta.x / map_width).round() * map_width;
+                }
                 transform.translation -= delta.extend(0.);
             } else {
                 *last_cursor_pos = Some(world_pos);
@@ -205,6 +505,7 @@ fn cursor_drag_system(
 fn zoom_main_camera_system(
     mut scroll_evr: MessageReader<MouseWheel>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
     projection: Single<&mut Projection, With<MainCamera>>,
 ) {
     let mut projection = projection.into_inner();
@@ -215,10 +516,10 @@ fn zoom_main_camera_system(
         }
 
         // Handle keyboard zoom
-        if keyboard_input.pressed(KeyCode::KeyQ) {
+        if key_bindings.pressed(InputAction::ZoomOut, &keyboard_input) {
             orthographic.scale *= 1.01;
         }
-        if keyboard_input.pressed(KeyCode::KeyE) {
+        if key_bindings.pressed(InputAction::ZoomIn, &keyboard_input) {
             orthographic.scale *= 0.99;
         }
 
@@ -239,6 +540,9 @@ fn limit_main_camera_within_map_bounds(transform: &mut Transform, map_setting: &
 
     if !grid.wrap_flags.contains(WrapFlags::WrapX) {
         transform.translation.x = transform.translation.x.clamp(left_bottom[0], right_top[0]);
+    } else {
+        // Keep the camera on the first copy of the map, so scrolling east or west never ends.
+        transform.translation.x = wrap_x_position(transform.translation.x, *grid);
     }
 
     if !grid.wrap_flags.contains(WrapFlags::WrapY) {
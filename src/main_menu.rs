@@ -0,0 +1,431 @@
+//! The main menu, shown once the assets are loaded: the setup of a new game, where the player chooses the map
+//! and their nation before the map is generated.
+//!
+//! A scenario started from the command line skips the menu, see [`crate::scenario`].
+
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+use civ_map_generator::{
+    grid::{
+        Grid, GridSize, WorldSizeType, WrapFlags,
+        hex_grid::{HexGrid, HexLayout, HexOrientation, Offset},
+    },
+    map_parameters::{MapParameters, MapParametersBuilder, MapType, Rainfall, SeaLevel, WorldGrid},
+    nation::Nation,
+    ruleset::Ruleset,
+    tile_map::TileMap,
+};
+use enum_map::Enum;
+
+use crate::{
+    MainCamera, MapSetting, RulesetResource, assets::AppState, rng::GameRng,
+    scenario::PendingScenario,
+};
+
+const WORLD_SIZES: [WorldSizeType; 6] = [
+    WorldSizeType::Duel,
+    WorldSizeType::Tiny,
+    WorldSizeType::Small,
+    WorldSizeType::Standard,
+    WorldSizeType::Large,
+    WorldSizeType::Huge,
+];
+const MAP_TYPES: [MapType; 2] = [MapType::Fractal, MapType::Pangaea];
+const SEA_LEVELS: [SeaLevel; 3] = [SeaLevel::Low, SeaLevel::Normal, SeaLevel::High];
+const RAINFALLS: [Rainfall; 3] = [Rainfall::Arid, Rainfall::Normal, Rainfall::Wet];
+/// The number of AI civilizations can't go beyond this, the most a Huge map holds in Civ V.
+const MAX_OPPONENTS: u32 = 11;
+
+/// The choices of the player for the next new game.
+#[derive(Resource, Clone)]
+pub struct NewGameSettings {
+    pub world_size: WorldSizeType,
+    /// Whether the map wraps around from east to west.
+    pub wrap_x: bool,
+    pub map_type: MapType,
+    pub sea_level: SeaLevel,
+    pub rainfall: Rainfall,
+    pub seed: u64,
+    /// The nation of the player, or `None` for the nation with the first starting tile of the map.
+    pub nation: Option<Nation>,
+    /// The number of AI civilizations.
+    pub opponents: u32,
+}
+
+impl Default for NewGameSettings {
+    fn default() -> Self {
+        Self {
+            world_size: WorldSizeType::Standard,
+            wrap_x: true,
+            map_type: MapType::Fractal,
+            sea_level: SeaLevel::Normal,
+            rainfall: Rainfall::Normal,
+            seed: random_seed(),
+            nation: None,
+            opponents: 7,
+        }
+    }
+}
+
+impl NewGameSettings {
+    pub fn map_parameters(&self) -> MapParameters {
+        let grid = HexGrid {
+            size: HexGrid::default_size(self.world_size),
+            layout: HexLayout {
+                orientation: HexOrientation::Pointy,
+                size: [50., 50.],
+                origin: [0., 0.],
+            },
+            wrap_flags: if self.wrap_x {
+                WrapFlags::WrapX
+            } else {
+                WrapFlags::empty()
+            },
+            offset: Offset::Odd,
+        };
+        let world_grid = WorldGrid::from_grid(grid);
+
+        MapParametersBuilder::new(world_grid)
+            .map_type(self.map_type)
+            .sea_level(self.sea_level)
+            .rainfall(self.rainfall)
+            .seed(GameRng::map_seed(self.seed))
+            .num_civilization(self.opponents + 1)
+            .build()
+    }
+
+    /// Give the chosen nation of the player the first starting tile of the generated map,
+    /// which is the tile of the player, see [`crate::civilization`].
+    ///
+    /// The nation on that tile takes the place of the chosen nation, if the chosen nation is also on the map.
+    pub fn assign_player_nation(&self, tile_map: &mut TileMap) {
+        let Some(nation) = self.nation else {
+            return;
+        };
+        let starting_tiles = &mut tile_map.starting_tile_and_civilization;
+        let Some(&first_tile) = starting_tiles.keys().min_by_key(|tile| tile.index()) else {
+            return;
+        };
+        let replaced = starting_tiles[&first_tile];
+        if let Some((&tile, _)) = starting_tiles
+            .iter()
+            .find(|&(_, &starting_nation)| starting_nation == nation)
+        {
+            starting_tiles.insert(tile, replaced);
+        }
+        starting_tiles.insert(first_tile, nation);
+    }
+}
+
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+/// The nations a player can choose: every nation of the ruleset but the city-states and the barbarians.
+fn playable_nations(ruleset: &Ruleset) -> Vec<Nation> {
+    let mut nations: Vec<Nation> = (0..Nation::LENGTH)
+        .map(Nation::from_usize)
+        .filter(|&nation| {
+            nation != Nation::Barbarians
+                && ruleset
+                    .nations
+                    .get(nation.as_str())
+                    .is_some_and(|info| info.city_state_type.is_empty())
+        })
+        .collect();
+    nations.sort_by_key(|nation| nation.as_str());
+    nations
+}
+
+/// The setting changed by a row of the setup screen.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SetupOption {
+    WorldSize,
+    Wrap,
+    MapType,
+    SeaLevel,
+    Rainfall,
+    Seed,
+    Nation,
+    Opponents,
+}
+
+impl SetupOption {
+    const ALL: [Self; 8] = [
+        Self::WorldSize,
+        Self::Wrap,
+        Self::MapType,
+        Self::SeaLevel,
+        Self::Rainfall,
+        Self::Seed,
+        Self::Nation,
+        Self::Opponents,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::WorldSize => "Map size",
+            Self::Wrap => "Wrap east-west",
+            Self::MapType => "Map type",
+            Self::SeaLevel => "Sea level",
+            Self::Rainfall => "Rainfall",
+            Self::Seed => "Seed",
+            Self::Nation => "Nation",
+            Self::Opponents => "Opponents",
+        }
+    }
+
+    fn value(self, settings: &NewGameSettings) -> String {
+        match self {
+            Self::WorldSize => format!("{:?}", settings.world_size),
+            Self::Wrap => if settings.wrap_x { "Yes" } else { "No" }.to_owned(),
+            Self::MapType => format!("{:?}", settings.map_type),
+            Self::SeaLevel => format!("{:?}", settings.sea_level),
+            Self::Rainfall => format!("{:?}", settings.rainfall),
+            Self::Seed => settings.seed.to_string(),
+            Self::Nation => settings
+                .nation
+                .map_or("Random".to_owned(), |nation| nation.as_str().to_owned()),
+            Self::Opponents => settings.opponents.to_string(),
+        }
+    }
+
+    /// Choose the next value of the setting, or the previous one if `step` is negative.
+    fn cycle(self, settings: &mut NewGameSettings, step: i32, ruleset: &Ruleset) {
+        match self {
+            Self::WorldSize => settings.world_size = cycle(&WORLD_SIZES, settings.world_size, step),
+            Self::Wrap => settings.wrap_x = !settings.wrap_x,
+            Self::MapType => settings.map_type = cycle(&MAP_TYPES, settings.map_type, step),
+            Self::SeaLevel => settings.sea_level = cycle(&SEA_LEVELS, settings.sea_level, step),
+            Self::Rainfall => settings.rainfall = cycle(&RAINFALLS, settings.rainfall, step),
+            // Any other seed is as good, so both buttons pick a new one.
+            Self::Seed => settings.seed = random_seed(),
+            Self::Nation => {
+                let choices: Vec<Option<Nation>> = std::iter::once(None)
+                    .chain(playable_nations(ruleset).into_iter().map(Some))
+                    .collect();
+                settings.nation = cycle(&choices, settings.nation, step);
+            }
+            Self::Opponents => {
+                settings.opponents =
+                    (settings.opponents as i32 + step).clamp(1, MAX_OPPONENTS as i32) as u32;
+            }
+        }
+    }
+}
+
+/// The value after `current` in `values`, going backward if `step` is negative and around at both ends.
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T, step: i32) -> T {
+    let index = values
+        .iter()
+        .position(|&value| value == current)
+        .unwrap_or_default() as i32;
+    values[(index + step).rem_euclid(values.len() as i32) as usize]
+}
+
+/// The setup screen of a new game.
+#[derive(Component)]
+struct MainMenu;
+
+/// The text showing the value of a setting.
+#[derive(Component)]
+struct SetupOptionValue(SetupOption);
+
+/// A button changing a setting, see [`SetupOption::cycle`].
+#[derive(Component)]
+struct SetupOptionButton {
+    option: SetupOption,
+    step: i32,
+}
+
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NewGameSettings>()
+            .add_systems(OnEnter(AppState::MainMenu), setup_main_menu)
+            .add_systems(OnExit(AppState::MainMenu), despawn_main_menu)
+            .add_systems(
+                Update,
+                update_setup_option_values
+                    .run_if(in_state(AppState::MainMenu).and(resource_changed::<NewGameSettings>)),
+            );
+    }
+}
+
+fn setup_main_menu(
+    mut commands: Commands,
+    pending_scenario: Option<Res<PendingScenario>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    // The map and the nations of a scenario are already chosen.
+    if pending_scenario.is_some() {
+        next_state.set(AppState::MapGenerating);
+        return;
+    }
+
+    let text_font = TextFont {
+        font_size: 16.,
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK),
+            MainMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("Civilization-Remastered".to_owned()),
+                TextFont {
+                    font_size: 32.,
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+            parent.spawn((
+                Text("New game".to_owned()),
+                TextFont {
+                    font_size: 20.,
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+            for option in SetupOption::ALL {
+                parent
+                    .spawn((
+                        Node {
+                            width: Val::Px(400.),
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(8.),
+                            ..default()
+                        },
+                        Pickable::IGNORE,
+                    ))
+                    .with_children(|row| {
+                        row.spawn((
+                            Node {
+                                width: Val::Px(160.),
+                                ..default()
+                            },
+                            Text(option.name().to_owned()),
+                            text_font.clone(),
+                            Pickable::IGNORE,
+                        ));
+                        option_button(row, option, -1, &text_font);
+                        row.spawn((
+                            Node {
+                                flex_grow: 1.,
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            Text::default(),
+                            text_font.clone(),
+                            TextLayout::new_with_justify(Justify::Center),
+                            SetupOptionValue(option),
+                            Pickable::IGNORE,
+                        ));
+                        option_button(row, option, 1, &text_font);
+                    });
+            }
+            parent
+                .spawn((
+                    Node {
+                        margin: UiRect::top(Val::Px(12.)),
+                        padding: UiRect::axes(Val::Px(16.), Val::Px(4.)),
+                        border: UiRect::all(Val::Px(2.)),
+                        ..default()
+                    },
+                    BorderColor::all(Color::srgb(1., 0.85, 0.1)),
+                    Text("Start game".to_owned()),
+                    text_font.clone(),
+                ))
+                .observe(start_new_game_on_click);
+        });
+}
+
+fn option_button(
+    row: &mut ChildSpawnerCommands,
+    option: SetupOption,
+    step: i32,
+    text_font: &TextFont,
+) {
+    row.spawn((
+        Node {
+            padding: UiRect::horizontal(Val::Px(6.)),
+            border: UiRect::all(Val::Px(1.)),
+            ..default()
+        },
+        BorderColor::all(Color::WHITE),
+        Text(if step < 0 { "<" } else { ">" }.to_owned()),
+        text_font.clone(),
+        SetupOptionButton { option, step },
+    ))
+    .observe(change_setup_option_on_click);
+}
+
+fn update_setup_option_values(
+    settings: Res<NewGameSettings>,
+    mut query_value: Query<(&SetupOptionValue, &mut Text)>,
+) {
+    for (&SetupOptionValue(option), mut text) in query_value.iter_mut() {
+        text.0 = option.value(&settings);
+    }
+}
+
+fn change_setup_option_on_click(
+    click: On<Pointer<Click>>,
+    ruleset: Res<RulesetResource>,
+    mut settings: ResMut<NewGameSettings>,
+    query_button: Query<&SetupOptionButton>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    if let Ok(button) = query_button.get(click.entity) {
+        button.option.cycle(&mut settings, button.step, &ruleset.0);
+    }
+}
+
+/// Generate the map of the chosen settings, see [`crate::generating_map`].
+fn start_new_game_on_click(
+    click: On<Pointer<Click>>,
+    mut commands: Commands,
+    settings: Res<NewGameSettings>,
+    mut camera_transform: Single<&mut Transform, With<MainCamera>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    let map_parameters = settings.map_parameters();
+    let map_center = map_parameters.world_grid.grid.center();
+    camera_transform.translation.x = map_center[0];
+    camera_transform.translation.y = map_center[1];
+    commands.insert_resource(GameRng::new(settings.seed));
+    commands.insert_resource(MapSetting(Arc::new(map_parameters)));
+    next_state.set(AppState::MapGenerating);
+}
+
+fn despawn_main_menu(mut commands: Commands, query_menu: Query<Entity, With<MainMenu>>) {
+    for menu in query_menu.iter() {
+        commands.entity(menu).despawn();
+    }
+}
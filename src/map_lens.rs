@@ -0,0 +1,507 @@
+//! The map lenses: tints over the explored tiles showing one aspect of the map, chosen from a toolbar in the top
+//! right corner of the screen. The settler lens shows where cities can be founded and how good the sites are,
+//! the appeal lens how pleasant the surroundings of the tiles are, the danger lens the tiles in reach of the
+//! enemies of the player, and the resource lens the resources the player knows of.
+//!
+//! Every lens is a [`LensEvaluator`] registered in [`MapLenses`], and the tints are drawn by [`OverlayChunk`]s.
+
+use std::collections::HashSet;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+use civ_map_generator::{
+    grid::hex_grid::Hex,
+    nation::Nation,
+    ruleset::Ruleset,
+    tile::Tile,
+    tile_component::{Feature, TerrainType},
+    tile_map::TileMap,
+};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    city::{City, is_city_site},
+    civilization::{Civilization, Civilizations, PlayerCivilization},
+    combat::CITY_STRIKE_RANGE,
+    diplomacy::DiplomacyState,
+    overlay_chunk::OverlayChunk,
+    unit::MapUnit,
+    unit_component::{Movement, Owner, RangedStrength, Unit},
+    visibility::VisibilityLayer,
+    world_map::resource_type_color,
+    yields::TileYields,
+};
+
+/// Above the terrain and the improvements, below the yield overlay.
+const LENS_Z: f32 = 3.4;
+const TINT_ALPHA: f32 = 0.45;
+const BUTTON_COLOR: Color = Color::BLACK;
+const ACTIVE_BUTTON_COLOR: Color = Color::srgb(0.2, 0.35, 0.6);
+
+/// What the lenses know of the game to tint the tiles, as seen by the player.
+pub struct LensContext<'a> {
+    pub tile_map: &'a TileMap,
+    pub ruleset: &'a Ruleset,
+    pub player: Nation,
+    pub civilization: &'a Civilization,
+    pub visibility_layer: &'a VisibilityLayer,
+    pub tile_yields: &'a TileYields,
+    pub city_tiles: Vec<Tile>,
+    /// The tiles inside the borders of the other nations.
+    pub foreign_tiles: HashSet<Tile>,
+    /// The tiles of the units and the cities at war with the player which it can see, with how far they can
+    /// attack in a turn.
+    pub threats: Vec<(Tile, u32)>,
+}
+
+/// A lens: the tint of every tile, `None` for the tiles it doesn't tint. Only the tiles explored by the player are
+/// tinted.
+pub trait LensEvaluator: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+
+    fn tint(&self, tile: Tile, context: &LensContext) -> Option<Color>;
+}
+
+/// The lenses of the toolbar, in order.
+#[derive(Resource)]
+pub struct MapLenses(Vec<Box<dyn LensEvaluator>>);
+
+impl Default for MapLenses {
+    fn default() -> Self {
+        let mut lenses = Self(Vec::new());
+        lenses.add(SettlerLens);
+        lenses.add(AppealLens);
+        lenses.add(DangerLens);
+        lenses.add(ResourceLens);
+        lenses
+    }
+}
+
+impl MapLenses {
+    pub fn add(&mut self, lens: impl LensEvaluator) {
+        self.0.push(Box::new(lens));
+    }
+}
+
+/// The index in [`MapLenses`] of the lens shown, if any.
+#[derive(Resource, Default)]
+pub struct ActiveLens(pub Option<usize>);
+
+/// How good the tiles are for a new city: the yields of the tiles around it, and the sea for its harbor.
+/// The tiles where no city can be founded are red.
+struct SettlerLens;
+
+impl SettlerLens {
+    const POOR_SITE: Color = Color::srgb(0.95, 0.8, 0.2);
+    const GOOD_SITE: Color = Color::srgb(0.2, 0.85, 0.3);
+    const NO_SITE: Color = Color::srgb(0.8, 0.15, 0.15);
+    /// The yields around a site of the best quality.
+    const BEST_YIELDS: f32 = 24.;
+}
+
+impl LensEvaluator for SettlerLens {
+    fn name(&self) -> &'static str {
+        "Settler"
+    }
+
+    fn tint(&self, tile: Tile, context: &LensContext) -> Option<Color> {
+        let tile_map = context.tile_map;
+        if tile.is_water(tile_map) {
+            return None;
+        }
+        if context.foreign_tiles.contains(&tile)
+            || !is_city_site(tile, tile_map, context.city_tiles.iter().copied())
+        {
+            return Some(Self::NO_SITE);
+        }
+        let grid = tile_map.world_grid.grid;
+        let mut score: f32 = tile
+            .tiles_in_distance(1, grid)
+            .filter(|&nearby| context.visibility_layer.is_explored(context.player, nearby))
+            .map(|nearby| {
+                let yields = context.tile_yields.get(nearby);
+                yields.food * 1.5 + yields.production + yields.gold * 0.5
+            })
+            .sum();
+        if tile
+            .neighbor_tiles(grid)
+            .any(|neighbor| neighbor.is_water(tile_map))
+        {
+            score += 2.;
+        }
+        Some(Self::POOR_SITE.mix(&Self::GOOD_SITE, (score / Self::BEST_YIELDS).clamp(0., 1.)))
+    }
+}
+
+/// How pleasant the surroundings of the tiles are: natural wonders, mountains, the sea and the forests around a tile
+/// raise its appeal, and the jungles lower it. Tiles of average appeal aren't tinted.
+struct AppealLens;
+
+impl AppealLens {
+    fn appeal(tile: Tile, tile_map: &TileMap) -> i32 {
+        tile.neighbor_tiles(tile_map.world_grid.grid)
+            .map(|neighbor| {
+                if neighbor.natural_wonder(tile_map).is_some() {
+                    return 2;
+                }
+                if neighbor.terrain_type(tile_map) == TerrainType::Mountain
+                    || neighbor.is_water(tile_map)
+                {
+                    return 1;
+                }
+                match neighbor.feature(tile_map) {
+                    Some(Feature::Forest) => 1,
+                    Some(Feature::Jungle) => -1,
+                    _ => 0,
+                }
+            })
+            .sum()
+    }
+}
+
+impl LensEvaluator for AppealLens {
+    fn name(&self) -> &'static str {
+        "Appeal"
+    }
+
+    fn tint(&self, tile: Tile, context: &LensContext) -> Option<Color> {
+        if tile.is_water(context.tile_map) {
+            return None;
+        }
+        match Self::appeal(tile, context.tile_map) {
+            4.. => Some(Color::srgb(0.1, 0.75, 0.45)),
+            2..=3 => Some(Color::srgb(0.55, 0.85, 0.45)),
+            -1..=1 => None,
+            _ => Some(Color::srgb(0.55, 0.4, 0.2)),
+        }
+    }
+}
+
+/// The tiles the enemies of the player can attack next turn, darker next to them. The reach of a unit is counted in
+/// tiles, without the cost of the terrain, so it is the most a unit can reach.
+struct DangerLens;
+
+impl LensEvaluator for DangerLens {
+    fn name(&self) -> &'static str {
+        "Danger"
+    }
+
+    fn tint(&self, tile: Tile, context: &LensContext) -> Option<Color> {
+        let grid = context.tile_map.world_grid.grid;
+        let closest = context
+            .threats
+            .iter()
+            .filter(|&&(threat_tile, reach)| threat_tile.distance_to(tile, grid) <= reach)
+            .map(|&(threat_tile, _)| threat_tile.distance_to(tile, grid))
+            .min()?;
+        Some(if closest <= 1 {
+            Color::srgb(0.85, 0.1, 0.1)
+        } else {
+            Color::srgb(0.95, 0.5, 0.2)
+        })
+    }
+}
+
+/// The resources the player knows of, colored by their type like on the minimap.
+struct ResourceLens;
+
+impl LensEvaluator for ResourceLens {
+    fn name(&self) -> &'static str {
+        "Resources"
+    }
+
+    fn tint(&self, tile: Tile, context: &LensContext) -> Option<Color> {
+        let (resource, _) = context
+            .civilization
+            .visible_resource(tile, context.tile_map)?;
+        Some(resource_type_color(
+            &context.ruleset.tile_resources[resource.as_str()].resource_type,
+        ))
+    }
+}
+
+/// A chunk of the lens shown.
+#[derive(Component)]
+struct LensChunk;
+
+/// The toolbar choosing the lens.
+#[derive(Component)]
+pub struct LensToolbar;
+
+/// A button of the toolbar, showing the lens at this index in [`MapLenses`].
+#[derive(Component)]
+struct LensButton(usize);
+
+pub struct MapLensPlugin;
+
+impl Plugin for MapLensPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapLenses>()
+            .init_resource::<ActiveLens>()
+            .add_systems(OnEnter(AppState::GameStart), setup_lens_toolbar)
+            .add_systems(
+                Update,
+                (
+                    setup_lens_chunks.run_if(resource_added::<TileYields>),
+                    update_lens_chunks.run_if(
+                        resource_exists::<TileYields>.and(resource_exists::<VisibilityLayer>),
+                    ),
+                    update_lens_buttons.run_if(resource_changed::<ActiveLens>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            );
+    }
+}
+
+fn setup_lens_toolbar(
+    mut commands: Commands,
+    map_lenses: Res<MapLenses>,
+    mut active_lens: ResMut<ActiveLens>,
+) {
+    // A new game starts without a lens.
+    active_lens.0 = None;
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                top: Val::Px(10.),
+                column_gap: Val::Px(4.),
+                ..default()
+            },
+            LensToolbar,
+        ))
+        .with_children(|toolbar| {
+            for (index, lens) in map_lenses.0.iter().enumerate() {
+                toolbar
+                    .spawn((
+                        Node {
+                            padding: UiRect::axes(Val::Px(6.), Val::Px(2.)),
+                            border: UiRect::all(Val::Px(1.)),
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_COLOR),
+                        BorderColor::all(Color::WHITE),
+                        Text(lens.name().to_owned()),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        LensButton(index),
+                    ))
+                    .observe(toggle_lens_on_click);
+            }
+        });
+}
+
+/// Show the lens of the button, or hide it if it is already shown.
+fn toggle_lens_on_click(
+    click: On<Pointer<Click>>,
+    mut active_lens: ResMut<ActiveLens>,
+    query_button: Query<&LensButton>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    let Ok(&LensButton(index)) = query_button.get(click.entity) else {
+        return;
+    };
+    active_lens.0 = if active_lens.0 == Some(index) {
+        None
+    } else {
+        Some(index)
+    };
+}
+
+fn update_lens_buttons(
+    active_lens: Res<ActiveLens>,
+    mut query_button: Query<(&LensButton, &mut BackgroundColor)>,
+) {
+    for (&LensButton(index), mut background_color) in query_button.iter_mut() {
+        background_color.0 = if active_lens.0 == Some(index) {
+            ACTIVE_BUTTON_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+    }
+}
+
+/// Spawn the chunks of the map, replacing the chunks of the previous map, e.g. when a save is loaded.
+fn setup_lens_chunks(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    mut active_lens: ResMut<ActiveLens>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    query_chunk: Query<Entity, With<LensChunk>>,
+) {
+    for entity in query_chunk.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    // The tints are colored by their vertices, so every chunk shares a white material.
+    let material = color_materials.add(Color::WHITE);
+    for chunk in OverlayChunk::all(map.0.world_grid.grid) {
+        commands.spawn((
+            chunk,
+            LensChunk,
+            Mesh2d(meshes.add(TintMesh::default().into_mesh())),
+            MeshMaterial2d(material.clone()),
+            Transform::from_xyz(0., 0., LENS_Z),
+            Visibility::Hidden,
+        ));
+    }
+    // Build the meshes of the new chunks.
+    active_lens.set_changed();
+}
+
+/// Rebuild the meshes of the chunks when a lens is chosen or what it shows may have changed, and hide the chunks
+/// when no lens is shown.
+fn update_lens_chunks(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    map_lenses: Res<MapLenses>,
+    active_lens: Res<ActiveLens>,
+    player_civilization: Res<PlayerCivilization>,
+    civilizations: Res<Civilizations>,
+    diplomacy: Res<DiplomacyState>,
+    visibility_layer: Res<VisibilityLayer>,
+    tile_yields: Res<TileYields>,
+    query_city: Query<(&City, &Owner)>,
+    query_unit: Query<(&Unit, &Owner, &MapUnit, &Movement, &RangedStrength)>,
+    query_changed_unit: Query<(), Changed<MapUnit>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query_chunk: Query<(&OverlayChunk, &Mesh2d, &mut Visibility), With<LensChunk>>,
+) {
+    let Some(lens) = active_lens.0.and_then(|index| map_lenses.0.get(index)) else {
+        if active_lens.is_changed() {
+            for (_, _, mut visibility) in query_chunk.iter_mut() {
+                *visibility = Visibility::Hidden;
+            }
+        }
+        return;
+    };
+    if !active_lens.is_changed()
+        && !visibility_layer.is_changed()
+        && !tile_yields.is_changed()
+        && !civilizations.is_changed()
+        && !diplomacy.is_changed()
+        && query_changed_unit.is_empty()
+    {
+        return;
+    }
+
+    let tile_map = &map.0;
+    let player = player_civilization.0;
+    let is_threat = |tile: Tile, nation: Nation| {
+        diplomacy.is_at_war(player, nation) && visibility_layer.is_visible(player, tile)
+    };
+    let context = LensContext {
+        tile_map,
+        ruleset: &ruleset.0,
+        player,
+        civilization: civilizations.get(player),
+        visibility_layer: &visibility_layer,
+        tile_yields: &tile_yields,
+        city_tiles: query_city.iter().map(|(city, _)| city.tile).collect(),
+        foreign_tiles: query_city
+            .iter()
+            .filter(|(_, owner)| owner.nation() != player)
+            .flat_map(|(city, _)| city.owned_tiles.iter().copied())
+            .collect(),
+        threats: query_unit
+            .iter()
+            .filter(|(unit, owner, map_unit, ..)| {
+                matches!(unit, Unit::Military(_)) && is_threat(map_unit.tile, owner.nation())
+            })
+            .map(|(_, _, map_unit, movement, ranged_strength)| {
+                let moves = movement.max.ceil() as u32;
+                // A ranged unit shoots from the last tile it moves to.
+                let reach = if ranged_strength.strength > 0 {
+                    moves.saturating_sub(1) + ranged_strength.range
+                } else {
+                    moves
+                };
+                (map_unit.tile, reach)
+            })
+            .chain(
+                query_city
+                    .iter()
+                    .filter(|(city, owner)| is_threat(city.tile, owner.nation()))
+                    .map(|(city, _)| (city.tile, CITY_STRIKE_RANGE)),
+            )
+            .collect(),
+    };
+
+    let grid = tile_map.world_grid.grid;
+    let corners = grid.layout.all_corners(Hex::new(0, 0)).map(Vec2::from);
+    for (chunk, mesh_2d, mut visibility) in query_chunk.iter_mut() {
+        let mut tints = TintMesh::default();
+        for (center, tile) in chunk.tiles(grid) {
+            if !visibility_layer.is_explored(player, tile) {
+                continue;
+            }
+            if let Some(color) = lens.tint(tile, &context) {
+                tints.add_hexagon(center, &corners, color.with_alpha(TINT_ALPHA));
+            }
+        }
+
+        *visibility = if tints.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        if let Some(mesh) = meshes.get_mut(&mesh_2d.0) {
+            *mesh = tints.into_mesh();
+        }
+    }
+}
+
+/// The vertices of the tinted tiles of a chunk: a hexagon of the color of every tile.
+#[derive(Default)]
+struct TintMesh {
+    positions: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl TintMesh {
+    fn add_hexagon(&mut self, center: Vec2, corners: &[Vec2; 6], color: Color) {
+        let first_index = self.positions.len() as u32;
+        let color = color.to_linear().to_f32_array();
+        self.positions.push([center.x, center.y, 0.]);
+        self.colors.push(color);
+        for corner in corners {
+            self.positions
+                .push([center.x + corner.x, center.y + corner.y, 0.]);
+            self.colors.push(color);
+        }
+        for index in 0..6 {
+            self.indices.extend([
+                first_index,
+                first_index + 1 + index,
+                first_index + 1 + (index + 1) % 6,
+            ]);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn into_mesh(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
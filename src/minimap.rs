@@ -1,49 +1,261 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
 use bevy::{
     asset::{Assets, Handle, RenderAssetUsages},
     camera::{
         Camera, Camera2d, OrthographicProjection, Projection, RenderTarget,
-        visibility::RenderLayers,
+        visibility::{RenderLayers, Visibility},
     },
     color::Color,
     ecs::{
+        change_detection::DetectChanges,
         component::Component,
         entity::Entity,
+        hierarchy::ChildOf,
+        lifecycle::RemovedComponents,
         observer::On,
-        query::{Changed, With, Without},
+        query::{Changed, Or, With, Without},
         resource::Resource,
         system::{Commands, Local, Query, Res, ResMut, Single},
+        world::Ref,
     },
     image::Image,
+    input::{ButtonInput, keyboard::KeyCode},
+    log::error,
     math::{Rect, Vec2, Vec3},
     mesh::{Mesh, Mesh2d},
     picking::{
         Pickable,
-        events::{Click, Pointer},
+        events::{Click, Drag, DragEnd, Pointer},
         pointer::PointerButton,
     },
     render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    sprite::Sprite,
     sprite_render::{ColorMaterial, MeshMaterial2d},
     transform::components::Transform,
     ui::{
-        BorderColor, Node, Overflow, OverflowAxis, PositionType, UiRect, Val,
+        BackgroundColor, BorderColor, Node, Overflow, OverflowAxis, PositionType, UiRect, Val,
         widget::{ImageNode, NodeImageMode},
     },
     utils::default,
+    window::Window,
+};
+use civ_map_generator::{
+    grid::Grid,
+    nation::Nation,
+    tile::Tile,
+    tile_component::{BaseTerrain, TerrainType},
 };
-use civ_map_generator::{grid::Grid, tile::Tile, tile_component::BaseTerrain};
 use enum_map::{EnumMap, enum_map};
-
-use crate::{MainCamera, TileMapResource, assets::MaterialResource, custom_mesh::hex_mesh};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    MainCamera, RulesetResource, TileMapResource,
+    accessibility::AccessibilitySettings,
+    assets::MaterialResource,
+    camera::{CameraCommands, PAN_DURATION},
+    city::City,
+    civilization::PlayerCivilization,
+    custom_mesh::hex_mesh,
+    unit_component::Owner,
+    visibility::{TileVisibility, VisibilityLayer},
+    world_map::{ResourceIcon, resource_type_color},
+};
 
 #[derive(Component)]
 pub struct FieldOfViewIndicator;
 
+/// A copy of the [`FieldOfViewIndicator`] on a map which wraps around, shifted by this many minimap widths
+/// and heights.
+#[derive(Component)]
+pub struct AuxiliaryFOVIndicator(Vec2);
+
+/// The UI node showing the minimap.
 #[derive(Component)]
-pub struct AuxiliaryFOVIndicator;
+pub struct MinimapPanel;
 
+/// The handle dragged to dock the minimap to another corner of the window.
+#[derive(Component)]
+struct MinimapMoveHandle;
+
+/// The handle dragged to resize the minimap, on the corner of the minimap opposite to its docked corner.
+#[derive(Component)]
+struct MinimapResizeHandle;
+
+/// The size of the minimap at scale 1.
 const MINIMAP_WIDTH: f32 = 300.;
 const MINIMAP_HEIGHT: f32 = 200.;
+const MIN_MINIMAP_SCALE: f32 = 0.5;
+const MAX_MINIMAP_SCALE: f32 = 2.5;
+/// The space between the minimap and the edges of the window.
+const MINIMAP_MARGIN: f32 = 20.;
+const HANDLE_SIZE: f32 = 12.;
+
+const MINIMAP_LAYOUT_PATH: &str = "settings/minimap.json";
+
+/// The corner of the window the minimap is docked to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MinimapCorner {
+    pub right: bool,
+    pub bottom: bool,
+}
+
+/// Where the minimap is docked and how large it is, kept between games in [`MINIMAP_LAYOUT_PATH`].
+#[derive(Resource, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct MinimapLayout {
+    pub corner: MinimapCorner,
+    /// The size of the minimap relative to [`MINIMAP_WIDTH`] and [`MINIMAP_HEIGHT`].
+    pub scale: f32,
+}
+
+impl Default for MinimapLayout {
+    fn default() -> Self {
+        Self {
+            corner: MinimapCorner {
+                right: true,
+                bottom: false,
+            },
+            scale: 1.,
+        }
+    }
+}
+
+impl MinimapLayout {
+    /// The layout saved by the last game, or the default layout if there is none.
+    pub fn load() -> Self {
+        fs::read_to_string(MINIMAP_LAYOUT_PATH)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(MINIMAP_WIDTH, MINIMAP_HEIGHT) * self.scale
+    }
+
+    /// The position and the size of the minimap panel, docked to its corner.
+    fn panel_node(&self) -> Node {
+        let size = self.size();
+        let (left, right) = if self.corner.right {
+            (Val::Auto, Val::Px(MINIMAP_MARGIN))
+        } else {
+            (Val::Px(MINIMAP_MARGIN), Val::Auto)
+        };
+        let (top, bottom) = if self.corner.bottom {
+            (Val::Auto, Val::Px(MINIMAP_MARGIN))
+        } else {
+            (Val::Px(MINIMAP_MARGIN), Val::Auto)
+        };
+        Node {
+            left,
+            right,
+            top,
+            bottom,
+            width: Val::Px(size.x),
+            height: Val::Px(size.y),
+            ..Default::default()
+        }
+    }
+
+    /// The position of the resize handle, on the corner of the minimap opposite to its docked corner.
+    fn resize_handle_node(&self) -> Node {
+        let (left, right) = if self.corner.right {
+            (Val::Px(0.), Val::Auto)
+        } else {
+            (Val::Auto, Val::Px(0.))
+        };
+        let (top, bottom) = if self.corner.bottom {
+            (Val::Px(0.), Val::Auto)
+        } else {
+            (Val::Auto, Val::Px(0.))
+        };
+        Node {
+            left,
+            right,
+            top,
+            bottom,
+            ..Default::default()
+        }
+    }
+}
+
+/// What the tiles of the minimap show, cycled with the `V` key.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MinimapMode {
+    /// The base terrain of every tile, with the resource dots.
+    #[default]
+    Terrain,
+    /// The civilization or city-state owning every tile.
+    Political,
+    /// The resources revealed to the player, as dots colored by their type on a dark map.
+    Resources,
+    /// Water, flatland, hills and mountains, from dark to light.
+    HeightMap,
+}
+
+impl MinimapMode {
+    fn next(self) -> Self {
+        match self {
+            MinimapMode::Terrain => MinimapMode::Political,
+            MinimapMode::Political => MinimapMode::Resources,
+            MinimapMode::Resources => MinimapMode::HeightMap,
+            MinimapMode::HeightMap => MinimapMode::Terrain,
+        }
+    }
+}
 
+/// A tile of the minimap, recolored for every [`MinimapMode`].
+#[derive(Component)]
+pub struct MinimapTile(pub Tile);
+
+/// The parent of the resource dots of the minimap, shown in [`MinimapMode::Terrain`] and [`MinimapMode::Resources`].
+#[derive(Component)]
+pub struct MinimapResourceLayer;
+
+/// The materials of the minimap tiles for every [`MinimapMode`].
+#[derive(Resource)]
+pub struct MinimapMaterials {
+    terrain: EnumMap<BaseTerrain, Handle<ColorMaterial>>,
+    land: Handle<ColorMaterial>,
+    water: Handle<ColorMaterial>,
+    hill: Handle<ColorMaterial>,
+    mountain: Handle<ColorMaterial>,
+    /// The land and the water colors of the resources mode, dark so the dots stand out.
+    dim_land: Handle<ColorMaterial>,
+    dim_water: Handle<ColorMaterial>,
+    /// The outer color of every nation owning tiles, added once the nation is first drawn.
+    nations: HashMap<Nation, Handle<ColorMaterial>>,
+}
+
+/// The fog over a tile of the minimap, see [`MinimapFog`].
+#[derive(Component)]
+struct MinimapFogTile;
+
+/// The fog of the minimap tiles, as on the world map: black over the tiles the player never explored, and a veil
+/// over the tiles explored but not seen now. The visible tiles show every [`MinimapMode`] in full color.
+///
+/// Only the fog of the tiles whose visibility changed since it was drawn is updated, see [`update_minimap_fog`].
+#[derive(Resource)]
+pub struct MinimapFog {
+    unexplored: Handle<ColorMaterial>,
+    explored: Handle<ColorMaterial>,
+    /// The fog of every tile, by tile index.
+    entities: Vec<Entity>,
+    /// The visibility shown by the fog of every tile, `None` until it is drawn.
+    drawn: Vec<Option<TileVisibility>>,
+    /// The civilization whose visibility is drawn.
+    player: Option<Nation>,
+}
+
+/// The size of the area seen by the main camera at zoom 1, relative to the size of the whole map,
+/// so the field of view indicator can be sized for any size of the minimap.
 #[derive(Resource, Default)]
 pub struct DefaultFovIndicatorSize {
     pub width: f32,
@@ -53,11 +265,13 @@ pub struct DefaultFovIndicatorSize {
 pub fn setup_minimap(
     mut commands: Commands,
     map: Option<Res<TileMapResource>>,
+    ruleset: Res<RulesetResource>,
     mut default_fov_indicator_size: ResMut<DefaultFovIndicatorSize>,
     materials: Res<MaterialResource>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut images: ResMut<Assets<Image>>,
     mut color_materials: ResMut<Assets<ColorMaterial>>,
+    minimap_layout: Res<MinimapLayout>,
     mut enable_minimap: Local<bool>,
     query_main_camera: Single<&Camera, With<MainCamera>>,
 ) {
@@ -79,6 +293,18 @@ pub fn setup_minimap(
     };
 
     let hex_mesh = meshes.add(hex_mesh(&minimap_grid));
+    let fog_unexplored = color_materials.add(ColorMaterial::from_color(Color::BLACK));
+    let fog_explored = color_materials.add(ColorMaterial::from_color(Color::BLACK.with_alpha(0.5)));
+    let mut fog_entities = vec![Entity::PLACEHOLDER; tile_map.all_tiles().count()];
+
+    let resource_layer = commands
+        .spawn((
+            Transform::default(),
+            Visibility::Hidden,
+            RenderLayers::layer(1),
+            MinimapResourceLayer,
+        ))
+        .id();
 
     for tile in tile_map.all_tiles() {
         let offset_coordinate = tile.to_offset(minimap_grid);
@@ -91,9 +317,52 @@ pub fn setup_minimap(
                 ..Default::default()
             },
             RenderLayers::layer(1),
+            MinimapTile(tile),
         ));
+        // Above the tile and its resource dot.
+        fog_entities[tile.index()] = commands
+            .spawn((
+                Mesh2d(hex_mesh.clone()),
+                MeshMaterial2d(fog_unexplored.clone()),
+                Transform::from_xyz(pixel_position[0], pixel_position[1], 9.8),
+                RenderLayers::layer(1),
+                MinimapFogTile,
+            ))
+            .id();
+
+        // The resources are shown as dots colored by their type, once the player can see them
+        if let Some((resource, _)) = tile.resource(tile_map) {
+            let resource_type = &ruleset.0.tile_resources[resource.as_str()].resource_type;
+            commands.spawn((
+                Sprite::from_color(resource_type_color(resource_type), Vec2::splat(6.)),
+                Transform::from_xyz(pixel_position[0], pixel_position[1], 9.5),
+                Visibility::Hidden,
+                RenderLayers::layer(1),
+                ResourceIcon(resource.as_str().to_owned()),
+                ChildOf(resource_layer),
+            ));
+        }
     }
 
+    let mut color_material = |color: Color| color_materials.add(ColorMaterial::from_color(color));
+    commands.insert_resource(MinimapMaterials {
+        land: color_material(Color::srgb(0.35, 0.35, 0.35)),
+        water: color_material(Color::srgb(0.08, 0.08, 0.1)),
+        hill: color_material(Color::srgb(0.6, 0.6, 0.6)),
+        mountain: color_material(Color::srgb(0.92, 0.92, 0.92)),
+        dim_land: color_material(Color::srgb(0.25, 0.22, 0.18)),
+        dim_water: color_material(Color::srgb(0.08, 0.1, 0.2)),
+        nations: HashMap::new(),
+        terrain: base_terrain_and_material,
+    });
+    commands.insert_resource(MinimapFog {
+        unexplored: fog_unexplored,
+        explored: fog_explored,
+        drawn: vec![None; fog_entities.len()],
+        entities: fog_entities,
+        player: None,
+    });
+
     let minimap_center = minimap_grid.center();
     let minimap_width = minimap_center[0] * 2.0;
     let minimap_height = minimap_center[1] * 2.0;
@@ -144,31 +413,25 @@ pub fn setup_minimap(
         .logical_viewport_size()
         .unwrap();
 
-    let fov_indicator_width = logical_viewport_size.x / world_grid_width * MINIMAP_WIDTH;
-    let fov_indicator_height = logical_viewport_size.y / world_grid_height * MINIMAP_HEIGHT;
-
     *default_fov_indicator_size = DefaultFovIndicatorSize {
-        width: fov_indicator_width,
-        height: fov_indicator_height,
+        width: logical_viewport_size.x / world_grid_width,
+        height: logical_viewport_size.y / world_grid_height,
     };
 
     let minimap = commands
         .spawn((
             Node {
                 position_type: PositionType::Absolute,
-                right: Val::Px(20.0),
-                top: Val::Px(20.0),
-                width: Val::Px(MINIMAP_WIDTH),
-                height: Val::Px(MINIMAP_HEIGHT),
                 border: UiRect::all(Val::Px(2.0)),
                 overflow: Overflow {
                     x: OverflowAxis::Clip,
                     y: OverflowAxis::Clip,
                 },
-                ..Default::default()
+                ..minimap_layout.panel_node()
             },
             BorderColor::all(Color::BLACK),
             ImageNode::new(image_handle).with_mode(NodeImageMode::Stretch),
+            MinimapPanel,
         ))
         .observe(minimap_click_handler)
         .id();
@@ -180,10 +443,6 @@ pub fn setup_minimap(
             .spawn((
                 Node {
                     position_type: PositionType::Absolute,
-                    left: Val::Px(MINIMAP_WIDTH / 2.0 - fov_indicator_width / 2.0),
-                    bottom: Val::Px(MINIMAP_HEIGHT / 2.0 - fov_indicator_height / 2.0),
-                    width: Val::Px(fov_indicator_width),
-                    height: Val::Px(fov_indicator_height),
                     border: UiRect::all(Val::Px(2.0)),
                     ..Default::default()
                 },
@@ -192,62 +451,57 @@ pub fn setup_minimap(
                 FieldOfViewIndicator,
             ))
             .id();
+
+        // Dragging the move handle docks the minimap to the closest corner of the window,
+        // dragging the resize handle scales it.
+        parent
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(0.),
+                    top: Val::Px(0.),
+                    width: Val::Px(HANDLE_SIZE),
+                    height: Val::Px(HANDLE_SIZE),
+                    ..Default::default()
+                },
+                BackgroundColor(Color::BLACK.with_alpha(0.7)),
+                MinimapMoveHandle,
+            ))
+            .observe(move_minimap_on_drag)
+            .observe(dock_minimap_on_drag_end);
+        parent
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(HANDLE_SIZE),
+                    height: Val::Px(HANDLE_SIZE),
+                    ..minimap_layout.resize_handle_node()
+                },
+                BackgroundColor(Color::WHITE.with_alpha(0.7)),
+                MinimapResizeHandle,
+            ))
+            .observe(resize_minimap_on_drag);
     });
 
     commands
         .entity(field_of_view_indicator)
         .with_children(|parent| {
+            let mut offsets = Vec::new();
             if grid.wrap_x() {
-                parent.spawn((
-                    Node {
-                        position_type: PositionType::Absolute,
-                        right: Val::Px(MINIMAP_WIDTH),
-                        width: Val::Px(fov_indicator_width),
-                        height: Val::Px(fov_indicator_height),
-                        border: UiRect::all(Val::Px(2.0)),
-                        ..Default::default()
-                    },
-                    BorderColor::all(Color::WHITE),
-                    AuxiliaryFOVIndicator,
-                ));
-                parent.spawn((
-                    Node {
-                        position_type: PositionType::Absolute,
-                        right: Val::Px(-MINIMAP_WIDTH),
-                        width: Val::Px(fov_indicator_width),
-                        height: Val::Px(fov_indicator_height),
-                        border: UiRect::all(Val::Px(2.0)),
-                        ..Default::default()
-                    },
-                    BorderColor::all(Color::WHITE),
-                    AuxiliaryFOVIndicator,
-                ));
+                offsets.extend([Vec2::new(-1., 0.), Vec2::new(1., 0.)]);
             }
-
             if grid.wrap_y() {
+                offsets.extend([Vec2::new(0., -1.), Vec2::new(0., 1.)]);
+            }
+            for offset in offsets {
                 parent.spawn((
                     Node {
                         position_type: PositionType::Absolute,
-                        bottom: Val::Px(MINIMAP_HEIGHT),
-                        width: Val::Px(fov_indicator_width),
-                        height: Val::Px(fov_indicator_height),
-                        border: UiRect::all(Val::Px(2.0)),
-                        ..Default::default()
-                    },
-                    BorderColor::all(Color::WHITE),
-                    AuxiliaryFOVIndicator,
-                ));
-                parent.spawn((
-                    Node {
-                        position_type: PositionType::Absolute,
-                        bottom: Val::Px(-MINIMAP_HEIGHT),
-                        width: Val::Px(fov_indicator_width),
-                        height: Val::Px(fov_indicator_height),
                         border: UiRect::all(Val::Px(2.0)),
                         ..Default::default()
                     },
                     BorderColor::all(Color::WHITE),
-                    AuxiliaryFOVIndicator,
+                    AuxiliaryFOVIndicator(offset),
                 ));
             }
         });
@@ -257,87 +511,75 @@ pub fn setup_minimap(
 
 fn minimap_click_handler(
     click: On<Pointer<Click>>,
-    query_main_camera: Single<(&mut Transform, &Projection), With<MainCamera>>,
-    query_minimap_indicator: Single<&mut Node, With<FieldOfViewIndicator>>,
-    mut query_auxiliary_fov_indicators: Query<
-        &mut Node,
-        (With<AuxiliaryFOVIndicator>, Without<FieldOfViewIndicator>),
-    >,
     map: Option<Res<TileMapResource>>,
-    default_fov_indicator_size: Res<DefaultFovIndicatorSize>,
+    mut camera_commands: ResMut<CameraCommands>,
 ) {
-    if map.is_none() {
+    let Some(map) = map else {
         return;
     };
+    // Dragging the handles of the minimap doesn't move the camera.
+    if click.entity != click.original_event_target()
+        || !matches!(click.button, PointerButton::Primary)
+    {
+        return;
+    }
 
-    let fov_width = default_fov_indicator_size.width;
-    let fov_height = default_fov_indicator_size.height;
-
-    let tile_map = &map.unwrap().0;
-    let grid = tile_map.world_grid.grid;
+    let grid = map.0.world_grid.grid;
     let width = grid.center()[0] * 2.0;
     let height = grid.center()[1] * 2.0;
 
-    let (mut camera_transform, projection) = query_main_camera.into_inner();
-
-    if matches!(click.button, PointerButton::Primary)
-        && let Projection::Orthographic(orthographic) = projection
-    {
-        let scale = orthographic.scale;
-
-        let drag_position = click.hit.position.unwrap().truncate();
-        // Invert the y-axis to match the world coordinate system
-        let normalized_drag_position = Vec2::new(drag_position[0] + 0.5, -drag_position[1] + 0.5);
-
-        camera_transform.translation.x = normalized_drag_position[0] * width;
-        camera_transform.translation.y = normalized_drag_position[1] * height;
-
-        let mut minimap_indicator_node = query_minimap_indicator.into_inner();
-        minimap_indicator_node.left =
-            Val::Px(normalized_drag_position[0] * MINIMAP_WIDTH - fov_width / 2.0 * scale);
-        minimap_indicator_node.bottom =
-            Val::Px(normalized_drag_position[1] * MINIMAP_HEIGHT - fov_height / 2.0 * scale);
-        minimap_indicator_node.width = Val::Px(fov_width * scale);
-        minimap_indicator_node.height = Val::Px(fov_height * scale);
-
-        query_auxiliary_fov_indicators
-            .iter_mut()
-            .for_each(|mut node| {
-                node.width = Val::Px(fov_width * scale);
-                node.height = Val::Px(fov_height * scale);
-            });
-    }
+    let drag_position = click.hit.position.unwrap().truncate();
+    // Invert the y-axis to match the world coordinate system
+    let normalized_drag_position = Vec2::new(drag_position[0] + 0.5, -drag_position[1] + 0.5);
+
+    // The field of view indicator follows the camera, see `minimap_fov_update`.
+    camera_commands.pan_to(
+        Vec2::new(
+            normalized_drag_position[0] * width,
+            normalized_drag_position[1] * height,
+        ),
+        PAN_DURATION,
+    );
 }
 
+/// Move the field of view indicator to the area seen by the main camera, sized to the zoom of the camera
+/// and to the size of the minimap.
 pub fn minimap_fov_update(
-    query_main_camera: Single<(&Transform, &Projection), (Changed<Camera>, With<MainCamera>)>,
+    query_main_camera: Single<(Ref<Transform>, Ref<Projection>), With<MainCamera>>,
     map: Option<Res<TileMapResource>>,
+    minimap_layout: Res<MinimapLayout>,
     query_minimap_indicator: Single<&mut Node, With<FieldOfViewIndicator>>,
     mut query_auxiliary_fov_indicators: Query<
-        &mut Node,
-        (With<AuxiliaryFOVIndicator>, Without<FieldOfViewIndicator>),
+        (&mut Node, &AuxiliaryFOVIndicator),
+        Without<FieldOfViewIndicator>,
     >,
     default_fov_indicator_size: Res<DefaultFovIndicatorSize>,
 ) {
-    if map.is_none() {
+    let Some(map) = map else {
+        return;
+    };
+    let (camera_transform, projection) = query_main_camera.into_inner();
+    if !camera_transform.is_changed()
+        && !projection.is_changed()
+        && !minimap_layout.is_changed()
+        && !default_fov_indicator_size.is_changed()
+    {
         return;
     }
 
-    let tile_map = &map.unwrap().0;
-    let grid = tile_map.world_grid.grid;
+    let grid = map.0.world_grid.grid;
     let width = grid.center()[0] * 2.0;
     let height = grid.center()[1] * 2.0;
 
-    let (camera_transform, projection) = query_main_camera.into_inner();
-
-    let scale = if let Projection::Orthographic(orthographic) = projection {
+    let scale = if let Projection::Orthographic(orthographic) = &*projection {
         orthographic.scale
     } else {
         1.0
     };
 
-    let fov_width = default_fov_indicator_size.width;
-    let fov_height = default_fov_indicator_size.height;
+    let minimap_size = minimap_layout.size();
+    let fov_width = default_fov_indicator_size.width * minimap_size.x * scale;
+    let fov_height = default_fov_indicator_size.height * minimap_size.y * scale;
 
     let camera_position = camera_transform.translation.truncate().to_array();
     let mut camera_offset_coordinate = grid.pixel_to_offset(camera_position);
@@ -364,16 +606,234 @@ pub fn minimap_fov_update(
 
     let mut minimap_indicator_node = query_minimap_indicator.into_inner();
     minimap_indicator_node.left =
-        Val::Px(normalized_drag_position[0] * MINIMAP_WIDTH - fov_width / 2.0 * scale);
+        Val::Px(normalized_drag_position[0] * minimap_size.x - fov_width / 2.0);
     minimap_indicator_node.bottom =
-        Val::Px(normalized_drag_position[1] * MINIMAP_HEIGHT - fov_height / 2.0 * scale);
-    minimap_indicator_node.width = Val::Px(fov_width * scale);
-    minimap_indicator_node.height = Val::Px(fov_height * scale);
-
-    query_auxiliary_fov_indicators
-        .iter_mut()
-        .for_each(|mut node| {
-            node.width = Val::Px(fov_width * scale);
-            node.height = Val::Px(fov_height * scale);
-        });
+        Val::Px(normalized_drag_position[1] * minimap_size.y - fov_height / 2.0);
+    minimap_indicator_node.width = Val::Px(fov_width);
+    minimap_indicator_node.height = Val::Px(fov_height);
+
+    // The copies of the indicator are one minimap away from it, for the maps which wrap around.
+    query_auxiliary_fov_indicators.iter_mut().for_each(
+        |(mut node, AuxiliaryFOVIndicator(offset))| {
+            node.left = Val::Px(offset.x * minimap_size.x);
+            node.bottom = Val::Px(offset.y * minimap_size.y);
+            node.width = Val::Px(fov_width);
+            node.height = Val::Px(fov_height);
+        },
+    );
+}
+
+/// Follow the cursor while the move handle is dragged.
+fn move_minimap_on_drag(
+    drag: On<Pointer<Drag>>,
+    mut query_panel: Single<&mut Node, With<MinimapPanel>>,
+) {
+    let position = drag.pointer_location.position - Vec2::splat(HANDLE_SIZE / 2.);
+    query_panel.left = Val::Px(position.x);
+    query_panel.top = Val::Px(position.y);
+    query_panel.right = Val::Auto;
+    query_panel.bottom = Val::Auto;
+}
+
+/// Dock the minimap to the corner of the window closest to where the move handle was dropped.
+fn dock_minimap_on_drag_end(
+    drag_end: On<Pointer<DragEnd>>,
+    window: Single<&Window>,
+    mut minimap_layout: ResMut<MinimapLayout>,
+) {
+    let position = drag_end.pointer_location.position;
+    let size = minimap_layout.size();
+    // The middle of the minimap, which is dragged by its top left corner.
+    let center = position + size / 2.;
+    minimap_layout.corner = MinimapCorner {
+        right: center.x > window.width() / 2.,
+        bottom: center.y > window.height() / 2.,
+    };
+}
+
+/// Scale the minimap while the resize handle is dragged away from the docked corner or toward it.
+fn resize_minimap_on_drag(drag: On<Pointer<Drag>>, mut minimap_layout: ResMut<MinimapLayout>) {
+    let corner = minimap_layout.corner;
+    // The handle is on the free corner, so moving it away from the docked corner grows the minimap.
+    let growth = Vec2::new(
+        if corner.right {
+            -drag.delta.x
+        } else {
+            drag.delta.x
+        },
+        if corner.bottom {
+            -drag.delta.y
+        } else {
+            drag.delta.y
+        },
+    );
+    let scale = minimap_layout.scale + (growth.x / MINIMAP_WIDTH + growth.y / MINIMAP_HEIGHT) / 2.;
+    minimap_layout.scale = scale.clamp(MIN_MINIMAP_SCALE, MAX_MINIMAP_SCALE);
+}
+
+/// Place and size the minimap for its layout, and save the layout once it changed.
+pub fn apply_minimap_layout(
+    minimap_layout: Res<MinimapLayout>,
+    mut query_panel: Query<&mut Node, With<MinimapPanel>>,
+    mut query_resize_handle: Query<&mut Node, (With<MinimapResizeHandle>, Without<MinimapPanel>)>,
+) {
+    if !minimap_layout.is_changed() {
+        return;
+    }
+    for mut node in query_panel.iter_mut() {
+        *node = Node {
+            position_type: node.position_type,
+            border: node.border,
+            overflow: node.overflow,
+            ..minimap_layout.panel_node()
+        };
+    }
+    for mut node in query_resize_handle.iter_mut() {
+        *node = Node {
+            position_type: node.position_type,
+            width: node.width,
+            height: node.height,
+            ..minimap_layout.resize_handle_node()
+        };
+    }
+    if !minimap_layout.is_added()
+        && let Err(error) = minimap_layout.write(Path::new(MINIMAP_LAYOUT_PATH))
+    {
+        error!("Can't save the minimap layout: {error}");
+    }
+}
+
+pub fn cycle_minimap_mode_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut minimap_mode: ResMut<MinimapMode>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyV) {
+        *minimap_mode = minimap_mode.next();
+    }
+}
+
+/// Recolor the tiles of the minimap when its mode changes, and in the political mode when the borders or the
+/// palette of the nations change.
+pub fn update_minimap_tiles(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    minimap_mode: Res<MinimapMode>,
+    minimap_materials: Option<ResMut<MinimapMaterials>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut removed_cities: RemovedComponents<City>,
+    query_city: Query<(&City, &Owner)>,
+    query_changed_city: Query<(), Or<(Changed<City>, Changed<Owner>)>>,
+    mut query_tile: Query<(&MinimapTile, &mut MeshMaterial2d<ColorMaterial>)>,
+    mut query_resource_layer: Query<&mut Visibility, With<MinimapResourceLayer>>,
+) {
+    let Some(mut minimap_materials) = minimap_materials else {
+        return;
+    };
+    let borders_changed = removed_cities.read().count() > 0 || !query_changed_city.is_empty();
+    // The colors of the nations change with the palette, so their materials are made again.
+    if accessibility_settings.is_changed() {
+        minimap_materials.nations.clear();
+    }
+    let political_changed = *minimap_mode == MinimapMode::Political
+        && (borders_changed || accessibility_settings.is_changed());
+    if !minimap_mode.is_changed() && !minimap_materials.is_added() && !political_changed {
+        return;
+    }
+
+    let tile_map = &map.0;
+    let mut tile_owners = HashMap::new();
+    if *minimap_mode == MinimapMode::Political {
+        for (city, owner) in query_city.iter() {
+            for &tile in &city.owned_tiles {
+                tile_owners.insert(tile, owner.nation());
+            }
+        }
+    }
+
+    let minimap_materials = &mut *minimap_materials;
+    for (minimap_tile, mut material) in query_tile.iter_mut() {
+        let tile = minimap_tile.0;
+        let terrain_type = tile.terrain_type(tile_map);
+        let is_water = terrain_type == TerrainType::Water;
+        material.0 = match *minimap_mode {
+            MinimapMode::Terrain => minimap_materials.terrain[tile.base_terrain(tile_map)].clone(),
+            MinimapMode::Political => match tile_owners.get(&tile) {
+                Some(&nation) => minimap_materials
+                    .nations
+                    .entry(nation)
+                    .or_insert_with(|| {
+                        let color = accessibility_settings
+                            .player_colors(nation, &ruleset.0)
+                            .outer();
+                        color_materials.add(ColorMaterial::from_color(color))
+                    })
+                    .clone(),
+                None if is_water => minimap_materials.water.clone(),
+                None => minimap_materials.land.clone(),
+            },
+            MinimapMode::Resources if is_water => minimap_materials.dim_water.clone(),
+            MinimapMode::Resources => minimap_materials.dim_land.clone(),
+            MinimapMode::HeightMap => match terrain_type {
+                TerrainType::Water => minimap_materials.water.clone(),
+                TerrainType::Flatland => minimap_materials.land.clone(),
+                TerrainType::Hill => minimap_materials.hill.clone(),
+                TerrainType::Mountain => minimap_materials.mountain.clone(),
+            },
+        };
+    }
+
+    for mut visibility in query_resource_layer.iter_mut() {
+        *visibility = if matches!(*minimap_mode, MinimapMode::Terrain | MinimapMode::Resources) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Uncover the tiles of the minimap the player explored, and veil the ones the player doesn't see now.
+pub fn update_minimap_fog(
+    player_civilization: Option<Res<PlayerCivilization>>,
+    visibility_layer: Res<VisibilityLayer>,
+    minimap_fog: Option<ResMut<MinimapFog>>,
+    mut query_fog: Query<
+        (&mut Visibility, &mut MeshMaterial2d<ColorMaterial>),
+        With<MinimapFogTile>,
+    >,
+) {
+    let (Some(player_civilization), Some(mut minimap_fog)) = (player_civilization, minimap_fog)
+    else {
+        return;
+    };
+    let player = player_civilization.0;
+    // What is drawn is only a cache, changing it must not run this system again.
+    let minimap_fog = minimap_fog.bypass_change_detection();
+    if minimap_fog.player != Some(player) {
+        minimap_fog.player = Some(player);
+        minimap_fog.drawn.fill(None);
+    }
+
+    for (index, &entity) in minimap_fog.entities.iter().enumerate() {
+        let tile_visibility = visibility_layer.get(player, Tile::new(index));
+        if minimap_fog.drawn[index] == Some(tile_visibility) {
+            continue;
+        }
+        minimap_fog.drawn[index] = Some(tile_visibility);
+
+        let Ok((mut visibility, mut material)) = query_fog.get_mut(entity) else {
+            continue;
+        };
+        match tile_visibility {
+            TileVisibility::Visible => *visibility = Visibility::Hidden,
+            TileVisibility::Explored => {
+                *visibility = Visibility::Inherited;
+                material.0 = minimap_fog.explored.clone();
+            }
+            TileVisibility::Unexplored => {
+                *visibility = Visibility::Inherited;
+                material.0 = minimap_fog.unexplored.clone();
+            }
+        }
+    }
 }
@@ -0,0 +1,495 @@
+//! The mods: folders in [`MODS_PATH`] whose `jsons` folder holds ruleset files in the format of the base ruleset,
+//! e.g. `mods/More Units/jsons/Units.json`.
+//!
+//! The main menu lists the installed mods, see [`ModList`], where the player enables them and chooses their order.
+//! Before a new game, the enabled mods are merged in order on top of the base ruleset, see [`merge_mod_files`]: an entry
+//! whose name isn't in the ruleset is added, an entry with the name of an entry of the ruleset overrides it, and an
+//! entry with `"delete": true` removes it. A mod adding units, buildings or resources must also add their images in
+//! `assets/Images`.
+//!
+//! The mods written for Unciv are read too, see [`crate::unciv_ruleset`], and so are the gameplay XML files of
+//! Civilization V, see [`crate::civ5_xml`]. The files made for an older version of the format are upgraded, see
+//! [`crate::ruleset_schema`].
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+use civ_map_generator::ruleset::Ruleset;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{
+    RulesetResource,
+    assets::AppState,
+    civ5_xml::read_civ5_xml,
+    ruleset_schema::upgraded_entries,
+    ruleset_validation::RulesetFiles,
+    scenario::PendingScenario,
+    unciv_ruleset::{UNCIV_MOD_OPTIONS_FILE, UNCIV_TERRAINS_FILE, convert_unciv_files},
+};
+
+/// The folder of the installed mods, one folder per mod.
+pub const MODS_PATH: &str = "mods";
+/// The folder of a mod holding its ruleset files.
+const MOD_RULESET_FOLDER: &str = "jsons";
+
+/// An installed mod.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ModEntry {
+    /// The name of the folder of the mod.
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// The installed mods in their load order: a mod later in the list overrides the mods before it.
+#[derive(Resource, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ModList(pub Vec<ModEntry>);
+
+impl ModList {
+    /// The mods installed in [`MODS_PATH`], in the order and with the choices of `saved`. The mods installed since
+    /// are added disabled at the end, and the mods removed since are left out.
+    pub fn scan(saved: &ModList) -> Self {
+        let mut installed: Vec<String> = fs::read_dir(MODS_PATH)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                path.is_dir()
+                    .then(|| path.file_name()?.to_str().map(str::to_owned))
+                    .flatten()
+            })
+            .collect();
+        installed.sort();
+
+        let mut mods: Vec<ModEntry> = saved
+            .0
+            .iter()
+            .filter(|entry| installed.contains(&entry.name))
+            .cloned()
+            .collect();
+        for name in installed {
+            if !mods.iter().any(|entry| entry.name == name) {
+                mods.push(ModEntry {
+                    name,
+                    enabled: false,
+                });
+            }
+        }
+        Self(mods)
+    }
+
+    /// The base ruleset with the enabled mods merged in order. A mod which can't be read, or which adds broken
+    /// references to the ruleset, is left out, see [`crate::ruleset_validation`].
+    pub fn ruleset(&self) -> Ruleset {
+        let mut ruleset = Ruleset::default();
+        let mut ruleset_files = RulesetFiles::read_base().unwrap_or_else(|error| {
+            error!("Can't read the base ruleset files to validate them: {error}");
+            RulesetFiles::default()
+        });
+        let mut diagnostics = ruleset_files.validate();
+        for diagnostic in &diagnostics {
+            warn!("Broken reference in the base ruleset: {diagnostic}");
+        }
+
+        for (name, folder) in self.enabled_folders() {
+            let files = match read_mod_files(&folder, &ruleset) {
+                Ok(files) => files,
+                Err(error) => {
+                    error!("Can't load the mod {name}: {error}");
+                    continue;
+                }
+            };
+
+            let mut checked_files = ruleset_files.clone();
+            checked_files.add(&folder, files.clone());
+            let checked_diagnostics = checked_files.validate();
+            let new_diagnostics: Vec<_> = checked_diagnostics
+                .iter()
+                .filter(|diagnostic| !diagnostics.contains(diagnostic))
+                .collect();
+            if !new_diagnostics.is_empty() {
+                error!("Can't load the mod {name}, it breaks references of the ruleset:");
+                for diagnostic in new_diagnostics {
+                    error!("  {diagnostic}");
+                }
+                continue;
+            }
+
+            if let Err(error) = merge_mod_files(&mut ruleset, files) {
+                error!("Can't load the mod {name}: {error}");
+                continue;
+            }
+            ruleset_files = checked_files;
+            diagnostics = checked_diagnostics;
+        }
+        ruleset
+    }
+
+    /// The enabled mods in load order, with the folder of their ruleset files.
+    pub fn enabled_folders(&self) -> impl Iterator<Item = (&str, PathBuf)> {
+        self.0.iter().filter(|entry| entry.enabled).map(|entry| {
+            let folder = Path::new(MODS_PATH)
+                .join(&entry.name)
+                .join(MOD_RULESET_FOLDER);
+            (entry.name.as_str(), folder)
+        })
+    }
+
+    /// Move the mod at `index` by `step` places in the load order, if it stays in the list.
+    fn move_mod(&mut self, index: usize, step: isize) {
+        if let Some(target) = index.checked_add_signed(step)
+            && target < self.0.len()
+        {
+            self.0.swap(index, target);
+        }
+    }
+}
+
+/// The ruleset files a mod can change. The technologies and the policies, which are grouped into columns and
+/// branches, are kept from the base ruleset.
+pub const MOD_FILES: [&str; 13] = [
+    "BaseTerrains.json",
+    "TerrainTypes.json",
+    "Features.json",
+    "NaturalWonders.json",
+    "TileResources.json",
+    "TileImprovements.json",
+    "Units.json",
+    "UnitTypes.json",
+    "Buildings.json",
+    "Nations.json",
+    "Specialists.json",
+    "Eras.json",
+    "Difficulties.json",
+];
+
+/// The ruleset files of the mod in `folder` by file name, see [`MOD_FILES`]. The files the mod doesn't have are
+/// skipped.
+///
+/// The files of Unciv mods and the Civ V XML files are converted into our format, see [`convert_unciv_files`] and
+/// [`read_civ5_xml`]. `ruleset` is the ruleset the mod is merged into, which tells the terrains the mod doesn't add.
+pub fn read_mod_files(
+    folder: &Path,
+    ruleset: &Ruleset,
+) -> io::Result<HashMap<&'static str, Vec<Value>>> {
+    let mut files = HashMap::new();
+    for file in MOD_FILES {
+        if let Some(entries) = read_mod_file(&folder.join(file))? {
+            files.insert(file, upgraded_entries(entries, file)?);
+        }
+    }
+    let civ5_terrains = read_civ5_xml(folder, &mut files)?;
+    let unciv_terrains = read_mod_file(&folder.join(UNCIV_TERRAINS_FILE))?
+        .map(|terrains| upgraded_entries(terrains, UNCIV_TERRAINS_FILE))
+        .transpose()?;
+    if civ5_terrains.is_some() || unciv_terrains.is_some() {
+        let terrains = civ5_terrains
+            .into_iter()
+            .chain(unciv_terrains)
+            .flatten()
+            .collect();
+        let mod_options = read_mod_file(&folder.join(UNCIV_MOD_OPTIONS_FILE))?;
+        convert_unciv_files(&mut files, terrains, mod_options, ruleset);
+    }
+    Ok(files)
+}
+
+/// Merge the ruleset files of a mod, read by [`read_mod_files`], into `ruleset`. Every entry is read before any
+/// entry is merged, so a mod with an invalid entry changes nothing.
+pub fn merge_mod_files(
+    ruleset: &mut Ruleset,
+    mut files: HashMap<&'static str, Vec<Value>>,
+) -> io::Result<()> {
+    macro_rules! merge_files {
+        ($($file:literal => $field:ident),* $(,)?) => {
+            $(
+                let $field = named_entries(files.remove($file).unwrap_or_default(), $file)?;
+            )*
+            $(
+                for (name, entry) in $field {
+                    match entry {
+                        Some(entry) => {
+                            ruleset.$field.insert(name, entry);
+                        }
+                        None => {
+                            ruleset.$field.remove(&name);
+                        }
+                    }
+                }
+            )*
+        };
+    }
+
+    merge_files!(
+        "BaseTerrains.json" => base_terrains,
+        "TerrainTypes.json" => terrain_types,
+        "Features.json" => features,
+        "NaturalWonders.json" => natural_wonders,
+        "TileResources.json" => tile_resources,
+        "TileImprovements.json" => tile_improvements,
+        "Units.json" => units,
+        "UnitTypes.json" => unit_types,
+        "Buildings.json" => buildings,
+        "Nations.json" => nations,
+        "Specialists.json" => specialists,
+        "Eras.json" => eras,
+        "Difficulties.json" => difficulties,
+    );
+    Ok(())
+}
+
+/// The content of a ruleset file, `None` if the file doesn't exist.
+pub fn read_mod_file(path: &Path) -> io::Result<Option<Value>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    serde_json::from_str(&strip_trailing_commas(&strip_comments(&content)))
+        .map(Some)
+        .map_err(|error| io::Error::other(format!("{}: {error}", path.display())))
+}
+
+/// The entries of the ruleset file `file` by name, `None` for the entries deleting the entry of this name.
+fn named_entries<T: DeserializeOwned>(
+    entries: Vec<Value>,
+    file: &str,
+) -> io::Result<Vec<(String, Option<T>)>> {
+    entries
+        .into_iter()
+        .map(|mut entry| {
+            let name = entry
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+                .ok_or_else(|| io::Error::other(format!("{file}: an entry has no name")))?;
+            let delete = entry
+                .as_object_mut()
+                .and_then(|object| object.remove("delete"))
+                .is_some_and(|delete| delete == Value::Bool(true));
+            if delete {
+                return Ok((name, None));
+            }
+            let entry = serde_json::from_value(entry)
+                .map_err(|error| io::Error::other(format!("{name} in {file}: {error}")))?;
+            Ok((name, Some(entry)))
+        })
+        .collect()
+}
+
+/// Remove the `//` and `/* */` comments the ruleset files have, which JSON doesn't allow.
+fn strip_comments(content: &str) -> String {
+    let mut stripped = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(char) = chars.next() {
+        if in_string {
+            stripped.push(char);
+            match char {
+                '\\' => stripped.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (char, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                stripped.push(char);
+            }
+            ('/', Some('/')) => while chars.next_if(|&next| next != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                while let Some(next) = chars.next() {
+                    if next == '*' && chars.next_if_eq(&'/').is_some() {
+                        break;
+                    }
+                }
+            }
+            _ => stripped.push(char),
+        }
+    }
+    stripped
+}
+
+/// Remove the commas before the end of a list or an object, which JSON doesn't allow either.
+fn strip_trailing_commas(content: &str) -> String {
+    let mut stripped = String::with_capacity(content.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, char) in content.char_indices() {
+        if in_string {
+            match char {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if char == '"' {
+            in_string = true;
+        } else if char == ',' && content[index + 1..].trim_start().starts_with([']', '}']) {
+            continue;
+        }
+        stripped.push(char);
+    }
+    stripped
+}
+
+/// The list of the mods in the main menu.
+#[derive(Component)]
+struct ModPanel;
+
+/// A button of a mod in the list.
+#[derive(Component, Clone, Copy)]
+enum ModButton {
+    Toggle(usize),
+    Move(usize, isize),
+}
+
+pub struct ModManagerPlugin;
+
+impl Plugin for ModManagerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (apply_mods, show_mod_panel)
+                .run_if(in_state(AppState::MainMenu).and(resource_changed::<ModList>))
+                .run_if(not(resource_exists::<PendingScenario>)),
+        )
+        .add_systems(OnExit(AppState::MainMenu), despawn_mod_panel);
+    }
+}
+
+/// Replace the ruleset with the base ruleset and the enabled mods, so the setup of the new game already uses them,
+/// e.g. the nations added by a mod can be chosen.
+fn apply_mods(mut commands: Commands, mod_list: Res<ModList>) {
+    commands.insert_resource(RulesetResource(Arc::new(mod_list.ruleset())));
+}
+
+fn show_mod_panel(
+    mut commands: Commands,
+    mod_list: Res<ModList>,
+    query_panel: Query<Entity, With<ModPanel>>,
+) {
+    for panel in query_panel.iter() {
+        commands.entity(panel).despawn();
+    }
+
+    let text_font = TextFont {
+        font_size: 16.,
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.),
+                top: Val::Px(20.),
+                width: Val::Px(300.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.),
+                padding: UiRect::all(Val::Px(10.)),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            BorderColor::all(Color::WHITE),
+            // Above the setup of the new game, which covers the screen.
+            GlobalZIndex(1),
+            ModPanel,
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text("Mods".to_owned()),
+                TextFont {
+                    font_size: 20.,
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+            if mod_list.0.is_empty() {
+                panel.spawn((
+                    Text(format!("No mods installed in the `{MODS_PATH}` folder")),
+                    text_font.clone(),
+                    Pickable::IGNORE,
+                ));
+            }
+            for (index, entry) in mod_list.0.iter().enumerate() {
+                panel
+                    .spawn((
+                        Node {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(6.),
+                            ..default()
+                        },
+                        Pickable::IGNORE,
+                    ))
+                    .with_children(|row| {
+                        row.spawn((
+                            Node {
+                                flex_grow: 1.,
+                                ..default()
+                            },
+                            Text(entry.name.clone()),
+                            text_font.clone(),
+                            Pickable::IGNORE,
+                        ));
+                        let buttons = [
+                            (
+                                if entry.enabled { "On" } else { "Off" },
+                                ModButton::Toggle(index),
+                            ),
+                            ("Up", ModButton::Move(index, -1)),
+                            ("Down", ModButton::Move(index, 1)),
+                        ];
+                        for (label, button) in buttons {
+                            row.spawn((
+                                Node {
+                                    padding: UiRect::horizontal(Val::Px(6.)),
+                                    border: UiRect::all(Val::Px(1.)),
+                                    ..default()
+                                },
+                                BorderColor::all(Color::WHITE),
+                                Text(label.to_owned()),
+                                text_font.clone(),
+                                button,
+                            ))
+                            .observe(change_mod_on_click);
+                        }
+                    });
+            }
+        });
+}
+
+fn change_mod_on_click(
+    click: On<Pointer<Click>>,
+    mut mod_list: ResMut<ModList>,
+    query_button: Query<&ModButton>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    match query_button.get(click.entity) {
+        Ok(&ModButton::Toggle(index)) => {
+            if let Some(entry) = mod_list.0.get_mut(index) {
+                entry.enabled = !entry.enabled;
+            }
+        }
+        Ok(&ModButton::Move(index, step)) => mod_list.move_mod(index, step),
+        Err(_) => {}
+    }
+}
+
+fn despawn_mod_panel(mut commands: Commands, query_panel: Query<Entity, With<ModPanel>>) {
+    for panel in query_panel.iter() {
+        commands.entity(panel).despawn();
+    }
+}
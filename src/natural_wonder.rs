@@ -0,0 +1,230 @@
+//! The discovery of the natural wonders by the player: the first time the player explores a tile of a natural wonder,
+//! a splash panel shows its art, its yields and its effects.
+
+use std::collections::{HashSet, VecDeque};
+
+use bevy::{
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+use civ_map_generator::tile::Tile;
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::{AppState, MaterialResource},
+    civilization::PlayerCivilization,
+    visibility::VisibilityLayer,
+};
+
+/// Written when the player explores a tile of a natural wonder for the first time.
+#[derive(Message)]
+pub struct NaturalWonderDiscovered {
+    pub name: String,
+    pub tile: Tile,
+}
+
+/// The natural wonders the player has explored, by name, and the discoveries waiting for their splash.
+///
+/// It is derived from the [`VisibilityLayer`], so the natural wonders explored in a loaded game are discovered
+/// silently when the game is loaded.
+#[derive(Resource, Default)]
+pub struct DiscoveredNaturalWonders {
+    names: HashSet<String>,
+    pending_splashes: VecDeque<String>,
+}
+
+/// The splash panel of a natural wonder.
+#[derive(Component)]
+pub struct NaturalWonderSplash;
+
+const SPLASH_ART_SIZE: f32 = 256.;
+
+pub struct NaturalWonderPlugin;
+
+impl Plugin for NaturalWonderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DiscoveredNaturalWonders>()
+            .add_message::<NaturalWonderDiscovered>()
+            .add_systems(
+                Update,
+                (
+                    discover_natural_wonders.run_if(resource_exists_and_changed::<VisibilityLayer>),
+                    show_natural_wonder_splash,
+                    close_natural_wonder_splash_on_escape,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            );
+    }
+}
+
+/// Discover the natural wonders on the tiles the player explored.
+fn discover_natural_wonders(
+    map: Res<TileMapResource>,
+    player_civilization: Res<PlayerCivilization>,
+    visibility_layer: Res<VisibilityLayer>,
+    mut discovered: ResMut<DiscoveredNaturalWonders>,
+    mut natural_wonder_discovered: MessageWriter<NaturalWonderDiscovered>,
+) {
+    let tile_map = &map.0;
+    // A new game or a loaded game: what is already explored isn't a discovery.
+    let is_new_layer = visibility_layer.is_added();
+    if is_new_layer {
+        *discovered = DiscoveredNaturalWonders::default();
+    }
+
+    for tile in tile_map.all_tiles() {
+        let Some(natural_wonder) = tile.natural_wonder(tile_map) else {
+            continue;
+        };
+        let name = natural_wonder.as_str();
+        if discovered.names.contains(name)
+            || !visibility_layer.is_explored(player_civilization.0, tile)
+        {
+            continue;
+        }
+        discovered.names.insert(name.to_owned());
+        if !is_new_layer {
+            discovered.pending_splashes.push_back(name.to_owned());
+            natural_wonder_discovered.write(NaturalWonderDiscovered {
+                name: name.to_owned(),
+                tile,
+            });
+        }
+    }
+}
+
+/// Show the splash of the next discovered natural wonder once the previous splash is closed.
+fn show_natural_wonder_splash(
+    mut commands: Commands,
+    ruleset: Res<RulesetResource>,
+    materials: Res<MaterialResource>,
+    mut discovered: ResMut<DiscoveredNaturalWonders>,
+    query_splash: Query<(), With<NaturalWonderSplash>>,
+) {
+    if !query_splash.is_empty() {
+        return;
+    }
+    let Some(name) = discovered.pending_splashes.pop_front() else {
+        return;
+    };
+    let natural_wonder = &ruleset.0.natural_wonders[name.as_str()];
+
+    let yields: Vec<String> = [
+        (natural_wonder.food, "Food"),
+        (natural_wonder.production, "Production"),
+        (natural_wonder.gold, "Gold"),
+        (natural_wonder.science, "Science"),
+        (natural_wonder.culture, "Culture"),
+        (natural_wonder.faith, "Faith"),
+    ]
+    .into_iter()
+    .filter(|&(amount, _)| amount != 0.)
+    .map(|(amount, name)| format!("{amount:+} {name}"))
+    .collect();
+    // The uniques about where the natural wonder is placed on the map don't matter to the player.
+    let effects: Vec<&str> = natural_wonder
+        .uniques
+        .iter()
+        .map(String::as_str)
+        .filter(|unique| !unique.starts_with("Must be") && !unique.starts_with("Occurs"))
+        .collect();
+    let mut description = format!("You discovered {name}!");
+    if !yields.is_empty() {
+        description.push_str(&format!("\n{}", yields.join(", ")));
+    }
+    for effect in effects {
+        description.push_str(&format!("\n{effect}"));
+    }
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(50.),
+                top: Val::Percent(50.),
+                width: Val::Px(SPLASH_ART_SIZE + 24.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.),
+                padding: UiRect::all(Val::Px(12.)),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            // Centered on the screen.
+            UiTransform::from_translation(Val2::percent(-50., -50.)),
+            BackgroundColor(Color::BLACK.with_alpha(0.9)),
+            BorderColor::all(Color::srgb(1., 0.85, 0.1)),
+            GlobalZIndex(10),
+            NaturalWonderSplash,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text(name.clone()),
+                TextFont {
+                    font_size: 20.,
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+            if let Some(art) = materials.get_texture_handle(&name) {
+                parent.spawn((
+                    Node {
+                        width: Val::Px(SPLASH_ART_SIZE),
+                        height: Val::Px(SPLASH_ART_SIZE),
+                        ..default()
+                    },
+                    ImageNode::new(art),
+                    Pickable::IGNORE,
+                ));
+            }
+            parent.spawn((
+                Text(description),
+                TextFont {
+                    font_size: 14.,
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+            parent
+                .spawn((
+                    Node {
+                        padding: UiRect::horizontal(Val::Px(8.)),
+                        border: UiRect::all(Val::Px(1.)),
+                        ..default()
+                    },
+                    BorderColor::all(Color::WHITE),
+                    Text("Close".to_owned()),
+                    TextFont {
+                        font_size: 14.,
+                        ..default()
+                    },
+                ))
+                .observe(close_natural_wonder_splash_on_click);
+        });
+}
+
+fn close_natural_wonder_splash_on_escape(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    query_splash: Query<Entity, With<NaturalWonderSplash>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        for splash in query_splash.iter() {
+            commands.entity(splash).despawn();
+        }
+    }
+}
+
+fn close_natural_wonder_splash_on_click(
+    click: On<Pointer<Click>>,
+    mut commands: Commands,
+    query_splash: Query<Entity, With<NaturalWonderSplash>>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    for splash in query_splash.iter() {
+        commands.entity(splash).despawn();
+    }
+}
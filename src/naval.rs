@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    city::{City, grow_cities},
+    diplomacy::DiplomacyState,
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, UnitDomain},
+    unit_component::{Owner, Unit},
+    yields::TileYields,
+};
+
+pub struct NavalPlugin;
+
+impl Plugin for NavalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            TurnProcessing,
+            update_blockades.in_set(TurnSet::Cities).before(grow_cities),
+        );
+    }
+}
+
+/// Blockade the water tiles of every city which are on or next to a military ship of a nation at war with it.
+///
+/// The citizens of the city can't work blockaded tiles, so the ones working them move to other tiles
+/// and the city loses the yields of the sea until the ships leave.
+fn update_blockades(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    diplomacy: Res<DiplomacyState>,
+    mut query_city: Query<(&mut City, &Owner)>,
+    query_unit: Query<(&Unit, &Owner, &MapUnit)>,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+
+    let ships: Vec<(Tile, Nation)> = query_unit
+        .iter()
+        .filter(|(unit, ..)| {
+            matches!(unit, Unit::Military(_))
+                && UnitDomain::of_unit(unit.name(), ruleset) == UnitDomain::Water
+        })
+        .map(|(_, owner, map_unit)| (map_unit.tile, owner.nation()))
+        .collect();
+
+    for (mut city, owner) in query_city.iter_mut() {
+        let blockaded_tiles: Vec<Tile> = city
+            .owned_tiles
+            .iter()
+            .copied()
+            .filter(|&tile| {
+                tile.is_water(tile_map)
+                    && ships.iter().any(|&(ship_tile, ship_nation)| {
+                        ship_tile.distance_to(tile, grid) <= 1
+                            && diplomacy.is_at_war(owner.nation(), ship_nation)
+                    })
+            })
+            .collect();
+        if blockaded_tiles != city.blockaded_tiles {
+            city.blockaded_tiles = blockaded_tiles;
+            city.assign_citizens(grid, &tile_yields);
+        }
+    }
+}
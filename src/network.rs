@@ -0,0 +1,612 @@
+//! Lockstep network multiplayer.
+//!
+//! Every player runs the whole simulation, and only the commands of the players are sent over the network.
+//! The host puts the commands of all players in a single stream and sends it to every client, and every player,
+//! the host included, carries out the stream in the same order. The game is deterministic, so every player
+//! stays in the same state, which is checked with a hash of the state after every turn.
+//!
+//! The messages are newline-delimited JSON over TCP.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+};
+
+use bevy::prelude::*;
+use civ_map_generator::nation::Nation;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    TileMapResource,
+    assets::AppState,
+    city::City,
+    civilization::{Civilizations, PlayerCivilization},
+    command::{ExecuteCommand, PlayerCommand},
+    rng::GameRng,
+    save::{GameSnapshot, PendingSave, SaveFile, load_save_file},
+    turn::{EndTurn, TurnStarted},
+    unit::MapUnit,
+    unit_component::{Health, Movement, Unit},
+};
+
+/// The frames waited after carrying out a command before a turn may end or a player may join,
+/// so the requests written by the command are handled first.
+const SETTLE_FRAMES: u32 = 3;
+
+/// How to start a network game, from the command line: `--host <port>` or `--join <address>`.
+#[derive(Resource, Clone, Debug)]
+pub enum NetworkSetting {
+    Host { port: u16 },
+    Join { address: SocketAddr },
+}
+
+impl NetworkSetting {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Option<Self> {
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--host" => {
+                    let port = args.next()?.parse().ok()?;
+                    return Some(NetworkSetting::Host { port });
+                }
+                "--join" => {
+                    let address = args.next()?.parse().ok()?;
+                    return Some(NetworkSetting::Join { address });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// An entry of the stream of the game, carried out in order by every player.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum StreamEntry {
+    Joined(Nation),
+    Left(Nation),
+    Command {
+        nation: Nation,
+        command: PlayerCommand,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum ClientMessage {
+    Command(PlayerCommand),
+    /// The hash of the state of the client at the start of `turn`.
+    StateHash {
+        turn: u32,
+        hash: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum HostMessage {
+    /// The first message sent to a client: the nation it plays and the game it joins.
+    Welcome {
+        nation: Nation,
+        players: Vec<Nation>,
+        game: Box<SaveFile>,
+    },
+    Entry(StreamEntry),
+    /// The state of a player differed from the state of the host at the start of `turn`.
+    Desync {
+        turn: u32,
+    },
+}
+
+/// Written when the state of the players differs at the start of `turn`.
+#[derive(Message)]
+pub struct Desynced {
+    pub turn: u32,
+}
+
+/// A TCP connection sending and receiving newline-delimited JSON messages.
+struct Connection {
+    stream: TcpStream,
+    /// The bytes received after the last complete message.
+    buffer: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Send the whole message, blocking until it is sent.
+    fn send(&mut self, message: &impl Serialize) -> io::Result<()> {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.stream.set_nonblocking(false)?;
+        let result = self.stream.write_all(&line);
+        self.stream.set_nonblocking(true)?;
+        result
+    }
+
+    /// The messages received since the last call, without blocking. Fails once the connection is closed.
+    fn receive<T: DeserializeOwned>(&mut self) -> io::Result<Vec<T>> {
+        let mut chunk = [0; 4096];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(length) => self.buffer.extend_from_slice(&chunk[..length]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            messages.push(serde_json::from_slice(&line[..end])?);
+        }
+        Ok(messages)
+    }
+}
+
+enum Role {
+    Host {
+        listener: TcpListener,
+        clients: Vec<(Nation, Connection)>,
+        /// The hashes sent by the clients which haven't been compared yet.
+        reported_hashes: Vec<(Nation, u32, u64)>,
+    },
+    Client {
+        connection: Connection,
+        inbox: VecDeque<HostMessage>,
+        welcomed: bool,
+    },
+}
+
+/// The network game being played.
+#[derive(Resource)]
+pub struct NetworkSession {
+    role: Role,
+    /// The nations played by people, the local player included. The AI plays the others.
+    pub players: Vec<Nation>,
+    /// The entries of the stream not carried out yet, in order.
+    queue: VecDeque<StreamEntry>,
+    /// The players who ended the current turn.
+    ended_turn: HashSet<Nation>,
+    /// The frames left to wait before carrying out the next entries, see [`SETTLE_FRAMES`].
+    settle_frames: u32,
+    /// Every player ended the turn, so the turn ends once the last commands are handled.
+    turn_ending: bool,
+    /// The turn has ended and is being processed, until [`TurnStarted`].
+    waiting_for_turn: bool,
+    /// The hash of the local state at the start of every turn.
+    hashes: HashMap<u32, u64>,
+}
+
+impl NetworkSession {
+    fn new(role: Role, players: Vec<Nation>) -> Self {
+        Self {
+            role,
+            players,
+            queue: VecDeque::new(),
+            ended_turn: HashSet::new(),
+            settle_frames: 0,
+            turn_ending: false,
+            waiting_for_turn: false,
+            hashes: HashMap::new(),
+        }
+    }
+
+    /// Return `true` if every entry is carried out and handled, so the state can be sent to a new player.
+    fn is_settled(&self) -> bool {
+        self.queue.is_empty()
+            && self.settle_frames == 0
+            && !self.turn_ending
+            && !self.waiting_for_turn
+    }
+
+    /// Carry out the entries of the stream in order.
+    ///
+    /// The turn ends once every player sent [`PlayerCommand::EndTurn`], and no entry is carried out
+    /// while the turn is processed, so every player carries out the same entries in the same turn.
+    fn carry_out_entries(
+        &mut self,
+        execute_command: &mut MessageWriter<ExecuteCommand>,
+        end_turn: &mut MessageWriter<EndTurn>,
+    ) {
+        if self.waiting_for_turn {
+            return;
+        }
+        if self.settle_frames > 0 {
+            self.settle_frames -= 1;
+            return;
+        }
+        if self.turn_ending {
+            self.turn_ending = false;
+            self.waiting_for_turn = true;
+            end_turn.write(EndTurn);
+            return;
+        }
+
+        while let Some(entry) = self.queue.pop_front() {
+            match entry {
+                StreamEntry::Joined(nation) => {
+                    if !self.players.contains(&nation) {
+                        self.players.push(nation);
+                    }
+                }
+                StreamEntry::Left(nation) => {
+                    self.players.retain(|&player| player != nation);
+                    self.ended_turn.remove(&nation);
+                }
+                StreamEntry::Command {
+                    nation,
+                    command: PlayerCommand::EndTurn,
+                } => {
+                    self.ended_turn.insert(nation);
+                }
+                StreamEntry::Command { nation, command } => {
+                    execute_command.write(ExecuteCommand { nation, command });
+                    self.settle_frames = SETTLE_FRAMES;
+                }
+            }
+
+            if self
+                .players
+                .iter()
+                .all(|player| self.ended_turn.contains(player))
+            {
+                self.ended_turn.clear();
+                self.turn_ending = true;
+                self.settle_frames = SETTLE_FRAMES;
+                return;
+            }
+        }
+    }
+}
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(setting) = NetworkSetting::from_args(std::env::args().skip(1)) {
+            app.insert_resource(setting);
+        }
+        app.add_message::<Desynced>()
+            .add_systems(
+                OnEnter(AppState::GameStart),
+                start_network_session.run_if(resource_exists::<NetworkSetting>),
+            )
+            .add_systems(
+                Update,
+                (
+                    hash_state.run_if(on_message::<TurnStarted>),
+                    run_host,
+                    run_client.run_if(not(resource_exists::<PendingSave>)),
+                )
+                    .chain()
+                    .run_if(resource_exists::<NetworkSession>.and(in_state(AppState::GameStart))),
+            );
+    }
+}
+
+/// Host a game, or join one and wait for the game of the host.
+fn start_network_session(
+    mut commands: Commands,
+    setting: Res<NetworkSetting>,
+    player_civilization: Res<PlayerCivilization>,
+) {
+    let session = match *setting {
+        NetworkSetting::Host { port } => {
+            TcpListener::bind(("0.0.0.0", port)).and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                info!("Hosting a network game on port {port}");
+                Ok(NetworkSession::new(
+                    Role::Host {
+                        listener,
+                        clients: Vec::new(),
+                        reported_hashes: Vec::new(),
+                    },
+                    vec![player_civilization.0],
+                ))
+            })
+        }
+        NetworkSetting::Join { address } => TcpStream::connect(address)
+            .and_then(Connection::new)
+            .map(|connection| {
+                info!("Joined the network game on {address}");
+                NetworkSession::new(
+                    Role::Client {
+                        connection,
+                        inbox: VecDeque::new(),
+                        welcomed: false,
+                    },
+                    Vec::new(),
+                )
+            }),
+    };
+
+    match session {
+        Ok(session) => commands.insert_resource(session),
+        Err(error) => error!("Can't start the network game: {error}"),
+    }
+    // The game of the host is loaded when joining, which enters this state again.
+    commands.remove_resource::<NetworkSetting>();
+}
+
+/// Hash the state of the game at the start of the turn, once the turn has been processed.
+///
+/// Only the values most likely to differ when the simulations of the players diverge are hashed.
+fn hash_state(
+    mut turn_started: MessageReader<TurnStarted>,
+    mut session: ResMut<NetworkSession>,
+    rng: Res<GameRng>,
+    civilizations: Res<Civilizations>,
+    query_city: Query<&City>,
+    query_unit: Query<(&Unit, &MapUnit, &Health, &Movement)>,
+) {
+    let Some(&TurnStarted { turn }) = turn_started.read().last() else {
+        return;
+    };
+
+    let mut hasher = StateHasher::default();
+    hasher.write_u64(turn as u64);
+    hasher.write_bytes(&serde_json::to_vec(&*rng).unwrap_or_default());
+
+    let mut nations: Vec<_> = civilizations.iter().map(|(nation, _)| nation).collect();
+    nations.sort_by_key(|nation| nation.as_str());
+    for nation in nations {
+        let civilization = civilizations.get(nation);
+        hasher.write_bytes(nation.as_str().as_bytes());
+        hasher.write_f32(civilization.gold);
+        hasher.write_f32(civilization.culture);
+        hasher.write_f32(civilization.happiness);
+        hasher.write_u64(civilization.researched_technologies.len() as u64);
+    }
+
+    let mut cities: Vec<_> = query_city.iter().collect();
+    cities.sort_by_key(|city| city.tile.index());
+    for city in cities {
+        hasher.write_u64(city.tile.index() as u64);
+        hasher.write_u64(city.population as u64);
+        hasher.write_f32(city.food_stored);
+        hasher.write_u64(city.owned_tiles.len() as u64);
+        hasher.write_u64(city.damage as u64);
+    }
+
+    let mut units: Vec<_> = query_unit.iter().collect();
+    units.sort_by(|(a, a_unit, ..), (b, b_unit, ..)| {
+        a_unit
+            .tile
+            .index()
+            .cmp(&b_unit.tile.index())
+            .then_with(|| a.name().cmp(b.name()))
+    });
+    for (unit, map_unit, health, movement) in units {
+        hasher.write_u64(map_unit.tile.index() as u64);
+        hasher.write_bytes(unit.name().as_bytes());
+        hasher.write_u64(health.current as u64);
+        hasher.write_f32(movement.current);
+    }
+
+    session.hashes.insert(turn, hasher.0);
+    session.waiting_for_turn = false;
+}
+
+/// A 64-bit FNV-1a hasher, which unlike the hasher of the standard library gives the same hash on every machine.
+struct StateHasher(u64);
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl StateHasher {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_bits().to_le_bytes());
+    }
+}
+
+/// Accept new players, put the commands of every player in the stream, and compare the hashes of the clients.
+fn run_host(
+    mut player_command: MessageReader<PlayerCommand>,
+    mut execute_command: MessageWriter<ExecuteCommand>,
+    mut end_turn: MessageWriter<EndTurn>,
+    mut desynced: MessageWriter<Desynced>,
+    mut session: ResMut<NetworkSession>,
+    player_civilization: Res<PlayerCivilization>,
+    map: Res<TileMapResource>,
+    snapshot: GameSnapshot,
+) {
+    let session = &mut *session;
+    let settled = session.is_settled();
+    let Role::Host {
+        listener,
+        clients,
+        reported_hashes,
+    } = &mut session.role
+    else {
+        return;
+    };
+    let mut new_entries = Vec::new();
+
+    // New players join only between two commands, so the game they receive is the game every player has.
+    while settled && let Ok((stream, address)) = listener.accept() {
+        let mut free_nations: Vec<_> = map
+            .0
+            .starting_tile_and_civilization
+            .iter()
+            .filter(|(_, nation)| !session.players.contains(nation))
+            .collect();
+        free_nations.sort_by_key(|(tile, _)| tile.index());
+        let Some(&(_, &nation)) = free_nations.first() else {
+            warn!("Refused {address}: every civilization is already played");
+            continue;
+        };
+
+        let mut players = session.players.clone();
+        players.push(nation);
+        let welcome = HostMessage::Welcome {
+            nation,
+            players,
+            game: Box::new(snapshot.save_file()),
+        };
+        match Connection::new(stream).and_then(|mut connection| {
+            connection.send(&welcome)?;
+            Ok(connection)
+        }) {
+            Ok(connection) => {
+                info!("{address} joined the game as {}", nation.as_str());
+                clients.push((nation, connection));
+                // The entry is sent to the new player too, but it is already among the players.
+                new_entries.push(StreamEntry::Joined(nation));
+                session.players.push(nation);
+            }
+            Err(error) => warn!("Can't welcome {address}: {error}"),
+        }
+    }
+
+    for command in player_command.read() {
+        new_entries.push(StreamEntry::Command {
+            nation: player_civilization.0,
+            command: command.clone(),
+        });
+    }
+
+    let mut disconnected = Vec::new();
+    for (nation, connection) in clients.iter_mut() {
+        match connection.receive::<ClientMessage>() {
+            Ok(messages) => {
+                for message in messages {
+                    match message {
+                        ClientMessage::Command(command) => {
+                            new_entries.push(StreamEntry::Command {
+                                nation: *nation,
+                                command,
+                            });
+                        }
+                        ClientMessage::StateHash { turn, hash } => {
+                            reported_hashes.push((*nation, turn, hash));
+                        }
+                    }
+                }
+            }
+            Err(error) => {
+                warn!("{} left the game: {error}", nation.as_str());
+                disconnected.push(*nation);
+            }
+        }
+    }
+    for nation in disconnected {
+        clients.retain(|(client, _)| *client != nation);
+        new_entries.push(StreamEntry::Left(nation));
+    }
+
+    for entry in &new_entries {
+        let message = HostMessage::Entry(entry.clone());
+        // A client failing here is found out and removed the next time it is read.
+        for (_, connection) in clients.iter_mut() {
+            let _ = connection.send(&message);
+        }
+    }
+
+    reported_hashes.retain(|&(nation, turn, hash)| {
+        let Some(&host_hash) = session.hashes.get(&turn) else {
+            return true;
+        };
+        if hash != host_hash {
+            error!("{} is out of sync since turn {turn}", nation.as_str());
+            desynced.write(Desynced { turn });
+            for (_, connection) in clients.iter_mut() {
+                let _ = connection.send(&HostMessage::Desync { turn });
+            }
+        }
+        false
+    });
+
+    session.queue.extend(new_entries);
+    session.carry_out_entries(&mut execute_command, &mut end_turn);
+}
+
+/// Send the commands of the player to the host, and carry out the stream received from the host.
+fn run_client(
+    mut commands: Commands,
+    mut player_command: MessageReader<PlayerCommand>,
+    mut execute_command: MessageWriter<ExecuteCommand>,
+    mut end_turn: MessageWriter<EndTurn>,
+    mut desynced: MessageWriter<Desynced>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut session: ResMut<NetworkSession>,
+    mut sent_hashes: Local<HashSet<u32>>,
+) {
+    let session = &mut *session;
+    let Role::Client {
+        connection,
+        inbox,
+        welcomed,
+    } = &mut session.role
+    else {
+        return;
+    };
+
+    let mut result = player_command.read().try_for_each(|command| {
+        // The commands are carried out when the host sends them back in the stream.
+        connection.send(&ClientMessage::Command(command.clone()))
+    });
+    for (&turn, &hash) in &session.hashes {
+        if result.is_ok() && sent_hashes.insert(turn) {
+            result = connection.send(&ClientMessage::StateHash { turn, hash });
+        }
+    }
+    if let Err(error) = result.and_then(|()| {
+        inbox.extend(connection.receive::<HostMessage>()?);
+        Ok(())
+    }) {
+        error!("Lost the connection to the host: {error}");
+        commands.remove_resource::<NetworkSession>();
+        return;
+    }
+
+    while let Some(message) = inbox.pop_front() {
+        match message {
+            HostMessage::Welcome {
+                nation,
+                players,
+                mut game,
+            } => {
+                info!("Playing {} in the network game", nation.as_str());
+                game.player_civilization = nation;
+                session.players = players;
+                *welcomed = true;
+                // The hashes of the local game mean nothing in the game of the host.
+                session.hashes.clear();
+                sent_hashes.clear();
+                load_save_file(&mut commands, &mut next_state, *game);
+                // The rest of the stream is carried out once the game of the host is loaded.
+                return;
+            }
+            HostMessage::Entry(entry) => session.queue.push_back(entry),
+            HostMessage::Desync { turn } => {
+                error!("The game is out of sync since turn {turn}");
+                desynced.write(Desynced { turn });
+            }
+        }
+    }
+
+    if *welcomed {
+        session.carry_out_entries(&mut execute_command, &mut end_turn);
+    }
+}
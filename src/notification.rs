@@ -0,0 +1,607 @@
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::AppState,
+    barbarian::EncampmentCleared,
+    city::{BordersExpanded, City, CityCaptured},
+    city_state::{CityStateAllyChanged, CityStates, CompleteQuest, QuestGoal, QuestIssued},
+    civilization::Civilizations,
+    combat::{CityAttacked, CityStruck, UnitCaptured},
+    deal::{DealAccepted, DealProposed, ResearchAgreementEnded},
+    diplomacy::{Denounced, PeaceMade, WarDeclared},
+    economy::UnitDisbanded,
+    era::EraChanged,
+    espionage::{ElectionRigged, SpyDetected, SpyRecruited, TechStolen},
+    game_event::{CityFounded, CombatResolved, TechResearched},
+    golden_age::{GoldenAgeEnded, GoldenAgeStarted},
+    great_person::GreatPersonBorn,
+    pillage::TilePillaged,
+    policy::PolicyAdopted,
+    random_event::{EventFired, RandomEvents},
+    scenario::ScenarioEnded,
+    turn::TurnManager,
+    wonder::{WonderBuilt, WonderLost},
+};
+
+/// Something that happened to a nation, shown to its player.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Notification {
+    /// The turn the notification was sent.
+    pub turn: u32,
+    /// The nation the notification is sent to.
+    pub nation: Nation,
+    pub text: String,
+    /// The tile the camera jumps to when the notification is clicked, `None` if it isn't about a place.
+    pub location: Option<Tile>,
+}
+
+/// Every notification sent since the start of the game, from the oldest to the newest.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct Notifications(pub Vec<Notification>);
+
+impl Notifications {
+    /// The notifications sent to `nation`, from the oldest to the newest.
+    pub fn for_nation(&self, nation: Nation) -> impl Iterator<Item = &Notification> {
+        self.0
+            .iter()
+            .filter(move |notification| notification.nation == nation)
+    }
+
+    fn push(&mut self, turn: u32, nation: Nation, text: String, location: Option<Tile>) {
+        self.0.push(Notification {
+            turn,
+            nation,
+            text,
+            location,
+        });
+    }
+}
+
+pub struct NotificationPlugin;
+
+impl Plugin for NotificationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Notifications>().add_systems(
+            Update,
+            (
+                notify_cities,
+                notify_progress,
+                notify_civilization_events,
+                notify_combat,
+                notify_diplomacy,
+                notify_espionage,
+            )
+                .run_if(in_state(AppState::GameStart)),
+        );
+    }
+}
+
+fn notify_cities(
+    mut city_founded: MessageReader<CityFounded>,
+    mut borders_expanded: MessageReader<BordersExpanded>,
+    mut city_captured: MessageReader<CityCaptured>,
+    mut wonder_built: MessageReader<WonderBuilt>,
+    mut wonder_lost: MessageReader<WonderLost>,
+    civilizations: Res<Civilizations>,
+    turn_manager: Res<TurnManager>,
+    mut notifications: ResMut<Notifications>,
+    query_city: Query<&City>,
+) {
+    let turn = turn_manager.turn;
+
+    for CityFounded {
+        nation, name, tile, ..
+    } in city_founded.read()
+    {
+        notifications.push(
+            turn,
+            *nation,
+            format!("{name} has been founded."),
+            Some(*tile),
+        );
+    }
+    for &BordersExpanded { city, nation, tile } in borders_expanded.read() {
+        let Ok(city) = query_city.get(city) else {
+            continue;
+        };
+        notifications.push(
+            turn,
+            nation,
+            format!("The borders of {} have expanded.", city.name),
+            Some(tile),
+        );
+    }
+    for CityCaptured {
+        nation,
+        previous_nation,
+        name,
+        tile,
+        ..
+    } in city_captured.read()
+    {
+        let location = Some(*tile);
+        notifications.push(
+            turn,
+            *nation,
+            format!("You captured {name}. It is now a puppet."),
+            location,
+        );
+        notifications.push(
+            turn,
+            *previous_nation,
+            format!("{name} was captured by {}.", nation.as_str()),
+            location,
+        );
+    }
+    for WonderBuilt {
+        nation,
+        wonder,
+        city_name,
+        tile,
+        is_world_wonder,
+    } in wonder_built.read()
+    {
+        notifications.push(
+            turn,
+            *nation,
+            format!("{wonder} has been completed in {city_name}."),
+            Some(*tile),
+        );
+        // Every nation hears of a world wonder.
+        if *is_world_wonder {
+            let mut others: Vec<Nation> = civilizations
+                .iter()
+                .map(|(other, _)| other)
+                .filter(|other| other != nation)
+                .collect();
+            others.sort_by_key(|other| other.as_str());
+            for other in others {
+                notifications.push(
+                    turn,
+                    other,
+                    format!("{} has completed {wonder}.", nation.as_str()),
+                    None,
+                );
+            }
+        }
+    }
+    for WonderLost {
+        nation,
+        wonder,
+        city_name,
+        gold,
+    } in wonder_lost.read()
+    {
+        notifications.push(
+            turn,
+            *nation,
+            format!(
+                "{city_name} lost the race for {wonder}. Its production has been refunded as {gold:.0} gold."
+            ),
+            None,
+        );
+    }
+}
+
+fn notify_progress(
+    mut tech_researched: MessageReader<TechResearched>,
+    mut era_changed: MessageReader<EraChanged>,
+    mut policy_adopted: MessageReader<PolicyAdopted>,
+    turn_manager: Res<TurnManager>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let turn = turn_manager.turn;
+
+    for TechResearched { nation, technology } in tech_researched.read() {
+        notifications.push(
+            turn,
+            *nation,
+            format!("{technology} has been researched."),
+            None,
+        );
+    }
+    for EraChanged { nation, era } in era_changed.read() {
+        notifications.push(turn, *nation, format!("You have entered the {era}."), None);
+    }
+    for PolicyAdopted { nation, policy } in policy_adopted.read() {
+        notifications.push(turn, *nation, format!("{policy} has been adopted."), None);
+    }
+}
+
+fn notify_civilization_events(
+    mut great_person_born: MessageReader<GreatPersonBorn>,
+    mut golden_age_started: MessageReader<GoldenAgeStarted>,
+    mut golden_age_ended: MessageReader<GoldenAgeEnded>,
+    mut unit_disbanded: MessageReader<UnitDisbanded>,
+    mut event_fired: MessageReader<EventFired>,
+    mut scenario_ended: MessageReader<ScenarioEnded>,
+    random_events: Res<RandomEvents>,
+    civilizations: Res<Civilizations>,
+    turn_manager: Res<TurnManager>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let turn = turn_manager.turn;
+
+    for GreatPersonBorn { nation, unit_name } in great_person_born.read() {
+        notifications.push(turn, *nation, format!("A {unit_name} has been born."), None);
+    }
+    for &GoldenAgeStarted { nation } in golden_age_started.read() {
+        notifications.push(turn, nation, "A golden age has started.".to_owned(), None);
+    }
+    for &GoldenAgeEnded { nation } in golden_age_ended.read() {
+        notifications.push(turn, nation, "The golden age has ended.".to_owned(), None);
+    }
+    for UnitDisbanded { nation, unit_name } in unit_disbanded.read() {
+        notifications.push(
+            turn,
+            *nation,
+            format!("A {unit_name} has been disbanded because the treasury is empty."),
+            None,
+        );
+    }
+    for EventFired { nation, event } in event_fired.read() {
+        if let Some(event) = random_events.get(event) {
+            notifications.push(turn, *nation, event.text.clone(), None);
+        }
+    }
+    for ScenarioEnded { scenario, winner } in scenario_ended.read() {
+        let text = match winner {
+            Some(winner) => format!("{} has won the scenario {scenario}.", winner.as_str()),
+            None => format!("The scenario {scenario} is over without a winner."),
+        };
+        let mut nations: Vec<_> = civilizations.iter().map(|(nation, _)| nation).collect();
+        nations.sort_by_key(|nation| nation.as_str());
+        for nation in nations {
+            notifications.push(turn, nation, text.clone(), None);
+        }
+    }
+}
+
+/// Notify both sides of every fight, every bombardment of a city, every strike of a city and every pillaged tile.
+fn notify_combat(
+    mut combat_resolved: MessageReader<CombatResolved>,
+    mut city_attacked: MessageReader<CityAttacked>,
+    mut city_struck: MessageReader<CityStruck>,
+    mut tile_pillaged: MessageReader<TilePillaged>,
+    mut unit_captured: MessageReader<UnitCaptured>,
+    turn_manager: Res<TurnManager>,
+    mut notifications: ResMut<Notifications>,
+    query_city: Query<&City>,
+) {
+    let turn = turn_manager.turn;
+
+    for combat in combat_resolved.read() {
+        let location = Some(combat.defender_tile);
+        let attacker = combat.attacker_nation.as_str();
+        let defender = combat.defender_nation.as_str();
+
+        let attacker_text = if combat.defender_killed {
+            format!("Your unit destroyed a unit of {defender}.")
+        } else if combat.attacker_killed {
+            format!("Your unit was destroyed attacking a unit of {defender}.")
+        } else {
+            format!("Your unit attacked a unit of {defender}.")
+        };
+        let defender_text = if combat.defender_killed {
+            format!("Your unit was destroyed by a unit of {attacker}.")
+        } else {
+            format!("Your unit was attacked by a unit of {attacker}.")
+        };
+        notifications.push(turn, combat.attacker_nation, attacker_text, location);
+        notifications.push(turn, combat.defender_nation, defender_text, location);
+    }
+
+    for capture in unit_captured.read() {
+        let location = Some(capture.tile);
+        let name = &capture.unit_name;
+        let (nation_text, victim_text) = if capture.destroyed {
+            (
+                format!(
+                    "Your unit destroyed a {name} of {}.",
+                    capture.previous_nation.as_str()
+                ),
+                format!("Your {name} was destroyed by {}.", capture.nation.as_str()),
+            )
+        } else {
+            (
+                format!(
+                    "Your unit captured a {name} of {}.",
+                    capture.previous_nation.as_str()
+                ),
+                format!("Your {name} was captured by {}.", capture.nation.as_str()),
+            )
+        };
+        notifications.push(turn, capture.nation, nation_text, location);
+        notifications.push(turn, capture.previous_nation, victim_text, location);
+    }
+
+    for attack in city_attacked.read() {
+        let Ok(city) = query_city.get(attack.city) else {
+            continue;
+        };
+        let location = Some(attack.city_tile);
+        let name = &city.name;
+        let damage = attack.damage_to_city;
+        let verb = if attack.is_ranged {
+            "bombarded"
+        } else {
+            "attacked"
+        };
+
+        let attacker_text = if attack.attacker_killed {
+            format!("Your unit was destroyed attacking {name}.")
+        } else {
+            format!("Your unit {verb} {name} for {damage} damage.")
+        };
+        let city_text = format!(
+            "{name} was {verb} by a unit of {} for {damage} damage.",
+            attack.attacker_nation.as_str()
+        );
+        notifications.push(turn, attack.attacker_nation, attacker_text, location);
+        notifications.push(turn, attack.city_nation, city_text, location);
+    }
+
+    for strike in city_struck.read() {
+        let Ok(city) = query_city.get(strike.city) else {
+            continue;
+        };
+        let location = Some(strike.target_tile);
+        let name = &city.name;
+        let damage = strike.damage_to_target;
+
+        let city_text = if strike.target_killed {
+            format!(
+                "{name} destroyed a unit of {}.",
+                strike.target_nation.as_str()
+            )
+        } else {
+            format!(
+                "{name} struck a unit of {} for {damage} damage.",
+                strike.target_nation.as_str()
+            )
+        };
+        let target_text = if strike.target_killed {
+            format!("Your unit was destroyed by {name}.")
+        } else {
+            format!("Your unit was struck by {name} for {damage} damage.")
+        };
+        notifications.push(turn, strike.city_nation, city_text, location);
+        notifications.push(turn, strike.target_nation, target_text, location);
+    }
+
+    for pillage in tile_pillaged.read() {
+        let location = Some(pillage.tile);
+        notifications.push(
+            turn,
+            pillage.nation,
+            format!(
+                "Your unit pillaged a tile of {} for {} gold.",
+                pillage.victim.as_str(),
+                pillage.gold
+            ),
+            location,
+        );
+        notifications.push(
+            turn,
+            pillage.victim,
+            format!(
+                "A unit of {} pillaged one of your tiles.",
+                pillage.nation.as_str()
+            ),
+            location,
+        );
+    }
+}
+
+fn notify_diplomacy(
+    mut war_declared: MessageReader<WarDeclared>,
+    mut peace_made: MessageReader<PeaceMade>,
+    mut denounced: MessageReader<Denounced>,
+    mut city_state_ally_changed: MessageReader<CityStateAllyChanged>,
+    mut encampment_cleared: MessageReader<EncampmentCleared>,
+    mut deal_proposed: MessageReader<DealProposed>,
+    mut deal_accepted: MessageReader<DealAccepted>,
+    mut research_agreement_ended: MessageReader<ResearchAgreementEnded>,
+    (mut quest_issued, mut complete_quest): (
+        MessageReader<QuestIssued>,
+        MessageReader<CompleteQuest>,
+    ),
+    city_states: Res<CityStates>,
+    turn_manager: Res<TurnManager>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let turn = turn_manager.turn;
+
+    for &WarDeclared { nation, target } in war_declared.read() {
+        let text = format!(
+            "{} has declared war on {}.",
+            nation.as_str(),
+            target.as_str()
+        );
+        notifications.push(turn, nation, text.clone(), None);
+        notifications.push(turn, target, text, None);
+    }
+    for &PeaceMade { nation, target } in peace_made.read() {
+        let text = format!(
+            "{} has made peace with {}.",
+            nation.as_str(),
+            target.as_str()
+        );
+        notifications.push(turn, nation, text.clone(), None);
+        notifications.push(turn, target, text, None);
+    }
+    for &Denounced { nation, target } in denounced.read() {
+        let text = format!("{} has denounced {}.", nation.as_str(), target.as_str());
+        notifications.push(turn, nation, text.clone(), None);
+        notifications.push(turn, target, text, None);
+    }
+    for &CityStateAllyChanged { city_state, ally } in city_state_ally_changed.read() {
+        if let Some(ally) = ally {
+            notifications.push(
+                turn,
+                ally,
+                format!("{} is now your ally.", city_state.as_str()),
+                None,
+            );
+        }
+    }
+    for &EncampmentCleared { nation, tile, gold } in encampment_cleared.read() {
+        notifications.push(
+            turn,
+            nation,
+            format!("A barbarian encampment has been cleared, giving {gold} gold."),
+            Some(tile),
+        );
+    }
+    for DealProposed { deal } in deal_proposed.read() {
+        notifications.push(
+            turn,
+            deal.to,
+            format!("{} has proposed a deal.", deal.from.as_str()),
+            None,
+        );
+    }
+    for DealAccepted { deal } in deal_accepted.read() {
+        let text = format!(
+            "{} has accepted the deal with {}.",
+            deal.to.as_str(),
+            deal.from.as_str()
+        );
+        notifications.push(turn, deal.from, text.clone(), None);
+        notifications.push(turn, deal.to, text, None);
+    }
+    for &ResearchAgreementEnded {
+        nation,
+        partner,
+        science,
+    } in research_agreement_ended.read()
+    {
+        notifications.push(
+            turn,
+            nation,
+            format!(
+                "The research agreement with {} has ended, giving {science:.0} science.",
+                partner.as_str()
+            ),
+            None,
+        );
+    }
+    for QuestIssued {
+        city_state,
+        goal,
+        expires_on_turn,
+    } in quest_issued.read()
+    {
+        let (task, location) = match goal {
+            QuestGoal::ClearBarbarianCamp(tile) => (
+                "clear a nearby barbarian encampment".to_owned(),
+                Some(*tile),
+            ),
+            QuestGoal::ConnectResource(resource) => (format!("connect {resource}"), None),
+            QuestGoal::ConstructWonder(wonder) => (format!("build {wonder}"), None),
+        };
+        let Some(state) = city_states.0.get(city_state) else {
+            continue;
+        };
+        let mut nations: Vec<Nation> = state.influence.keys().copied().collect();
+        nations.sort_by_key(|nation| nation.as_str());
+        for nation in nations {
+            notifications.push(
+                turn,
+                nation,
+                format!(
+                    "{} asks you to {task} before turn {expires_on_turn}.",
+                    city_state.as_str()
+                ),
+                location,
+            );
+        }
+    }
+    for CompleteQuest {
+        nation,
+        city_state,
+        quest,
+    } in complete_quest.read()
+    {
+        notifications.push(
+            turn,
+            *nation,
+            format!(
+                "You completed the quest {quest} of {}.",
+                city_state.as_str()
+            ),
+            None,
+        );
+    }
+}
+
+fn notify_espionage(
+    mut spy_recruited: MessageReader<SpyRecruited>,
+    mut tech_stolen: MessageReader<TechStolen>,
+    mut spy_detected: MessageReader<SpyDetected>,
+    mut election_rigged: MessageReader<ElectionRigged>,
+    turn_manager: Res<TurnManager>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let turn = turn_manager.turn;
+
+    for &SpyRecruited { nation } in spy_recruited.read() {
+        notifications.push(
+            turn,
+            nation,
+            "A new spy has been recruited.".to_owned(),
+            None,
+        );
+    }
+    for TechStolen {
+        nation,
+        victim,
+        technology,
+    } in tech_stolen.read()
+    {
+        notifications.push(
+            turn,
+            *nation,
+            format!("Your spy stole {technology} from {}.", victim.as_str()),
+            None,
+        );
+    }
+    for &SpyDetected {
+        nation,
+        victim,
+        killed,
+    } in spy_detected.read()
+    {
+        let (spy_text, victim_text) = if killed {
+            (
+                format!("Your spy has been killed by {}.", victim.as_str()),
+                format!("A spy of {} has been caught and killed.", nation.as_str()),
+            )
+        } else {
+            (
+                format!("Your spy has been identified by {}.", victim.as_str()),
+                format!("A spy of {} stole a technology from you.", nation.as_str()),
+            )
+        };
+        notifications.push(turn, nation, spy_text, None);
+        notifications.push(turn, victim, victim_text, None);
+    }
+    for &ElectionRigged {
+        nation,
+        city_state,
+        influence,
+    } in election_rigged.read()
+    {
+        notifications.push(
+            turn,
+            nation,
+            format!(
+                "Your spy rigged the elections of {}, giving {influence} influence.",
+                city_state.as_str()
+            ),
+            None,
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! The chunks of the overlays drawn over the map, e.g. the yield overlay and the map lenses.
+//!
+//! The map is split into chunks of [`CHUNK_SIZE`] x [`CHUNK_SIZE`] tiles, and the overlay of a chunk is batched
+//! into a single mesh with vertex colors, rebuilt only when what it shows changes.
+
+use bevy::prelude::*;
+use civ_map_generator::{
+    grid::{Grid, hex_grid::HexGrid, offset_coordinate::OffsetCoordinate},
+    tile::Tile,
+};
+
+use crate::{MainCamera, TileMapResource, assets::AppState, world_map::show_main_camera_area};
+
+/// The width and the height of a chunk, in tiles.
+pub const CHUNK_SIZE: i32 = 8;
+
+/// A chunk of an overlay, the tiles from `origin` to `origin + size` in offset coordinates.
+#[derive(Component)]
+pub struct OverlayChunk {
+    pub origin: [i32; 2],
+    pub size: [i32; 2],
+}
+
+impl OverlayChunk {
+    /// The chunks covering the map.
+    pub fn all(grid: HexGrid) -> impl Iterator<Item = Self> {
+        let (width, height) = (grid.width() as i32, grid.height() as i32);
+        (0..height)
+            .step_by(CHUNK_SIZE as usize)
+            .flat_map(move |origin_y| {
+                (0..width)
+                    .step_by(CHUNK_SIZE as usize)
+                    .map(move |origin_x| Self {
+                        origin: [origin_x, origin_y],
+                        size: [
+                            CHUNK_SIZE.min(width - origin_x),
+                            CHUNK_SIZE.min(height - origin_y),
+                        ],
+                    })
+            })
+    }
+
+    /// The tiles of the chunk, with their centers relative to the origin of the chunk.
+    pub fn tiles(&self, grid: HexGrid) -> impl Iterator<Item = (Vec2, Tile)> + '_ {
+        let [origin_x, origin_y] = self.origin;
+        let [width, height] = self.size;
+        let origin_position =
+            Vec2::from(grid.offset_to_pixel(OffsetCoordinate::new(origin_x, origin_y)));
+        (origin_y..origin_y + height).flat_map(move |y| {
+            (origin_x..origin_x + width).map(move |x| {
+                let offset_coordinate = OffsetCoordinate::new(x, y);
+                let center = Vec2::from(grid.offset_to_pixel(offset_coordinate));
+                (
+                    center - origin_position,
+                    Tile::from_offset(offset_coordinate, grid),
+                )
+            })
+        })
+    }
+}
+
+pub struct OverlayChunkPlugin;
+
+impl Plugin for OverlayChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            position_overlay_chunks
+                .after(show_main_camera_area)
+                .run_if(in_state(AppState::GameStart).and(resource_exists::<TileMapResource>)),
+        );
+    }
+}
+
+/// Move every chunk to the copy of its tiles closest to the camera, like the tiles on a wrapping map,
+/// see [`show_main_camera_area`]. The chunks keep their height, which sets the order of the overlays.
+fn position_overlay_chunks(
+    map: Res<TileMapResource>,
+    camera: Single<&Transform, With<MainCamera>>,
+    mut query_chunk: Query<(&OverlayChunk, &mut Transform), Without<MainCamera>>,
+) {
+    let grid = map.0.world_grid.grid;
+    let camera_position = camera.translation.truncate().to_array();
+    let [camera_x, _] = grid.pixel_to_offset(camera_position).to_array();
+    let width = grid.width() as i32;
+
+    for (chunk, mut transform) in query_chunk.iter_mut() {
+        let [mut x, y] = chunk.origin;
+        if grid.wrap_x() {
+            let chunk_center_x = x + chunk.size[0] / 2;
+            x += ((camera_x - chunk_center_x) as f32 / width as f32).round() as i32 * width;
+        }
+        let [pixel_x, pixel_y] = grid.offset_to_pixel(OffsetCoordinate::new(x, y));
+        transform.translation.x = pixel_x;
+        transform.translation.y = pixel_y;
+    }
+}
@@ -0,0 +1,580 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use bevy::prelude::*;
+use civ_map_generator::{
+    grid::{Grid, hex_grid::HexGrid},
+    nation::Nation,
+    ruleset::Ruleset,
+    tile::Tile,
+    tile_component::BaseTerrain,
+    tile_map::TileMap,
+};
+
+use crate::{
+    civilization::Civilization,
+    diplomacy::DiplomacyState,
+    embarkation::Embarkation,
+    unit::{UnitDomain, tile_movement_cost},
+};
+
+/// The unique of the units which move freely through zones of control.
+const IGNORES_ZONE_OF_CONTROL_UNIQUE: &str = "Ignores Zone of Control";
+/// The unique of the early ships which must stay on coast and lake tiles.
+const NO_OCEAN_UNIQUE: &str = "Cannot enter ocean tiles";
+/// The start of the unique `Cannot enter ocean tiles <before discovering [Technology]>`.
+const NO_OCEAN_BEFORE_PREFIX: &str = "Cannot enter ocean tiles <before discovering [";
+
+/// Return `true` if the ship `unit_name` of the civilization can enter ocean tiles.
+///
+/// Ships with `Cannot enter ocean tiles` never can, and the ones with the `<before discovering [Technology]>`
+/// condition can once their civilization knows the technology.
+pub fn can_enter_ocean(unit_name: &str, civilization: &Civilization, ruleset: &Ruleset) -> bool {
+    ruleset.units[unit_name].uniques.iter().all(|unique| {
+        if unique == NO_OCEAN_UNIQUE {
+            return false;
+        }
+        unique
+            .strip_prefix(NO_OCEAN_BEFORE_PREFIX)
+            .and_then(|rest| rest.strip_suffix("]>"))
+            .is_none_or(|technology| civilization.has_technology(technology))
+    })
+}
+
+/// Whether the zone of control rules of the ruleset apply. The Civ V rulesets have them, so they are on by default.
+#[derive(Resource, Clone, Copy)]
+pub struct ZoneOfControlRule(pub bool);
+
+impl Default for ZoneOfControlRule {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// The tiles where the enemies of a unit exert their zone of control: the neighbors of their military units.
+///
+/// Moving from one of these tiles to another one ends the movement of the unit for the turn,
+/// see [`ZoneOfControl::ends_movement`].
+#[derive(Default)]
+pub struct ZoneOfControl(HashSet<Tile>);
+
+impl ZoneOfControl {
+    /// The zone of control of the enemies of `nation` for the unit `unit_name`.
+    ///
+    /// `military_units` are the tiles and nations of every military unit. The zone is empty if the rule is off
+    /// or if the unit ignores zones of control.
+    pub fn of_enemies(
+        nation: Nation,
+        unit_name: &str,
+        military_units: &[(Tile, Nation)],
+        rule: ZoneOfControlRule,
+        diplomacy: &DiplomacyState,
+        tile_map: &TileMap,
+        ruleset: &Ruleset,
+    ) -> Self {
+        let ignores_zone_of_control = ruleset.units[unit_name]
+            .uniques
+            .iter()
+            .any(|unique| unique == IGNORES_ZONE_OF_CONTROL_UNIQUE);
+        if !rule.0 || ignores_zone_of_control {
+            return Self::default();
+        }
+
+        let grid = tile_map.world_grid.grid;
+        let mut tiles = HashSet::new();
+        for &(tile, owner) in military_units {
+            if diplomacy.is_at_war(nation, owner) {
+                tiles.extend(tile.neighbor_tiles(grid));
+            }
+        }
+        Self(tiles)
+    }
+
+    pub fn ends_movement(&self, from: Tile, to: Tile) -> bool {
+        self.0.contains(&from) && self.0.contains(&to)
+    }
+}
+
+/// Everything the pathfinder needs to know about the moving unit and the map.
+pub struct MovementRules<'a> {
+    pub tile_map: &'a TileMap,
+    pub ruleset: &'a Ruleset,
+    pub domain: UnitDomain,
+    /// The movement points of the unit at the start of a turn.
+    pub max_movement: f32,
+    /// How a land unit can embark, `None` if it can't enter water tiles.
+    pub embarkation: Option<Embarkation>,
+    /// `false` for the ships which must stay on coast and lake tiles, see [`can_enter_ocean`].
+    pub ocean: bool,
+    /// Zone of control hook, see [`ZoneOfControl::ends_movement`]. Returns `true` if moving from the first tile
+    /// to the second one ends the unit's movement for this turn.
+    pub ends_movement: Option<&'a dyn Fn(Tile, Tile) -> bool>,
+}
+
+impl<'a> MovementRules<'a> {
+    /// The rules for the unit `unit_name` of the civilization, without zone of control.
+    pub fn new(
+        unit_name: &str,
+        civilization: &Civilization,
+        max_movement: f32,
+        tile_map: &'a TileMap,
+        ruleset: &'a Ruleset,
+    ) -> Self {
+        Self {
+            tile_map,
+            ruleset,
+            domain: UnitDomain::of_unit(unit_name, ruleset),
+            max_movement,
+            embarkation: Embarkation::of_civilization(civilization, ruleset),
+            ocean: can_enter_ocean(unit_name, civilization, ruleset),
+            ends_movement: None,
+        }
+    }
+
+    /// The movement cost of moving from `from` to its neighbor `to`, or `None` if the move is not allowed.
+    ///
+    /// Crossing a river, entering an enemy zone of control, embarking and disembarking use up all the movement points
+    /// of a turn. Embarked units have their own movement points, so a water tile costs the part of a turn
+    /// it takes them to cross it.
+    pub fn step_cost(&self, from: Tile, to: Tile) -> Option<f32> {
+        let tile_map = self.tile_map;
+        let is_land_unit = self.domain == UnitDomain::Land;
+
+        let cost = if is_land_unit && to.is_water(tile_map) {
+            let embarkation = self
+                .embarkation
+                .filter(|embarkation| embarkation.can_enter(to, tile_map))?;
+            tile_movement_cost(to, UnitDomain::Water, tile_map, self.ruleset)?
+                * (self.max_movement / embarkation.movement)
+        } else if !self.ocean && to.base_terrain(tile_map) == BaseTerrain::Ocean {
+            return None;
+        } else {
+            tile_movement_cost(to, self.domain, tile_map, self.ruleset)?
+        };
+
+        let ends_movement = self.ends_movement.is_some_and(|ends| ends(from, to));
+        let changes_embarkation = is_land_unit && from.is_water(tile_map) != to.is_water(tile_map);
+
+        if ends_movement || changes_embarkation || crosses_river(from, to, tile_map) {
+            Some(cost.max(self.max_movement))
+        } else {
+            Some(cost)
+        }
+    }
+
+    /// The lowest cost a step may have, see [`MovementRules::step_cost`]. Every tile costs at least 1,
+    /// but a water tile costs less to embarked units with more movement points than the unit.
+    pub fn min_step_cost(&self) -> f32 {
+        match self.embarkation {
+            Some(embarkation) if self.domain == UnitDomain::Land => {
+                (self.max_movement / embarkation.movement).min(1.)
+            }
+            _ => 1.,
+        }
+    }
+}
+
+/// Return `true` if there is a river on the edge between the neighboring tiles `from` and `to`.
+pub fn crosses_river(from: Tile, to: Tile, tile_map: &TileMap) -> bool {
+    let grid = tile_map.world_grid.grid;
+    grid.edge_direction_array().iter().any(|&direction| {
+        from.neighbor_tile(direction, grid) == Some(to)
+            && from.has_river_in_direction(direction, tile_map)
+    })
+}
+
+/// A path found by [`find_path`].
+#[derive(Clone, Debug, Default)]
+pub struct Path {
+    /// The tiles to move through, excluding the start tile. The last tile is the destination.
+    pub tiles: Vec<Tile>,
+    /// The total movement cost of the path.
+    pub cost: f32,
+}
+
+/// The turn on which a unit following `path` from `from` reaches every tile of the path, 0 being the current turn.
+///
+/// The unit moves like the queued moves: it may enter a tile as long as it has movement points left,
+/// and gets `rules.max_movement` points back at the start of every turn.
+pub fn turns_to_reach(
+    path: &Path,
+    from: Tile,
+    movement_left: f32,
+    rules: &MovementRules,
+) -> Vec<u32> {
+    let mut turn = 0;
+    let mut movement = movement_left;
+    let mut tile = from;
+    path.tiles
+        .iter()
+        .map(|&next_tile| {
+            if movement <= 0. {
+                turn += 1;
+                movement = rules.max_movement;
+            }
+            let cost = rules
+                .step_cost(tile, next_tile)
+                .unwrap_or(rules.max_movement);
+            movement = (movement - cost).max(0.);
+            tile = next_tile;
+            turn
+        })
+        .collect()
+}
+
+/// Find the cheapest path from `from` to `to` with the A* algorithm.
+///
+/// Returns `None` if `to` can't be reached. Map wrapping is handled by [`Tile::neighbor_tiles`].
+pub fn find_path(from: Tile, to: Tile, rules: &MovementRules) -> Option<Path> {
+    let grid = rules.tile_map.world_grid.grid;
+    let min_step_cost = rules.min_step_cost();
+
+    if from == to {
+        return Some(Path::default());
+    }
+    cheapest_path(
+        from,
+        rules,
+        |tile| tile == to,
+        |tile| heuristic(tile, to, grid) * min_step_cost,
+    )
+}
+
+/// Find the cheapest path from `from` to the closest tile for which `is_target` returns `true`,
+/// with the Dijkstra algorithm. `from` itself is never a target.
+///
+/// Returns `None` if no such tile can be reached.
+pub fn find_path_to_closest(
+    from: Tile,
+    rules: &MovementRules,
+    is_target: impl Fn(Tile) -> bool,
+) -> Option<Path> {
+    cheapest_path(from, rules, |tile| tile != from && is_target(tile), |_| 0.)
+}
+
+/// The A* search shared by [`find_path`] and [`find_path_to_closest`]: the cheapest path from `from`
+/// to the first tile `is_goal` accepts, with `estimate` never overestimating the cost left from a tile.
+fn cheapest_path(
+    from: Tile,
+    rules: &MovementRules,
+    is_goal: impl Fn(Tile) -> bool,
+    estimate: impl Fn(Tile) -> f32,
+) -> Option<Path> {
+    let grid = rules.tile_map.world_grid.grid;
+
+    let mut open_set = BinaryHeap::new();
+    let mut came_from: HashMap<Tile, Tile> = HashMap::new();
+    let mut cost_so_far: HashMap<Tile, f32> = HashMap::new();
+
+    cost_so_far.insert(from, 0.);
+    open_set.push(Node {
+        tile: from,
+        cost: 0.,
+        estimated_total_cost: estimate(from),
+    });
+
+    while let Some(Node { tile, cost, .. }) = open_set.pop() {
+        // Skip outdated entries, a cheaper way to this tile has been found after it was pushed.
+        if cost > cost_so_far[&tile] {
+            continue;
+        }
+
+        if is_goal(tile) {
+            let mut tiles = vec![tile];
+            let mut current = tile;
+            while let Some(&previous) = came_from.get(&current) {
+                if previous == from {
+                    break;
+                }
+                tiles.push(previous);
+                current = previous;
+            }
+            tiles.reverse();
+            return Some(Path { tiles, cost });
+        }
+
+        for neighbor in tile.neighbor_tiles(grid) {
+            let Some(step_cost) = rules.step_cost(tile, neighbor) else {
+                continue;
+            };
+            let new_cost = cost + step_cost;
+            if cost_so_far
+                .get(&neighbor)
+                .is_none_or(|&old_cost| new_cost < old_cost)
+            {
+                cost_so_far.insert(neighbor, new_cost);
+                came_from.insert(neighbor, tile);
+                open_set.push(Node {
+                    tile: neighbor,
+                    cost: new_cost,
+                    estimated_total_cost: new_cost + estimate(neighbor),
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// The number of steps from `from` to `to`. Scaled by [`MovementRules::min_step_cost`],
+/// it never overestimates the real cost.
+fn heuristic(from: Tile, to: Tile, grid: HexGrid) -> f32 {
+    from.distance_to(to, grid) as f32
+}
+
+struct Node {
+    tile: Tile,
+    cost: f32,
+    estimated_total_cost: f32,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    /// `BinaryHeap` is a max-heap, so the order is reversed to pop the lowest estimated cost first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimated_total_cost
+            .total_cmp(&self.estimated_total_cost)
+            .then_with(|| self.cost.total_cmp(&other.cost))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use civ_map_generator::{
+        grid::{
+            GridSize, WrapFlags,
+            hex_grid::{HexLayout, HexOrientation, Offset},
+            offset_coordinate::OffsetCoordinate,
+        },
+        map_parameters::{MapParametersBuilder, WorldGrid},
+        tile_component::TerrainType,
+    };
+
+    use super::*;
+
+    /// A map drawn row by row, the first row being `y = 0`: `.` is grassland, `h` a hill, `M` a mountain
+    /// and `~` coast. The rows are offset as in the game, every odd row being shifted to the right.
+    pub(crate) fn test_map(rows: &[&str]) -> TileMap {
+        let grid = HexGrid {
+            size: GridSize {
+                width: rows[0].len() as u32,
+                height: rows.len() as u32,
+            },
+            layout: HexLayout {
+                orientation: HexOrientation::Pointy,
+                size: [50., 50.],
+                origin: [0., 0.],
+            },
+            wrap_flags: WrapFlags::empty(),
+            offset: Offset::Odd,
+        };
+        let map_parameters = MapParametersBuilder::new(WorldGrid::from_grid(grid)).build();
+        let mut tile_map = TileMap::new(&map_parameters);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, symbol) in row.chars().enumerate() {
+                let (terrain_type, base_terrain) = match symbol {
+                    '.' => (TerrainType::Flatland, BaseTerrain::Grassland),
+                    'h' => (TerrainType::Hill, BaseTerrain::Grassland),
+                    'M' => (TerrainType::Mountain, BaseTerrain::Grassland),
+                    '~' => (TerrainType::Water, BaseTerrain::Coast),
+                    _ => panic!("Unknown tile {symbol}"),
+                };
+                let index = tile(&tile_map, x, y).index();
+                tile_map.terrain_type_list[index] = terrain_type;
+                tile_map.base_terrain_list[index] = base_terrain;
+            }
+        }
+        tile_map
+    }
+
+    pub(crate) fn tile(tile_map: &TileMap, x: usize, y: usize) -> Tile {
+        Tile::from_offset(
+            OffsetCoordinate::new(x as i32, y as i32),
+            tile_map.world_grid.grid,
+        )
+    }
+
+    /// The rules of a land unit with 2 movement points which can embark on coast tiles.
+    fn land_unit_rules<'a>(tile_map: &'a TileMap, ruleset: &'a Ruleset) -> MovementRules<'a> {
+        MovementRules {
+            tile_map,
+            ruleset,
+            domain: UnitDomain::Land,
+            max_movement: 2.,
+            embarkation: Some(Embarkation {
+                ocean: false,
+                movement: 2.,
+            }),
+            ocean: true,
+            ends_movement: None,
+        }
+    }
+
+    #[test]
+    fn finds_straight_path() {
+        let tile_map = test_map(&[".....", ".....", "....."]);
+        let ruleset = Ruleset::default();
+        let rules = land_unit_rules(&tile_map, &ruleset);
+
+        let path = find_path(tile(&tile_map, 0, 1), tile(&tile_map, 4, 1), &rules).unwrap();
+        let expected: Vec<_> = (1..=4).map(|x| tile(&tile_map, x, 1)).collect();
+        assert_eq!(path.tiles, expected);
+        assert_eq!(path.cost, 4.);
+    }
+
+    #[test]
+    fn goes_around_impassable_terrain() {
+        let tile_map = test_map(&[".....", ".MMM.", "....."]);
+        let ruleset = Ruleset::default();
+        let rules = land_unit_rules(&tile_map, &ruleset);
+
+        let path = find_path(tile(&tile_map, 0, 1), tile(&tile_map, 4, 1), &rules).unwrap();
+        assert_eq!(path.tiles.last(), Some(&tile(&tile_map, 4, 1)));
+        assert!(
+            path.tiles
+                .iter()
+                .all(|tile| tile.terrain_type(&tile_map) != TerrainType::Mountain)
+        );
+        assert_eq!(path.cost, 5.);
+    }
+
+    #[test]
+    fn avoids_moving_inside_zone_of_control() {
+        let tile_map = test_map(&[".....", ".....", "....."]);
+        let ruleset = Ruleset::default();
+        let zone_of_control = ZoneOfControl((1..=3).map(|x| tile(&tile_map, x, 1)).collect());
+        let ends_movement = |from, to| zone_of_control.ends_movement(from, to);
+        let rules = MovementRules {
+            ends_movement: Some(&ends_movement),
+            ..land_unit_rules(&tile_map, &ruleset)
+        };
+
+        // Going through the zone costs 1 + 2 + 2 + 1, going around it costs 5.
+        let path = find_path(tile(&tile_map, 0, 1), tile(&tile_map, 4, 1), &rules).unwrap();
+        assert_eq!(path.cost, 5.);
+        assert!(
+            path.tiles
+                .windows(2)
+                .all(|step| !zone_of_control.ends_movement(step[0], step[1]))
+        );
+    }
+
+    #[test]
+    fn goes_through_zone_of_control_without_another_way() {
+        let tile_map = test_map(&["MMMMM", ".....", "MMMMM"]);
+        let ruleset = Ruleset::default();
+        let zone_of_control = ZoneOfControl((1..=3).map(|x| tile(&tile_map, x, 1)).collect());
+        let ends_movement = |from, to| zone_of_control.ends_movement(from, to);
+        let rules = MovementRules {
+            ends_movement: Some(&ends_movement),
+            ..land_unit_rules(&tile_map, &ruleset)
+        };
+
+        let path = find_path(tile(&tile_map, 0, 1), tile(&tile_map, 4, 1), &rules).unwrap();
+        assert_eq!(path.tiles.len(), 4);
+        assert_eq!(path.cost, 6.);
+    }
+
+    #[test]
+    fn embarks_and_disembarks() {
+        let tile_map = test_map(&["MMMMMM", "..~~..", "MMMMMM"]);
+        let ruleset = Ruleset::default();
+        let rules = land_unit_rules(&tile_map, &ruleset);
+
+        // Embarking and disembarking use up all the movement points of a turn.
+        let path = find_path(tile(&tile_map, 0, 1), tile(&tile_map, 5, 1), &rules).unwrap();
+        let expected: Vec<_> = (1..=5).map(|x| tile(&tile_map, x, 1)).collect();
+        assert_eq!(path.tiles, expected);
+        assert_eq!(path.cost, 1. + 2. + 1. + 2. + 1.);
+    }
+
+    #[test]
+    fn returns_none_for_unreachable_targets() {
+        let tile_map = test_map(&["MMMMMM", "..~~..", "MMMMMM"]);
+        let ruleset = Ruleset::default();
+        let rules = MovementRules {
+            embarkation: None,
+            ..land_unit_rules(&tile_map, &ruleset)
+        };
+
+        assert!(find_path(tile(&tile_map, 0, 1), tile(&tile_map, 5, 1), &rules).is_none());
+        assert!(find_path(tile(&tile_map, 0, 1), tile(&tile_map, 0, 0), &rules).is_none());
+        assert!(
+            find_path_to_closest(tile(&tile_map, 0, 1), &rules, |tile| {
+                tile.is_water(&tile_map)
+            })
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn finds_path_to_closest_target() {
+        let tile_map = test_map(&[".....", "..h..", "....."]);
+        let ruleset = Ruleset::default();
+        let rules = land_unit_rules(&tile_map, &ruleset);
+
+        let hill = tile(&tile_map, 2, 1);
+        let path =
+            find_path_to_closest(tile(&tile_map, 0, 1), &rules, |tile| tile == hill).unwrap();
+        assert_eq!(path.tiles, [tile(&tile_map, 1, 1), hill]);
+        assert_eq!(path.cost, 1. + 2.);
+    }
+
+    #[test]
+    fn finds_cheapest_path_with_fast_embarked_units() {
+        let tile_map = test_map(&["~~~~~~~", ".hhhhh.", "MMMMMMM"]);
+        let ruleset = Ruleset::default();
+        // Embarked units move 4 tiles a turn, so a water tile costs half a movement point.
+        let rules = MovementRules {
+            embarkation: Some(Embarkation {
+                ocean: false,
+                movement: 4.,
+            }),
+            ..land_unit_rules(&tile_map, &ruleset)
+        };
+        assert_eq!(rules.min_step_cost(), 0.5);
+
+        let from = tile(&tile_map, 0, 1);
+        let to = tile(&tile_map, 6, 1);
+        let path = find_path(from, to, &rules).unwrap();
+        let cheapest = find_path_to_closest(from, &rules, |tile| tile == to).unwrap();
+        // Embarking, 5 water tiles and disembarking cost 2 + 2.5 + 2, less than the 11 of the hills.
+        assert_eq!(cheapest.cost, 6.5);
+        assert_eq!(path.cost, cheapest.cost);
+    }
+
+    #[test]
+    fn counts_turns_to_reach_each_tile() {
+        let tile_map = test_map(&["......", "..h...", "......"]);
+        let ruleset = Ruleset::default();
+        let rules = land_unit_rules(&tile_map, &ruleset);
+
+        let from = tile(&tile_map, 0, 1);
+        let path = find_path(from, tile(&tile_map, 1, 1), &rules).unwrap();
+        assert_eq!(turns_to_reach(&path, from, 2., &rules), [0]);
+
+        // The unit may enter the hill with 1 point left, and moves on the next turn.
+        let path = Path {
+            tiles: (1..=4).map(|x| tile(&tile_map, x, 1)).collect(),
+            cost: 5.,
+        };
+        assert_eq!(turns_to_reach(&path, from, 2., &rules), [0, 0, 1, 1]);
+        assert_eq!(turns_to_reach(&path, from, 0., &rules), [1, 1, 2, 2]);
+    }
+}
@@ -0,0 +1,556 @@
+//! The pause menu, opened with the `Escape` key when no other window is open: resume the game, change the options,
+//! save or load the game, quit to the main menu or exit the game. Quitting and exiting ask for a confirmation first.
+
+use bevy::{
+    app::AppExit,
+    input::InputSystems,
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    accessibility::{AccessibilitySettings, PlayerPalette},
+    animation::{AnimationSettings, AnimationSpeed},
+    assets::AppState,
+    audio::{AudioSettings, spawn_volume_sliders},
+    camera::EdgePanSettings,
+    city_screen::CityScreen,
+    diplomacy_screen::DiplomacyScreen,
+    key_bindings::{InputAction, KeyBindings, key_name},
+    natural_wonder::NaturalWonderSplash,
+    route_overlay::RouteOverlay,
+    save::{GameEntities, LoadGame, QUICK_SAVE_PATH, SaveGame, leave_game},
+    settings::{RESOLUTIONS, UserSettings, WindowModeSetting},
+    technology::TechTreeScreen,
+    turn_blocker::TurnBlocker,
+    world_map::SelectedUnit,
+    yield_overlay::YieldOverlay,
+};
+
+/// The page of the pause menu shown.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PauseMenuPage {
+    #[default]
+    Closed,
+    Main,
+    Options,
+    /// The mode and the size of the window, see [`UserSettings`].
+    Display,
+    /// The overlays drawn over the map.
+    Overlays,
+    /// The volume sliders, see [`crate::audio`].
+    Audio,
+    /// The keys bound to the actions, see [`KeyBindings`].
+    Controls,
+    /// Waiting for the key to bind to the action.
+    Rebind(InputAction),
+    /// Asking to confirm that the game is left for the main menu.
+    ConfirmQuitToMenu,
+    /// Asking to confirm that the game is closed.
+    ConfirmExit,
+}
+
+impl PauseMenuPage {
+    /// The page shown when going back from this page.
+    fn parent(self) -> Self {
+        match self {
+            Self::Closed | Self::Main => Self::Closed,
+            Self::Options | Self::ConfirmQuitToMenu | Self::ConfirmExit => Self::Main,
+            Self::Display | Self::Overlays | Self::Audio | Self::Controls => Self::Options,
+            Self::Rebind(_) => Self::Controls,
+        }
+    }
+}
+
+/// The window of the pause menu.
+#[derive(Component)]
+pub struct PauseMenu;
+
+/// An entry of the pause menu.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum PauseMenuEntry {
+    Resume,
+    Options,
+    Save,
+    Load,
+    QuitToMenu,
+    Exit,
+    ToggleEdgePan,
+    CyclePalette,
+    TogglePatterns,
+    CycleAnimationSpeed,
+    ToggleAnimations,
+    ToggleCombatEffects,
+    Display,
+    CycleWindowMode,
+    CycleResolution,
+    Overlays,
+    ToggleYieldOverlay,
+    ToggleTradeRoutes,
+    ToggleUnitPaths,
+    Audio,
+    Controls,
+    Rebind(InputAction),
+    ResetControls,
+    Back,
+    ConfirmQuitToMenu,
+    ConfirmExit,
+}
+
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenuPage>()
+            // Before the other windows handle the key in `Update`, so a window closed with `Escape` this frame
+            // doesn't let the menu open as well.
+            .add_systems(
+                PreUpdate,
+                toggle_pause_menu_on_escape
+                    .after(InputSystems)
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                Update,
+                (
+                    rebind_key,
+                    show_pause_menu.run_if(
+                        resource_changed::<PauseMenuPage>
+                            .or(resource_changed::<EdgePanSettings>)
+                            .or(resource_changed::<AccessibilitySettings>)
+                            .or(resource_changed::<AnimationSettings>)
+                            .or(resource_changed::<YieldOverlay>)
+                            .or(resource_changed::<RouteOverlay>)
+                            .or(resource_changed::<KeyBindings>),
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(OnExit(AppState::GameStart), close_pause_menu);
+    }
+}
+
+fn toggle_pause_menu_on_escape(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    selected_unit: Res<SelectedUnit>,
+    mut page: ResMut<PauseMenuPage>,
+    query_window: Query<
+        (),
+        Or<(
+            With<CityScreen>,
+            With<DiplomacyScreen>,
+            With<NaturalWonderSplash>,
+            With<TechTreeScreen>,
+            With<TurnBlocker>,
+        )>,
+    >,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    *page = match *page {
+        // `Escape` first closes the other windows and deselects the unit.
+        PauseMenuPage::Closed if selected_unit.0.is_some() || !query_window.is_empty() => {
+            return;
+        }
+        PauseMenuPage::Closed => PauseMenuPage::Main,
+        page => page.parent(),
+    };
+}
+
+/// Bind the action waiting for a key to the first key pressed. `Escape` cancels, see [`toggle_pause_menu_on_escape`].
+fn rebind_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut page: ResMut<PauseMenuPage>,
+    mut key_bindings: ResMut<KeyBindings>,
+) {
+    let PauseMenuPage::Rebind(action) = *page else {
+        return;
+    };
+    if let Some(&key) = keyboard_input
+        .get_just_pressed()
+        .find(|&&key| key_name(key).is_some())
+    {
+        key_bindings.bind(action, key);
+        *page = PauseMenuPage::Controls;
+    }
+}
+
+fn close_pause_menu(mut page: ResMut<PauseMenuPage>) {
+    *page = PauseMenuPage::Closed;
+}
+
+/// Show the entries of the current page of the menu.
+fn show_pause_menu(
+    mut commands: Commands,
+    page: Res<PauseMenuPage>,
+    edge_pan_settings: Res<EdgePanSettings>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    animation_settings: Res<AnimationSettings>,
+    yield_overlay: Res<YieldOverlay>,
+    route_overlay: Res<RouteOverlay>,
+    audio_settings: Res<AudioSettings>,
+    key_bindings: Res<KeyBindings>,
+    user_settings: Res<UserSettings>,
+    query_menu: Query<Entity, With<PauseMenu>>,
+) {
+    for menu in query_menu.iter() {
+        commands.entity(menu).despawn();
+    }
+
+    let (title, entries): (&str, Vec<(String, PauseMenuEntry)>) = match *page {
+        PauseMenuPage::Closed => return,
+        PauseMenuPage::Main => (
+            "Paused",
+            vec![
+                ("Resume".to_owned(), PauseMenuEntry::Resume),
+                ("Options".to_owned(), PauseMenuEntry::Options),
+                ("Save".to_owned(), PauseMenuEntry::Save),
+                ("Load".to_owned(), PauseMenuEntry::Load),
+                ("Quit to main menu".to_owned(), PauseMenuEntry::QuitToMenu),
+                ("Exit game".to_owned(), PauseMenuEntry::Exit),
+            ],
+        ),
+        PauseMenuPage::Options => (
+            "Options",
+            vec![
+                (
+                    format!(
+                        "Edge scrolling: {}",
+                        if edge_pan_settings.enabled {
+                            "On"
+                        } else {
+                            "Off"
+                        }
+                    ),
+                    PauseMenuEntry::ToggleEdgePan,
+                ),
+                (
+                    format!("Player colors: {}", accessibility_settings.palette.name()),
+                    PauseMenuEntry::CyclePalette,
+                ),
+                (
+                    format!(
+                        "Flag patterns: {}",
+                        if accessibility_settings.patterns {
+                            "On"
+                        } else {
+                            "Off"
+                        }
+                    ),
+                    PauseMenuEntry::TogglePatterns,
+                ),
+                (
+                    format!("Unit speed: {}", animation_settings.speed.name()),
+                    PauseMenuEntry::CycleAnimationSpeed,
+                ),
+                (
+                    format!(
+                        "Animations: {}",
+                        if animation_settings.skip_animations {
+                            "Off"
+                        } else {
+                            "On"
+                        }
+                    ),
+                    PauseMenuEntry::ToggleAnimations,
+                ),
+                (
+                    format!(
+                        "Combat effects: {}",
+                        if animation_settings.skip_combat_effects {
+                            "Off"
+                        } else {
+                            "On"
+                        }
+                    ),
+                    PauseMenuEntry::ToggleCombatEffects,
+                ),
+                ("Display".to_owned(), PauseMenuEntry::Display),
+                ("Overlays".to_owned(), PauseMenuEntry::Overlays),
+                ("Audio".to_owned(), PauseMenuEntry::Audio),
+                ("Controls".to_owned(), PauseMenuEntry::Controls),
+                ("Back".to_owned(), PauseMenuEntry::Back),
+            ],
+        ),
+        PauseMenuPage::Display => (
+            "Display",
+            vec![
+                (
+                    format!("Window: {}", user_settings.window_mode.name()),
+                    PauseMenuEntry::CycleWindowMode,
+                ),
+                (
+                    format!(
+                        "Resolution: {} x {}",
+                        user_settings.resolution[0], user_settings.resolution[1]
+                    ),
+                    PauseMenuEntry::CycleResolution,
+                ),
+                ("Back".to_owned(), PauseMenuEntry::Back),
+            ],
+        ),
+        PauseMenuPage::Overlays => (
+            "Overlays",
+            vec![
+                (
+                    format!("Yields: {}", if yield_overlay.shown { "On" } else { "Off" }),
+                    PauseMenuEntry::ToggleYieldOverlay,
+                ),
+                (
+                    format!(
+                        "Trade routes: {}",
+                        if route_overlay.trade_routes {
+                            "On"
+                        } else {
+                            "Off"
+                        }
+                    ),
+                    PauseMenuEntry::ToggleTradeRoutes,
+                ),
+                (
+                    format!(
+                        "Unit paths: {}",
+                        if route_overlay.unit_paths {
+                            "On"
+                        } else {
+                            "Off"
+                        }
+                    ),
+                    PauseMenuEntry::ToggleUnitPaths,
+                ),
+                ("Back".to_owned(), PauseMenuEntry::Back),
+            ],
+        ),
+        PauseMenuPage::Audio => ("Audio", vec![("Back".to_owned(), PauseMenuEntry::Back)]),
+        PauseMenuPage::Controls => (
+            "Controls",
+            InputAction::ALL
+                .into_iter()
+                .map(|action| {
+                    (
+                        format!("{}: {}", action.name(), key_bindings.keys_label(action)),
+                        PauseMenuEntry::Rebind(action),
+                    )
+                })
+                .chain([
+                    (
+                        "Reset to defaults".to_owned(),
+                        PauseMenuEntry::ResetControls,
+                    ),
+                    ("Back".to_owned(), PauseMenuEntry::Back),
+                ])
+                .collect(),
+        ),
+        PauseMenuPage::Rebind(action) => (
+            "Press a key, or Escape to cancel",
+            vec![(
+                format!("{}: {}", action.name(), key_bindings.keys_label(action)),
+                PauseMenuEntry::Back,
+            )],
+        ),
+        PauseMenuPage::ConfirmQuitToMenu => (
+            "Quit to the main menu? The game isn't saved.",
+            vec![
+                ("Quit".to_owned(), PauseMenuEntry::ConfirmQuitToMenu),
+                ("Cancel".to_owned(), PauseMenuEntry::Back),
+            ],
+        ),
+        PauseMenuPage::ConfirmExit => (
+            "Exit the game? The game isn't saved.",
+            vec![
+                ("Exit".to_owned(), PauseMenuEntry::ConfirmExit),
+                ("Cancel".to_owned(), PauseMenuEntry::Back),
+            ],
+        ),
+    };
+
+    commands
+        .spawn((
+            // Covers the screen, so the map can't be clicked while the menu is open.
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.5)),
+            GlobalZIndex(20),
+            PauseMenu,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(280.),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Stretch,
+                        row_gap: Val::Px(6.),
+                        padding: UiRect::all(Val::Px(12.)),
+                        border: UiRect::all(Val::Px(2.)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::BLACK.with_alpha(0.9)),
+                    BorderColor::all(Color::WHITE),
+                ))
+                .with_children(|window| {
+                    window.spawn((
+                        Text(title.to_owned()),
+                        TextFont {
+                            font_size: 18.,
+                            ..default()
+                        },
+                        TextLayout::new_with_justify(Justify::Center),
+                        Pickable::IGNORE,
+                    ));
+                    if *page == PauseMenuPage::Audio {
+                        spawn_volume_sliders(window, &audio_settings);
+                    }
+                    for (label, entry) in entries {
+                        window
+                            .spawn((
+                                Node {
+                                    justify_content: JustifyContent::Center,
+                                    padding: UiRect::vertical(Val::Px(2.)),
+                                    border: UiRect::all(Val::Px(1.)),
+                                    ..default()
+                                },
+                                BorderColor::all(Color::WHITE),
+                                Text(label),
+                                TextFont {
+                                    font_size: 16.,
+                                    ..default()
+                                },
+                                TextLayout::new_with_justify(Justify::Center),
+                                entry,
+                            ))
+                            .observe(choose_pause_menu_entry_on_click);
+                    }
+                });
+        });
+}
+
+fn choose_pause_menu_entry_on_click(
+    click: On<Pointer<Click>>,
+    mut commands: Commands,
+    mut page: ResMut<PauseMenuPage>,
+    mut edge_pan_settings: ResMut<EdgePanSettings>,
+    mut accessibility_settings: ResMut<AccessibilitySettings>,
+    mut animation_settings: ResMut<AnimationSettings>,
+    (mut yield_overlay, mut route_overlay): (ResMut<YieldOverlay>, ResMut<RouteOverlay>),
+    mut key_bindings: ResMut<KeyBindings>,
+    (mut window, mut user_settings): (
+        Single<&mut Window, With<PrimaryWindow>>,
+        ResMut<UserSettings>,
+    ),
+    mut next_state: ResMut<NextState<AppState>>,
+    mut save_game: MessageWriter<SaveGame>,
+    mut load_game: MessageWriter<LoadGame>,
+    mut app_exit: MessageWriter<AppExit>,
+    query_entry: Query<&PauseMenuEntry>,
+    query_world: Query<Entity, GameEntities>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    let Ok(&entry) = query_entry.get(click.entity) else {
+        return;
+    };
+    match entry {
+        PauseMenuEntry::Resume => *page = PauseMenuPage::Closed,
+        PauseMenuEntry::Options => *page = PauseMenuPage::Options,
+        PauseMenuEntry::Save => {
+            save_game.write(SaveGame {
+                path: QUICK_SAVE_PATH.into(),
+            });
+            *page = PauseMenuPage::Closed;
+        }
+        PauseMenuEntry::Load => {
+            load_game.write(LoadGame {
+                path: QUICK_SAVE_PATH.into(),
+            });
+            *page = PauseMenuPage::Closed;
+        }
+        PauseMenuEntry::QuitToMenu => *page = PauseMenuPage::ConfirmQuitToMenu,
+        PauseMenuEntry::Exit => *page = PauseMenuPage::ConfirmExit,
+        PauseMenuEntry::ToggleEdgePan => edge_pan_settings.enabled = !edge_pan_settings.enabled,
+        PauseMenuEntry::CyclePalette => {
+            let palettes = PlayerPalette::ALL;
+            let index = palettes
+                .iter()
+                .position(|&palette| palette == accessibility_settings.palette)
+                .unwrap_or_default();
+            accessibility_settings.palette = palettes[(index + 1) % palettes.len()];
+        }
+        PauseMenuEntry::TogglePatterns => {
+            accessibility_settings.patterns = !accessibility_settings.patterns;
+        }
+        PauseMenuEntry::CycleAnimationSpeed => {
+            let speeds = AnimationSpeed::ALL;
+            let index = speeds
+                .iter()
+                .position(|&speed| speed == animation_settings.speed)
+                .unwrap_or_default();
+            animation_settings.speed = speeds[(index + 1) % speeds.len()];
+        }
+        PauseMenuEntry::ToggleAnimations => {
+            animation_settings.skip_animations = !animation_settings.skip_animations;
+        }
+        PauseMenuEntry::ToggleCombatEffects => {
+            animation_settings.skip_combat_effects = !animation_settings.skip_combat_effects;
+        }
+        PauseMenuEntry::Display => *page = PauseMenuPage::Display,
+        // The window changes at once, and the settings are changed here too so the page shows the new values.
+        PauseMenuEntry::CycleWindowMode => {
+            let modes = WindowModeSetting::ALL;
+            let index = modes
+                .iter()
+                .position(|&mode| mode == user_settings.window_mode)
+                .unwrap_or_default();
+            let mode = modes[(index + 1) % modes.len()];
+            window.mode = mode.window_mode();
+            // Back in a window, the window gets its size from before the fullscreen.
+            if mode == WindowModeSetting::Windowed {
+                let [width, height] = user_settings.resolution;
+                window.resolution.set_physical_resolution(width, height);
+            }
+            user_settings.window_mode = mode;
+            page.set_changed();
+        }
+        PauseMenuEntry::CycleResolution => {
+            // A size set by resizing the window by hand goes on with the smallest resolution.
+            let index = RESOLUTIONS
+                .iter()
+                .position(|&resolution| resolution == user_settings.resolution)
+                .map_or(0, |index| (index + 1) % RESOLUTIONS.len());
+            let [width, height] = RESOLUTIONS[index];
+            // A fullscreen window covers the monitor, and gets the resolution when it is windowed again.
+            if user_settings.window_mode == WindowModeSetting::Windowed {
+                window.resolution.set_physical_resolution(width, height);
+            }
+            user_settings.resolution = RESOLUTIONS[index];
+            page.set_changed();
+        }
+        PauseMenuEntry::Overlays => *page = PauseMenuPage::Overlays,
+        PauseMenuEntry::ToggleYieldOverlay => yield_overlay.shown = !yield_overlay.shown,
+        PauseMenuEntry::ToggleTradeRoutes => {
+            route_overlay.trade_routes = !route_overlay.trade_routes;
+        }
+        PauseMenuEntry::ToggleUnitPaths => route_overlay.unit_paths = !route_overlay.unit_paths,
+        PauseMenuEntry::Audio => *page = PauseMenuPage::Audio,
+        PauseMenuEntry::Controls => *page = PauseMenuPage::Controls,
+        PauseMenuEntry::Rebind(action) => *page = PauseMenuPage::Rebind(action),
+        PauseMenuEntry::ResetControls => *key_bindings = KeyBindings::default(),
+        PauseMenuEntry::Back => *page = page.parent(),
+        PauseMenuEntry::ConfirmQuitToMenu => {
+            *page = PauseMenuPage::Closed;
+            leave_game(&mut commands, &mut next_state, &query_world);
+        }
+        PauseMenuEntry::ConfirmExit => {
+            app_exit.write(AppExit::Success);
+        }
+    }
+}
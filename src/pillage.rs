@@ -0,0 +1,294 @@
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset, tile::Tile};
+
+use crate::{
+    RulesetResource,
+    assets::AppState,
+    barbarian::BARBARIAN_ENCAMPMENT,
+    city::City,
+    civilization::Civilizations,
+    command::{PlayerCommand, UnitId},
+    diplomacy::DiplomacyState,
+    game_event::TileChanged,
+    improvement::TileImprovementLayer,
+    key_bindings::{InputAction, KeyBindings},
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, MovePath, restore_movement_points},
+    unit_component::{Health, Movement, Owner, Unit, UnitOrder},
+    world_map::SelectedUnit,
+};
+
+/// The gold a unit gets for pillaging an improvement, and for pillaging a road.
+const IMPROVEMENT_PILLAGE_GOLD: f32 = 20.;
+const ROAD_PILLAGE_GOLD: f32 = 5.;
+/// Pillaging heals the unit by 25 health points, as in Civ V.
+const PILLAGE_HEALING: u32 = 25;
+/// Pillaging uses 1 movement point.
+const PILLAGE_MOVEMENT_COST: f32 = 1.;
+/// A worker needs 3 turns to repair a pillaged improvement or road.
+const REPAIR_TURNS: u32 = 3;
+/// The unique of the units which build improvements, and so can repair them.
+const BUILD_IMPROVEMENTS_UNIQUE: &str = "Can build [Land] improvements on tiles";
+
+/// Request for a military unit to pillage the improvement, or else the road, of the enemy tile it stands on.
+#[derive(Message)]
+pub struct Pillage {
+    pub unit: Entity,
+}
+
+/// Request for a worker to repair the pillaged improvement or road of the tile it stands on.
+#[derive(Message)]
+pub struct Repair {
+    pub worker: Entity,
+}
+
+/// Written when a unit of `nation` pillaged a tile of `victim`, getting `gold`.
+#[derive(Message)]
+pub struct TilePillaged {
+    pub nation: Nation,
+    pub victim: Nation,
+    pub tile: Tile,
+    pub gold: f32,
+}
+
+pub struct PillagePlugin;
+
+impl Plugin for PillagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<Pillage>()
+            .add_message::<Repair>()
+            .add_message::<TilePillaged>()
+            .add_systems(
+                Update,
+                (pillage_or_repair_on_key, pillage_tiles, start_repairs)
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                repair_tiles
+                    .in_set(TurnSet::Units)
+                    .before(restore_movement_points),
+            );
+    }
+}
+
+/// Return `true` if `tile` has an improvement or a road left to pillage. Barbarian encampments can't be pillaged.
+pub fn can_pillage(tile: Tile, improvement_layer: &TileImprovementLayer) -> bool {
+    improvement_layer
+        .working_improvement(tile)
+        .is_some_and(|improvement| improvement != BARBARIAN_ENCAMPMENT)
+        || improvement_layer.has_working_road(tile)
+}
+
+/// Return `true` if the unit builds improvements and roads, and so can repair them.
+pub fn builds_improvements(unit_name: &str, ruleset: &Ruleset) -> bool {
+    ruleset.units[unit_name]
+        .uniques
+        .iter()
+        .any(|unique| unique == BUILD_IMPROVEMENTS_UNIQUE)
+}
+
+/// Return `true` if `tile` has a pillaged improvement or road.
+fn needs_repair(tile: Tile, improvement_layer: &TileImprovementLayer) -> bool {
+    improvement_layer.is_improvement_pillaged(tile) || improvement_layer.is_road_pillaged(tile)
+}
+
+/// Pillage with the selected unit, or repair its tile, on the keys bound to [`InputAction::Pillage`] and
+/// [`InputAction::Repair`].
+fn pillage_or_repair_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    selected_unit: Res<SelectedUnit>,
+    query_unit: Query<(&Unit, &MapUnit)>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    let Some(selected) = selected_unit.0 else {
+        return;
+    };
+    let Ok((unit, map_unit)) = query_unit.get(selected) else {
+        return;
+    };
+
+    if key_bindings.just_pressed(InputAction::Pillage, &keyboard_input) {
+        player_command.write(PlayerCommand::Pillage {
+            unit: UnitId::new(unit, map_unit),
+        });
+    } else if key_bindings.just_pressed(InputAction::Repair, &keyboard_input) {
+        player_command.write(PlayerCommand::Repair {
+            worker: UnitId::new(unit, map_unit),
+        });
+    }
+}
+
+/// Pillage the tiles requested by [`Pillage`] messages.
+///
+/// Only military units with movement points left can pillage, on a tile inside the borders of a city
+/// of a nation at war with them. The improvement is pillaged first, then the road.
+/// The unit heals, its nation gets gold, and pillaging uses [`PILLAGE_MOVEMENT_COST`].
+fn pillage_tiles(
+    mut pillage: MessageReader<Pillage>,
+    mut tile_pillaged: MessageWriter<TilePillaged>,
+    mut tile_changed: MessageWriter<TileChanged>,
+    diplomacy: Res<DiplomacyState>,
+    mut civilizations: ResMut<Civilizations>,
+    mut improvement_layer: ResMut<TileImprovementLayer>,
+    query_city: Query<(&City, &Owner)>,
+    mut query_unit: Query<(&Unit, &Owner, &MapUnit, &mut Movement, &mut Health)>,
+) {
+    for &Pillage { unit: entity } in pillage.read() {
+        let Ok((unit, owner, map_unit, mut movement, mut health)) = query_unit.get_mut(entity)
+        else {
+            continue;
+        };
+        let nation = owner.nation();
+        let tile = map_unit.tile;
+
+        let victim = query_city
+            .iter()
+            .find(|(city, _)| city.owned_tiles.contains(&tile))
+            .map(|(_, city_owner)| city_owner.nation());
+        let Some(victim) = victim.filter(|&victim| diplomacy.is_at_war(nation, victim)) else {
+            continue;
+        };
+        if !matches!(unit, Unit::Military(_))
+            || movement.current <= 0.
+            || !can_pillage(tile, &improvement_layer)
+        {
+            continue;
+        }
+
+        let gold = if improvement_layer
+            .working_improvement(tile)
+            .is_some_and(|improvement| improvement != BARBARIAN_ENCAMPMENT)
+        {
+            improvement_layer.set_improvement_pillaged(tile, true, &mut tile_changed);
+            IMPROVEMENT_PILLAGE_GOLD
+        } else {
+            improvement_layer.set_road_pillaged(tile, true, &mut tile_changed);
+            ROAD_PILLAGE_GOLD
+        };
+
+        civilizations.get_mut(nation).gold += gold;
+        health.current = (health.current + PILLAGE_HEALING).min(health.max);
+        movement.current = (movement.current - PILLAGE_MOVEMENT_COST).max(0.);
+        tile_pillaged.write(TilePillaged {
+            nation,
+            victim,
+            tile,
+            gold,
+        });
+    }
+}
+
+/// Let the workers requested by [`Repair`] messages start to repair their tile, see [`UnitOrder::Repair`].
+fn start_repairs(
+    mut repair: MessageReader<Repair>,
+    ruleset: Res<RulesetResource>,
+    improvement_layer: Res<TileImprovementLayer>,
+    mut query_unit: Query<(&Unit, &MapUnit, &mut MovePath, &mut UnitOrder)>,
+) {
+    let ruleset = &ruleset.0;
+
+    for &Repair { worker } in repair.read() {
+        let Ok((unit, map_unit, mut move_path, mut order)) = query_unit.get_mut(worker) else {
+            continue;
+        };
+        if !builds_improvements(unit.name(), ruleset)
+            || !needs_repair(map_unit.tile, &improvement_layer)
+            || matches!(*order, UnitOrder::Repair { .. })
+        {
+            continue;
+        }
+        move_path.0.clear();
+        *order = UnitOrder::Repair {
+            turns: REPAIR_TURNS,
+        };
+    }
+}
+
+/// Count down the repairs of the workers, and repair their tile when the countdown ends:
+/// the improvement first, then the road.
+fn repair_tiles(
+    mut tile_changed: MessageWriter<TileChanged>,
+    mut improvement_layer: ResMut<TileImprovementLayer>,
+    mut query_unit: Query<(&MapUnit, &mut UnitOrder)>,
+) {
+    for (map_unit, mut order) in query_unit.iter_mut() {
+        let UnitOrder::Repair { turns } = *order else {
+            continue;
+        };
+        let tile = map_unit.tile;
+
+        if turns > 1 {
+            *order = UnitOrder::Repair { turns: turns - 1 };
+            continue;
+        }
+        *order = UnitOrder::None;
+        if improvement_layer.is_improvement_pillaged(tile) {
+            improvement_layer.set_improvement_pillaged(tile, false, &mut tile_changed);
+        } else if improvement_layer.is_road_pillaged(tile) {
+            improvement_layer.set_road_pillaged(tile, false, &mut tile_changed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{message::Messages, system::RunSystemOnce};
+
+    use super::*;
+
+    #[test]
+    fn repairs_pillaged_roads() {
+        let tile = Tile::new(0);
+        let mut world = World::new();
+        world.init_resource::<Messages<TileChanged>>();
+        world.insert_resource(TileImprovementLayer::new(1));
+        world
+            .run_system_once(
+                move |mut tile_changed: MessageWriter<TileChanged>,
+                      mut improvement_layer: ResMut<TileImprovementLayer>| {
+                    improvement_layer.set_road(tile, true, &mut tile_changed);
+                },
+            )
+            .unwrap();
+        assert!(can_pillage(tile, world.resource::<TileImprovementLayer>()));
+
+        world
+            .run_system_once(
+                move |mut tile_changed: MessageWriter<TileChanged>,
+                      mut improvement_layer: ResMut<TileImprovementLayer>| {
+                    improvement_layer.set_road_pillaged(tile, true, &mut tile_changed);
+                },
+            )
+            .unwrap();
+        let improvement_layer = world.resource::<TileImprovementLayer>();
+        assert!(improvement_layer.has_road(tile) && !improvement_layer.has_working_road(tile));
+        assert!(!can_pillage(tile, improvement_layer));
+        assert!(needs_repair(tile, improvement_layer));
+
+        let worker = world
+            .spawn((
+                MapUnit { tile },
+                UnitOrder::Repair {
+                    turns: REPAIR_TURNS,
+                },
+            ))
+            .id();
+        for _ in 1..REPAIR_TURNS {
+            world.run_system_once(repair_tiles).unwrap();
+        }
+        assert!(
+            !world
+                .resource::<TileImprovementLayer>()
+                .has_working_road(tile)
+        );
+
+        world.run_system_once(repair_tiles).unwrap();
+        let improvement_layer = world.resource::<TileImprovementLayer>();
+        assert!(improvement_layer.has_working_road(tile));
+        assert!(!needs_repair(tile, improvement_layer));
+        assert_eq!(world.get::<UnitOrder>(worker), Some(&UnitOrder::None));
+    }
+}
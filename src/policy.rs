@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset};
+
+use crate::{
+    RulesetResource,
+    assets::AppState,
+    city::City,
+    civilization::{Civilization, Civilizations},
+    era::eras_in_order,
+    turn::{TurnProcessing, TurnSet},
+    unit_component::Owner,
+    yields::TileYields,
+};
+
+/// Request to adopt a policy, or to open a policy branch, for a civilization.
+#[derive(Message)]
+pub struct AdoptPolicy {
+    pub nation: Nation,
+    pub policy: String,
+}
+
+/// Written when a civilization adopts a policy or opens a policy branch.
+#[derive(Message)]
+pub struct PolicyAdopted {
+    pub nation: Nation,
+    pub policy: String,
+}
+
+/// The culture needed to adopt the next policy, using the Civ V formula `25 + (6 * adopted)^1.7`,
+/// increased by 30% for every city after the first and rounded down to a multiple of 5.
+pub fn policy_cost(adopted_policy_count: usize, city_count: usize) -> f32 {
+    let cost = 25. + (6. * adopted_policy_count as f32).powf(1.7);
+    let cost = cost * (1. + 0.3 * city_count.saturating_sub(1) as f32);
+    (cost / 5.).floor() * 5.
+}
+
+/// Return `true` if the civilization can adopt the policy or open the policy branch, ignoring its culture.
+///
+/// A branch can be opened from its era on. A policy can be adopted when its branch is open
+/// and the policies it requires are adopted.
+pub fn can_adopt_policy(policy: &str, civilization: &Civilization, ruleset: &Ruleset) -> bool {
+    if civilization.adopted_policies.contains(policy) {
+        return false;
+    }
+
+    if let Some(branch) = ruleset.policies.get(policy) {
+        let eras = eras_in_order(ruleset);
+        let era_index = |era: &str| eras.iter().position(|ordered_era| ordered_era == era);
+        return era_index(&branch.era) <= era_index(&civilization.era);
+    }
+
+    ruleset.policies.values().any(|branch| {
+        branch.policies.iter().any(|branch_policy| {
+            branch_policy.name == policy
+                && civilization.adopted_policies.contains(&branch.name)
+                && branch_policy
+                    .requires
+                    .iter()
+                    .all(|required| civilization.adopted_policies.contains(required))
+        })
+    })
+}
+
+/// Return the uniques of a policy or a policy branch.
+pub fn policy_uniques<'a>(policy: &str, ruleset: &'a Ruleset) -> &'a [String] {
+    if let Some(branch) = ruleset.policies.get(policy) {
+        return &branch.uniques;
+    }
+    ruleset
+        .policies
+        .values()
+        .flat_map(|branch| &branch.policies)
+        .find(|branch_policy| branch_policy.name == policy)
+        .map_or(&[], |branch_policy| &branch_policy.uniques)
+}
+
+pub struct PolicyPlugin;
+
+impl Plugin for PolicyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<AdoptPolicy>()
+            .add_message::<PolicyAdopted>()
+            .add_systems(
+                Update,
+                adopt_policies.run_if(in_state(AppState::GameStart).and(on_message::<AdoptPolicy>)),
+            )
+            .add_systems(TurnProcessing, accumulate_culture.in_set(TurnSet::Culture));
+    }
+}
+
+fn adopt_policies(
+    mut adopt_policy: MessageReader<AdoptPolicy>,
+    mut policy_adopted: MessageWriter<PolicyAdopted>,
+    ruleset: Res<RulesetResource>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let ruleset = &ruleset.0;
+
+    for AdoptPolicy { nation, policy } in adopt_policy.read() {
+        // Puppet cities don't make policies more expensive.
+        let city_count = query_city
+            .iter()
+            .filter(|(city, owner)| owner.nation() == *nation && !city.is_puppet)
+            .count();
+        let civilization = civilizations.get_mut(*nation);
+        let cost = policy_cost(civilization.adopted_policies.len(), city_count);
+        if civilization.culture < cost || !can_adopt_policy(policy, civilization, ruleset) {
+            continue;
+        }
+
+        civilization.culture -= cost;
+        civilization.adopted_policies.insert(policy.clone());
+        civilization
+            .effects
+            .apply_uniques(policy_uniques(policy, ruleset));
+        policy_adopted.write(PolicyAdopted {
+            nation: *nation,
+            policy: policy.clone(),
+        });
+    }
+}
+
+/// Add the culture of every city to its civilization.
+fn accumulate_culture(
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let ruleset = &ruleset.0;
+
+    for (city, owner) in query_city.iter() {
+        let civilization = civilizations.get_mut(owner.nation());
+        civilization.culture += city.culture(&tile_yields, civilization, ruleset);
+    }
+}
@@ -0,0 +1,376 @@
+use bevy::{
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+use civ_map_generator::nation::Nation;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    TileMapResource,
+    assets::AppState,
+    city::City,
+    civilization::{Civilization, Civilizations, PlayerCivilization},
+    command::PlayerCommand,
+    game_event::TileChanged,
+    improvement::TileImprovementLayer,
+    rng::GameRng,
+    turn::{TurnManager, TurnProcessing, TurnSet},
+    unit_component::Owner,
+};
+
+/// The random events, defined with the ruleset data in `assets/Events/Events.json`.
+const EVENTS_JSON: &str = include_str!("../assets/Events/Events.json");
+
+/// A random event which may happen to a civilization at the start of a turn.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RandomEventInfo {
+    pub name: String,
+    pub text: String,
+    /// The chance every turn that the event happens to a civilization matching all its triggers.
+    pub chance: f32,
+    pub triggers: Vec<EventTrigger>,
+    /// Applied as soon as the event happens.
+    pub effects: Vec<EventEffect>,
+    /// The civilization must choose one of them. An event without choices is only applied.
+    pub choices: Vec<EventChoice>,
+}
+
+/// A condition a civilization must match for an event to happen to it.
+#[derive(Clone, Debug, Deserialize)]
+pub enum EventTrigger {
+    /// From this turn on.
+    MinTurn(u32),
+    /// Once the civilization knows this technology.
+    Technology(String),
+    /// While the civilization is unhappy.
+    Unhappy,
+}
+
+impl EventTrigger {
+    fn matches(&self, civilization: &Civilization, turn: u32) -> bool {
+        match self {
+            EventTrigger::MinTurn(min_turn) => turn >= *min_turn,
+            EventTrigger::Technology(technology) => civilization.has_technology(technology),
+            EventTrigger::Unhappy => civilization.happiness < 0.,
+        }
+    }
+}
+
+/// What an event or one of its choices does to the civilization.
+#[derive(Clone, Debug, Deserialize)]
+pub enum EventEffect {
+    Gold(f32),
+    Culture(f32),
+    /// Added to the next research, like the science of a research agreement.
+    Science(f32),
+    /// Pillage every improvement inside the borders of the civilization with this chance.
+    PillageImprovements(f32),
+    /// Repair every pillaged improvement and road inside the borders of the civilization.
+    RepairImprovements,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EventChoice {
+    pub text: String,
+    pub effects: Vec<EventEffect>,
+}
+
+/// The random events of the ruleset.
+#[derive(Resource)]
+pub struct RandomEvents(pub Vec<RandomEventInfo>);
+
+impl Default for RandomEvents {
+    fn default() -> Self {
+        Self(serde_json::from_str(EVENTS_JSON).expect("Events.json should be valid"))
+    }
+}
+
+impl RandomEvents {
+    pub fn get(&self, name: &str) -> Option<&RandomEventInfo> {
+        self.0.iter().find(|event| event.name == name)
+    }
+}
+
+/// An event waiting for `nation` to choose one of its choices.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingEvent {
+    pub nation: Nation,
+    pub event: String,
+}
+
+/// The events waiting for a choice, in the order they happened.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct PendingEvents(pub Vec<PendingEvent>);
+
+impl PendingEvents {
+    /// The oldest event waiting for a choice of `nation`.
+    pub fn first_for(&self, nation: Nation) -> Option<&PendingEvent> {
+        self.0.iter().find(|pending| pending.nation == nation)
+    }
+}
+
+/// Choice of `nation` for the pending event `event`, the index of one of its choices.
+#[derive(Message)]
+pub struct ChooseEventOption {
+    pub nation: Nation,
+    pub event: String,
+    pub choice: usize,
+}
+
+/// Written when the event `event` happened to `nation`. The UI shows its choices if it has any.
+#[derive(Message)]
+pub struct EventFired {
+    pub nation: Nation,
+    pub event: String,
+}
+
+pub struct RandomEventPlugin;
+
+impl Plugin for RandomEventPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RandomEvents>()
+            .init_resource::<PendingEvents>()
+            .add_message::<ChooseEventOption>()
+            .add_message::<EventFired>()
+            .add_systems(
+                Update,
+                (
+                    choose_event_options.run_if(on_message::<ChooseEventOption>),
+                    show_event_popup,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(TurnProcessing, fire_random_events.in_set(TurnSet::Events));
+    }
+}
+
+/// Apply `effects` to `nation`. Only the tiles inside the borders of its cities are pillaged or repaired.
+fn apply_event_effects(
+    effects: &[EventEffect],
+    nation: Nation,
+    civilizations: &mut Civilizations,
+    improvement_layer: &mut TileImprovementLayer,
+    tile_changed: &mut MessageWriter<TileChanged>,
+    rng: &mut GameRng,
+    query_city: &Query<(&City, &Owner)>,
+) {
+    let mut owned_tiles: Vec<_> = query_city
+        .iter()
+        .filter(|(_, owner)| owner.nation() == nation)
+        .flat_map(|(city, _)| city.owned_tiles.iter().copied())
+        .collect();
+    // Pillage in a fixed order, so every player of a network game draws the same random numbers.
+    owned_tiles.sort_by_key(|tile| tile.index());
+
+    for effect in effects {
+        match *effect {
+            EventEffect::Gold(gold) => civilizations.get_mut(nation).gold += gold,
+            EventEffect::Culture(culture) => civilizations.get_mut(nation).culture += culture,
+            EventEffect::Science(science) => {
+                civilizations.get_mut(nation).science_overflow += science;
+            }
+            EventEffect::PillageImprovements(chance) => {
+                for &tile in &owned_tiles {
+                    if improvement_layer.working_improvement(tile).is_some() && rng.gen_bool(chance)
+                    {
+                        improvement_layer.set_improvement_pillaged(tile, true, tile_changed);
+                    }
+                }
+            }
+            EventEffect::RepairImprovements => {
+                for &tile in &owned_tiles {
+                    if improvement_layer.is_improvement_pillaged(tile) {
+                        improvement_layer.set_improvement_pillaged(tile, false, tile_changed);
+                    }
+                    if improvement_layer.is_road_pillaged(tile) {
+                        improvement_layer.set_road_pillaged(tile, false, tile_changed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Let at most one random event happen to every civilization: the first event of the ruleset
+/// whose triggers match and whose chance is drawn. A civilization with a pending event gets no new one.
+fn fire_random_events(
+    mut event_fired: MessageWriter<EventFired>,
+    mut tile_changed: MessageWriter<TileChanged>,
+    mut rng: ResMut<GameRng>,
+    map: Res<TileMapResource>,
+    turn_manager: Res<TurnManager>,
+    random_events: Res<RandomEvents>,
+    mut pending_events: ResMut<PendingEvents>,
+    mut civilizations: ResMut<Civilizations>,
+    mut improvement_layer: ResMut<TileImprovementLayer>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let mut nations: Vec<_> = map.0.starting_tile_and_civilization.iter().collect();
+    nations.sort_by_key(|(tile, _)| tile.index());
+
+    for (_, &nation) in nations {
+        if pending_events.first_for(nation).is_some() {
+            continue;
+        }
+        let civilization = civilizations.get(nation);
+        let event = random_events.0.iter().find(|event| {
+            event
+                .triggers
+                .iter()
+                .all(|trigger| trigger.matches(civilization, turn_manager.turn))
+                && rng.gen_bool(event.chance)
+        });
+        let Some(event) = event else {
+            continue;
+        };
+
+        apply_event_effects(
+            &event.effects,
+            nation,
+            &mut civilizations,
+            &mut improvement_layer,
+            &mut tile_changed,
+            &mut rng,
+            &query_city,
+        );
+        if !event.choices.is_empty() {
+            pending_events.0.push(PendingEvent {
+                nation,
+                event: event.name.clone(),
+            });
+        }
+        event_fired.write(EventFired {
+            nation,
+            event: event.name.clone(),
+        });
+    }
+}
+
+/// Apply the choices requested by [`ChooseEventOption`] messages to their pending events.
+fn choose_event_options(
+    mut choose_event_option: MessageReader<ChooseEventOption>,
+    mut tile_changed: MessageWriter<TileChanged>,
+    mut rng: ResMut<GameRng>,
+    random_events: Res<RandomEvents>,
+    mut pending_events: ResMut<PendingEvents>,
+    mut civilizations: ResMut<Civilizations>,
+    mut improvement_layer: ResMut<TileImprovementLayer>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    for ChooseEventOption {
+        nation,
+        event,
+        choice,
+    } in choose_event_option.read()
+    {
+        let Some(index) = pending_events
+            .0
+            .iter()
+            .position(|pending| pending.nation == *nation && pending.event == *event)
+        else {
+            continue;
+        };
+        let Some(choice) = random_events
+            .get(event)
+            .and_then(|event| event.choices.get(*choice))
+        else {
+            continue;
+        };
+
+        pending_events.0.remove(index);
+        apply_event_effects(
+            &choice.effects,
+            *nation,
+            &mut civilizations,
+            &mut improvement_layer,
+            &mut tile_changed,
+            &mut rng,
+            &query_city,
+        );
+    }
+}
+
+/// The window showing a pending event of the player and its choices.
+#[derive(Component)]
+struct EventPopup(String);
+
+/// A button of the [`EventPopup`], choosing the choice at this index.
+#[derive(Component)]
+struct EventChoiceButton(usize);
+
+/// Show the oldest pending event of the player, and close the window once it has been chosen.
+fn show_event_popup(
+    mut commands: Commands,
+    player_civilization: Res<PlayerCivilization>,
+    random_events: Res<RandomEvents>,
+    pending_events: Res<PendingEvents>,
+    query_popup: Query<(Entity, &EventPopup)>,
+) {
+    let pending = pending_events
+        .first_for(player_civilization.0)
+        .map(|pending| pending.event.as_str());
+    if let Ok((entity, popup)) = query_popup.single() {
+        if pending == Some(popup.0.as_str()) {
+            return;
+        }
+        commands.entity(entity).despawn();
+    }
+    let Some(event) = pending.and_then(|event| random_events.get(event)) else {
+        return;
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(30.),
+                top: Val::Percent(30.),
+                width: Val::Percent(40.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.),
+                padding: UiRect::all(Val::Px(12.)),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.9)),
+            BorderColor::all(Color::WHITE),
+            EventPopup(event.name.clone()),
+        ))
+        .with_children(|parent| {
+            parent.spawn(Text(format!("{}\n\n{}", event.name, event.text)));
+            for (index, choice) in event.choices.iter().enumerate() {
+                parent
+                    .spawn((
+                        Node {
+                            padding: UiRect::all(Val::Px(4.)),
+                            border: UiRect::all(Val::Px(1.)),
+                            ..default()
+                        },
+                        BorderColor::all(Color::WHITE),
+                        Text(choice.text.clone()),
+                        EventChoiceButton(index),
+                    ))
+                    .observe(choose_event_option_on_click);
+            }
+        });
+}
+
+fn choose_event_option_on_click(
+    click: On<Pointer<Click>>,
+    query_button: Query<(&EventChoiceButton, &ChildOf)>,
+    query_popup: Query<&EventPopup>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    if let Ok((&EventChoiceButton(choice), child_of)) = query_button.get(click.entity)
+        && let Ok(popup) = query_popup.get(child_of.parent())
+    {
+        player_command.write(PlayerCommand::ChooseEventOption {
+            event: popup.0.clone(),
+            choice,
+        });
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::ecs::resource::Resource;
+use serde::{Deserialize, Serialize};
+
+/// The random number generator used by all gameplay randomness (combat, barbarians, events...).
+///
+/// It is a hand-written PCG-XSH-RR 64/32 generator instead of `rand::StdRng`, because the algorithm behind `StdRng`
+/// may change between `rand` versions and platforms. PCG is fully specified, so the same seed produces the same
+/// sequence on every build, and its whole state is two `u64`s, which makes it trivial to save and restore.
+#[derive(Resource, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRng {
+    state: u64,
+    increment: u64,
+}
+
+impl GameRng {
+    const MULTIPLIER: u64 = 6364136223846793005;
+    const DEFAULT_STREAM: u64 = 1442695040888963407;
+    /// The stream of the seed of the map generation, apart from the stream of the gameplay rolls.
+    const MAP_STREAM: u64 = 0x6d61_705f_7365_6564;
+
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, Self::DEFAULT_STREAM)
+    }
+
+    /// Create a generator on a specific stream.
+    ///
+    /// Generators with the same seed but different streams produce independent sequences.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        // The increment must be odd.
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// The seed given to the map generator for the seed `seed` of a game.
+    ///
+    /// The map is generated from a seed drawn from this generator instead of the seed of the game itself, so the map
+    /// and the gameplay rolls of a seed both depend on the sequence of this generator, which the tests pin.
+    pub fn map_seed(seed: u64) -> u64 {
+        Self::with_stream(seed, Self::MAP_STREAM).next_u64()
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rotation = (old_state >> 59) as u32;
+        xor_shifted.rotate_right(rotation)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    /// Return a float in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        // Use the upper 24 bits, which is the precision of the `f32` mantissa.
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Return an integer in `[low, high)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `low >= high`.
+    pub fn gen_range(&mut self, low: i32, high: i32) -> i32 {
+        assert!(low < high, "Invalid range: {}..{}", low, high);
+        let range = (high as i64 - low as i64) as u64;
+        // Rejection sampling to avoid modulo bias.
+        let zone = u32::MAX as u64 + 1 - (u32::MAX as u64 + 1) % range;
+        loop {
+            let value = self.next_u32() as u64;
+            if value < zone {
+                return (low as i64 + (value % range) as i64) as i32;
+            }
+        }
+    }
+
+    /// Return `true` with the given probability.
+    pub fn gen_bool(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Shuffle a slice in place with the Fisher-Yates algorithm.
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.gen_range(0, i as i32 + 1) as usize;
+            slice.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The first outputs of the reference implementation of PCG32, `pcg32-demo` seeded with 42 on stream 54.
+    #[test]
+    fn matches_the_reference_implementation() {
+        let mut rng = GameRng::with_stream(42, 54);
+        let outputs: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            outputs,
+            [
+                0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e
+            ]
+        );
+    }
+
+    /// A change of these values changes every game played from a seed, and breaks the saves and the replays.
+    #[test]
+    fn keeps_the_sequence_of_a_seed() {
+        let mut rng = GameRng::new(42);
+        let outputs: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            outputs,
+            [
+                0x1d5ddcb9, 0x726c11a4, 0xd44fb6c0, 0x28b658c3, 0x468dee08, 0x18abf114
+            ]
+        );
+
+        let mut rng = GameRng::new(42);
+        let rolls: Vec<i32> = (0..10).map(|_| rng.gen_range(0, 100)).collect();
+        assert_eq!(rolls, [17, 28, 20, 15, 32, 56, 98, 3, 9, 51]);
+    }
+
+    #[test]
+    fn keeps_the_map_seed_of_a_seed() {
+        assert_eq!(GameRng::map_seed(42), 0x50df_1a6d_0074_d9f1);
+    }
+}
@@ -0,0 +1,186 @@
+//! Roads built by workers. They connect the cities to their capital, see [`crate::connection`],
+//! and cost their maintenance inside the borders, see [`crate::economy`].
+
+use bevy::prelude::*;
+use civ_map_generator::{ruleset::Ruleset, tile::Tile, tile_map::TileMap};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    civilization::{Civilization, Civilizations},
+    command::{PlayerCommand, UnitId},
+    game_event::TileChanged,
+    improvement::TileImprovementLayer,
+    key_bindings::{InputAction, KeyBindings},
+    pillage::builds_improvements,
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, MovePath, restore_movement_points},
+    unit_component::{Owner, Unit, UnitOrder},
+    world_map::SelectedUnit,
+};
+
+/// The name of the road in the tile improvements of the ruleset, whose `techRequired` is needed to build roads.
+pub const ROAD: &str = "Road";
+/// A worker needs 3 turns to build a road, as in Civ V.
+const ROAD_TURNS: u32 = 3;
+
+/// Request for a worker to build a road on the tile it stands on.
+#[derive(Message)]
+pub struct BuildRoad {
+    pub worker: Entity,
+}
+
+pub struct RoadPlugin;
+
+impl Plugin for RoadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<BuildRoad>()
+            .add_systems(
+                Update,
+                (build_road_on_key, start_road_building)
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                build_roads
+                    .in_set(TurnSet::Units)
+                    .before(restore_movement_points),
+            );
+    }
+}
+
+/// Return `true` if the unit `unit_name` of the civilization can build a road on `tile`: it is a worker,
+/// its civilization knows the technology of roads, and `tile` is a land tile without a road.
+pub fn can_build_road(
+    unit_name: &str,
+    tile: Tile,
+    civilization: &Civilization,
+    improvement_layer: &TileImprovementLayer,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+) -> bool {
+    builds_improvements(unit_name, ruleset)
+        && civilization.has_technology(&ruleset.tile_improvements[ROAD].required_tech)
+        && !tile.is_water(tile_map)
+        && !improvement_layer.has_road(tile)
+}
+
+fn build_road_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    selected_unit: Res<SelectedUnit>,
+    query_unit: Query<(&Unit, &MapUnit)>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if key_bindings.just_pressed(InputAction::BuildRoad, &keyboard_input)
+        && let Some(worker) = selected_unit.0
+        && let Ok((unit, map_unit)) = query_unit.get(worker)
+    {
+        player_command.write(PlayerCommand::BuildRoad {
+            worker: UnitId::new(unit, map_unit),
+        });
+    }
+}
+
+/// Let the workers requested by [`BuildRoad`] messages start to build a road on their tile,
+/// see [`UnitOrder::BuildRoad`].
+fn start_road_building(
+    mut build_road: MessageReader<BuildRoad>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    improvement_layer: Res<TileImprovementLayer>,
+    mut query_unit: Query<(&Unit, &Owner, &MapUnit, &mut MovePath, &mut UnitOrder)>,
+) {
+    let ruleset = &ruleset.0;
+
+    for &BuildRoad { worker } in build_road.read() {
+        let Ok((unit, owner, map_unit, mut move_path, mut order)) = query_unit.get_mut(worker)
+        else {
+            continue;
+        };
+        if matches!(*order, UnitOrder::BuildRoad { .. })
+            || !can_build_road(
+                unit.name(),
+                map_unit.tile,
+                civilizations.get(owner.nation()),
+                &improvement_layer,
+                &map.0,
+                ruleset,
+            )
+        {
+            continue;
+        }
+        move_path.0.clear();
+        *order = UnitOrder::BuildRoad { turns: ROAD_TURNS };
+    }
+}
+
+/// Count down the roads of the workers, and build the road of their tile when the countdown ends.
+fn build_roads(
+    mut tile_changed: MessageWriter<TileChanged>,
+    mut improvement_layer: ResMut<TileImprovementLayer>,
+    mut query_unit: Query<(&MapUnit, &mut UnitOrder)>,
+) {
+    for (map_unit, mut order) in query_unit.iter_mut() {
+        let UnitOrder::BuildRoad { turns } = *order else {
+            continue;
+        };
+
+        if turns > 1 {
+            *order = UnitOrder::BuildRoad { turns: turns - 1 };
+            continue;
+        }
+        *order = UnitOrder::None;
+        improvement_layer.set_road(map_unit.tile, true, &mut tile_changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{message::Messages, system::RunSystemOnce};
+
+    use super::*;
+    use crate::economy::road_maintenance;
+
+    #[test]
+    fn built_roads_cost_maintenance() {
+        let city_tile = Tile::new(0);
+        let owned_tiles = [city_tile, Tile::new(1), Tile::new(2)];
+
+        let mut world = World::new();
+        world.init_resource::<Messages<TileChanged>>();
+        world.insert_resource(TileImprovementLayer::new(owned_tiles.len()));
+        for &tile in &owned_tiles {
+            world.spawn((MapUnit { tile }, UnitOrder::BuildRoad { turns: ROAD_TURNS }));
+        }
+
+        for _ in 1..ROAD_TURNS {
+            world.run_system_once(build_roads).unwrap();
+        }
+        let improvement_layer = world.resource::<TileImprovementLayer>();
+        assert!(
+            owned_tiles
+                .iter()
+                .all(|&tile| !improvement_layer.has_road(tile))
+        );
+        assert_eq!(
+            road_maintenance(city_tile, &owned_tiles, improvement_layer),
+            0.
+        );
+
+        world.run_system_once(build_roads).unwrap();
+        let improvement_layer = world.resource::<TileImprovementLayer>();
+        assert!(
+            owned_tiles
+                .iter()
+                .all(|&tile| improvement_layer.has_road(tile))
+        );
+        // The road of the city tile is free.
+        assert_eq!(
+            road_maintenance(city_tile, &owned_tiles, improvement_layer),
+            2.
+        );
+    }
+}
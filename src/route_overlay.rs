@@ -0,0 +1,160 @@
+//! The route overlay: dashed lines crawling along the trade routes connecting the cities to their capital, and along
+//! the paths the units of the player are ordered to walk over the next turns, in the color of their nation.
+//!
+//! Both are toggled in the overlays page of the pause menu, see [`RouteOverlay`]. Only the parts of the lines over
+//! the tiles the player explored are drawn.
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    accessibility::AccessibilitySettings,
+    assets::AppState,
+    civilization::PlayerCivilization,
+    connection::CityConnections,
+    unit::{MapUnit, MovePath},
+    unit_component::Owner,
+    visibility::VisibilityLayer,
+    world_map::{WorldTile, WorldTileEntities},
+};
+
+const DASH_LENGTH: f32 = 8.;
+const GAP_LENGTH: f32 = 6.;
+/// How fast the dashes crawl toward the end of the line, in pixels per second.
+const DASH_SPEED: f32 = 12.;
+/// The dashes of the trade routes are paler than the ones of the unit paths, so they are told apart.
+const TRADE_ROUTE_ALPHA: f32 = 0.6;
+
+/// Which routes the overlay shows.
+#[derive(Resource)]
+pub struct RouteOverlay {
+    pub trade_routes: bool,
+    pub unit_paths: bool,
+}
+
+impl Default for RouteOverlay {
+    fn default() -> Self {
+        Self {
+            trade_routes: false,
+            unit_paths: true,
+        }
+    }
+}
+
+pub struct RouteOverlayPlugin;
+
+impl Plugin for RouteOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RouteOverlay>().add_systems(
+            Update,
+            draw_routes.run_if(
+                in_state(AppState::GameStart)
+                    .and(resource_exists::<TileMapResource>)
+                    .and(resource_exists::<WorldTileEntities>)
+                    .and(resource_exists::<VisibilityLayer>),
+            ),
+        );
+    }
+}
+
+/// Draw a dashed line through `points`, with the dashes shifted `phase` pixels along the line.
+fn dashed_line(gizmos: &mut Gizmos, points: &[Vec2], phase: f32, color: Color) {
+    let period = DASH_LENGTH + GAP_LENGTH;
+    // The distance along the line to the start of the segment.
+    let mut distance = 0.;
+    for segment in points.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        let length = start.distance(end);
+        let direction = (end - start).normalize_or_zero();
+        // The last dash starting before the segment, which may reach into it.
+        let mut dash_start = ((distance - phase) / period).floor() * period + phase;
+        while dash_start < distance + length {
+            let from = dash_start.max(distance) - distance;
+            let to = (dash_start + DASH_LENGTH).min(distance + length) - distance;
+            if to > from {
+                gizmos.line_2d(start + direction * from, start + direction * to, color);
+            }
+            dash_start += period;
+        }
+        distance += length;
+    }
+}
+
+fn draw_routes(
+    mut gizmos: Gizmos,
+    time: Res<Time>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    route_overlay: Res<RouteOverlay>,
+    city_connections: Res<CityConnections>,
+    visibility_layer: Res<VisibilityLayer>,
+    player_civilization: Res<PlayerCivilization>,
+    tile_entities: Res<WorldTileEntities>,
+    query_unit: Query<(&MapUnit, &MovePath, &Owner)>,
+    query_tile: Query<(&GlobalTransform, &InheritedVisibility), With<WorldTile>>,
+) {
+    if !route_overlay.trade_routes && !route_overlay.unit_paths {
+        return;
+    }
+
+    let player = player_civilization.0;
+    let grid = map.0.world_grid.grid;
+    // Two neighboring tiles drawn farther apart than this are on both sides of the wrapping edge of the map.
+    let max_step = Vec2::from(grid.layout.size).max_element() * 4.;
+    let phase = (time.elapsed_secs() * DASH_SPEED).rem_euclid(DASH_LENGTH + GAP_LENGTH);
+    let tile_position = |tile: Tile| {
+        if !visibility_layer.is_explored(player, tile) {
+            return None;
+        }
+        let (transform, visibility) = query_tile.get(*tile_entities.0.get(&tile)?).ok()?;
+        visibility.get().then(|| transform.translation().truncate())
+    };
+    let draw_route = |gizmos: &mut Gizmos, tiles: &[Tile], color: Color| {
+        // The route is cut where it leaves the tiles drawn or wraps around the map.
+        let mut points: Vec<Vec2> = Vec::new();
+        for position in tiles.iter().map(|&tile| tile_position(tile)) {
+            match position {
+                Some(position)
+                    if points
+                        .last()
+                        .is_none_or(|last| last.distance(position) <= max_step) =>
+                {
+                    points.push(position);
+                }
+                _ => {
+                    dashed_line(gizmos, &points, phase, color);
+                    points = position.into_iter().collect();
+                }
+            }
+        }
+        dashed_line(gizmos, &points, phase, color);
+    };
+    let nation_color = |nation: Nation| {
+        accessibility_settings
+            .player_colors(nation, &ruleset.0)
+            .outer()
+    };
+
+    if route_overlay.trade_routes {
+        for route in city_connections.routes() {
+            draw_route(
+                &mut gizmos,
+                &route.tiles,
+                nation_color(route.nation).with_alpha(TRADE_ROUTE_ALPHA),
+            );
+        }
+    }
+    if route_overlay.unit_paths {
+        for (map_unit, move_path, owner) in query_unit.iter() {
+            if owner.nation() != player || move_path.0.is_empty() {
+                continue;
+            }
+            let tiles: Vec<Tile> = std::iter::once(map_unit.tile)
+                .chain(move_path.0.iter().copied())
+                .collect();
+            draw_route(&mut gizmos, &tiles, nation_color(owner.nation()));
+        }
+    }
+}
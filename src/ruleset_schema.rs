@@ -0,0 +1,75 @@
+//! The versions of the format of the ruleset files, so the mods keep working as the format changes.
+//!
+//! A ruleset file is either a list of entries, in the format of version 1, or an object with the version of its
+//! format and its entries:
+//!
+//! ```json
+//! {
+//!     "schemaVersion": 1,
+//!     "entries": [
+//!         { "name": "Warrior", ... }
+//!     ]
+//! }
+//! ```
+//!
+//! The files of an older version are upgraded to [`SCHEMA_VERSION`] when they are read, by the [`MIGRATIONS`] from
+//! their version. A change of the format bumps [`SCHEMA_VERSION`] and adds the migration upgrading the files of the
+//! previous version, so the files never need to be rewritten by hand.
+
+use std::io;
+
+use serde_json::Value;
+
+/// The version of the format of the ruleset files read by the game.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The field of a ruleset file holding the version of its format.
+const SCHEMA_VERSION_FIELD: &str = "schemaVersion";
+/// The field of a ruleset file with a version holding its entries.
+const ENTRIES_FIELD: &str = "entries";
+
+/// Upgrade the entries of a ruleset file, by file name, from one version to the next.
+type Migration = fn(file: &str, entries: &mut Vec<Value>);
+
+/// The migrations in order: the first one upgrades the files of version 1 to version 2, and so on.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize - 1] = [];
+
+/// The entries of the ruleset file `file`, upgraded to [`SCHEMA_VERSION`].
+///
+/// A file made for a newer version of the game is an error: its entries may not mean what they say in our format.
+pub fn upgraded_entries(content: Value, file: &str) -> io::Result<Vec<Value>> {
+    let (version, mut entries) = match content {
+        Value::Array(entries) => (1, entries),
+        Value::Object(mut object) => {
+            let version = object
+                .get(SCHEMA_VERSION_FIELD)
+                .and_then(Value::as_u64)
+                .and_then(|version| u32::try_from(version).ok())
+                .filter(|&version| version >= 1)
+                .ok_or_else(|| {
+                    io::Error::other(format!("{file}: {SCHEMA_VERSION_FIELD} isn't a version"))
+                })?;
+            let Some(Value::Array(entries)) = object.remove(ENTRIES_FIELD) else {
+                return Err(io::Error::other(format!(
+                    "{file}: {ENTRIES_FIELD} isn't a list"
+                )));
+            };
+            (version, entries)
+        }
+        _ => {
+            return Err(io::Error::other(format!(
+                "{file}: the file is neither a list nor an object with {SCHEMA_VERSION_FIELD}"
+            )));
+        }
+    };
+
+    if version > SCHEMA_VERSION {
+        return Err(io::Error::other(format!(
+            "{file}: the file is in version {version} of the format, newer than version {SCHEMA_VERSION}"
+        )));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(file, &mut entries);
+    }
+    Ok(entries)
+}
@@ -0,0 +1,258 @@
+//! Checking the references between the entries of the ruleset files once they are loaded, e.g. a feature occurring on
+//! a base terrain which doesn't exist, a technology requiring a missing technology or a wonder which must be next to
+//! an unknown terrain. The systems look the entries up by name, e.g. `ruleset.features["Forest"]`, and would panic
+//! in the middle of a game on a broken reference.
+//!
+//! The references are checked in the ruleset files, so each broken reference is reported with its file, its entry and
+//! its field, see [`RulesetDiagnostic`]. A mod adding broken references is left out, see [`ModList::ruleset`].
+//!
+//! [`ModList::ruleset`]: crate::mod_manager::ModList::ruleset
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::{
+    mod_manager::{MOD_FILES, read_mod_file},
+    ruleset_schema::upgraded_entries,
+};
+
+/// The folder of the files of the base ruleset.
+pub const BASE_RULESET_PATH: &str = "src/jsons/Civ V - Gods & Kings";
+/// The file of the technologies, whose entries are grouped into columns.
+const TECHNOLOGIES_FILE: &str = "Techs.json";
+
+/// The filters of the `Must be next to [terrainFilter]` and `Must be on [terrainFilter]` uniques which aren't a
+/// terrain name, see [`crate::effect::matches_terrain_filter`].
+const TERRAIN_FILTERS: [&str; 4] = ["Land", "Water", "River", "Fresh water"];
+
+/// The fields referencing an entry of another file: the file of the entry, the field and the file it references.
+/// A field is either a name or a list of names.
+const REFERENCES: [(&str, &str, &str); 30] = [
+    ("Features.json", "occursOnType", "TerrainTypes.json"),
+    ("Features.json", "occursOnBase", "BaseTerrains.json"),
+    ("NaturalWonders.json", "occursOnType", "TerrainTypes.json"),
+    ("NaturalWonders.json", "occursOnBase", "BaseTerrains.json"),
+    ("NaturalWonders.json", "turnsIntoType", "TerrainTypes.json"),
+    ("NaturalWonders.json", "turnsIntoBase", "BaseTerrains.json"),
+    (
+        "TileResources.json",
+        "canBeFoundOnType",
+        "TerrainTypes.json",
+    ),
+    (
+        "TileResources.json",
+        "canBeFoundOnBase",
+        "BaseTerrains.json",
+    ),
+    ("TileResources.json", "canBeFoundOnFeature", "Features.json"),
+    ("TileResources.json", "improvement", "TileImprovements.json"),
+    ("TileResources.json", "revealedBy", TECHNOLOGIES_FILE),
+    (
+        "TileImprovements.json",
+        "canBeBuiltOnType",
+        "TerrainTypes.json",
+    ),
+    (
+        "TileImprovements.json",
+        "canBeBuiltOnBase",
+        "BaseTerrains.json",
+    ),
+    (
+        "TileImprovements.json",
+        "canBeBuiltOnFeature",
+        "Features.json",
+    ),
+    ("TileImprovements.json", "techRequired", TECHNOLOGIES_FILE),
+    ("TileImprovements.json", "uniqueTo", "Nations.json"),
+    (TECHNOLOGIES_FILE, "prerequisites", TECHNOLOGIES_FILE),
+    (TECHNOLOGIES_FILE, "era", "Eras.json"),
+    ("Units.json", "unitType", "UnitTypes.json"),
+    ("Units.json", "requiredTech", TECHNOLOGIES_FILE),
+    ("Units.json", "obsoleteTech", TECHNOLOGIES_FILE),
+    ("Units.json", "upgradesTo", "Units.json"),
+    ("Units.json", "replaces", "Units.json"),
+    ("Units.json", "uniqueTo", "Nations.json"),
+    ("Units.json", "requiredResource", "TileResources.json"),
+    ("Buildings.json", "requiredTech", TECHNOLOGIES_FILE),
+    ("Buildings.json", "requiredBuilding", "Buildings.json"),
+    ("Buildings.json", "replaces", "Buildings.json"),
+    ("Buildings.json", "uniqueTo", "Nations.json"),
+    ("Eras.json", "startingMilitaryUnit", "Units.json"),
+];
+
+/// A broken reference of the ruleset.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RulesetDiagnostic {
+    /// The file the entry with the broken reference comes from.
+    pub path: PathBuf,
+    pub entry: String,
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for RulesetDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}: {}",
+            self.path.display(),
+            self.entry,
+            self.field,
+            self.message
+        )
+    }
+}
+
+/// The entries of the ruleset files by file name and by entry name, with the file each entry comes from.
+#[derive(Clone, Default)]
+pub struct RulesetFiles(HashMap<String, BTreeMap<String, (PathBuf, Value)>>);
+
+impl RulesetFiles {
+    /// The files of the base ruleset.
+    pub fn read_base() -> io::Result<Self> {
+        let folder = Path::new(BASE_RULESET_PATH);
+        let mut files = HashMap::new();
+        for file in MOD_FILES.into_iter().chain([TECHNOLOGIES_FILE]) {
+            if let Some(entries) = read_mod_file(&folder.join(file))? {
+                files.insert(file, upgraded_entries(entries, file)?);
+            }
+        }
+        let mut ruleset_files = Self::default();
+        ruleset_files.add(folder, files);
+        Ok(ruleset_files)
+    }
+
+    /// Add the files read from `folder`, by file name, as a mod is merged: an entry overrides the entry of the same
+    /// name, and an entry with `"delete": true` removes it.
+    pub fn add(&mut self, folder: &Path, files: HashMap<&'static str, Vec<Value>>) {
+        for (file, entries) in files {
+            let entries = if file == TECHNOLOGIES_FILE {
+                technologies(entries)
+            } else {
+                entries
+            };
+            let path = folder.join(file);
+            let file_entries = self.0.entry(file.to_owned()).or_default();
+            for entry in entries {
+                let Some(name) = entry.get("name").and_then(Value::as_str) else {
+                    continue;
+                };
+                if entry.get("delete") == Some(&Value::Bool(true)) {
+                    file_entries.remove(name);
+                } else {
+                    file_entries.insert(name.to_owned(), (path.clone(), entry));
+                }
+            }
+        }
+    }
+
+    /// The broken references of the ruleset.
+    pub fn validate(&self) -> Vec<RulesetDiagnostic> {
+        let mut diagnostics = Vec::new();
+        for (file, field, referenced_file) in REFERENCES {
+            for (name, (path, entry)) in self.entries(file) {
+                for reference in names(entry.get(field)) {
+                    if !self.has(referenced_file, reference) {
+                        diagnostics.push(RulesetDiagnostic {
+                            path: path.clone(),
+                            entry: name.clone(),
+                            field: field.to_owned(),
+                            message: format!("{reference} isn't in {referenced_file}"),
+                        });
+                    }
+                }
+            }
+        }
+
+        for (name, (path, entry)) in self.entries("Buildings.json") {
+            for unique in names(entry.get("uniques")) {
+                let broken_reference = if let Some(filter) = unique
+                    .strip_prefix("Must be next to [")
+                    .or_else(|| unique.strip_prefix("Must be on ["))
+                    .and_then(|rest| rest.split_once(']'))
+                    .map(|(filter, _)| filter)
+                {
+                    (!self.is_terrain_filter(filter))
+                        .then(|| format!("{filter} isn't a terrain filter"))
+                } else if let Some(building) = unique
+                    .strip_prefix("Requires a [")
+                    .and_then(|rest| rest.strip_suffix("] in all cities"))
+                {
+                    (!self.has("Buildings.json", building))
+                        .then(|| format!("{building} isn't in Buildings.json"))
+                } else {
+                    None
+                };
+                if let Some(message) = broken_reference {
+                    diagnostics.push(RulesetDiagnostic {
+                        path: path.clone(),
+                        entry: name.clone(),
+                        field: format!("uniques: {unique}"),
+                        message,
+                    });
+                }
+            }
+        }
+        diagnostics
+    }
+
+    fn entries(&self, file: &str) -> impl Iterator<Item = (&String, &(PathBuf, Value))> {
+        self.0.get(file).into_iter().flatten()
+    }
+
+    fn has(&self, file: &str, name: &str) -> bool {
+        self.0
+            .get(file)
+            .is_some_and(|entries| entries.contains_key(name))
+    }
+
+    /// Whether a terrain filter of a unique is a terrain name or one of the [`TERRAIN_FILTERS`].
+    fn is_terrain_filter(&self, filter: &str) -> bool {
+        TERRAIN_FILTERS.contains(&filter)
+            || [
+                "TerrainTypes.json",
+                "BaseTerrains.json",
+                "Features.json",
+                "NaturalWonders.json",
+            ]
+            .into_iter()
+            .any(|file| self.has(file, filter))
+    }
+}
+
+/// The technologies of the columns of the technology file, with the era of their column.
+fn technologies(columns: Vec<Value>) -> Vec<Value> {
+    columns
+        .into_iter()
+        .flat_map(|mut column| {
+            let era = column.get("era").cloned();
+            let technologies = match column.get_mut("techs").map(Value::take) {
+                Some(Value::Array(technologies)) => technologies,
+                _ => Vec::new(),
+            };
+            technologies.into_iter().map(move |mut technology| {
+                if let (Some(era), Some(technology)) = (&era, technology.as_object_mut()) {
+                    technology.insert("era".to_owned(), era.clone());
+                }
+                technology
+            })
+        })
+        .collect()
+}
+
+/// The names of a field which is a name or a list of names. An empty name is no reference.
+fn names(field: Option<&Value>) -> Vec<&str> {
+    match field {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+    .into_iter()
+    .filter(|name| !name.is_empty())
+    .collect()
+}
@@ -0,0 +1,526 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    hash::Hash,
+    io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use civ_map_generator::{nation::Nation, tile::Tile, tile_map::TileMap};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    accessibility::AccessibilitySettings,
+    assets::{AppState, MaterialResource},
+    barbarian::{BarbarianEncampment, encampment_bundle},
+    city::{City, city_bundle},
+    city_state::CityStates,
+    civilization::{Civilizations, Difficulty, PlayerCivilization},
+    custom_material::ColorReplaceMaterial,
+    deal::Deals,
+    demographics::History,
+    diplomacy::DiplomacyState,
+    diplomacy_screen::{DiplomacyScreen, DiplomacyScreenButton},
+    end_turn_button::EndTurnButton,
+    improvement::TileImprovementLayer,
+    map_lens::LensToolbar,
+    notification::Notifications,
+    random_event::PendingEvents,
+    rng::GameRng,
+    scenario::ActiveScenario,
+    settings::UserSettings,
+    technology::{ResearchButtonText, TechTreeScreen},
+    turn::{TurnManager, TurnStarted},
+    unit::{MapUnit, MovePath},
+    unit_component::{Health, Movement, Owner, Unit, UnitOrder},
+    unit_sprite::{UnitMeshes, unit_bundle},
+    visibility::VisibilityLayer,
+    world_map::{MovePathPreview, SelectedUnit, WorldTile, WorldTileEntities},
+    yields::TileYields,
+};
+
+/// The version of the save format. Increase it whenever the saved data changes,
+/// so old saves are refused instead of being loaded wrong.
+const SAVE_VERSION: u32 = 11;
+/// The save written with F5 and loaded with F9.
+pub const QUICK_SAVE_PATH: &str = "saves/quicksave.json";
+/// The save written every few turns, see [`UserSettings::autosave_turns`].
+pub const AUTOSAVE_PATH: &str = "saves/autosave.json";
+
+/// (De)serialize a map as a list of key-value pairs, for the maps whose keys can't be JSON object keys, e.g. tuples.
+pub mod map_as_pairs {
+    use super::{Deserialize, Deserializer, Hash, HashMap, Serialize, Serializer};
+
+    pub fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Vec::<(K, V)>::deserialize(deserializer).map(|pairs| pairs.into_iter().collect())
+    }
+}
+
+/// A unit in a save file.
+#[derive(Serialize, Deserialize)]
+struct SavedUnit {
+    name: String,
+    owner: Owner,
+    tile: Tile,
+    health: Health,
+    movement: Movement,
+    move_path: MovePath,
+    order: UnitOrder,
+}
+
+/// Everything needed to continue a game.
+///
+/// The tile yields are not saved, they are computed again from the map and the improvements.
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub tile_map: TileMap,
+    pub turn_manager: TurnManager,
+    pub rng: GameRng,
+    pub player_civilization: Nation,
+    pub difficulty: String,
+    pub civilizations: Civilizations,
+    pub diplomacy: DiplomacyState,
+    pub deals: Deals,
+    pub city_states: CityStates,
+    pub visibility_layer: VisibilityLayer,
+    pub improvement_layer: TileImprovementLayer,
+    pub history: History,
+    pub notifications: Notifications,
+    pub pending_events: PendingEvents,
+    /// The scenario being played, `None` for a game on a generated map.
+    pub scenario: Option<ActiveScenario>,
+    cities: Vec<(City, Owner)>,
+    units: Vec<SavedUnit>,
+    encampments: Vec<BarbarianEncampment>,
+}
+
+/// Only the version of a save file, read first so saves of another version are refused with a clear error.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The save was written by a version of the game with another save format.
+    Version {
+        found: u32,
+    },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "{error}"),
+            SaveError::Json(error) => write!(f, "invalid save file: {error}"),
+            SaveError::Version { found } => write!(
+                f,
+                "the save file has version {found}, but only version {SAVE_VERSION} is supported"
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Json(error)
+    }
+}
+
+pub fn write_save_file(path: &Path, save_file: &SaveFile) -> Result<(), SaveError> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, serde_json::to_string(save_file)?)?;
+    Ok(())
+}
+
+pub fn read_save_file(path: &Path) -> Result<SaveFile, SaveError> {
+    let content = fs::read_to_string(path)?;
+    let header: SaveHeader = serde_json::from_str(&content)?;
+    if header.version != SAVE_VERSION {
+        return Err(SaveError::Version {
+            found: header.version,
+        });
+    }
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Request to save the current game to `path`.
+#[derive(Message)]
+pub struct SaveGame {
+    pub path: PathBuf,
+}
+
+/// Request to replace the current game with the game saved in `path`.
+#[derive(Message)]
+pub struct LoadGame {
+    pub path: PathBuf,
+}
+
+/// The save being loaded, from the [`LoadGame`] request until it is restored.
+#[derive(Resource)]
+pub struct PendingSave(SaveFile);
+
+/// The resources holding the state of the game, saved together.
+#[derive(SystemParam)]
+struct GameResources<'w> {
+    map: Res<'w, TileMapResource>,
+    turn_manager: Res<'w, TurnManager>,
+    rng: Res<'w, GameRng>,
+    player_civilization: Res<'w, PlayerCivilization>,
+    difficulty: Res<'w, Difficulty>,
+    civilizations: Res<'w, Civilizations>,
+    diplomacy: Res<'w, DiplomacyState>,
+    deals: Res<'w, Deals>,
+    city_states: Res<'w, CityStates>,
+    visibility_layer: Res<'w, VisibilityLayer>,
+    improvement_layer: Res<'w, TileImprovementLayer>,
+    history: Res<'w, History>,
+    notifications: Res<'w, Notifications>,
+    pending_events: Res<'w, PendingEvents>,
+    scenario: Option<Res<'w, ActiveScenario>>,
+}
+
+/// The whole state of the current game, to build a [`SaveFile`] from.
+#[derive(SystemParam)]
+pub struct GameSnapshot<'w, 's> {
+    game: GameResources<'w>,
+    query_city: Query<'w, 's, (&'static City, &'static Owner)>,
+    query_unit: Query<
+        'w,
+        's,
+        (
+            &'static Unit,
+            &'static Owner,
+            &'static MapUnit,
+            &'static Health,
+            &'static Movement,
+            &'static MovePath,
+            &'static UnitOrder,
+        ),
+    >,
+    query_encampment: Query<'w, 's, &'static BarbarianEncampment>,
+}
+
+impl GameSnapshot<'_, '_> {
+    pub fn save_file(&self) -> SaveFile {
+        let game = &self.game;
+
+        // Sort the entities by tile, so the same game always gives the same save.
+        let mut cities: Vec<_> = self
+            .query_city
+            .iter()
+            .map(|(city, &owner)| (city.clone(), owner))
+            .collect();
+        cities.sort_by_key(|(city, _)| city.tile.index());
+
+        let mut units: Vec<_> = self
+            .query_unit
+            .iter()
+            .map(
+                |(unit, &owner, map_unit, &health, &movement, move_path, &order)| SavedUnit {
+                    name: unit.name().to_owned(),
+                    owner,
+                    tile: map_unit.tile,
+                    health,
+                    movement,
+                    move_path: move_path.clone(),
+                    order,
+                },
+            )
+            .collect();
+        units.sort_by(|a, b| {
+            a.tile
+                .index()
+                .cmp(&b.tile.index())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let mut encampments: Vec<_> = self.query_encampment.iter().cloned().collect();
+        encampments.sort_by_key(|encampment| encampment.tile.index());
+
+        SaveFile {
+            version: SAVE_VERSION,
+            tile_map: game.map.0.clone(),
+            turn_manager: game.turn_manager.clone(),
+            rng: game.rng.clone(),
+            player_civilization: game.player_civilization.0,
+            difficulty: game.difficulty.0.clone(),
+            civilizations: game.civilizations.clone(),
+            diplomacy: game.diplomacy.clone(),
+            deals: game.deals.clone(),
+            city_states: game.city_states.clone(),
+            visibility_layer: game.visibility_layer.clone(),
+            improvement_layer: game.improvement_layer.clone(),
+            history: game.history.clone(),
+            notifications: game.notifications.clone(),
+            pending_events: game.pending_events.clone(),
+            scenario: game.scenario.as_deref().cloned(),
+            cities,
+            units,
+            encampments,
+        }
+    }
+}
+
+/// The entities of the current game, despawned when the game is left, e.g. when a save is loaded.
+///
+/// The units, the cities and everything else on the map are children of the [`WorldTile`]s.
+pub type GameEntities = Or<(
+    With<WorldTile>,
+    With<ResearchButtonText>,
+    With<TechTreeScreen>,
+    With<DiplomacyScreenButton>,
+    With<DiplomacyScreen>,
+    With<EndTurnButton>,
+    With<LensToolbar>,
+)>;
+
+/// Leave the current game for the main menu: despawn its world and reset its state,
+/// so the next game starts from scratch.
+pub fn leave_game(
+    commands: &mut Commands,
+    next_state: &mut NextState<AppState>,
+    query_world: &Query<Entity, GameEntities>,
+) {
+    for entity in query_world.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<WorldTileEntities>();
+    commands.remove_resource::<TileYields>();
+    commands.remove_resource::<TileMapResource>();
+    commands.remove_resource::<ActiveScenario>();
+    commands.insert_resource(SelectedUnit::default());
+    commands.insert_resource(MovePathPreview::default());
+    // The other resources of the game are set up again when the next game starts.
+    commands.insert_resource(TurnManager::default());
+    commands.insert_resource(DiplomacyState::default());
+    commands.insert_resource(Deals::default());
+    commands.insert_resource(History::default());
+    commands.insert_resource(Notifications::default());
+    commands.insert_resource(PendingEvents::default());
+    next_state.set(AppState::MainMenu);
+}
+
+/// Leave the current game and replace it with `save_file`.
+///
+/// The saved game is restored a few frames later, once its world is set up, and [`PendingSave`] exists until then.
+pub fn load_save_file(
+    commands: &mut Commands,
+    next_state: &mut NextState<AppState>,
+    save_file: SaveFile,
+) {
+    commands.insert_resource(PendingSave(save_file));
+    next_state.set(AppState::LoadingSave);
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SaveGame>()
+            .add_message::<LoadGame>()
+            .add_systems(
+                Update,
+                (
+                    save_or_load_on_key,
+                    autosave.run_if(on_message::<TurnStarted>),
+                    save_game.run_if(on_message::<SaveGame>),
+                    load_game.run_if(on_message::<LoadGame>),
+                    // The world of the saved map is set up when the world tiles are added again.
+                    restore_game.run_if(
+                        resource_exists::<PendingSave>.and(resource_added::<WorldTileEntities>),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(OnEnter(AppState::LoadingSave), start_loading_save);
+    }
+}
+
+fn save_or_load_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut save_game: MessageWriter<SaveGame>,
+    mut load_game: MessageWriter<LoadGame>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        save_game.write(SaveGame {
+            path: QUICK_SAVE_PATH.into(),
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        load_game.write(LoadGame {
+            path: QUICK_SAVE_PATH.into(),
+        });
+    }
+}
+
+/// Save the game at the start of every turn divisible by the autosave frequency of the player.
+fn autosave(
+    mut turn_started: MessageReader<TurnStarted>,
+    user_settings: Res<UserSettings>,
+    mut save_game: MessageWriter<SaveGame>,
+) {
+    let autosave_turns = user_settings.autosave_turns;
+    if turn_started
+        .read()
+        .any(|&TurnStarted { turn }| autosave_turns > 0 && turn % autosave_turns == 0)
+    {
+        save_game.write(SaveGame {
+            path: AUTOSAVE_PATH.into(),
+        });
+    }
+}
+
+fn save_game(mut save_game: MessageReader<SaveGame>, snapshot: GameSnapshot) {
+    for SaveGame { path } in save_game.read() {
+        match write_save_file(path, &snapshot.save_file()) {
+            Ok(()) => info!("Saved the game to {}", path.display()),
+            Err(error) => error!("Can't save the game to {}: {error}", path.display()),
+        }
+    }
+}
+
+/// Read the requested save and leave the current game. The current game goes on if the save can't be read.
+fn load_game(
+    mut commands: Commands,
+    mut load_game: MessageReader<LoadGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(LoadGame { path }) = load_game.read().last() else {
+        return;
+    };
+    match read_save_file(path) {
+        Ok(save_file) => load_save_file(&mut commands, &mut next_state, save_file),
+        Err(error) => error!("Can't load the game from {}: {error}", path.display()),
+    }
+}
+
+/// Despawn the world of the current game and replace its map with the saved one,
+/// then enter [`AppState::GameStart`] again so the world is set up from the saved map.
+///
+/// The resources removed here are created again from the new map. The rest of the save is restored by [`restore_game`].
+fn start_loading_save(
+    mut commands: Commands,
+    pending_save: Res<PendingSave>,
+    mut next_state: ResMut<NextState<AppState>>,
+    query_world: Query<Entity, GameEntities>,
+) {
+    for entity in query_world.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<WorldTileEntities>();
+    commands.remove_resource::<TileYields>();
+    commands.insert_resource(TileMapResource(pending_save.0.tile_map.clone()));
+    commands.insert_resource(SelectedUnit::default());
+    commands.insert_resource(MovePathPreview::default());
+    next_state.set(AppState::GameStart);
+}
+
+/// Replace the state of the new game set up from the saved map with the saved state.
+///
+/// The starting units of the new game are replaced by the saved units, and the tile yields are computed again
+/// with the saved improvements.
+fn restore_game(
+    mut commands: Commands,
+    pending_save: Res<PendingSave>,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    materials: Res<MaterialResource>,
+    unit_meshes: Res<UnitMeshes>,
+    tile_entities: Res<WorldTileEntities>,
+    mut custom_materials: ResMut<Assets<ColorReplaceMaterial>>,
+    query_unit: Query<Entity, With<Unit>>,
+) {
+    let save_file = &pending_save.0;
+    let ruleset = &ruleset.0;
+    let grid = save_file.tile_map.world_grid.grid;
+
+    commands.insert_resource(save_file.turn_manager.clone());
+    commands.insert_resource(save_file.rng.clone());
+    commands.insert_resource(PlayerCivilization(save_file.player_civilization));
+    commands.insert_resource(Difficulty(save_file.difficulty.clone()));
+    commands.insert_resource(save_file.civilizations.clone());
+    commands.insert_resource(save_file.diplomacy.clone());
+    commands.insert_resource(save_file.deals.clone());
+    commands.insert_resource(save_file.city_states.clone());
+    commands.insert_resource(save_file.visibility_layer.clone());
+    commands.insert_resource(save_file.improvement_layer.clone());
+    commands.insert_resource(save_file.history.clone());
+    commands.insert_resource(save_file.notifications.clone());
+    commands.insert_resource(save_file.pending_events.clone());
+    match &save_file.scenario {
+        Some(scenario) => commands.insert_resource(scenario.clone()),
+        None => commands.remove_resource::<ActiveScenario>(),
+    }
+    commands.remove_resource::<TileYields>();
+
+    for unit in query_unit.iter() {
+        commands.entity(unit).despawn();
+    }
+
+    for (city, owner) in &save_file.cities {
+        commands.spawn((
+            city_bundle(city.clone(), *owner, grid, ruleset, &accessibility_settings),
+            ChildOf(tile_entities.0[&city.tile]),
+        ));
+    }
+
+    for unit in &save_file.units {
+        commands
+            .spawn((
+                unit_bundle(
+                    &unit.name,
+                    unit.owner,
+                    unit.tile,
+                    ruleset,
+                    &accessibility_settings,
+                    &unit_meshes,
+                    &mut custom_materials,
+                    &materials,
+                ),
+                ChildOf(tile_entities.0[&unit.tile]),
+            ))
+            .insert((
+                unit.health,
+                unit.movement,
+                unit.move_path.clone(),
+                unit.order,
+            ));
+    }
+
+    for encampment in &save_file.encampments {
+        commands
+            .entity(tile_entities.0[&encampment.tile])
+            .with_child(encampment_bundle(encampment.clone(), grid, &materials));
+    }
+
+    commands.remove_resource::<PendingSave>();
+}
@@ -0,0 +1,358 @@
+//! Scenarios: games played on a prepared map with predefined civilizations, cities and units,
+//! instead of a randomly generated map.
+//!
+//! A scenario is a JSON file started from the command line with `--scenario <path>`. Its map uses the format
+//! of the map of a save file, so a map can be copied from a save. The player civilization and the difficulty
+//! of a scenario are locked, and a scenario may replace the usual victory conditions with its own.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, tile::Tile, tile_map::TileMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    accessibility::AccessibilitySettings,
+    assets::{AppState, MaterialResource},
+    city::{City, city_bundle},
+    civilization::{Civilizations, Difficulty},
+    custom_material::ColorReplaceMaterial,
+    technology::learn_technologies_with_prerequisites,
+    turn::{TurnManager, TurnProcessing, TurnSet},
+    unit_component::{Owner, Unit},
+    unit_sprite::{UnitMeshes, unit_bundle},
+    world_map::WorldTileEntities,
+    yields::TileYields,
+};
+
+/// A game prepared in advance, see the [module documentation](self).
+#[derive(Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub tile_map: TileMap,
+    /// The civilization of the player, one of the civilizations of the map.
+    pub player_civilization: Nation,
+    /// The difficulty of the scenario, the default difficulty if it is not given.
+    #[serde(default)]
+    pub difficulty: Option<String>,
+    #[serde(default)]
+    pub civilizations: Vec<ScenarioCivilization>,
+    /// The cities, the first city of every nation being its capital.
+    #[serde(default)]
+    pub cities: Vec<ScenarioCity>,
+    /// The units, replacing the starting units of the map. Without units, the usual starting units are kept.
+    #[serde(default)]
+    pub units: Vec<ScenarioUnit>,
+    /// The victory conditions replacing the usual ones, if any.
+    #[serde(default)]
+    pub victory: Option<ScenarioVictory>,
+}
+
+/// What a civilization of the map starts with, in addition to the usual start.
+#[derive(Deserialize)]
+pub struct ScenarioCivilization {
+    pub nation: Nation,
+    #[serde(default)]
+    pub gold: f32,
+    /// Technologies known from the start with their prerequisites, in addition to the technologies without prerequisites.
+    #[serde(default)]
+    pub technologies: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ScenarioCity {
+    pub name: String,
+    pub owner: Owner,
+    pub tile: Tile,
+    pub population: u32,
+    #[serde(default)]
+    pub buildings: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ScenarioUnit {
+    pub name: String,
+    pub owner: Owner,
+    pub tile: Tile,
+}
+
+/// The victory conditions of a scenario. The scenario ends as soon as one of them is met.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScenarioVictory {
+    /// The civilization owning the cities on all these tiles wins.
+    #[serde(default)]
+    pub hold_cities: Vec<Tile>,
+    /// The scenario ends without a winner once this turn is over.
+    #[serde(default)]
+    pub turn_limit: Option<u32>,
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScenarioError::Io(error) => write!(f, "{error}"),
+            ScenarioError::Json(error) => write!(f, "invalid scenario file: {error}"),
+        }
+    }
+}
+
+impl From<io::Error> for ScenarioError {
+    fn from(error: io::Error) -> Self {
+        ScenarioError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ScenarioError {
+    fn from(error: serde_json::Error) -> Self {
+        ScenarioError::Json(error)
+    }
+}
+
+pub fn read_scenario_file(path: &Path) -> Result<Scenario, ScenarioError> {
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// The path of the scenario to play, from the command line: `--scenario <path>`.
+fn scenario_path_from_args(mut args: impl Iterator<Item = String>) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        if arg == "--scenario" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// The scenario being started, until its cities and units are placed on its map.
+#[derive(Resource)]
+pub struct PendingScenario(pub Scenario);
+
+/// The scenario being played, saved with the game.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct ActiveScenario {
+    pub name: String,
+    pub victory: Option<ScenarioVictory>,
+    /// Set once a victory condition is met, so the scenario ends only once.
+    pub ended: bool,
+}
+
+/// Written when the scenario ends, with the civilization which won it, if any.
+#[derive(Message)]
+pub struct ScenarioEnded {
+    pub scenario: String,
+    pub winner: Option<Nation>,
+}
+
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(path) = scenario_path_from_args(std::env::args().skip(1)) {
+            match read_scenario_file(&path) {
+                Ok(scenario) => {
+                    app.insert_resource(PendingScenario(scenario));
+                }
+                Err(error) => error!(
+                    "Can't load the scenario {}, a random map is generated instead: {error}",
+                    path.display()
+                ),
+            }
+        }
+        app.add_message::<ScenarioEnded>()
+            .add_systems(
+                OnEnter(AppState::MapGenerating),
+                start_scenario.run_if(resource_exists::<PendingScenario>),
+            )
+            .add_systems(
+                Update,
+                // The cities need the tile yields to assign their citizens.
+                apply_scenario
+                    .run_if(
+                        resource_exists::<PendingScenario>
+                            .and(resource_exists::<WorldTileEntities>)
+                            .and(resource_exists::<TileYields>),
+                    )
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                check_scenario_victory
+                    .in_set(TurnSet::Victory)
+                    .run_if(resource_exists::<ActiveScenario>),
+            );
+    }
+}
+
+/// Use the map of the scenario instead of generating one, and lock its difficulty.
+fn start_scenario(
+    mut commands: Commands,
+    pending_scenario: Res<PendingScenario>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let scenario = &pending_scenario.0;
+    commands.insert_resource(TileMapResource(scenario.tile_map.clone()));
+    if let Some(difficulty) = &scenario.difficulty {
+        commands.insert_resource(Difficulty(difficulty.clone()));
+    }
+    commands.insert_resource(ActiveScenario {
+        name: scenario.name.clone(),
+        victory: scenario.victory.clone(),
+        ended: false,
+    });
+    next_state.set(AppState::GameStart);
+}
+
+/// Replace the usual start of the game set up from the map of the scenario with the start of the scenario:
+/// the civilizations, the cities and the units. The player civilization is set up from the scenario
+/// with the other civilizations, see [`crate::civilization`].
+fn apply_scenario(
+    mut commands: Commands,
+    pending_scenario: Res<PendingScenario>,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    materials: Res<MaterialResource>,
+    unit_meshes: Res<UnitMeshes>,
+    tile_entities: Res<WorldTileEntities>,
+    tile_yields: Res<TileYields>,
+    mut civilizations: ResMut<Civilizations>,
+    mut custom_materials: ResMut<Assets<ColorReplaceMaterial>>,
+    query_unit: Query<Entity, With<Unit>>,
+) {
+    let scenario = &pending_scenario.0;
+    let ruleset = &ruleset.0;
+    let grid = scenario.tile_map.world_grid.grid;
+
+    for scenario_civilization in &scenario.civilizations {
+        let civilization = civilizations.get_mut(scenario_civilization.nation);
+        civilization.gold += scenario_civilization.gold;
+        learn_technologies_with_prerequisites(
+            &scenario_civilization.technologies,
+            civilization,
+            ruleset,
+        );
+    }
+
+    let mut cities: Vec<(City, Owner)> = Vec::new();
+    for scenario_city in &scenario.cities {
+        let nation = scenario_city.owner.nation();
+        let owned_tiles = scenario_city
+            .tile
+            .tiles_in_distance(1, grid)
+            .filter(|&tile| {
+                !cities
+                    .iter()
+                    .any(|(city, _)| city.owned_tiles.contains(&tile))
+            })
+            .collect();
+        let mut city = City {
+            name: scenario_city.name.clone(),
+            tile: scenario_city.tile,
+            population: scenario_city.population.max(1),
+            food_stored: 0.,
+            owned_tiles,
+            worked_tiles: Vec::new(),
+            locked_tiles: Vec::new(),
+            specialists: Vec::new(),
+            production: None,
+            production_stored: 0.,
+            buildings: scenario_city.buildings.clone(),
+            is_capital: !cities.iter().any(|(_, owner)| owner.nation() == nation),
+            border_culture: 0.,
+            damage: 0,
+            has_struck: false,
+            blockaded_tiles: Vec::new(),
+            is_puppet: false,
+        };
+        city.assign_citizens(grid, &tile_yields);
+        cities.push((city, scenario_city.owner));
+    }
+    for (city, owner) in cities {
+        let tile = city.tile;
+        commands.spawn((
+            city_bundle(city, owner, grid, ruleset, &accessibility_settings),
+            ChildOf(tile_entities.0[&tile]),
+        ));
+    }
+
+    if !scenario.units.is_empty() {
+        for unit in query_unit.iter() {
+            commands.entity(unit).despawn();
+        }
+        for unit in &scenario.units {
+            commands
+                .entity(tile_entities.0[&unit.tile])
+                .with_child(unit_bundle(
+                    &unit.name,
+                    unit.owner,
+                    unit.tile,
+                    ruleset,
+                    &accessibility_settings,
+                    &unit_meshes,
+                    &mut custom_materials,
+                    &materials,
+                ));
+        }
+    }
+
+    info!(
+        "Started the scenario {}: {}",
+        scenario.name, scenario.description
+    );
+    commands.remove_resource::<PendingScenario>();
+}
+
+/// End the scenario once a civilization holds all the cities of its victory conditions, or once its last turn is over.
+fn check_scenario_victory(
+    mut scenario_ended: MessageWriter<ScenarioEnded>,
+    turn_manager: Res<TurnManager>,
+    mut active_scenario: ResMut<ActiveScenario>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    if active_scenario.ended {
+        return;
+    }
+    let Some(victory) = &active_scenario.victory else {
+        return;
+    };
+
+    let holders: Vec<_> = victory
+        .hold_cities
+        .iter()
+        .map(|&tile| {
+            query_city
+                .iter()
+                .find(|(city, _)| city.tile == tile)
+                .and_then(|(_, owner)| match *owner {
+                    Owner::Civilization(nation) => Some(nation),
+                    _ => None,
+                })
+        })
+        .collect();
+    let winner = match holders.first() {
+        Some(&Some(nation)) if holders.iter().all(|&holder| holder == Some(nation)) => Some(nation),
+        _ => None,
+    };
+    let turn_limit_reached = victory
+        .turn_limit
+        .is_some_and(|turn_limit| turn_manager.turn >= turn_limit);
+
+    if winner.is_some() || turn_limit_reached {
+        active_scenario.ended = true;
+        scenario_ended.write(ScenarioEnded {
+            scenario: active_scenario.name.clone(),
+            winner,
+        });
+    }
+}
@@ -0,0 +1,153 @@
+//! Screenshots, saved as timestamped PNG files in [`SCREENSHOTS_PATH`]:
+//!
+//! - `F12` captures the window as it is shown, with the UI.
+//! - `Shift + F12` exports the area of the map seen by the main camera, without the UI, rendered to an image of
+//!   [`EXPORT_WIDTH`] x [`EXPORT_HEIGHT`] pixels whatever the size of the window.
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    asset::RenderAssetUsages,
+    camera::{RenderTarget, visibility::RenderLayers},
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::screenshot::{Screenshot, ScreenshotCaptured, save_to_disk},
+    },
+};
+
+use crate::{MainCamera, assets::AppState};
+
+/// The folder the screenshots are saved in.
+pub const SCREENSHOTS_PATH: &str = "screenshots";
+const EXPORT_WIDTH: u32 = 3840;
+const EXPORT_HEIGHT: u32 = 2160;
+
+/// The camera rendering the map to an image for an export, despawned once the image is saved.
+#[derive(Component)]
+struct ExportCamera {
+    image: Handle<Image>,
+    path: PathBuf,
+    /// Whether the camera has rendered the image once, so it can be captured.
+    rendered: bool,
+}
+
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (take_screenshot_on_key, capture_exported_area)
+                .chain()
+                .run_if(in_state(AppState::GameStart)),
+        );
+    }
+}
+
+/// The path of a new screenshot, e.g. `screenshots/map-1700000000.png`.
+fn screenshot_path(prefix: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    PathBuf::from(SCREENSHOTS_PATH).join(format!("{prefix}-{timestamp}.png"))
+}
+
+fn take_screenshot_on_key(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut images: ResMut<Assets<Image>>,
+    main_camera: Single<(&Transform, &Camera, &Projection), With<MainCamera>>,
+    query_export_camera: Query<(), With<ExportCamera>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F12) {
+        return;
+    }
+    if let Err(error) = fs::create_dir_all(SCREENSHOTS_PATH) {
+        error!("Can't create the folder of the screenshots {SCREENSHOTS_PATH}: {error}");
+        return;
+    }
+    if !keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        commands
+            .spawn(Screenshot::primary_window())
+            .observe(save_to_disk(screenshot_path("screenshot")));
+        return;
+    }
+    // One export at a time, the image is large.
+    if !query_export_camera.is_empty() {
+        return;
+    }
+
+    let (transform, camera, projection) = main_camera.into_inner();
+    let Projection::Orthographic(orthographic) = projection else {
+        return;
+    };
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+    // Only the tiles around the main camera are drawn, see `show_main_camera_area`, so the export fits in the area
+    // seen by the main camera.
+    let scale = orthographic.scale
+        * (viewport_size.x / EXPORT_WIDTH as f32).min(viewport_size.y / EXPORT_HEIGHT as f32);
+
+    let mut image = Image::new_uninit(
+        Extent3d {
+            width: EXPORT_WIDTH,
+            height: EXPORT_HEIGHT,
+            ..default()
+        },
+        TextureDimension::D2,
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::all(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_DST
+        | TextureUsages::COPY_SRC
+        | TextureUsages::RENDER_ATTACHMENT;
+    let image = images.add(image);
+
+    commands.spawn((
+        Camera2d,
+        Camera {
+            target: RenderTarget::Image(image.clone().into()),
+            order: -2,
+            ..default()
+        },
+        Projection::Orthographic(OrthographicProjection {
+            scale,
+            ..OrthographicProjection::default_2d()
+        }),
+        *transform,
+        RenderLayers::layer(0),
+        ExportCamera {
+            image,
+            path: screenshot_path("map"),
+            rendered: false,
+        },
+    ));
+}
+
+/// Save the image of the export camera once it has been rendered, then despawn the camera.
+fn capture_exported_area(
+    mut commands: Commands,
+    mut query_export_camera: Query<(Entity, &mut ExportCamera)>,
+) {
+    for (entity, mut export_camera) in query_export_camera.iter_mut() {
+        if !export_camera.rendered {
+            export_camera.rendered = true;
+            continue;
+        }
+        commands
+            .spawn(Screenshot::image(export_camera.image.clone()))
+            .observe(save_to_disk(export_camera.path.clone()))
+            .observe(move |_: On<ScreenshotCaptured>, mut commands: Commands| {
+                commands.entity(entity).despawn();
+            });
+        // Captured once only.
+        commands.entity(entity).remove::<ExportCamera>();
+    }
+}
@@ -0,0 +1,192 @@
+//! The settings of the player, kept between games in [`USER_SETTINGS_PATH`]: the size and the mode of the window,
+//! the volumes, the scale of the UI, the key bindings, how often the game is saved automatically and the mods.
+//!
+//! The file is read before the window is created, and written when the game closes. Meanwhile the settings follow
+//! the changes of the player, e.g. a resized window, a resolution chosen or a key bound in the options of the pause
+//! menu, see [`crate::pause_menu`].
+
+use std::{fs, io, path::Path};
+
+use bevy::{
+    prelude::*,
+    window::{MonitorSelection, PrimaryWindow, VideoModeSelection, WindowMode, WindowResolution},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{audio::AudioSettings, key_bindings::KeyBindings, mod_manager::ModList};
+
+/// The settings changed by the player, in the TOML format so they are easy to edit by hand.
+pub const USER_SETTINGS_PATH: &str = "settings/settings.toml";
+
+/// The resolutions offered in the options, in physical pixels.
+pub const RESOLUTIONS: [[u32; 2]; 6] = [
+    [1280, 720],
+    [1366, 768],
+    [1600, 900],
+    [1920, 1080],
+    [2560, 1440],
+    [3840, 2160],
+];
+
+/// How the window is shown.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    /// A window without decorations covering the whole monitor.
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub const ALL: [Self; 3] = [Self::Windowed, Self::Borderless, Self::Fullscreen];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Windowed => "Windowed",
+            Self::Borderless => "Borderless",
+            Self::Fullscreen => "Fullscreen",
+        }
+    }
+
+    pub fn window_mode(self) -> WindowMode {
+        match self {
+            Self::Windowed => WindowMode::Windowed,
+            Self::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+            Self::Fullscreen => {
+                WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current)
+            }
+        }
+    }
+
+    pub fn of_window_mode(window_mode: WindowMode) -> Self {
+        match window_mode {
+            WindowMode::Windowed => Self::Windowed,
+            WindowMode::BorderlessFullscreen(_) => Self::Borderless,
+            WindowMode::Fullscreen(..) => Self::Fullscreen,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// The width and the height of the window, in physical pixels. A fullscreen window covers the monitor instead,
+    /// and gets this size back in the windowed mode.
+    pub resolution: [u32; 2],
+    pub window_mode: WindowModeSetting,
+    /// The size of the UI relative to its size at scale 1.
+    pub ui_scale: f32,
+    /// The game is saved to [`crate::save::AUTOSAVE_PATH`] at the start of every turn divisible by this number,
+    /// and never with 0.
+    pub autosave_turns: u32,
+    pub audio: AudioSettings,
+    pub key_bindings: KeyBindings,
+    /// The mods in their load order, see [`crate::mod_manager`].
+    pub mods: ModList,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            resolution: [1280, 720],
+            window_mode: WindowModeSetting::default(),
+            ui_scale: 1.,
+            autosave_turns: 5,
+            audio: AudioSettings::default(),
+            key_bindings: KeyBindings::default(),
+            mods: ModList::default(),
+        }
+    }
+}
+
+impl UserSettings {
+    /// The settings saved by the last game, or the default settings if there are none.
+    pub fn load() -> Self {
+        fs::read_to_string(USER_SETTINGS_PATH)
+            .ok()
+            .and_then(|content| {
+                toml::from_str(&content)
+                    .inspect_err(|error| error!("Can't read the settings: {error}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    /// The primary window, with the size and the mode chosen by the player.
+    pub fn window(&self) -> Window {
+        let [width, height] = self.resolution;
+        Window {
+            title: "Civilization-Remastered".to_owned(),
+            resolution: WindowResolution::new(width, height),
+            mode: self.window_mode.window_mode(),
+            ..default()
+        }
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_user_settings)
+            .add_systems(Last, save_user_settings_on_exit);
+    }
+}
+
+/// Keep the settings in step with the window, the volumes, the scale of the UI, the key bindings and the mods.
+fn update_user_settings(
+    mut user_settings: ResMut<UserSettings>,
+    audio_settings: Res<AudioSettings>,
+    key_bindings: Res<KeyBindings>,
+    mod_list: Res<ModList>,
+    ui_scale: Res<UiScale>,
+    query_window: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+) {
+    if audio_settings.is_changed() {
+        user_settings.audio = *audio_settings;
+    }
+    if key_bindings.is_changed() {
+        user_settings.key_bindings = key_bindings.clone();
+    }
+    if mod_list.is_changed() {
+        user_settings.mods = mod_list.clone();
+    }
+    if ui_scale.is_changed() {
+        user_settings.ui_scale = ui_scale.0;
+    }
+    // The window changes whenever the cursor moves, so the settings are only changed with the size or the mode.
+    if let Ok(window) = query_window.single() {
+        let window_mode = WindowModeSetting::of_window_mode(window.mode);
+        let resolution = [
+            window.resolution.physical_width(),
+            window.resolution.physical_height(),
+        ];
+        // The size of a fullscreen window is the size of the monitor, the windowed size is kept for later.
+        if window_mode == WindowModeSetting::Windowed && user_settings.resolution != resolution {
+            user_settings.resolution = resolution;
+        }
+        if user_settings.window_mode != window_mode {
+            user_settings.window_mode = window_mode;
+        }
+    }
+}
+
+fn save_user_settings_on_exit(
+    mut app_exit: MessageReader<AppExit>,
+    user_settings: Res<UserSettings>,
+) {
+    if app_exit.read().count() > 0
+        && let Err(error) = user_settings.write(Path::new(USER_SETTINGS_PATH))
+    {
+        error!("Can't save the settings: {error}");
+    }
+}
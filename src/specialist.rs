@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use civ_map_generator::ruleset::Ruleset;
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    city::City,
+    yields::{TileYields, Yields},
+};
+
+impl City {
+    /// The specialist slots of the buildings of the city, by specialist name, e.g. 2 `Scientist` for a University.
+    pub fn specialist_slots(&self, ruleset: &Ruleset) -> HashMap<String, u32> {
+        let mut slots: HashMap<String, u32> = HashMap::new();
+        for building in &self.buildings {
+            for (specialist, &count) in &ruleset.buildings[building].specialist_slots {
+                *slots.entry(specialist.clone()).or_default() += count.max(0) as u32;
+            }
+        }
+        slots
+    }
+
+    /// The number of citizens working as `specialist`.
+    pub fn specialist_count(&self, specialist: &str) -> u32 {
+        self.specialists
+            .iter()
+            .filter(|name| *name == specialist)
+            .count() as u32
+    }
+
+    /// The yields of the specialists of the city.
+    pub fn specialist_yields(&self, ruleset: &Ruleset) -> Yields {
+        self.specialists
+            .iter()
+            .map(|specialist| {
+                let specialist = &ruleset.specialists[specialist];
+                Yields {
+                    food: specialist.food,
+                    production: specialist.production,
+                    gold: specialist.gold,
+                    science: specialist.science,
+                    culture: specialist.culture,
+                    faith: 0.,
+                }
+            })
+            .sum()
+    }
+}
+
+/// Request to let `count` citizens of the city work as `specialist` instead of working tiles.
+#[derive(Message)]
+pub struct SetSpecialists {
+    pub city: Entity,
+    pub specialist: String,
+    pub count: u32,
+}
+
+pub struct SpecialistPlugin;
+
+impl Plugin for SpecialistPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SetSpecialists>().add_systems(
+            Update,
+            set_specialists
+                .run_if(on_message::<SetSpecialists>)
+                .run_if(in_state(AppState::GameStart).and(resource_exists::<TileYields>)),
+        );
+    }
+}
+
+/// Change the specialists of the cities requested by [`SetSpecialists`] messages.
+///
+/// The count is limited by the slots of the buildings of the city and by its citizens,
+/// and the citizens left work tiles again.
+fn set_specialists(
+    mut set_specialists: MessageReader<SetSpecialists>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    mut query_city: Query<&mut City>,
+) {
+    let grid = map.0.world_grid.grid;
+    let ruleset = &ruleset.0;
+
+    for SetSpecialists {
+        city,
+        specialist,
+        count,
+    } in set_specialists.read()
+    {
+        let Ok(mut city) = query_city.get_mut(*city) else {
+            continue;
+        };
+
+        let slots = city
+            .specialist_slots(ruleset)
+            .get(specialist)
+            .copied()
+            .unwrap_or_default();
+        let other_specialists = city.specialists.len() as u32 - city.specialist_count(specialist);
+        let count = (*count)
+            .min(slots)
+            .min(city.population.saturating_sub(other_specialists));
+
+        city.specialists.retain(|name| name != specialist);
+        city.specialists
+            .extend(std::iter::repeat_n(specialist.clone(), count as usize));
+        city.assign_citizens(grid, &tile_yields);
+    }
+}
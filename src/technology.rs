@@ -1,8 +1,11 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
 use bevy::{
     color::{
         Color,
         palettes::css::{BLACK, RED, WHITE},
     },
+    input::mouse::MouseWheel,
     math::Vec2,
     picking::{
         events::{Click, Drag, Pointer},
@@ -10,14 +13,265 @@ use bevy::{
     },
     prelude::*,
     ui::{
-        BackgroundColor, BorderColor, Node, Overflow, PositionType, ScrollPosition, UiRect, Val,
+        BackgroundColor, BorderColor, Node, Overflow, PositionType, UiRect, UiTransform, Val, Val2,
         percent, widget::Text,
     },
 };
-use civ_map_generator::ruleset::Ruleset;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset};
 
 use crate::RulesetResource;
-use crate::assets::MaterialResource;
+use crate::assets::{AppState, MaterialResource};
+use crate::city::City;
+use crate::civilization::{Civilization, Civilizations, PlayerCivilization};
+use crate::command::PlayerCommand;
+use crate::era::{apply_era_effects, civilization_era};
+use crate::game_event::TechResearched;
+use crate::turn::{TurnProcessing, TurnSet};
+use crate::unit_component::Owner;
+use crate::yields::TileYields;
+
+/// Request to change the technology a civilization researches.
+///
+/// If some prerequisites of the technology are not researched yet, they are researched first.
+#[derive(Message)]
+pub struct ChooseResearch {
+    pub nation: Nation,
+    pub technology: String,
+}
+
+/// Written when researching a technology reveals resources on the map to a civilization.
+#[derive(Message)]
+pub struct ResourcesRevealed {
+    pub nation: Nation,
+    pub resources: Vec<String>,
+}
+
+/// Return the resources the civilization can see: the resources without `revealedBy` technology,
+/// and the resources whose `revealedBy` technology has been researched.
+pub fn revealed_resources(civilization: &Civilization, ruleset: &Ruleset) -> HashSet<String> {
+    ruleset
+        .tile_resources
+        .values()
+        .filter(|resource| civilization.has_technology(&resource.revealed_by))
+        .map(|resource| resource.name.clone())
+        .collect()
+}
+
+/// Return `true` if the civilization can research the technology now:
+/// it is not researched yet and all of its prerequisites are.
+pub fn can_research(technology: &str, civilization: &Civilization, ruleset: &Ruleset) -> bool {
+    !civilization.researched_technologies.contains(technology)
+        && ruleset.technologies[technology]
+            .prerequisites
+            .iter()
+            .all(|prerequisite| civilization.researched_technologies.contains(prerequisite))
+}
+
+/// Return the technologies to research to get `technology`, in an order where every technology comes after its prerequisites.
+///
+/// The list ends with `technology`, and is empty if it is already researched.
+pub fn research_path(
+    technology: &str,
+    civilization: &Civilization,
+    ruleset: &Ruleset,
+) -> Vec<String> {
+    fn visit(
+        technology: &str,
+        civilization: &Civilization,
+        ruleset: &Ruleset,
+        path: &mut Vec<String>,
+    ) {
+        if civilization.researched_technologies.contains(technology)
+            || path.iter().any(|visited| visited == technology)
+        {
+            return;
+        }
+        for prerequisite in &ruleset.technologies[technology].prerequisites {
+            visit(prerequisite, civilization, ruleset, path);
+        }
+        path.push(technology.to_owned());
+    }
+
+    let mut path = Vec::new();
+    visit(technology, civilization, ruleset, &mut path);
+    path
+}
+
+/// Give technologies to a civilization at once with all their prerequisites, e.g. for an advanced start,
+/// and update its era and the resources it can see.
+pub fn learn_technologies_with_prerequisites<'a>(
+    technologies: impl IntoIterator<Item = &'a String>,
+    civilization: &mut Civilization,
+    ruleset: &Ruleset,
+) {
+    for technology in technologies {
+        for learned in research_path(technology, civilization, ruleset) {
+            civilization.learn_technology(&learned);
+        }
+    }
+    civilization.era = civilization_era(civilization, ruleset);
+    civilization.revealed_resources = revealed_resources(civilization, ruleset);
+    apply_era_effects(&civilization.era.clone(), civilization, ruleset);
+}
+
+/// Return the number of turns needed to finish the technology with `science_per_turn`, or `None` if it never finishes.
+pub fn turns_to_research(
+    technology: &str,
+    civilization: &Civilization,
+    science_per_turn: f32,
+    ruleset: &Ruleset,
+) -> Option<u32> {
+    if science_per_turn <= 0. {
+        return None;
+    }
+    let progress = civilization
+        .research_progress
+        .get(technology)
+        .copied()
+        .unwrap_or_default();
+    let science_left = ruleset.technologies[technology].cost as f32 - progress;
+    Some((science_left / science_per_turn).ceil().max(1.) as u32)
+}
+
+pub struct TechnologyPlugin;
+
+impl Plugin for TechnologyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ChooseResearch>()
+            .add_message::<ToggleTechTree>()
+            .add_message::<ResourcesRevealed>()
+            .add_systems(
+                Update,
+                (
+                    toggle_tech_tree_on_key,
+                    toggle_tech_tree.run_if(on_message::<ToggleTechTree>),
+                    choose_research.run_if(on_message::<ChooseResearch>),
+                    reveal_resources.run_if(on_message::<TechResearched>),
+                    pan_and_zoom_tech_tree,
+                    update_tech_tree,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(TurnProcessing, research.in_set(TurnSet::Research));
+    }
+}
+
+fn choose_research(
+    mut choose_research: MessageReader<ChooseResearch>,
+    ruleset: Res<RulesetResource>,
+    mut civilizations: ResMut<Civilizations>,
+) {
+    for ChooseResearch { nation, technology } in choose_research.read() {
+        let civilization = civilizations.get_mut(*nation);
+        let mut path: VecDeque<_> = research_path(technology, civilization, &ruleset.0).into();
+        if let Some(first) = path.pop_front() {
+            civilization.current_research = Some(first);
+            civilization.research_queue = path;
+        }
+    }
+}
+
+/// Reveal the resources of the technologies researched by every civilization.
+fn reveal_resources(
+    mut tech_researched: MessageReader<TechResearched>,
+    mut resources_revealed: MessageWriter<ResourcesRevealed>,
+    ruleset: Res<RulesetResource>,
+    mut civilizations: ResMut<Civilizations>,
+) {
+    for &TechResearched { nation, .. } in tech_researched.read() {
+        let civilization = civilizations.get_mut(nation);
+        let revealed_resources = revealed_resources(civilization, &ruleset.0);
+        let resources: Vec<_> = revealed_resources
+            .difference(&civilization.revealed_resources)
+            .cloned()
+            .collect();
+        civilization.revealed_resources = revealed_resources;
+        if !resources.is_empty() {
+            resources_revealed.write(ResourcesRevealed { nation, resources });
+        }
+    }
+}
+
+/// Add the science of the cities of every civilization to its current research.
+///
+/// When a technology is finished, the next technology of the research queue is researched.
+/// The science of a civilization without current research is lost, except the overflow of the last finished technology.
+fn research(
+    mut tech_researched: MessageWriter<TechResearched>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    mut civilizations: ResMut<Civilizations>,
+    query_city: Query<(&City, &Owner)>,
+) {
+    let ruleset = &ruleset.0;
+
+    let mut science_per_nation = HashMap::new();
+    for (city, owner) in query_city.iter() {
+        let science = city.science(&tile_yields, civilizations.get(owner.nation()), ruleset);
+        *science_per_nation.entry(owner.nation()).or_insert(0.) += science;
+    }
+
+    for (nation, science) in science_per_nation {
+        let civilization = civilizations.get_mut(nation);
+        let Some(technology) = civilization.current_research.clone() else {
+            continue;
+        };
+
+        let science = science + std::mem::take(&mut civilization.science_overflow);
+        let progress = civilization
+            .research_progress
+            .entry(technology.clone())
+            .or_default();
+        *progress += science;
+
+        let cost = ruleset.technologies[&technology].cost as f32;
+        if *progress >= cost {
+            civilization.science_overflow = *progress - cost;
+            civilization.research_progress.remove(&technology);
+            civilization
+                .researched_technologies
+                .insert(technology.clone());
+            civilization.current_research = civilization.research_queue.pop_front();
+            tech_researched.write(TechResearched { nation, technology });
+        }
+    }
+}
+
+/// The width of a column of the tech tree, in pixels.
+const TECH_TREE_COLUMN_WIDTH: f32 = 400.;
+/// The height of a row of the tech tree, in pixels.
+const TECH_TREE_ROW_HEIGHT: f32 = 80.;
+/// The height of the era names above the tech tree, in pixels.
+const TECH_TREE_HEADER_HEIGHT: f32 = 40.;
+const TECHNOLOGY_CARD_SIZE: Vec2 = Vec2::new(300., 60.);
+
+/// Request to open the tech tree screen, or to close it if it is open.
+#[derive(Message)]
+pub struct ToggleTechTree;
+
+/// The full screen panel of the tech tree.
+#[derive(Component)]
+pub struct TechTreeScreen;
+
+/// The node holding all the technology cards, moved and scaled to pan and zoom the tech tree.
+#[derive(Component)]
+struct TechTreeCanvas {
+    offset: Vec2,
+    zoom: f32,
+}
+
+/// The card of a technology in the tech tree.
+#[derive(Component)]
+struct TechnologyCard(String);
+
+/// The text showing the turns needed to research a technology.
+#[derive(Component)]
+struct TechnologyTurnsText(String);
+
+/// The text of the button opening the tech tree, showing the current research.
+#[derive(Component)]
+pub struct ResearchButtonText;
 
 pub fn setup_tech_button(mut commands: Commands) {
     commands
@@ -33,27 +287,79 @@ pub fn setup_tech_button(mut commands: Commands) {
             },
             BackgroundColor(Color::BLACK),
             BorderColor::all(Color::WHITE),
-            Text("Open Tech Tree".to_string()),
+            Text("Choose research".to_string()),
+            ResearchButtonText,
         ))
-        .observe(open_tech_tree);
+        .observe(
+            |click: On<Pointer<Click>>, mut toggle_tech_tree: MessageWriter<ToggleTechTree>| {
+                if matches!(click.button, PointerButton::Primary) {
+                    toggle_tech_tree.write(ToggleTechTree);
+                }
+            },
+        );
 }
 
-#[derive(Component)]
-struct ScrollableNode;
+/// Return the science per turn of every city of `nation`.
+pub fn science_per_turn(
+    nation: Nation,
+    civilization: &Civilization,
+    query_city: &Query<(&City, &Owner)>,
+    tile_yields: &TileYields,
+    ruleset: &Ruleset,
+) -> f32 {
+    query_city
+        .iter()
+        .filter(|(_, owner)| owner.nation() == nation)
+        .map(|(city, _)| city.science(tile_yields, civilization, ruleset))
+        .sum()
+}
 
-fn open_tech_tree(
-    drag: On<Pointer<Click>>,
+fn toggle_tech_tree_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut toggle_tech_tree: MessageWriter<ToggleTechTree>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyT) {
+        toggle_tech_tree.write(ToggleTechTree);
+    }
+}
+
+fn toggle_tech_tree(
     mut commands: Commands,
+    mut toggle_tech_tree: MessageReader<ToggleTechTree>,
     ruleset: Res<RulesetResource>,
     materials: Res<MaterialResource>,
+    query_screen: Query<Entity, With<TechTreeScreen>>,
 ) {
-    let ruleset = &ruleset.0;
+    // Toggling twice in the same frame does nothing.
+    if toggle_tech_tree.read().count().is_multiple_of(2) {
+        return;
+    }
+
+    if let Ok(screen) = query_screen.single() {
+        commands.entity(screen).despawn();
+    } else {
+        spawn_tech_tree(&mut commands, &ruleset.0, &materials);
+    }
+}
+
+/// The position of the top left corner of a technology card in the tech tree.
+fn technology_card_position(row: u32, column: u32) -> Vec2 {
+    Vec2::new(
+        // Notice: In json file, column starts from 0
+        column as f32 * TECH_TREE_COLUMN_WIDTH
+            + (TECH_TREE_COLUMN_WIDTH - TECHNOLOGY_CARD_SIZE.x) / 2.,
+        // Notice: In json file, row starts from 1, maybe 0 in the future
+        TECH_TREE_HEADER_HEIGHT + (row as f32 - 1.) * TECH_TREE_ROW_HEIGHT,
+    )
+}
+
+fn spawn_tech_tree(commands: &mut Commands, ruleset: &Ruleset, materials: &MaterialResource) {
     let column_count = ruleset
         .technologies
         .values()
         .map(|technology| technology.column)
         .max()
-        .unwrap() as i16
+        .unwrap()
         + 1;
 
     let row_count = ruleset
@@ -61,97 +367,247 @@ fn open_tech_tree(
         .values()
         .map(|technology| technology.row)
         .max()
-        .unwrap() as i16
-        + 1;
+        .unwrap();
 
-    if matches!(drag.button, PointerButton::Primary) {
-        commands
-            .spawn((
-                Node {
-                    width: percent(100),
-                    height: percent(100),
-                    overflow: Overflow::scroll_x(),
-                    ..Default::default()
-                },
-                ScrollPosition(Vec2::ZERO),
-                ScrollableNode,
-                BackgroundColor(Color::srgb(0.1, 0.1, 0.1)),
-            ))
-            .observe(
-                |drag: On<Pointer<Drag>>,
-                 mut scroll_position_query: Query<
-                    (&mut ScrollPosition, &Node, &ComputedNode),
-                    With<ScrollableNode>,
-                >| {
-                    // We will edit the query in the future
-                    // `node` is unnessarily because we have known `node.overflow` before
-                    if let Ok((mut scroll_position, node, computed)) =
-                        scroll_position_query.single_mut()
-                    {
-                        let max_offset = (computed.content_size() - computed.size())
-                            * computed.inverse_scale_factor();
-                        let delta = drag.delta;
-                        if node.overflow.x == OverflowAxis::Scroll && delta.x != 0. {
-                            // Is this node already scrolled all the way in the direction of the scroll?
-                            let max = if delta.x > 0. {
-                                scroll_position.x >= max_offset.x
-                            } else {
-                                scroll_position.x <= 0.
-                            };
-
-                            if !max {
-                                scroll_position.x += delta.x;
-                            }
-                        }
+    // The first column of every era, where the name of the era is shown.
+    let mut era_and_first_column: Vec<(String, u32)> = Vec::new();
+    for technology in ruleset.technologies.values() {
+        match era_and_first_column
+            .iter_mut()
+            .find(|(era, _)| *era == technology.era)
+        {
+            Some((_, column)) => *column = (*column).min(technology.column),
+            None => era_and_first_column.push((technology.era.clone(), technology.column)),
+        }
+    }
+
+    let canvas_size = Vec2::new(
+        column_count as f32 * TECH_TREE_COLUMN_WIDTH,
+        TECH_TREE_HEADER_HEIGHT + row_count as f32 * TECH_TREE_ROW_HEIGHT,
+    );
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: percent(100),
+                height: percent(100),
+                overflow: Overflow::clip(),
+                ..Default::default()
+            },
+            BackgroundColor(Color::srgb(0.1, 0.1, 0.1)),
+            TechTreeScreen,
+        ))
+        .observe(
+            |drag: On<Pointer<Drag>>, mut query_canvas: Query<&mut TechTreeCanvas>| {
+                if let Ok(mut canvas) = query_canvas.single_mut() {
+                    canvas.offset += drag.delta;
+                }
+            },
+        )
+        .with_children(|builder| {
+            builder
+                .spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        width: px(canvas_size.x),
+                        height: px(canvas_size.y),
+                        ..default()
+                    },
+                    UiTransform::IDENTITY,
+                    TechTreeCanvas {
+                        offset: Vec2::ZERO,
+                        zoom: 1.,
+                    },
+                    Pickable::IGNORE,
+                ))
+                .with_children(|builder| {
+                    for (era, column) in &era_and_first_column {
+                        builder.spawn((
+                            Node {
+                                position_type: PositionType::Absolute,
+                                left: px(*column as f32 * TECH_TREE_COLUMN_WIDTH + 10.),
+                                top: px(10.),
+                                ..default()
+                            },
+                            Text(era.clone()),
+                            TextFont {
+                                font_size: 18.,
+                                ..default()
+                            },
+                            Pickable::IGNORE,
+                        ));
+                    }
 
-                        // It's unnecessary to check because `node.overflow.y == OverflowAxis::Scroll` is always false in this example.
-                        /* if node.overflow.y == OverflowAxis::Scroll && delta.y != 0. {
-                            // Is this node already scrolled all the way in the direction of the scroll?
-                            let max = if delta.y > 0. {
-                                scroll_position.y >= max_offset.y
-                            } else {
-                                scroll_position.y <= 0.
-                            };
-
-                            if !max {
-                                scroll_position.y += delta.y;
+                    for technology in ruleset.technologies.values() {
+                        let end = technology_card_position(technology.row, technology.column)
+                            + Vec2::new(0., TECHNOLOGY_CARD_SIZE.y / 2.);
+                        for prerequisite in &technology.prerequisites {
+                            let prerequisite = &ruleset.technologies[prerequisite];
+                            let start =
+                                technology_card_position(prerequisite.row, prerequisite.column)
+                                    + Vec2::new(
+                                        TECHNOLOGY_CARD_SIZE.x,
+                                        TECHNOLOGY_CARD_SIZE.y / 2.,
+                                    );
+                            for segment in prerequisite_arrow(start, end) {
+                                builder.spawn(segment);
                             }
-                        } */
+                        }
                     }
-                },
-            )
-            .with_children(|builder| {
-                builder
-                    .spawn(Node {
-                        display: Display::Grid,
-                        grid_template_rows: RepeatedGridTrack::fr(row_count as u16, 1.),
-                        grid_template_columns: RepeatedGridTrack::px(column_count as i32, 400.),
-                        ..default()
-                    })
-                    .with_children(|builder| {
-                        ruleset.technologies.values().for_each(|technology| {
-                            builder.spawn((
+
+                    for technology in ruleset.technologies.values() {
+                        let position = technology_card_position(technology.row, technology.column);
+                        builder
+                            .spawn((
                                 Node {
-                                    grid_row: GridPlacement::start(
-                                        technology.row as i16, // Notice: In json file, row starts from 1, maybe 0 in the future
-                                    ),
-                                    grid_column: GridPlacement::start(technology.column as i16 + 1), // Notice: In json file, column starts from 0
-                                    border: UiRect::all(Val::Px(2.0)),
+                                    position_type: PositionType::Absolute,
+                                    left: px(position.x),
+                                    top: px(position.y),
                                     ..default()
                                 },
-                                Pickable {
-                                    should_block_lower: false,
-                                    is_hoverable: true,
-                                },
+                                BorderRadius::all(Val::Px(10.0)),
+                                BackgroundColor(Color::NONE),
+                                TechnologyCard(technology.name.clone()),
                                 children![technology_button(
                                     technology.name.clone(),
-                                    &materials,
+                                    materials,
                                     ruleset
                                 )],
-                            ));
-                        });
-                    });
-            });
+                            ))
+                            .observe(choose_research_on_click);
+                    }
+                });
+        });
+}
+
+/// The line segments of the arrow from the right side of a prerequisite card to the left side of the card which requires it.
+///
+/// The line goes right, then up or down, then right again, so it never crosses the cards in between.
+fn prerequisite_arrow(start: Vec2, end: Vec2) -> Vec<impl Bundle> {
+    const LINE_WIDTH: f32 = 2.;
+    let line_color = BackgroundColor(Color::srgb(0.6, 0.6, 0.6));
+    let middle_x = (start.x + end.x) / 2.;
+    let top = start.y.min(end.y);
+
+    [
+        Rect::new(start.x, start.y, middle_x, start.y + LINE_WIDTH),
+        Rect::new(
+            middle_x,
+            top,
+            middle_x + LINE_WIDTH,
+            top + (start.y - end.y).abs() + LINE_WIDTH,
+        ),
+        Rect::new(middle_x, end.y, end.x, end.y + LINE_WIDTH),
+    ]
+    .into_iter()
+    .map(|rect| {
+        (
+            Node {
+                position_type: PositionType::Absolute,
+                left: px(rect.min.x),
+                top: px(rect.min.y),
+                width: px(rect.width()),
+                height: px(rect.height()),
+                ..default()
+            },
+            line_color,
+            Pickable::IGNORE,
+        )
+    })
+    .collect()
+}
+
+fn choose_research_on_click(
+    click: On<Pointer<Click>>,
+    query_card: Query<&TechnologyCard>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    if let Ok(card) = query_card.get(click.entity) {
+        player_command.write(PlayerCommand::ChooseResearch {
+            technology: card.0.clone(),
+        });
+    }
+}
+
+/// Zoom the tech tree with the mouse wheel, and apply the pan and zoom to the canvas.
+fn pan_and_zoom_tech_tree(
+    mut scroll_evr: MessageReader<MouseWheel>,
+    mut query_canvas: Query<(&mut TechTreeCanvas, &mut UiTransform)>,
+) {
+    let Ok((mut canvas, mut ui_transform)) = query_canvas.single_mut() else {
+        return;
+    };
+
+    for event in scroll_evr.read() {
+        canvas.zoom = (canvas.zoom * (1.0 + event.y * 0.1)).clamp(0.3, 1.5);
+    }
+
+    ui_transform.translation = Val2::px(canvas.offset.x, canvas.offset.y);
+    ui_transform.scale = Vec2::splat(canvas.zoom);
+}
+
+/// Color the technology cards by their research state, and show the turns needed to research them.
+fn update_tech_tree(
+    player_civilization: Res<PlayerCivilization>,
+    civilizations: Res<Civilizations>,
+    ruleset: Res<RulesetResource>,
+    tile_yields: Res<TileYields>,
+    query_city: Query<(&City, &Owner)>,
+    mut query_card: Query<(&TechnologyCard, &mut BackgroundColor)>,
+    mut query_turns_text: Query<(&TechnologyTurnsText, &mut Text), Without<ResearchButtonText>>,
+    mut query_button_text: Query<&mut Text, With<ResearchButtonText>>,
+) {
+    let ruleset = &ruleset.0;
+    let nation = player_civilization.0;
+    let civilization = civilizations.get(nation);
+    let science_per_turn =
+        science_per_turn(nation, civilization, &query_city, &tile_yields, ruleset);
+
+    for (card, mut background_color) in query_card.iter_mut() {
+        let technology = card.0.as_str();
+        background_color.0 = if civilization.researched_technologies.contains(technology) {
+            Color::srgb(0.2, 0.5, 0.2)
+        } else if civilization.current_research.as_deref() == Some(technology) {
+            Color::srgb(0.2, 0.3, 0.7)
+        } else if civilization
+            .research_queue
+            .iter()
+            .any(|queued| queued == technology)
+        {
+            Color::srgb(0.15, 0.2, 0.4)
+        } else if can_research(technology, civilization, ruleset) {
+            Color::NONE
+        } else {
+            Color::srgb(0.3, 0.3, 0.3)
+        };
+    }
+
+    for (turns_text, mut text) in query_turns_text.iter_mut() {
+        let technology = turns_text.0.as_str();
+        text.0 = if civilization.researched_technologies.contains(technology) {
+            String::new()
+        } else {
+            match turns_to_research(technology, civilization, science_per_turn, ruleset) {
+                Some(turns) => format!("{turns} turns"),
+                None => "-".to_owned(),
+            }
+        };
+    }
+
+    if let Ok(mut text) = query_button_text.single_mut() {
+        text.0 = match &civilization.current_research {
+            Some(technology) => {
+                match turns_to_research(technology, civilization, science_per_turn, ruleset) {
+                    Some(turns) => format!("{technology} ({turns} turns)"),
+                    None => technology.clone(),
+                }
+            }
+            None => "Choose research".to_owned(),
+        };
     }
 }
 
@@ -232,7 +688,8 @@ fn technology_button(
                         border: UiRect::all(Val::Px(2.0)),
                         ..default()
                     },
-                    Text("5000 turns".to_string()),
+                    Text(String::new()),
+                    TechnologyTurnsText(technology_name.clone()),
                     TextFont {
                         font_size: 12.,
                         ..default()
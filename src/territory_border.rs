@@ -0,0 +1,221 @@
+//! The borders of the nations: the tiles owned by the cities of a nation are tinted with its color, and outlined
+//! along the edges they share with the tiles of other nations or with unowned tiles.
+//!
+//! The borders are children of the [`crate::world_map::WorldTile`] entities, so they follow the tiles when the map wraps around.
+//! Only the tiles whose owner changed and their neighbors are redrawn, e.g. when the borders of a city grow or a city
+//! is captured, and every tile when the palette of the nations changes.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use civ_map_generator::{
+    grid::{
+        Grid,
+        hex_grid::{Hex, HexGrid},
+    },
+    nation::Nation,
+    tile::Tile,
+};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    accessibility::AccessibilitySettings,
+    assets::AppState,
+    city::City,
+    custom_mesh::{hex_mesh, polyline_mesh},
+    grid::world_position_to_tile,
+    unit_component::Owner,
+    world_map::WorldTileEntities,
+};
+
+const BORDER_WIDTH: f32 = 3.;
+/// The outline is pulled toward the center of the tile, so the outlines of two neighboring nations lie side by side.
+const BORDER_INSET: f32 = 0.92;
+const FILL_ALPHA: f32 = 0.2;
+/// Above the terrain, below the yield overlay, the cities and the units.
+const FILL_Z: f32 = 3.2;
+const BORDER_Z: f32 = 3.3;
+
+/// The border drawn on a tile owned by a nation.
+#[derive(Component)]
+struct TerritoryBorder(Tile);
+
+/// The materials of a nation, shared by all its tiles.
+struct NationMaterials {
+    fill: Handle<ColorMaterial>,
+    border: Handle<ColorMaterial>,
+}
+
+pub struct TerritoryBorderPlugin;
+
+impl Plugin for TerritoryBorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_territory_borders.run_if(
+                in_state(AppState::GameStart)
+                    .and(resource_exists::<TileMapResource>)
+                    .and(resource_exists::<WorldTileEntities>),
+            ),
+        );
+    }
+}
+
+/// The outline of the tile centered on `world_center` along its edges shared with tiles not owned by `nation`,
+/// relative to the center of the tile. Consecutive edges make a single line, so the outline turns the corners without gaps.
+fn border_polylines(
+    nation: Nation,
+    tile_owners: &HashMap<Tile, Nation>,
+    hex_corners: &[Vec2],
+    world_center: Vec2,
+    grid: HexGrid,
+) -> Vec<Vec<(Vec2, f32)>> {
+    let corner_count = hex_corners.len();
+    // The edge from the corner `index` to the next corner is a border edge.
+    let is_border_edge: Vec<bool> = (0..corner_count)
+        .map(|index| {
+            let corner = hex_corners[index];
+            let next_corner = hex_corners[(index + 1) % corner_count];
+            // The neighbor across the edge is the tile mirroring this tile over the middle of the edge.
+            world_position_to_tile(world_center + corner + next_corner, grid)
+                .is_none_or(|neighbor| tile_owners.get(&neighbor) != Some(&nation))
+        })
+        .collect();
+
+    let point = |index: usize| {
+        (
+            hex_corners[index % corner_count] * BORDER_INSET,
+            BORDER_WIDTH,
+        )
+    };
+    // A tile with no neighbor of its nation is outlined all around. The line goes one edge past its start, so it
+    // turns the first corner like the others.
+    if is_border_edge.iter().all(|&is_border| is_border) {
+        return vec![(0..=corner_count + 1).map(point).collect()];
+    }
+    // Otherwise every run of border edges starts after an edge which is not a border.
+    let mut polylines = Vec::new();
+    for start in 0..corner_count {
+        if !is_border_edge[start] || is_border_edge[(start + corner_count - 1) % corner_count] {
+            continue;
+        }
+        let mut polyline = vec![point(start)];
+        let mut index = start;
+        while is_border_edge[index % corner_count] {
+            index += 1;
+            polyline.push(point(index));
+        }
+        polylines.push(polyline);
+    }
+    polylines
+}
+
+/// Redraw the borders of the tiles whose owner changed and of their neighbors.
+fn update_territory_borders(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    tile_entities: Res<WorldTileEntities>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut previous_owners: Local<HashMap<Tile, Nation>>,
+    mut nation_materials: Local<HashMap<Nation, NationMaterials>>,
+    mut fill_mesh: Local<Option<Handle<Mesh>>>,
+    mut removed_cities: RemovedComponents<City>,
+    query_city: Query<(&City, &Owner)>,
+    query_changed_city: Query<(), Or<(Changed<City>, Changed<Owner>)>>,
+    query_border: Query<(Entity, &TerritoryBorder)>,
+) {
+    let borders_changed = removed_cities.read().count() > 0 || !query_changed_city.is_empty();
+    // A new map or a loaded game has new tiles, and a new palette new colors, so every border is redrawn.
+    let redraw_all = tile_entities.is_changed() || accessibility_settings.is_changed();
+    if !borders_changed && !redraw_all {
+        return;
+    }
+
+    let grid = map.0.world_grid.grid;
+    let mut tile_owners = HashMap::new();
+    for (city, owner) in query_city.iter() {
+        for &tile in &city.owned_tiles {
+            tile_owners.insert(tile, owner.nation());
+        }
+    }
+
+    if redraw_all {
+        previous_owners.clear();
+        nation_materials.clear();
+        *fill_mesh = None;
+        for (entity, _) in query_border.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+    let changed_tiles: HashSet<Tile> = if redraw_all {
+        tile_owners.keys().copied().collect()
+    } else {
+        tile_owners
+            .keys()
+            .chain(previous_owners.keys())
+            .copied()
+            .filter(|tile| tile_owners.get(tile) != previous_owners.get(tile))
+            .collect()
+    };
+    // The outline of a tile depends on the owners of its neighbors.
+    let tiles_to_redraw: HashSet<Tile> = changed_tiles
+        .iter()
+        .flat_map(|&tile| std::iter::once(tile).chain(tile.neighbor_tiles(grid)))
+        .collect();
+    if !redraw_all {
+        for (entity, &TerritoryBorder(tile)) in query_border.iter() {
+            if tiles_to_redraw.contains(&tile) {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    let fill_mesh = fill_mesh
+        .get_or_insert_with(|| meshes.add(hex_mesh(&grid)))
+        .clone();
+    let hex_corners: Vec<Vec2> = grid
+        .layout
+        .all_corners(Hex::new(0, 0))
+        .map(Vec2::from)
+        .to_vec();
+    for &tile in &tiles_to_redraw {
+        let (Some(&nation), Some(&tile_entity)) =
+            (tile_owners.get(&tile), tile_entities.0.get(&tile))
+        else {
+            continue;
+        };
+        let materials = nation_materials.entry(nation).or_insert_with(|| {
+            let color = accessibility_settings
+                .player_colors(nation, &ruleset.0)
+                .outer();
+            NationMaterials {
+                fill: color_materials.add(color.with_alpha(FILL_ALPHA)),
+                border: color_materials.add(color),
+            }
+        });
+
+        let world_center = Vec2::from(grid.offset_to_pixel(tile.to_offset(grid)));
+        let polylines = border_polylines(nation, &tile_owners, &hex_corners, world_center, grid);
+        commands.entity(tile_entity).with_children(|parent| {
+            parent.spawn((
+                TerritoryBorder(tile),
+                Mesh2d(fill_mesh.clone()),
+                MeshMaterial2d(materials.fill.clone()),
+                Transform::from_xyz(0., 0., FILL_Z),
+            ));
+            if !polylines.is_empty() {
+                parent.spawn((
+                    TerritoryBorder(tile),
+                    Mesh2d(meshes.add(polyline_mesh(&polylines))),
+                    MeshMaterial2d(materials.border.clone()),
+                    Transform::from_xyz(0., 0., BORDER_Z),
+                ));
+            }
+        });
+    }
+
+    *previous_owners = tile_owners;
+}
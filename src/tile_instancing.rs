@@ -0,0 +1,192 @@
+//! An alternative way to draw the base terrain of the tiles, started with `--instanced-tiles`.
+//!
+//! Instead of the merged meshes of the [`crate::world_map::TileChunk`]s, the base terrain of the whole map is
+//! drawn by a [`TileInstanceMaterial`]: one mesh for every tile of the map, whose positions and textures are read
+//! from a buffer of [`TileInstance`]s, and the textures of the base terrains stacked into one texture array.
+//! The map is drawn in one draw call, or three on a map which wraps around, for its copies on both sides.
+//!
+//! If the textures of the base terrains can't be stacked, e.g. because they don't have the same size,
+//! the chunks draw the base terrain as usual.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+    render::{
+        render_resource::{TextureViewDescriptor, TextureViewDimension},
+        storage::ShaderStorageBuffer,
+    },
+};
+use civ_map_generator::{
+    grid::{Grid, hex_grid::Hex},
+    tile_component::BaseTerrain,
+};
+use enum_map::Enum;
+
+use crate::{
+    TileMapResource,
+    assets::MaterialResource,
+    custom_material::{ATTRIBUTE_TILE_INSTANCE, TileInstance, TileInstanceMaterial},
+    grid::map_pixel_width,
+};
+
+/// How the base terrain of the tiles is drawn.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TileRenderPath {
+    /// The merged meshes of the chunks, see [`crate::world_map::update_tile_chunks`].
+    #[default]
+    Chunked,
+    /// A [`TileInstanceMaterial`], see the [module documentation](self).
+    Instanced,
+}
+
+impl TileRenderPath {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        if args.any(|arg| arg == "--instanced-tiles") {
+            Self::Instanced
+        } else {
+            Self::Chunked
+        }
+    }
+}
+
+/// Present while the base terrain is drawn by the instanced layer, so the chunks leave it out.
+#[derive(Resource)]
+pub struct InstancedTiles;
+
+/// A copy of the instanced base terrain layer.
+#[derive(Component)]
+pub struct InstancedTileLayer;
+
+pub struct TileInstancingPlugin;
+
+impl Plugin for TileInstancingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TileRenderPath::from_args(std::env::args().skip(1)));
+    }
+}
+
+/// Build the instanced base terrain layer of a new map, e.g. when a save is loaded.
+pub fn setup_instanced_tiles(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    tile_render_path: Res<TileRenderPath>,
+    materials: Res<MaterialResource>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut instance_materials: ResMut<Assets<TileInstanceMaterial>>,
+    query_layer: Query<Entity, With<InstancedTileLayer>>,
+) {
+    for entity in query_layer.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<InstancedTiles>();
+    if *tile_render_path != TileRenderPath::Instanced {
+        return;
+    }
+
+    let textures: Vec<Handle<Image>> = (0..BaseTerrain::LENGTH)
+        .map(|index| materials.texture_handle(BaseTerrain::from_usize(index).as_str()))
+        .collect();
+    let Some(texture_array) = stack_textures(&textures, &images) else {
+        warn!("The base terrain textures can't be stacked, the tiles are drawn in chunks instead.");
+        return;
+    };
+
+    let tile_map = &map.0;
+    let grid = tile_map.world_grid.grid;
+    let tile_pixel_size = Vec2::from(grid.layout.size) * 2.;
+    let corners = grid.layout.all_corners(Hex::new(0, 0)).map(Vec2::from);
+
+    let mut instances = Vec::new();
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut instance_indices = Vec::new();
+    let mut indices = Vec::new();
+    for tile in tile_map.all_tiles() {
+        let instance_index = instances.len() as u32;
+        instances.push(TileInstance {
+            position: Vec2::from(grid.offset_to_pixel(tile.to_offset(grid))),
+            texture_index: tile.base_terrain(tile_map).into_usize() as u32,
+            ..Default::default()
+        });
+
+        let first_index = positions.len() as u32;
+        for corner in corners {
+            positions.push([corner.x, corner.y, 0.]);
+            uvs.push([
+                corner.x / tile_pixel_size.x + 0.5,
+                0.5 - corner.y / tile_pixel_size.y,
+            ]);
+            instance_indices.push(instance_index);
+        }
+        indices.extend(
+            [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 5]
+                .iter()
+                .map(|&offset| first_index + offset),
+        );
+    }
+
+    let mesh = meshes.add(
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_attribute(ATTRIBUTE_TILE_INSTANCE, instance_indices)
+        .with_inserted_indices(Indices::U32(indices)),
+    );
+    let material = instance_materials.add(TileInstanceMaterial {
+        instances: buffers.add(ShaderStorageBuffer::from(instances)),
+        textures: images.add(texture_array),
+    });
+
+    // The camera stays on the first copy of the map, see `crate::grid::wrap_x_position`,
+    // so the copies on both sides fill the screen near the edges of a map which wraps around.
+    let copies: &[f32] = if grid.wrap_x() { &[-1., 0., 1.] } else { &[0.] };
+    let map_width = map_pixel_width(grid);
+    for copy in copies {
+        commands.spawn((
+            Mesh2d(mesh.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_xyz(copy * map_width, 0., 0.),
+            InstancedTileLayer,
+        ));
+    }
+    commands.insert_resource(InstancedTiles);
+}
+
+/// Stack the textures into the layers of a texture array, if they all are loaded with the same size and format.
+fn stack_textures(textures: &[Handle<Image>], images: &Assets<Image>) -> Option<Image> {
+    let images = textures
+        .iter()
+        .map(|texture| images.get(texture))
+        .collect::<Option<Vec<_>>>()?;
+    let first = images.first()?;
+    let descriptor = &first.texture_descriptor;
+    let same_layout = images.iter().all(|image| {
+        image.texture_descriptor.size == descriptor.size
+            && image.texture_descriptor.format == descriptor.format
+            && image.texture_descriptor.mip_level_count == descriptor.mip_level_count
+            && image.texture_descriptor.size.depth_or_array_layers == 1
+    });
+    if !same_layout {
+        return None;
+    }
+
+    // The data of an image with layers holds every mip level of the first layer, then of the second layer...
+    let mut data = Vec::new();
+    for image in &images {
+        data.extend_from_slice(image.data.as_ref()?);
+    }
+    let mut texture_array = (*first).clone();
+    texture_array.data = Some(data);
+    texture_array.texture_descriptor.size.depth_or_array_layers = images.len() as u32;
+    texture_array.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..default()
+    });
+    Some(texture_array)
+}
@@ -0,0 +1,137 @@
+use bevy::{
+    ecs::schedule::{ScheduleLabel, SystemSet},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::AppState;
+
+/// Keeps track of the current turn.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct TurnManager {
+    pub turn: u32,
+    /// Where the turn is, not saved since a game is always saved and loaded while the player plays.
+    #[serde(skip)]
+    pub phase: TurnPhase,
+}
+
+/// Where the current turn is, from the point of view of the local player.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TurnPhase {
+    /// The player gives orders.
+    #[default]
+    Playing,
+    /// The player ended the turn, and the other players of a network game haven't yet.
+    WaitingForPlayers,
+    /// The turn has ended and is being processed, e.g. the AI plays.
+    Processing,
+}
+
+/// Request to end the current turn.
+#[derive(Message)]
+pub struct EndTurn;
+
+/// Written after all end-of-turn processing is done and the new turn has begun.
+#[derive(Message)]
+pub struct TurnStarted {
+    pub turn: u32,
+}
+
+/// The schedule that runs once between two turns.
+///
+/// All per-turn processing (city growth, research, economy, unit upkeep...) is added to this schedule
+/// in one of the [`TurnSet`]s, so the order in which they are processed is explicit.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TurnProcessing;
+
+/// The order of per-turn processing inside [`TurnProcessing`].
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TurnSet {
+    /// Update the influence of the civilizations with the city-states and the diplomatic cooldowns.
+    Diplomacy,
+    /// Compute the happiness of every civilization, which the cities depend on.
+    Happiness,
+    /// Grow cities and add their production.
+    Cities,
+    /// Add the science of every civilization to its research.
+    Research,
+    /// Add the culture of every civilization toward its next policy.
+    Culture,
+    /// Advance the missions of the spies.
+    Espionage,
+    /// Collect gold and pay the maintenance of units, buildings and roads.
+    Economy,
+    /// Restore unit movement points and handle unit upkeep.
+    Units,
+    /// Spawn barbarian encampments and units, and move the barbarian units with their full movement points.
+    Barbarians,
+    /// Let the random events happen to the civilizations, see [`crate::random_event`].
+    Events,
+    /// Let the AI choose the research and the production of its civilizations, and give orders to their units.
+    Ai,
+    /// Record the statistics of every civilization once everything else is done.
+    Statistics,
+    /// Check whether the scenario being played is over, see [`crate::scenario`].
+    Victory,
+}
+
+pub struct TurnPlugin;
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TurnManager>()
+            .add_message::<EndTurn>()
+            .add_message::<TurnStarted>()
+            .init_schedule(TurnProcessing)
+            .configure_sets(
+                TurnProcessing,
+                (
+                    TurnSet::Diplomacy,
+                    TurnSet::Happiness,
+                    TurnSet::Cities,
+                    TurnSet::Research,
+                    TurnSet::Culture,
+                    TurnSet::Espionage,
+                    TurnSet::Economy,
+                    TurnSet::Units,
+                    TurnSet::Barbarians,
+                    TurnSet::Events,
+                    TurnSet::Ai,
+                    TurnSet::Statistics,
+                    TurnSet::Victory,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    process_turn.run_if(|turn_manager: Res<TurnManager>| {
+                        turn_manager.phase == TurnPhase::Processing
+                    }),
+                    start_turn_processing.run_if(on_message::<EndTurn>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            );
+    }
+}
+
+/// Process the turn from the next frame on, so the turn is shown as processed before the game stops to process it.
+fn start_turn_processing(
+    mut end_turn: MessageReader<EndTurn>,
+    mut turn_manager: ResMut<TurnManager>,
+) {
+    end_turn.clear();
+    turn_manager.phase = TurnPhase::Processing;
+}
+
+fn process_turn(world: &mut World) {
+    world.run_schedule(TurnProcessing);
+
+    let mut turn_manager = world.resource_mut::<TurnManager>();
+    turn_manager.turn += 1;
+    turn_manager.phase = TurnPhase::Playing;
+    let turn = turn_manager.turn;
+
+    world.write_message(TurnStarted { turn });
+}
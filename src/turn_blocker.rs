@@ -0,0 +1,411 @@
+//! The decisions the player has to make before ending the turn: the research to choose, the production of the cities
+//! producing nothing, and the units waiting for orders.
+//!
+//! `Enter` ends the turn when nothing is pending. Otherwise it opens a window listing the pending decisions, where a
+//! click leads to the decision, or ends the turn anyway. `Shift + Enter` ends the turn without asking.
+//!
+//! `Tab` or `.` select the next unit waiting for orders and move the camera to it, and `,` the previous one, see
+//! [`InputAction::NextUnit`]. The key skipping the turn of the selected unit, `Space` by default, selects the next unit
+//! once the unit is told to wait, see [`UnitOrder::Skip`].
+
+use bevy::{
+    picking::{events::Click, pointer::PointerButton},
+    prelude::*,
+};
+use civ_map_generator::{grid::hex_grid::HexGrid, nation::Nation, ruleset::Ruleset, tile::Tile};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    camera::{CameraCommands, PAN_DURATION},
+    city::City,
+    city_screen::OpenCityScreen,
+    civilization::{Civilization, Civilizations, PlayerCivilization},
+    command::{PlayerCommand, UnitId},
+    key_bindings::{InputAction, KeyBindings},
+    technology::{ToggleTechTree, can_research},
+    turn::{TurnManager, TurnPhase},
+    unit::{MapUnit, MovePath},
+    unit_component::{Movement, Owner, Unit, UnitOrder},
+    unit_sprite::MoveAnimation,
+    world_map::SelectedUnit,
+};
+
+/// Request to end the turn of the player, once the pending decisions are made or skipped.
+#[derive(Message)]
+pub struct RequestEndTurn {
+    /// Whether the turn ends even if decisions are pending.
+    pub skip_pending: bool,
+}
+
+/// A decision the player has to make before ending the turn.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PendingDecision {
+    ChooseResearch,
+    ChooseProduction {
+        city: Entity,
+        name: String,
+    },
+    /// The number of units waiting for orders.
+    IdleUnits(usize),
+}
+
+impl PendingDecision {
+    pub fn label(&self) -> String {
+        match self {
+            Self::ChooseResearch => "Choose a research".to_owned(),
+            Self::ChooseProduction { name, .. } => format!("Choose the production of {name}"),
+            Self::IdleUnits(1) => "1 unit needs orders".to_owned(),
+            Self::IdleUnits(count) => format!("{count} units need orders"),
+        }
+    }
+}
+
+/// The window listing the pending decisions.
+#[derive(Component)]
+pub struct TurnBlocker;
+
+/// An entry of the [`TurnBlocker`].
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum TurnBlockerEntry {
+    Research,
+    City(Entity),
+    NextIdleUnit,
+    EndTurnAnyway,
+    Close,
+}
+
+pub struct TurnBlockerPlugin;
+
+impl Plugin for TurnBlockerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<RequestEndTurn>()
+            .add_systems(
+                Update,
+                (
+                    // The turn ends once the units have walked to where the player sent them.
+                    request_end_turn_on_key.run_if(not(any_with_component::<MoveAnimation>)),
+                    end_turn_or_show_pending_decisions.run_if(on_message::<RequestEndTurn>),
+                    select_next_idle_unit_on_key,
+                    close_turn_blocker_on_escape,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart).and(resource_exists::<TileMapResource>)),
+            )
+            .add_systems(OnExit(AppState::GameStart), close_turn_blocker);
+    }
+}
+
+/// The units of `nation` with movement points left, no path to follow and no order, sorted so they are visited in
+/// the same order every time.
+pub fn idle_units(
+    nation: Nation,
+    query_unit: &Query<(Entity, &Owner, &MapUnit, &Movement, &MovePath, &UnitOrder)>,
+) -> Vec<(Entity, Tile)> {
+    let mut units: Vec<_> = query_unit
+        .iter()
+        .filter(|(_, owner, _, movement, move_path, order)| {
+            owner.nation() == nation
+                && movement.current > 0.
+                && move_path.0.is_empty()
+                && **order == UnitOrder::None
+        })
+        .map(|(entity, _, map_unit, ..)| (entity, map_unit.tile))
+        .collect();
+    units.sort_by_key(|&(entity, _)| entity);
+    units
+}
+
+/// The decisions `nation` has to make before ending the turn, with `idle_unit_count` units waiting for orders.
+pub fn pending_decisions(
+    nation: Nation,
+    civilization: &Civilization,
+    ruleset: &Ruleset,
+    query_city: &Query<(Entity, &City, &Owner)>,
+    idle_unit_count: usize,
+) -> Vec<PendingDecision> {
+    let mut decisions = Vec::new();
+    if civilization.current_research.is_none()
+        && ruleset
+            .technologies
+            .keys()
+            .any(|technology| can_research(technology, civilization, ruleset))
+    {
+        decisions.push(PendingDecision::ChooseResearch);
+    }
+    // Puppets choose their production themselves.
+    let mut cities: Vec<_> = query_city
+        .iter()
+        .filter(|(_, city, owner)| {
+            owner.nation() == nation && !city.is_puppet && city.production.is_none()
+        })
+        .map(|(entity, city, _)| (city.name.clone(), entity))
+        .collect();
+    cities.sort();
+    decisions.extend(
+        cities
+            .into_iter()
+            .map(|(name, city)| PendingDecision::ChooseProduction { city, name }),
+    );
+    if idle_unit_count > 0 {
+        decisions.push(PendingDecision::IdleUnits(idle_unit_count));
+    }
+    decisions
+}
+
+fn request_end_turn_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut request_end_turn: MessageWriter<RequestEndTurn>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Enter) {
+        request_end_turn.write(RequestEndTurn {
+            skip_pending: keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+        });
+    }
+}
+
+/// End the turn if no decision is pending or the player skips them, otherwise list the pending decisions.
+fn end_turn_or_show_pending_decisions(
+    mut commands: Commands,
+    mut request_end_turn: MessageReader<RequestEndTurn>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    player_civilization: Res<PlayerCivilization>,
+    query_city: Query<(Entity, &City, &Owner)>,
+    query_unit: Query<(Entity, &Owner, &MapUnit, &Movement, &MovePath, &UnitOrder)>,
+    query_blocker: Query<Entity, With<TurnBlocker>>,
+    mut turn_manager: ResMut<TurnManager>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    // Every request is read, so none is left for the next frame.
+    let skip_pending = request_end_turn
+        .read()
+        .filter(|request| request.skip_pending)
+        .count()
+        > 0;
+    // The turn is already ended.
+    if turn_manager.phase != TurnPhase::Playing {
+        return;
+    }
+
+    for blocker in query_blocker.iter() {
+        commands.entity(blocker).despawn();
+    }
+
+    let nation = player_civilization.0;
+    let decisions = pending_decisions(
+        nation,
+        civilizations.get(nation),
+        &ruleset.0,
+        &query_city,
+        idle_units(nation, &query_unit).len(),
+    );
+    if skip_pending || decisions.is_empty() {
+        player_command.write(PlayerCommand::EndTurn);
+        turn_manager.phase = TurnPhase::WaitingForPlayers;
+        return;
+    }
+
+    let entries = decisions
+        .iter()
+        .map(|decision| {
+            let entry = match decision {
+                PendingDecision::ChooseResearch => TurnBlockerEntry::Research,
+                &PendingDecision::ChooseProduction { city, .. } => TurnBlockerEntry::City(city),
+                PendingDecision::IdleUnits(_) => TurnBlockerEntry::NextIdleUnit,
+            };
+            (decision.label(), entry)
+        })
+        .chain([
+            (
+                "End turn anyway".to_owned(),
+                TurnBlockerEntry::EndTurnAnyway,
+            ),
+            ("Close".to_owned(), TurnBlockerEntry::Close),
+        ]);
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(50.),
+                top: Val::Percent(50.),
+                width: Val::Px(300.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Stretch,
+                row_gap: Val::Px(6.),
+                padding: UiRect::all(Val::Px(12.)),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            // Centered on the screen.
+            UiTransform::from_translation(Val2::percent(-50., -50.)),
+            BackgroundColor(Color::BLACK.with_alpha(0.9)),
+            BorderColor::all(Color::WHITE),
+            GlobalZIndex(10),
+            TurnBlocker,
+        ))
+        .with_children(|window| {
+            window.spawn((
+                Text("Before ending the turn".to_owned()),
+                TextFont {
+                    font_size: 18.,
+                    ..default()
+                },
+                Pickable::IGNORE,
+            ));
+            for (label, entry) in entries {
+                window
+                    .spawn((
+                        Node {
+                            padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+                            border: UiRect::all(Val::Px(1.)),
+                            ..default()
+                        },
+                        BorderColor::all(Color::WHITE),
+                        Text(label),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        entry,
+                    ))
+                    .observe(choose_turn_blocker_entry_on_click);
+            }
+        });
+}
+
+/// Select the unit waiting for orders after the selected unit, or before it if `previous`, and move the camera to it.
+/// The selected unit is only selected again if no other unit waits for orders.
+fn select_next_idle_unit(
+    nation: Nation,
+    grid: HexGrid,
+    query_unit: &Query<(Entity, &Owner, &MapUnit, &Movement, &MovePath, &UnitOrder)>,
+    selected_unit: &mut SelectedUnit,
+    camera_commands: &mut CameraCommands,
+    previous: bool,
+) {
+    let mut units = idle_units(nation, query_unit);
+    if previous {
+        units.reverse();
+    }
+    let comes_after_selected = |entity: Entity| match selected_unit.0 {
+        Some(selected) if previous => entity < selected,
+        Some(selected) => entity > selected,
+        None => false,
+    };
+    let Some(&(unit, tile)) = units
+        .iter()
+        .find(|&&(entity, _)| comes_after_selected(entity))
+        .or_else(|| {
+            units
+                .iter()
+                .find(|&&(entity, _)| selected_unit.0 != Some(entity))
+        })
+        .or_else(|| units.first())
+    else {
+        return;
+    };
+    selected_unit.0 = Some(unit);
+    camera_commands.pan_to_tile(tile, grid, PAN_DURATION);
+}
+
+fn select_next_idle_unit_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    map: Res<TileMapResource>,
+    player_civilization: Res<PlayerCivilization>,
+    query_unit: Query<(Entity, &Owner, &MapUnit, &Movement, &MovePath, &UnitOrder)>,
+    query_selected: Query<(&Unit, &MapUnit)>,
+    mut selected_unit: ResMut<SelectedUnit>,
+    mut camera_commands: ResMut<CameraCommands>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    let skip = key_bindings.just_pressed(InputAction::SkipUnit, &keyboard_input);
+    let previous = key_bindings.just_pressed(InputAction::PreviousUnit, &keyboard_input);
+    if !skip && !previous && !key_bindings.just_pressed(InputAction::NextUnit, &keyboard_input) {
+        return;
+    }
+    // The order reaches the unit after this frame, so the unit is still idle when the next one is looked for.
+    if skip
+        && let Some(selected) = selected_unit.0
+        && let Ok((unit, map_unit)) = query_selected.get(selected)
+    {
+        player_command.write(PlayerCommand::SetOrder {
+            unit: UnitId::new(unit, map_unit),
+            order: UnitOrder::Skip,
+        });
+    }
+    select_next_idle_unit(
+        player_civilization.0,
+        map.0.world_grid.grid,
+        &query_unit,
+        &mut selected_unit,
+        &mut camera_commands,
+        previous && !skip,
+    );
+}
+
+/// Lead the player to the decision clicked, or end the turn anyway. The window is closed either way.
+fn choose_turn_blocker_entry_on_click(
+    click: On<Pointer<Click>>,
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    player_civilization: Res<PlayerCivilization>,
+    query_entry: Query<&TurnBlockerEntry>,
+    query_unit: Query<(Entity, &Owner, &MapUnit, &Movement, &MovePath, &UnitOrder)>,
+    query_blocker: Query<Entity, With<TurnBlocker>>,
+    mut selected_unit: ResMut<SelectedUnit>,
+    mut camera_commands: ResMut<CameraCommands>,
+    mut toggle_tech_tree: MessageWriter<ToggleTechTree>,
+    mut open_city_screen: MessageWriter<OpenCityScreen>,
+    mut request_end_turn: MessageWriter<RequestEndTurn>,
+) {
+    if !matches!(click.button, PointerButton::Primary) {
+        return;
+    }
+    let Ok(&entry) = query_entry.get(click.entity) else {
+        return;
+    };
+    match entry {
+        TurnBlockerEntry::Research => {
+            toggle_tech_tree.write(ToggleTechTree);
+        }
+        TurnBlockerEntry::City(city) => {
+            open_city_screen.write(OpenCityScreen { city });
+        }
+        TurnBlockerEntry::NextIdleUnit => select_next_idle_unit(
+            player_civilization.0,
+            map.0.world_grid.grid,
+            &query_unit,
+            &mut selected_unit,
+            &mut camera_commands,
+            false,
+        ),
+        TurnBlockerEntry::EndTurnAnyway => {
+            request_end_turn.write(RequestEndTurn { skip_pending: true });
+        }
+        TurnBlockerEntry::Close => {}
+    }
+    for blocker in query_blocker.iter() {
+        commands.entity(blocker).despawn();
+    }
+}
+
+fn close_turn_blocker_on_escape(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    query_blocker: Query<Entity, With<TurnBlocker>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        for blocker in query_blocker.iter() {
+            commands.entity(blocker).despawn();
+        }
+    }
+}
+
+fn close_turn_blocker(mut commands: Commands, query_blocker: Query<Entity, With<TurnBlocker>>) {
+    for blocker in query_blocker.iter() {
+        commands.entity(blocker).despawn();
+    }
+}
@@ -0,0 +1,244 @@
+//! Reading the ruleset files of Unciv mods, so the mods written for Unciv can be installed as they are.
+//!
+//! The Unciv files are close to ours, with a few differences converted before a mod is merged, see [`convert_unciv_files`]:
+//!
+//! - Unciv keeps all the terrains in `Terrains.json`, where the hills are a terrain feature and the mountains a land
+//!   base terrain. We split them into `BaseTerrains.json`, `TerrainTypes.json` (`Flatland`, `Hill`, `Mountain` and
+//!   `Water`), `Features.json` and `NaturalWonders.json`.
+//! - Unciv lists the terrains a feature occurs on, a resource can be found on and an improvement can be built on in one
+//!   list, which we split into the terrain types, the base terrains and the features.
+//! - Unciv names a few terrains differently, see [`RENAMED_TERRAINS`].
+//! - Unciv removes units, buildings and nations of the base ruleset in `ModOptions.json`, which become entries with
+//!   `"delete": true`.
+
+use std::collections::HashMap;
+
+use civ_map_generator::ruleset::Ruleset;
+use serde_json::{Map, Value, json};
+
+/// The file of an Unciv mod holding all its terrains.
+pub const UNCIV_TERRAINS_FILE: &str = "Terrains.json";
+/// The file of an Unciv mod holding its options.
+pub const UNCIV_MOD_OPTIONS_FILE: &str = "ModOptions.json";
+
+/// The terrains Unciv names differently, by their Unciv name.
+const RENAMED_TERRAINS: [(&str, &str); 3] = [
+    ("Plains", "Plain"),
+    ("Lakes", "Lake"),
+    ("Flood plains", "Floodplain"),
+];
+
+/// The Unciv terrains which are terrain types in our ruleset.
+const TERRAIN_TYPES: [&str; 2] = ["Hill", "Mountain"];
+
+/// The lists of names in `ModOptions.json` removing entries of the base ruleset, with the file of these entries.
+const REMOVED_ENTRIES: [(&str, &str); 3] = [
+    ("unitsToRemove", "Units.json"),
+    ("buildingsToRemove", "Buildings.json"),
+    ("nationsToRemove", "Nations.json"),
+];
+
+/// What a terrain name of an Unciv file is in our ruleset.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TerrainKind {
+    Type,
+    WaterBase,
+    LandBase,
+    Feature,
+}
+
+/// Convert the Unciv parts of the ruleset files of a mod, read by file name, into our format.
+///
+/// - `terrains` are the entries of [`UNCIV_TERRAINS_FILE`], moved to the files of our ruleset.
+/// - `mod_options` is the content of [`UNCIV_MOD_OPTIONS_FILE`].
+///
+/// The files already in our format are left as they are, so a mod can mix both formats.
+pub fn convert_unciv_files(
+    files: &mut HashMap<&'static str, Vec<Value>>,
+    terrains: Vec<Value>,
+    mod_options: Option<Value>,
+    ruleset: &Ruleset,
+) {
+    let mut terrains: Vec<Value> = terrains.into_iter().map(rename_terrains).collect();
+    for entries in files.values_mut() {
+        for entry in entries.iter_mut() {
+            *entry = rename_terrains(entry.take());
+        }
+    }
+
+    let mod_terrain_kinds: HashMap<String, TerrainKind> = terrains
+        .iter()
+        .filter_map(|terrain| {
+            let name = terrain.get("name")?.as_str()?;
+            let kind = match terrain.get("type")?.as_str()? {
+                _ if TERRAIN_TYPES.contains(&name) => TerrainKind::Type,
+                "Water" => TerrainKind::WaterBase,
+                "Land" => TerrainKind::LandBase,
+                _ => TerrainKind::Feature,
+            };
+            Some((name.to_owned(), kind))
+        })
+        .collect();
+    let terrain_kind = |name: &str| {
+        if TERRAIN_TYPES.contains(&name) {
+            return TerrainKind::Type;
+        }
+        if let Some(kind) = mod_terrain_kinds.get(name) {
+            return *kind;
+        }
+        match ruleset.base_terrains.get(name) {
+            Some(_) if ["Ocean", "Coast", "Lake"].contains(&name) => TerrainKind::WaterBase,
+            Some(_) => TerrainKind::LandBase,
+            None => TerrainKind::Feature,
+        }
+    };
+
+    for mut terrain in terrains.drain(..) {
+        let Some(object) = terrain.as_object_mut() else {
+            continue;
+        };
+        let name = object
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned();
+        let file = match object.get("type").and_then(Value::as_str) {
+            _ if TERRAIN_TYPES.contains(&name.as_str()) => {
+                object.insert("type".to_owned(), json!("TerrainType"));
+                "TerrainTypes.json"
+            }
+            Some("Water" | "Land") => "BaseTerrains.json",
+            Some("NaturalWonder") => {
+                split_occurs_on(object, &terrain_kind);
+                if let Some(Value::String(turns_into)) = object.remove("turnsInto") {
+                    let turns_into_type = match terrain_kind(&turns_into) {
+                        TerrainKind::Type => turns_into.clone(),
+                        TerrainKind::WaterBase => "Water".to_owned(),
+                        TerrainKind::LandBase | TerrainKind::Feature => "Flatland".to_owned(),
+                    };
+                    object.insert("turnsIntoType".to_owned(), json!(turns_into_type));
+                    if turns_into_type != turns_into {
+                        object.insert("turnsIntoBase".to_owned(), json!(turns_into));
+                    }
+                }
+                "NaturalWonders.json"
+            }
+            _ => {
+                split_occurs_on(object, &terrain_kind);
+                "Features.json"
+            }
+        };
+        files.entry(file).or_default().push(terrain);
+    }
+
+    for (file, unciv_field, field) in [
+        ("TileResources.json", "terrainsCanBeFoundOn", "canBeFoundOn"),
+        (
+            "TileImprovements.json",
+            "terrainsCanBeBuiltOn",
+            "canBeBuiltOn",
+        ),
+    ] {
+        for entry in files.get_mut(file).into_iter().flatten() {
+            let Some(object) = entry.as_object_mut() else {
+                continue;
+            };
+            let Some(terrains) = object.remove(unciv_field) else {
+                continue;
+            };
+            let (mut types, mut bases, mut features) = (Vec::new(), Vec::new(), Vec::new());
+            for terrain in names(&terrains) {
+                match terrain_kind(terrain) {
+                    TerrainKind::Type => types.push(terrain),
+                    TerrainKind::WaterBase | TerrainKind::LandBase => bases.push(terrain),
+                    TerrainKind::Feature => features.push(terrain),
+                }
+            }
+            for (suffix, names) in [("Type", types), ("Base", bases), ("Feature", features)] {
+                if !names.is_empty() {
+                    object.insert(format!("{field}{suffix}"), json!(names));
+                }
+            }
+        }
+    }
+
+    if let Some(mod_options) = mod_options {
+        for (list, file) in REMOVED_ENTRIES {
+            let mut removed: Vec<Value> = mod_options
+                .get(list)
+                .map(names)
+                .into_iter()
+                .flatten()
+                .map(|name| json!({ "name": name, "delete": true }))
+                .collect();
+            // The removals come first, so the mod can add back an entry of the same name
+            let entries = files.entry(file).or_default();
+            removed.append(entries);
+            *entries = removed;
+        }
+    }
+}
+
+/// Split the Unciv `occursOn` of a feature or a natural wonder into `occursOnType` and `occursOnBase`.
+///
+/// Our features and natural wonders occur on a terrain type and a base terrain, so the types of the listed base
+/// terrains are added: `Flatland` for the land ones and `Water` for the water ones.
+fn split_occurs_on(object: &mut Map<String, Value>, terrain_kind: &impl Fn(&str) -> TerrainKind) {
+    let Some(occurs_on) = object.remove("occursOn") else {
+        return;
+    };
+    let (mut types, mut bases) = (Vec::new(), Vec::new());
+    for terrain in names(&occurs_on) {
+        let terrain_type = match terrain_kind(terrain) {
+            TerrainKind::Type => terrain,
+            TerrainKind::WaterBase => "Water",
+            TerrainKind::LandBase => "Flatland",
+            // Our features don't occur on other features
+            TerrainKind::Feature => continue,
+        };
+        if terrain_type != terrain {
+            bases.push(terrain);
+        }
+        if !types.contains(&terrain_type) {
+            types.push(terrain_type);
+        }
+    }
+    object.insert("occursOnType".to_owned(), json!(types));
+    if !bases.is_empty() {
+        object.insert("occursOnBase".to_owned(), json!(bases));
+    }
+}
+
+/// The names in a JSON list of names.
+fn names(list: &Value) -> Vec<&str> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect()
+}
+
+/// Rename the terrains Unciv names differently, see [`RENAMED_TERRAINS`], in the strings of `value` which are a terrain
+/// name or hold one in square brackets, as the uniques do.
+fn rename_terrains(value: Value) -> Value {
+    match value {
+        Value::String(mut string) => {
+            for (unciv_name, name) in RENAMED_TERRAINS {
+                if string == unciv_name {
+                    string = name.to_owned();
+                } else {
+                    string = string.replace(&format!("[{unciv_name}]"), &format!("[{name}]"));
+                }
+            }
+            Value::String(string)
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(rename_terrains).collect()),
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, rename_terrains(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset, tile::Tile, tile_map::TileMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    civilization::Civilizations,
+    diplomacy::DiplomacyState,
+    game_event::UnitMoved,
+    pathfinding::{MovementRules, ZoneOfControl, ZoneOfControlRule},
+    turn::{TurnProcessing, TurnSet},
+    unit_component::{Health, Movement, Owner, RangedStrength, Strength, Unit, UnitOrder},
+    visibility::SightRange,
+};
+
+/// The position of a unit on the map.
+///
+/// The unit entity is kept a child of the [`crate::world_map::WorldTile`] entity it stands on,
+/// so it is drawn at the right place without having its own world position, see [`crate::unit_sprite`].
+#[derive(Component)]
+pub struct MapUnit {
+    pub tile: Tile,
+}
+
+/// The tiles the unit will move through, in order. The destination is the last tile.
+///
+/// The unit moves as far as its movement points allow, and continues at the start of the next turn.
+#[derive(Component, Clone, Default, Serialize, Deserialize)]
+pub struct MovePath(pub VecDeque<Tile>);
+
+/// Request to spawn a new unit of `owner` on `tile`, e.g. when a city finishes producing it.
+///
+/// The tile must respect the stacking rules, see [`find_spawn_tile`].
+#[derive(Message)]
+pub struct SpawnUnit {
+    pub unit_name: String,
+    pub owner: Owner,
+    pub tile: Tile,
+}
+
+/// Where a unit can move, read from the `movementType` of its unit type in the ruleset.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnitDomain {
+    Land,
+    Water,
+    Air,
+}
+
+impl UnitDomain {
+    pub fn of_unit(unit_name: &str, ruleset: &Ruleset) -> Self {
+        let unit_type = &ruleset.units[unit_name].unit_type;
+        match ruleset.unit_types[unit_type].movement_type.as_str() {
+            "Water" => UnitDomain::Water,
+            "Air" => UnitDomain::Air,
+            _ => UnitDomain::Land,
+        }
+    }
+}
+
+/// The unit types of the civilian units, on land and on water.
+const CIVILIAN_UNIT_TYPES: [&str; 3] = ["Civilian", "Civilian Water", "WaterCivilian"];
+
+/// Return `true` if the units of `unit_type` are civilians, e.g. settlers and work boats.
+pub fn is_civilian_unit_type(unit_type: &str) -> bool {
+    CIVILIAN_UNIT_TYPES.contains(&unit_type)
+}
+
+/// Return the [`Unit`] component of a unit. Units of a civilian unit type are civilians, all the others are military
+/// units, see [`is_civilian_unit_type`].
+pub fn unit_kind(unit_name: &str, ruleset: &Ruleset) -> Unit {
+    if is_civilian_unit_type(&ruleset.units[unit_name].unit_type) {
+        Unit::Civilian(unit_name.to_owned())
+    } else {
+        Unit::Military(unit_name.to_owned())
+    }
+}
+
+/// Find the tile to place a new unit produced on `tile`.
+///
+/// A tile holds at most one military unit and one civilian unit. If `tile` is already taken by a unit of the same kind,
+/// the closest neighboring tile the unit can enter is used instead.
+/// `is_taken(tile, is_military)` returns `true` if `tile` already has a unit of that kind.
+pub fn find_spawn_tile(
+    tile: Tile,
+    unit: &Unit,
+    is_taken: impl Fn(Tile, bool) -> bool,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+) -> Option<Tile> {
+    let is_military = matches!(unit, Unit::Military(_));
+    let domain = UnitDomain::of_unit(unit.name(), ruleset);
+    let grid = tile_map.world_grid.grid;
+
+    std::iter::once(tile)
+        .chain(tile.neighbor_tiles(grid))
+        .find(|&candidate| {
+            !is_taken(candidate, is_military)
+                && (candidate == tile
+                    || tile_movement_cost(candidate, domain, tile_map, ruleset).is_some())
+        })
+}
+
+/// Units have 100 health points when they are at full health, as in Civ V.
+pub const MAX_HEALTH: u32 = 100;
+
+/// The tiles which already have a unit, with `true` for military units and `false` for civilians.
+pub fn taken_tiles(query_unit: &Query<(&MapUnit, &Unit)>) -> HashSet<(Tile, bool)> {
+    query_unit
+        .iter()
+        .map(|(map_unit, unit)| (map_unit.tile, matches!(unit, Unit::Military(_))))
+        .collect()
+}
+
+/// The components every unit on the map needs, besides its sprite.
+pub fn unit_components(unit_name: &str, tile: Tile, ruleset: &Ruleset) -> impl Bundle {
+    let unit = &ruleset.units[unit_name];
+    let max_movement = unit.movement as f32;
+    (
+        MapUnit { tile },
+        Movement {
+            current: max_movement,
+            max: max_movement,
+        },
+        MovePath::default(),
+        SightRange::default(),
+        Strength(unit.strength.max(0) as u32),
+        RangedStrength {
+            strength: unit.ranged_strength.max(0) as u32,
+            range: unit.range.max(0) as u32,
+        },
+        Health {
+            current: MAX_HEALTH,
+            max: MAX_HEALTH,
+        },
+        UnitOrder::default(),
+    )
+}
+
+/// Return the movement points needed to enter `tile`, or `None` if a unit of `domain` can't enter it.
+///
+/// The cost is the highest `movementCost` among the base terrain, the terrain type and the feature of the tile,
+/// e.g. a forest on a hill costs 2, not 3.
+pub fn tile_movement_cost(
+    tile: Tile,
+    domain: UnitDomain,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+) -> Option<f32> {
+    let is_water = tile.is_water(tile_map);
+    match domain {
+        UnitDomain::Land if is_water => return None,
+        UnitDomain::Water if !is_water => return None,
+        UnitDomain::Water | UnitDomain::Air => return Some(1.),
+        UnitDomain::Land => {}
+    }
+
+    if tile.natural_wonder(tile_map).is_some() {
+        return None;
+    }
+
+    let base_terrain = &ruleset.base_terrains[tile.base_terrain(tile_map).as_str()];
+    let terrain_type = &ruleset.terrain_types[tile.terrain_type(tile_map).as_str()];
+    if terrain_type.impassable {
+        return None;
+    }
+
+    let mut cost = base_terrain.movement_cost.max(terrain_type.movement_cost);
+
+    if let Some(feature) = tile.feature(tile_map) {
+        let feature = &ruleset.features[feature.as_str()];
+        if feature.impassable {
+            return None;
+        }
+        cost = cost.max(feature.movement_cost);
+    }
+
+    Some(cost.max(1) as f32)
+}
+
+pub struct UnitPlugin;
+
+impl Plugin for UnitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SpawnUnit>()
+            .add_systems(
+                Update,
+                execute_queued_moves.run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                restore_movement_points.in_set(TurnSet::Units),
+            );
+    }
+}
+
+/// Move units along their [`MovePath`] while they have movement points left.
+///
+/// As in Civ V, a unit with any movement points left may always enter a passable tile,
+/// even if the tile costs more than the remaining points. The costs are the ones of the pathfinder,
+/// see [`MovementRules::step_cost`].
+///
+/// A unit never enters a tile with a unit of another civilization, or with a unit of its civilization of the same kind,
+/// see [`find_spawn_tile`]. Its path stops in front of such a tile, fights go through [`crate::combat::Attack`].
+fn execute_queued_moves(
+    map: Option<Res<TileMapResource>>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    diplomacy: Res<DiplomacyState>,
+    zone_of_control_rule: Res<ZoneOfControlRule>,
+    mut query: Query<(
+        Entity,
+        &Unit,
+        &Owner,
+        &mut MapUnit,
+        &mut Movement,
+        &mut MovePath,
+    )>,
+    mut unit_moved: MessageWriter<UnitMoved>,
+) {
+    let Some(map) = map else {
+        return;
+    };
+
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+
+    // The units which exert a zone of control, where they stood at the start of the moves.
+    let military_units: Vec<_> = query
+        .iter()
+        .filter(|(_, unit, ..)| matches!(unit, Unit::Military(_)))
+        .map(|(_, _, owner, map_unit, ..)| (map_unit.tile, owner.nation()))
+        .collect();
+
+    // The nations and the kinds, `true` for military units, of the units on each tile, kept up to date as units move.
+    let mut units_on_tiles: HashMap<Tile, Vec<(Nation, bool)>> = HashMap::new();
+    for (_, unit, owner, map_unit, ..) in query.iter() {
+        units_on_tiles
+            .entry(map_unit.tile)
+            .or_default()
+            .push((owner.nation(), matches!(unit, Unit::Military(_))));
+    }
+
+    for (entity, unit, owner, mut map_unit, mut movement, mut move_path) in query.iter_mut() {
+        if move_path.0.is_empty() || movement.current <= 0. {
+            continue;
+        }
+
+        let zone_of_control = ZoneOfControl::of_enemies(
+            owner.nation(),
+            unit.name(),
+            &military_units,
+            *zone_of_control_rule,
+            &diplomacy,
+            tile_map,
+            ruleset,
+        );
+        let ends_movement = |from, to| zone_of_control.ends_movement(from, to);
+
+        let rules = MovementRules {
+            ends_movement: Some(&ends_movement),
+            ..MovementRules::new(
+                unit.name(),
+                civilizations.get(owner.nation()),
+                movement.max,
+                tile_map,
+                ruleset,
+            )
+        };
+
+        let nation = owner.nation();
+        let is_military = matches!(unit, Unit::Military(_));
+        let is_taken = |units: &Vec<(Nation, bool)>| {
+            units.iter().any(|&(other_nation, other_is_military)| {
+                other_nation != nation || other_is_military == is_military
+            })
+        };
+
+        let mut path = vec![map_unit.tile];
+        while movement.current > 0.
+            && let Some(&next_tile) = move_path.0.front()
+        {
+            let cost = rules
+                .step_cost(map_unit.tile, next_tile)
+                .filter(|_| !units_on_tiles.get(&next_tile).is_some_and(is_taken));
+            let Some(cost) = cost else {
+                // The path is blocked, so the remaining path is dropped.
+                move_path.0.clear();
+                break;
+            };
+            move_path.0.pop_front();
+            if let Some(units) = units_on_tiles.get_mut(&map_unit.tile)
+                && let Some(index) = units
+                    .iter()
+                    .position(|&entry| entry == (nation, is_military))
+            {
+                units.swap_remove(index);
+            }
+            units_on_tiles
+                .entry(next_tile)
+                .or_default()
+                .push((nation, is_military));
+            map_unit.tile = next_tile;
+            movement.current = (movement.current - cost).max(0.);
+            path.push(next_tile);
+        }
+        if path.len() > 1 {
+            unit_moved.write(UnitMoved { unit: entity, path });
+        }
+    }
+}
+
+pub fn restore_movement_points(mut query: Query<&mut Movement>) {
+    for mut movement in query.iter_mut() {
+        movement.current = movement.max;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::matches_unit_filter;
+
+    #[test]
+    fn work_boats_are_civilians() {
+        let ruleset = Ruleset::default();
+        assert_eq!(ruleset.units["Work Boats"].unit_type, "Civilian Water");
+
+        assert!(matches!(
+            unit_kind("Work Boats", &ruleset),
+            Unit::Civilian(_)
+        ));
+        assert!(matches!(unit_kind("Settler", &ruleset), Unit::Civilian(_)));
+        assert!(matches!(unit_kind("Trireme", &ruleset), Unit::Military(_)));
+        assert!(matches_unit_filter("Work Boats", "Civilian", &ruleset));
+        assert!(!matches_unit_filter("Work Boats", "Military", &ruleset));
+    }
+}
@@ -1,10 +1,21 @@
 use bevy::prelude::*;
 use civ_map_generator::nation::Nation;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
 pub enum Owner {
     Civilization(Nation),
     CityState(Nation),
+    Barbarian,
+}
+
+impl Owner {
+    pub fn nation(&self) -> Nation {
+        match *self {
+            Owner::Civilization(nation) | Owner::CityState(nation) => nation,
+            Owner::Barbarian => Nation::Barbarians,
+        }
+    }
 }
 
 #[derive(Component)]
@@ -13,19 +24,64 @@ pub enum Unit {
     Military(String),
 }
 
+impl Unit {
+    pub fn name(&self) -> &str {
+        match self {
+            Unit::Civilian(name) | Unit::Military(name) => name,
+        }
+    }
+}
+
+/// The melee strength of a unit, used to attack and to defend.
 #[derive(Component)]
 pub struct Strength(pub u32);
 
+/// The ranged strength of a unit and the distance it can attack from. Units without ranged attack have a strength of 0.
 #[derive(Component)]
+pub struct RangedStrength {
+    pub strength: u32,
+    pub range: u32,
+}
+
+/// The health of a unit. Units die when their health reaches 0.
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
 pub struct Health {
     pub current: u32,
     pub max: u32,
 }
 
-#[derive(Component)]
+/// The movement points of a unit.
+///
+/// Movement points are fractional because some moves (e.g. along roads) cost less than one point.
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
 pub struct Movement {
-    pub current: u32,
-    pub max: u32,
+    pub current: f32,
+    pub max: f32,
+}
+
+/// A standing order of a unit, kept from turn to turn until the unit moves or is given another order,
+/// see [`crate::unit_order`].
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum UnitOrder {
+    #[default]
+    None,
+    /// The unit gets a defense bonus for every turn it has been fortified.
+    Fortify { turns: u32 },
+    /// The unit waits until it is fully healed.
+    Heal,
+    /// The unit waits until an enemy comes near.
+    Alert,
+    /// The unit waits until the player gives it another order.
+    Sleep,
+    /// The unit waits until the next turn.
+    Skip,
+    /// The worker repairs the pillaged improvement or road of its tile in `turns` turns, see [`crate::pillage`].
+    Repair { turns: u32 },
+    /// The worker builds a road on its tile in `turns` turns, see [`crate::road`].
+    BuildRoad { turns: u32 },
+    /// The unit keeps moving to the closest unexplored tile it can reach, until it is attacked
+    /// or nothing is left to explore.
+    Explore,
 }
 
 #[derive(Component)]
@@ -0,0 +1,264 @@
+//! Selecting several units at once: dragging the left mouse button with `Shift` held draws a band over the map, and
+//! the units of the player inside it are selected together, see [`SelectedGroup`].
+//!
+//! A move order given to the selected unit sends the whole group. The other units of the group spread over the tiles
+//! around its destination, as a tile holds only one military unit and one civilian unit.
+
+use std::collections::HashSet;
+
+use bevy::{picking::hover::HoverMap, prelude::*};
+use civ_map_generator::{grid::hex_grid::Hex, tile::Tile};
+
+use crate::{
+    MainCamera, RulesetResource, TileMapResource,
+    assets::AppState,
+    civilization::{Civilizations, PlayerCivilization},
+    command::{PlayerCommand, UnitId},
+    pathfinding::{MovementRules, find_path},
+    unit::MapUnit,
+    unit_component::{Movement, Owner, Unit},
+    world_map::{
+        MoveMode, MovePathPreview, SelectedUnit, WorldTile, WorldTileEntities,
+        attack_on_right_click, cursor_over_ui, move_order_on_right_click, select_unit_on_click,
+    },
+};
+
+/// The units of the group end their move at most this far from the destination of the selected unit.
+const GROUP_SPREAD: u32 = 2;
+const BAND_COLOR: Color = Color::srgb(1., 0.85, 0.1);
+/// The tiles of the other units of the group are outlined paler than the tile of the selected unit.
+const GROUP_COLOR: Color = Color::srgba(1., 0.85, 0.1, 0.5);
+
+/// The units of the player selected together with a band. The first of them is the [`SelectedUnit`], whose path is
+/// previewed, and the others follow it. Selecting a unit out of the group ends it.
+#[derive(Resource, Default)]
+pub struct SelectedGroup(pub Vec<Entity>);
+
+pub struct UnitGroupPlugin;
+
+impl Plugin for UnitGroupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedGroup>().add_systems(
+            Update,
+            (
+                (band_select, end_group_on_selection_change)
+                    .chain()
+                    .after(select_unit_on_click),
+                move_group
+                    .after(attack_on_right_click)
+                    .before(move_order_on_right_click),
+                draw_selected_group,
+            )
+                .run_if(
+                    in_state(AppState::GameStart)
+                        .and(resource_exists::<TileMapResource>)
+                        .and(resource_exists::<WorldTileEntities>),
+                ),
+        );
+    }
+}
+
+/// Return `true` if `Shift` is held, so a left drag draws a band instead of moving the camera.
+pub fn band_select_held(keyboard_input: &ButtonInput<KeyCode>) -> bool {
+    keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+}
+
+/// Draw the band while the left mouse button is held with `Shift`, and select the units of the player inside it when
+/// the button is released. Military units come first, so one of them becomes the [`SelectedUnit`].
+fn band_select(
+    mut gizmos: Gizmos,
+    input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    hover_map: Res<HoverMap>,
+    ui_nodes: Query<(), With<Node>>,
+    player_civilization: Res<PlayerCivilization>,
+    query_unit: Query<(
+        Entity,
+        &Unit,
+        &Owner,
+        &GlobalTransform,
+        &InheritedVisibility,
+    )>,
+    mut selected_unit: ResMut<SelectedUnit>,
+    mut selected_group: ResMut<SelectedGroup>,
+    // The corner where the band started, and the corner under the cursor, in world coordinates.
+    mut band: Local<Option<(Vec2, Vec2)>>,
+) {
+    let (camera, camera_transform) = *camera;
+    let cursor = window
+        .cursor_position()
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position).ok());
+
+    if input.just_pressed(MouseButton::Left)
+        && band_select_held(&keyboard_input)
+        && !cursor_over_ui(&hover_map, &ui_nodes)
+    {
+        *band = cursor.map(|cursor| (cursor, cursor));
+    }
+    let Some((start, end)) = band.as_mut() else {
+        return;
+    };
+    // The band keeps its last corner while the cursor is out of the window.
+    if let Some(cursor) = cursor {
+        *end = cursor;
+    }
+    let rect = Rect::from_corners(*start, *end);
+
+    if input.pressed(MouseButton::Left) {
+        gizmos.rect_2d(rect.center(), rect.size(), BAND_COLOR);
+        return;
+    }
+    *band = None;
+
+    let player = player_civilization.0;
+    let mut units: Vec<(Entity, bool)> = query_unit
+        .iter()
+        .filter(|(_, _, owner, transform, visibility)| {
+            owner.nation() == player
+                && visibility.get()
+                && rect.contains(transform.translation().truncate())
+        })
+        .map(|(entity, unit, ..)| (entity, matches!(unit, Unit::Military(_))))
+        .collect();
+    if units.is_empty() {
+        return;
+    }
+    units.sort_by_key(|&(entity, is_military)| (!is_military, entity));
+
+    selected_unit.0 = Some(units[0].0);
+    selected_group.0 = units.into_iter().map(|(entity, _)| entity).collect();
+}
+
+/// End the group when a unit out of it is selected, or no unit is.
+fn end_group_on_selection_change(
+    selected_unit: Res<SelectedUnit>,
+    mut selected_group: ResMut<SelectedGroup>,
+) {
+    if selected_unit.is_changed()
+        && !selected_group.0.is_empty()
+        && !selected_unit
+            .0
+            .is_some_and(|selected| selected_group.0.contains(&selected))
+    {
+        selected_group.0.clear();
+    }
+}
+
+/// When the selected unit is ordered to move, send the other units of the group to the closest tiles around its
+/// destination they can reach and are free for a unit of their kind.
+fn move_group(
+    input: Res<ButtonInput<MouseButton>>,
+    hover_map: Res<HoverMap>,
+    ui_nodes: Query<(), With<Node>>,
+    move_mode: Res<MoveMode>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    selected_unit: Res<SelectedUnit>,
+    selected_group: Res<SelectedGroup>,
+    move_path_preview: Res<MovePathPreview>,
+    query_unit: Query<(Entity, &Unit, &Owner, &MapUnit, &Movement)>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if selected_group.0.len() < 2 {
+        return;
+    }
+    // The same conditions as the move of the selected unit, see `move_order_on_right_click`.
+    let order_move = if move_mode.0 {
+        input.just_pressed(MouseButton::Left) && !cursor_over_ui(&hover_map, &ui_nodes)
+    } else {
+        input.just_released(MouseButton::Right)
+    };
+    if !order_move {
+        return;
+    }
+    // An attack clears the previewed path, and the group stays where it is.
+    let (Some(leader), Some(path)) = (selected_unit.0, &move_path_preview.path) else {
+        return;
+    };
+    let (Some(&destination), Ok((_, leader_unit, ..))) =
+        (path.tiles.last(), query_unit.get(leader))
+    else {
+        return;
+    };
+
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+    let grid = tile_map.world_grid.grid;
+    // The tiles taken by the units out of the group, and by the units of the group already sent.
+    let mut taken_tiles: HashSet<(Tile, bool)> = query_unit
+        .iter()
+        .filter(|(entity, ..)| !selected_group.0.contains(entity))
+        .map(|(_, unit, _, map_unit, _)| (map_unit.tile, matches!(unit, Unit::Military(_))))
+        .collect();
+    taken_tiles.insert((destination, matches!(leader_unit, Unit::Military(_))));
+    let mut candidates: Vec<Tile> = destination.tiles_in_distance(GROUP_SPREAD, grid).collect();
+    candidates.sort_by_key(|&tile| tile.distance_to(destination, grid));
+
+    for &entity in &selected_group.0 {
+        if entity == leader {
+            continue;
+        }
+        let Ok((_, unit, owner, map_unit, movement)) = query_unit.get(entity) else {
+            continue;
+        };
+        let is_military = matches!(unit, Unit::Military(_));
+        let rules = MovementRules::new(
+            unit.name(),
+            civilizations.get(owner.nation()),
+            movement.max,
+            tile_map,
+            ruleset,
+        );
+        let Some((tile, path)) = candidates
+            .iter()
+            .filter(|&&tile| !taken_tiles.contains(&(tile, is_military)))
+            .find_map(|&tile| find_path(map_unit.tile, tile, &rules).map(|path| (tile, path)))
+        else {
+            continue;
+        };
+        taken_tiles.insert((tile, is_military));
+        if !path.tiles.is_empty() {
+            player_command.write(PlayerCommand::MoveUnit {
+                unit: UnitId::new(unit, map_unit),
+                path: path.tiles,
+            });
+        }
+    }
+}
+
+/// Outline the tiles of the units of the group, besides the tile of the selected unit.
+fn draw_selected_group(
+    mut gizmos: Gizmos,
+    map: Res<TileMapResource>,
+    tile_entities: Res<WorldTileEntities>,
+    selected_unit: Res<SelectedUnit>,
+    selected_group: Res<SelectedGroup>,
+    query_unit: Query<&MapUnit>,
+    query_tile_transform: Query<&GlobalTransform, With<WorldTile>>,
+) {
+    let corners = map.0.world_grid.grid.layout.all_corners(Hex::new(0, 0));
+    for &entity in &selected_group.0 {
+        if selected_unit.0 == Some(entity) {
+            continue;
+        }
+        let Some(center) = query_unit
+            .get(entity)
+            .ok()
+            .and_then(|map_unit| tile_entities.0.get(&map_unit.tile))
+            .and_then(|&tile_entity| query_tile_transform.get(tile_entity).ok())
+            .map(|transform| transform.translation().truncate())
+        else {
+            continue;
+        };
+        gizmos.linestrip_2d(
+            corners
+                .iter()
+                .chain(corners.first())
+                .map(|&corner| center + Vec2::from(corner)),
+            GROUP_COLOR,
+        );
+    }
+}
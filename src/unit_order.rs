@@ -0,0 +1,309 @@
+use bevy::prelude::*;
+use civ_map_generator::{ruleset::Ruleset, tile::Tile};
+
+use crate::{
+    RulesetResource, TileMapResource,
+    assets::AppState,
+    city::City,
+    civilization::Civilizations,
+    combat::CityStruck,
+    command::{PlayerCommand, UnitId},
+    diplomacy::DiplomacyState,
+    embarkation::Embarked,
+    game_event::CombatResolved,
+    key_bindings::{InputAction, KeyBindings},
+    pathfinding::{MovementRules, find_path_to_closest},
+    turn::{TurnProcessing, TurnSet},
+    unit::{MapUnit, MovePath, UnitDomain, restore_movement_points},
+    unit_component::{Health, Movement, Owner, Unit, UnitOrder},
+    visibility::VisibilityLayer,
+    world_map::SelectedUnit,
+};
+
+/// Every turn spent fortified adds 25% to the defense strength of a unit.
+const FORTIFY_BONUS_PER_TURN: f32 = 0.25;
+/// The fortification bonus stops growing after 2 turns.
+const MAX_FORTIFY_TURNS: u32 = 2;
+/// Units on alert wake up when an enemy military unit comes within this distance.
+const ALERT_DISTANCE: u32 = 2;
+/// The health points healed in a turn by a unit which didn't act, as in Civ V:
+/// in a city of its nation, inside its borders, and anywhere else.
+const HEALING_IN_CITY: u32 = 25;
+const HEALING_IN_FRIENDLY_TERRITORY: u32 = 20;
+const HEALING_ELSEWHERE: u32 = 10;
+
+impl UnitOrder {
+    /// The defense bonus of the order, e.g. `0.5` for a unit fortified for 2 turns.
+    pub fn defense_bonus(&self) -> f32 {
+        match *self {
+            UnitOrder::Fortify { turns } => {
+                turns.min(MAX_FORTIFY_TURNS) as f32 * FORTIFY_BONUS_PER_TURN
+            }
+            _ => 0.,
+        }
+    }
+}
+
+/// Request to give a standing order to a unit.
+#[derive(Message)]
+pub struct SetUnitOrder {
+    pub unit: Entity,
+    pub order: UnitOrder,
+}
+
+pub struct UnitOrderPlugin;
+
+impl Plugin for UnitOrderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SetUnitOrder>()
+            .add_systems(
+                Update,
+                (
+                    set_order_on_key,
+                    set_unit_orders,
+                    cancel_orders_of_moved_units,
+                    stop_exploring_when_attacked,
+                    explore,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            )
+            .add_systems(
+                TurnProcessing,
+                (heal_units, carry_out_orders)
+                    .chain()
+                    .in_set(TurnSet::Units)
+                    .before(restore_movement_points),
+            );
+    }
+}
+
+/// Return `true` if the unit can fortify: only military land units can, and not while they are embarked.
+fn can_fortify(unit: &Unit, is_embarked: bool, ruleset: &Ruleset) -> bool {
+    matches!(unit, Unit::Military(_))
+        && !is_embarked
+        && UnitDomain::of_unit(unit.name(), ruleset) == UnitDomain::Land
+}
+
+/// Give an order to the selected unit with the keys bound to the orders, see [`KeyBindings`]: by default F to
+/// fortify, H to heal, A or X to stay on alert, Z to sleep and O to explore.
+///
+/// Skipping the turn of the unit selects the next unit too, see [`crate::turn_blocker`].
+fn set_order_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    selected_unit: Res<SelectedUnit>,
+    query_unit: Query<(&Unit, &MapUnit)>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    let just_pressed = |action| key_bindings.just_pressed(action, &keyboard_input);
+    let order = if just_pressed(InputAction::Fortify) {
+        UnitOrder::Fortify { turns: 0 }
+    } else if just_pressed(InputAction::Heal) {
+        UnitOrder::Heal
+    } else if just_pressed(InputAction::Alert) {
+        UnitOrder::Alert
+    } else if just_pressed(InputAction::Sleep) {
+        UnitOrder::Sleep
+    } else if just_pressed(InputAction::Explore) {
+        UnitOrder::Explore
+    } else {
+        return;
+    };
+
+    if let Some(selected) = selected_unit.0
+        && let Ok((unit, map_unit)) = query_unit.get(selected)
+    {
+        player_command.write(PlayerCommand::SetOrder {
+            unit: UnitId::new(unit, map_unit),
+            order,
+        });
+    }
+}
+
+/// Give the requested orders to the units, and stop them where they are.
+///
+/// Units that can't fortify are refused the order, and healing units must be damaged.
+/// Ordering a fortified unit to fortify again keeps its bonus.
+fn set_unit_orders(
+    mut set_unit_order: MessageReader<SetUnitOrder>,
+    ruleset: Res<RulesetResource>,
+    mut query_unit: Query<(&Unit, &Health, Has<Embarked>, &mut MovePath, &mut UnitOrder)>,
+) {
+    let ruleset = &ruleset.0;
+
+    for &SetUnitOrder {
+        unit: entity,
+        order,
+    } in set_unit_order.read()
+    {
+        let Ok((unit, health, is_embarked, mut move_path, mut unit_order)) =
+            query_unit.get_mut(entity)
+        else {
+            continue;
+        };
+
+        let order = match order {
+            UnitOrder::Fortify { .. } if !can_fortify(unit, is_embarked, ruleset) => continue,
+            UnitOrder::Fortify { .. } if matches!(*unit_order, UnitOrder::Fortify { .. }) => {
+                continue;
+            }
+            UnitOrder::Fortify { .. } => UnitOrder::Fortify { turns: 0 },
+            UnitOrder::Heal if health.current >= health.max => continue,
+            // Repairs and roads start with a `Repair` or a `BuildRoad` request, which checks the tile.
+            UnitOrder::Repair { .. } | UnitOrder::BuildRoad { .. } => continue,
+            order => order,
+        };
+        move_path.0.clear();
+        *unit_order = order;
+    }
+}
+
+/// Moving cancels the order of a unit, except for exploring units which move on their own.
+/// Units spawned this frame keep their order, e.g. when a game is loaded.
+fn cancel_orders_of_moved_units(mut query_unit: Query<(Ref<MapUnit>, &mut UnitOrder)>) {
+    for (map_unit, mut order) in query_unit.iter_mut() {
+        if map_unit.is_changed()
+            && !map_unit.is_added()
+            && !matches!(*order, UnitOrder::None | UnitOrder::Explore)
+        {
+            *order = UnitOrder::None;
+        }
+    }
+}
+
+/// Exploring units attacked by a unit or a city stop where they are, and wait for the player.
+fn stop_exploring_when_attacked(
+    mut combat_resolved: MessageReader<CombatResolved>,
+    mut city_struck: MessageReader<CityStruck>,
+    mut query_unit: Query<(&mut MovePath, &mut UnitOrder)>,
+) {
+    let attacked = combat_resolved
+        .read()
+        .map(|combat| combat.defender)
+        .chain(city_struck.read().map(|strike| strike.target));
+    for unit in attacked {
+        if let Ok((mut move_path, mut order)) = query_unit.get_mut(unit)
+            && *order == UnitOrder::Explore
+        {
+            move_path.0.clear();
+            *order = UnitOrder::None;
+        }
+    }
+}
+
+/// Send the exploring units which have arrived to the closest unexplored tile they can reach.
+///
+/// Units which can't reach any unexplored tile stop exploring.
+fn explore(
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    civilizations: Res<Civilizations>,
+    visibility_layer: Res<VisibilityLayer>,
+    mut query_unit: Query<
+        (
+            &Unit,
+            &Owner,
+            &MapUnit,
+            &Movement,
+            &mut MovePath,
+            &mut UnitOrder,
+        ),
+        Or<(Changed<MovePath>, Changed<UnitOrder>, Changed<Movement>)>,
+    >,
+) {
+    let tile_map = &map.0;
+    let ruleset = &ruleset.0;
+
+    for (unit, owner, map_unit, movement, mut move_path, mut order) in query_unit.iter_mut() {
+        if *order != UnitOrder::Explore || !move_path.0.is_empty() || movement.current <= 0. {
+            continue;
+        }
+
+        let nation = owner.nation();
+        let rules = MovementRules::new(
+            unit.name(),
+            civilizations.get(nation),
+            movement.max,
+            tile_map,
+            ruleset,
+        );
+        match find_path_to_closest(map_unit.tile, &rules, |tile| {
+            !visibility_layer.is_explored(nation, tile)
+        }) {
+            Some(path) => move_path.0 = path.tiles.into(),
+            None => *order = UnitOrder::None,
+        }
+    }
+}
+
+/// Heal the damaged units which kept all their movement points this turn.
+///
+/// Units heal faster inside the borders of their nation, and even faster in one of its cities.
+fn heal_units(
+    query_city: Query<(&City, &Owner)>,
+    mut query_unit: Query<(&Owner, &MapUnit, &Movement, &mut Health)>,
+) {
+    for (owner, map_unit, movement, mut health) in query_unit.iter_mut() {
+        if health.current >= health.max || movement.current < movement.max {
+            continue;
+        }
+
+        let tile = map_unit.tile;
+        let own_cities = || {
+            query_city
+                .iter()
+                .filter(|(_, city_owner)| city_owner.nation() == owner.nation())
+                .map(|(city, _)| city)
+        };
+        let healing = if own_cities().any(|city| city.tile == tile) {
+            HEALING_IN_CITY
+        } else if own_cities().any(|city| city.owned_tiles.contains(&tile)) {
+            HEALING_IN_FRIENDLY_TERRITORY
+        } else {
+            HEALING_ELSEWHERE
+        };
+        health.current = (health.current + healing).min(health.max);
+    }
+}
+
+/// Carry out the standing orders at the end of the turn.
+///
+/// Fortified units dig in further, healed and skipping units wake up, and units on alert wake up
+/// when an enemy military unit is within [`ALERT_DISTANCE`].
+fn carry_out_orders(
+    map: Res<TileMapResource>,
+    diplomacy: Res<DiplomacyState>,
+    query_military: Query<(&Unit, &Owner, &MapUnit)>,
+    mut query_unit: Query<(&Owner, &MapUnit, &Health, &mut UnitOrder)>,
+) {
+    let grid = map.0.world_grid.grid;
+
+    let military_units: Vec<(Tile, Owner)> = query_military
+        .iter()
+        .filter(|(unit, ..)| matches!(unit, Unit::Military(_)))
+        .map(|(_, &owner, map_unit)| (map_unit.tile, owner))
+        .collect();
+
+    for (owner, map_unit, health, mut order) in query_unit.iter_mut() {
+        match *order {
+            UnitOrder::Fortify { turns } if turns < MAX_FORTIFY_TURNS => {
+                *order = UnitOrder::Fortify { turns: turns + 1 };
+            }
+            UnitOrder::Heal if health.current >= health.max => {
+                *order = UnitOrder::None;
+            }
+            UnitOrder::Skip => *order = UnitOrder::None,
+            UnitOrder::Alert => {
+                let enemy_near = military_units.iter().any(|(tile, enemy)| {
+                    tile.distance_to(map_unit.tile, grid) <= ALERT_DISTANCE
+                        && diplomacy.is_at_war(owner.nation(), enemy.nation())
+                });
+                if enemy_near {
+                    *order = UnitOrder::None;
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,403 @@
+//! The sprites of the units: the icon of every unit on an owner-colored backplate, drawn on the tile it stands on.
+//!
+//! A unit entity is a child of the [`crate::world_map::WorldTile`] entity of its tile, so it follows the tile
+//! when the map wraps around. It is moved to its new tile whenever its [`MapUnit`] changes, and the units
+//! sharing a tile are spread side by side with a badge showing how many they are.
+//!
+//! A unit walking along its path is drawn walking from tile to tile with a [`MoveAnimation`], at the speed of the
+//! [`AnimationSettings`].
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use civ_map_generator::{ruleset::Ruleset, tile::Tile};
+
+use crate::{
+    ColorReplaceMaterial, RulesetResource, TileMapResource,
+    accessibility::{AccessibilitySettings, PlayerColors},
+    animation::AnimationSettings,
+    assets::{AppState, MaterialResource},
+    combat::UnitCaptured,
+    game_event::UnitMoved,
+    grid::map_pixel_width,
+    unit::{MapUnit, SpawnUnit, unit_components, unit_kind},
+    unit_component::{Owner, Unit},
+    world_map::WorldTileEntities,
+};
+
+/// The meshes shared by every unit icon, kept to spawn units after the map is set up.
+#[derive(Resource)]
+pub struct UnitMeshes {
+    inner_rectangle: Handle<Mesh>,
+    outer_rectangle: Handle<Mesh>,
+    tile_pixel_size: Vec2,
+}
+
+impl UnitMeshes {
+    pub fn new(meshes: &mut Assets<Mesh>, tile_pixel_size: Vec2) -> Self {
+        let radius = icon_size(tile_pixel_size);
+        Self {
+            inner_rectangle: meshes.add(Rectangle::new(radius / 2., radius / 2.)),
+            outer_rectangle: meshes.add(Rectangle::new(radius, radius)),
+            tile_pixel_size,
+        }
+    }
+
+    /// The width and the height of the backplate of a unit icon.
+    pub fn icon_size(&self) -> f32 {
+        icon_size(self.tile_pixel_size)
+    }
+}
+
+fn icon_size(tile_pixel_size: Vec2) -> f32 {
+    tile_pixel_size.min_element() / 3.0
+}
+
+/// The badge showing how many units of the same kind share a tile, a child of the first of these units.
+#[derive(Component)]
+pub struct StackBadge;
+
+/// A unit walking along the tiles of its last move, drawn at `offset` from where it stands on its tile.
+#[derive(Component)]
+pub struct MoveAnimation {
+    /// The centers of the tiles of the move relative to the last tile, unwrapped so the unit walks across the
+    /// wrapping edge of the map.
+    waypoints: Vec<Vec2>,
+    /// The number of tiles walked so far.
+    progress: f32,
+    offset: Vec2,
+}
+
+impl MoveAnimation {
+    /// The offset after walking `progress` tiles.
+    fn offset_at(&self, progress: f32) -> Vec2 {
+        let last = self.waypoints.len() - 1;
+        let index = (progress.floor() as usize).min(last);
+        let next = (index + 1).min(last);
+        self.waypoints[index].lerp(self.waypoints[next], progress - index as f32)
+    }
+}
+
+pub struct UnitSpritePlugin;
+
+impl Plugin for UnitSpritePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_units.run_if(on_message::<SpawnUnit>),
+                sync_unit_tiles,
+                arrange_units_on_tiles,
+                start_move_animations.run_if(on_message::<UnitMoved>),
+                animate_unit_moves,
+                recolor_units.run_if(
+                    resource_changed::<AccessibilitySettings>.or(on_message::<UnitCaptured>),
+                ),
+            )
+                .chain()
+                .run_if(resource_exists::<UnitMeshes>.and(resource_exists::<WorldTileEntities>))
+                .run_if(in_state(AppState::GameStart)),
+        );
+    }
+}
+
+/// Spawn the units requested by [`SpawnUnit`] messages as children of their tile.
+pub fn spawn_units(
+    mut commands: Commands,
+    mut spawn_unit: MessageReader<SpawnUnit>,
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    materials: Res<MaterialResource>,
+    unit_meshes: Res<UnitMeshes>,
+    tile_entities: Res<WorldTileEntities>,
+    mut custom_materials: ResMut<Assets<ColorReplaceMaterial>>,
+) {
+    let ruleset = &ruleset.0;
+
+    for SpawnUnit {
+        unit_name,
+        owner,
+        tile,
+    } in spawn_unit.read()
+    {
+        commands
+            .entity(tile_entities.0[tile])
+            .with_child(unit_bundle(
+                unit_name,
+                *owner,
+                *tile,
+                ruleset,
+                &accessibility_settings,
+                &unit_meshes,
+                &mut custom_materials,
+                &materials,
+            ));
+    }
+}
+
+/// The components and the icon of a unit of `owner` on `tile`, with full health and movement points.
+pub fn unit_bundle(
+    unit_name: &str,
+    owner: Owner,
+    tile: Tile,
+    ruleset: &Ruleset,
+    accessibility_settings: &AccessibilitySettings,
+    unit_meshes: &UnitMeshes,
+    custom_materials: &mut ResMut<Assets<ColorReplaceMaterial>>,
+    materials: &MaterialResource,
+) -> impl Bundle {
+    (
+        unit_components(unit_name, tile, ruleset),
+        unit_icon(
+            unit_kind(unit_name, ruleset),
+            owner,
+            accessibility_settings.player_colors(owner.nation(), ruleset),
+            unit_meshes.inner_rectangle.clone(),
+            unit_meshes.outer_rectangle.clone(),
+            custom_materials,
+            materials,
+            unit_meshes.tile_pixel_size,
+        ),
+    )
+}
+
+/// The icon of a unit in the colors of its owner. Military units are drawn in the upper half of the tile
+/// and civilian units in the lower half, so both fit on the same tile.
+fn unit_icon(
+    unit: Unit,
+    owner: Owner,
+    player_colors: PlayerColors,
+    inner_rectangle: Handle<Mesh>,
+    outer_rectangle: Handle<Mesh>,
+    custom_materials: &mut ResMut<Assets<ColorReplaceMaterial>>,
+    materials: &MaterialResource,
+    tile_pixel_size: Vec2,
+) -> impl Bundle {
+    let (unit_name, transform_y, out_texture_name) = match &unit {
+        Unit::Civilian(unit) => (unit.to_owned(), -tile_pixel_size.y / 4., "sv_unitcitizen"),
+        Unit::Military(unit) => (unit.to_owned(), tile_pixel_size.y / 4., "sv_unitmilitary"),
+    };
+
+    (
+        unit,
+        owner,
+        Mesh2d(inner_rectangle.clone()),
+        MeshMaterial2d(custom_materials.add(color_replace_material(
+            player_colors,
+            materials.texture_handle(&unit_name),
+        ))),
+        Transform {
+            translation: Vec3::new(0., transform_y, 6.),
+            ..Default::default()
+        },
+        children![(
+            Mesh2d(outer_rectangle.clone()),
+            MeshMaterial2d(custom_materials.add(color_replace_material(
+                player_colors,
+                materials.texture_handle(out_texture_name),
+            ))),
+            Transform::from_xyz(0., 0., -1.),
+        )],
+    )
+}
+
+fn color_replace_material(
+    player_colors: PlayerColors,
+    texture: Handle<Image>,
+) -> ColorReplaceMaterial {
+    ColorReplaceMaterial {
+        inner_color: LinearRgba::from_u8_array_no_alpha(player_colors.inner_color),
+        outer_color: LinearRgba::from_u8_array_no_alpha(player_colors.outer_color),
+        texture,
+        pattern: player_colors.pattern.shader_value(),
+    }
+}
+
+/// Redraw the icons of the units in the colors of the palette chosen in the accessibility settings,
+/// or of the new owner of a captured unit.
+fn recolor_units(
+    ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    mut custom_materials: ResMut<Assets<ColorReplaceMaterial>>,
+    query_unit: Query<(&Owner, &MeshMaterial2d<ColorReplaceMaterial>, &Children), With<Unit>>,
+    query_backplate: Query<&MeshMaterial2d<ColorReplaceMaterial>>,
+) {
+    if accessibility_settings.is_added() {
+        return;
+    }
+    for (owner, icon_material, children) in query_unit.iter() {
+        let player_colors = accessibility_settings.player_colors(owner.nation(), &ruleset.0);
+        let backplate_materials = children
+            .iter()
+            .filter_map(|child| query_backplate.get(child).ok());
+        for material in std::iter::once(icon_material).chain(backplate_materials) {
+            if let Some(material) = custom_materials.get_mut(&material.0) {
+                let texture = material.texture.clone();
+                *material = color_replace_material(player_colors, texture);
+            }
+        }
+    }
+}
+
+/// Move the units whose tile changed, e.g. after a move or a victorious attack, under the entity of their new tile.
+fn sync_unit_tiles(
+    mut commands: Commands,
+    tile_entities: Res<WorldTileEntities>,
+    query_unit: Query<(Entity, &MapUnit, &ChildOf), Changed<MapUnit>>,
+) {
+    for (entity, map_unit, child_of) in query_unit.iter() {
+        let tile_entity = tile_entities.0[&map_unit.tile];
+        if child_of.parent() != tile_entity {
+            commands.entity(entity).insert(ChildOf(tile_entity));
+        }
+    }
+}
+
+/// Spread the units of the same kind sharing a tile side by side, e.g. units in a city or embarked units,
+/// and show their number with a [`StackBadge`] on the first of them.
+///
+/// The units are only arranged again when a unit moves, is spawned or is removed.
+fn arrange_units_on_tiles(
+    mut commands: Commands,
+    unit_meshes: Res<UnitMeshes>,
+    mut removed_units: RemovedComponents<MapUnit>,
+    query_changed: Query<(), Changed<MapUnit>>,
+    mut query_unit: Query<(
+        Entity,
+        &Unit,
+        &MapUnit,
+        &mut Transform,
+        Option<&MoveAnimation>,
+    )>,
+    query_badge: Query<Entity, With<StackBadge>>,
+) {
+    let units_removed = removed_units.read().count() > 0;
+    if !units_removed && query_changed.is_empty() {
+        return;
+    }
+
+    // Group the units by tile and by kind, in a fixed order so the same unit stays first in its stack.
+    let mut stacks: BTreeMap<_, Vec<Entity>> = BTreeMap::new();
+    for (entity, unit, map_unit, _) in query_unit.iter() {
+        let is_military = matches!(unit, Unit::Military(_));
+        stacks
+            .entry((map_unit.tile.index(), is_military))
+            .or_default()
+            .push(entity);
+    }
+
+    for badge in query_badge.iter() {
+        commands.entity(badge).despawn();
+    }
+
+    let tile_width = unit_meshes.tile_pixel_size.x;
+    for mut units in stacks.into_values() {
+        units.sort();
+        let count = units.len();
+        // The stack is at most half a tile wide, the icons overlapping when there are many units.
+        let spacing = (tile_width / 6.).min(tile_width / 2. / count as f32);
+        for (index, &entity) in units.iter().enumerate() {
+            if let Ok((.., mut transform, move_animation)) = query_unit.get_mut(entity) {
+                // A walking unit keeps its offset along its path.
+                let offset = move_animation.map_or(0., |move_animation| move_animation.offset.x);
+                transform.translation.x =
+                    (index as f32 - (count - 1) as f32 / 2.) * spacing + offset;
+            }
+        }
+
+        if count > 1 {
+            commands.entity(units[0]).with_child((
+                StackBadge,
+                Text2d::new(count.to_string()),
+                TextFont {
+                    font_size: 12.,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                Transform::from_xyz(-tile_width / 8., tile_width / 12., 1.),
+            ));
+        }
+    }
+}
+
+/// Start walking the units which moved from their first tile, unless the animations are skipped.
+///
+/// The units are already children of their last tile, see [`sync_unit_tiles`], so they are drawn back at their
+/// first tile at once, before a frame shows them on their last tile.
+fn start_move_animations(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    animation_settings: Res<AnimationSettings>,
+    mut unit_moved: MessageReader<UnitMoved>,
+    mut query_unit: Query<(&mut Transform, Option<&MoveAnimation>)>,
+) {
+    if animation_settings.skip_animations {
+        unit_moved.clear();
+        return;
+    }
+
+    let grid = map.0.world_grid.grid;
+    let map_width = map_pixel_width(grid);
+    let position_of = |tile: Tile| Vec2::from(grid.offset_to_pixel(tile.to_offset(grid)));
+
+    for UnitMoved { unit, path } in unit_moved.read() {
+        let Ok((mut transform, move_animation)) = query_unit.get_mut(*unit) else {
+            continue;
+        };
+        // A unit moving again before the end of its last walk starts from the end of it.
+        if let Some(move_animation) = move_animation {
+            transform.translation -= move_animation.offset.extend(0.);
+        }
+
+        let mut waypoints: Vec<Vec2> = Vec::with_capacity(path.len());
+        for &tile in path {
+            let mut position = position_of(tile);
+            if let Some(&previous) = waypoints.last()
+                && grid.wrap_x()
+            {
+                position.x -= ((position.x - previous.x) / map_width).round() * map_width;
+            }
+            waypoints.push(position);
+        }
+        let Some(&last) = waypoints.last() else {
+            continue;
+        };
+        for waypoint in &mut waypoints {
+            *waypoint -= last;
+        }
+
+        let offset = waypoints[0];
+        transform.translation += offset.extend(0.);
+        commands.entity(*unit).try_insert(MoveAnimation {
+            waypoints,
+            progress: 0.,
+            offset,
+        });
+    }
+}
+
+/// Walk the units along their path, and stop them on their tile at the end of it.
+fn animate_unit_moves(
+    mut commands: Commands,
+    time: Res<Time>,
+    animation_settings: Res<AnimationSettings>,
+    mut query_unit: Query<(Entity, &mut Transform, &mut MoveAnimation)>,
+) {
+    for (entity, mut transform, mut move_animation) in query_unit.iter_mut() {
+        let end = (move_animation.waypoints.len() - 1) as f32;
+        move_animation.progress = if animation_settings.skip_animations {
+            end
+        } else {
+            (move_animation.progress
+                + animation_settings.speed.tiles_per_second() * time.delta_secs())
+            .min(end)
+        };
+
+        let offset = move_animation.offset_at(move_animation.progress);
+        transform.translation += (offset - move_animation.offset).extend(0.);
+        move_animation.offset = offset;
+        if move_animation.progress >= end {
+            commands.entity(entity).try_remove::<MoveAnimation>();
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! The health bars and the status icons drawn over the units: the health of a damaged unit above its icon, and
+//! on its right whether it is fortified, embarked, or, for the units of the player, out of movement points.
+//!
+//! They are children of their unit, so they follow it when it walks or when the map wraps around. They keep a
+//! readable size as the main camera zooms out, and are hidden when it is zoomed out too far to tell the units apart.
+
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::{
+    MainCamera,
+    assets::AppState,
+    civilization::PlayerCivilization,
+    embarkation::Embarked,
+    unit::MapUnit,
+    unit_component::{Health, Movement, Owner, UnitOrder},
+    unit_sprite::UnitMeshes,
+};
+
+/// The health bars and the status icons can't shrink or grow beyond these scales, so they stay readable at every
+/// zoom without covering the tile.
+const MIN_STATUS_SCALE: f32 = 0.75;
+const MAX_STATUS_SCALE: f32 = 1.5;
+/// The health bars and the status icons are hidden when the main camera is zoomed out beyond this scale.
+const HIDE_STATUS_ZOOM: f32 = 3.;
+
+const HEALTH_BAR_HEIGHT: f32 = 4.;
+const HEALTH_BAR_BACKGROUND: Color = Color::srgba(0., 0., 0., 0.8);
+const STATUS_ICON_SIZE: f32 = 12.;
+
+/// A status shown by an icon next to a unit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum UnitStatusIcon {
+    Fortified,
+    Embarked,
+    OutOfMoves,
+}
+
+impl UnitStatusIcon {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Fortified => "F",
+            Self::Embarked => "E",
+            Self::OutOfMoves => "0",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Self::Fortified => Color::srgb(0.35, 0.45, 0.6),
+            Self::Embarked => Color::srgb(0.1, 0.45, 0.8),
+            Self::OutOfMoves => Color::srgb(0.4, 0.4, 0.4),
+        }
+    }
+}
+
+/// The health bar and the status icons of a unit, a child of the unit on top of its icon.
+#[derive(Component)]
+struct UnitStatus {
+    health_bar: Entity,
+    health_fill: Entity,
+    icons: Entity,
+}
+
+pub struct UnitStatusPlugin;
+
+impl Plugin for UnitStatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_unit_statuses,
+                update_unit_statuses,
+                scale_unit_statuses,
+            )
+                .chain()
+                .run_if(in_state(AppState::GameStart).and(resource_exists::<UnitMeshes>)),
+        );
+    }
+}
+
+/// Spawn the health bar and the status icons of the new units, updated by [`update_unit_statuses`].
+fn spawn_unit_statuses(
+    mut commands: Commands,
+    unit_meshes: Res<UnitMeshes>,
+    query_unit: Query<Entity, Added<MapUnit>>,
+) {
+    let icon_size = unit_meshes.icon_size();
+    for unit in query_unit.iter() {
+        let health_fill = commands
+            .spawn((
+                Sprite::from_color(Color::WHITE, Vec2::new(icon_size, HEALTH_BAR_HEIGHT)),
+                // The fill shrinks toward the left edge of the bar.
+                Anchor::CENTER_LEFT,
+                Transform::from_xyz(-icon_size / 2., 0., 0.1),
+            ))
+            .id();
+        let health_bar = commands
+            .spawn((
+                Sprite::from_color(
+                    HEALTH_BAR_BACKGROUND,
+                    Vec2::new(icon_size + 2., HEALTH_BAR_HEIGHT + 2.),
+                ),
+                Transform::default(),
+                Visibility::Hidden,
+            ))
+            .add_child(health_fill)
+            .id();
+        let icons = commands
+            .spawn((
+                Transform::from_xyz(icon_size / 2. + STATUS_ICON_SIZE / 2., 0., 0.),
+                Visibility::Inherited,
+            ))
+            .id();
+        let status = commands
+            .spawn((
+                UnitStatus {
+                    health_bar,
+                    health_fill,
+                    icons,
+                },
+                // Just above the icon of the unit, over the other units of the tile.
+                Transform::from_xyz(0., icon_size / 2. + HEALTH_BAR_HEIGHT, 3.),
+                Visibility::Inherited,
+            ))
+            .add_children(&[health_bar, icons])
+            .id();
+        commands.entity(unit).add_child(status);
+    }
+}
+
+/// Redraw the health bar and the status icons of the units whose health, movement points or order changed, or
+/// which embarked or disembarked.
+fn update_unit_statuses(
+    mut commands: Commands,
+    player_civilization: Res<PlayerCivilization>,
+    mut disembarked: RemovedComponents<Embarked>,
+    query_changed: Query<
+        Entity,
+        Or<(
+            Changed<Health>,
+            Changed<Movement>,
+            Changed<UnitOrder>,
+            Added<Embarked>,
+        )>,
+    >,
+    query_new_status: Query<&ChildOf, Added<UnitStatus>>,
+    query_unit: Query<(
+        &Health,
+        &Movement,
+        &UnitOrder,
+        &Owner,
+        Has<Embarked>,
+        &Children,
+    )>,
+    query_status: Query<&UnitStatus>,
+    mut query_sprite: Query<(&mut Sprite, &mut Transform, &mut Visibility)>,
+) {
+    let units: Vec<Entity> = query_changed
+        .iter()
+        .chain(disembarked.read())
+        .chain(query_new_status.iter().map(ChildOf::parent))
+        .collect();
+    for unit in units {
+        let Ok((health, movement, order, owner, is_embarked, children)) = query_unit.get(unit)
+        else {
+            continue;
+        };
+        let Some(status) = children
+            .iter()
+            .find_map(|child| query_status.get(child).ok())
+        else {
+            continue;
+        };
+
+        let health_fraction = health.current as f32 / health.max.max(1) as f32;
+        if let Ok((.., mut visibility)) = query_sprite.get_mut(status.health_bar) {
+            *visibility = if health_fraction < 1. {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+        if let Ok((mut sprite, mut transform, _)) = query_sprite.get_mut(status.health_fill) {
+            // From green at full health to red near death.
+            sprite.color = Color::hsl(health_fraction * 120., 0.8, 0.45);
+            transform.scale.x = health_fraction;
+        }
+
+        let mut icons = Vec::new();
+        if matches!(order, UnitOrder::Fortify { .. }) {
+            icons.push(UnitStatusIcon::Fortified);
+        }
+        if is_embarked {
+            icons.push(UnitStatusIcon::Embarked);
+        }
+        if owner.nation() == player_civilization.0 && movement.current <= 0. {
+            icons.push(UnitStatusIcon::OutOfMoves);
+        }
+        commands.entity(status.icons).despawn_related::<Children>();
+        for (index, icon) in icons.into_iter().enumerate() {
+            commands.entity(status.icons).with_child((
+                Sprite::from_color(icon.color(), Vec2::splat(STATUS_ICON_SIZE)),
+                Transform::from_xyz(0., -(index as f32) * (STATUS_ICON_SIZE + 1.), 0.),
+                children![(
+                    Text2d::new(icon.label()),
+                    TextFont {
+                        font_size: 10.,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Transform::from_xyz(0., 0., 0.1),
+                )],
+            ));
+        }
+    }
+}
+
+/// Keep the health bars and the status icons readable as the main camera zooms, and hide them when it is zoomed
+/// out too far.
+fn scale_unit_statuses(
+    projection: Single<Ref<Projection>, With<MainCamera>>,
+    mut query_status: Query<(Ref<UnitStatus>, &mut Transform, &mut Visibility)>,
+) {
+    let zoom = match &**projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.,
+    };
+    let scale = zoom.clamp(MIN_STATUS_SCALE, MAX_STATUS_SCALE);
+    let visibility = if zoom > HIDE_STATUS_ZOOM {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    for (status, mut transform, mut status_visibility) in query_status.iter_mut() {
+        if !projection.is_changed() && !status.is_added() {
+            continue;
+        }
+        transform.scale = Vec3::splat(scale);
+        *status_visibility = visibility;
+    }
+}
@@ -0,0 +1,164 @@
+//! Upgrading units to the unit their type leads to, e.g. a Warrior to a Swordsman, for gold.
+//!
+//! A unit upgrades inside the borders of its nation, once its nation can build the new unit. The unit keeps its
+//! health and its order, but spends the rest of its turn upgrading.
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset};
+
+use crate::{
+    ColorReplaceMaterial, RulesetResource,
+    assets::{AppState, MaterialResource},
+    city::{City, can_build_unit},
+    civilization::{Civilization, Civilizations},
+    command::{PlayerCommand, UnitId},
+    embarkation::Embarked,
+    key_bindings::{InputAction, KeyBindings},
+    unit::{MapUnit, unit_kind},
+    unit_component::{Movement, Owner, RangedStrength, Strength, Unit},
+    world_map::SelectedUnit,
+};
+
+/// The gold an upgrade costs besides twice the difference of production cost of the two units, as in Civ V.
+const UPGRADE_BASE_COST: f32 = 10.;
+const UPGRADE_COST_PER_PRODUCTION: f32 = 2.;
+
+/// Request to upgrade a unit, see [`upgrade_of`].
+#[derive(Message)]
+pub struct UpgradeUnit {
+    pub unit: Entity,
+}
+
+pub struct UnitUpgradePlugin;
+
+impl Plugin for UnitUpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<UpgradeUnit>().add_systems(
+            Update,
+            (
+                upgrade_unit_on_key,
+                upgrade_units.run_if(on_message::<UpgradeUnit>),
+            )
+                .chain()
+                .run_if(in_state(AppState::GameStart).and(resource_exists::<MaterialResource>)),
+        );
+    }
+}
+
+/// The unit `unit_name` upgrades to for `nation`, or `None` if it has no upgrade or the nation can't build it yet.
+/// A nation with a unique unit replacing the upgrade gets its unique unit, e.g. a Roman Warrior becomes a Legion.
+pub fn upgrade_of(
+    unit_name: &str,
+    nation: Nation,
+    civilization: &Civilization,
+    ruleset: &Ruleset,
+) -> Option<String> {
+    let upgrade = &ruleset.units[unit_name].upgrades_to;
+    if upgrade.is_empty() {
+        return None;
+    }
+    let upgrade = ruleset
+        .units
+        .iter()
+        .find(|(_, unit)| unit.unique_to == nation.as_str() && &unit.replaces == upgrade)
+        .map_or(upgrade, |(name, _)| name);
+    can_build_unit(upgrade, nation, civilization, ruleset).then(|| upgrade.clone())
+}
+
+/// The gold needed to upgrade `unit_name` to `upgrade`.
+pub fn upgrade_cost(unit_name: &str, upgrade: &str, ruleset: &Ruleset) -> f32 {
+    let production = ruleset.units[upgrade].cost as f32 - ruleset.units[unit_name].cost as f32;
+    UPGRADE_BASE_COST + UPGRADE_COST_PER_PRODUCTION * production.max(0.)
+}
+
+fn upgrade_unit_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    selected_unit: Res<SelectedUnit>,
+    query_unit: Query<(&Unit, &MapUnit)>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if key_bindings.just_pressed(InputAction::UpgradeUnit, &keyboard_input)
+        && let Some(selected) = selected_unit.0
+        && let Ok((unit, map_unit)) = query_unit.get(selected)
+    {
+        player_command.write(PlayerCommand::UpgradeUnit {
+            unit: UnitId::new(unit, map_unit),
+        });
+    }
+}
+
+/// Upgrade the requested units and take the gold from their nation.
+///
+/// The unit must stand inside the borders of its nation, not embarked, with movement points left, and its nation
+/// must have the gold. The icon of the unit shows the new unit.
+fn upgrade_units(
+    mut upgrade_unit: MessageReader<UpgradeUnit>,
+    ruleset: Res<RulesetResource>,
+    materials: Res<MaterialResource>,
+    mut civilizations: ResMut<Civilizations>,
+    mut custom_materials: ResMut<Assets<ColorReplaceMaterial>>,
+    query_city: Query<(&City, &Owner)>,
+    mut query_unit: Query<
+        (
+            &mut Unit,
+            &Owner,
+            &MapUnit,
+            &mut Movement,
+            &mut Strength,
+            &mut RangedStrength,
+            &MeshMaterial2d<ColorReplaceMaterial>,
+        ),
+        Without<Embarked>,
+    >,
+) {
+    let ruleset = &ruleset.0;
+
+    for &UpgradeUnit { unit: entity } in upgrade_unit.read() {
+        let Ok((
+            mut unit,
+            owner,
+            map_unit,
+            mut movement,
+            mut strength,
+            mut ranged_strength,
+            icon_material,
+        )) = query_unit.get_mut(entity)
+        else {
+            continue;
+        };
+
+        let nation = owner.nation();
+        let in_own_borders = query_city.iter().any(|(city, city_owner)| {
+            city_owner.nation() == nation && city.owned_tiles.contains(&map_unit.tile)
+        });
+        if !in_own_borders || movement.current <= 0. {
+            continue;
+        }
+        let Some(upgrade) = upgrade_of(unit.name(), nation, civilizations.get(nation), ruleset)
+        else {
+            continue;
+        };
+        let cost = upgrade_cost(unit.name(), &upgrade, ruleset);
+        let civilization = civilizations.get_mut(nation);
+        if civilization.gold < cost {
+            continue;
+        }
+        civilization.gold -= cost;
+
+        let new_unit = &ruleset.units[&upgrade];
+        *strength = Strength(new_unit.strength.max(0) as u32);
+        *ranged_strength = RangedStrength {
+            strength: new_unit.ranged_strength.max(0) as u32,
+            range: new_unit.range.max(0) as u32,
+        };
+        *movement = Movement {
+            current: 0.,
+            max: new_unit.movement as f32,
+        };
+        if let Some(material) = custom_materials.get_mut(&icon_material.0) {
+            material.texture = materials.texture_handle(&upgrade);
+        }
+        *unit = unit_kind(&upgrade, ruleset);
+    }
+}
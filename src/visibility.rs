@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::VertexAttributeValues,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use civ_map_generator::{
+    grid::{Grid, hex_grid::HexGrid},
+    nation::Nation,
+    ruleset::Ruleset,
+    tile::Tile,
+    tile_map::TileMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource, assets::AppState, city::City,
+    civilization::PlayerCivilization, custom_mesh::hex_mesh, grid::has_line_of_sight,
+    unit::MapUnit, unit_component::Owner, world_map::WorldTileEntities,
+};
+
+/// How much a civilization knows about a tile.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum TileVisibility {
+    /// The tile has never been seen.
+    #[default]
+    Unexplored,
+    /// The tile has been seen before, but is not seen now.
+    Explored,
+    /// The tile is currently seen by a unit or a city.
+    Visible,
+}
+
+/// The visibility of every tile for every civilization and city-state.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct VisibilityLayer {
+    tile_count: usize,
+    nation_and_visibility_list: HashMap<Nation, Vec<TileVisibility>>,
+}
+
+impl VisibilityLayer {
+    pub fn new(tile_count: usize) -> Self {
+        Self {
+            tile_count,
+            nation_and_visibility_list: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, nation: Nation, tile: Tile) -> TileVisibility {
+        self.nation_and_visibility_list
+            .get(&nation)
+            .map_or(TileVisibility::Unexplored, |visibility_list| {
+                visibility_list[tile.index()]
+            })
+    }
+
+    pub fn is_visible(&self, nation: Nation, tile: Tile) -> bool {
+        self.get(nation, tile) == TileVisibility::Visible
+    }
+
+    pub fn is_explored(&self, nation: Nation, tile: Tile) -> bool {
+        self.get(nation, tile) != TileVisibility::Unexplored
+    }
+
+    fn visibility_list_mut(&mut self, nation: Nation) -> &mut Vec<TileVisibility> {
+        let tile_count = self.tile_count;
+        self.nation_and_visibility_list
+            .entry(nation)
+            .or_insert_with(|| vec![TileVisibility::Unexplored; tile_count])
+    }
+
+    /// Turn every currently visible tile into an explored tile.
+    fn hide_all(&mut self) {
+        self.nation_and_visibility_list
+            .values_mut()
+            .flatten()
+            .filter(|visibility| **visibility == TileVisibility::Visible)
+            .for_each(|visibility| *visibility = TileVisibility::Explored);
+    }
+}
+
+/// How many tiles away a unit or a city can see.
+#[derive(Component)]
+pub struct SightRange(pub u32);
+
+/// In Civ V, units and cities see 2 tiles away.
+pub const DEFAULT_SIGHT_RANGE: u32 = 2;
+
+impl Default for SightRange {
+    fn default() -> Self {
+        Self(DEFAULT_SIGHT_RANGE)
+    }
+}
+
+/// Return every tile within `range` of `origin` which can be seen from `origin`.
+pub fn visible_tiles(origin: Tile, range: u32, tile_map: &TileMap, ruleset: &Ruleset) -> Vec<Tile> {
+    let grid = tile_map.world_grid.grid;
+    origin
+        .tiles_in_distance(range, grid)
+        .filter(|&tile| has_line_of_sight(origin, tile, tile_map, ruleset))
+        .collect()
+}
+
+pub struct VisibilityPlugin;
+
+impl Plugin for VisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameStart), setup_visibility_layer)
+            .add_systems(
+                Update,
+                (
+                    setup_fog_overlay.run_if(resource_added::<WorldTileEntities>),
+                    update_visibility,
+                    render_fog.run_if(
+                        resource_exists_and_changed::<VisibilityLayer>
+                            .or(resource_added::<FogOverlays>),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::GameStart)),
+            );
+    }
+}
+
+fn setup_visibility_layer(mut commands: Commands, map: Res<TileMapResource>) {
+    let grid = map.0.world_grid.grid;
+    let tile_count = (grid.width() * grid.height()) as usize;
+    commands.insert_resource(VisibilityLayer::new(tile_count));
+}
+
+/// Recalculate what every civilization sees whenever a unit moves or a sight source is added or removed.
+fn update_visibility(
+    map: Option<Res<TileMapResource>>,
+    ruleset: Res<RulesetResource>,
+    visibility_layer: Option<ResMut<VisibilityLayer>>,
+    query_changed: Query<(), Or<(Changed<MapUnit>, Changed<SightRange>)>>,
+    mut removed: RemovedComponents<SightRange>,
+    query_unit: Query<(&Owner, &SightRange, &MapUnit)>,
+    query_city: Query<(&Owner, &SightRange, &City)>,
+) {
+    let (Some(map), Some(mut visibility_layer)) = (map, visibility_layer) else {
+        return;
+    };
+
+    if query_changed.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+
+    let tile_map = &map.0;
+
+    visibility_layer.hide_all();
+
+    let sight_sources = query_unit
+        .iter()
+        .map(|(owner, sight_range, map_unit)| (owner, sight_range, map_unit.tile))
+        .chain(
+            query_city
+                .iter()
+                .map(|(owner, sight_range, city)| (owner, sight_range, city.tile)),
+        );
+
+    for (owner, sight_range, origin) in sight_sources {
+        let visibility_list = visibility_layer.visibility_list_mut(owner.nation());
+        for tile in visible_tiles(origin, sight_range.0, tile_map, &ruleset.0) {
+            visibility_list[tile.index()] = TileVisibility::Visible;
+        }
+    }
+}
+
+/// The overlay drawn above a tile which is not visible to the player.
+#[derive(Component)]
+struct FogOverlay;
+
+/// The fog overlays of the tiles, and what they show, so only the overlays of the tiles
+/// whose visibility changed are updated.
+#[derive(Resource)]
+struct FogOverlays {
+    unexplored: Handle<ColorMaterial>,
+    explored: Handle<ColorMaterial>,
+    /// The overlay of every tile, by tile index.
+    entities: Vec<Entity>,
+    /// The visibility shown by the overlay of every tile, `None` until it is drawn.
+    drawn: Vec<Option<TileVisibility>>,
+    /// The civilization whose visibility is drawn.
+    player: Option<Nation>,
+}
+
+/// The width and the height in pixels of the cloud texture of the unexplored tiles.
+const CLOUD_TEXTURE_SIZE: u32 = 64;
+
+fn setup_fog_overlay(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    tile_entities: Res<WorldTileEntities>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let grid = map.0.world_grid.grid;
+    let fog_mesh = meshes.add(fog_mesh(&grid));
+
+    let unexplored = color_materials.add(ColorMaterial {
+        texture: Some(images.add(cloud_image())),
+        ..ColorMaterial::default()
+    });
+    // A dark gray veil, which makes the explored tiles look darker and duller than the visible ones.
+    let explored = color_materials.add(ColorMaterial::from_color(Color::srgba(
+        0.12, 0.12, 0.14, 0.6,
+    )));
+
+    let tile_count = (grid.width() * grid.height()) as usize;
+    let entities = (0..tile_count)
+        .map(|index| {
+            commands
+                .spawn((
+                    Mesh2d(fog_mesh.clone()),
+                    MeshMaterial2d(unexplored.clone()),
+                    // Draw the fog above everything on the tile, including units.
+                    Transform::from_xyz(0., 0., 10.),
+                    FogOverlay,
+                    ChildOf(tile_entities.0[&Tile::new(index)]),
+                ))
+                .id()
+        })
+        .collect();
+
+    commands.insert_resource(FogOverlays {
+        unexplored,
+        explored,
+        entities,
+        drawn: vec![None; tile_count],
+        player: None,
+    });
+}
+
+/// The mesh of a hexagon with texture coordinates, mapping the texture on the bounding box of the hexagon.
+fn fog_mesh(grid: &HexGrid) -> Mesh {
+    let mesh = hex_mesh(grid);
+    let size = Vec2::from(grid.layout.size) * 2.;
+    let uvs: Vec<[f32; 2]> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions
+            .iter()
+            .map(|&[x, y, _]| [x / size.x + 0.5, 0.5 - y / size.y])
+            .collect(),
+        _ => Vec::new(),
+    };
+    mesh.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
+
+/// A dark cloudy texture made of a few octaves of value noise. The noise wraps around the texture,
+/// so neighboring unexplored tiles don't show hard seams.
+fn cloud_image() -> Image {
+    let size = CLOUD_TEXTURE_SIZE as usize;
+    // A fixed lattice of random values, the same in every game.
+    let lattice = |x: usize, y: usize, period: usize| {
+        let mut seed = ((x % period) * 7919 + (y % period) * 104_729 + period) as u64;
+        seed = (seed ^ (seed >> 33)).wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+        seed = (seed ^ (seed >> 33)).wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+        (seed >> 40) as f32 / (1u64 << 24) as f32
+    };
+    let smooth = |t: f32| t * t * (3. - 2. * t);
+
+    let mut data = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let mut value = 0.;
+            let mut weight = 0.5;
+            for period in [4, 8, 16] {
+                let cell = size as f32 / period as f32;
+                let (fx, fy) = (x as f32 / cell, y as f32 / cell);
+                let (x0, y0) = (fx as usize, fy as usize);
+                let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+                let top = lattice(x0, y0, period) * (1. - tx) + lattice(x0 + 1, y0, period) * tx;
+                let bottom =
+                    lattice(x0, y0 + 1, period) * (1. - tx) + lattice(x0 + 1, y0 + 1, period) * tx;
+                value += (top * (1. - ty) + bottom * ty) * weight;
+                weight /= 2.;
+            }
+            // Mostly black, with faint gray wisps.
+            let shade = (value.powi(3) * 90.) as u8;
+            data.extend([shade, shade, shade.saturating_add(6), 255]);
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: CLOUD_TEXTURE_SIZE,
+            height: CLOUD_TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// Darken the tiles explored but not visible to the player, cover the unexplored ones with clouds,
+/// and hide foreign units on tiles the player doesn't see.
+///
+/// Only the overlays of the tiles whose visibility changed since they were drawn are updated.
+fn render_fog(
+    player_civilization: Option<Res<PlayerCivilization>>,
+    visibility_layer: Res<VisibilityLayer>,
+    fog_overlays: Option<ResMut<FogOverlays>>,
+    mut query_fog: Query<(&mut Visibility, &mut MeshMaterial2d<ColorMaterial>), With<FogOverlay>>,
+    mut query_unit: Query<(&MapUnit, &Owner, &mut Visibility), Without<FogOverlay>>,
+) {
+    let (Some(player_civilization), Some(mut fog_overlays)) = (player_civilization, fog_overlays)
+    else {
+        return;
+    };
+    let player = player_civilization.0;
+    // What is drawn is only a cache, changing it must not run this system again.
+    let fog_overlays = fog_overlays.bypass_change_detection();
+    if fog_overlays.player != Some(player) {
+        fog_overlays.player = Some(player);
+        fog_overlays.drawn.fill(None);
+    }
+
+    for (index, &entity) in fog_overlays.entities.iter().enumerate() {
+        let tile_visibility = visibility_layer.get(player, Tile::new(index));
+        if fog_overlays.drawn[index] == Some(tile_visibility) {
+            continue;
+        }
+        fog_overlays.drawn[index] = Some(tile_visibility);
+
+        let Ok((mut visibility, mut material)) = query_fog.get_mut(entity) else {
+            continue;
+        };
+        match tile_visibility {
+            TileVisibility::Visible => *visibility = Visibility::Hidden,
+            TileVisibility::Explored => {
+                *visibility = Visibility::Inherited;
+                material.0 = fog_overlays.explored.clone();
+            }
+            TileVisibility::Unexplored => {
+                *visibility = Visibility::Inherited;
+                material.0 = fog_overlays.unexplored.clone();
+            }
+        }
+    }
+
+    for (map_unit, owner, mut visibility) in query_unit.iter_mut() {
+        *visibility =
+            if owner.nation() == player || visibility_layer.is_visible(player, map_unit.tile) {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+    }
+}
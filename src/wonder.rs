@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use civ_map_generator::{nation::Nation, ruleset::Ruleset, tile::Tile};
+
+use crate::{
+    RulesetResource,
+    city::{City, CityProduction, produce_in_cities},
+    civilization::Civilizations,
+    turn::{TurnProcessing, TurnSet},
+    unit_component::Owner,
+};
+
+/// The start of the unique `Requires a [Building] in all cities` of the national wonders.
+const REQUIRES_IN_ALL_CITIES_PREFIX: &str = "Requires a [";
+const REQUIRES_IN_ALL_CITIES_SUFFIX: &str = "] in all cities";
+
+/// The wonders built in the world, gathered from the buildings of the cities.
+///
+/// Wonders are not stored anywhere else, so a captured city brings its wonders to its new owner.
+#[derive(Default)]
+pub struct Wonders {
+    /// The world wonders built by any nation.
+    world: HashSet<String>,
+    /// The national wonders built by every nation.
+    national: HashMap<Nation, HashSet<String>>,
+    /// The buildings every city of a nation has, see [`required_in_all_cities`].
+    in_every_city: HashMap<Nation, HashSet<String>>,
+}
+
+impl Wonders {
+    /// Gather the wonders of `cities`, given with the nation owning them.
+    pub fn of_cities<'a>(
+        cities: impl IntoIterator<Item = (&'a City, Nation)>,
+        ruleset: &Ruleset,
+    ) -> Self {
+        let mut wonders = Self::default();
+        for (city, nation) in cities {
+            for building in &city.buildings {
+                let info = &ruleset.buildings[building];
+                if info.is_wonder {
+                    wonders.world.insert(building.clone());
+                } else if info.is_national_wonder {
+                    wonders
+                        .national
+                        .entry(nation)
+                        .or_default()
+                        .insert(building.clone());
+                }
+            }
+
+            let buildings: HashSet<String> = city.buildings.iter().cloned().collect();
+            wonders
+                .in_every_city
+                .entry(nation)
+                .and_modify(|in_every_city| in_every_city.retain(|b| buildings.contains(b)))
+                .or_insert(buildings);
+        }
+        wonders
+    }
+
+    /// Return `true` if `nation` can still build the building `building_name`.
+    ///
+    /// A world wonder can only be built once in the world, and a national wonder once by every nation,
+    /// after the building of its `Requires a [Building] in all cities` unique has been built in all the cities
+    /// of the nation.
+    /// Other buildings are always allowed.
+    pub fn allows(&self, building_name: &str, nation: Nation, ruleset: &Ruleset) -> bool {
+        let building = &ruleset.buildings[building_name];
+        if building.is_wonder {
+            !self.world.contains(building_name)
+        } else if building.is_national_wonder {
+            let built = self
+                .national
+                .get(&nation)
+                .is_some_and(|national| national.contains(building_name));
+            let has_prerequisite =
+                required_in_all_cities(building_name, ruleset).is_none_or(|required| {
+                    self.in_every_city
+                        .get(&nation)
+                        .is_some_and(|buildings| buildings.contains(required))
+                });
+            !built && has_prerequisite
+        } else {
+            true
+        }
+    }
+
+    /// Record a wonder completed during the turn, so no other city can complete it too.
+    pub fn insert(&mut self, building_name: &str, nation: Nation, ruleset: &Ruleset) {
+        let building = &ruleset.buildings[building_name];
+        if building.is_wonder {
+            self.world.insert(building_name.to_owned());
+        } else if building.is_national_wonder {
+            self.national
+                .entry(nation)
+                .or_default()
+                .insert(building_name.to_owned());
+        }
+    }
+}
+
+/// The building a national wonder needs in every city, from its `Requires a [Building] in all cities` unique.
+fn required_in_all_cities<'a>(building_name: &str, ruleset: &'a Ruleset) -> Option<&'a str> {
+    ruleset.buildings[building_name]
+        .uniques
+        .iter()
+        .find_map(|unique| {
+            unique
+                .strip_prefix(REQUIRES_IN_ALL_CITIES_PREFIX)?
+                .strip_suffix(REQUIRES_IN_ALL_CITIES_SUFFIX)
+        })
+}
+
+/// Written when a city of `nation` completed a world wonder or a national wonder.
+#[derive(Message)]
+pub struct WonderBuilt {
+    pub nation: Nation,
+    pub wonder: String,
+    pub city_name: String,
+    pub tile: Tile,
+    pub is_world_wonder: bool,
+}
+
+/// Written when a city of `nation` lost the race for a world wonder, and got back `gold` for its production.
+#[derive(Message)]
+pub struct WonderLost {
+    pub nation: Nation,
+    pub wonder: String,
+    pub city_name: String,
+    pub gold: f32,
+}
+
+pub struct WonderPlugin;
+
+impl Plugin for WonderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<WonderBuilt>()
+            .add_message::<WonderLost>()
+            .add_systems(
+                TurnProcessing,
+                refund_lost_wonders
+                    .in_set(TurnSet::Cities)
+                    .after(produce_in_cities),
+            );
+    }
+}
+
+/// Stop the cities still building a world wonder which has been built elsewhere.
+///
+/// As in Civ V, the production they put into it is refunded as gold.
+fn refund_lost_wonders(
+    mut wonder_lost: MessageWriter<WonderLost>,
+    ruleset: Res<RulesetResource>,
+    mut civilizations: ResMut<Civilizations>,
+    mut query_city: Query<(&mut City, &Owner)>,
+) {
+    let ruleset = &ruleset.0;
+    let wonders = Wonders::of_cities(
+        query_city
+            .iter()
+            .map(|(city, owner)| (city, owner.nation())),
+        ruleset,
+    );
+
+    let mut cities: Vec<_> = query_city.iter_mut().collect();
+    cities.sort_by_key(|(city, _)| city.tile.index());
+    for (mut city, owner) in cities {
+        let Some(CityProduction::Building(building)) = city.production.clone() else {
+            continue;
+        };
+        let nation = owner.nation();
+        if !ruleset.buildings[&building].is_wonder || wonders.allows(&building, nation, ruleset) {
+            continue;
+        }
+
+        let gold = city.production_stored;
+        civilizations.get_mut(nation).gold += gold;
+        city.production_stored = 0.;
+        city.production = None;
+        wonder_lost.write(WonderLost {
+            nation,
+            wonder: building,
+            city_name: city.name.clone(),
+            gold,
+        });
+    }
+}
@@ -0,0 +1,169 @@
+//! The worked tiles of the city shown in the [`CityScreen`]: a citizen on every tile the city works, red on
+//! the tiles the player locked, and the tiles around the city it can't work dimmed.
+//!
+//! While the city screen is open, clicking a tile the city can work locks a citizen on it, and clicking a locked
+//! tile unlocks it, see [`City::lock_tile`].
+
+use bevy::{picking::hover::HoverMap, prelude::*};
+
+use crate::{
+    MainCamera, TileMapResource,
+    assets::AppState,
+    city::{CITY_WORK_RANGE, City},
+    city_screen::CityScreen,
+    command::PlayerCommand,
+    custom_mesh::hex_mesh,
+    grid::cursor_to_tile,
+    world_map::{WorldTileEntities, cursor_over_ui},
+};
+
+/// The tiles this far from the city which it can't work are dimmed, so the tiles it can work stand out.
+const DIM_DISTANCE: u32 = CITY_WORK_RANGE + 2;
+const DIM_COLOR: Color = Color::srgba(0., 0., 0., 0.5);
+const CITIZEN_SIZE: f32 = 14.;
+const CITIZEN_COLOR: Color = Color::srgb(0.95, 0.85, 0.4);
+const LOCKED_CITIZEN_COLOR: Color = Color::srgb(0.9, 0.3, 0.2);
+/// Above the terrain and the yield overlay, below the cities and the units.
+const DIM_Z: f32 = 3.6;
+const CITIZEN_Z: f32 = 3.7;
+
+/// A part of the worked tiles overlay on a tile.
+#[derive(Component)]
+struct WorkedTileOverlay;
+
+pub struct WorkedTilesPlugin;
+
+impl Plugin for WorkedTilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (lock_tile_on_click, update_worked_tile_overlays)
+                .chain()
+                .run_if(
+                    in_state(AppState::GameStart)
+                        .and(resource_exists::<TileMapResource>)
+                        .and(resource_exists::<WorldTileEntities>),
+                ),
+        );
+    }
+}
+
+/// Lock a citizen on the tile clicked with the left mouse button, or unlock it, while the city screen is open.
+fn lock_tile_on_click(
+    input: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    map: Res<TileMapResource>,
+    hover_map: Res<HoverMap>,
+    ui_nodes: Query<(), With<Node>>,
+    query_screen: Query<&CityScreen>,
+    query_city: Query<&City>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if !input.just_pressed(MouseButton::Left) || cursor_over_ui(&hover_map, &ui_nodes) {
+        return;
+    }
+    let Some(city) = query_screen
+        .iter()
+        .find_map(|&CityScreen(city)| query_city.get(city).ok())
+    else {
+        return;
+    };
+
+    let grid = map.0.world_grid.grid;
+    let (camera, camera_transform) = *camera;
+    let Some(tile) = cursor_to_tile(&window, camera, camera_transform, grid) else {
+        return;
+    };
+    if !city.can_work_tile(tile, grid) {
+        return;
+    }
+    player_command.write(PlayerCommand::LockTile {
+        city: city.tile,
+        tile,
+        locked: !city.locked_tiles.contains(&tile),
+    });
+}
+
+/// The meshes and the materials of the overlay, shared by every tile.
+struct OverlayAssets {
+    dim_mesh: Handle<Mesh>,
+    dim_material: Handle<ColorMaterial>,
+    citizen_mesh: Handle<Mesh>,
+    citizen_material: Handle<ColorMaterial>,
+    locked_citizen_material: Handle<ColorMaterial>,
+}
+
+/// Redraw the overlay when the city screen opens or closes, or when the city shown works other tiles.
+fn update_worked_tile_overlays(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    tile_entities: Res<WorldTileEntities>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut overlay_assets: Local<Option<OverlayAssets>>,
+    mut closed_screens: RemovedComponents<CityScreen>,
+    query_new_screen: Query<(), Added<CityScreen>>,
+    query_screen: Query<&CityScreen>,
+    query_city: Query<Ref<City>>,
+    query_overlay: Query<Entity, With<WorkedTileOverlay>>,
+) {
+    let city = query_screen
+        .iter()
+        .find_map(|&CityScreen(city)| query_city.get(city).ok());
+    let screen_changed = !query_new_screen.is_empty() || closed_screens.read().count() > 0;
+    if !screen_changed && !city.as_ref().is_some_and(|city| city.is_changed()) {
+        return;
+    }
+
+    for overlay in query_overlay.iter() {
+        commands.entity(overlay).despawn();
+    }
+    let Some(city) = city else {
+        return;
+    };
+
+    let grid = map.0.world_grid.grid;
+    if map.is_changed() {
+        *overlay_assets = None;
+    }
+    let overlay_assets = overlay_assets.get_or_insert_with(|| OverlayAssets {
+        dim_mesh: meshes.add(hex_mesh(&grid)),
+        dim_material: color_materials.add(DIM_COLOR),
+        citizen_mesh: meshes.add(Circle::new(CITIZEN_SIZE / 2.)),
+        citizen_material: color_materials.add(CITIZEN_COLOR),
+        locked_citizen_material: color_materials.add(LOCKED_CITIZEN_COLOR),
+    });
+
+    for tile in city.tile.tiles_in_distance(DIM_DISTANCE, grid) {
+        if tile == city.tile || city.can_work_tile(tile, grid) {
+            continue;
+        }
+        let Some(&tile_entity) = tile_entities.0.get(&tile) else {
+            continue;
+        };
+        commands.entity(tile_entity).with_child((
+            WorkedTileOverlay,
+            Mesh2d(overlay_assets.dim_mesh.clone()),
+            MeshMaterial2d(overlay_assets.dim_material.clone()),
+            Transform::from_xyz(0., 0., DIM_Z),
+        ));
+    }
+    for &tile in &city.worked_tiles {
+        let Some(&tile_entity) = tile_entities.0.get(&tile) else {
+            continue;
+        };
+        let material = if city.locked_tiles.contains(&tile) {
+            &overlay_assets.locked_citizen_material
+        } else {
+            &overlay_assets.citizen_material
+        };
+        // In the bottom of the tile, so the units standing on it don't hide it.
+        commands.entity(tile_entity).with_child((
+            WorkedTileOverlay,
+            Mesh2d(overlay_assets.citizen_mesh.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_xyz(0., -CITIZEN_SIZE, CITIZEN_Z),
+        ));
+    }
+}
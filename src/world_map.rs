@@ -1,34 +1,153 @@
-use std::{collections::HashMap, f32::consts::FRAC_PI_2};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::{FRAC_PI_2, PI},
+    hash::{DefaultHasher, Hash, Hasher},
+};
 
-use bevy::prelude::*;
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    picking::hover::HoverMap,
+    picking::pointer::PointerId,
+    prelude::*,
+};
 use civ_map_generator::{
     grid::{
         Grid,
-        hex_grid::{Hex, HexOrientation},
+        hex_grid::{Hex, HexGrid, HexOrientation},
         offset_coordinate::OffsetCoordinate,
     },
-    ruleset::Ruleset,
     tile::Tile,
-    tile_component::{BaseTerrain, Feature, TerrainType},
-    tile_map::RiverEdge,
+    tile_component::{Feature, TerrainType},
+    tile_map::{RiverEdge, TileMap},
 };
 
 use crate::{
     ColorReplaceMaterial, MainCamera, RulesetResource, TileMapResource,
+    accessibility::AccessibilitySettings,
     assets::MaterialResource,
-    custom_mesh::{hex_mesh, line_mesh},
-    unit_component::{Owner, Unit},
+    city::City,
+    civilization::{Civilizations, PlayerCivilization},
+    command::{PlayerCommand, UnitId},
+    custom_mesh::polyline_mesh,
+    diplomacy::DiplomacyState,
+    era::{StartingEra, starting_units},
+    grid::{cursor_to_tile, map_pixel_width, world_position_to_tile},
+    key_bindings::{InputAction, KeyBindings},
+    pathfinding::{
+        MovementRules, Path, ZoneOfControl, ZoneOfControlRule, find_path, turns_to_reach,
+    },
+    tile_instancing::InstancedTiles,
+    unit::{MapUnit, find_spawn_tile, unit_kind},
+    unit_component::{Movement, Owner, Unit},
+    unit_group::band_select_held,
+    unit_sprite::{UnitMeshes, unit_bundle},
+    visibility::VisibilityLayer,
+    yields::TileYields,
 };
 
-use enum_map::{EnumMap, enum_map};
-
 #[derive(Component)]
 pub struct WorldTile(pub Tile);
 
+/// The [`WorldTile`] entity of every tile, used to attach units and other per-tile entities to their tile.
+#[derive(Resource)]
+pub struct WorldTileEntities(pub HashMap<Tile, Entity>);
+
+/// The unit currently selected by the player.
+#[derive(Resource, Default)]
+pub struct SelectedUnit(pub Option<Entity>);
+
+/// Whether the selected unit moves to the tile of the next left click, entered with the key of
+/// [`InputAction::MoveUnit`], `M` by default. The path to the tile under the cursor is previewed meanwhile.
+#[derive(Resource, Default)]
+pub struct MoveMode(pub bool);
+
+/// The path previewed while the player holds the right mouse button to choose the destination of the selected unit,
+/// or moves the cursor in [`MoveMode`].
+#[derive(Resource, Default)]
+pub struct MovePathPreview {
+    pub path: Option<Path>,
+    /// The turn on which the unit reaches every tile of the path, see [`turns_to_reach`].
+    pub turns: Vec<u32>,
+}
+
+/// The number of turns the selected unit needs to reach a tile of the previewed path where it ends a turn.
+#[derive(Component)]
+pub struct PathTurnBadge;
+
+/// The width and the height of a [`TileChunk`], in tiles.
+const CHUNK_SIZE: i32 = 16;
+
+/// A chunk of [`CHUNK_SIZE`] x [`CHUNK_SIZE`] tiles, the tiles from `origin` to `origin + size` in offset coordinates.
+///
+/// The terrain of the tiles of a chunk is merged into one mesh per texture: the base terrains, the hills and
+/// the mountains, the features and the natural wonders. The meshes are children of the chunk.
+#[derive(Component, Clone, Copy)]
+pub struct TileChunk {
+    origin: [i32; 2],
+    size: [i32; 2],
+}
+
+impl TileChunk {
+    fn tiles(&self, grid: HexGrid) -> impl Iterator<Item = ([i32; 2], Tile)> + '_ {
+        let [origin_x, origin_y] = self.origin;
+        let [width, height] = self.size;
+        (origin_y..origin_y + height).flat_map(move |y| {
+            (origin_x..origin_x + width).map(move |x| {
+                let offset_coordinate = OffsetCoordinate::new(x, y);
+                ([x, y], Tile::from_offset(offset_coordinate, grid))
+            })
+        })
+    }
+
+    /// What the terrain of the chunk looks like, so only the chunks whose tiles changed are rebuilt.
+    ///
+    /// The base terrain of the neighbors is included for the transitions between the tiles, see [`ChunkMesh::add_transition`].
+    fn signature(&self, tile_map: &TileMap) -> u64 {
+        let grid = tile_map.world_grid.grid;
+        let mut hasher = DefaultHasher::new();
+        for (_, tile) in self.tiles(grid) {
+            tile.base_terrain(tile_map).as_str().hash(&mut hasher);
+            for neighbor in tile.neighbor_tiles(grid) {
+                neighbor.base_terrain(tile_map).as_str().hash(&mut hasher);
+            }
+            tile.terrain_type(tile_map).as_str().hash(&mut hasher);
+            tile.feature(tile_map)
+                .map(|feature| feature.as_str())
+                .hash(&mut hasher);
+            tile.natural_wonder(tile_map)
+                .map(|natural_wonder| natural_wonder.as_str())
+                .hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+/// The chunks of the map with the [`TileChunk::signature`] of their terrain when their meshes were built,
+/// and the materials of their meshes.
+#[derive(Resource, Default)]
+pub struct TileChunks {
+    grid_size: [u32; 2],
+    /// Whether the chunks leave the base terrain to the instanced layer, see [`crate::tile_instancing`].
+    instanced_base_terrain: bool,
+    chunks: Vec<(Entity, TileChunk, Option<u64>)>,
+    materials: HashMap<String, Handle<ColorMaterial>>,
+}
+
+const SELECTION_COLOR: Color = Color::srgb(1., 0.85, 0.1);
+
+const RIVER_COLOR: Color = Color::srgb(0.25, 0.5, 0.9);
+/// The width of a river edge at its ends, where it joins the next edge of the river.
+const RIVER_WIDTH: f32 = 2.;
+/// The number of segments of a river edge, enough for a smooth meander.
+const RIVER_EDGE_SEGMENTS: usize = 8;
+
 pub fn setup_tile_map(
     mut commands: Commands,
     map: Option<Res<TileMapResource>>,
     ruleset: Res<RulesetResource>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    starting_era: Res<StartingEra>,
     materials: Res<MaterialResource>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut color_materials: ResMut<Assets<ColorMaterial>>,
@@ -42,199 +161,477 @@ pub fn setup_tile_map(
 
     let grid = tile_map.world_grid.grid;
 
-    let base_terrain_and_material: EnumMap<BaseTerrain, Handle<ColorMaterial>> = enum_map! {
-        base_terrain => color_materials.add(materials.texture_handle(base_terrain.as_str())),
-    };
-
-    let mut tile_and_river_flow_direction = HashMap::new();
+    let mut tile_and_river_edges = HashMap::new();
 
     tile_map.river_list.iter().flatten().for_each(|river_edge| {
-        tile_and_river_flow_direction
+        tile_and_river_edges
             .entry(river_edge.tile)
             .or_insert_with(Vec::new)
-            .push(river_edge.flow_direction);
+            .push(river_edge);
     });
 
-    let all_possible_river_edge_mesh: Vec<_> = grid
-        .corner_direction_array()
-        .iter()
-        .map(|&flow_direction| {
-            let river_edge = RiverEdge {
-                tile: Tile::new(0),
-                flow_direction,
+    let river_material = color_materials.add(ColorMaterial::from_color(RIVER_COLOR));
+
+    let tile_pixel_size = Vec2::from(grid.layout.size) * Vec2::new(2.0, 2.0);
+
+    let mut tile_entities = HashMap::new();
+    let mut starting_units_to_place = Vec::new();
+
+    for tile in tile_map.all_tiles() {
+        // Spawn the tile, the terrain itself is drawn by its chunk, see `update_tile_chunks`
+        // this is the base tile entity that will be used to spawn the child entities
+        let tile_entity = commands
+            .spawn((Transform::default(), Visibility::Hidden, WorldTile(tile)))
+            .id();
+
+        tile_entities.insert(tile, tile_entity);
+
+        // Draw river edges
+        if let Some(river_edges) = tile_and_river_edges.get(&tile) {
+            commands.entity(tile_entity).with_child((
+                Mesh2d(meshes.add(river_mesh(river_edges, grid))),
+                MeshMaterial2d(river_material.clone()),
+                Transform::from_xyz(0., 0., 5.),
+            ));
+        };
+
+        let ruleset = &ruleset.0;
+
+        // Draw the resource, hidden until the player can see it, see `update_resource_icons`
+        if let Some((resource, _)) = tile.resource(tile_map) {
+            let resource = resource.as_str();
+            let icon_size = tile_pixel_size / 3.;
+            let sprite = match materials.get_texture_handle(resource_texture_name(resource)) {
+                Some(image) => Sprite {
+                    custom_size: Some(icon_size),
+                    image,
+                    ..Default::default()
+                },
+                None => Sprite::from_color(
+                    resource_type_color(&ruleset.tile_resources[resource].resource_type),
+                    icon_size / 2.,
+                ),
             };
+            commands.entity(tile_entity).with_child((
+                sprite,
+                Transform::from_xyz(-tile_pixel_size.x / 6., -tile_pixel_size.y / 4., 3.),
+                Visibility::Hidden,
+                ResourceIcon(resource.to_owned()),
+            ));
+        }
+
+        // The starting units are placed around their starting tile once every tile is spawned
+        if let Some(&civilization) = tile_map.starting_tile_and_civilization.get(&tile) {
+            starting_units_to_place.extend(
+                starting_units(civilization, &starting_era.0, ruleset)
+                    .into_iter()
+                    .map(|unit_name| (tile, unit_name, Owner::Civilization(civilization))),
+            );
+        }
+
+        // Place a settler at the starting tile of the city-state
+        if let Some(&city_state) = tile_map.starting_tile_and_city_state.get(&tile) {
+            starting_units_to_place.push((
+                tile,
+                "Settler".to_owned(),
+                Owner::CityState(city_state),
+            ));
+        }
+    }
+
+    let unit_meshes = UnitMeshes::new(&mut meshes, tile_pixel_size);
+
+    // The starting tile holds the first military unit and the first settler, the other units go to the neighboring tiles.
+    let ruleset = &ruleset.0;
+    let mut taken = HashSet::new();
+    for (starting_tile, unit_name, owner) in starting_units_to_place {
+        let unit = unit_kind(&unit_name, ruleset);
+        let Some(tile) = find_spawn_tile(
+            starting_tile,
+            &unit,
+            |tile, is_military| taken.contains(&(tile, is_military)),
+            tile_map,
+            ruleset,
+        ) else {
+            continue;
+        };
+        taken.insert((tile, matches!(unit, Unit::Military(_))));
+        commands
+            .entity(tile_entities[&tile])
+            .with_child(unit_bundle(
+                &unit_name,
+                owner,
+                tile,
+                ruleset,
+                &accessibility_settings,
+                &unit_meshes,
+                &mut custom_materials,
+                &materials,
+            ));
+    }
+
+    commands.insert_resource(unit_meshes);
+    commands.insert_resource(WorldTileEntities(tile_entities));
+}
 
+/// The mesh of the river edges of a tile, relative to the center of the tile.
+///
+/// Every edge meanders a little and changes width along its length, differently for every edge,
+/// but starts and ends on the corners of the tile with the same width, so it joins the next edge of the river.
+/// The corners come from the layout of the grid, so the edges follow both pointy and flat hexagons.
+fn river_mesh(river_edges: &[&RiverEdge], grid: HexGrid) -> Mesh {
+    let polylines: Vec<_> = river_edges
+        .iter()
+        .map(|river_edge| {
             let [start_corner_direction, end_corner_direction] =
                 river_edge.start_and_end_corner_directions(grid);
-            let start_corner_position = grid.layout.corner(Hex::new(0, 0), start_corner_direction);
-            let end_corner_position = grid.layout.corner(Hex::new(0, 0), end_corner_direction);
+            let start = Vec2::from(grid.layout.corner(Hex::new(0, 0), start_corner_direction));
+            let end = Vec2::from(grid.layout.corner(Hex::new(0, 0), end_corner_direction));
+
+            let direction_index = grid
+                .corner_direction_array()
+                .iter()
+                .position(|&direction| direction == river_edge.flow_direction)
+                .unwrap_or_default();
+            let seed = river_edge.tile.index() as u64 * 6 + direction_index as u64;
+            // Bend to one side or the other, in one or two waves.
+            let amplitude = (end - start).length() * 0.08 * (edge_noise(seed) * 2. - 1.);
+            let waves = if edge_noise(seed + 1) < 0.5 { 1. } else { 2. };
+            let width_variation = 0.5 * edge_noise(seed + 2);
 
-            let start = [start_corner_position[0], start_corner_position[1], 0.0];
-            let end = [end_corner_position[0], end_corner_position[1], 0.0];
-            let line_mesh = line_mesh(start.into(), end.into(), 1.5);
-            (flow_direction, line_mesh)
+            let normal = (end - start).perp().normalize_or_zero();
+            (0..=RIVER_EDGE_SEGMENTS)
+                .map(|segment| {
+                    let t = segment as f32 / RIVER_EDGE_SEGMENTS as f32;
+                    let point = start.lerp(end, t) + normal * amplitude * (t * PI * waves).sin();
+                    let width = RIVER_WIDTH * (1. + width_variation * (t * PI).sin());
+                    (point, width)
+                })
+                .collect()
         })
         .collect();
+    polyline_mesh(&polylines)
+}
 
-    let tile_pixel_size = Vec2::from(grid.layout.size) * Vec2::new(2.0, 2.0);
+/// A pseudo-random number in `[0, 1)` from `seed`, so every river edge keeps the same shape in every game
+/// without drawing from the random numbers of the game.
+fn edge_noise(seed: u64) -> f32 {
+    let mut x = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    (x >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Build the terrain meshes of the chunks whose tiles changed, and spawn the chunks of a new map,
+/// e.g. when a save is loaded.
+pub fn update_tile_chunks(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    materials: Res<MaterialResource>,
+    mut tile_chunks: ResMut<TileChunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    instanced_tiles: Option<Res<InstancedTiles>>,
+) {
+    let tile_map = &map.0;
+    let grid = tile_map.world_grid.grid;
+    let (width, height) = (grid.width() as i32, grid.height() as i32);
 
+    let tile_chunks = &mut *tile_chunks;
+    let instanced_base_terrain = instanced_tiles.is_some();
+    if tile_chunks.instanced_base_terrain != instanced_base_terrain {
+        tile_chunks.instanced_base_terrain = instanced_base_terrain;
+        for (.., built_signature) in &mut tile_chunks.chunks {
+            *built_signature = None;
+        }
+    }
+    if tile_chunks.grid_size != [grid.width(), grid.height()] {
+        for &(entity, ..) in &tile_chunks.chunks {
+            commands.entity(entity).despawn();
+        }
+        tile_chunks.grid_size = [grid.width(), grid.height()];
+        tile_chunks.chunks.clear();
+        for origin_y in (0..height).step_by(CHUNK_SIZE as usize) {
+            for origin_x in (0..width).step_by(CHUNK_SIZE as usize) {
+                let chunk = TileChunk {
+                    origin: [origin_x, origin_y],
+                    size: [
+                        CHUNK_SIZE.min(width - origin_x),
+                        CHUNK_SIZE.min(height - origin_y),
+                    ],
+                };
+                let entity = commands
+                    .spawn((chunk, Transform::default(), Visibility::Inherited))
+                    .id();
+                tile_chunks.chunks.push((entity, chunk, None));
+            }
+        }
+    }
+
+    let tile_pixel_size = Vec2::from(grid.layout.size) * 2.;
     // We only need to rotate the sprite for `Feature::Ice` because it was originally designed exclusively for Pointy-oriented hexagons.
     // Other terrain sprites were created to work seamlessly with both Pointy and Flat hexagon orientations.
     let feature_ice_sprite_rotation = match grid.layout.orientation {
-        HexOrientation::Pointy => Quat::default(),
-        HexOrientation::Flat => Quat::from_rotation_z(FRAC_PI_2 * 3.),
+        HexOrientation::Pointy => 0.,
+        HexOrientation::Flat => FRAC_PI_2 * 3.,
     };
+    let hex_corners = grid.layout.all_corners(Hex::new(0, 0)).map(Vec2::from);
 
-    let hex_mesh = meshes.add(hex_mesh(&grid));
+    for (entity, chunk, built_signature) in &mut tile_chunks.chunks {
+        let signature = chunk.signature(tile_map);
+        if *built_signature == Some(signature) {
+            continue;
+        }
+        *built_signature = Some(signature);
 
-    for tile in tile_map.all_tiles() {
-        // Spawn the tile with base terrain
-        // this is the base tile entity that will be used to spawn the child entities
-        let tile_entity = commands
-            .spawn((
-                Mesh2d(hex_mesh.clone()),
-                MeshMaterial2d(base_terrain_and_material[tile.base_terrain(tile_map)].clone()),
-                Visibility::Hidden,
-                WorldTile(tile),
-            ))
-            .id();
+        // The layers of the chunk by texture, with their height above the chunk.
+        let mut layers: HashMap<(String, u32), ChunkMesh> = HashMap::new();
+        let origin_position = Vec2::from(
+            grid.offset_to_pixel(OffsetCoordinate::new(chunk.origin[0], chunk.origin[1])),
+        );
+        for ([x, y], tile) in chunk.tiles(grid) {
+            let center =
+                Vec2::from(grid.offset_to_pixel(OffsetCoordinate::new(x, y))) - origin_position;
 
-        commands.entity(tile_entity).with_children(|parent| {
-            // Draw river edges
-            if let Some(flow_direction_list) = tile_and_river_flow_direction.get(&tile) {
-                flow_direction_list.iter().for_each(|direction| {
-                    let (_, line_mesh) = all_possible_river_edge_mesh
-                        .iter()
-                        .find(|(d, _)| d == direction)
-                        .unwrap();
-                    parent.spawn((
-                        Mesh2d(meshes.add(line_mesh.clone())),
-                        MeshMaterial2d(
-                            color_materials
-                                .add(ColorMaterial::from_color(Color::srgb_u8(140, 215, 215))),
-                        ),
-                        Transform {
-                            translation: Vec3::new(0., 0., 5.),
-                            ..Default::default()
-                        },
-                    ));
-                })
-            };
+            let base_terrain = tile.base_terrain(tile_map);
+            if !instanced_base_terrain {
+                layers
+                    .entry((base_terrain.as_str().to_owned(), 0))
+                    .or_default()
+                    .add_hex(center, &hex_corners, tile_pixel_size);
+            }
+
+            // Blend the edges shared with a different base terrain, e.g. grassland fading into desert.
+            // Land and water keep a hard edge, which is the coast.
+            let world_center = Vec2::from(grid.offset_to_pixel(OffsetCoordinate::new(x, y)));
+            for (index, &corner) in hex_corners.iter().enumerate() {
+                let next_corner = hex_corners[(index + 1) % hex_corners.len()];
+                // The neighbor across the edge is the tile mirroring this tile over the middle of the edge.
+                let Some(neighbor) =
+                    world_position_to_tile(world_center + corner + next_corner, grid)
+                else {
+                    continue;
+                };
+                let neighbor_base_terrain = neighbor.base_terrain(tile_map);
+                if neighbor_base_terrain != base_terrain
+                    && neighbor.is_water(tile_map) == tile.is_water(tile_map)
+                {
+                    layers
+                        .entry((neighbor_base_terrain.as_str().to_owned(), 1))
+                        .or_default()
+                        .add_transition(center, [corner, next_corner], tile_pixel_size);
+                }
+            }
 
             // Draw terrain type Mountain with no natural wonder and Hill
             // Notice terrain type Flatland and Water are not drawn in this moment because they only need to be drawn with base terrain
             let terrain_type = tile.terrain_type(tile_map);
             let is_mountain_without_wonder =
                 terrain_type == TerrainType::Mountain && tile.natural_wonder(tile_map).is_none();
-
             if is_mountain_without_wonder || terrain_type == TerrainType::Hill {
-                parent.spawn((
-                    Sprite {
-                        custom_size: Some(tile_pixel_size),
-                        image: materials.texture_handle(terrain_type.as_str()),
-                        ..Default::default()
-                    },
-                    Transform {
-                        translation: Vec3::new(0., 0., 3.),
-                        ..Default::default()
-                    },
-                ));
+                layers
+                    .entry((terrain_type.as_str().to_owned(), 3))
+                    .or_default()
+                    .add_quad(center, tile_pixel_size, 0.);
             }
 
-            // Draw the feature
             if let Some(feature) = tile.feature(tile_map) {
-                parent.spawn((
-                    Sprite {
-                        custom_size: Some(tile_pixel_size),
-                        image: materials.texture_handle(feature.as_str()),
-                        ..Default::default()
-                    },
-                    Transform {
-                        translation: Vec3::new(0., 0., 2.),
-                        rotation: if feature == Feature::Ice {
-                            feature_ice_sprite_rotation
-                        } else {
-                            Quat::default()
-                        },
-                        ..Default::default()
-                    },
-                ));
+                let rotation = if feature == Feature::Ice {
+                    feature_ice_sprite_rotation
+                } else {
+                    0.
+                };
+                layers
+                    .entry((feature.as_str().to_owned(), 2))
+                    .or_default()
+                    .add_quad(center, tile_pixel_size, rotation);
             }
 
-            // Draw the natural wonder
-            if let Some(natural_wonder) = tile.natural_wonder(tile_map) {
-                parent.spawn((
-                    Sprite {
-                        custom_size: Some(tile_pixel_size),
-                        image: materials.texture_handle(natural_wonder.as_str()),
-                        ..Default::default()
-                    },
-                    Transform {
-                        translation: Vec3::new(0., 0., 2.),
-                        ..Default::default()
-                    },
-                ));
+            // A natural wonder is drawn once over all its tiles, by its first tile.
+            if let Some(natural_wonder) = tile.natural_wonder(tile_map)
+                && let Some((offset, size)) = natural_wonder_sprite(tile, tile_map, tile_pixel_size)
+            {
+                layers
+                    .entry((natural_wonder.as_str().to_owned(), 2))
+                    .or_default()
+                    .add_quad(center + offset, size, 0.);
             }
-        });
+        }
 
-        let ruleset = &ruleset.0;
-        let radius = tile_pixel_size.min_element() / 3.0;
+        let mut chunk_commands = commands.entity(*entity);
+        chunk_commands.despawn_related::<Children>();
+        for ((texture_name, z), layer) in layers {
+            let material = tile_chunks
+                .materials
+                .entry(texture_name.clone())
+                .or_insert_with(|| color_materials.add(materials.texture_handle(&texture_name)))
+                .clone();
+            chunk_commands.with_child((
+                Mesh2d(meshes.add(layer.into_mesh())),
+                MeshMaterial2d(material),
+                Transform::from_xyz(0., 0., z as f32),
+            ));
+        }
+    }
+}
 
-        let inner_rectangle = meshes.add(Rectangle::new(radius / 2., radius / 2.));
-        let outer_rectangle = meshes.add(Rectangle::new(radius, radius));
+/// The sprite of the natural wonder on `tile` covering all its connected tiles, e.g. both tiles of the
+/// Great Barrier Reef: the offset of its center from `tile` and its size.
+///
+/// Only the first tile of the natural wonder gets the sprite, so it is drawn once.
+fn natural_wonder_sprite(
+    tile: Tile,
+    tile_map: &TileMap,
+    tile_pixel_size: Vec2,
+) -> Option<(Vec2, Vec2)> {
+    let grid = tile_map.world_grid.grid;
+    let natural_wonder = tile.natural_wonder(tile_map)?;
+    let map_width = map_pixel_width(grid);
+    let position_of = |tile: Tile| Vec2::from(grid.offset_to_pixel(tile.to_offset(grid)));
 
-        // Place settler and warriors at the starting tile of the civilization
-        if let Some(&civilization) = tile_map.starting_tile_and_civilization.get(&tile) {
-            let replace_warrior_unit = ruleset.units.values().find(|&unit| {
-                unit.unique_to == civilization.as_str() && unit.replaces == "Warrior"
-            });
-            let military_unit = if let Some(unit) = replace_warrior_unit {
-                unit.name.clone()
-            } else {
-                "Warrior".to_string()
-            };
+    // The positions of the tiles are unwrapped around `tile`, so a natural wonder across the edge
+    // of a wrapping map stays in one piece.
+    let origin = position_of(tile);
+    let mut visited = HashSet::from([tile.index()]);
+    let mut stack = vec![(tile, Vec2::ZERO)];
+    let (mut min, mut max) = (Vec2::ZERO, Vec2::ZERO);
+    while let Some((current, position)) = stack.pop() {
+        if current.index() < tile.index() {
+            return None;
+        }
+        min = min.min(position);
+        max = max.max(position);
+        for neighbor in current.neighbor_tiles(grid) {
+            if neighbor.natural_wonder(tile_map) != Some(natural_wonder)
+                || !visited.insert(neighbor.index())
+            {
+                continue;
+            }
+            let mut neighbor_position = position_of(neighbor) - origin;
+            if grid.wrap_x() {
+                neighbor_position.x -=
+                    ((neighbor_position.x - position.x) / map_width).round() * map_width;
+            }
+            stack.push((neighbor, neighbor_position));
+        }
+    }
 
-            // Spawn the military unit
-            commands.entity(tile_entity).with_children(|parent| {
-                parent.spawn(unit_icon(
-                    Unit::Military(military_unit),
-                    Owner::Civilization(civilization),
-                    ruleset,
-                    inner_rectangle.clone(),
-                    outer_rectangle.clone(),
-                    &mut custom_materials,
-                    &materials,
-                    tile_pixel_size,
-                ));
-
-                parent.spawn(unit_icon(
-                    Unit::Civilian("Settler".to_owned()),
-                    Owner::Civilization(civilization),
-                    ruleset,
-                    inner_rectangle.clone(),
-                    outer_rectangle.clone(),
-                    &mut custom_materials,
-                    &materials,
-                    tile_pixel_size,
-                ));
-            });
+    // The art is square, so it covers the longest side of the tiles.
+    let size = Vec2::splat(((max - min) + tile_pixel_size).max_element());
+    Some(((min + max) / 2., size))
+}
+
+/// Move every chunk to the copy of its tiles closest to the camera, like the tiles on a wrapping map,
+/// see [`show_main_camera_area`].
+pub fn position_tile_chunks(
+    map: Res<TileMapResource>,
+    camera: Single<&Transform, With<MainCamera>>,
+    mut query_chunk: Query<(&TileChunk, &mut Transform), Without<MainCamera>>,
+) {
+    let grid = map.0.world_grid.grid;
+    let camera_position = camera.translation.truncate().to_array();
+    let [camera_x, _] = grid.pixel_to_offset(camera_position).to_array();
+    let width = grid.width() as i32;
+
+    for (chunk, mut transform) in query_chunk.iter_mut() {
+        let [mut x, y] = chunk.origin;
+        if grid.wrap_x() {
+            let chunk_center_x = x + chunk.size[0] / 2;
+            x += ((camera_x - chunk_center_x) as f32 / width as f32).round() as i32 * width;
         }
+        let [pixel_x, pixel_y] = grid.offset_to_pixel(OffsetCoordinate::new(x, y));
+        transform.translation = Vec3::new(pixel_x, pixel_y, 0.);
+    }
+}
 
-        // Place settler ast the starting tile of city state
-        if let Some(&city_state) = tile_map.starting_tile_and_city_state.get(&tile) {
-            commands.entity(tile_entity).with_children(|parent| {
-                parent.spawn(unit_icon(
-                    Unit::Civilian("Settler".to_owned()),
-                    Owner::CityState(city_state),
-                    ruleset,
-                    inner_rectangle.clone(),
-                    outer_rectangle.clone(),
-                    &mut custom_materials,
-                    &materials,
-                    tile_pixel_size,
-                ));
-            });
+/// How deep the transition between two base terrains reaches into a tile, relative to the distance
+/// from the center of the tile to its edge.
+const TRANSITION_DEPTH: f32 = 0.35;
+
+/// The vertices of one texture of a chunk: the hexagons of the base terrains, the transitions between them
+/// and the squares of the sprites.
+#[derive(Default)]
+struct ChunkMesh {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl ChunkMesh {
+    /// A vertex at `offset` from the center of a tile, the texture being stretched over the tile.
+    fn push_tile_vertex(&mut self, center: Vec2, offset: Vec2, tile_pixel_size: Vec2, alpha: f32) {
+        self.positions
+            .push([center.x + offset.x, center.y + offset.y, 0.]);
+        self.uvs.push([
+            offset.x / tile_pixel_size.x + 0.5,
+            0.5 - offset.y / tile_pixel_size.y,
+        ]);
+        self.colors.push([1., 1., 1., alpha]);
+    }
+
+    /// A hexagon showing the texture stretched over the tile.
+    fn add_hex(&mut self, center: Vec2, corners: &[Vec2; 6], tile_pixel_size: Vec2) {
+        let first_index = self.positions.len() as u32;
+        for &corner in corners {
+            self.push_tile_vertex(center, corner, tile_pixel_size, 1.);
         }
+        self.indices.extend(
+            [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 5]
+                .iter()
+                .map(|&offset| first_index + offset),
+        );
+    }
+
+    /// A strip along the edge of a tile between the corners `edge`, showing the texture of the neighbor across
+    /// the edge. It is half transparent on the edge and fades out toward the center of the tile, so the neighbor,
+    /// which draws the same strip on its side, blends with the tile.
+    fn add_transition(&mut self, center: Vec2, edge: [Vec2; 2], tile_pixel_size: Vec2) {
+        let first_index = self.positions.len() as u32;
+        let [start, end] = edge;
+        self.push_tile_vertex(center, start, tile_pixel_size, 0.5);
+        self.push_tile_vertex(center, end, tile_pixel_size, 0.5);
+        self.push_tile_vertex(center, end * (1. - TRANSITION_DEPTH), tile_pixel_size, 0.);
+        self.push_tile_vertex(center, start * (1. - TRANSITION_DEPTH), tile_pixel_size, 0.);
+        self.indices.extend(
+            [0, 1, 2, 0, 2, 3]
+                .iter()
+                .map(|&offset| first_index + offset),
+        );
+    }
+
+    /// A square of `size` showing the whole texture, rotated by `rotation` radians around its center.
+    fn add_quad(&mut self, center: Vec2, size: Vec2, rotation: f32) {
+        let first_index = self.positions.len() as u32;
+        let rotation = Vec2::from_angle(rotation);
+        for ([dx, dy], uv) in [
+            ([-0.5, -0.5], [0., 1.]),
+            ([0.5, -0.5], [1., 1.]),
+            ([0.5, 0.5], [1., 0.]),
+            ([-0.5, 0.5], [0., 0.]),
+        ] {
+            let corner = center + rotation.rotate(Vec2::new(dx, dy) * size);
+            self.positions.push([corner.x, corner.y, 0.]);
+            self.uvs.push(uv);
+            self.colors.push([1.; 4]);
+        }
+        self.indices.extend(
+            [0, 1, 2, 0, 2, 3]
+                .iter()
+                .map(|&offset| first_index + offset),
+        );
+    }
+
+    fn into_mesh(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(self.indices))
     }
 }
 
@@ -243,7 +640,7 @@ pub fn setup_tile_map(
 /// This function dynamically crops the world map display area to always match the main camera's viewport.
 /// Non-visible areas are hidden to prevent visual confusion, with this mechanism supporting both wrap and non-wrap map projection modes.
 pub fn show_main_camera_area(
-    query: Single<&mut Transform, With<MainCamera>>,
+    query: Single<(&Transform, &Camera, &Projection), With<MainCamera>>,
     map: Option<Res<TileMapResource>>,
     mut query_world_tile: Query<
         (&mut Visibility, &mut Transform, &WorldTile),
@@ -258,29 +655,63 @@ pub fn show_main_camera_area(
 
     let grid = tile_map.world_grid.grid;
 
-    // The width and height of the visible area in tiles.
-    // Please make sure they are odd numbers. That will make sure the center of the camera is exactly on the center of the visible area.
+    // The smallest width and height of the visible area in tiles, grown to cover the viewport of the camera when zoomed out.
+    // They are odd numbers. That will make sure the center of the camera is exactly on the center of the visible area.
     const WIDTH_OF_VISIBLE_AREA: i32 = 37;
     const HEIGHT_OF_VISIBLE_AREA: i32 = 21;
 
-    // `width_of_camera` should < grid's width
-    // Because if it's not, the same tile will be drawn twice due to the grid's wrapping behavior.
-    assert!(WIDTH_OF_VISIBLE_AREA < grid.width() as i32);
-    // `height_of_camera` should < grid's height
-    // Because if it's not, the same tile will be drawn twice due to the grid's wrapping behavior.
-    assert!(HEIGHT_OF_VISIBLE_AREA < grid.height() as i32);
+    let (camera_transform, camera, projection) = query.into_inner();
+    let zoom = match projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.,
+    };
+    let viewport_size = camera
+        .logical_viewport_size()
+        .map_or(Vec2::ZERO, |size| size * zoom);
+    let origin = Vec2::from(grid.offset_to_pixel(OffsetCoordinate::new(0, 0)));
+    let column_width = (Vec2::from(grid.offset_to_pixel(OffsetCoordinate::new(1, 0))) - origin)
+        .x
+        .abs();
+    let row_height = (Vec2::from(grid.offset_to_pixel(OffsetCoordinate::new(0, 2))) - origin)
+        .y
+        .abs()
+        / 2.;
+    // The visible area must be narrower than the grid on a wrapping axis,
+    // because if it's not, the same tile would have to be drawn twice due to the grid's wrapping behavior.
+    let visible_tiles = |viewport: f32, spacing: f32, minimum: i32, grid_size: u32, wraps: bool| {
+        let tiles = ((viewport / spacing).ceil() as i32 + 2).max(minimum) | 1;
+        if wraps {
+            tiles.min((grid_size as i32 - 2) | 1)
+        } else {
+            tiles
+        }
+    };
+    let width_of_visible_area = visible_tiles(
+        viewport_size.x,
+        column_width,
+        WIDTH_OF_VISIBLE_AREA,
+        grid.width(),
+        grid.wrap_x(),
+    );
+    let height_of_visible_area = visible_tiles(
+        viewport_size.y,
+        row_height,
+        HEIGHT_OF_VISIBLE_AREA,
+        grid.height(),
+        grid.wrap_y(),
+    );
 
-    let camera_position = query.into_inner().translation.truncate().to_array();
+    let camera_position = camera_transform.translation.truncate().to_array();
     let camera_offset_coordinate = grid.pixel_to_offset(camera_position).to_array();
-    let mut left_x = camera_offset_coordinate[0] - WIDTH_OF_VISIBLE_AREA / 2;
-    let mut right_x = camera_offset_coordinate[0] + WIDTH_OF_VISIBLE_AREA / 2;
+    let mut left_x = camera_offset_coordinate[0] - width_of_visible_area / 2;
+    let mut right_x = camera_offset_coordinate[0] + width_of_visible_area / 2;
     // If the grid does not wrap on the x-axis, then we need to make sure that the left_x and right_x are within the bounds of the grid.
     if !grid.wrap_x() {
         left_x = left_x.max(0);
         right_x = right_x.min(grid.width() as i32 - 1);
     }
-    let mut bottom_y = camera_offset_coordinate[1] - HEIGHT_OF_VISIBLE_AREA / 2;
-    let mut top_y = camera_offset_coordinate[1] + HEIGHT_OF_VISIBLE_AREA / 2;
+    let mut bottom_y = camera_offset_coordinate[1] - height_of_visible_area / 2;
+    let mut top_y = camera_offset_coordinate[1] + height_of_visible_area / 2;
     // If the grid does not wrap on the y-axis, then we need to make sure that the bottom_y and top_y are within the bounds of the grid.
     if !grid.wrap_y() {
         bottom_y = bottom_y.max(0);
@@ -308,49 +739,523 @@ pub fn show_main_camera_area(
     }
 }
 
-fn unit_icon(
-    unit: Unit,
-    owner: Owner,
-    ruleset: &Ruleset,
-    inner_rectangle: Handle<Mesh>,
-    outer_rectangle: Handle<Mesh>,
-    custom_materials: &mut ResMut<Assets<ColorReplaceMaterial>>,
-    materials: &MaterialResource,
-    tile_pixel_size: Vec2,
-) -> impl Bundle {
-    let (unit_name, transform_y, out_texture_name) = match &unit {
-        Unit::Civilian(unit) => (unit.to_owned(), -tile_pixel_size.y / 4., "sv_unitcitizen"),
-        Unit::Military(unit) => (unit.to_owned(), tile_pixel_size.y / 4., "sv_unitmilitary"),
+/// Return `true` if the mouse pointer is over a UI node, so clicks should not reach the world map.
+pub fn cursor_over_ui(hover_map: &HoverMap, ui_nodes: &Query<(), With<Node>>) -> bool {
+    hover_map
+        .get(&PointerId::Mouse)
+        .is_some_and(|hovered| hovered.keys().any(|&entity| ui_nodes.contains(entity)))
+}
+
+/// Select a unit on the tile clicked with the left mouse button.
+///
+/// Military units are selected first. Clicking the tile of the selected unit again selects the next unit on that tile.
+pub fn select_unit_on_click(
+    input: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    map: Option<Res<TileMapResource>>,
+    hover_map: Res<HoverMap>,
+    ui_nodes: Query<(), With<Node>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    move_mode: Res<MoveMode>,
+    query_unit: Query<(Entity, &MapUnit, &Unit)>,
+    mut selected_unit: ResMut<SelectedUnit>,
+) {
+    // In move mode the click chooses where the selected unit goes, see `move_order_on_right_click`, and with `Shift`
+    // it starts a band selecting several units, see `crate::unit_group`.
+    if !input.just_pressed(MouseButton::Left)
+        || move_mode.0
+        || band_select_held(&keyboard_input)
+        || cursor_over_ui(&hover_map, &ui_nodes)
+    {
+        return;
+    }
+
+    let Some(map) = map else {
+        return;
+    };
+
+    let (camera, camera_transform) = camera.into_inner();
+    let Some(tile) = cursor_to_tile(&window, camera, camera_transform, map.0.world_grid.grid)
+    else {
+        return;
+    };
+
+    let mut units_on_tile: Vec<_> = query_unit
+        .iter()
+        .filter(|(_, map_unit, _)| map_unit.tile == tile)
+        .map(|(entity, _, unit)| (entity, matches!(unit, Unit::Military(_))))
+        .collect();
+
+    if units_on_tile.is_empty() {
+        return;
+    }
+
+    // Military units first, then keep a stable order so cycling visits every unit.
+    units_on_tile.sort_by_key(|&(entity, is_military)| (!is_military, entity));
+
+    let next_index = selected_unit
+        .0
+        .and_then(|selected| {
+            units_on_tile
+                .iter()
+                .position(|&(entity, _)| entity == selected)
+        })
+        .map_or(0, |index| (index + 1) % units_on_tile.len());
+
+    selected_unit.0 = Some(units_on_tile[next_index].0);
+}
+
+/// Preview the path of the selected unit while the right mouse button is held, and order the move when it is released.
+///
+/// In [`MoveMode`] the path is previewed without holding a button, and the move is ordered with a left click.
+pub fn move_order_on_right_click(
+    input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    hover_map: Res<HoverMap>,
+    ui_nodes: Query<(), With<Node>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    map: Option<Res<TileMapResource>>,
+    ruleset: Res<RulesetResource>,
+    selected_unit: Res<SelectedUnit>,
+    civilizations: Res<Civilizations>,
+    diplomacy: Res<DiplomacyState>,
+    zone_of_control_rule: Res<ZoneOfControlRule>,
+    query_unit: Query<(&Unit, &Owner, &MapUnit, &Movement)>,
+    mut move_path_preview: ResMut<MovePathPreview>,
+    mut move_mode: ResMut<MoveMode>,
+    mut player_command: MessageWriter<PlayerCommand>,
+    mut last_target_tile: Local<Option<Tile>>,
+) {
+    // Move mode ends when the unit it was entered for is no longer selected.
+    if selected_unit.is_changed() && move_mode.0 {
+        move_mode.0 = false;
+        *move_path_preview = MovePathPreview::default();
+    }
+
+    let (Some(map), Some(selected)) = (map, selected_unit.0) else {
+        return;
+    };
+
+    let Ok((unit, owner, map_unit, movement)) = query_unit.get(selected) else {
+        return;
     };
 
-    let nation = match owner {
-        Owner::Civilization(ref nation) | Owner::CityState(ref nation) => nation.as_str(),
+    if key_bindings.just_pressed(InputAction::MoveUnit, &keyboard_input) {
+        move_mode.0 = true;
+        *last_target_tile = None;
+    }
+
+    let order_move = if move_mode.0 {
+        input.just_pressed(MouseButton::Left) && !cursor_over_ui(&hover_map, &ui_nodes)
+    } else {
+        input.just_released(MouseButton::Right)
     };
+    if order_move {
+        if let Some(path) = std::mem::take(&mut *move_path_preview).path {
+            player_command.write(PlayerCommand::MoveUnit {
+                unit: UnitId::new(unit, map_unit),
+                path: path.tiles,
+            });
+        }
+        move_mode.0 = false;
+        *last_target_tile = None;
+        return;
+    }
+
+    if !move_mode.0 && !input.pressed(MouseButton::Right) {
+        return;
+    }
+
+    let tile_map = &map.0;
+    let (camera, camera_transform) = camera.into_inner();
+    let target_tile = cursor_to_tile(&window, camera, camera_transform, tile_map.world_grid.grid);
 
-    let outer_color = ruleset.nations[nation].outer_color;
-    let inner_color = ruleset.nations[nation].inner_color;
-
-    (
-        unit,
-        owner,
-        Mesh2d(inner_rectangle.clone()),
-        MeshMaterial2d(custom_materials.add(ColorReplaceMaterial {
-            inner_color: LinearRgba::from_u8_array_no_alpha(inner_color),
-            outer_color: LinearRgba::from_u8_array_no_alpha(outer_color),
-            texture: materials.texture_handle(&unit_name),
-        })),
-        Transform {
-            translation: Vec3::new(0., transform_y, 6.),
-            ..Default::default()
+    // Only search for a new path when the cursor moves onto another tile.
+    if target_tile == *last_target_tile {
+        return;
+    }
+    *last_target_tile = target_tile;
+
+    let ruleset = &ruleset.0;
+    let military_units: Vec<_> = query_unit
+        .iter()
+        .filter(|(unit, ..)| matches!(unit, Unit::Military(_)))
+        .map(|(_, owner, map_unit, _)| (map_unit.tile, owner.nation()))
+        .collect();
+    let zone_of_control = ZoneOfControl::of_enemies(
+        owner.nation(),
+        unit.name(),
+        &military_units,
+        *zone_of_control_rule,
+        &diplomacy,
+        tile_map,
+        ruleset,
+    );
+    let ends_movement = |from, to| zone_of_control.ends_movement(from, to);
+    let rules = MovementRules {
+        ends_movement: Some(&ends_movement),
+        ..MovementRules::new(
+            unit.name(),
+            civilizations.get(owner.nation()),
+            movement.max,
+            tile_map,
+            ruleset,
+        )
+    };
+
+    let path = target_tile.and_then(|target_tile| find_path(map_unit.tile, target_tile, &rules));
+    let turns = path.as_ref().map_or_else(Vec::new, |path| {
+        turns_to_reach(path, map_unit.tile, movement.current, &rules)
+    });
+    *move_path_preview = MovePathPreview { path, turns };
+}
+
+/// Attack the unit of a nation at war on the tile where the right mouse button is released with the selected unit,
+/// instead of moving there. Military units are attacked first. The city of a nation at war is attacked
+/// if no unit stands on its tile.
+pub fn attack_on_right_click(
+    input: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    map: Option<Res<TileMapResource>>,
+    selected_unit: Res<SelectedUnit>,
+    diplomacy: Res<DiplomacyState>,
+    query_unit: Query<(Entity, &Unit, &MapUnit, &Owner)>,
+    query_city: Query<(&City, &Owner)>,
+    mut move_path_preview: ResMut<MovePathPreview>,
+    mut player_command: MessageWriter<PlayerCommand>,
+) {
+    if !input.just_released(MouseButton::Right) {
+        return;
+    }
+    let (Some(map), Some(selected)) = (map, selected_unit.0) else {
+        return;
+    };
+    let Ok((_, selected_unit, selected_map_unit, selected_owner)) = query_unit.get(selected) else {
+        return;
+    };
+
+    let (camera, camera_transform) = camera.into_inner();
+    let Some(target_tile) =
+        cursor_to_tile(&window, camera, camera_transform, map.0.world_grid.grid)
+    else {
+        return;
+    };
+
+    let defender = query_unit
+        .iter()
+        .filter(|(_, _, map_unit, owner)| {
+            map_unit.tile == target_tile
+                && diplomacy.is_at_war(owner.nation(), selected_owner.nation())
+        })
+        .max_by_key(|(_, unit, ..)| matches!(unit, Unit::Military(_)));
+    let attacker = UnitId::new(selected_unit, selected_map_unit);
+
+    if let Some((_, unit, map_unit, ..)) = defender {
+        player_command.write(PlayerCommand::Attack {
+            attacker,
+            defender: UnitId::new(unit, map_unit),
+        });
+        *move_path_preview = MovePathPreview::default();
+    } else if matches!(selected_unit, Unit::Military(_))
+        && query_city.iter().any(|(city, owner)| {
+            city.tile == target_tile && diplomacy.is_at_war(owner.nation(), selected_owner.nation())
+        })
+    {
+        player_command.write(PlayerCommand::AttackCity {
+            attacker,
+            city: target_tile,
+        });
+        *move_path_preview = MovePathPreview::default();
+    }
+}
+
+/// Leave the [`MoveMode`] with `Escape`, or deselect the unit if the player isn't choosing where it moves.
+pub fn deselect_on_escape(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut selected_unit: ResMut<SelectedUnit>,
+    mut move_path_preview: ResMut<MovePathPreview>,
+    mut move_mode: ResMut<MoveMode>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        if move_mode.0 {
+            move_mode.0 = false;
+        } else {
+            selected_unit.0 = None;
+        }
+        *move_path_preview = MovePathPreview::default();
+    }
+}
+
+/// Outline the tile of the selected unit, and draw the previewed path as a line through the centers of its tiles
+/// with a dot on every tile where the unit ends a turn.
+pub fn draw_move_path_preview(
+    mut gizmos: Gizmos,
+    map: Option<Res<TileMapResource>>,
+    selected_unit: Res<SelectedUnit>,
+    move_path_preview: Res<MovePathPreview>,
+    tile_entities: Option<Res<WorldTileEntities>>,
+    query_unit: Query<&MapUnit>,
+    query_tile_transform: Query<&GlobalTransform, With<WorldTile>>,
+) {
+    let (Some(map), Some(selected), Some(tile_entities)) = (map, selected_unit.0, tile_entities)
+    else {
+        return;
+    };
+
+    let Ok(map_unit) = query_unit.get(selected) else {
+        return;
+    };
+
+    let tile_position = |tile: Tile| {
+        query_tile_transform
+            .get(tile_entities.0[&tile])
+            .ok()
+            .map(|transform| transform.translation().truncate())
+    };
+
+    if let Some(center) = tile_position(map_unit.tile) {
+        let corners = map.0.world_grid.grid.layout.all_corners(Hex::new(0, 0));
+        gizmos.linestrip_2d(
+            corners
+                .iter()
+                .chain(corners.first())
+                .map(|&corner| center + Vec2::from(corner)),
+            SELECTION_COLOR,
+        );
+    }
+
+    let Some(path) = &move_path_preview.path else {
+        return;
+    };
+
+    let points: Vec<Vec2> = std::iter::once(map_unit.tile)
+        .chain(path.tiles.iter().copied())
+        .filter_map(tile_position)
+        .collect();
+
+    gizmos.linestrip_2d(points, Color::WHITE);
+
+    for tile in turn_ends(&path.tiles, &move_path_preview.turns).map(|(tile, _)| tile) {
+        if let Some(center) = tile_position(tile) {
+            gizmos.circle_2d(center, 4., Color::WHITE);
+        }
+    }
+}
+
+/// Show on every tile of the previewed path where the unit ends a turn how many turns it takes to get there.
+///
+/// The badges are children of their tile, so they follow it when the map wraps around.
+pub fn update_path_turn_badges(
+    mut commands: Commands,
+    move_path_preview: Res<MovePathPreview>,
+    tile_entities: Option<Res<WorldTileEntities>>,
+    query_badge: Query<Entity, With<PathTurnBadge>>,
+) {
+    if !move_path_preview.is_changed() {
+        return;
+    }
+    for badge in query_badge.iter() {
+        commands.entity(badge).despawn();
+    }
+
+    let (Some(path), Some(tile_entities)) = (&move_path_preview.path, tile_entities) else {
+        return;
+    };
+
+    for (tile, turn) in turn_ends(&path.tiles, &move_path_preview.turns) {
+        commands.entity(tile_entities.0[&tile]).with_child((
+            PathTurnBadge,
+            Text2d::new((turn + 1).to_string()),
+            TextFont {
+                font_size: 14.,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Transform::from_xyz(0., 10., 20.),
+        ));
+    }
+}
+
+/// The tiles of a path where the unit ends a turn, with the turn, see [`turns_to_reach`].
+/// The destination always ends the path.
+fn turn_ends<'a>(tiles: &'a [Tile], turns: &'a [u32]) -> impl Iterator<Item = (Tile, u32)> + 'a {
+    tiles
+        .iter()
+        .zip(turns)
+        .enumerate()
+        .filter(|&(index, (_, &turn))| turns.get(index + 1).is_none_or(|&next| next > turn))
+        .map(|(_, (&tile, &turn))| (tile, turn))
+}
+
+/// The tooltip describing the tile under the cursor.
+#[derive(Component)]
+pub struct TileTooltip(Tile);
+
+/// Show the terrain, the rivers, the yields and the coordinates of the tile under the cursor.
+///
+/// Nothing is shown for the tiles the player hasn't explored, and while the cursor is over the UI or a city,
+/// which has its own tooltip.
+pub fn update_tile_tooltip(
+    mut commands: Commands,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    map: Res<TileMapResource>,
+    tile_yields: Option<Res<TileYields>>,
+    player_civilization: Res<PlayerCivilization>,
+    visibility_layer: Res<VisibilityLayer>,
+    hover_map: Res<HoverMap>,
+    ui_nodes: Query<(), With<Node>>,
+    query_city: Query<(), With<City>>,
+    mut query_tooltip: Query<(Entity, &mut TileTooltip, &mut Node, &mut Text)>,
+) {
+    let tile_map = &map.0;
+    let grid = tile_map.world_grid.grid;
+    let (camera, camera_transform) = camera.into_inner();
+
+    let hovers_city = hover_map
+        .get(&PointerId::Mouse)
+        .is_some_and(|hovered| hovered.keys().any(|&entity| query_city.contains(entity)));
+    let hovered_tile = window.cursor_position().zip(
+        cursor_to_tile(&window, camera, camera_transform, grid)
+            .filter(|&tile| visibility_layer.is_explored(player_civilization.0, tile)),
+    );
+    let Some((cursor_position, tile)) =
+        hovered_tile.filter(|_| !hovers_city && !cursor_over_ui(&hover_map, &ui_nodes))
+    else {
+        for (entity, ..) in query_tooltip.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    };
+
+    let left = Val::Px(cursor_position.x + 16.);
+    let top = Val::Px(cursor_position.y + 16.);
+    if let Ok((_, mut tooltip, mut node, mut text)) = query_tooltip.single_mut() {
+        node.left = left;
+        node.top = top;
+        if tooltip.0 != tile {
+            tooltip.0 = tile;
+            text.0 = tile_description(tile, tile_map, tile_yields.as_deref());
+        }
+        return;
+    }
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left,
+            top,
+            padding: UiRect::all(Val::Px(6.0)),
+            border: UiRect::all(Val::Px(1.0)),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.8)),
+        BorderColor::all(Color::WHITE),
+        Pickable::IGNORE,
+        Text(tile_description(tile, tile_map, tile_yields.as_deref())),
+        TextFont {
+            font_size: 14.,
+            ..default()
         },
-        children![(
-            Mesh2d(outer_rectangle.clone()),
-            MeshMaterial2d(custom_materials.add(ColorReplaceMaterial {
-                inner_color: LinearRgba::from_u8_array_no_alpha(inner_color,),
-                outer_color: LinearRgba::from_u8_array_no_alpha(outer_color,),
-                texture: materials.texture_handle(out_texture_name),
-            },)),
-            Transform::from_xyz(0., 0., -1.),
-        )],
-    )
+        TileTooltip(tile),
+    ));
+}
+
+/// The text of the [`TileTooltip`] of `tile`.
+fn tile_description(tile: Tile, tile_map: &TileMap, tile_yields: Option<&TileYields>) -> String {
+    let grid = tile_map.world_grid.grid;
+    let [x, y] = tile.to_offset(grid).to_array();
+
+    let mut lines = vec![format!(
+        "{} / {}",
+        tile.terrain_type(tile_map).as_str(),
+        tile.base_terrain(tile_map).as_str()
+    )];
+    if let Some(feature) = tile.feature(tile_map) {
+        lines.push(feature.as_str().to_owned());
+    }
+    if let Some(natural_wonder) = tile.natural_wonder(tile_map) {
+        lines.push(natural_wonder.as_str().to_owned());
+    }
+
+    let river_edges: Vec<_> = grid
+        .edge_direction_array()
+        .iter()
+        .filter(|&&direction| tile.has_river_in_direction(direction, tile_map))
+        .map(|direction| format!("{direction:?}"))
+        .collect();
+    if !river_edges.is_empty() {
+        lines.push(format!("River: {}", river_edges.join(", ")));
+    }
+
+    if let Some(tile_yields) = tile_yields {
+        let yields = tile_yields.get(tile);
+        let yields: Vec<_> = [
+            ("Food", yields.food),
+            ("Production", yields.production),
+            ("Gold", yields.gold),
+            ("Science", yields.science),
+            ("Culture", yields.culture),
+            ("Faith", yields.faith),
+        ]
+        .into_iter()
+        .filter(|&(_, amount)| amount != 0.)
+        .map(|(name, amount)| format!("{amount} {name}"))
+        .collect();
+        if !yields.is_empty() {
+            lines.push(yields.join(", "));
+        }
+    }
+
+    lines.push(format!("Tile {} ({x}, {y})", tile.index()));
+    lines.join("\n")
+}
+
+/// The icon of a resource on the world map or on the minimap, shown once the player can see the resource.
+#[derive(Component)]
+pub struct ResourceIcon(pub String);
+
+/// The name of the image of a resource, for the resources whose image is named differently.
+fn resource_texture_name(resource: &str) -> &str {
+    match resource {
+        "Cattle" => "Cow",
+        "Bananas" => "Banana",
+        "Horses" => "Horse",
+        "Dyes" => "Dye",
+        "Whales" => "Whale",
+        _ => resource,
+    }
+}
+
+/// The color of the marker of the resources without an image, and of the resources on the minimap,
+/// from the `resourceType` of the resource in the ruleset.
+pub fn resource_type_color(resource_type: &str) -> Color {
+    match resource_type {
+        "Strategic" => Color::srgb(0.85, 0.25, 0.2),
+        "Luxury" => Color::srgb(0.7, 0.35, 0.9),
+        _ => Color::srgb(0.3, 0.8, 0.3),
+    }
+}
+
+/// Show the icons of the resources the player can see, e.g. Iron once Bronze Working is researched.
+pub fn update_resource_icons(
+    player_civilization: Res<PlayerCivilization>,
+    civilizations: Res<Civilizations>,
+    mut query_icon: Query<(Ref<ResourceIcon>, &mut Visibility)>,
+) {
+    let civilization = civilizations.get(player_civilization.0);
+    for (icon, mut visibility) in query_icon.iter_mut() {
+        if !civilizations.is_changed() && !player_civilization.is_changed() && !icon.is_added() {
+            continue;
+        }
+        *visibility = if civilization.is_resource_revealed(&icon.0) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
 }
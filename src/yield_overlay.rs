@@ -0,0 +1,196 @@
+//! The yield overlay: small colored pips on every explored tile showing its food, production and gold,
+//! toggled with the `Y` key.
+//!
+//! The pips are drawn by [`OverlayChunk`]s, rebuilt only when the yields or the explored tiles change.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+};
+
+use crate::{
+    TileMapResource,
+    assets::AppState,
+    civilization::PlayerCivilization,
+    overlay_chunk::OverlayChunk,
+    visibility::VisibilityLayer,
+    yields::{TileYields, Yields},
+};
+
+/// At most this many pips are drawn for a yield, whatever its amount.
+const MAX_PIPS: u32 = 5;
+const PIP_SIZE: f32 = 6.;
+const PIP_SPACING: f32 = 8.;
+/// Above the terrain and the improvements, below the cities and the units.
+const OVERLAY_Z: f32 = 3.5;
+
+/// Whether the yield overlay is shown.
+#[derive(Resource, Default)]
+pub struct YieldOverlay {
+    pub shown: bool,
+}
+
+/// A chunk of the yield overlay.
+#[derive(Component)]
+struct YieldChunk;
+
+pub struct YieldOverlayPlugin;
+
+impl Plugin for YieldOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<YieldOverlay>().add_systems(
+            Update,
+            (
+                toggle_yield_overlay_on_key,
+                setup_yield_chunks.run_if(resource_added::<TileYields>),
+                update_yield_chunks.run_if(resource_exists::<TileYields>),
+            )
+                .chain()
+                .run_if(in_state(AppState::GameStart)),
+        );
+    }
+}
+
+fn toggle_yield_overlay_on_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut yield_overlay: ResMut<YieldOverlay>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyY) {
+        yield_overlay.shown = !yield_overlay.shown;
+    }
+}
+
+/// Spawn the chunks of the map, replacing the chunks of the previous map, e.g. when a save is loaded.
+fn setup_yield_chunks(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    mut yield_overlay: ResMut<YieldOverlay>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    query_chunk: Query<Entity, With<YieldChunk>>,
+) {
+    for entity in query_chunk.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    // The pips are colored by their vertices, so every chunk shares a white material.
+    let material = color_materials.add(Color::WHITE);
+
+    for chunk in OverlayChunk::all(map.0.world_grid.grid) {
+        commands.spawn((
+            chunk,
+            YieldChunk,
+            Mesh2d(meshes.add(empty_mesh())),
+            MeshMaterial2d(material.clone()),
+            Transform::from_xyz(0., 0., OVERLAY_Z),
+            Visibility::Hidden,
+        ));
+    }
+    // Build the meshes of the new chunks.
+    yield_overlay.set_changed();
+}
+
+/// Rebuild the meshes of the chunks when the overlay is shown, the yields change or the player explores new tiles,
+/// and hide the chunks when the overlay is hidden.
+fn update_yield_chunks(
+    map: Res<TileMapResource>,
+    yield_overlay: Res<YieldOverlay>,
+    tile_yields: Res<TileYields>,
+    player_civilization: Res<PlayerCivilization>,
+    visibility_layer: Res<VisibilityLayer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query_chunk: Query<(&OverlayChunk, &Mesh2d, &mut Visibility), With<YieldChunk>>,
+) {
+    if !yield_overlay.shown {
+        if yield_overlay.is_changed() {
+            for (_, _, mut visibility) in query_chunk.iter_mut() {
+                *visibility = Visibility::Hidden;
+            }
+        }
+        return;
+    }
+    if !yield_overlay.is_changed() && !tile_yields.is_changed() && !visibility_layer.is_changed() {
+        return;
+    }
+
+    let grid = map.0.world_grid.grid;
+    for (chunk, mesh_2d, mut visibility) in query_chunk.iter_mut() {
+        let mut pips = PipMesh::default();
+        for (center, tile) in chunk.tiles(grid) {
+            if !visibility_layer.is_explored(player_civilization.0, tile) {
+                continue;
+            }
+            pips.add_tile(center, tile_yields.get(tile));
+        }
+
+        *visibility = if pips.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        if let Some(mesh) = meshes.get_mut(&mesh_2d.0) {
+            *mesh = pips.into_mesh();
+        }
+    }
+}
+
+/// The vertices of the pips of a chunk: one row of pips for the food, the production and the gold of every tile.
+#[derive(Default)]
+struct PipMesh {
+    positions: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl PipMesh {
+    fn add_tile(&mut self, center: Vec2, yields: Yields) {
+        let rows = [
+            (yields.food, Color::srgb(0.35, 0.8, 0.25)),
+            (yields.production, Color::srgb(0.9, 0.5, 0.15)),
+            (yields.gold, Color::srgb(1., 0.85, 0.1)),
+        ];
+        for (row, (amount, color)) in rows.into_iter().enumerate() {
+            let count = (amount.round().max(0.) as u32).min(MAX_PIPS);
+            let row_width = count as f32 * PIP_SPACING;
+            let y = center.y + PIP_SPACING - row as f32 * PIP_SPACING;
+            for index in 0..count {
+                let x = center.x - row_width / 2. + (index as f32 + 0.5) * PIP_SPACING;
+                self.add_pip(Vec2::new(x, y), color);
+            }
+        }
+    }
+
+    fn add_pip(&mut self, center: Vec2, color: Color) {
+        let first_index = self.positions.len() as u32;
+        let half_size = PIP_SIZE / 2.;
+        for [dx, dy] in [[-1., -1.], [1., -1.], [1., 1.], [-1., 1.]] {
+            self.positions
+                .push([center.x + dx * half_size, center.y + dy * half_size, 0.]);
+            self.colors.push(color.to_linear().to_f32_array());
+        }
+        self.indices.extend(
+            [0, 1, 2, 0, 2, 3]
+                .iter()
+                .map(|&offset| first_index + offset),
+        );
+    }
+
+    fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn into_mesh(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
+
+fn empty_mesh() -> Mesh {
+    PipMesh::default().into_mesh()
+}
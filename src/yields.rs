@@ -0,0 +1,250 @@
+use std::ops::{Add, AddAssign, Mul};
+
+use bevy::prelude::*;
+use civ_map_generator::{ruleset::Ruleset, tile::Tile, tile_map::TileMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    RulesetResource, TileMapResource, assets::AppState, game_event::TileChanged,
+    improvement::TileImprovementLayer,
+};
+
+/// The yields of a tile, a building, a city...
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Yields {
+    pub food: f32,
+    pub production: f32,
+    pub gold: f32,
+    pub science: f32,
+    pub culture: f32,
+    pub faith: f32,
+}
+
+impl Yields {
+    /// Return yields with `amount` of the yield named `name` (e.g. `Food`), or `None` if there is no such yield.
+    pub fn from_name(name: &str, amount: f32) -> Option<Self> {
+        let mut yields = Yields::default();
+        match name {
+            "Food" => yields.food = amount,
+            "Production" => yields.production = amount,
+            "Gold" => yields.gold = amount,
+            "Science" => yields.science = amount,
+            "Culture" => yields.culture = amount,
+            "Faith" => yields.faith = amount,
+            _ => return None,
+        }
+        Some(yields)
+    }
+
+    /// Increase every yield by the percentage of the same yield in `percent`, e.g. `+10` for 10% more.
+    pub fn increased_by_percent(self, percent: Yields) -> Self {
+        let increase = |amount: f32, percent: f32| amount * (1. + percent / 100.);
+        Self {
+            food: increase(self.food, percent.food),
+            production: increase(self.production, percent.production),
+            gold: increase(self.gold, percent.gold),
+            science: increase(self.science, percent.science),
+            culture: increase(self.culture, percent.culture),
+            faith: increase(self.faith, percent.faith),
+        }
+    }
+}
+
+impl Add for Yields {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            food: self.food + other.food,
+            production: self.production + other.production,
+            gold: self.gold + other.gold,
+            science: self.science + other.science,
+            culture: self.culture + other.culture,
+            faith: self.faith + other.faith,
+        }
+    }
+}
+
+impl AddAssign for Yields {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Mul<f32> for Yields {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        Self {
+            food: self.food * factor,
+            production: self.production * factor,
+            gold: self.gold * factor,
+            science: self.science * factor,
+            culture: self.culture * factor,
+            faith: self.faith * factor,
+        }
+    }
+}
+
+impl std::iter::Sum for Yields {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Yields::default(), Add::add)
+    }
+}
+
+/// Compute the yields of a tile from the ruleset.
+///
+/// The base terrain gives the starting yields. The terrain type, the feature and the natural wonder either replace them
+/// (when they have `overrideStats`) or add to them. The resource and the improvement always add to them,
+/// unless the improvement is pillaged.
+pub fn compute_tile_yields(
+    tile: Tile,
+    tile_map: &TileMap,
+    ruleset: &Ruleset,
+    improvement_layer: &TileImprovementLayer,
+) -> Yields {
+    let base_terrain = &ruleset.base_terrains[tile.base_terrain(tile_map).as_str()];
+    let mut yields = Yields {
+        food: base_terrain.food,
+        production: base_terrain.production,
+        gold: base_terrain.gold,
+        ..default()
+    };
+
+    let terrain_type = &ruleset.terrain_types[tile.terrain_type(tile_map).as_str()];
+    let terrain_type_yields = Yields {
+        production: terrain_type.production,
+        ..default()
+    };
+    apply_yields(
+        &mut yields,
+        terrain_type_yields,
+        terrain_type.override_stats,
+    );
+
+    if let Some(feature) = tile.feature(tile_map) {
+        let feature = &ruleset.features[feature.as_str()];
+        let feature_yields = Yields {
+            food: feature.food,
+            production: feature.production,
+            gold: feature.gold,
+            ..default()
+        };
+        apply_yields(&mut yields, feature_yields, feature.override_stats);
+    }
+
+    if let Some(natural_wonder) = tile.natural_wonder(tile_map) {
+        let natural_wonder = &ruleset.natural_wonders[natural_wonder.as_str()];
+        let natural_wonder_yields = Yields {
+            food: natural_wonder.food,
+            production: natural_wonder.production,
+            gold: natural_wonder.gold,
+            science: natural_wonder.science,
+            culture: natural_wonder.culture,
+            faith: natural_wonder.faith,
+        };
+        apply_yields(
+            &mut yields,
+            natural_wonder_yields,
+            natural_wonder.override_stats,
+        );
+    }
+
+    if let Some((resource, _)) = tile.resource(tile_map) {
+        let resource = &ruleset.tile_resources[resource.as_str()];
+        yields += Yields {
+            food: resource.food,
+            production: resource.production,
+            gold: resource.gold,
+            ..default()
+        };
+    }
+
+    if let Some(improvement) = improvement_layer.working_improvement(tile) {
+        let improvement = &ruleset.tile_improvements[improvement];
+        yields += Yields {
+            food: improvement.food,
+            production: improvement.production,
+            gold: improvement.gold,
+            science: improvement.science,
+            culture: improvement.culture,
+            faith: improvement.faith,
+        };
+    }
+
+    yields
+}
+
+fn apply_yields(yields: &mut Yields, other: Yields, override_stats: bool) {
+    if override_stats {
+        *yields = other;
+    } else {
+        *yields += other;
+    }
+}
+
+/// The yields of every tile, indexed by tile.
+///
+/// Yields are computed once when the game starts, and only the tiles in [`TileChanged`] messages are computed again.
+#[derive(Resource)]
+pub struct TileYields(Vec<Yields>);
+
+impl TileYields {
+    pub fn get(&self, tile: Tile) -> Yields {
+        self.0[tile.index()]
+    }
+}
+
+/// The yields of every tile, in the order of the tile indices.
+impl FromIterator<Yields> for TileYields {
+    fn from_iter<T: IntoIterator<Item = Yields>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+pub struct YieldsPlugin;
+
+impl Plugin for YieldsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                setup_tile_yields.run_if(not(resource_exists::<TileYields>)),
+                update_changed_tile_yields.run_if(on_message::<TileChanged>),
+            )
+                .chain()
+                .run_if(in_state(AppState::GameStart)),
+        );
+    }
+}
+
+fn setup_tile_yields(
+    mut commands: Commands,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    improvement_layer: Res<TileImprovementLayer>,
+) {
+    let tile_map = &map.0;
+    let tile_yields: TileYields = tile_map
+        .all_tiles()
+        .map(|tile| compute_tile_yields(tile, tile_map, &ruleset.0, &improvement_layer))
+        .collect();
+    commands.insert_resource(tile_yields);
+}
+
+pub fn update_changed_tile_yields(
+    mut tile_changed: MessageReader<TileChanged>,
+    map: Res<TileMapResource>,
+    ruleset: Res<RulesetResource>,
+    improvement_layer: Res<TileImprovementLayer>,
+    tile_yields: Option<ResMut<TileYields>>,
+) {
+    let Some(mut tile_yields) = tile_yields else {
+        return;
+    };
+
+    for &TileChanged(tile) in tile_changed.read() {
+        tile_yields.0[tile.index()] =
+            compute_tile_yields(tile, &map.0, &ruleset.0, &improvement_layer);
+    }
+}